@@ -0,0 +1,147 @@
+//! The `can-i-deploy` command: queries the matrix of a Pact Broker to check if versions of
+//! pacticipants can be safely deployed
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use serde_json::json;
+
+use pact_matching::models::http_utils::TlsConfig;
+use pact_verifier::pact_broker::{can_i_deploy, Matrix, MatrixSelector, MatrixTarget};
+
+use crate::publish::http_auth;
+
+/// Position of the option that was given after the index of the pacticipant and before the
+/// index of the next one
+fn position_for_pacticipant(args: &ArgMatches, name: &str, start: usize, end: usize) -> Option<usize> {
+  args.indices_of(name)
+    .and_then(|mut indices| indices.position(|index| index > start && index < end))
+}
+
+fn value_for_pacticipant<'a>(args: &'a ArgMatches, name: &str, start: usize, end: usize) -> Option<&'a str> {
+  position_for_pacticipant(args, name, start, end)
+    .and_then(|position| args.values_of(name).and_then(|mut values| values.nth(position)))
+}
+
+/// Builds the selectors from the `--pacticipant` options. Each pacticipant must be followed by
+/// either a `--version` or `--latest` option.
+fn matrix_selectors(args: &ArgMatches) -> anyhow::Result<Vec<MatrixSelector>> {
+  let pacticipants = args.values_of("pacticipant").unwrap().collect::<Vec<_>>();
+  let indices = args.indices_of("pacticipant").unwrap().collect::<Vec<_>>();
+  let mut selectors = vec![];
+  for (i, (pacticipant, index)) in pacticipants.iter().zip(indices.iter()).enumerate() {
+    let end = indices.get(i + 1).copied().unwrap_or(usize::MAX);
+    let selector = match value_for_pacticipant(args, "version", *index, end) {
+      Some(version) => MatrixSelector::version(pacticipant, version),
+      None => if position_for_pacticipant(args, "latest", *index, end).is_some() {
+        MatrixSelector::latest(pacticipant, value_for_pacticipant(args, "tag", *index, end))
+      } else {
+        return Err(anyhow!("A version or --latest must be given for pacticipant '{}'", pacticipant))
+      }
+    };
+    selectors.push(selector);
+  }
+  Ok(selectors)
+}
+
+fn matrix_target(args: &ArgMatches) -> Option<MatrixTarget> {
+  args.value_of("to-environment").map(|environment| MatrixTarget::Environment(environment.to_string()))
+    .or_else(|| args.value_of("to").map(|tag| MatrixTarget::Tag(tag.to_string())))
+}
+
+fn display_option<T: ToString>(value: &Option<T>, default: &str) -> String {
+  value.as_ref().map(|value| value.to_string()).unwrap_or_else(|| default.to_string())
+}
+
+fn print_matrix(matrix: &Matrix) {
+  for row in &matrix.rows {
+    let result = match row.success {
+      Some(true) => "success",
+      Some(false) => "failed",
+      None => "not verified"
+    };
+    println!("{} {} -> {} {}: {}", row.consumer, display_option(&row.consumer_version, "?"),
+      row.provider, display_option(&row.provider_version, "?"), result);
+  }
+  println!();
+  println!("{}", matrix.summary.reason);
+}
+
+fn matrix_json(matrix: &Matrix) -> serde_json::Value {
+  json!({
+    "summary": {
+      "deployable": matrix.summary.deployable,
+      "reason": matrix.summary.reason,
+      "success": matrix.summary.success,
+      "failed": matrix.summary.failed,
+      "unknown": matrix.summary.unknown
+    },
+    "matrix": matrix.rows.iter().map(|row| json!({
+      "consumer": row.consumer,
+      "consumerVersion": row.consumer_version,
+      "provider": row.provider,
+      "providerVersion": row.provider_version,
+      "success": row.success
+    })).collect::<Vec<_>>()
+  })
+}
+
+pub(crate) fn handle_can_i_deploy_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let broker_url = args.value_of("broker-url").unwrap();
+  let selectors = matrix_selectors(args)?;
+  let target = matrix_target(args);
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()?;
+
+  let matrix = runtime.block_on(can_i_deploy(broker_url, &selectors, &target, http_auth(args), &TlsConfig::default()))
+    .map_err(|err| anyhow!("Failed to query the matrix of the Pact Broker - {}", err))?;
+  if args.is_present("json") {
+    println!("{}", serde_json::to_string_pretty(&matrix_json(&matrix))?);
+  } else {
+    print_matrix(&matrix);
+  }
+
+  if matrix.deployable() {
+    Ok(())
+  } else {
+    Err(anyhow!("The pacticipant versions can not be safely deployed"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use crate::setup_app;
+
+  use super::*;
+
+  fn matches(args: Vec<&str>) -> ArgMatches<'static> {
+    let app = setup_app("pact_cli".to_string(), "v0.0.1");
+    let mut all_args = vec!["pact_cli", "can-i-deploy", "-b", "http://localhost:9292"];
+    all_args.extend(args);
+    app.get_matches_from_safe(all_args).unwrap().subcommand_matches("can-i-deploy").unwrap().clone()
+  }
+
+  #[test]
+  fn matrix_selectors_from_the_command_line() {
+    let args = matches(vec!["--pacticipant", "Foo", "--version", "1.2.3", "--pacticipant", "Bar", "--latest", "--tag", "prod",
+      "--pacticipant", "Baz", "--latest", "--to-environment", "production"]);
+    expect!(matrix_selectors(&args).unwrap()).to(be_equal_to(vec![
+      MatrixSelector::version("Foo", "1.2.3"),
+      MatrixSelector::latest("Bar", Some("prod")),
+      MatrixSelector::latest("Baz", None)
+    ]));
+    expect!(matrix_target(&args)).to(be_some().value(MatrixTarget::Environment("production".to_string())));
+
+    let args = matches(vec!["--pacticipant", "Foo", "--version", "1.2.3", "--to", "prod"]);
+    expect!(matrix_target(&args)).to(be_some().value(MatrixTarget::Tag("prod".to_string())));
+  }
+
+  #[test]
+  fn matrix_selectors_requires_a_version_for_each_pacticipant() {
+    let args = matches(vec!["--pacticipant", "Foo", "--pacticipant", "Bar", "--version", "1.2.3"]);
+    expect!(matrix_selectors(&args).unwrap_err().to_string())
+      .to(be_equal_to("A version or --latest must be given for pacticipant 'Foo'"));
+  }
+}
@@ -0,0 +1,286 @@
+//! The `diff` command: compares the interactions in two pact files
+
+use std::path::Path;
+
+use clap::ArgMatches;
+use serde_json::{json, Value};
+
+use pact_matching::models::{DifferenceType, Interaction};
+
+use crate::pact_file::PactFile;
+
+/// A difference between two versions of the same interaction
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Difference {
+  /// Part of the interaction that is different (request, response or message)
+  pub part: String,
+  /// Type of difference (i.e. method, status, body)
+  pub kind: String,
+  /// Description of the difference
+  pub description: String
+}
+
+impl Difference {
+  fn new(part: &str, kind: &str, description: String) -> Self {
+    Difference { part: part.to_string(), kind: kind.to_string(), description }
+  }
+
+  fn to_json(&self) -> Value {
+    json!({
+      "part": self.part,
+      "type": self.kind,
+      "description": self.description
+    })
+  }
+}
+
+/// An interaction that was added, removed or changed between two pact files
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum InteractionDiff {
+  /// Interaction is only in the second pact file
+  Added { description: String, provider_states: Vec<String> },
+  /// Interaction is only in the first pact file
+  Removed { description: String, provider_states: Vec<String> },
+  /// Interaction is in both pact files, but is different
+  Changed { description: String, provider_states: Vec<String>, differences: Vec<Difference> }
+}
+
+impl InteractionDiff {
+  fn display(&self) -> String {
+    match self {
+      InteractionDiff::Added { description, provider_states } =>
+        format!("+ {}", interaction_name(description, provider_states)),
+      InteractionDiff::Removed { description, provider_states } =>
+        format!("- {}", interaction_name(description, provider_states)),
+      InteractionDiff::Changed { description, provider_states, differences } => {
+        let mut lines = vec![format!("~ {}", interaction_name(description, provider_states))];
+        for difference in differences {
+          lines.push(format!("    {}: {}", difference.part, difference.description));
+        }
+        lines.join("\n")
+      }
+    }
+  }
+
+  fn to_json(&self) -> Value {
+    match self {
+      InteractionDiff::Added { description, provider_states } |
+      InteractionDiff::Removed { description, provider_states } => json!({
+        "description": description,
+        "providerStates": provider_states
+      }),
+      InteractionDiff::Changed { description, provider_states, differences } => json!({
+        "description": description,
+        "providerStates": provider_states,
+        "differences": differences.iter().map(|d| d.to_json()).collect::<Vec<Value>>()
+      })
+    }
+  }
+}
+
+fn interaction_name(description: &str, provider_states: &[String]) -> String {
+  if provider_states.is_empty() {
+    format!("'{}'", description)
+  } else {
+    format!("'{}' given '{}'", description, provider_states.join("', '"))
+  }
+}
+
+fn difference_kind(difference_type: &DifferenceType) -> &'static str {
+  match difference_type {
+    DifferenceType::Method => "method",
+    DifferenceType::Path => "path",
+    DifferenceType::Headers => "headers",
+    DifferenceType::QueryParameters => "query",
+    DifferenceType::Body => "body",
+    DifferenceType::MatchingRules => "matchingRules",
+    DifferenceType::Status => "status"
+  }
+}
+
+fn state_names(interaction: &dyn Interaction) -> Vec<String> {
+  interaction.provider_states().iter().map(|state| state.name.clone()).collect()
+}
+
+/// Returns the differences between two interactions with the same description and provider states
+pub(crate) fn interaction_differences(old: &dyn Interaction, new: &dyn Interaction) -> Vec<Difference> {
+  match (old.as_request_response(), new.as_request_response(), old.as_message(), new.as_message()) {
+    (Some(old), Some(new), _, _) => {
+      let request = old.request.differences_from(&new.request).into_iter()
+        .map(|(kind, description)| Difference::new("request", difference_kind(&kind), description));
+      let response = old.response.differences_from(&new.response).into_iter()
+        .map(|(kind, description)| Difference::new("response", difference_kind(&kind), description));
+      request.chain(response).collect()
+    },
+    (_, _, Some(old), Some(new)) => {
+      let mut differences = vec![];
+      if old.contents != new.contents {
+        differences.push(Difference::new("message", "body",
+          format!("Message contents '{}' != '{}'", old.contents.str_value(), new.contents.str_value())));
+      }
+      if old.metadata != new.metadata {
+        differences.push(Difference::new("message", "metadata",
+          format!("Message metadata {:?} != {:?}", old.metadata, new.metadata)));
+      }
+      if old.matching_rules != new.matching_rules {
+        differences.push(Difference::new("message", "matchingRules",
+          format!("Message matching rules {:?} != {:?}", old.matching_rules, new.matching_rules)));
+      }
+      differences
+    },
+    _ => vec![Difference::new("interaction", "type",
+      format!("Interaction type {} != {}", old.type_of(), new.type_of()))]
+  }
+}
+
+/// Compares the interactions in the two pacts. Interactions are matched on their description and
+/// provider states.
+pub(crate) fn diff_pacts(old: &PactFile, new: &PactFile) -> Vec<InteractionDiff> {
+  let old = old.boxed();
+  let new = new.boxed();
+  let old_interactions = old.interactions();
+  let new_interactions = new.interactions();
+  let same = |a: &dyn Interaction, b: &dyn Interaction|
+    a.description() == b.description() && state_names(a) == state_names(b);
+
+  let mut diffs = vec![];
+  for interaction in &old_interactions {
+    match new_interactions.iter().find(|i| same(**i, *interaction)) {
+      Some(new_interaction) => {
+        let differences = interaction_differences(*interaction, *new_interaction);
+        if !differences.is_empty() {
+          diffs.push(InteractionDiff::Changed {
+            description: interaction.description(),
+            provider_states: state_names(*interaction),
+            differences
+          });
+        }
+      },
+      None => diffs.push(InteractionDiff::Removed {
+        description: interaction.description(),
+        provider_states: state_names(*interaction)
+      })
+    }
+  }
+  for interaction in &new_interactions {
+    if !old_interactions.iter().any(|i| same(*i, *interaction)) {
+      diffs.push(InteractionDiff::Added {
+        description: interaction.description(),
+        provider_states: state_names(*interaction)
+      });
+    }
+  }
+  diffs
+}
+
+/// Converts the differences to JSON, grouped by added, removed and changed interactions
+pub(crate) fn diff_to_json(diffs: &[InteractionDiff]) -> Value {
+  let select = |f: fn(&InteractionDiff) -> bool| diffs.iter()
+    .filter(|diff| f(diff))
+    .map(|diff| diff.to_json())
+    .collect::<Vec<Value>>();
+  json!({
+    "added": select(|diff| matches!(diff, InteractionDiff::Added { .. })),
+    "removed": select(|diff| matches!(diff, InteractionDiff::Removed { .. })),
+    "changed": select(|diff| matches!(diff, InteractionDiff::Changed { .. }))
+  })
+}
+
+pub(crate) fn handle_diff_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let old = PactFile::load(Path::new(args.value_of("old").unwrap()))?;
+  let new = PactFile::load(Path::new(args.value_of("new").unwrap()))?;
+  let diffs = diff_pacts(&old, &new);
+  if args.is_present("json") {
+    println!("{}", serde_json::to_string_pretty(&diff_to_json(&diffs))?);
+  } else if diffs.is_empty() {
+    println!("No differences found");
+  } else {
+    for diff in diffs {
+      println!("{}", diff.display());
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_matching::models::RequestResponseInteraction;
+  use pact_test_support::builders::*;
+
+  use super::*;
+
+  fn interaction_builder(description: &str, method: &str, status: u16) -> InteractionBuilder {
+    InteractionBuilder::new(description)
+      .request(RequestBuilder::new().method(method).build())
+      .response(ResponseBuilder::new().status(status).build())
+  }
+
+  fn interaction(description: &str, method: &str, status: u16) -> RequestResponseInteraction {
+    interaction_builder(description, method, status).build()
+  }
+
+  fn http_pact(interactions: Vec<RequestResponseInteraction>) -> PactFile {
+    let builder = interactions.into_iter()
+      .fold(PactBuilder::new("consumer", "provider"), |builder, interaction| builder.interaction(interaction));
+    PactFile::RequestResponse(builder.build())
+  }
+
+  #[test]
+  fn diff_pacts_finds_added_removed_and_changed_interactions() {
+    let with_state = interaction_builder("b", "GET", 200).given("user exists").build();
+    let old = http_pact(vec![interaction("a", "GET", 200), interaction("b", "GET", 200), interaction("c", "GET", 200)]);
+    let new = http_pact(vec![interaction("a", "GET", 200), with_state, interaction("c", "POST", 404)]);
+
+    expect!(diff_pacts(&old, &new)).to(be_equal_to(vec![
+      InteractionDiff::Removed { description: "b".to_string(), provider_states: vec![] },
+      InteractionDiff::Changed {
+        description: "c".to_string(),
+        provider_states: vec![],
+        differences: vec![
+          Difference::new("request", "method", "Request method GET != POST".to_string()),
+          Difference::new("response", "status", "Response status 200 != 404".to_string())
+        ]
+      },
+      InteractionDiff::Added { description: "b".to_string(), provider_states: vec!["user exists".to_string()] }
+    ]));
+    expect!(diff_pacts(&old, &old).iter()).to(be_empty());
+  }
+
+  #[test]
+  fn interaction_differences_compares_messages() {
+    let old = MessageBuilder::new("a").build();
+    let new = MessageBuilder::new("a").metadata("destination", "orders").build();
+    let differences = interaction_differences(&old, &new);
+    expect!(differences.iter().map(|d| d.kind.clone()).collect::<Vec<String>>())
+      .to(be_equal_to(vec!["metadata".to_string()]));
+    expect!(interaction_differences(&old, &interaction("a", "GET", 200))).to(be_equal_to(vec![
+      Difference::new("interaction", "type",
+        "Interaction type V3 Asynchronous/Messages != V3 Synchronous/HTTP".to_string())
+    ]));
+  }
+
+  #[test]
+  fn diff_to_json_groups_the_differences() {
+    let diffs = vec![
+      InteractionDiff::Added { description: "a".to_string(), provider_states: vec!["state".to_string()] },
+      InteractionDiff::Changed {
+        description: "b".to_string(),
+        provider_states: vec![],
+        differences: vec![Difference::new("response", "status", "Response status 200 != 404".to_string())]
+      }
+    ];
+    expect!(diff_to_json(&diffs)).to(be_equal_to(json!({
+      "added": [ { "description": "a", "providerStates": ["state"] } ],
+      "removed": [],
+      "changed": [
+        {
+          "description": "b",
+          "providerStates": [],
+          "differences": [ { "part": "response", "type": "status", "description": "Response status 200 != 404" } ]
+        }
+      ]
+    })));
+  }
+}
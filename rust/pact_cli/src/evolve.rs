@@ -0,0 +1,201 @@
+//! The `evolve` command: replaces the exact values of volatile fields (timestamps, UUIDs and
+//! numeric IDs) in the bodies of a pact with matchers and generators
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use regex::Regex;
+use serde_json::{json, Value};
+
+use pact_matching::models::evolve::{evolve_body, EvolvedField};
+
+use crate::pact_file::PactFile;
+
+/// A field of an interaction that was loosened
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EvolvedEntry {
+  /// Description of the interaction
+  pub description: String,
+  /// Part of the interaction (request, response or message)
+  pub part: String,
+  /// The field that was loosened
+  pub field: EvolvedField
+}
+
+impl EvolvedEntry {
+  fn to_json(&self) -> Value {
+    json!({
+      "interaction": self.description,
+      "part": self.part,
+      "path": self.field.path,
+      "value": self.field.value.to_string(),
+      "rule": self.field.rule.to_json(),
+      "generator": self.field.generator.as_ref().and_then(|g| g.to_json())
+    })
+  }
+}
+
+/// Loosens the volatile fields of the interaction parts whose description matches the filter (and
+/// are the given part, if any). Returns the fields that were loosened.
+pub(crate) fn evolve_pact(
+  pact: &mut PactFile,
+  description: Option<&Regex>,
+  part: Option<&str>
+) -> Vec<EvolvedEntry> {
+  let spec_version = pact.boxed().specification_version();
+  let mut entries = vec![];
+  pact.for_each_body(|desc, part_name, body, content_type, matching_rules, generators| {
+    if description.map(|re| re.is_match(desc)).unwrap_or(true) && part.map(|p| p == part_name).unwrap_or(true) {
+      let fields = evolve_body(body, content_type, matching_rules, generators, &spec_version);
+      entries.extend(fields.into_iter().map(|field| EvolvedEntry {
+        description: desc.to_string(),
+        part: part_name.to_string(),
+        field
+      }));
+    }
+  });
+  entries
+}
+
+/// Handles the `evolve` command
+pub(crate) fn handle_evolve_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let file = args.value_of("file").unwrap();
+  let mut pact = PactFile::load(Path::new(file))?;
+  let description = match args.value_of("interaction") {
+    Some(filter) => Some(Regex::new(filter)
+      .map_err(|err| anyhow!("'{}' is not a valid regular expression: {}", filter, err))?),
+    None => None
+  };
+  let entries = evolve_pact(&mut pact, description.as_ref(), args.value_of("part"));
+
+  if args.is_present("json") {
+    let json = Value::Array(entries.iter().map(|entry| entry.to_json()).collect());
+    println!("{}", serde_json::to_string_pretty(&json)?);
+  } else if entries.is_empty() {
+    println!("No volatile fields found");
+  } else {
+    for entry in &entries {
+      println!("{} [{}] {} is a {} -> {}", entry.description, entry.part, entry.field.path,
+        entry.field.value, entry.field.rule.to_json());
+    }
+  }
+
+  if !args.is_present("dry-run") && !entries.is_empty() {
+    pact.write(Path::new(args.value_of("output").unwrap_or(file)))?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::json;
+
+  use pact_matching::models::{RequestResponseInteraction, RequestResponsePact};
+  use pact_matching::models::generators::{Generator, GeneratorCategory};
+  use pact_matching::models::matchingrules::MatchingRule;
+  use pact_matching::models::message_pact::MessagePact;
+  use pact_matching::models::v4::V4Pact;
+  use pact_test_support::builders::{InteractionBuilder, MessageBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+
+  use super::*;
+
+  fn interaction(description: &str, body: Value) -> RequestResponseInteraction {
+    InteractionBuilder::new(description)
+      .request(RequestBuilder::get("/users/100").build())
+      .response(ResponseBuilder::new().status(200).json_body(body).build())
+      .build()
+  }
+
+  fn http_pact_builder() -> PactBuilder {
+    PactBuilder::new("web", "users")
+      .interaction(interaction("get a user", json!({
+        "id": 100,
+        "name": "Fred",
+        "created": "2021-05-04T10:11:12"
+      })))
+      .interaction(interaction("get an order", json!({
+        "orderId": "f2e62b6b-5c8a-4b8c-9c2e-2b6b5c8a4b8c"
+      })))
+  }
+
+  fn http_pact() -> RequestResponsePact {
+    http_pact_builder().build()
+  }
+
+  #[test]
+  fn evolve_pact_loosens_the_volatile_fields_in_all_the_interactions() {
+    let mut pact = PactFile::RequestResponse(http_pact());
+    let entries = evolve_pact(&mut pact, None, None).iter()
+      .map(|e| format!("{}:{}:{}", e.description, e.part, e.field.path))
+      .collect::<Vec<String>>();
+    expect!(entries).to(be_equal_to(vec![
+      "get a user:response:$.created".to_string(),
+      "get a user:response:$.id".to_string(),
+      "get an order:response:$.orderId".to_string()
+    ]));
+
+    if let PactFile::RequestResponse(pact) = pact {
+      let response = &pact.interactions[0].response;
+      let body_rules = response.matching_rules.rules_for_category("body").unwrap();
+      expect!(body_rules.rules["$.id"].rules.clone()).to(be_equal_to(vec![MatchingRule::Integer]));
+      expect!(response.generators.categories[&GeneratorCategory::BODY].get("$.id"))
+        .to(be_some().value(&Generator::RandomInt(1, i32::MAX)));
+    } else {
+      panic!("Expected a request/response pact");
+    }
+  }
+
+  #[test]
+  fn evolve_pact_only_updates_the_selected_interactions() {
+    let mut pact = PactFile::RequestResponse(http_pact());
+    let re = Regex::new("order").unwrap();
+    let entries = evolve_pact(&mut pact, Some(&re), Some("response"));
+    expect!(entries.len()).to(be_equal_to(1));
+    expect!(evolve_pact(&mut pact, Some(&re), Some("request")).iter()).to(be_empty());
+
+    // Running it again does not change fields that already have matchers
+    expect!(evolve_pact(&mut pact, Some(&re), None).iter()).to(be_empty());
+    expect!(evolve_pact(&mut pact, None, None).len()).to(be_equal_to(2));
+  }
+
+  #[test]
+  fn evolve_pact_updates_message_and_v4_pacts() {
+    let message_pact: MessagePact = PactBuilder::new("web", "events")
+      .message(MessageBuilder::new("a user created event")
+        .json_contents(json!({ "userId": 100 }))
+        .build())
+      .build_message_pact();
+    let mut pact = PactFile::Message(message_pact);
+    expect!(evolve_pact(&mut pact, None, None).iter().map(|e| e.part.clone()).collect::<Vec<String>>())
+      .to(be_equal_to(vec!["message".to_string()]));
+
+    let mut pact = PactFile::V4(http_pact_builder().build_v4());
+    expect!(evolve_pact(&mut pact, None, None).len()).to(be_equal_to(3));
+    expect!(evolve_pact(&mut pact, None, None).iter()).to(be_empty());
+    let pact: V4Pact = match pact {
+      PactFile::V4(pact) => pact,
+      _ => panic!("Expected a V4 pact")
+    };
+    let http = pact.interactions[0].as_v4_http().unwrap();
+    expect!(http.response.matching_rules.is_empty()).to(be_false());
+  }
+
+  #[test]
+  fn evolved_entry_to_json() {
+    let mut pact = PactFile::RequestResponse(http_pact());
+    let entries = evolve_pact(&mut pact, Some(&Regex::new("order").unwrap()), None);
+    expect!(entries[0].to_json()).to(be_equal_to(json!({
+      "interaction": "get an order",
+      "part": "response",
+      "path": "$.orderId",
+      "value": "UUID",
+      "rule": {
+        "match": "regex",
+        "regex": "^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$"
+      },
+      "generator": { "type": "Uuid" }
+    })));
+  }
+}
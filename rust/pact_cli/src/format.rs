@@ -0,0 +1,150 @@
+//! The `format` command: rewrites pact files into a canonical form, so changes to pact files
+//! produce clean diffs
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use log::*;
+
+use pact_matching::models::Interaction;
+use pact_matching::models::v4::V4Interaction;
+
+use crate::pact_file::PactFile;
+
+/// Returns the interaction with its key set, calculating the key if the interaction does not
+/// already have one
+fn with_key(interaction: &dyn V4Interaction) -> Box<dyn V4Interaction> {
+  if interaction.key().is_some() {
+    interaction.boxed_v4()
+  } else if let Some(http) = interaction.as_v4_http() {
+    http.with_key().boxed_v4()
+  } else if let Some(message) = interaction.as_v4_async_message() {
+    message.with_key().boxed_v4()
+  } else if let Some(message) = interaction.as_v4_sync_message() {
+    message.with_key().boxed_v4()
+  } else {
+    interaction.boxed_v4()
+  }
+}
+
+fn sort_key(interaction: &dyn Interaction) -> (String, Vec<String>, String) {
+  let states = interaction.provider_states().iter().map(|state| state.name.clone()).collect();
+  (interaction.description(), states, interaction.type_of())
+}
+
+/// Puts the pact into its canonical form. The interactions are sorted by description, provider
+/// states and type, and V4 interactions all have their keys set. The keys of the headers, query
+/// parameters and metadata, and JSON bodies, are normalised when the pact is converted to JSON.
+pub(crate) fn canonicalise(pact: &mut PactFile) {
+  match pact {
+    PactFile::RequestResponse(pact) => pact.interactions.sort_by_cached_key(|i| sort_key(i)),
+    PactFile::Message(pact) => pact.messages.sort_by_cached_key(|m| sort_key(m)),
+    PactFile::V4(pact) => {
+      pact.interactions = pact.interactions.iter().map(|i| with_key(i.as_ref())).collect();
+      pact.interactions.sort_by_cached_key(|i| sort_key(i.to_super()));
+    }
+  }
+}
+
+/// Returns the pact file contents in the canonical form
+pub(crate) fn format_pact(pact: &PactFile) -> anyhow::Result<String> {
+  let mut pact = pact.clone();
+  canonicalise(&mut pact);
+  let pact = pact.boxed();
+  serde_json::to_string_pretty(&pact.to_json(pact.specification_version())).map_err(|err| err.into())
+}
+
+pub(crate) fn handle_format_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let check = args.is_present("check");
+  let mut unformatted = vec![];
+  for file in args.values_of("files").unwrap() {
+    let path = Path::new(file);
+    debug!("Formatting pact file '{}'", file);
+    let contents = fs::read_to_string(path)
+      .map_err(|err| anyhow!("Failed to read pact file '{}' - {}", file, err))?;
+    let pact = PactFile::load(path).map_err(|err| anyhow!("Failed to load pact file '{}' - {}", file, err))?;
+    if format_pact(&pact)?.trim_end() != contents.trim_end() {
+      unformatted.push(file);
+      if !check {
+        let mut pact = pact;
+        canonicalise(&mut pact);
+        pact.write(path)?;
+        println!("Formatted '{}'", file);
+      }
+    }
+  }
+
+  if check && !unformatted.is_empty() {
+    for file in &unformatted {
+      println!("'{}' is not formatted", file);
+    }
+    Err(anyhow!("{} pact file(s) are not formatted", unformatted.len()))
+  } else {
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::{json, Value};
+
+  use pact_matching::models::load_pact_from_json;
+  use pact_test_support::builders::{InteractionBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+
+  use super::*;
+
+  fn pact_builder() -> PactBuilder {
+    let interaction = |description: &str, body: Value| InteractionBuilder::new(description)
+      .request(RequestBuilder::get("/users")
+        .header("X-Trace", "1")
+        .header("Accept", "application/json")
+        .build())
+      .response(ResponseBuilder::new().status(200).json_body(body).build())
+      .build();
+    PactBuilder::new("web", "users")
+      .interaction(interaction("get users", json!([{ "name": "Fred", "id": 2 }])))
+      .interaction(interaction("a request for users", json!({ "b": 1, "a": 2 })))
+  }
+
+  #[test]
+  fn format_pact_sorts_the_interactions_and_keys() {
+    let formatted = format_pact(&PactFile::RequestResponse(pact_builder().build())).unwrap();
+    let json: Value = serde_json::from_str(&formatted).unwrap();
+    let descriptions = json["interactions"].as_array().unwrap().iter()
+      .map(|i| i["description"].clone())
+      .collect::<Vec<Value>>();
+    expect!(descriptions).to(be_equal_to(vec![json!("a request for users"), json!("get users")]));
+    expect!(formatted.find("\"Accept\"").unwrap() < formatted.find("\"X-Trace\"").unwrap()).to(be_true());
+    expect!(formatted.find("\"a\": 2").unwrap() < formatted.find("\"b\": 1").unwrap()).to(be_true());
+  }
+
+  #[test]
+  fn format_pact_is_idempotent() {
+    let formatted = format_pact(&PactFile::RequestResponse(pact_builder().build())).unwrap();
+    let json: Value = serde_json::from_str(&formatted).unwrap();
+    let pact = PactFile::from_pact(load_pact_from_json("test", &json).unwrap().as_ref()).unwrap();
+    expect!(format_pact(&pact).unwrap()).to(be_equal_to(formatted));
+  }
+
+  #[test]
+  fn canonicalise_sets_the_keys_of_v4_interactions() {
+    let mut v4 = pact_builder().build_v4();
+    v4.interactions = v4.interactions.iter().map(|i| {
+      let mut http = i.as_v4_http().unwrap();
+      http.key = None;
+      http.boxed_v4()
+    }).collect();
+    let mut pact = PactFile::V4(v4);
+    canonicalise(&mut pact);
+    match pact {
+      PactFile::V4(pact) => {
+        expect!(pact.interactions.iter().all(|i| i.key().is_some())).to(be_true());
+        expect!(pact.interactions[0].description()).to(be_equal_to("a request for users"));
+      },
+      _ => panic!("Expected a V4 pact")
+    }
+  }
+}
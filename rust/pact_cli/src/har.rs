@@ -0,0 +1,300 @@
+//! The `har` command: imports the requests and responses from an HTTP Archive (HAR) file, as
+//! captured by browsers and proxies, into a pact to bootstrap it from a real session
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use log::*;
+use regex::Regex;
+use serde_json::Value;
+use url::Url;
+
+use pact_matching::models::{MergeStrategy, Request, RequestResponseInteraction, RequestResponsePact, Response, write_pact};
+use pact_matching::models::matchingrules::{MatchingRule, MatchingRules, RuleLogic};
+use pact_models::{Consumer, OptionalBody, PactSpecification, Provider};
+use pact_models::content_types::ContentType;
+
+/// Headers that are not imported, as they are set by the HTTP client or server (or are HTTP/2
+/// pseudo-headers, which start with a colon)
+const IGNORED_HEADERS: [&str; 7] = ["host", "connection", "content-length", "transfer-encoding",
+  "content-encoding", "accept-encoding", "keep-alive"];
+
+/// Result of importing a HAR file
+#[derive(Debug, Clone)]
+pub(crate) struct HarImport {
+  /// The pact with the imported interactions
+  pub pact: RequestResponsePact,
+  /// Number of entries that were skipped, as they did not match the URL filter or were duplicates
+  pub skipped: usize
+}
+
+fn har_headers(headers: Option<&Value>) -> Option<HashMap<String, Vec<String>>> {
+  let mut result: HashMap<String, Vec<String>> = HashMap::new();
+  for header in headers.and_then(|h| h.as_array()).into_iter().flatten() {
+    let name = header.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+    let value = header.get("value").and_then(|v| v.as_str()).unwrap_or_default();
+    if !name.is_empty() && !name.starts_with(':') && !IGNORED_HEADERS.contains(&name.to_lowercase().as_str()) {
+      result.entry(name.to_string()).or_default().push(value.to_string());
+    }
+  }
+  if result.is_empty() { None } else { Some(result) }
+}
+
+/// Converts the text of a HAR body into a pact body. JSON bodies are normalised, and base64
+/// encoded bodies (binary content) are decoded.
+fn har_body(text: Option<&str>, mime_type: Option<&str>, encoding: Option<&str>) -> anyhow::Result<OptionalBody> {
+  let text = match text {
+    Some(text) if !text.is_empty() => text,
+    _ => return Ok(OptionalBody::Missing)
+  };
+  let content_type = mime_type.filter(|mt| !mt.is_empty())
+    .and_then(|mt| ContentType::parse(mt).ok());
+  if encoding == Some("base64") {
+    let bytes = base64::decode(text).map_err(|err| anyhow!("Invalid base64 encoded body - {}", err))?;
+    return Ok(OptionalBody::Present(bytes.into(), content_type));
+  }
+  match &content_type {
+    Some(ct) if ct.is_json() => match serde_json::from_str::<Value>(text) {
+      Ok(json) => Ok(OptionalBody::Present(json.to_string().into(), content_type)),
+      Err(err) => {
+        warn!("Body has a JSON content type but is not valid JSON, importing it as text - {}", err);
+        Ok(OptionalBody::Present(text.to_string().into(), content_type))
+      }
+    },
+    _ => Ok(OptionalBody::Present(text.to_string().into(), content_type))
+  }
+}
+
+/// Adds type matchers to the values of the JSON body, so the values do not have to be the same
+/// as the recorded ones. Arrays are matched with a minimum length of one.
+pub(crate) fn add_type_matchers(json: &Value, path: &str, matching_rules: &mut MatchingRules) {
+  match json {
+    Value::Object(map) => for (key, value) in map {
+      let child = if !key.is_empty() && key.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-') {
+        format!("{}.{}", path, key)
+      } else {
+        format!("{}['{}']", path, key.replace('\'', "\\'"))
+      };
+      add_type_matchers(value, &child, matching_rules);
+    },
+    Value::Array(items) => if let Some(item) = items.first() {
+      matching_rules.add_category("body").add_rule(path, MatchingRule::MinType(1), &RuleLogic::And);
+      add_type_matchers(item, &format!("{}[*]", path), matching_rules);
+    },
+    Value::Null => (),
+    _ => matching_rules.add_category("body").add_rule(path, MatchingRule::Type, &RuleLogic::And)
+  }
+}
+
+/// Converts a HAR entry into an interaction
+fn har_interaction(entry: &Value) -> anyhow::Result<RequestResponseInteraction> {
+  let har_request = entry.get("request").ok_or_else(|| anyhow!("Entry has no request"))?;
+  let har_response = entry.get("response").ok_or_else(|| anyhow!("Entry has no response"))?;
+  let url = har_request.get("url").and_then(|u| u.as_str()).ok_or_else(|| anyhow!("Request has no URL"))?;
+  let url = Url::parse(url).map_err(|err| anyhow!("Request URL '{}' is not valid - {}", url, err))?;
+  let method = har_request.get("method").and_then(|m| m.as_str()).unwrap_or("GET").to_uppercase();
+
+  let mut query: HashMap<String, Vec<String>> = HashMap::new();
+  for (name, value) in url.query_pairs() {
+    query.entry(name.to_string()).or_default().push(value.to_string());
+  }
+  let post_data = har_request.get("postData");
+  let request = Request {
+    method: method.clone(),
+    path: url.path().to_string(),
+    query: if query.is_empty() { None } else { Some(query) },
+    headers: har_headers(har_request.get("headers")),
+    body: har_body(post_data.and_then(|p| p.get("text")).and_then(|t| t.as_str()),
+      post_data.and_then(|p| p.get("mimeType")).and_then(|m| m.as_str()), None)?,
+    .. Request::default()
+  };
+
+  let status = har_response.get("status").and_then(|s| s.as_u64()).unwrap_or(200) as u16;
+  let content = har_response.get("content");
+  let mut response = Response {
+    status,
+    headers: har_headers(har_response.get("headers")),
+    body: har_body(content.and_then(|c| c.get("text")).and_then(|t| t.as_str()),
+      content.and_then(|c| c.get("mimeType")).and_then(|m| m.as_str()),
+      content.and_then(|c| c.get("encoding")).and_then(|e| e.as_str()))?,
+    .. Response::default()
+  };
+  if let OptionalBody::Present(bytes, Some(ct)) = &response.body {
+    if ct.is_json() {
+      if let Ok(json) = serde_json::from_slice::<Value>(bytes) {
+        add_type_matchers(&json, "$", &mut response.matching_rules);
+      }
+    }
+  }
+
+  let description = match url.query() {
+    Some(query) => format!("{} {}?{} -> {}", method, url.path(), query, status),
+    None => format!("{} {} -> {}", method, url.path(), status)
+  };
+  Ok(RequestResponseInteraction {
+    description,
+    request,
+    response,
+    .. RequestResponseInteraction::default()
+  })
+}
+
+/// Imports the entries of the HAR document as interactions. Only entries with a URL that matches
+/// the filter are imported, and entries with the same method, URL and response status as an
+/// earlier entry are skipped.
+pub(crate) fn import_har(har: &Value, consumer: &str, provider: &str, filter: Option<&Regex>) -> anyhow::Result<HarImport> {
+  let entries = har.pointer("/log/entries").and_then(|e| e.as_array())
+    .ok_or_else(|| anyhow!("It is not a HAR file, as it does not have any log entries"))?;
+  let mut interactions: Vec<RequestResponseInteraction> = vec![];
+  let mut skipped = 0;
+  for (index, entry) in entries.iter().enumerate() {
+    let url = entry.pointer("/request/url").and_then(|u| u.as_str()).unwrap_or_default();
+    if filter.map(|re| !re.is_match(url)).unwrap_or(false) {
+      debug!("Skipping entry {} as the URL '{}' does not match the filter", index, url);
+      skipped += 1;
+      continue;
+    }
+    let interaction = har_interaction(entry).map_err(|err| anyhow!("Failed to import entry {} - {}", index, err))?;
+    if interactions.iter().any(|i| i.description == interaction.description) {
+      debug!("Skipping entry {} as it is a duplicate of '{}'", index, interaction.description);
+      skipped += 1;
+    } else {
+      interactions.push(interaction);
+    }
+  }
+
+  Ok(HarImport {
+    pact: RequestResponsePact {
+      consumer: Consumer { name: consumer.to_string() },
+      provider: Provider { name: provider.to_string() },
+      interactions,
+      specification_version: PactSpecification::V3,
+      .. RequestResponsePact::default()
+    },
+    skipped
+  })
+}
+
+pub(crate) fn handle_har_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let file = args.value_of("file").unwrap();
+  let contents = fs::read_to_string(file).map_err(|err| anyhow!("Failed to read HAR file '{}' - {}", file, err))?;
+  let har: Value = serde_json::from_str(&contents).map_err(|err| anyhow!("Failed to parse HAR file '{}' - {}", file, err))?;
+  let filter = match args.value_of("url") {
+    Some(filter) => Some(Regex::new(filter)
+      .map_err(|err| anyhow!("'{}' is not a valid regular expression: {}", filter, err))?),
+    None => None
+  };
+
+  let result = import_har(&har, args.value_of("consumer").unwrap(), args.value_of("provider").unwrap(), filter.as_ref())?;
+  let output = args.value_of("output").unwrap();
+  let interactions = result.pact.interactions.len();
+  write_pact(Box::new(result.pact), Path::new(output), PactSpecification::V3, true, MergeStrategy::default())?;
+  println!("Imported {} interaction(s) from '{}' into '{}' ({} entries skipped)", interactions, file, output, result.skipped);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use pact_matching::matchingrules;
+  use pact_matching::models::HttpPart;
+
+  use super::*;
+
+  fn har() -> Value {
+    json!({
+      "log": {
+        "version": "1.2",
+        "entries": [
+          {
+            "request": {
+              "method": "GET",
+              "url": "https://api.example.com/users/100?fields=name&fields=email",
+              "headers": [
+                { "name": ":authority", "value": "api.example.com" },
+                { "name": "Accept", "value": "application/json" },
+                { "name": "Accept-Encoding", "value": "gzip" }
+              ]
+            },
+            "response": {
+              "status": 200,
+              "headers": [ { "name": "Content-Type", "value": "application/json; charset=utf-8" } ],
+              "content": {
+                "mimeType": "application/json; charset=utf-8",
+                "text": "{\"id\": 100, \"name\": \"Fred\", \"roles\": [\"admin\"], \"manager\": null}"
+              }
+            }
+          },
+          {
+            "request": {
+              "method": "POST",
+              "url": "https://api.example.com/users",
+              "headers": [ { "name": "Content-Type", "value": "application/x-www-form-urlencoded" } ],
+              "postData": { "mimeType": "application/x-www-form-urlencoded", "text": "name=Fred" }
+            },
+            "response": {
+              "status": 201,
+              "content": { "mimeType": "image/png", "text": "iVBORw0K", "encoding": "base64" }
+            }
+          },
+          {
+            "request": { "method": "GET", "url": "https://cdn.example.com/app.js" },
+            "response": { "status": 200, "content": { "mimeType": "application/javascript", "text": "" } }
+          },
+          {
+            "request": { "method": "GET", "url": "https://api.example.com/users/100?fields=name&fields=email" },
+            "response": { "status": 200, "content": {} }
+          }
+        ]
+      }
+    })
+  }
+
+  #[test]
+  fn import_har_converts_the_entries_into_interactions() {
+    let filter = Regex::new("^https://api.example.com/").unwrap();
+    let result = import_har(&har(), "web", "users", Some(&filter)).unwrap();
+    expect!(result.skipped).to(be_equal_to(2));
+    let interactions = &result.pact.interactions;
+    expect!(interactions.iter().map(|i| i.description.clone()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "GET /users/100?fields=name&fields=email -> 200".to_string(),
+      "POST /users -> 201".to_string()
+    ]));
+
+    let get = &interactions[0];
+    expect!(get.request.path.as_str()).to(be_equal_to("/users/100"));
+    expect!(get.request.query.clone()).to(be_some().value(hashmap! {
+      "fields".to_string() => vec!["name".to_string(), "email".to_string()]
+    }));
+    expect!(get.request.headers.clone()).to(be_some().value(hashmap! {
+      "Accept".to_string() => vec!["application/json".to_string()]
+    }));
+    expect!(get.response.body.str_value()).to(be_equal_to("{\"id\":100,\"manager\":null,\"name\":\"Fred\",\"roles\":[\"admin\"]}"));
+    expect!(get.response.content_type().map(|ct| ct.is_json())).to(be_some().value(true));
+    expect!(get.response.matching_rules.clone()).to(be_equal_to(matchingrules! {
+      "body" => {
+        "$.id" => [ MatchingRule::Type ],
+        "$.name" => [ MatchingRule::Type ],
+        "$.roles" => [ MatchingRule::MinType(1) ],
+        "$.roles[*]" => [ MatchingRule::Type ]
+      }
+    }));
+
+    let post = &interactions[1];
+    expect!(post.request.body.str_value()).to(be_equal_to("name=Fred"));
+    expect!(post.response.body.value().unwrap().to_vec()).to(be_equal_to(vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A]));
+    expect!(post.response.matching_rules.is_empty()).to(be_true());
+  }
+
+  #[test]
+  fn import_har_fails_if_it_is_not_a_har_file() {
+    expect!(import_har(&json!({ "entries": [] }), "web", "users", None)).to(be_err());
+    expect!(import_har(&json!({ "log": { "entries": [ { "request": {} } ] } }), "web", "users", None)).to(be_err());
+  }
+}
@@ -0,0 +1,872 @@
+//! The `pact_cli` crate provides a command line tool for inspecting and modifying pact files.
+//!
+//! ## Commands
+//!
+//! ### rules
+//!
+//! Lists, removes or adds matching rules in bulk across all the interactions in a pact file.
+//!
+//! * `pact_cli rules list -f <file> [--json]` lists all the matching rules.
+//! * `pact_cli rules remove -f <file> [--category <category>] [--path <path>] [--type <type>]` removes
+//!   all the matching rules selected by the category, path and rule type (the `match` attribute of the rule).
+//! * `pact_cli rules add -f <file> --category <category> --path <path> --rule <json>` adds the
+//!   matching rule (given in its pact JSON form, i.e. `{"match": "type"}`) to all the interactions.
+//!
+//! The `remove` and `add` actions can be limited to interactions whose description matches a regular
+//! expression with `--interaction <regex>`, and to a part of the interaction with `--part <part>`
+//! (`request`, `response` or `message`). The pact file is updated in place, unless an output file
+//! is given with `-o, --output <file>`.
+//!
+//! ### evolve
+//!
+//! `pact_cli evolve -f <file> [-o <output>] [--dry-run] [--json]` loosens the exact value
+//! expectations of the bodies in a pact file (i.e. one generated from recorded traffic). JSON
+//! fields with values that look volatile are given a matcher and a generator, so they no longer
+//! need to match the recorded value exactly:
+//!
+//! * UUID strings get a regex matcher and a UUID generator.
+//! * Date and timestamp strings (ISO 8601 formats) get a date or timestamp matcher and generator
+//!   with the format of the value.
+//! * Integer values of ID attributes (`id`, or names ending in `Id` or `_id`) get an integer
+//!   matcher and a random integer generator.
+//!
+//! Fields that already have a matcher are not changed. V2 pacts only get regex and type matchers,
+//! as they do not support the others or generators. The fields that are updated are listed, and
+//! with `--dry-run` the pact file is not updated. The `--interaction` and `--part` options limit
+//! the interactions that are updated, as with the `rules` command.
+//!
+//! ### redact
+//!
+//! `pact_cli redact -f <file> [-o <output>] [--header <name>...] [--query <name>...] [--body <path>...] [--placeholder <value>]`
+//! replaces sensitive values (i.e. tokens or personal information) in a pact file, so the pact can
+//! be safely published. The values of the headers (case-insensitive), query parameters and body
+//! fields (given as path expressions, i.e. `$.user.email` or `$.items[*].token`) are replaced with
+//! the placeholder (`REDACTED` by default) and a type matcher is set on them. Non-string body values
+//! are replaced with an empty value of the same type (`0`, `false`, `[]` or `{}`). Only JSON bodies
+//! are supported. The `--interaction` and `--part` options limit the interactions that are updated,
+//! as with the `rules` command.
+//!
+//! ### diff
+//!
+//! `pact_cli diff <old-file> <new-file> [--json]` compares the interactions in two pact files, and
+//! prints the interactions that were added, removed or changed. Interactions are matched on their
+//! description and provider states, and for changed interactions the differences in the request
+//! and response (or message) are listed.
+//!
+//! ### merge
+//!
+//! `pact_cli merge -o <output> [--on-conflict fail|keep-first] <file> <file>...` merges two or more
+//! pact files for the same consumer and provider into the output file. Interactions with the same
+//! description and provider states are only included once. If they are defined differently in the
+//! pact files, the merge fails unless `--on-conflict keep-first` is given, in which case the
+//! interaction from the first pact file is kept.
+//!
+//! ### format
+//!
+//! `pact_cli format [--check] <file>...` rewrites the pact files into a canonical form, so changes to
+//! pact files produce clean diffs in code review. The interactions are sorted by description,
+//! provider states and type, the keys of the headers, query parameters and metadata are sorted,
+//! JSON bodies are normalised and V4 interactions all have their keys set. With `--check` the pact
+//! files are not updated, and the command fails if any of them are not in the canonical form.
+//!
+//! ### publish
+//!
+//! `pact_cli publish -b <broker-url> -a <consumer-version> [-t <tag>...] [--branch <branch>] <file>...`
+//! publishes the pact files to a Pact Broker for the consumer version. The tags and branch are
+//! added to the consumer version before the pacts are published. Publishing fails if a different
+//! pact has already been published for the same consumer version. The broker credentials can be
+//! given with `--user` and `--password`, or `--token` (or the `PACT_BROKER_*` environment variables).
+//!
+//! ### can-i-deploy
+//!
+//! `pact_cli can-i-deploy -b <broker-url> --pacticipant <name> (--version <version> | --latest [--tag <tag>])... [--to-environment <env> | --to <tag>] [--json]`
+//! queries the matrix of a Pact Broker to check if the pacticipant versions can be safely deployed
+//! (to the environment, or with the latest versions of the other pacticipants with the tag). It
+//! fails if the versions can not be deployed, or the broker is not able to tell.
+//!
+//! ### create-version-tag, record-deployment and record-release
+//!
+//! * `pact_cli create-version-tag -b <broker-url> -p <pacticipant> -e <version> -t <tag>...` adds the
+//!   tags to the pacticipant version.
+//! * `pact_cli record-deployment -b <broker-url> -p <pacticipant> -e <version> --environment <env> [--application-instance <instance>]`
+//!   records that the pacticipant version has been deployed to the environment.
+//! * `pact_cli record-release -b <broker-url> -p <pacticipant> -e <version> --environment <env>`
+//!   records that the pacticipant version has been released to the environment.
+//!
+//! ### verify
+//!
+//! `pact_cli verify -f <file> [--spec <version>] [--strict] [--json]` checks that the pact file is
+//! valid for its specification version (taken from the pact metadata, unless given with `--spec`).
+//! Errors and warnings are printed with the path to the invalid item in the pact file. Unknown
+//! attributes are reported as warnings, or as errors with `--strict`.
+//!
+//! ### openapi
+//!
+//! `pact_cli openapi import -f <document> -o <output> --consumer <name> [--provider <name>]` generates
+//! a skeleton V4 pact from an OpenAPI 3 document (in JSON or YAML form), as a starting point for the
+//! contract with the provider. There is an interaction for each operation, with the request and the
+//! first successful response built from the examples in the document (or example values generated
+//! from the schemas). The path has a regex matcher if it has parameters, and the bodies, required
+//! query parameters and headers have type-based matching rules. The provider name defaults to the
+//! title of the document.
+//!
+//! `pact_cli openapi export -o <output> [--title <title>] [--api-version <version>] <file>...` does
+//! the reverse, and generates a skeleton OpenAPI 3 document from the HTTP interactions in the pact
+//! files for a provider (i.e. to document a provider API that is only defined by its pacts). The
+//! interactions are grouped into operations by path and method. Numeric and UUID path segments are
+//! assumed to be identifiers and become path parameters (i.e. `/users/100` becomes
+//! `/users/{userId}`). The schemas of JSON bodies are inferred from the example bodies and their
+//! matching rules. The document is written as YAML if the output file has a `.yaml` or `.yml`
+//! extension, otherwise as JSON.
+//!
+//! ### har
+//!
+//! `pact_cli har -f <file> -o <output> --consumer <name> --provider <name> [--url <regex>]` imports
+//! the requests and responses from an HTTP Archive (HAR) file, as captured by a browser or proxy,
+//! into a pact to bootstrap it from a real session. Only the entries with a URL that matches the
+//! `--url` regex are imported, and duplicate entries are skipped. Headers set by the HTTP client or
+//! server (i.e. `Host` and `Content-Length`) are not imported. JSON bodies are normalised, base64
+//! encoded response bodies are decoded, and JSON response bodies get type matchers for all their
+//! values, so the pact does not depend on the recorded data.
+//!
+//! ### postman
+//!
+//! `pact_cli postman -f <file> -o <output> [--base-url <url>]` exports the HTTP interactions of a
+//! pact file as a Postman collection (v2.1), so the requests can be replayed against a provider
+//! manually while debugging it. Each request has the headers, query parameters and body of the
+//! interaction, and a test that checks the response status. The provider states are added to the
+//! description of the requests. The base URL is set as the `baseUrl` collection variable, and
+//! defaults to `http://localhost:8080`.
+//!
+//! ### stub
+//!
+//! `pact_cli stub [-p <port>] [--cors] [--ignore <part>...] [--tls-cert <file> --tls-key <file>] [<path>...] [-b <broker-url> --provider <name>]`
+//! serves the interactions from the pact files (or the pact files in directories), and the latest
+//! pacts for the provider from a Pact Broker, as a stub provider until it is stopped with Ctrl-C.
+//! It uses the stub mode of the mock server, so the response of the closest matching interaction
+//! is returned, ignoring mismatches in the request body and query parameters by default (the
+//! parts to ignore can be given with `--ignore`).
+//!
+//! ### spec-tests
+//!
+//! `pact_cli spec-tests -d <dir> [--spec <version>] [--filter <regex>] [--failures-only] [--json]`
+//! runs the pact-specification test cases in the directory against the matching engine. The test
+//! cases use the layout of the pact-specification project (i.e. `v3/request/method/*.json`), with
+//! the specification version and type of test case taken from the directory names. It fails if
+//! any of the test cases fail.
+
+#![warn(missing_docs)]
+
+use std::env;
+use std::str::FromStr;
+
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, ErrorKind, SubCommand};
+use log::LevelFilter;
+use simplelog::{Config, SimpleLogger, TermLogger, TerminalMode};
+
+mod can_i_deploy;
+mod diff;
+mod evolve;
+mod format;
+mod har;
+mod merge;
+mod openapi;
+mod pact_file;
+mod postman;
+mod publish;
+mod redact;
+mod rules;
+mod spec_tests;
+mod stub;
+mod verify;
+mod versions;
+
+fn setup_loggers(level: &str, no_term_log: bool) -> Result<(), String> {
+  let log_level = match level {
+    "none" => LevelFilter::Off,
+    _ => LevelFilter::from_str(level).unwrap()
+  };
+  if no_term_log {
+    SimpleLogger::init(log_level, Config::default()).map_err(|e| format!("{:?}", e))
+  } else {
+    TermLogger::init(log_level, Config::default(), TerminalMode::Stderr).map_err(|e| format!("{:?}", e))
+  }
+}
+
+fn pact_file_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("file")
+    .short("f")
+    .long("file")
+    .takes_value(true)
+    .use_delimiter(false)
+    .required(true)
+    .help("the pact file to process")
+}
+
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("output")
+    .short("o")
+    .long("output")
+    .takes_value(true)
+    .use_delimiter(false)
+    .help("the file to write the updated pact to (defaults to updating the pact file in place)")
+}
+
+fn broker_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  let mut args = vec![
+    Arg::with_name("broker-url")
+      .short("b")
+      .long("broker-url")
+      .env("PACT_BROKER_BASE_URL")
+      .takes_value(true)
+      .use_delimiter(false)
+      .required(true)
+      .help("URL of the Pact Broker")
+  ];
+  args.extend(broker_auth_args());
+  args
+}
+
+fn broker_auth_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  vec![
+    Arg::with_name("user")
+      .long("user")
+      .env("PACT_BROKER_USERNAME")
+      .takes_value(true)
+      .use_delimiter(false)
+      .conflicts_with("token")
+      .help("username to use with the Pact Broker"),
+    Arg::with_name("password")
+      .long("password")
+      .env("PACT_BROKER_PASSWORD")
+      .takes_value(true)
+      .use_delimiter(false)
+      .conflicts_with("token")
+      .help("password to use with the Pact Broker"),
+    Arg::with_name("token")
+      .long("token")
+      .env("PACT_BROKER_TOKEN")
+      .takes_value(true)
+      .use_delimiter(false)
+      .conflicts_with("user")
+      .help("bearer token to use with the Pact Broker")
+  ]
+}
+
+fn pacticipant_version_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  vec![
+    Arg::with_name("pacticipant")
+      .short("p")
+      .long("pacticipant")
+      .takes_value(true)
+      .use_delimiter(false)
+      .required(true)
+      .help("name of the pacticipant"),
+    Arg::with_name("version")
+      .short("e")
+      .long("version")
+      .takes_value(true)
+      .use_delimiter(false)
+      .required(true)
+      .help("the version of the pacticipant")
+  ]
+}
+
+fn interaction_filter_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  vec![
+    Arg::with_name("interaction")
+      .long("interaction")
+      .takes_value(true)
+      .use_delimiter(false)
+      .help("only update interactions whose description matches this regular expression"),
+    Arg::with_name("part")
+      .long("part")
+      .takes_value(true)
+      .use_delimiter(false)
+      .possible_values(&["request", "response", "message"])
+      .help("only update this part of the interactions")
+  ]
+}
+
+fn setup_app<'a, 'b>(program: String, version: &'b str) -> App<'a, 'b> {
+  App::new(program)
+    .version(version)
+    .about("Command line tools for working with pact files")
+    .version_short("v")
+    .setting(AppSettings::ArgRequiredElseHelp)
+    .setting(AppSettings::SubcommandRequired)
+    .setting(AppSettings::GlobalVersion)
+    .setting(AppSettings::VersionlessSubcommands)
+    .setting(AppSettings::ColoredHelp)
+    .arg(Arg::with_name("loglevel")
+      .short("l")
+      .long("loglevel")
+      .takes_value(true)
+      .use_delimiter(false)
+      .global(true)
+      .possible_values(&["error", "warn", "info", "debug", "trace", "none"])
+      .help("Log level (defaults to warn)"))
+    .arg(Arg::with_name("no-term-log")
+      .long("no-term-log")
+      .global(true)
+      .help("Use a simple logger instead of the term based one"))
+    .subcommand(SubCommand::with_name("rules")
+      .about("Lists, removes or adds matching rules across all the interactions in a pact file")
+      .setting(AppSettings::SubcommandRequiredElseHelp)
+      .setting(AppSettings::ColoredHelp)
+      .subcommand(SubCommand::with_name("list")
+        .about("Lists all the matching rules in the pact file")
+        .arg(pact_file_arg())
+        .arg(Arg::with_name("json")
+          .long("json")
+          .help("output the matching rules as JSON"))
+        .setting(AppSettings::ColoredHelp))
+      .subcommand(SubCommand::with_name("remove")
+        .about("Removes the matching rules selected by category, path and type")
+        .arg(pact_file_arg())
+        .arg(output_arg())
+        .args(&interaction_filter_args())
+        .arg(Arg::with_name("category")
+          .short("c")
+          .long("category")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("only remove rules in this category (i.e. body, header, query, path)"))
+        .arg(Arg::with_name("path")
+          .short("p")
+          .long("path")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("only remove rules for this path (i.e. $.id for a body rule)"))
+        .arg(Arg::with_name("type")
+          .short("t")
+          .long("type")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("only remove rules of this type (i.e. type, regex, integer)"))
+        .setting(AppSettings::ColoredHelp))
+      .subcommand(SubCommand::with_name("add")
+        .about("Adds a matching rule to a path across the interactions")
+        .arg(pact_file_arg())
+        .arg(output_arg())
+        .args(&interaction_filter_args())
+        .arg(Arg::with_name("category")
+          .short("c")
+          .long("category")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("category to add the rule to (i.e. body, header, query, path)"))
+        .arg(Arg::with_name("path")
+          .short("p")
+          .long("path")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("path to add the rule to (i.e. $.id for a body rule)"))
+        .arg(Arg::with_name("rule")
+          .short("r")
+          .long("rule")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("the matching rule to add, in JSON form (i.e. {\"match\": \"type\"})"))
+        .setting(AppSettings::ColoredHelp)))
+    .subcommand(SubCommand::with_name("evolve")
+      .about("Replaces the exact values of volatile fields (timestamps, UUIDs and IDs) with matchers and generators")
+      .arg(pact_file_arg())
+      .arg(output_arg())
+      .args(&interaction_filter_args())
+      .arg(Arg::with_name("dry-run")
+        .long("dry-run")
+        .help("only list the fields that would be updated, without updating the pact file"))
+      .arg(Arg::with_name("json")
+        .long("json")
+        .help("output the updated fields as JSON"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("redact")
+      .about("Replaces sensitive header values, query parameters and body fields with placeholders and matchers")
+      .arg(pact_file_arg())
+      .arg(output_arg())
+      .args(&interaction_filter_args())
+      .arg(Arg::with_name("header")
+        .long("header")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .help("name of a header to redact (case-insensitive)"))
+      .arg(Arg::with_name("query")
+        .long("query")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .help("name of a query parameter to redact"))
+      .arg(Arg::with_name("body")
+        .long("body")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .help("path expression of a body field to redact (i.e. $.user.email)"))
+      .group(ArgGroup::with_name("redactions")
+        .args(&["header", "query", "body"])
+        .multiple(true)
+        .required(true))
+      .arg(Arg::with_name("placeholder")
+        .long("placeholder")
+        .takes_value(true)
+        .use_delimiter(false)
+        .default_value("REDACTED")
+        .help("value to replace the redacted string values with"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("diff")
+      .about("Compares the interactions in two pact files")
+      .arg(Arg::with_name("old")
+        .takes_value(true)
+        .required(true)
+        .help("the pact file to compare against"))
+      .arg(Arg::with_name("new")
+        .takes_value(true)
+        .required(true)
+        .help("the pact file to compare"))
+      .arg(Arg::with_name("json")
+        .long("json")
+        .help("output the differences as JSON"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("merge")
+      .about("Merges two or more pact files for the same consumer and provider")
+      .arg(Arg::with_name("files")
+        .takes_value(true)
+        .multiple(true)
+        .min_values(2)
+        .required(true)
+        .help("the pact files to merge"))
+      .arg(Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("the file to write the merged pact to"))
+      .arg(Arg::with_name("on-conflict")
+        .long("on-conflict")
+        .takes_value(true)
+        .use_delimiter(false)
+        .possible_values(&["fail", "keep-first"])
+        .default_value("fail")
+        .help("what to do when an interaction is defined differently in the pact files"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("format")
+      .about("Rewrites pact files into a canonical form")
+      .arg(Arg::with_name("files")
+        .takes_value(true)
+        .multiple(true)
+        .required(true)
+        .help("the pact files to format"))
+      .arg(Arg::with_name("check")
+        .long("check")
+        .help("only check that the pact files are formatted, without updating them"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("publish")
+      .about("Publishes pact files to a Pact Broker")
+      .arg(Arg::with_name("files")
+        .takes_value(true)
+        .multiple(true)
+        .required(true)
+        .help("the pact files to publish"))
+      .arg(Arg::with_name("broker-url")
+        .short("b")
+        .long("broker-url")
+        .env("PACT_BROKER_BASE_URL")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("URL of the Pact Broker to publish the pacts to"))
+      .arg(Arg::with_name("consumer-app-version")
+        .short("a")
+        .long("consumer-app-version")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("the version of the consumer the pacts are for"))
+      .arg(Arg::with_name("tag")
+        .short("t")
+        .long("tag")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .help("tag to add to the consumer version (can be repeated)"))
+      .arg(Arg::with_name("branch")
+        .long("branch")
+        .takes_value(true)
+        .use_delimiter(false)
+        .help("the branch of the consumer version"))
+      .arg(Arg::with_name("user")
+        .long("user")
+        .env("PACT_BROKER_USERNAME")
+        .takes_value(true)
+        .use_delimiter(false)
+        .conflicts_with("token")
+        .help("username to use when publishing to the Pact Broker"))
+      .arg(Arg::with_name("password")
+        .long("password")
+        .env("PACT_BROKER_PASSWORD")
+        .takes_value(true)
+        .use_delimiter(false)
+        .conflicts_with("token")
+        .help("password to use when publishing to the Pact Broker"))
+      .arg(Arg::with_name("token")
+        .long("token")
+        .env("PACT_BROKER_TOKEN")
+        .takes_value(true)
+        .use_delimiter(false)
+        .conflicts_with("user")
+        .help("bearer token to use when publishing to the Pact Broker"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("can-i-deploy")
+      .about("Checks with a Pact Broker if versions of pacticipants can be safely deployed")
+      .args(&broker_args())
+      .arg(Arg::with_name("pacticipant")
+        .long("pacticipant")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .required(true)
+        .help("name of the pacticipant, followed by --version or --latest (can be repeated)"))
+      .arg(Arg::with_name("version")
+        .short("e")
+        .long("version")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .help("the version of the preceding pacticipant"))
+      .arg(Arg::with_name("latest")
+        .long("latest")
+        .multiple(true)
+        .help("use the latest version of the preceding pacticipant"))
+      .arg(Arg::with_name("tag")
+        .long("tag")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .requires("latest")
+        .help("use the latest version of the preceding pacticipant with this tag"))
+      .arg(Arg::with_name("to-environment")
+        .long("to-environment")
+        .takes_value(true)
+        .use_delimiter(false)
+        .conflicts_with("to")
+        .help("the environment the versions are to be deployed to"))
+      .arg(Arg::with_name("to")
+        .long("to")
+        .takes_value(true)
+        .use_delimiter(false)
+        .help("the tag of the pacticipant versions the versions are to be deployed with"))
+      .arg(Arg::with_name("json")
+        .long("json")
+        .help("output the result as JSON"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("create-version-tag")
+      .about("Adds a tag to a version of a pacticipant in a Pact Broker")
+      .args(&broker_args())
+      .args(&pacticipant_version_args())
+      .arg(Arg::with_name("tag")
+        .short("t")
+        .long("tag")
+        .takes_value(true)
+        .use_delimiter(false)
+        .multiple(true)
+        .number_of_values(1)
+        .required(true)
+        .help("tag to add to the version (can be repeated)"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("record-deployment")
+      .about("Records the deployment of a version of a pacticipant to an environment in a Pact Broker")
+      .args(&broker_args())
+      .args(&pacticipant_version_args())
+      .arg(Arg::with_name("environment")
+        .long("environment")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("name of the environment the version was deployed to"))
+      .arg(Arg::with_name("application-instance")
+        .long("application-instance")
+        .takes_value(true)
+        .use_delimiter(false)
+        .help("the instance of the application that was deployed, if more than one is deployed to the environment"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("record-release")
+      .about("Records the release of a version of a pacticipant to an environment in a Pact Broker")
+      .args(&broker_args())
+      .args(&pacticipant_version_args())
+      .arg(Arg::with_name("environment")
+        .long("environment")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("name of the environment the version was released to"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("verify")
+      .about("Checks that a pact file is valid for its specification version")
+      .arg(pact_file_arg())
+      .arg(Arg::with_name("spec")
+        .short("s")
+        .long("spec")
+        .takes_value(true)
+        .use_delimiter(false)
+        .possible_values(&["1", "1.1", "2", "3", "4"])
+        .help("the specification version to verify against (defaults to the version in the pact metadata)"))
+      .arg(Arg::with_name("strict")
+        .long("strict")
+        .help("report unknown attributes as errors instead of warnings"))
+      .arg(Arg::with_name("json")
+        .long("json")
+        .help("output the results as JSON"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("openapi")
+      .about("Converts between OpenAPI 3 documents and pacts")
+      .setting(AppSettings::SubcommandRequiredElseHelp)
+      .setting(AppSettings::ColoredHelp)
+      .subcommand(SubCommand::with_name("import")
+        .about("Generates a skeleton pact with an interaction for each operation in an OpenAPI 3 document")
+        .arg(Arg::with_name("file")
+          .short("f")
+          .long("file")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("the OpenAPI document to import (JSON or YAML)"))
+        .arg(Arg::with_name("output")
+          .short("o")
+          .long("output")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("the file to write the pact to"))
+        .arg(Arg::with_name("consumer")
+          .long("consumer")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("name of the consumer"))
+        .arg(Arg::with_name("provider")
+          .long("provider")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("name of the provider (defaults to the title of the OpenAPI document)"))
+        .setting(AppSettings::ColoredHelp))
+      .subcommand(SubCommand::with_name("export")
+        .about("Generates a skeleton OpenAPI 3 document from the HTTP interactions in pact files for a provider")
+        .arg(Arg::with_name("files")
+          .takes_value(true)
+          .multiple(true)
+          .required(true)
+          .help("the pact files to export"))
+        .arg(Arg::with_name("output")
+          .short("o")
+          .long("output")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("the file to write the OpenAPI document to (as YAML if it has a .yaml or .yml extension)"))
+        .arg(Arg::with_name("title")
+          .long("title")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("title of the API (defaults to the name of the provider)"))
+        .arg(Arg::with_name("api-version")
+          .long("api-version")
+          .takes_value(true)
+          .use_delimiter(false)
+          .default_value("1.0.0")
+          .help("version of the API"))
+        .setting(AppSettings::ColoredHelp)))
+    .subcommand(SubCommand::with_name("har")
+      .about("Imports the requests and responses from a HAR file into a pact")
+      .arg(Arg::with_name("file")
+        .short("f")
+        .long("file")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("the HAR file to import"))
+      .arg(Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("the file to write the pact to"))
+      .arg(Arg::with_name("consumer")
+        .long("consumer")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("name of the consumer"))
+      .arg(Arg::with_name("provider")
+        .long("provider")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("name of the provider"))
+      .arg(Arg::with_name("url")
+        .long("url")
+        .takes_value(true)
+        .use_delimiter(false)
+        .help("only import the entries with a URL that matches this regular expression"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("postman")
+      .about("Exports the HTTP interactions of a pact file as a Postman collection")
+      .arg(pact_file_arg())
+      .arg(Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("the file to write the Postman collection to"))
+      .arg(Arg::with_name("base-url")
+        .long("base-url")
+        .takes_value(true)
+        .use_delimiter(false)
+        .default_value("http://localhost:8080")
+        .help("base URL of the provider, set as the baseUrl variable of the collection"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("stub")
+      .about("Serves the pacts in directories or from a Pact Broker as a stub provider")
+      .arg(Arg::with_name("paths")
+        .takes_value(true)
+        .multiple(true)
+        .required_unless("broker-url")
+        .help("the pact files, or directories of pact files, to serve"))
+      .arg(Arg::with_name("broker-url")
+        .short("b")
+        .long("broker-url")
+        .takes_value(true)
+        .use_delimiter(false)
+        .requires("provider")
+        .help("URL of a Pact Broker to fetch the latest pacts for the provider from"))
+      .arg(Arg::with_name("provider")
+        .long("provider")
+        .takes_value(true)
+        .use_delimiter(false)
+        .requires("broker-url")
+        .help("name of the provider to fetch the pacts for from the Pact Broker"))
+      .args(&broker_auth_args())
+      .arg(Arg::with_name("port")
+        .short("p")
+        .long("port")
+        .takes_value(true)
+        .use_delimiter(false)
+        .help("port the stub server runs on (defaults to a port assigned by the OS)"))
+      .arg(Arg::with_name("cors")
+        .long("cors")
+        .help("respond to CORS pre-flight requests"))
+      .arg(Arg::with_name("ignore")
+        .long("ignore")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .possible_values(&["body", "headers", "query"])
+        .help("a part of the requests to ignore mismatches in (can be repeated, defaults to body and query)"))
+      .arg(Arg::with_name("tls-cert")
+        .long("tls-cert")
+        .takes_value(true)
+        .use_delimiter(false)
+        .requires("tls-key")
+        .help("PEM file with the certificate to serve the stub with HTTPS"))
+      .arg(Arg::with_name("tls-key")
+        .long("tls-key")
+        .takes_value(true)
+        .use_delimiter(false)
+        .requires("tls-cert")
+        .help("PEM file with the private key of the certificate"))
+      .setting(AppSettings::ColoredHelp))
+    .subcommand(SubCommand::with_name("spec-tests")
+      .about("Runs pact-specification test cases against the matching engine")
+      .arg(Arg::with_name("dir")
+        .short("d")
+        .long("dir")
+        .takes_value(true)
+        .use_delimiter(false)
+        .required(true)
+        .help("the directory containing the test cases"))
+      .arg(Arg::with_name("spec")
+        .short("s")
+        .long("spec")
+        .takes_value(true)
+        .use_delimiter(false)
+        .possible_values(&["1", "1.1", "2", "3", "4"])
+        .help("the specification version to run all the test cases with (defaults to the version from the directory names)"))
+      .arg(Arg::with_name("filter")
+        .long("filter")
+        .takes_value(true)
+        .use_delimiter(false)
+        .help("only run the test cases whose path matches this regular expression"))
+      .arg(Arg::with_name("failures-only")
+        .long("failures-only")
+        .help("only display the test cases that failed"))
+      .arg(Arg::with_name("json")
+        .long("json")
+        .help("output the results as JSON"))
+      .setting(AppSettings::ColoredHelp))
+}
+
+fn handle_command(matches: &ArgMatches) -> Result<(), i32> {
+  let result = match matches.subcommand() {
+    ("rules", Some(sub_matches)) => rules::handle_rules_command(sub_matches),
+    ("redact", Some(sub_matches)) => redact::handle_redact_command(sub_matches),
+    ("diff", Some(sub_matches)) => diff::handle_diff_command(sub_matches),
+    ("evolve", Some(sub_matches)) => evolve::handle_evolve_command(sub_matches),
+    ("merge", Some(sub_matches)) => merge::handle_merge_command(sub_matches),
+    ("format", Some(sub_matches)) => format::handle_format_command(sub_matches),
+    ("publish", Some(sub_matches)) => publish::handle_publish_command(sub_matches),
+    ("can-i-deploy", Some(sub_matches)) => can_i_deploy::handle_can_i_deploy_command(sub_matches),
+    ("create-version-tag", Some(sub_matches)) => versions::handle_create_version_tag_command(sub_matches),
+    ("record-deployment", Some(sub_matches)) => versions::handle_record_deployment_command(sub_matches),
+    ("record-release", Some(sub_matches)) => versions::handle_record_release_command(sub_matches),
+    ("verify", Some(sub_matches)) => verify::handle_verify_command(sub_matches),
+    ("spec-tests", Some(sub_matches)) => spec_tests::handle_spec_tests_command(sub_matches),
+    ("openapi", Some(sub_matches)) => openapi::handle_openapi_command(sub_matches),
+    ("har", Some(sub_matches)) => har::handle_har_command(sub_matches),
+    ("postman", Some(sub_matches)) => postman::handle_postman_command(sub_matches),
+    ("stub", Some(sub_matches)) => stub::handle_stub_command(sub_matches),
+    _ => return Err(3)
+  };
+  result.map_err(|err| {
+    eprintln!("ERROR: {}", err);
+    eprintln!();
+    1
+  })
+}
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  let program = args[0].clone();
+  let version = format!("v{}", clap::crate_version!());
+  let app = setup_app(program, version.as_str());
+
+  match app.get_matches_safe() {
+    Ok(ref matches) => {
+      let log_level = matches.value_of("loglevel").unwrap_or("warn");
+      if let Err(err) = setup_loggers(log_level, matches.is_present("no-term-log")) {
+        eprintln!("WARN: Could not setup loggers: {}", err);
+        eprintln!();
+      }
+      if let Err(err) = handle_command(matches) {
+        std::process::exit(err)
+      }
+    },
+    Err(ref err) => match err.kind {
+      ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => println!("{}", err.message),
+      _ => err.exit()
+    }
+  }
+}
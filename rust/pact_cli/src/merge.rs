@@ -0,0 +1,240 @@
+//! The `merge` command: merges two or more pact files for the same consumer and provider
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use log::*;
+
+use pact_matching::models::{Interaction, PactConflict};
+
+use crate::pact_file::PactFile;
+
+/// What to do when the same interaction is defined differently in the pacts being merged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ConflictBehaviour {
+  /// Fail the merge
+  Fail,
+  /// Keep the interaction from the first pact it was found in
+  KeepFirst
+}
+
+impl FromStr for ConflictBehaviour {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "fail" => Ok(ConflictBehaviour::Fail),
+      "keep-first" => Ok(ConflictBehaviour::KeepFirst),
+      _ => Err(anyhow!("'{}' is not a valid conflict behaviour, expected fail or keep-first", s))
+    }
+  }
+}
+
+fn same_interaction(a: &dyn Interaction, b: &dyn Interaction) -> bool {
+  a.description() == b.description() && a.provider_states() == b.provider_states()
+}
+
+/// Returns the differences between the two interactions if they have the same description and
+/// provider states
+fn interaction_conflicts(a: &dyn Interaction, b: &dyn Interaction) -> Vec<PactConflict> {
+  if !same_interaction(a, b) {
+    return vec![];
+  }
+
+  let conflict = |description: &str| PactConflict {
+    interaction: a.description(),
+    description: description.to_string()
+  };
+  match (a.as_request_response(), b.as_request_response(), a.as_message(), b.as_message()) {
+    (Some(a), Some(_), _, _) => a.conflicts_with(b),
+    (_, _, Some(a), Some(b)) => {
+      let mut conflicts = vec![];
+      if a.contents != b.contents {
+        conflicts.push(conflict("Message contents are different"));
+      }
+      if a.metadata != b.metadata {
+        conflicts.push(conflict("Message metadata is different"));
+      }
+      conflicts
+    },
+    _ => vec![conflict(&format!("Interaction types are different ({} and {})", a.type_of(), b.type_of()))]
+  }
+}
+
+/// Returns all the conflicts between the interactions of the two pacts
+pub(crate) fn find_conflicts(pact: &PactFile, other: &PactFile) -> Vec<PactConflict> {
+  let pact = pact.boxed();
+  let other = other.boxed();
+  let mut conflicts = vec![];
+  for a in pact.interactions() {
+    for b in other.interactions() {
+      conflicts.extend(interaction_conflicts(a, b));
+    }
+  }
+  conflicts
+}
+
+/// Merges the pacts in order, using the `ReadWritePact::merge` implementation of the first pact.
+/// Interactions with the same description and provider states are only included once.
+pub(crate) fn merge_pacts(pacts: Vec<PactFile>, on_conflict: ConflictBehaviour) -> anyhow::Result<PactFile> {
+  let mut pacts = pacts.into_iter();
+  let mut merged = pacts.next().ok_or_else(|| anyhow!("At least one pact is required to merge"))?;
+  merged.sort_interactions();
+
+  for mut pact in pacts {
+    let conflicts = find_conflicts(&merged, &pact);
+    if !conflicts.is_empty() {
+      match on_conflict {
+        ConflictBehaviour::Fail => {
+          let details = conflicts.iter()
+            .map(|conflict| format!("  '{}': {}", conflict.interaction, conflict.description))
+            .collect::<Vec<String>>()
+            .join("\n");
+          return Err(anyhow!("Unable to merge pacts, as there were {} conflict(s) between the interactions:\n{}",
+            conflicts.len(), details));
+        },
+        ConflictBehaviour::KeepFirst => for conflict in &conflicts {
+          warn!("Interaction '{}' conflicts with an earlier pact ({}), keeping the first one",
+            conflict.interaction, conflict.description);
+        }
+      }
+    }
+
+    if on_conflict == ConflictBehaviour::KeepFirst {
+      let existing = merged.boxed();
+      pact.retain_interactions(|interaction| !existing.interactions().iter()
+        .any(|i| same_interaction(*i, interaction)));
+    }
+    pact.sort_interactions();
+
+    let result = merged.boxed().merge(pact.boxed().as_ref())?;
+    merged = PactFile::from_pact(result.as_ref())?;
+  }
+
+  Ok(merged)
+}
+
+pub(crate) fn handle_merge_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let on_conflict = args.value_of("on-conflict").unwrap_or("fail").parse()?;
+  let pacts = args.values_of("files").unwrap()
+    .map(|file| {
+      debug!("Loading pact file '{}'", file);
+      PactFile::load(Path::new(file)).map_err(|err| anyhow!("Failed to load pact file '{}' - {}", file, err))
+    })
+    .collect::<anyhow::Result<Vec<PactFile>>>()?;
+  let pact_count = pacts.len();
+  let merged = merge_pacts(pacts, on_conflict)?;
+  let output = args.value_of("output").unwrap();
+  merged.write(Path::new(output))?;
+  println!("Merged {} pact file(s) with {} interaction(s) into '{}'", pact_count, merged.len(), output);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_matching::models::{Request, RequestResponseInteraction, RequestResponsePact, Response};
+  use pact_matching::models::message::Message;
+  use pact_matching::models::message_pact::MessagePact;
+  use pact_models::{Consumer, OptionalBody, Provider};
+
+  use super::*;
+
+  fn interaction(description: &str, status: u16) -> RequestResponseInteraction {
+    RequestResponseInteraction {
+      description: description.to_string(),
+      request: Request::default(),
+      response: Response { status, .. Response::default() },
+      .. RequestResponseInteraction::default()
+    }
+  }
+
+  fn http_pact(interactions: Vec<RequestResponseInteraction>) -> PactFile {
+    PactFile::RequestResponse(RequestResponsePact {
+      consumer: Consumer { name: "consumer".to_string() },
+      provider: Provider { name: "provider".to_string() },
+      interactions,
+      .. RequestResponsePact::default()
+    })
+  }
+
+  fn descriptions(pact: &PactFile) -> Vec<(String, u16)> {
+    match pact {
+      PactFile::RequestResponse(pact) => pact.interactions.iter()
+        .map(|i| (i.description.clone(), i.response.status))
+        .collect(),
+      _ => panic!("Expected a request/response pact, got {:?}", pact)
+    }
+  }
+
+  #[test]
+  fn merges_the_interactions_from_all_the_pacts() {
+    let result = merge_pacts(vec![
+      http_pact(vec![interaction("c", 200), interaction("a", 200)]),
+      http_pact(vec![interaction("b", 200)]),
+      http_pact(vec![interaction("a", 200), interaction("d", 200)])
+    ], ConflictBehaviour::Fail).unwrap();
+    expect!(descriptions(&result)).to(be_equal_to(vec![
+      ("a".to_string(), 200), ("b".to_string(), 200), ("c".to_string(), 200), ("d".to_string(), 200)
+    ]));
+  }
+
+  #[test]
+  fn fails_on_conflicting_interactions_by_default() {
+    let result = merge_pacts(vec![
+      http_pact(vec![interaction("a", 200)]),
+      http_pact(vec![interaction("a", 404)])
+    ], ConflictBehaviour::Fail);
+    expect!(result.as_ref()).to(be_err());
+    expect!(result.unwrap_err().to_string()).to(be_equal_to(
+      "Unable to merge pacts, as there were 1 conflict(s) between the interactions:\n  'a': Response status 200 != 404".to_string()));
+  }
+
+  #[test]
+  fn keeps_the_first_interaction_when_there_is_a_conflict() {
+    let result = merge_pacts(vec![
+      http_pact(vec![interaction("a", 200)]),
+      http_pact(vec![interaction("a", 404), interaction("b", 500)])
+    ], ConflictBehaviour::KeepFirst).unwrap();
+    expect!(descriptions(&result)).to(be_equal_to(vec![("a".to_string(), 200), ("b".to_string(), 500)]));
+  }
+
+  #[test]
+  fn detects_conflicting_messages() {
+    let message = |contents: &str| Message {
+      description: "a message".to_string(),
+      contents: OptionalBody::Present(contents.as_bytes().to_vec().into(), None),
+      .. Message::default()
+    };
+    let pact = |contents: &str| PactFile::Message(MessagePact {
+      consumer: Consumer { name: "consumer".to_string() },
+      provider: Provider { name: "provider".to_string() },
+      messages: vec![message(contents)],
+      .. MessagePact::default()
+    });
+
+    expect!(merge_pacts(vec![pact("1"), pact("2")], ConflictBehaviour::Fail)).to(be_err());
+    let result = merge_pacts(vec![pact("1"), pact("2")], ConflictBehaviour::KeepFirst).unwrap();
+    match result {
+      PactFile::Message(pact) => {
+        expect!(pact.messages).to(be_equal_to(vec![message("1")]));
+      },
+      _ => panic!("Expected a message pact")
+    }
+  }
+
+  #[test]
+  fn fails_if_the_consumer_or_provider_is_different() {
+    let mut other = RequestResponsePact {
+      consumer: Consumer { name: "other consumer".to_string() },
+      provider: Provider { name: "provider".to_string() },
+      .. RequestResponsePact::default()
+    };
+    other.interactions.push(interaction("b", 200));
+    expect!(merge_pacts(vec![http_pact(vec![interaction("a", 200)]), PactFile::RequestResponse(other)],
+      ConflictBehaviour::KeepFirst)).to(be_err());
+  }
+}
@@ -0,0 +1,417 @@
+//! Generates a skeleton OpenAPI 3 document from the HTTP interactions in pact files for a provider.
+//! The paths, operations and response codes come from the interactions, and the schemas of the
+//! JSON bodies are inferred from the example bodies and their matching rules.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use lazy_static::lazy_static;
+use log::*;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use pact_matching::models::Pact;
+use pact_matching::models::evolve::VolatileValue;
+use pact_matching::models::matchingrules::{MatchingRule, MatchingRules};
+use pact_matching::models::v4::SynchronousHttp;
+use pact_models::OptionalBody;
+use pact_models::content_types::ContentType;
+
+use crate::pact_file::PactFile;
+
+lazy_static! {
+  static ref UUID_SEGMENT: Regex = Regex::new("^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$").unwrap();
+  static ref NUMERIC_SEGMENT: Regex = Regex::new(r"^\d+$").unwrap();
+}
+
+/// Headers that are described by other parts of an OpenAPI document, and are not exported as
+/// header parameters
+const RESERVED_HEADERS: [&str; 3] = ["accept", "content-type", "authorization"];
+
+/// Converts the path of a request into a path template. Numeric and UUID segments are assumed to
+/// be identifiers, and are replaced with a parameter named after the previous segment (i.e.
+/// `/users/100` becomes `/users/{userId}`). Returns the template and the parameters with their
+/// example values.
+pub(crate) fn path_template(path: &str) -> (String, Vec<(String, Value)>) {
+  let mut parameters: Vec<(String, Value)> = vec![];
+  let mut previous = "";
+  let segments = path.split('/').map(|segment| {
+    let value = if NUMERIC_SEGMENT.is_match(segment) {
+      segment.parse::<u64>().ok().map(|n| json!(n))
+    } else if UUID_SEGMENT.is_match(segment) {
+      Some(json!(segment))
+    } else {
+      None
+    };
+    let result = match value {
+      Some(value) => {
+        let base = previous.strip_suffix('s').unwrap_or(previous);
+        let mut name = if base.is_empty() { "id".to_string() } else { format!("{}Id", base) };
+        if parameters.iter().any(|(n, _)| *n == name) {
+          name = format!("{}{}", name, parameters.len() + 1);
+        }
+        parameters.push((name.clone(), value));
+        format!("{{{}}}", name)
+      },
+      None => segment.to_string()
+    };
+    previous = segment;
+    result
+  }).collect::<Vec<_>>();
+  (segments.join("/"), parameters)
+}
+
+/// Infers the JSON schema of the value. The matching rules of the body are used to refine the
+/// schema (i.e. regex matchers become patterns, and array length matchers set the number of items).
+pub(crate) fn infer_schema(value: &Value, path: &mut Vec<String>, matching_rules: &MatchingRules) -> Value {
+  let mut schema = match value {
+    Value::Object(map) => {
+      let properties = map.iter().map(|(key, value)| {
+        path.push(key.clone());
+        let schema = infer_schema(value, path, matching_rules);
+        path.pop();
+        (key.clone(), schema)
+      }).collect::<Map<String, Value>>();
+      json!({ "type": "object", "properties": properties })
+    },
+    Value::Array(items) => match items.first() {
+      Some(item) => {
+        path.push("0".to_string());
+        let schema = infer_schema(item, path, matching_rules);
+        path.pop();
+        json!({ "type": "array", "items": schema })
+      },
+      None => json!({ "type": "array", "items": {} })
+    },
+    Value::String(_) => match VolatileValue::detect(None, value) {
+      Some(VolatileValue::Uuid) => json!({ "type": "string", "format": "uuid" }),
+      Some(VolatileValue::Timestamp(_)) => json!({ "type": "string", "format": "date-time" }),
+      Some(VolatileValue::Date(_)) => json!({ "type": "string", "format": "date" }),
+      _ => json!({ "type": "string" })
+    },
+    Value::Number(n) => if n.is_f64() { json!({ "type": "number" }) } else { json!({ "type": "integer" }) },
+    Value::Bool(_) => json!({ "type": "boolean" }),
+    Value::Null => json!({ "nullable": true })
+  };
+
+  let segments = path.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+  let rules = matching_rules.rules_for_category("body")
+    .and_then(|category| category.select_best_matcher(&segments));
+  if let (Some(rules), Value::Object(schema)) = (rules, &mut schema) {
+    for rule in &rules.rules {
+      match (rule, value) {
+        (MatchingRule::Regex(regex), Value::String(_)) => {
+          schema.insert("pattern".to_string(), json!(regex));
+        },
+        (MatchingRule::Date(_), Value::String(_)) => {
+          schema.insert("format".to_string(), json!("date"));
+        },
+        (MatchingRule::Timestamp(_), Value::String(_)) => {
+          schema.insert("format".to_string(), json!("date-time"));
+        },
+        (MatchingRule::Integer, _) => {
+          schema.insert("type".to_string(), json!("integer"));
+        },
+        (MatchingRule::Number, _) | (MatchingRule::Decimal, _) => {
+          schema.insert("type".to_string(), json!("number"));
+        },
+        (MatchingRule::MinType(min), Value::Array(_)) => {
+          schema.insert("minItems".to_string(), json!(min));
+        },
+        (MatchingRule::MaxType(max), Value::Array(_)) => {
+          schema.insert("maxItems".to_string(), json!(max));
+        },
+        (MatchingRule::MinMaxType(min, max), Value::Array(_)) => {
+          schema.insert("minItems".to_string(), json!(min));
+          schema.insert("maxItems".to_string(), json!(max));
+        },
+        _ => ()
+      }
+    }
+  }
+  schema
+}
+
+/// Media type object for the body of a request or response, with the schema inferred from JSON
+/// bodies
+fn content(body: &OptionalBody, content_type: Option<ContentType>, matching_rules: &MatchingRules) -> Option<Value> {
+  if !body.is_present() {
+    return None;
+  }
+  let content_type = content_type.map(|ct| ct.base_type().to_string())
+    .unwrap_or_else(|| "application/octet-stream".to_string());
+  let media_type = match serde_json::from_slice::<Value>(&body.value().unwrap_or_default()) {
+    Ok(json) if content_type.contains("json") => json!({
+      "schema": infer_schema(&json, &mut vec!["$".to_string()], matching_rules),
+      "example": json
+    }),
+    _ => json!({ "schema": { "type": "string" } })
+  };
+  Some(json!({ content_type: media_type }))
+}
+
+fn string_schema(value: &str) -> Value {
+  if NUMERIC_SEGMENT.is_match(value) { json!({ "type": "integer" }) } else { json!({ "type": "string" }) }
+}
+
+/// Operation being built from the interactions with the same path and method
+#[derive(Debug, Default)]
+struct Operation {
+  summary: String,
+  parameters: BTreeMap<(String, String), Value>,
+  request_body: Option<Value>,
+  responses: BTreeMap<String, Value>
+}
+
+impl Operation {
+  fn add_parameter(&mut self, location: &str, name: &str, schema: Value, example: Value, required: bool) {
+    self.parameters.entry((location.to_string(), name.to_string())).or_insert_with(|| json!({
+      "name": name,
+      "in": location,
+      "required": required,
+      "schema": schema,
+      "example": example
+    }));
+  }
+
+  fn add_interaction(&mut self, interaction: &SynchronousHttp, path_parameters: &[(String, Value)]) {
+    if self.summary.is_empty() {
+      self.summary = interaction.description.clone();
+    }
+
+    let request = &interaction.request;
+    for (name, example) in path_parameters {
+      let schema = if example.is_number() { json!({ "type": "integer" }) } else { json!({ "type": "string", "format": "uuid" }) };
+      self.add_parameter("path", name, schema, example.clone(), true);
+    }
+    if let Some(query) = &request.query {
+      for (name, values) in query {
+        let example = values.first().cloned().unwrap_or_default();
+        self.add_parameter("query", name, string_schema(&example), json!(example), false);
+      }
+    }
+    if let Some(headers) = &request.headers {
+      for (name, values) in headers.iter().filter(|(name, _)| !RESERVED_HEADERS.contains(&name.to_lowercase().as_str())) {
+        let example = values.join(", ");
+        self.add_parameter("header", name, json!({ "type": "string" }), json!(example), false);
+      }
+    }
+    if self.request_body.is_none() {
+      self.request_body = content(&request.body, request.content_type(), &request.matching_rules).map(|content| json!({ "content": content }));
+    }
+
+    let response = &interaction.response;
+    self.responses.entry(response.status.to_string()).or_insert_with(|| {
+      let mut definition = json!({ "description": interaction.description });
+      let map = definition.as_object_mut().unwrap();
+      if let Some(headers) = &response.headers {
+        let headers = headers.iter()
+          .filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"))
+          .map(|(name, values)| (name.clone(), json!({
+            "schema": { "type": "string" },
+            "example": values.join(", ")
+          })))
+          .collect::<Map<String, Value>>();
+        if !headers.is_empty() {
+          map.insert("headers".to_string(), Value::Object(headers));
+        }
+      }
+      if let Some(content) = content(&response.body, response.content_type(), &response.matching_rules) {
+        map.insert("content".to_string(), content);
+      }
+      definition
+    });
+  }
+
+  fn to_json(&self) -> Value {
+    let mut json = json!({
+      "summary": self.summary,
+      "responses": self.responses
+    });
+    let map = json.as_object_mut().unwrap();
+    if !self.parameters.is_empty() {
+      map.insert("parameters".to_string(), Value::Array(self.parameters.values().cloned().collect()));
+    }
+    if let Some(body) = &self.request_body {
+      map.insert("requestBody".to_string(), body.clone());
+    }
+    json
+  }
+}
+
+/// Generates an OpenAPI 3 document from the HTTP interactions of the pacts. All the pacts must be
+/// for the same provider. Message interactions are ignored.
+pub(crate) fn export_document(pacts: &[Box<dyn Pact>], title: Option<&str>, version: &str) -> anyhow::Result<Value> {
+  let provider = pacts.first().map(|pact| pact.provider().name)
+    .ok_or_else(|| anyhow!("At least one pact is required"))?;
+  if let Some(pact) = pacts.iter().find(|pact| pact.provider().name != provider) {
+    return Err(anyhow!("All the pacts must be for the same provider, but found '{}' and '{}'",
+      provider, pact.provider().name));
+  }
+
+  let mut operations: BTreeMap<String, BTreeMap<String, Operation>> = BTreeMap::new();
+  for pact in pacts {
+    for interaction in pact.interactions() {
+      match interaction.as_v4_http() {
+        Some(http) => {
+          let (template, parameters) = path_template(&http.request.path);
+          operations.entry(template).or_default()
+            .entry(http.request.method.to_lowercase()).or_default()
+            .add_interaction(&http, &parameters);
+        },
+        None => debug!("Ignoring interaction '{}' as it is not an HTTP interaction", interaction.description())
+      }
+    }
+  }
+
+  let paths = operations.iter().map(|(path, operations)| {
+    (path.clone(), operations.iter()
+      .map(|(method, operation)| (method.clone(), operation.to_json()))
+      .collect::<Map<String, Value>>().into())
+  }).collect::<Map<String, Value>>();
+  Ok(json!({
+    "openapi": "3.0.3",
+    "info": {
+      "title": title.map(|t| t.to_string()).unwrap_or(provider),
+      "version": version
+    },
+    "paths": paths
+  }))
+}
+
+pub(crate) fn handle_export_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let pacts = args.values_of("files").unwrap()
+    .map(|file| PactFile::load(Path::new(file))
+      .map(|pact| pact.boxed())
+      .map_err(|err| anyhow!("Failed to load pact file '{}' - {}", file, err)))
+    .collect::<anyhow::Result<Vec<_>>>()?;
+  let document = export_document(&pacts, args.value_of("title"), args.value_of("api-version").unwrap_or("1.0.0"))?;
+
+  let output = args.value_of("output").unwrap();
+  let contents = if output.ends_with(".yaml") || output.ends_with(".yml") {
+    serde_yaml::to_string(&document)?
+  } else {
+    serde_json::to_string_pretty(&document)?
+  };
+  fs::write(output, contents).map_err(|err| anyhow!("Failed to write OpenAPI document '{}' - {}", output, err))?;
+  println!("Exported {} path(s) from {} pact file(s) into '{}'", document["paths"].as_object().map(|p| p.len()).unwrap_or_default(),
+    pacts.len(), output);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_matching::matchingrules;
+  use pact_test_support::builders::{InteractionBuilder, MessageBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+
+  use crate::openapi::parse_document;
+
+  use super::*;
+
+  #[test]
+  fn path_template_replaces_identifier_segments() {
+    expect!(path_template("/users")).to(be_equal_to(("/users".to_string(), vec![])));
+    expect!(path_template("/users/100/orders/e2490de5-5bd3-43d5-b7c4-526e33f71304")).to(be_equal_to((
+      "/users/{userId}/orders/{orderId}".to_string(),
+      vec![("userId".to_string(), json!(100)), ("orderId".to_string(), json!("e2490de5-5bd3-43d5-b7c4-526e33f71304"))]
+    )));
+    expect!(path_template("/100/100")).to(be_equal_to((
+      "/{id}/{100Id}".to_string(),
+      vec![("id".to_string(), json!(100)), ("100Id".to_string(), json!(100))]
+    )));
+  }
+
+  #[test]
+  fn infer_schema_uses_the_matching_rules() {
+    let body = json!({
+      "id": 1,
+      "price": 10,
+      "code": "ABC",
+      "created": "2021-05-04T10:11:12",
+      "items": [ { "name": "a" } ],
+      "note": null
+    });
+    let matching_rules = matchingrules! {
+      "body" => {
+        "$.price" => [ MatchingRule::Number ],
+        "$.code" => [ MatchingRule::Regex("^[A-Z]+$".to_string()) ],
+        "$.items" => [ MatchingRule::MinType(1) ]
+      }
+    };
+    let schema = infer_schema(&body, &mut vec!["$".to_string()], &matching_rules);
+    expect!(schema).to(be_equal_to(json!({
+      "type": "object",
+      "properties": {
+        "id": { "type": "integer" },
+        "price": { "type": "number" },
+        "code": { "type": "string", "pattern": "^[A-Z]+$" },
+        "created": { "type": "string", "format": "date-time" },
+        "items": {
+          "type": "array",
+          "minItems": 1,
+          "items": { "type": "object", "properties": { "name": { "type": "string" } } }
+        },
+        "note": { "nullable": true }
+      }
+    })));
+  }
+
+  fn pact(provider: &str) -> Box<dyn Pact> {
+    Box::new(PactBuilder::new("web", provider)
+      .interaction(InteractionBuilder::new("get user 100")
+        .request(RequestBuilder::get("/users/100").query_param("fields", "name").build())
+        .response(ResponseBuilder::new().status(200).json_body(json!({ "name": "Fred" })).build())
+        .build())
+      .interaction(InteractionBuilder::new("get a user that does not exist")
+        .request(RequestBuilder::get("/users/200").build())
+        .response(ResponseBuilder::new().status(404).build())
+        .build())
+      .interaction(InteractionBuilder::new("create a user")
+        .request(RequestBuilder::post("/users").json_body(json!({ "name": "Fred" })).build())
+        .response(ResponseBuilder::new().status(201).build())
+        .build())
+      .build())
+  }
+
+  #[test]
+  fn export_document_combines_the_interactions() {
+    let message_pact: Box<dyn Pact> = Box::new(PactBuilder::new("web", "users")
+      .message(MessageBuilder::new("an event").build())
+      .build_message_pact());
+
+    let document = export_document(&[pact("users"), message_pact], None, "1.0.0").unwrap();
+    expect!(document["info"]["title"].clone()).to(be_equal_to(json!("users")));
+    let paths = document["paths"].as_object().unwrap();
+    expect!(paths.keys().cloned().collect::<Vec<_>>()).to(be_equal_to(vec!["/users".to_string(), "/users/{userId}".to_string()]));
+
+    let get = &paths["/users/{userId}"]["get"];
+    expect!(get["summary"].clone()).to(be_equal_to(json!("get user 100")));
+    expect!(get["responses"].as_object().unwrap().keys().cloned().collect::<Vec<_>>())
+      .to(be_equal_to(vec!["200".to_string(), "404".to_string()]));
+    expect!(get["responses"]["200"]["content"]["application/json"]["schema"].clone()).to(be_equal_to(json!({
+      "type": "object", "properties": { "name": { "type": "string" } }
+    })));
+    let parameters = get["parameters"].as_array().unwrap();
+    expect!(parameters.iter().map(|p| (p["in"].clone(), p["name"].clone())).collect::<Vec<_>>()).to(be_equal_to(vec![
+      (json!("path"), json!("userId")), (json!("query"), json!("fields"))
+    ]));
+
+    let post = &paths["/users"]["post"];
+    expect!(post["requestBody"]["content"]["application/json"]["example"].clone()).to(be_equal_to(json!({ "name": "Fred" })));
+    expect!(post["responses"]["201"].clone()).to(be_equal_to(json!({ "description": "create a user" })));
+
+    // The document can be loaded again
+    expect!(parse_document(&serde_json::to_string(&document).unwrap())).to(be_ok());
+  }
+
+  #[test]
+  fn export_document_requires_the_same_provider() {
+    expect!(export_document(&[pact("users"), pact("orders")], None, "1.0.0")).to(be_err());
+    expect!(export_document(&[], None, "1.0.0")).to(be_err());
+  }
+}
@@ -0,0 +1,579 @@
+//! Generates a skeleton V4 pact from an OpenAPI 3 document, with an interaction for each operation.
+//! The requests and responses are built from the examples in the document (or example values
+//! generated from the schemas), and the bodies, headers and query parameters have type-based
+//! matching rules, so the pact is a starting point for the contract with the provider.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use itertools::Itertools;
+use log::*;
+use serde_json::{json, Map, Value};
+
+use pact_matching::models::{MergeStrategy, write_pact};
+use pact_matching::models::matchingrules::{MatchingRule, MatchingRuleCategory, MatchingRules, RuleLogic};
+use pact_matching::models::v4::{SynchronousHttp, V4Interaction, V4Pact};
+use pact_matching::models::v4::http_parts::{HttpRequest, HttpResponse};
+use pact_models::{Consumer, OptionalBody, PactSpecification, Provider};
+use pact_models::content_types::ContentType;
+
+use crate::openapi::load_document;
+
+/// HTTP methods of the operations of a path item
+const METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// Maximum number of references to follow to resolve a value
+const MAX_REFERENCES: usize = 16;
+
+/// OpenAPI document, used to resolve local references (i.e. `#/components/schemas/User`)
+struct Document<'a> {
+  root: &'a Value
+}
+
+impl <'a> Document<'a> {
+  /// Follows the reference if the value is a reference object. Only local references are
+  /// supported, other references resolve to `null`.
+  fn resolve(&self, value: &'a Value) -> &'a Value {
+    self.resolve_schema(value, &mut vec![])
+  }
+
+  /// Follows the reference if the value is a reference object, adding the references that were
+  /// followed
+  fn resolve_schema(&self, value: &'a Value, refs: &mut Vec<&'a str>) -> &'a Value {
+    let mut value = value;
+    for _ in 0..MAX_REFERENCES {
+      match value.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) => match reference.strip_prefix('#') {
+          Some(pointer) => {
+            refs.push(reference);
+            value = self.root.pointer(pointer).unwrap_or(&Value::Null);
+          },
+          None => {
+            warn!("Ignoring external reference '{}'", reference);
+            return &Value::Null;
+          }
+        },
+        None => return value
+      }
+    }
+    &Value::Null
+  }
+
+  /// Resolves the schema, returning `None` if it is a recursive reference to a schema that is
+  /// already being expanded
+  fn expand_schema(&self, schema: &'a Value, refs: &[&'a str]) -> Option<(&'a Value, Vec<&'a str>)> {
+    let mut followed = vec![];
+    let schema = self.resolve_schema(schema, &mut followed);
+    if followed.iter().any(|r| refs.contains(r)) {
+      None
+    } else {
+      Some((schema, refs.iter().chain(followed.iter()).cloned().collect()))
+    }
+  }
+
+  /// Generates an example value from the schema. Examples given in the schema are used, otherwise
+  /// a value of the right type is generated. Recursive references generate `null`.
+  fn example(&self, schema: &'a Value, refs: &[&'a str]) -> Value {
+    let (schema, refs) = match self.expand_schema(schema, refs) {
+      Some(result) => result,
+      None => return Value::Null
+    };
+    if let Some(example) = schema.get("example") {
+      return example.clone();
+    }
+    if let Some(example) = schema.get("examples").and_then(|examples| examples.as_array()).and_then(|e| e.first()) {
+      return example.clone();
+    }
+    if let Some(value) = schema.get("default") {
+      return value.clone();
+    }
+    if let Some(value) = schema.get("enum").and_then(|values| values.as_array()).and_then(|v| v.first()) {
+      return value.clone();
+    }
+
+    if let Some(schemas) = schema.get("allOf").and_then(|s| s.as_array()) {
+      let mut example = Map::new();
+      for schema in schemas {
+        if let Value::Object(map) = self.example(schema, &refs) {
+          example.extend(map);
+        }
+      }
+      return Value::Object(example);
+    }
+    if let Some(schema) = first_alternative(schema) {
+      return self.example(schema, &refs);
+    }
+
+    match schema_type(schema) {
+      Some("object") => Value::Object(schema.get("properties")
+        .and_then(|p| p.as_object())
+        .map(|properties| properties.iter()
+          .map(|(name, schema)| (name.clone(), self.example(schema, &refs)))
+          .collect())
+        .unwrap_or_default()),
+      Some("array") => match schema.get("items") {
+        Some(items) => json!([ self.example(items, &refs) ]),
+        None => json!([])
+      },
+      Some("integer") => schema.get("minimum").cloned().unwrap_or_else(|| json!(1)),
+      Some("number") => schema.get("minimum").cloned().unwrap_or_else(|| json!(1.5)),
+      Some("boolean") => json!(true),
+      Some("string") => json!(match schema.get("format").and_then(|f| f.as_str()) {
+        Some("date") => "2021-01-01",
+        Some("date-time") => "2021-01-01T10:00:00Z",
+        Some("uuid") => "e2490de5-5bd3-43d5-b7c4-526e33f71304",
+        Some("email") => "user@example.com",
+        Some("uri") | Some("url") => "http://example.com",
+        _ => "string"
+      }),
+      _ => Value::Null
+    }
+  }
+
+  /// Adds type-based matching rules for the values of the schema to the body category. Recursive
+  /// references do not get matching rules.
+  fn add_body_rules(&self, schema: &'a Value, path: &str, rules: &mut MatchingRuleCategory, refs: &[&'a str]) {
+    let (schema, refs) = match self.expand_schema(schema, refs) {
+      Some(result) => result,
+      None => return
+    };
+
+    if let Some(schemas) = schema.get("allOf").and_then(|s| s.as_array()) {
+      for schema in schemas {
+        self.add_body_rules(schema, path, rules, &refs);
+      }
+      return;
+    }
+    if let Some(schema) = first_alternative(schema) {
+      self.add_body_rules(schema, path, rules, &refs);
+      return;
+    }
+
+    match schema_type(schema) {
+      Some("object") => match schema.get("properties").and_then(|p| p.as_object()) {
+        Some(properties) => for (name, schema) in properties {
+          self.add_body_rules(schema, &child_path(path, name), rules, &refs);
+        },
+        None => rules.add_rule(path, MatchingRule::Type, &RuleLogic::And)
+      },
+      Some("array") => {
+        rules.add_rule(path, MatchingRule::MinType(1), &RuleLogic::And);
+        if let Some(items) = schema.get("items") {
+          self.add_body_rules(items, &format!("{}[*]", path), rules, &refs);
+        }
+      },
+      Some("integer") => rules.add_rule(path, MatchingRule::Integer, &RuleLogic::And),
+      Some("number") => rules.add_rule(path, MatchingRule::Number, &RuleLogic::And),
+      Some(_) => rules.add_rule(path, MatchingRule::Type, &RuleLogic::And),
+      None => ()
+    }
+  }
+
+  /// Example value of a parameter or header, as a string
+  fn parameter_example(&self, parameter: &'a Value) -> String {
+    let example = parameter.get("example").cloned()
+      .or_else(|| parameter.get("examples")
+        .and_then(|examples| examples.as_object())
+        .and_then(|examples| examples.values().next())
+        .and_then(|example| self.resolve(example).get("value").cloned()))
+      .unwrap_or_else(|| parameter.get("schema").map(|schema| self.example(schema, &[])).unwrap_or_default());
+    match example {
+      Value::String(s) => s,
+      Value::Null => "string".to_string(),
+      value => value.to_string()
+    }
+  }
+
+  /// Builds the body from the content of a request body or response. JSON content is preferred
+  /// if there is more than one media type.
+  fn body(&self, content: Option<&'a Value>, matching_rules: &mut MatchingRules) -> Option<(String, OptionalBody)> {
+    let content = content.and_then(|c| c.as_object())?;
+    let (content_type, media_type) = content.iter()
+      .find(|(content_type, _)| ContentType::parse(content_type.as_str()).map(|ct| ct.is_json()).unwrap_or(false))
+      .or_else(|| content.iter().next())?;
+    let ct = ContentType::parse(content_type.as_str()).ok();
+    let is_json = ct.as_ref().map(|ct| ct.is_json()).unwrap_or(false);
+
+    let schema = media_type.get("schema");
+    let example = media_type.get("example").cloned()
+      .or_else(|| media_type.get("examples")
+        .and_then(|examples| examples.as_object())
+        .and_then(|examples| examples.values().next())
+        .and_then(|example| self.resolve(example).get("value").cloned()))
+      .or_else(|| schema.map(|schema| self.example(schema, &[])));
+    let body = match example {
+      Some(Value::Null) | None => OptionalBody::Missing,
+      Some(Value::String(s)) if !is_json => OptionalBody::Present(s.into(), ct),
+      Some(example) if is_json => OptionalBody::Present(example.to_string().into(), ct),
+      Some(_) => OptionalBody::Missing
+    };
+    if is_json && body.is_present() {
+      if let Some(schema) = schema {
+        self.add_body_rules(schema, "$", matching_rules.add_category("body"), &[]);
+      }
+    }
+    Some((content_type.clone(), body))
+  }
+
+  /// Parameters of the operation, including the ones defined on the path item. Parameters on the
+  /// operation override the ones on the path item with the same name and location.
+  fn parameters(&self, path_item: &'a Value, operation: &'a Value) -> Vec<&'a Value> {
+    let parameters = |value: &'a Value| value.get("parameters")
+      .and_then(|p| p.as_array())
+      .map(|p| p.iter().map(|parameter| self.resolve(parameter)).collect::<Vec<_>>())
+      .unwrap_or_default();
+    let operation_parameters = parameters(operation);
+    let key = |parameter: &Value| (parameter.get("name").cloned(), parameter.get("in").cloned());
+    parameters(path_item).into_iter()
+      .filter(|parameter| !operation_parameters.iter().any(|p| key(p) == key(parameter)))
+      .chain(operation_parameters.iter().cloned())
+      .collect()
+  }
+
+  /// Builds an interaction for the operation
+  fn interaction(&self, path: &str, method: &str, path_item: &'a Value, operation: &'a Value) -> anyhow::Result<SynchronousHttp> {
+    let mut request = HttpRequest {
+      method: method.to_uppercase(),
+      .. HttpRequest::default()
+    };
+    let mut path_values = HashMap::new();
+    let mut query = HashMap::new();
+    let mut headers = HashMap::new();
+    for parameter in self.parameters(path_item, operation) {
+      let name = parameter.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+      let required = parameter.get("required").and_then(|r| r.as_bool()).unwrap_or(false);
+      match parameter.get("in").and_then(|i| i.as_str()) {
+        Some("path") => {
+          path_values.insert(name.to_string(), self.parameter_example(parameter));
+        },
+        Some("query") if required => {
+          query.insert(name.to_string(), vec![self.parameter_example(parameter)]);
+          request.matching_rules.add_category("query").add_rule(name, MatchingRule::Type, &RuleLogic::And);
+        },
+        Some("header") if required && !is_reserved_header(name) => {
+          headers.insert(name.to_string(), vec![self.parameter_example(parameter)]);
+          request.matching_rules.add_category("header").add_rule(name, MatchingRule::Type, &RuleLogic::And);
+        },
+        _ => ()
+      }
+    }
+
+    let (example_path, path_regex) = expand_path(path, &path_values)?;
+    request.path = example_path;
+    if let Some(regex) = path_regex {
+      request.matching_rules.add_category("path").add_rule("", MatchingRule::Regex(regex), &RuleLogic::And);
+    }
+
+    let request_body = operation.get("requestBody").map(|body| self.resolve(body));
+    if let Some((content_type, body)) = self.body(request_body.and_then(|b| b.get("content")), &mut request.matching_rules) {
+      headers.insert("Content-Type".to_string(), vec![content_type]);
+      request.body = body;
+    }
+    if !query.is_empty() {
+      request.query = Some(query);
+    }
+    if !headers.is_empty() {
+      request.headers = Some(headers);
+    }
+
+    Ok(SynchronousHttp {
+      description: match operation.get("summary").and_then(|s| s.as_str()) {
+        Some(summary) => format!("{} {} - {}", method.to_uppercase(), path, summary),
+        None => format!("{} {}", method.to_uppercase(), path)
+      },
+      request,
+      response: self.response(operation),
+      .. SynchronousHttp::default()
+    }.with_key())
+  }
+
+  /// Builds the response from the first successful response of the operation, or the default
+  /// response if there are no successful responses
+  fn response(&self, operation: &'a Value) -> HttpResponse {
+    let responses = operation.get("responses").and_then(|r| r.as_object()).cloned().unwrap_or_default();
+    let status_codes = responses.keys()
+      .filter_map(|code| code.parse::<u16>().ok().map(|status| (status, code)))
+      .sorted()
+      .collect::<Vec<_>>();
+    let selected = status_codes.iter().find(|(status, _)| (200..300).contains(status))
+      .map(|(status, code)| (*status, responses.get(*code)))
+      .or_else(|| responses.get("default").map(|response| (200, Some(response))))
+      .or_else(|| status_codes.first().map(|(status, code)| (*status, responses.get(*code))));
+
+    let mut response = HttpResponse::default();
+    if let Some((status, Some(definition))) = selected {
+      let definition = self.resolve(definition);
+      response.status = status;
+      let mut headers = HashMap::new();
+      if let Some(definition_headers) = definition.get("headers").and_then(|h| h.as_object()) {
+        for (name, header) in definition_headers {
+          if !is_reserved_header(name) {
+            headers.insert(name.clone(), vec![self.parameter_example(self.resolve(header))]);
+            response.matching_rules.add_category("header").add_rule(name.as_str(), MatchingRule::Type, &RuleLogic::And);
+          }
+        }
+      }
+      if let Some((content_type, body)) = self.body(definition.get("content"), &mut response.matching_rules) {
+        headers.insert("Content-Type".to_string(), vec![content_type]);
+        response.body = body;
+      }
+      if !headers.is_empty() {
+        response.headers = Some(headers);
+      }
+    }
+    response
+  }
+}
+
+fn schema_type(schema: &Value) -> Option<&str> {
+  match schema.get("type") {
+    Some(Value::String(t)) => Some(t.as_str()),
+    // OpenAPI 3.1 allows a list of types, i.e. ["string", "null"]
+    Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).find(|t| *t != "null"),
+    _ => if schema.get("properties").is_some() {
+      Some("object")
+    } else if schema.get("items").is_some() {
+      Some("array")
+    } else {
+      None
+    }
+  }
+}
+
+fn first_alternative(schema: &Value) -> Option<&Value> {
+  schema.get("oneOf").or_else(|| schema.get("anyOf"))
+    .and_then(|s| s.as_array())
+    .and_then(|s| s.first())
+}
+
+/// Headers that OpenAPI documents describe elsewhere, and header parameters with these names are
+/// ignored
+fn is_reserved_header(name: &str) -> bool {
+  ["accept", "content-type", "authorization"].contains(&name.to_lowercase().as_str())
+}
+
+fn child_path(path: &str, name: &str) -> String {
+  if !name.is_empty() && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-') {
+    format!("{}.{}", path, name)
+  } else {
+    format!("{}['{}']", path, name.replace('\'', "\\'"))
+  }
+}
+
+/// Expands the path parameters of the path with the example values, and returns the example path
+/// with a regex matcher for the path if it has parameters
+fn expand_path(path: &str, values: &HashMap<String, String>) -> anyhow::Result<(String, Option<String>)> {
+  if !path.contains('{') {
+    return Ok((path.to_string(), None));
+  }
+
+  let mut example = String::new();
+  let mut regex = "^".to_string();
+  for (index, segment) in path.split('{').enumerate() {
+    if index == 0 {
+      example.push_str(segment);
+      regex.push_str(&regex::escape(segment));
+    } else {
+      let (name, rest) = segment.split_once('}')
+        .ok_or_else(|| anyhow!("Path '{}' has an unclosed parameter", path))?;
+      example.push_str(values.get(name).map(|value| value.as_str()).unwrap_or("1"));
+      example.push_str(rest);
+      regex.push_str("[^/]+");
+      regex.push_str(&regex::escape(rest));
+    }
+  }
+  regex.push('$');
+  Ok((example, Some(regex)))
+}
+
+/// Generates a V4 pact with an interaction for each operation in the OpenAPI document. The provider
+/// name defaults to the title of the document.
+pub(crate) fn import_pact(document: &Value, consumer: &str, provider: Option<&str>) -> anyhow::Result<V4Pact> {
+  let doc = Document { root: document };
+  let provider = provider.map(|p| p.to_string())
+    .or_else(|| document.pointer("/info/title").and_then(|t| t.as_str()).map(|t| t.to_string()))
+    .ok_or_else(|| anyhow!("The provider name is required, as the OpenAPI document does not have a title"))?;
+
+  let mut interactions: Vec<Box<dyn V4Interaction>> = vec![];
+  if let Some(paths) = document.get("paths").and_then(|p| p.as_object()) {
+    for (path, path_item) in paths {
+      let path_item = doc.resolve(path_item);
+      for method in &METHODS {
+        if let Some(operation) = path_item.get(*method) {
+          debug!("Generating interaction for {} {}", method.to_uppercase(), path);
+          interactions.push(doc.interaction(path, method, path_item, operation)?.boxed_v4());
+        }
+      }
+    }
+  }
+
+  Ok(V4Pact {
+    consumer: Consumer { name: consumer.to_string() },
+    provider: Provider { name: provider },
+    interactions,
+    .. V4Pact::default()
+  })
+}
+
+pub(crate) fn handle_import_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let file = args.value_of("file").unwrap();
+  let document = load_document(Path::new(file))?;
+  let pact = import_pact(&document, args.value_of("consumer").unwrap(), args.value_of("provider"))?;
+  let interactions = pact.interactions.len();
+  let output = args.value_of("output").unwrap();
+  write_pact(Box::new(pact), Path::new(output), PactSpecification::V4, true, MergeStrategy::default())?;
+  println!("Imported {} interaction(s) from '{}' into '{}'", interactions, file, output);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+
+  use pact_matching::matchingrules;
+
+  use crate::openapi::parse_document;
+
+  use super::*;
+
+  const DOCUMENT: &str = r##"
+openapi: 3.0.1
+info:
+  title: User Service
+  version: 1.0.0
+paths:
+  /users/{id}:
+    parameters:
+      - name: id
+        in: path
+        required: true
+        schema:
+          type: integer
+          example: 100
+    get:
+      summary: Get a user
+      parameters:
+        - name: fields
+          in: query
+          required: true
+          schema:
+            type: string
+        - name: page
+          in: query
+          schema:
+            type: integer
+      responses:
+        '404':
+          description: Not found
+        '200':
+          description: The user
+          headers:
+            X-Request-Id:
+              schema:
+                type: string
+                format: uuid
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/User'
+    delete:
+      responses:
+        '204':
+          description: Deleted
+  /users:
+    post:
+      requestBody:
+        content:
+          application/json:
+            example:
+              name: Fred
+            schema:
+              $ref: '#/components/schemas/User'
+      responses:
+        default:
+          description: Created
+components:
+  schemas:
+    User:
+      type: object
+      properties:
+        id:
+          type: integer
+        name:
+          type: string
+        tags:
+          type: array
+          items:
+            type: string
+        manager:
+          $ref: '#/components/schemas/User'
+"##;
+
+  fn interactions() -> Vec<SynchronousHttp> {
+    let document = parse_document(DOCUMENT).unwrap();
+    let pact = import_pact(&document, "web", None).unwrap();
+    expect!(pact.provider.name.as_str()).to(be_equal_to("User Service"));
+    pact.interactions.iter().map(|i| i.as_v4_http().unwrap()).collect()
+  }
+
+  #[test]
+  fn import_pact_creates_an_interaction_for_each_operation() {
+    let descriptions = interactions().iter().map(|i| i.description.clone()).collect::<Vec<_>>();
+    expect!(descriptions).to(be_equal_to(vec![
+      "POST /users".to_string(),
+      "GET /users/{id} - Get a user".to_string(),
+      "DELETE /users/{id}".to_string()
+    ]));
+    expect!(interactions().iter().all(|i| i.key.is_some())).to(be_true());
+  }
+
+  #[test]
+  fn import_pact_uses_the_examples_and_type_matchers() {
+    let interaction = interactions()[1].clone();
+    expect!(interaction.request.path.as_str()).to(be_equal_to("/users/100"));
+    expect!(interaction.request.query.clone()).to(be_some().value(hashmap! {
+      "fields".to_string() => vec!["string".to_string()]
+    }));
+    expect!(interaction.request.matching_rules.rules_for_category("path").unwrap().rules[""].rules.clone())
+      .to(be_equal_to(vec![MatchingRule::Regex("^/users/[^/]+$".to_string())]));
+
+    let response = interaction.response;
+    expect!(response.status).to(be_equal_to(200));
+    let headers = response.headers.clone().unwrap();
+    expect!(headers["Content-Type"].clone()).to(be_equal_to(vec!["application/json".to_string()]));
+    expect!(headers["X-Request-Id"].clone()).to(be_equal_to(vec!["e2490de5-5bd3-43d5-b7c4-526e33f71304".to_string()]));
+    let body: Value = serde_json::from_slice(&response.body.value().unwrap()).unwrap();
+    expect!(body["id"].clone()).to(be_equal_to(json!(1)));
+    expect!(body["tags"].clone()).to(be_equal_to(json!(["string"])));
+    expect!(body["manager"].clone()).to(be_equal_to(Value::Null));
+    let body_rules = response.matching_rules.rules_for_category("body").unwrap();
+    expect!(body_rules.rules["$.id"].rules.clone()).to(be_equal_to(vec![MatchingRule::Integer]));
+    expect!(body_rules.rules["$.tags"].rules.clone()).to(be_equal_to(vec![MatchingRule::MinType(1)]));
+    expect!(body_rules.rules["$.tags[*]"].rules.clone()).to(be_equal_to(vec![MatchingRule::Type]));
+    expect!(body_rules.rules.contains_key("$.manager")).to(be_false());
+  }
+
+  #[test]
+  fn import_pact_uses_the_request_body_example() {
+    let interaction = interactions()[0].clone();
+    expect!(interaction.request.body.str_value()).to(be_equal_to("{\"name\":\"Fred\"}"));
+    expect!(interaction.response.status).to(be_equal_to(200));
+    expect!(interaction.response.body).to(be_equal_to(OptionalBody::Missing));
+    let delete = interactions()[2].clone();
+    expect!(delete.response.status).to(be_equal_to(204));
+    expect!(delete.request.matching_rules).to(be_equal_to(matchingrules! {
+      "path" => { "" => [ MatchingRule::Regex("^/users/[^/]+$".to_string()) ] }
+    }));
+  }
+
+  #[test]
+  fn expand_path_replaces_the_parameters_with_the_examples() {
+    let values = hashmap! { "user-id".to_string() => "10".to_string() };
+    expect!(expand_path("/users/{user-id}/orders", &values).unwrap()).to(be_equal_to(
+      ("/users/10/orders".to_string(), Some("^/users/[^/]+/orders$".to_string()))));
+    expect!(expand_path("/users", &values).unwrap()).to(be_equal_to(("/users".to_string(), None)));
+  }
+}
@@ -0,0 +1,56 @@
+//! The `openapi` command: converts between OpenAPI 3 documents and pacts
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use serde_json::Value;
+
+pub(crate) mod export;
+pub(crate) mod import;
+
+/// Loads an OpenAPI document from a JSON or YAML file. Only OpenAPI 3 documents are supported.
+pub(crate) fn load_document(path: &Path) -> anyhow::Result<Value> {
+  let contents = fs::read_to_string(path)
+    .map_err(|err| anyhow!("Failed to read OpenAPI document '{}' - {}", path.display(), err))?;
+  parse_document(&contents).map_err(|err| anyhow!("Failed to load OpenAPI document '{}' - {}", path.display(), err))
+}
+
+/// Parses an OpenAPI document in JSON or YAML form
+pub(crate) fn parse_document(contents: &str) -> anyhow::Result<Value> {
+  let document: Value = if contents.trim_start().starts_with('{') {
+    serde_json::from_str(contents)?
+  } else {
+    serde_yaml::from_str(contents)?
+  };
+  match document.get("openapi") {
+    Some(Value::String(version)) if version.starts_with("3.") => Ok(document),
+    Some(version) => Err(anyhow!("OpenAPI version {} is not supported, only OpenAPI 3 documents are", version)),
+    None => Err(anyhow!("It is not an OpenAPI 3 document, as it does not have an 'openapi' version"))
+  }
+}
+
+pub(crate) fn handle_openapi_command(args: &ArgMatches) -> anyhow::Result<()> {
+  match args.subcommand() {
+    ("import", Some(args)) => import::handle_import_command(args),
+    ("export", Some(args)) => export::handle_export_command(args),
+    _ => Err(anyhow!("An openapi sub-command is required"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  #[test]
+  fn parse_document_supports_json_and_yaml() {
+    expect!(parse_document("{\"openapi\": \"3.0.1\", \"paths\": {}}")).to(be_ok());
+    expect!(parse_document("openapi: 3.0.1\npaths: {}\n")).to(be_ok());
+    expect!(parse_document("swagger: '2.0'\npaths: {}\n")).to(be_err());
+    expect!(parse_document("openapi: 2.0\n")).to(be_err());
+    expect!(parse_document("openapi: [\n")).to(be_err());
+  }
+}
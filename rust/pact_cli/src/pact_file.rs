@@ -0,0 +1,381 @@
+//! Loading and writing of pact files that are edited by the CLI commands
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::anyhow;
+use log::*;
+
+use pact_matching::models::{HttpPart, Interaction, MergeStrategy, Pact, read_pact, RequestResponsePact, write_pact};
+use pact_matching::models::generators::Generators;
+use pact_matching::models::matchingrules::MatchingRules;
+use pact_matching::models::message_pact::MessagePact;
+use pact_matching::models::v4::{V4Interaction, V4Pact};
+use pact_models::{OptionalBody, PactSpecification};
+use pact_models::content_types::ContentType;
+
+/// Mutable access to a part (request, response or message) of an interaction
+pub(crate) struct InteractionPart<'a> {
+  /// Description of the interaction
+  pub description: &'a str,
+  /// Name of the part (request, response or message)
+  pub part: &'a str,
+  /// Headers of the part (messages do not have headers)
+  pub headers: Option<&'a mut Option<HashMap<String, Vec<String>>>>,
+  /// Query parameters of the part (only requests have query parameters)
+  pub query: Option<&'a mut Option<HashMap<String, Vec<String>>>>,
+  /// Body or message contents
+  pub body: &'a mut OptionalBody,
+  /// Content type of the body
+  pub content_type: Option<ContentType>,
+  /// Matching rules of the part
+  pub matching_rules: &'a mut MatchingRules
+}
+
+/// Concrete pact loaded from a file, so the interactions can be modified
+#[derive(Debug, Clone)]
+pub(crate) enum PactFile {
+  /// V1 - V3 request/response pact
+  RequestResponse(RequestResponsePact),
+  /// V3 message pact
+  Message(MessagePact),
+  /// V4 pact
+  V4(V4Pact)
+}
+
+impl PactFile {
+  /// Loads the pact file from the given path
+  pub fn load(path: &Path) -> anyhow::Result<PactFile> {
+    let pact = read_pact(path)?;
+    PactFile::from_pact(pact.as_ref())
+  }
+
+  /// Converts the boxed pact into a concrete pact
+  pub fn from_pact(pact: &dyn Pact) -> anyhow::Result<PactFile> {
+    if pact.specification_version() == PactSpecification::V4 {
+      pact.as_v4_pact().map(PactFile::V4).map_err(|err| anyhow!(err))
+    } else if let Ok(pact) = pact.as_message_pact() {
+      Ok(PactFile::Message(pact))
+    } else {
+      pact.as_request_response_pact().map(PactFile::RequestResponse).map_err(|err| anyhow!(err))
+    }
+  }
+
+  /// Returns the pact as a boxed trait object
+  pub fn boxed(&self) -> Box<dyn Pact> {
+    match self {
+      PactFile::RequestResponse(pact) => Box::new(pact.clone()),
+      PactFile::Message(pact) => Box::new(pact.clone()),
+      PactFile::V4(pact) => Box::new(pact.clone())
+    }
+  }
+
+  /// Writes the pact out to the given path, replacing any existing file
+  pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+    let pact = self.boxed();
+    let spec = pact.specification_version();
+    debug!("Writing {:?} pact to {:?}", spec, path);
+    write_pact(pact, path, spec, true, MergeStrategy::default()).map_err(|err| err.into())
+  }
+
+  /// Returns the number of interactions in the pact
+  pub fn len(&self) -> usize {
+    match self {
+      PactFile::RequestResponse(pact) => pact.interactions.len(),
+      PactFile::Message(pact) => pact.messages.len(),
+      PactFile::V4(pact) => pact.interactions.len()
+    }
+  }
+
+  /// Sorts the interactions in the order the `ReadWritePact::merge` implementations join them on
+  pub fn sort_interactions(&mut self) {
+    match self {
+      PactFile::RequestResponse(pact) => pact.interactions
+        .sort_by_cached_key(|i| (state_names(i), i.description.clone())),
+      PactFile::Message(pact) => pact.messages
+        .sort_by_cached_key(|m| (m.description.clone(), state_names(m))),
+      PactFile::V4(pact) => pact.interactions
+        .sort_by_cached_key(|i| (i.key(), i.type_of(), state_names(i.to_super()), i.description()))
+    }
+  }
+
+  /// Only keeps the interactions for which the predicate returns true
+  pub fn retain_interactions<F>(&mut self, mut predicate: F)
+    where F: FnMut(&dyn Interaction) -> bool {
+    match self {
+      PactFile::RequestResponse(pact) => pact.interactions.retain(|i| predicate(i)),
+      PactFile::Message(pact) => pact.messages.retain(|m| predicate(m)),
+      PactFile::V4(pact) => pact.interactions.retain(|i| predicate(i.to_super()))
+    }
+  }
+
+  /// Calls the callback with the description, part name (request, response or message) and
+  /// matching rules for each part of all the interactions in the pact. The matching rules can be
+  /// modified by the callback.
+  pub fn for_each_matching_rules<F>(&mut self, mut callback: F)
+    where F: FnMut(&str, &str, &mut MatchingRules) {
+    match self {
+      PactFile::RequestResponse(pact) => for interaction in pact.interactions.iter_mut() {
+        callback(&interaction.description, "request", &mut interaction.request.matching_rules);
+        callback(&interaction.description, "response", &mut interaction.response.matching_rules);
+      },
+      PactFile::Message(pact) => for message in pact.messages.iter_mut() {
+        callback(&message.description, "message", &mut message.matching_rules);
+      },
+      PactFile::V4(pact) => for interaction in pact.interactions.iter_mut() {
+        if let Some(mut http) = interaction.as_v4_http() {
+          callback(&http.description, "request", &mut http.request.matching_rules);
+          callback(&http.description, "response", &mut http.response.matching_rules);
+          replace_v4(interaction, http);
+        } else if let Some(mut message) = interaction.as_v4_async_message() {
+          callback(&message.description, "message", &mut message.matching_rules);
+          replace_v4(interaction, message);
+        } else if let Some(mut message) = interaction.as_v4_sync_message() {
+          callback(&message.description, "request", &mut message.request.matching_rules);
+          for response in message.response.iter_mut() {
+            callback(&message.description, "response", &mut response.matching_rules);
+          }
+          replace_v4(interaction, message);
+        }
+      }
+    }
+  }
+
+  /// Calls the callback with the description, part name (request, response or message), body,
+  /// content type, matching rules and generators for each part of all the interactions in the pact.
+  /// The matching rules and generators can be modified by the callback.
+  pub fn for_each_body<F>(&mut self, mut callback: F)
+    where F: FnMut(&str, &str, &OptionalBody, Option<ContentType>, &mut MatchingRules, &mut Generators) {
+    match self {
+      PactFile::RequestResponse(pact) => for interaction in pact.interactions.iter_mut() {
+        let request = &mut interaction.request;
+        let content_type = request.content_type();
+        callback(&interaction.description, "request", &request.body, content_type,
+          &mut request.matching_rules, &mut request.generators);
+        let response = &mut interaction.response;
+        let content_type = response.content_type();
+        callback(&interaction.description, "response", &response.body, content_type,
+          &mut response.matching_rules, &mut response.generators);
+      },
+      PactFile::Message(pact) => for message in pact.messages.iter_mut() {
+        let content_type = Interaction::content_type(message);
+        callback(&message.description, "message", &message.contents, content_type,
+          &mut message.matching_rules, &mut message.generators);
+      },
+      PactFile::V4(pact) => for interaction in pact.interactions.iter_mut() {
+        if let Some(mut http) = interaction.as_v4_http() {
+          let request = &mut http.request;
+          let content_type = request.content_type();
+          callback(&http.description, "request", &request.body, content_type,
+            &mut request.matching_rules, &mut request.generators);
+          let response = &mut http.response;
+          let content_type = response.content_type();
+          callback(&http.description, "response", &response.body, content_type,
+            &mut response.matching_rules, &mut response.generators);
+          replace_v4(interaction, http);
+        } else if let Some(mut message) = interaction.as_v4_async_message() {
+          let content_type = Interaction::content_type(&message);
+          callback(&message.description, "message", &message.contents, content_type,
+            &mut message.matching_rules, &mut message.generators);
+          replace_v4(interaction, message);
+        } else if let Some(mut message) = interaction.as_v4_sync_message() {
+          let request = &mut message.request;
+          let content_type = request.content_type();
+          callback(&message.description, "request", &request.contents, content_type,
+            &mut request.matching_rules, &mut request.generators);
+          for response in message.response.iter_mut() {
+            let content_type = response.content_type();
+            callback(&message.description, "response", &response.contents, content_type,
+              &mut response.matching_rules, &mut response.generators);
+          }
+          replace_v4(interaction, message);
+        }
+      }
+    }
+  }
+
+  /// Calls the callback with mutable access to each part of all the interactions in the pact
+  pub fn for_each_part<F>(&mut self, mut callback: F) where F: FnMut(InteractionPart) {
+    match self {
+      PactFile::RequestResponse(pact) => for interaction in pact.interactions.iter_mut() {
+        let request = &mut interaction.request;
+        let content_type = request.content_type();
+        callback(InteractionPart {
+          description: &interaction.description,
+          part: "request",
+          headers: Some(&mut request.headers),
+          query: Some(&mut request.query),
+          body: &mut request.body,
+          content_type,
+          matching_rules: &mut request.matching_rules
+        });
+        let response = &mut interaction.response;
+        let content_type = response.content_type();
+        callback(InteractionPart {
+          description: &interaction.description,
+          part: "response",
+          headers: Some(&mut response.headers),
+          query: None,
+          body: &mut response.body,
+          content_type,
+          matching_rules: &mut response.matching_rules
+        });
+      },
+      PactFile::Message(pact) => for message in pact.messages.iter_mut() {
+        let content_type = Interaction::content_type(message);
+        callback(InteractionPart {
+          description: &message.description,
+          part: "message",
+          headers: None,
+          query: None,
+          body: &mut message.contents,
+          content_type,
+          matching_rules: &mut message.matching_rules
+        });
+      },
+      PactFile::V4(pact) => for interaction in pact.interactions.iter_mut() {
+        if let Some(mut http) = interaction.as_v4_http() {
+          let request = &mut http.request;
+          let content_type = request.content_type();
+          callback(InteractionPart {
+            description: &http.description,
+            part: "request",
+            headers: Some(&mut request.headers),
+            query: Some(&mut request.query),
+            body: &mut request.body,
+            content_type,
+            matching_rules: &mut request.matching_rules
+          });
+          let response = &mut http.response;
+          let content_type = response.content_type();
+          callback(InteractionPart {
+            description: &http.description,
+            part: "response",
+            headers: Some(&mut response.headers),
+            query: None,
+            body: &mut response.body,
+            content_type,
+            matching_rules: &mut response.matching_rules
+          });
+          replace_v4(interaction, http);
+        } else if let Some(mut message) = interaction.as_v4_async_message() {
+          let content_type = Interaction::content_type(&message);
+          callback(InteractionPart {
+            description: &message.description,
+            part: "message",
+            headers: None,
+            query: None,
+            body: &mut message.contents,
+            content_type,
+            matching_rules: &mut message.matching_rules
+          });
+          replace_v4(interaction, message);
+        } else if let Some(mut message) = interaction.as_v4_sync_message() {
+          let request = &mut message.request;
+          let content_type = request.content_type();
+          callback(InteractionPart {
+            description: &message.description,
+            part: "request",
+            headers: None,
+            query: None,
+            body: &mut request.contents,
+            content_type,
+            matching_rules: &mut request.matching_rules
+          });
+          for response in message.response.iter_mut() {
+            let content_type = response.content_type();
+            callback(InteractionPart {
+              description: &message.description,
+              part: "response",
+              headers: None,
+              query: None,
+              body: &mut response.contents,
+              content_type,
+              matching_rules: &mut response.matching_rules
+            });
+          }
+          replace_v4(interaction, message);
+        }
+      }
+    }
+  }
+}
+
+/// Replaces the V4 interaction with the updated copy of it. The copy is taken from the interaction,
+/// so it keeps the key of the interaction if it has one.
+fn replace_v4(interaction: &mut Box<dyn V4Interaction>, updated: impl V4Interaction) {
+  *interaction = updated.boxed_v4();
+}
+
+fn state_names(interaction: &dyn Interaction) -> Vec<String> {
+  interaction.provider_states().iter().map(|state| state.name.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::json;
+
+  use pact_matching::models::load_pact_from_json;
+  use pact_matching::models::matchingrules::{MatchingRule, RuleLogic};
+
+  use super::*;
+
+  fn sync_message_pact() -> PactFile {
+    let json = json!({
+      "consumer": { "name": "consumer" },
+      "provider": { "name": "provider" },
+      "interactions": [
+        {
+          "type": "Synchronous/Messages",
+          "description": "get a user",
+          "request": { "contents": { "content": { "id": 100 }, "contentType": "application/json" } },
+          "response": [
+            { "contents": { "content": { "name": "Fred" }, "contentType": "application/json" } }
+          ]
+        }
+      ],
+      "metadata": { "pactSpecification": { "version": "4.0" } }
+    });
+    let pact = load_pact_from_json("test", &json).unwrap();
+    PactFile::from_pact(pact.as_ref()).unwrap()
+  }
+
+  #[test]
+  fn for_each_part_includes_the_synchronous_message_request_and_responses() {
+    let mut pact = sync_message_pact();
+    let mut parts = vec![];
+    pact.for_each_part(|part| {
+      parts.push(format!("{}:{}:{}:{}", part.description, part.part, part.body.str_value(),
+        part.content_type.map(|ct| ct.to_string()).unwrap_or_default()));
+    });
+    expect!(parts).to(be_equal_to(vec![
+      "get a user:request:{\"id\":100}:application/json".to_string(),
+      "get a user:response:{\"name\":\"Fred\"}:application/json".to_string()
+    ]));
+  }
+
+  #[test]
+  fn for_each_matching_rules_updates_the_synchronous_message_parts() {
+    let mut pact = sync_message_pact();
+    let keys = |pact: &PactFile| match pact {
+      PactFile::V4(pact) => pact.interactions.iter().map(|interaction| interaction.key()).collect(),
+      _ => vec![]
+    };
+    let original_keys = keys(&pact);
+    pact.for_each_matching_rules(|_, _, rules| {
+      rules.add_category("body").add_rule("$.id", MatchingRule::Integer, &RuleLogic::And);
+    });
+    expect!(keys(&pact)).to(be_equal_to(original_keys));
+
+    let mut bodies = vec![];
+    pact.for_each_body(|description, part, body, _, rules, _| {
+      bodies.push(format!("{}:{}:{}:{}", description, part, body.str_value(),
+        rules.rules_for_category("body").is_some()));
+    });
+    expect!(bodies).to(be_equal_to(vec![
+      "get a user:request:{\"id\":100}:true".to_string(),
+      "get a user:response:{\"name\":\"Fred\"}:true".to_string()
+    ]));
+  }
+}
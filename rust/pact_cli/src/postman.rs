@@ -0,0 +1,200 @@
+//! The `postman` command: exports the HTTP interactions of a pact file as a Postman collection, so
+//! the requests can be replayed against a provider manually
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use itertools::Itertools;
+use log::*;
+use serde_json::{json, Value};
+
+use pact_matching::models::build_query_string;
+use pact_matching::models::v4::SynchronousHttp;
+use pact_models::OptionalBody;
+
+use crate::pact_file::PactFile;
+
+/// Schema of the Postman collections that are generated
+const COLLECTION_SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Converts the body of the request into a raw Postman body. JSON bodies are pretty-printed.
+fn request_body(interaction: &SynchronousHttp) -> Option<Value> {
+  let request = &interaction.request;
+  match &request.body {
+    OptionalBody::Present(bytes, _) => {
+      let content_type = request.content_type();
+      let (raw, language) = match content_type {
+        Some(ct) if ct.is_json() => {
+          let raw = serde_json::from_slice::<Value>(bytes)
+            .map(|json| serde_json::to_string_pretty(&json).unwrap_or_default())
+            .unwrap_or_else(|_| String::from_utf8_lossy(bytes).to_string());
+          (raw, "json")
+        },
+        Some(ct) if ct.is_xml() => (String::from_utf8_lossy(bytes).to_string(), "xml"),
+        _ => (String::from_utf8_lossy(bytes).to_string(), "text")
+      };
+      Some(json!({
+        "mode": "raw",
+        "raw": raw,
+        "options": { "raw": { "language": language } }
+      }))
+    },
+    _ => None
+  }
+}
+
+/// Converts the interaction into a Postman request item, with a test that checks the response
+/// status
+pub(crate) fn postman_item(interaction: &SynchronousHttp) -> Value {
+  let request = &interaction.request;
+  let path_segments = request.path.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<_>>();
+  let mut raw_url = format!("{{{{baseUrl}}}}{}", request.path);
+  let mut url = json!({
+    "host": [ "{{baseUrl}}" ],
+    "path": path_segments
+  });
+  if let Some(query) = request.query.as_ref().filter(|query| !query.is_empty()) {
+    raw_url.push('?');
+    raw_url.push_str(&build_query_string(query.clone()));
+    url["query"] = query.iter()
+      .sorted_by(|a, b| a.0.cmp(b.0))
+      .flat_map(|(key, values)| values.iter().map(move |value| json!({ "key": key, "value": value })))
+      .collect();
+  }
+  url["raw"] = json!(raw_url);
+
+  let headers = request.headers.as_ref()
+    .map(|headers| headers.iter()
+      .sorted_by(|a, b| a.0.cmp(b.0))
+      .map(|(key, values)| json!({ "key": key, "value": values.join(", ") }))
+      .collect::<Vec<_>>())
+    .unwrap_or_default();
+  let mut postman_request = json!({
+    "method": request.method.to_uppercase(),
+    "header": headers,
+    "url": url
+  });
+  if let Some(body) = request_body(interaction) {
+    postman_request["body"] = body;
+  }
+
+  let status = interaction.response.status;
+  let mut item = json!({
+    "name": interaction.description,
+    "request": postman_request,
+    "event": [
+      {
+        "listen": "test",
+        "script": {
+          "type": "text/javascript",
+          "exec": [
+            format!("pm.test(\"Status code is {}\", function () {{", status),
+            format!("  pm.response.to.have.status({});", status),
+            "});"
+          ]
+        }
+      }
+    ]
+  });
+  if !interaction.provider_states.is_empty() {
+    let states = interaction.provider_states.iter().map(|state| format!("* {}", state.name)).join("\n");
+    item["request"]["description"] = json!(format!("Provider states:\n{}", states));
+  }
+  item
+}
+
+/// Converts the HTTP interactions of the pact into a Postman collection. Message interactions
+/// are ignored.
+pub(crate) fn postman_collection(pact: &PactFile, base_url: &str) -> Value {
+  let pact = pact.boxed();
+  let items = pact.interactions().iter()
+    .filter_map(|interaction| {
+      let http = interaction.as_v4_http();
+      if http.is_none() {
+        debug!("Ignoring interaction '{}' as it is not an HTTP interaction", interaction.description());
+      }
+      http
+    })
+    .map(|interaction| postman_item(&interaction))
+    .collect::<Vec<_>>();
+  json!({
+    "info": {
+      "name": format!("{} - {}", pact.consumer().name, pact.provider().name),
+      "schema": COLLECTION_SCHEMA
+    },
+    "variable": [ { "key": "baseUrl", "value": base_url } ],
+    "item": items
+  })
+}
+
+pub(crate) fn handle_postman_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let file = args.value_of("file").unwrap();
+  let pact = PactFile::load(Path::new(file))?;
+  let collection = postman_collection(&pact, args.value_of("base-url").unwrap_or("http://localhost:8080"));
+  let output = args.value_of("output").unwrap();
+  fs::write(output, serde_json::to_string_pretty(&collection)?)
+    .map_err(|err| anyhow!("Failed to write Postman collection '{}' - {}", output, err))?;
+  println!("Exported {} request(s) from '{}' into '{}'", collection["item"].as_array().map(|i| i.len()).unwrap_or_default(),
+    file, output);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_test_support::builders::{InteractionBuilder, MessageBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+
+  use super::*;
+
+  #[test]
+  fn postman_item_includes_the_request_and_status_test() {
+    let interaction = InteractionBuilder::new("create a user")
+      .given("no users exist")
+      .request(RequestBuilder::post("/users")
+        .query_param("notify", "true")
+        .header("X-Trace", "1")
+        .json_body(json!({ "name": "Fred" }))
+        .build())
+      .response(ResponseBuilder::new().status(201).build())
+      .build_v4();
+
+    let item = postman_item(&interaction);
+    expect!(item["name"].clone()).to(be_equal_to(json!("create a user")));
+    expect!(item["request"]["method"].clone()).to(be_equal_to(json!("POST")));
+    expect!(item["request"]["url"].clone()).to(be_equal_to(json!({
+      "raw": "{{baseUrl}}/users?notify=true",
+      "host": [ "{{baseUrl}}" ],
+      "path": [ "users" ],
+      "query": [ { "key": "notify", "value": "true" } ]
+    })));
+    expect!(item["request"]["header"].as_array().unwrap().contains(&json!({ "key": "X-Trace", "value": "1" })))
+      .to(be_true());
+    expect!(item["request"]["body"].clone()).to(be_equal_to(json!({
+      "mode": "raw",
+      "raw": "{\n  \"name\": \"Fred\"\n}",
+      "options": { "raw": { "language": "json" } }
+    })));
+    expect!(item["request"]["description"].clone()).to(be_equal_to(json!("Provider states:\n* no users exist")));
+    expect!(item["event"][0]["script"]["exec"][1].clone()).to(be_equal_to(json!("  pm.response.to.have.status(201);")));
+  }
+
+  #[test]
+  fn postman_collection_only_includes_http_interactions() {
+    let builder = PactBuilder::new("web", "users")
+      .interaction(InteractionBuilder::new("get a user")
+        .request(RequestBuilder::get("/users/1").build())
+        .response(ResponseBuilder::new().status(200).build())
+        .build())
+      .message(MessageBuilder::new("a user created event").build());
+    let collection = postman_collection(&PactFile::V4(builder.build_v4()), "http://localhost:9000");
+    expect!(collection["info"]["name"].clone()).to(be_equal_to(json!("web - users")));
+    expect!(collection["variable"][0]["value"].clone()).to(be_equal_to(json!("http://localhost:9000")));
+    let items = collection["item"].as_array().unwrap();
+    expect!(items.len()).to(be_equal_to(1));
+    expect!(items[0]["request"]["url"]["path"].clone()).to(be_equal_to(json!(["users", "1"])));
+    expect!(items[0]["request"].get("body")).to(be_none());
+  }
+}
@@ -0,0 +1,89 @@
+//! The `publish` command: publishes pact files to a Pact Broker
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use log::*;
+use serde_json::Value;
+
+use pact_matching::models::http_utils::{HttpAuth, TlsConfig};
+use pact_matching::models::read_pact;
+use pact_verifier::pact_broker::{publish_pact, PublishOptions};
+
+fn publish_options(args: &ArgMatches) -> PublishOptions {
+  PublishOptions {
+    tags: args.values_of("tag")
+      .map(|tags| tags.map(|tag| tag.to_string()).collect())
+      .unwrap_or_default(),
+    branch: args.value_of("branch").map(|branch| branch.to_string())
+  }
+}
+
+pub(crate) fn http_auth(args: &ArgMatches) -> Option<HttpAuth> {
+  if let Some(token) = args.value_of("token") {
+    Some(HttpAuth::Token(token.to_string()))
+  } else {
+    args.value_of("user").map(|user| HttpAuth::User(user.to_string(),
+      args.value_of("password").map(|password| password.to_string())))
+  }
+}
+
+/// Loads the JSON of the pact file, checking that it is a valid pact first. The JSON is published
+/// as is, so the pact is not changed by being loaded and written out again.
+fn load_pact_json(file: &str) -> anyhow::Result<Value> {
+  let path = Path::new(file);
+  read_pact(path)?;
+  let json = fs::read_to_string(path)?;
+  serde_json::from_str(&json).map_err(|err| anyhow!(err))
+}
+
+pub(crate) fn handle_publish_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let broker_url = args.value_of("broker-url").unwrap();
+  let version = args.value_of("consumer-app-version").unwrap();
+  let options = publish_options(args);
+  let auth = http_auth(args);
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()?;
+
+  for file in args.values_of("files").unwrap() {
+    debug!("Loading pact file '{}'", file);
+    let pact_json = load_pact_json(file)
+      .map_err(|err| anyhow!("Failed to load pact file '{}' - {}", file, err))?;
+    runtime.block_on(publish_pact(broker_url, &pact_json, version, &options, auth.clone(), &TlsConfig::default()))
+      .map_err(|err| anyhow!("Failed to publish pact file '{}' - {}", file, err))?;
+    println!("Published pact file '{}' for consumer version {} to {}", file, version, broker_url);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use crate::setup_app;
+
+  use super::*;
+
+  #[test]
+  fn publish_options_from_the_command_line() {
+    let app = setup_app("pact_cli".to_string(), "v0.0.1");
+    let matches = app.get_matches_from_safe(vec!["pact_cli", "publish", "-b", "http://localhost:9292",
+      "-a", "1.0.0", "-t", "prod", "-t", "test", "--branch", "main", "--token", "1234", "pact.json"]).unwrap();
+    let args = matches.subcommand_matches("publish").unwrap();
+
+    let options = publish_options(args);
+    expect!(options.tags).to(be_equal_to(vec!["prod".to_string(), "test".to_string()]));
+    expect!(options.branch).to(be_some().value("main"));
+    expect!(http_auth(args).map(|auth| auth.to_string())).to(be_some().value("Token(1234)"));
+  }
+
+  #[test]
+  fn load_pact_json_fails_if_the_file_is_not_a_pact() {
+    expect!(load_pact_json("does-not-exist.json")).to(be_err());
+    let file = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    expect!(load_pact_json(file.to_str().unwrap())).to(be_err());
+  }
+}
@@ -0,0 +1,181 @@
+//! The `redact` command: replaces sensitive header values, query parameters and body fields in a
+//! pact with placeholder values and matchers, so the pact can be safely published
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use regex::Regex;
+
+use pact_matching::models::redact::{DEFAULT_PLACEHOLDER, RedactedField, Redactions};
+
+use crate::pact_file::PactFile;
+
+/// A value of an interaction that was redacted
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RedactedEntry {
+  /// Description of the interaction
+  pub description: String,
+  /// Part of the interaction (request, response or message)
+  pub part: String,
+  /// The value that was redacted
+  pub field: RedactedField
+}
+
+/// Redacts the values from the interaction parts whose description matches the filter (and are
+/// the given part, if any). Returns the values that were redacted.
+pub(crate) fn redact_pact(
+  pact: &mut PactFile,
+  redactions: &Redactions,
+  description: Option<&Regex>,
+  part: Option<&str>
+) -> Vec<RedactedEntry> {
+  let mut entries = vec![];
+  pact.for_each_part(|interaction_part| {
+    let (desc, part_name) = (interaction_part.description, interaction_part.part);
+    if description.map(|re| re.is_match(desc)).unwrap_or(true) && part.map(|p| p == part_name).unwrap_or(true) {
+      let mut fields = vec![];
+      if let Some(headers) = interaction_part.headers {
+        fields.extend(redactions.redact_headers(headers, interaction_part.matching_rules));
+      }
+      if let Some(query) = interaction_part.query {
+        fields.extend(redactions.redact_query(query, interaction_part.matching_rules));
+      }
+      fields.extend(redactions.redact_body(interaction_part.body, interaction_part.content_type,
+        interaction_part.matching_rules));
+      entries.extend(fields.into_iter().map(|field| RedactedEntry {
+        description: desc.to_string(),
+        part: part_name.to_string(),
+        field
+      }));
+    }
+  });
+  entries
+}
+
+fn values_of(args: &ArgMatches, name: &str) -> Vec<String> {
+  args.values_of(name).map(|values| values.map(|v| v.to_string()).collect()).unwrap_or_default()
+}
+
+/// Handles the `redact` command
+pub(crate) fn handle_redact_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let redactions = Redactions {
+    headers: values_of(args, "header"),
+    query: values_of(args, "query"),
+    body: values_of(args, "body"),
+    placeholder: args.value_of("placeholder").unwrap_or(DEFAULT_PLACEHOLDER).to_string()
+  };
+  redactions.validate().map_err(|err| anyhow!("Invalid body path - {}", err))?;
+
+  let file = args.value_of("file").unwrap();
+  let mut pact = PactFile::load(Path::new(file))?;
+  let description = match args.value_of("interaction") {
+    Some(filter) => Some(Regex::new(filter)
+      .map_err(|err| anyhow!("'{}' is not a valid regular expression: {}", filter, err))?),
+    None => None
+  };
+  let entries = redact_pact(&mut pact, &redactions, description.as_ref(), args.value_of("part"));
+
+  if entries.is_empty() {
+    println!("No values to redact found");
+  } else {
+    for entry in &entries {
+      println!("{} [{}] redacted {} {}", entry.description, entry.part, entry.field.category, entry.field.path);
+    }
+    pact.write(Path::new(args.value_of("output").unwrap_or(file)))?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::json;
+
+  use pact_matching::models::HttpPart;
+  use pact_test_support::builders::{InteractionBuilder, MessageBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+
+  use super::*;
+
+  fn pact_builder() -> PactBuilder {
+    PactBuilder::new("web", "users")
+      .interaction(InteractionBuilder::new("get a user")
+        .request(RequestBuilder::get("/users/100")
+          .header("Authorization", "Bearer 1234")
+          .query_param("api_key", "abcd")
+          .build())
+        .response(ResponseBuilder::new().status(200).json_body(json!({ "name": "Fred", "email": "fred@example.com" })).build())
+        .build())
+      .interaction(InteractionBuilder::new("get an order")
+        .request(RequestBuilder::get("/orders/1").build())
+        .response(ResponseBuilder::new().status(200).json_body(json!({ "total": 100 })).build())
+        .build())
+  }
+
+  fn redactions() -> Redactions {
+    Redactions {
+      headers: vec!["Authorization".to_string()],
+      query: vec!["api_key".to_string()],
+      body: vec!["$.email".to_string()],
+      placeholder: "XXX".to_string()
+    }
+  }
+
+  fn entries(entries: &[RedactedEntry]) -> Vec<String> {
+    entries.iter()
+      .map(|e| format!("{}:{}:{}:{}", e.description, e.part, e.field.category, e.field.path))
+      .collect()
+  }
+
+  #[test]
+  fn redact_pact_redacts_the_values_in_all_the_interactions() {
+    let mut pact = PactFile::RequestResponse(pact_builder().build());
+    expect!(entries(&redact_pact(&mut pact, &redactions(), None, None))).to(be_equal_to(vec![
+      "get a user:request:header:Authorization".to_string(),
+      "get a user:request:query:api_key".to_string(),
+      "get a user:response:body:$.email".to_string()
+    ]));
+
+    if let PactFile::RequestResponse(pact) = pact {
+      let interaction = &pact.interactions[0];
+      expect!(interaction.request.lookup_header_value("Authorization")).to(be_some().value("XXX"));
+      expect!(interaction.response.body.str_value()).to(be_equal_to("{\"email\":\"XXX\",\"name\":\"Fred\"}"));
+      expect!(interaction.request.matching_rules.rules_for_category("query")).to(be_some());
+    } else {
+      panic!("Expected a request/response pact");
+    }
+  }
+
+  #[test]
+  fn redact_pact_only_updates_the_selected_interactions() {
+    let mut pact = PactFile::RequestResponse(pact_builder().build());
+    let re = Regex::new("order").unwrap();
+    expect!(redact_pact(&mut pact, &redactions(), Some(&re), None).iter()).to(be_empty());
+    expect!(entries(&redact_pact(&mut pact, &redactions(), None, Some("response")))).to(be_equal_to(vec![
+      "get a user:response:body:$.email".to_string()
+    ]));
+  }
+
+  #[test]
+  fn redact_pact_updates_message_and_v4_pacts() {
+    let message_pact = PactBuilder::new("web", "events")
+      .message(MessageBuilder::new("a user created event")
+        .json_contents(json!({ "email": "fred@example.com" }))
+        .build())
+      .build_message_pact();
+    let mut pact = PactFile::Message(message_pact);
+    expect!(entries(&redact_pact(&mut pact, &redactions(), None, None))).to(be_equal_to(vec![
+      "a user created event:message:body:$.email".to_string()
+    ]));
+
+    let mut pact = PactFile::V4(pact_builder().build_v4());
+    expect!(redact_pact(&mut pact, &redactions(), None, None).len()).to(be_equal_to(3));
+    match pact {
+      PactFile::V4(pact) => {
+        let http = pact.interactions[0].as_v4_http().unwrap();
+        expect!(http.request.headers.unwrap()["Authorization"].clone()).to(be_equal_to(vec!["XXX".to_string()]));
+      },
+      _ => panic!("Expected a V4 pact")
+    }
+  }
+}
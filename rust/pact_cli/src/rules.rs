@@ -0,0 +1,357 @@
+//! The `rules` command: lists, removes or adds matching rules across all the interactions in a pact
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use itertools::Itertools;
+use regex::Regex;
+use serde_json::{json, Value};
+
+use pact_matching::models::matchingrules::{MatchingRule, MatchingRules, RuleLogic};
+
+use crate::pact_file::PactFile;
+
+/// A matching rule found in one of the interactions of a pact
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RuleEntry {
+  /// Description of the interaction
+  pub description: String,
+  /// Part of the interaction (request, response or message)
+  pub part: String,
+  /// Matching rule category (body, header, query, etc.)
+  pub category: String,
+  /// Path the rule applies to
+  pub path: String,
+  /// The matching rule
+  pub rule: MatchingRule
+}
+
+impl RuleEntry {
+  fn to_json(&self) -> Value {
+    json!({
+      "interaction": self.description,
+      "part": self.part,
+      "category": self.category,
+      "path": self.path,
+      "rule": self.rule.to_json()
+    })
+  }
+}
+
+/// Selects which matching rules an edit applies to
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RuleSelector {
+  /// Only interactions whose description matches this regex
+  pub description: Option<Regex>,
+  /// Only this part of the interactions (request, response or message)
+  pub part: Option<String>,
+  /// Only rules in this category
+  pub category: Option<String>,
+  /// Only rules with this path
+  pub path: Option<String>,
+  /// Only rules of this type (the `match` attribute of the rule)
+  pub rule_type: Option<String>
+}
+
+impl RuleSelector {
+  fn matches_part(&self, description: &str, part: &str) -> bool {
+    self.description.as_ref().map(|re| re.is_match(description)).unwrap_or(true) &&
+      self.part.as_ref().map(|p| p == part).unwrap_or(true)
+  }
+
+  fn matches_path(&self, category: &str, path: &str) -> bool {
+    self.category.as_ref().map(|c| c == category).unwrap_or(true) &&
+      self.path.as_ref().map(|p| p == path).unwrap_or(true)
+  }
+
+  fn matches_rule(&self, rule: &MatchingRule) -> bool {
+    self.rule_type.as_ref().map(|t| rule_type(rule) == *t).unwrap_or(true)
+  }
+}
+
+fn rule_type(rule: &MatchingRule) -> String {
+  rule.to_json().get("match").and_then(|v| v.as_str()).unwrap_or_default().to_string()
+}
+
+/// Returns all the matching rules in the pact, sorted by interaction, part, category and path
+pub(crate) fn list_rules(pact: &mut PactFile) -> Vec<RuleEntry> {
+  let mut entries = vec![];
+  pact.for_each_matching_rules(|description, part, matching_rules| {
+    for (category_name, category) in matching_rules.rules.iter().sorted_by_key(|(name, _)| name.to_string()) {
+      for (path, rule_list) in category.rules.iter().sorted_by_key(|(path, _)| path.to_string()) {
+        for rule in &rule_list.rules {
+          entries.push(RuleEntry {
+            description: description.to_string(),
+            part: part.to_string(),
+            category: category_name.clone(),
+            path: path.clone(),
+            rule: rule.clone()
+          });
+        }
+      }
+    }
+  });
+  entries
+}
+
+/// Removes all the matching rules selected by the selector. Returns the number of rules removed.
+pub(crate) fn remove_rules(pact: &mut PactFile, selector: &RuleSelector) -> usize {
+  let mut removed = 0;
+  pact.for_each_matching_rules(|description, part, matching_rules| {
+    if selector.matches_part(description, part) {
+      removed += remove_from_matching_rules(matching_rules, selector);
+    }
+  });
+  removed
+}
+
+fn remove_from_matching_rules(matching_rules: &mut MatchingRules, selector: &RuleSelector) -> usize {
+  let mut removed = 0;
+  for (category_name, category) in matching_rules.rules.iter_mut() {
+    for (path, rule_list) in category.rules.iter_mut() {
+      if selector.matches_path(category_name, path) {
+        let count = rule_list.rules.len();
+        rule_list.rules.retain(|rule| !selector.matches_rule(rule));
+        removed += count - rule_list.rules.len();
+      }
+    }
+    category.rules.retain(|_, rule_list| !rule_list.rules.is_empty());
+  }
+  matching_rules.rules.retain(|_, category| !category.is_empty());
+  removed
+}
+
+/// Adds the matching rule to the category and path of all the interaction parts selected by the
+/// selector. Returns the number of interaction parts updated.
+pub(crate) fn add_rule(
+  pact: &mut PactFile,
+  selector: &RuleSelector,
+  category: &str,
+  path: &str,
+  rule: &MatchingRule
+) -> usize {
+  let mut updated = 0;
+  pact.for_each_matching_rules(|description, part, matching_rules| {
+    if selector.matches_part(description, part) {
+      let category = matching_rules.add_category(category);
+      let exists = category.rules.get(path)
+        .map(|rule_list| rule_list.rules.contains(rule))
+        .unwrap_or(false);
+      if !exists {
+        category.add_rule(path, rule.clone(), &RuleLogic::And);
+        updated += 1;
+      }
+    }
+  });
+  updated
+}
+
+fn selector_from_args(args: &ArgMatches) -> anyhow::Result<RuleSelector> {
+  let description = match args.value_of("interaction") {
+    Some(filter) => Some(Regex::new(filter)
+      .map_err(|err| anyhow!("'{}' is not a valid regular expression: {}", filter, err))?),
+    None => None
+  };
+  Ok(RuleSelector {
+    description,
+    part: args.value_of("part").map(|s| s.to_string()),
+    category: args.value_of("category").map(|s| s.to_string()),
+    path: args.value_of("path").map(|s| s.to_string()),
+    rule_type: args.value_of("type").map(|s| s.to_string())
+  })
+}
+
+fn output_path<'a>(args: &'a ArgMatches) -> &'a Path {
+  Path::new(args.value_of("output").unwrap_or_else(|| args.value_of("file").unwrap()))
+}
+
+/// Handles the `rules` command
+pub(crate) fn handle_rules_command(args: &ArgMatches) -> anyhow::Result<()> {
+  match args.subcommand() {
+    ("list", Some(args)) => {
+      let mut pact = PactFile::load(Path::new(args.value_of("file").unwrap()))?;
+      let rules = list_rules(&mut pact);
+      if args.is_present("json") {
+        let json = Value::Array(rules.iter().map(|rule| rule.to_json()).collect());
+        println!("{}", serde_json::to_string_pretty(&json)?);
+      } else if rules.is_empty() {
+        println!("No matching rules found");
+      } else {
+        for rule in rules {
+          println!("{} [{}] {} {} -> {}", rule.description, rule.part, rule.category, rule.path,
+            rule.rule.to_json());
+        }
+      }
+      Ok(())
+    },
+    ("remove", Some(args)) => {
+      let mut pact = PactFile::load(Path::new(args.value_of("file").unwrap()))?;
+      let selector = selector_from_args(args)?;
+      let removed = remove_rules(&mut pact, &selector);
+      pact.write(output_path(args))?;
+      println!("Removed {} matching rule(s)", removed);
+      Ok(())
+    },
+    ("add", Some(args)) => {
+      let mut pact = PactFile::load(Path::new(args.value_of("file").unwrap()))?;
+      let selector = selector_from_args(args)?;
+      let rule_json = args.value_of("rule").unwrap();
+      let rule = serde_json::from_str(rule_json).ok()
+        .and_then(|json| MatchingRule::from_json(&json))
+        .ok_or_else(|| anyhow!("'{}' is not a valid matching rule", rule_json))?;
+      let updated = add_rule(&mut pact, &selector, args.value_of("category").unwrap(),
+        args.value_of("path").unwrap(), &rule);
+      pact.write(output_path(args))?;
+      println!("Added the matching rule to {} interaction part(s)", updated);
+      Ok(())
+    },
+    _ => Err(anyhow!("A rules sub-command is required"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_matching::matchingrules;
+  use pact_matching::models::{Request, RequestResponseInteraction, RequestResponsePact, Response};
+  use pact_matching::models::matchingrules::*;
+  use pact_matching::models::message::Message;
+  use pact_matching::models::message_pact::MessagePact;
+
+  use super::*;
+
+  fn http_pact() -> PactFile {
+    PactFile::RequestResponse(RequestResponsePact {
+      interactions: vec![
+        RequestResponseInteraction {
+          description: "get a user".to_string(),
+          request: Request {
+            matching_rules: matchingrules! {
+              "path" => { "" => [ MatchingRule::Regex("/users/\\d+".to_string()) ] }
+            },
+            .. Request::default()
+          },
+          response: Response {
+            matching_rules: matchingrules! {
+              "body" => {
+                "$.id" => [ MatchingRule::Integer ],
+                "$.name" => [ MatchingRule::Type ]
+              }
+            },
+            .. Response::default()
+          },
+          .. RequestResponseInteraction::default()
+        },
+        RequestResponseInteraction {
+          description: "get all users".to_string(),
+          response: Response {
+            matching_rules: matchingrules! {
+              "body" => { "$[*].name" => [ MatchingRule::Type ] }
+            },
+            .. Response::default()
+          },
+          .. RequestResponseInteraction::default()
+        }
+      ],
+      .. RequestResponsePact::default()
+    })
+  }
+
+  #[test]
+  fn list_rules_returns_the_rules_from_all_the_interactions() {
+    let mut pact = http_pact();
+    let rules = list_rules(&mut pact).iter()
+      .map(|r| format!("{}:{}:{}:{}:{}", r.description, r.part, r.category, r.path, rule_type(&r.rule)))
+      .collect::<Vec<String>>();
+    expect!(rules).to(be_equal_to(vec![
+      "get a user:request:path::regex".to_string(),
+      "get a user:response:body:$.id:integer".to_string(),
+      "get a user:response:body:$.name:type".to_string(),
+      "get all users:response:body:$[*].name:type".to_string()
+    ]));
+  }
+
+  #[test]
+  fn remove_rules_removes_rules_matching_the_type() {
+    let mut pact = http_pact();
+    let selector = RuleSelector { rule_type: Some("type".to_string()), .. RuleSelector::default() };
+    expect!(remove_rules(&mut pact, &selector)).to(be_equal_to(2));
+
+    let rules = list_rules(&mut pact);
+    expect!(rules.len()).to(be_equal_to(2));
+    expect!(rules.iter().any(|r| r.rule == MatchingRule::Type)).to(be_false());
+    if let PactFile::RequestResponse(pact) = pact {
+      expect!(pact.interactions[1].response.matching_rules.is_empty()).to(be_true());
+      expect!(pact.interactions[1].response.matching_rules.rules.contains_key("body")).to(be_false());
+    }
+  }
+
+  #[test]
+  fn remove_rules_only_removes_rules_for_the_selected_path() {
+    let mut pact = http_pact();
+    let selector = RuleSelector {
+      category: Some("body".to_string()),
+      path: Some("$.id".to_string()),
+      .. RuleSelector::default()
+    };
+    expect!(remove_rules(&mut pact, &selector)).to(be_equal_to(1));
+    expect!(list_rules(&mut pact).len()).to(be_equal_to(3));
+  }
+
+  #[test]
+  fn add_rule_adds_the_rule_to_the_selected_parts_only_once() {
+    let mut pact = http_pact();
+    let selector = RuleSelector {
+      description: Some(Regex::new("user$").unwrap()),
+      part: Some("response".to_string()),
+      .. RuleSelector::default()
+    };
+    let rule = MatchingRule::Regex("\\d{4}-\\d{2}-\\d{2}".to_string());
+    expect!(add_rule(&mut pact, &selector, "body", "$.created", &rule)).to(be_equal_to(1));
+    expect!(add_rule(&mut pact, &selector, "body", "$.created", &rule)).to(be_equal_to(0));
+
+    let rules = list_rules(&mut pact);
+    expect!(rules.iter().filter(|r| r.path == "$.created").count()).to(be_equal_to(1));
+    let added = rules.iter().find(|r| r.path == "$.created").unwrap();
+    expect!(added.description.as_str()).to(be_equal_to("get a user"));
+    expect!(added.part.as_str()).to(be_equal_to("response"));
+  }
+
+  #[test]
+  fn add_rule_updates_message_pacts() {
+    let mut pact = PactFile::Message(MessagePact {
+      messages: vec![ Message { description: "a message".to_string(), .. Message::default() } ],
+      .. MessagePact::default()
+    });
+    expect!(add_rule(&mut pact, &RuleSelector::default(), "body", "$.id", &MatchingRule::Integer)).to(be_equal_to(1));
+    expect!(list_rules(&mut pact)).to(be_equal_to(vec![
+      RuleEntry {
+        description: "a message".to_string(),
+        part: "message".to_string(),
+        category: "body".to_string(),
+        path: "$.id".to_string(),
+        rule: MatchingRule::Integer
+      }
+    ]));
+  }
+
+  #[test]
+  fn rule_entry_to_json() {
+    let entry = RuleEntry {
+      description: "a message".to_string(),
+      part: "message".to_string(),
+      category: "header".to_string(),
+      path: "Content-Type".to_string(),
+      rule: MatchingRule::Regex("application/.*json".to_string())
+    };
+    expect!(entry.to_json()).to(be_equal_to(json!({
+      "interaction": "a message",
+      "part": "message",
+      "category": "header",
+      "path": "Content-Type",
+      "rule": { "match": "regex", "regex": "application/.*json" }
+    })));
+  }
+}
@@ -0,0 +1,108 @@
+//! The `spec-tests` command: runs pact-specification test cases against the matching engine
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use regex::Regex;
+use serde_json::{json, Value};
+
+use pact_matching::spec_runner::{run_test_cases, SpecTestResult};
+
+use crate::verify::parse_spec;
+
+fn display_result(result: &SpecTestResult) -> String {
+  let case = &result.test_case;
+  let status = if result.passed() { "PASS" } else { "FAIL" };
+  let mut lines = vec![format!("{} {} {} '{}' ({})", status, case.spec.to_string(), case.case_type,
+    case.name(), case.source)];
+  if !result.passed() {
+    if let Some(comment) = &case.comment {
+      lines.push(format!("    {}", comment));
+    }
+    match &result.error {
+      Some(error) => lines.push(format!("    error: {}", error)),
+      None if case.expected_match => for mismatch in &result.mismatches {
+        lines.push(format!("    mismatch: {}", mismatch.description()));
+      },
+      None => lines.push("    expected a mismatch, but the values matched".to_string())
+    }
+  }
+  lines.join("\n")
+}
+
+/// Returns the number of test cases that passed and failed
+pub(crate) fn summarise(results: &[SpecTestResult]) -> (usize, usize) {
+  let passed = results.iter().filter(|result| result.passed()).count();
+  (passed, results.len() - passed)
+}
+
+pub(crate) fn handle_spec_tests_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let dir = args.value_of("dir").unwrap();
+  let spec = args.value_of("spec").map(|spec| parse_spec(spec)
+    .ok_or_else(|| anyhow!("'{}' is not a valid specification version", spec)))
+    .transpose()?;
+  let filter = args.value_of("filter").map(Regex::new).transpose()?;
+
+  let results = run_test_cases(Path::new(dir), spec)?.into_iter()
+    .filter(|result| filter.as_ref()
+      .map(|filter| filter.is_match(&result.test_case.source))
+      .unwrap_or(true))
+    .collect::<Vec<SpecTestResult>>();
+  let (passed, failed) = summarise(&results);
+
+  if args.is_present("json") {
+    let json = json!({
+      "passed": passed,
+      "failed": failed,
+      "results": results.iter().map(|result| result.to_json()).collect::<Vec<Value>>()
+    });
+    println!("{}", serde_json::to_string_pretty(&json)?);
+  } else {
+    for result in results.iter().filter(|result| !args.is_present("failures-only") || !result.passed()) {
+      println!("{}", display_result(result));
+    }
+    println!();
+    println!("{} test case(s), {} passed, {} failed", results.len(), passed, failed);
+  }
+
+  if failed > 0 {
+    Err(anyhow!("{} of {} test case(s) failed", failed, results.len()))
+  } else {
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_matching::spec_runner::{SpecTestCase, TestCaseType};
+  use pact_models::PactSpecification;
+
+  use super::*;
+
+  fn result(expected_match: bool) -> SpecTestResult {
+    let test_case = SpecTestCase::from_json("v3/request/method/different method.json", PactSpecification::V3,
+      TestCaseType::Request, &json!({
+        "match": expected_match,
+        "comment": "Methods is incorrect",
+        "expected": { "method": "POST", "path": "/" },
+        "actual": { "method": "GET", "path": "/" }
+      })).unwrap();
+    test_case.run()
+  }
+
+  #[test]
+  fn display_result_test() {
+    expect!(display_result(&result(false))).to(be_equal_to(
+      "PASS V3 request 'different method' (v3/request/method/different method.json)".to_string()));
+    expect!(display_result(&result(true))).to(be_equal_to(
+      "FAIL V3 request 'different method' (v3/request/method/different method.json)\n    Methods is incorrect\n    mismatch: expected POST but was GET".to_string()));
+  }
+
+  #[test]
+  fn summarise_test() {
+    expect!(summarise(&[result(false), result(true), result(false)])).to(be_equal_to((2, 1)));
+  }
+}
@@ -0,0 +1,128 @@
+//! The `stub` command: serves the pacts in directories, files or from a Pact Broker as a stub
+//! provider
+
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use log::*;
+
+use pact_matching::models::http_utils::TlsConfig;
+use pact_matching::models::Pact;
+use pact_mock_server::mock_server::{MockServerConfig, StubOptions};
+use pact_mock_server::multi_pact::{combine_pacts, load_pacts};
+use pact_mock_server::server_manager::ServerManager;
+use pact_mock_server::tls::TlsConfigBuilder;
+use pact_verifier::pact_broker::fetch_pacts_from_broker;
+
+use crate::publish::http_auth;
+
+/// Loads the pacts from the files and directories, and the latest pacts for the provider from the
+/// Pact Broker if a broker URL is given
+fn load_stub_pacts(args: &ArgMatches, runtime: &tokio::runtime::Runtime) -> anyhow::Result<Vec<Box<dyn Pact>>> {
+  let paths = args.values_of("paths")
+    .map(|paths| paths.map(PathBuf::from).collect::<Vec<_>>())
+    .unwrap_or_default();
+  let mut pacts = load_pacts(&paths).map_err(|err| anyhow!("Failed to load the pacts - {}", err))?;
+
+  if let Some(broker_url) = args.value_of("broker-url") {
+    let provider = args.value_of("provider").unwrap();
+    debug!("Fetching the pacts for provider '{}' from {}", provider, broker_url);
+    let results = runtime.block_on(fetch_pacts_from_broker(broker_url, provider, http_auth(args), &TlsConfig::default()))
+      .map_err(|err| anyhow!("Failed to fetch the pacts for provider '{}' from {} - {}", provider, broker_url, err))?;
+    for result in results {
+      let (pact, _, _) = result
+        .map_err(|err| anyhow!("Failed to fetch a pact for provider '{}' from {} - {}", provider, broker_url, err))?;
+      pacts.push(pact.boxed());
+    }
+  }
+
+  if pacts.is_empty() {
+    Err(anyhow!("There are no pacts to serve"))
+  } else {
+    Ok(pacts)
+  }
+}
+
+fn stub_config(args: &ArgMatches) -> anyhow::Result<MockServerConfig> {
+  let stub = match args.values_of("ignore") {
+    Some(parts) => parts.collect::<Vec<_>>().join(",").parse::<StubOptions>().map_err(|err| anyhow!(err))?,
+    None => StubOptions::default()
+  };
+  Ok(MockServerConfig {
+    cors_preflight: args.is_present("cors"),
+    stub: Some(stub),
+    .. MockServerConfig::default()
+  })
+}
+
+pub(crate) fn handle_stub_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()?;
+  let pacts = load_stub_pacts(args, &runtime)?;
+  let pact = combine_pacts(&pacts).map_err(|err| anyhow!(err))?;
+  let interactions = pact.interactions.len();
+  let config = stub_config(args)?;
+  let port = args.value_of("port").map(|port| port.parse::<u16>()).transpose()
+    .map_err(|err| anyhow!("Invalid port - {}", err))?
+    .unwrap_or(0);
+
+  let mut manager = ServerManager::new();
+  let id = "pact_cli_stub".to_string();
+  let (port, scheme) = match (args.value_of("tls-cert"), args.value_of("tls-key")) {
+    (Some(cert), Some(key)) => {
+      let tls = TlsConfigBuilder::new().cert_path(cert).key_path(key).build()
+        .map_err(|err| anyhow!("Failed to load the TLS certificate and key - {}", err))?;
+      (manager.start_tls_mock_server(id, pact.boxed(), port, &tls, config), "https")
+    },
+    _ => (manager.start_mock_server(id, pact.boxed(), port, config), "http")
+  };
+  let port = port.map_err(|err| anyhow!("Failed to start the stub server - {}", err))?;
+
+  println!("Stub server serving {} interaction(s) from {} pact(s) on {}://localhost:{}", interactions,
+    pacts.len(), scheme, port);
+  println!("Press Ctrl-C to stop the stub server");
+  if let Err(err) = runtime.block_on(tokio::signal::ctrl_c()) {
+    warn!("Failed to wait for Ctrl-C - {}", err);
+  }
+  manager.shutdown_all();
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use crate::setup_app;
+
+  use super::*;
+
+  #[test]
+  fn stub_config_from_the_command_line() {
+    let app = setup_app("pact_cli".to_string(), "v0.0.1");
+    let matches = app.get_matches_from_safe(vec!["pact_cli", "stub", "--cors", "--ignore", "headers",
+      "--ignore", "query", "pacts"]).unwrap();
+    let args = matches.subcommand_matches("stub").unwrap();
+
+    let config = stub_config(args).unwrap();
+    expect!(config.cors_preflight).to(be_true());
+    expect!(config.stub).to(be_some().value(StubOptions { ignore_body: false, ignore_headers: true, ignore_query: true }));
+  }
+
+  #[test]
+  fn stub_requires_the_provider_with_a_broker_url() {
+    let app = setup_app("pact_cli".to_string(), "v0.0.1");
+    expect!(app.get_matches_from_safe(vec!["pact_cli", "stub", "-b", "http://localhost:9292"])).to(be_err());
+  }
+
+  #[test]
+  fn load_stub_pacts_fails_if_there_are_no_pacts() {
+    let app = setup_app("pact_cli".to_string(), "v0.0.1");
+    let matches = app.get_matches_from_safe(vec!["pact_cli", "stub", "does-not-exist"]).unwrap();
+    let args = matches.subcommand_matches("stub").unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+    expect!(load_stub_pacts(args, &runtime)).to(be_err());
+  }
+}
@@ -0,0 +1,1040 @@
+//! The `verify` command: checks that a pact file is valid for its specification version
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use pact_matching::models::generators::Generator;
+use pact_matching::models::matchingrules::MatchingRule;
+use pact_matching::models::validate_method;
+use pact_models::content_types::ContentType;
+use pact_models::PactSpecification;
+
+/// Level of a verification result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ResultLevel {
+  /// The pact file is not valid
+  Error,
+  /// The pact file is valid, but something in it will be ignored or may not be what was intended
+  Warning
+}
+
+impl Display for ResultLevel {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ResultLevel::Error => write!(f, "ERROR"),
+      ResultLevel::Warning => write!(f, "WARNING")
+    }
+  }
+}
+
+/// Problem found when verifying a pact file
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct VerificationResult {
+  /// Path to the item in the pact file (i.e. `$.interactions[0].request.method`)
+  pub path: String,
+  /// Level of the result
+  pub level: ResultLevel,
+  /// Description of the problem
+  pub message: String
+}
+
+impl VerificationResult {
+  fn to_json(&self) -> Value {
+    json!({
+      "path": self.path,
+      "level": self.level.to_string(),
+      "message": self.message
+    })
+  }
+}
+
+const V4_INTERACTION_TYPES: [&str; 3] = ["Synchronous/HTTP", "Asynchronous/Messages", "Synchronous/Messages"];
+const V4_INTERACTION_ATTRIBUTES: [&str; 8] = ["type", "key", "description", "providerStates", "comments",
+  "pending", "_id", "id"];
+const MATCHER_TYPES: [&str; 16] = ["regex", "equality", "include", "type", "number", "integer", "decimal", "real",
+  "min", "max", "timestamp", "date", "time", "null", "contentType", "arrayContains"];
+const CONTENT_TYPE_HINTS: [&str; 3] = ["TEXT", "BINARY", "DEFAULT"];
+
+/// Returns the path to the attribute of the item at the given path
+fn child_path(path: &str, key: &str) -> String {
+  if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+    format!("{}.{}", path, key)
+  } else {
+    format!("{}['{}']", path, key)
+  }
+}
+
+fn index_path(path: &str, index: usize) -> String {
+  format!("{}[{}]", path, index)
+}
+
+fn json_type(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "Null",
+    Value::Bool(_) => "Boolean",
+    Value::Number(_) => "Number",
+    Value::String(_) => "String",
+    Value::Array(_) => "Array",
+    Value::Object(_) => "Object"
+  }
+}
+
+/// Collects the problems found while walking the pact JSON
+struct Verifier {
+  strict: bool,
+  results: Vec<VerificationResult>
+}
+
+impl Verifier {
+  fn new(strict: bool) -> Self {
+    Verifier { strict, results: vec![] }
+  }
+
+  fn error<S: Into<String>>(&mut self, path: &str, message: S) {
+    self.results.push(VerificationResult { path: path.to_string(), level: ResultLevel::Error, message: message.into() });
+  }
+
+  fn warning<S: Into<String>>(&mut self, path: &str, message: S) {
+    self.results.push(VerificationResult { path: path.to_string(), level: ResultLevel::Warning, message: message.into() });
+  }
+
+  /// Unknown attributes are errors in strict mode, otherwise warnings as they will be ignored
+  fn unknown_attributes(&mut self, path: &str, map: &Map<String, Value>, known: &[&str]) {
+    for key in map.keys().filter(|key| !known.contains(&key.as_str())) {
+      let message = format!("Unknown attribute '{}', it will be ignored", key);
+      if self.strict {
+        self.error(&child_path(path, key), message);
+      } else {
+        self.warning(&child_path(path, key), message);
+      }
+    }
+  }
+
+  fn expect_object<'a>(&mut self, path: &str, value: &'a Value) -> Option<&'a Map<String, Value>> {
+    match value {
+      Value::Object(map) => Some(map),
+      _ => {
+        self.error(path, format!("Must be an Object, got {}", json_type(value)));
+        None
+      }
+    }
+  }
+
+  fn expect_array<'a>(&mut self, path: &str, value: &'a Value) -> Option<&'a Vec<Value>> {
+    match value {
+      Value::Array(array) => Some(array),
+      _ => {
+        self.error(path, format!("Must be an Array, got {}", json_type(value)));
+        None
+      }
+    }
+  }
+
+  fn expect_string<'a>(&mut self, path: &str, value: &'a Value) -> Option<&'a str> {
+    match value {
+      Value::String(s) => Some(s.as_str()),
+      _ => {
+        self.error(path, format!("Must be a String, got {}", json_type(value)));
+        None
+      }
+    }
+  }
+
+  fn expect_bool(&mut self, path: &str, value: &Value) {
+    if !value.is_boolean() {
+      self.error(path, format!("Must be a Boolean, got {}", json_type(value)));
+    }
+  }
+
+  fn required<'a>(&mut self, path: &str, map: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    let value = map.get(key);
+    if value.is_none() {
+      self.error(path, format!("Missing required attribute '{}'", key));
+    }
+    value
+  }
+
+  fn verify_participant(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      if let Some(name) = self.required(path, map, "name") {
+        if let Some(name) = self.expect_string(&child_path(path, "name"), name) {
+          if name.is_empty() {
+            self.error(&child_path(path, "name"), "Name can not be empty");
+          }
+        }
+      }
+    }
+  }
+
+  fn verify_string_map(&mut self, path: &str, value: &Value, allow_arrays: bool) {
+    if let Some(map) = self.expect_object(path, value) {
+      for (key, value) in map {
+        let path = child_path(path, key);
+        match value {
+          Value::String(_) => (),
+          Value::Array(values) if allow_arrays => for (index, value) in values.iter().enumerate() {
+            self.expect_string(&index_path(&path, index), value);
+          },
+          _ if allow_arrays => self.error(&path, format!("Must be a String or an Array of Strings, got {}", json_type(value))),
+          _ => self.error(&path, format!("Must be a String, got {}", json_type(value)))
+        }
+      }
+    }
+  }
+
+  fn verify_provider_states(&mut self, path: &str, value: &Value) {
+    if let Some(states) = self.expect_array(path, value) {
+      for (index, state) in states.iter().enumerate() {
+        let path = index_path(path, index);
+        if let Some(map) = self.expect_object(&path, state) {
+          if let Some(name) = self.required(&path, map, "name") {
+            self.expect_string(&child_path(&path, "name"), name);
+          }
+          if let Some(params) = map.get("params") {
+            self.expect_object(&child_path(&path, "params"), params);
+          }
+          self.unknown_attributes(&path, map, &["name", "params"]);
+        }
+      }
+    }
+  }
+
+  fn verify_method(&mut self, path: &str, value: &Value) {
+    if let Some(method) = self.expect_string(path, value) {
+      if let Err(err) = validate_method(method) {
+        self.error(path, err);
+      }
+    }
+  }
+
+  fn verify_request_path(&mut self, path: &str, value: &Value) {
+    if let Some(request_path) = self.expect_string(path, value) {
+      if !request_path.starts_with('/') {
+        self.warning(path, format!("Path '{}' does not start with a '/'", request_path));
+      }
+    }
+  }
+
+  fn verify_status(&mut self, path: &str, value: &Value) {
+    match value.as_u64() {
+      Some(status) if (100..=599).contains(&status) => (),
+      Some(status) => self.error(path, format!("{} is not a valid HTTP status code", status)),
+      None => self.error(path, format!("Must be an Integer, got {}", json_type(value)))
+    }
+  }
+
+  fn verify_matcher(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      match map.get("match") {
+        Some(Value::String(match_type)) if !MATCHER_TYPES.contains(&match_type.as_str()) =>
+          self.error(&child_path(path, "match"), format!("'{}' is not a valid matcher type", match_type)),
+        Some(Value::String(_)) => match MatchingRule::from_json(value) {
+          Some(MatchingRule::Regex(regex)) => if let Err(err) = Regex::new(&regex) {
+            self.error(&child_path(path, "regex"), format!("'{}' is not a valid regular expression - {}", regex, err));
+          },
+          Some(_) => (),
+          None => self.error(path, "Matcher is missing a required attribute")
+        },
+        Some(other) => self.error(&child_path(path, "match"), format!("Must be a String, got {}", json_type(other))),
+        None => self.error(path, "Missing required attribute 'match'")
+      }
+    }
+  }
+
+  fn verify_rule_list(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      if let Some(combine) = map.get("combine") {
+        if let Some(combine) = self.expect_string(&child_path(path, "combine"), combine) {
+          if combine != "AND" && combine != "OR" {
+            self.error(&child_path(path, "combine"), format!("'{}' is not a valid value, must be AND or OR", combine));
+          }
+        }
+      }
+      if let Some(matchers) = self.required(path, map, "matchers") {
+        let matchers_path = child_path(path, "matchers");
+        if let Some(matchers) = self.expect_array(&matchers_path, matchers) {
+          if matchers.is_empty() {
+            self.warning(&matchers_path, "Matcher list is empty");
+          }
+          for (index, matcher) in matchers.iter().enumerate() {
+            self.verify_matcher(&index_path(&matchers_path, index), matcher);
+          }
+        }
+      }
+      self.unknown_attributes(path, map, &["combine", "matchers"]);
+    }
+  }
+
+  /// Verifies the matching rules in the V3 and V4 format, where the rules are grouped by category
+  fn verify_matching_rules(&mut self, path: &str, value: &Value, categories: &[&str]) {
+    if let Some(map) = self.expect_object(path, value) {
+      for (category, rules) in map {
+        let category_path = child_path(path, category);
+        if !categories.contains(&category.as_str()) {
+          self.error(&category_path, format!("'{}' is not a valid matching rule category here, expected one of {}",
+            category, categories.join(", ")));
+          continue;
+        }
+        match category.as_str() {
+          "path" | "method" | "status" => self.verify_rule_list(&category_path, rules),
+          _ => if let Some(rules) = self.expect_object(&category_path, rules) {
+            for (key, rule_list) in rules {
+              let rule_path = child_path(&category_path, key);
+              if category == "body" && !key.starts_with('$') {
+                self.error(&rule_path, format!("'{}' is not a valid body path, it must start with '$'", key));
+              }
+              self.verify_rule_list(&rule_path, rule_list);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn verify_generator(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      if let Some(gen_type) = self.required(path, map, "type") {
+        if let Some(gen_type) = self.expect_string(&child_path(path, "type"), gen_type) {
+          if Generator::from_map(&gen_type.to_string(), map).is_none() {
+            self.error(&child_path(path, "type"), format!("'{}' is not a valid generator", gen_type));
+          }
+        }
+      }
+    }
+  }
+
+  fn verify_generators(&mut self, path: &str, value: &Value, categories: &[&str]) {
+    if let Some(map) = self.expect_object(path, value) {
+      for (category, generators) in map {
+        let category_path = child_path(path, category);
+        if !categories.contains(&category.as_str()) {
+          self.error(&category_path, format!("'{}' is not a valid generator category here, expected one of {}",
+            category, categories.join(", ")));
+          continue;
+        }
+        match category.as_str() {
+          "path" | "method" | "status" => self.verify_generator(&category_path, generators),
+          _ => if let Some(generators) = self.expect_object(&category_path, generators) {
+            for (key, generator) in generators {
+              self.verify_generator(&child_path(&category_path, key), generator);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  /// Verifies a V4 contents block (`content`, `contentType`, `encoded` and `contentTypeHint`)
+  fn verify_contents(&mut self, path: &str, value: &Value) {
+    let map = match self.expect_object(path, value) {
+      Some(map) => map,
+      None => return
+    };
+
+    let content_type = match map.get("contentType") {
+      Some(content_type) => match self.expect_string(&child_path(path, "contentType"), content_type) {
+        Some(content_type) => match ContentType::parse(content_type) {
+          Ok(content_type) => Some(content_type),
+          Err(err) => {
+            self.error(&child_path(path, "contentType"), format!("'{}' is not a valid content type - {}", content_type, err));
+            None
+          }
+        },
+        None => None
+      },
+      None => {
+        if map.get("content").is_some() {
+          self.warning(path, "Missing attribute 'contentType', the content type will be detected from the content");
+        }
+        None
+      }
+    };
+
+    let encoding = match map.get("encoded") {
+      Some(Value::Bool(true)) => Some("base64".to_string()),
+      Some(Value::Bool(false)) | None => None,
+      Some(Value::String(encoding)) => match encoding.to_lowercase().as_str() {
+        "base64" | "json" => Some(encoding.to_lowercase()),
+        _ => {
+          self.error(&child_path(path, "encoded"), format!("'{}' is not a valid encoding, must be base64 or json", encoding));
+          None
+        }
+      },
+      Some(other) => {
+        self.error(&child_path(path, "encoded"), format!("Must be a Boolean or a String, got {}", json_type(other)));
+        None
+      }
+    };
+
+    if let Some(content) = map.get("content") {
+      let content_path = child_path(path, "content");
+      match encoding.as_deref() {
+        Some("base64") => if let Some(content) = self.expect_string(&content_path, content) {
+          if let Err(err) = base64::decode(content) {
+            self.error(&content_path, format!("Content is not valid base64 - {}", err));
+          }
+        },
+        Some(_) => (),
+        None => if !content.is_string() && !content.is_null() {
+          if let Some(content_type) = content_type.filter(|ct| !ct.is_json()) {
+            self.warning(&content_path, format!("Content is JSON, but the content type is '{}'", content_type));
+          }
+        }
+      }
+    }
+
+    if let Some(hint) = map.get("contentTypeHint") {
+      if let Some(hint) = self.expect_string(&child_path(path, "contentTypeHint"), hint) {
+        if !CONTENT_TYPE_HINTS.contains(&hint) {
+          self.error(&child_path(path, "contentTypeHint"), format!("'{}' is not a valid content type hint, expected one of {}",
+            hint, CONTENT_TYPE_HINTS.join(", ")));
+        }
+      }
+    }
+
+    self.unknown_attributes(path, map, &["content", "contentType", "encoded", "contentTypeHint"]);
+  }
+
+  fn verify_comments(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      if let Some(text) = map.get("text") {
+        let text_path = child_path(path, "text");
+        if let Some(text) = self.expect_array(&text_path, text) {
+          for (index, comment) in text.iter().enumerate() {
+            self.expect_string(&index_path(&text_path, index), comment);
+          }
+        }
+      }
+      if let Some(testname) = map.get("testname") {
+        self.expect_string(&child_path(path, "testname"), testname);
+      }
+    }
+  }
+
+  fn verify_v4_http_request(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      if let Some(method) = self.required(path, map, "method") {
+        self.verify_method(&child_path(path, "method"), method);
+      }
+      if let Some(request_path) = self.required(path, map, "path") {
+        self.verify_request_path(&child_path(path, "path"), request_path);
+      }
+      if let Some(query) = map.get("query") {
+        self.verify_string_map(&child_path(path, "query"), query, true);
+      }
+      if let Some(headers) = map.get("headers") {
+        self.verify_string_map(&child_path(path, "headers"), headers, true);
+      }
+      if let Some(body) = map.get("body") {
+        self.verify_contents(&child_path(path, "body"), body);
+      }
+      if let Some(rules) = map.get("matchingRules") {
+        self.verify_matching_rules(&child_path(path, "matchingRules"), rules,
+          &["method", "path", "query", "header", "body"]);
+      }
+      if let Some(generators) = map.get("generators") {
+        self.verify_generators(&child_path(path, "generators"), generators,
+          &["method", "path", "query", "header", "body"]);
+      }
+      self.unknown_attributes(path, map, &["method", "path", "query", "headers", "body", "matchingRules", "generators"]);
+    }
+  }
+
+  fn verify_v4_http_response(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      if let Some(status) = self.required(path, map, "status") {
+        self.verify_status(&child_path(path, "status"), status);
+      }
+      if let Some(headers) = map.get("headers") {
+        self.verify_string_map(&child_path(path, "headers"), headers, true);
+      }
+      if let Some(body) = map.get("body") {
+        self.verify_contents(&child_path(path, "body"), body);
+      }
+      if let Some(rules) = map.get("matchingRules") {
+        self.verify_matching_rules(&child_path(path, "matchingRules"), rules, &["status", "header", "body"]);
+      }
+      if let Some(generators) = map.get("generators") {
+        self.verify_generators(&child_path(path, "generators"), generators, &["status", "header", "body"]);
+      }
+      self.unknown_attributes(path, map, &["status", "headers", "body", "matchingRules", "generators"]);
+    }
+  }
+
+  /// Verifies the attributes of a message (the contents, metadata, matching rules and generators).
+  /// Returns the names of the attributes that were checked.
+  fn verify_v4_message_parts(&mut self, path: &str, map: &Map<String, Value>) -> [&'static str; 4] {
+    if let Some(contents) = map.get("contents") {
+      self.verify_contents(&child_path(path, "contents"), contents);
+    }
+    if let Some(metadata) = map.get("metadata") {
+      self.expect_object(&child_path(path, "metadata"), metadata);
+    }
+    if let Some(rules) = map.get("matchingRules") {
+      self.verify_matching_rules(&child_path(path, "matchingRules"), rules, &["body", "metadata"]);
+    }
+    if let Some(generators) = map.get("generators") {
+      self.verify_generators(&child_path(path, "generators"), generators, &["body", "metadata"]);
+    }
+    ["contents", "metadata", "matchingRules", "generators"]
+  }
+
+  fn verify_v4_message(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      let known = self.verify_v4_message_parts(path, map);
+      self.unknown_attributes(path, map, &known);
+    }
+  }
+
+  fn verify_v4_interaction(&mut self, path: &str, value: &Value, keys: &mut HashMap<String, String>) {
+    let map = match self.expect_object(path, value) {
+      Some(map) => map,
+      None => return
+    };
+
+    let interaction_type = match self.required(path, map, "type") {
+      Some(interaction_type) => match self.expect_string(&child_path(path, "type"), interaction_type) {
+        Some(interaction_type) if V4_INTERACTION_TYPES.contains(&interaction_type) => interaction_type,
+        Some(interaction_type) => {
+          self.warning(&child_path(path, "type"), format!("Unknown interaction type '{}', the interaction will be ignored",
+            interaction_type));
+          return;
+        },
+        None => return
+      },
+      None => return
+    };
+
+    match map.get("key") {
+      Some(key) => if let Some(key) = self.expect_string(&child_path(path, "key"), key) {
+        match keys.get(key) {
+          Some(other) => self.error(&child_path(path, "key"),
+            format!("Duplicate interaction key '{}', it is also used by {}", key, other)),
+          None => {
+            keys.insert(key.to_string(), path.to_string());
+          }
+        }
+      },
+      None => self.warning(path, "Missing attribute 'key', a key will be calculated when the pact is loaded")
+    }
+    if let Some(description) = self.required(path, map, "description") {
+      self.expect_string(&child_path(path, "description"), description);
+    }
+    if let Some(states) = map.get("providerStates") {
+      self.verify_provider_states(&child_path(path, "providerStates"), states);
+    }
+    if let Some(comments) = map.get("comments") {
+      self.verify_comments(&child_path(path, "comments"), comments);
+    }
+    if let Some(pending) = map.get("pending") {
+      self.expect_bool(&child_path(path, "pending"), pending);
+    }
+
+    let mut known = V4_INTERACTION_ATTRIBUTES.to_vec();
+    match interaction_type {
+      "Synchronous/HTTP" => {
+        if let Some(request) = self.required(path, map, "request") {
+          self.verify_v4_http_request(&child_path(path, "request"), request);
+        }
+        if let Some(response) = self.required(path, map, "response") {
+          self.verify_v4_http_response(&child_path(path, "response"), response);
+        }
+        known.extend_from_slice(&["request", "response"]);
+      },
+      "Asynchronous/Messages" => {
+        known.extend_from_slice(&self.verify_v4_message_parts(path, map));
+      },
+      _ => {
+        if let Some(request) = self.required(path, map, "request") {
+          self.verify_v4_message(&child_path(path, "request"), request);
+        }
+        if let Some(response) = map.get("response") {
+          let response_path = child_path(path, "response");
+          if let Some(responses) = self.expect_array(&response_path, response) {
+            for (index, response) in responses.iter().enumerate() {
+              self.verify_v4_message(&index_path(&response_path, index), response);
+            }
+          }
+        }
+        known.extend_from_slice(&["request", "response"]);
+      }
+    }
+    self.unknown_attributes(path, map, &known);
+  }
+
+  fn verify_v4_pact(&mut self, map: &Map<String, Value>) {
+    if let Some(interactions) = self.required("$", map, "interactions") {
+      if let Some(interactions) = self.expect_array("$.interactions", interactions) {
+        let mut keys = HashMap::new();
+        for (index, interaction) in interactions.iter().enumerate() {
+          self.verify_v4_interaction(&index_path("$.interactions", index), interaction, &mut keys);
+        }
+      }
+    }
+    self.unknown_attributes("$", map, &["consumer", "provider", "interactions", "metadata"]);
+  }
+
+  /// Verifies the V2 format of matching rules, where the keys are paths to the item (i.e. `$.body.id`)
+  fn verify_v2_matching_rules(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      for (key, matcher) in map {
+        let matcher_path = child_path(path, key);
+        if !key.starts_with('$') {
+          self.error(&matcher_path, format!("'{}' is not a valid matcher path, it must start with '$'", key));
+        }
+        match matcher {
+          Value::Object(m) if m.contains_key("match") => self.verify_matcher(&matcher_path, matcher),
+          Value::Object(_) => if MatchingRule::from_json(matcher).is_none() {
+            self.error(&matcher_path, "Matcher is missing a required attribute");
+          },
+          _ => self.error(&matcher_path, format!("Must be an Object, got {}", json_type(matcher)))
+        }
+      }
+    }
+  }
+
+  fn verify_http_part(&mut self, path: &str, map: &Map<String, Value>, spec: &PactSpecification, categories: &[&str]) {
+    if let Some(headers) = map.get("headers") {
+      self.verify_string_map(&child_path(path, "headers"), headers, *spec == PactSpecification::V3);
+    }
+    if let Some(rules) = map.get("matchingRules") {
+      if *spec == PactSpecification::V3 {
+        self.verify_matching_rules(&child_path(path, "matchingRules"), rules, categories);
+      } else {
+        self.verify_v2_matching_rules(&child_path(path, "matchingRules"), rules);
+      }
+    }
+    if let Some(generators) = map.get("generators") {
+      if *spec == PactSpecification::V3 {
+        self.verify_generators(&child_path(path, "generators"), generators, categories);
+      } else {
+        self.error(&child_path(path, "generators"), format!("Generators are not supported by {} pacts", spec.to_string()));
+      }
+    }
+  }
+
+  fn verify_interaction(&mut self, path: &str, value: &Value, spec: &PactSpecification) {
+    let map = match self.expect_object(path, value) {
+      Some(map) => map,
+      None => return
+    };
+
+    if let Some(description) = self.required(path, map, "description") {
+      self.expect_string(&child_path(path, "description"), description);
+    }
+    if *spec == PactSpecification::V3 {
+      if let Some(states) = map.get("providerStates") {
+        self.verify_provider_states(&child_path(path, "providerStates"), states);
+      }
+    }
+    if let Some(state) = map.get("providerState").or_else(|| map.get("provider_state")) {
+      self.expect_string(&child_path(path, "providerState"), state);
+    }
+
+    if let Some(request) = self.required(path, map, "request") {
+      let request_path = child_path(path, "request");
+      if let Some(request) = self.expect_object(&request_path, request) {
+        if let Some(method) = self.required(&request_path, request, "method") {
+          self.verify_method(&child_path(&request_path, "method"), method);
+        }
+        if let Some(request_path_value) = request.get("path") {
+          self.verify_request_path(&child_path(&request_path, "path"), request_path_value);
+        }
+        if let Some(query) = request.get("query") {
+          let query_path = child_path(&request_path, "query");
+          if *spec == PactSpecification::V3 {
+            if !query.is_string() {
+              self.verify_string_map(&query_path, query, true);
+            }
+          } else {
+            self.expect_string(&query_path, query);
+          }
+        }
+        self.verify_http_part(&request_path, request, spec, &["method", "path", "query", "header", "body"]);
+        self.unknown_attributes(&request_path, request, &["method", "path", "query", "headers", "body",
+          "matchingRules", "generators"]);
+      }
+    }
+
+    if let Some(response) = self.required(path, map, "response") {
+      let response_path = child_path(path, "response");
+      if let Some(response) = self.expect_object(&response_path, response) {
+        if let Some(status) = response.get("status") {
+          self.verify_status(&child_path(&response_path, "status"), status);
+        }
+        self.verify_http_part(&response_path, response, spec, &["status", "header", "body"]);
+        self.unknown_attributes(&response_path, response, &["status", "headers", "body", "matchingRules", "generators"]);
+      }
+    }
+
+    self.unknown_attributes(path, map, &["description", "providerState", "provider_state", "providerStates",
+      "request", "response", "_id"]);
+  }
+
+  fn verify_message(&mut self, path: &str, value: &Value) {
+    if let Some(map) = self.expect_object(path, value) {
+      if let Some(description) = self.required(path, map, "description") {
+        self.expect_string(&child_path(path, "description"), description);
+      }
+      if let Some(states) = map.get("providerStates") {
+        self.verify_provider_states(&child_path(path, "providerStates"), states);
+      }
+      if let Some(metadata) = map.get("metadata").or_else(|| map.get("metaData")) {
+        self.expect_object(&child_path(path, "metadata"), metadata);
+      }
+      if let Some(rules) = map.get("matchingRules") {
+        self.verify_matching_rules(&child_path(path, "matchingRules"), rules, &["body", "metadata"]);
+      }
+      if let Some(generators) = map.get("generators") {
+        self.verify_generators(&child_path(path, "generators"), generators, &["body", "metadata"]);
+      }
+      self.unknown_attributes(path, map, &["description", "providerStates", "contents", "metadata", "metaData",
+        "matchingRules", "generators", "_id"]);
+    }
+  }
+
+  fn verify_pact(&mut self, map: &Map<String, Value>, spec: PactSpecification) {
+    match (map.get("interactions"), map.get("messages")) {
+      (None, None) => self.error("$", "Missing required attribute 'interactions' or 'messages'"),
+      (interactions, messages) => {
+        if let Some(interactions) = interactions {
+          if let Some(interactions) = self.expect_array("$.interactions", interactions) {
+            for (index, interaction) in interactions.iter().enumerate() {
+              self.verify_interaction(&index_path("$.interactions", index), interaction, &spec);
+            }
+          }
+        }
+        if let Some(messages) = messages {
+          if spec != PactSpecification::V3 {
+            self.error("$.messages", format!("Messages are not supported by {} pacts", spec.to_string()));
+          } else if let Some(messages) = self.expect_array("$.messages", messages) {
+            for (index, message) in messages.iter().enumerate() {
+              self.verify_message(&index_path("$.messages", index), message);
+            }
+          }
+        }
+      }
+    }
+    self.unknown_attributes("$", map, &["consumer", "provider", "interactions", "messages", "metadata"]);
+  }
+}
+
+/// Returns the specification version from the pact metadata, if there is one
+pub(crate) fn spec_from_metadata(pact_json: &Value) -> Option<PactSpecification> {
+  let metadata = pact_json.get("metadata")?;
+  let version = metadata.get("pactSpecification")
+    .or_else(|| metadata.get("pact-specification"))
+    .and_then(|spec| spec.get("version"))
+    .or_else(|| metadata.get("pactSpecificationVersion"))?;
+  parse_spec(version.as_str()?)
+}
+
+/// Parses the specification version (i.e. `3.0.0` or `V3`)
+pub(crate) fn parse_spec(version: &str) -> Option<PactSpecification> {
+  let version = version.trim_start_matches(['v', 'V']);
+  let mut parts = version.split('.');
+  match (parts.next(), parts.next()) {
+    (Some("1"), Some("1")) => Some(PactSpecification::V1_1),
+    (Some("1"), _) => Some(PactSpecification::V1),
+    (Some("2"), _) => Some(PactSpecification::V2),
+    (Some("3"), _) => Some(PactSpecification::V3),
+    (Some("4"), _) => Some(PactSpecification::V4),
+    _ => None
+  }
+}
+
+/// Verifies the pact JSON against the given specification version. If no version is given, the
+/// version from the pact metadata is used (defaulting to V3). In strict mode, unknown attributes
+/// are reported as errors instead of warnings.
+pub(crate) fn verify_json(pact_json: &Value, spec: Option<PactSpecification>, strict: bool) -> Vec<VerificationResult> {
+  let mut verifier = Verifier::new(strict);
+  let map = match verifier.expect_object("$", pact_json) {
+    Some(map) => map,
+    None => return verifier.results
+  };
+
+  let spec = match spec.or_else(|| spec_from_metadata(pact_json)) {
+    Some(spec) => spec,
+    None => {
+      verifier.warning("$.metadata", "Could not determine the pact specification version from the metadata, assuming V3");
+      PactSpecification::V3
+    }
+  };
+
+  match map.get("consumer") {
+    Some(consumer) => verifier.verify_participant("$.consumer", consumer),
+    None => verifier.error("$", "Missing required attribute 'consumer'")
+  }
+  match map.get("provider") {
+    Some(provider) => verifier.verify_participant("$.provider", provider),
+    None => verifier.error("$", "Missing required attribute 'provider'")
+  }
+  if let Some(metadata) = map.get("metadata") {
+    verifier.expect_object("$.metadata", metadata);
+  }
+
+  if spec == PactSpecification::V4 {
+    verifier.verify_v4_pact(map);
+  } else {
+    verifier.verify_pact(map, spec);
+  }
+  verifier.results
+}
+
+pub(crate) fn handle_verify_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let file = args.value_of("file").unwrap();
+  let spec = match args.value_of("spec") {
+    Some(spec) => Some(parse_spec(spec).ok_or_else(|| anyhow!("'{}' is not a valid specification version", spec))?),
+    None => None
+  };
+  let contents = fs::read_to_string(Path::new(file))
+    .map_err(|err| anyhow!("Failed to read pact file '{}' - {}", file, err))?;
+  let pact_json: Value = serde_json::from_str(&contents)
+    .map_err(|err| anyhow!("Failed to parse pact file '{}' as JSON - {}", file, err))?;
+
+  let results = verify_json(&pact_json, spec, args.is_present("strict"));
+  if args.is_present("json") {
+    let json = Value::Array(results.iter().map(|result| result.to_json()).collect());
+    println!("{}", serde_json::to_string_pretty(&json)?);
+  } else {
+    for result in &results {
+      println!("{} {}: {}", result.level, result.path, result.message);
+    }
+  }
+
+  let errors = results.iter().filter(|result| result.level == ResultLevel::Error).count();
+  if errors > 0 {
+    Err(anyhow!("Pact file '{}' is not valid, found {} error(s)", file, errors))
+  } else {
+    if !args.is_present("json") {
+      println!("Pact file '{}' is valid", file);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_test_support::fixtures;
+
+  use super::*;
+
+  fn errors(results: &[VerificationResult]) -> Vec<(String, String)> {
+    results.iter()
+      .filter(|result| result.level == ResultLevel::Error)
+      .map(|result| (result.path.clone(), result.message.clone()))
+      .collect()
+  }
+
+  fn warnings(results: &[VerificationResult]) -> Vec<(String, String)> {
+    results.iter()
+      .filter(|result| result.level == ResultLevel::Warning)
+      .map(|result| (result.path.clone(), result.message.clone()))
+      .collect()
+  }
+
+  fn v4_pact(interactions: Value) -> Value {
+    json!({
+      "consumer": { "name": "consumer" },
+      "provider": { "name": "provider" },
+      "interactions": interactions,
+      "metadata": { "pactSpecification": { "version": "4.0" } }
+    })
+  }
+
+  #[test]
+  fn parse_spec_test() {
+    expect!(parse_spec("4.0")).to(be_some().value(PactSpecification::V4));
+    expect!(parse_spec("v3")).to(be_some().value(PactSpecification::V3));
+    expect!(parse_spec("1.1.0")).to(be_some().value(PactSpecification::V1_1));
+    expect!(parse_spec("1.0.0")).to(be_some().value(PactSpecification::V1));
+    expect!(parse_spec("five")).to(be_none());
+  }
+
+  #[test]
+  fn valid_v4_pact() {
+    let pact = v4_pact(json!([
+      {
+        "type": "Synchronous/HTTP",
+        "key": "001",
+        "description": "get a user",
+        "providerStates": [ { "name": "user exists", "params": { "id": 1 } } ],
+        "comments": { "text": [ "a comment" ], "testname": "user_test" },
+        "pending": false,
+        "request": {
+          "method": "GET",
+          "path": "/users/1",
+          "query": { "fields": [ "name" ] },
+          "headers": { "Accept": [ "application/json" ] },
+          "matchingRules": {
+            "path": { "combine": "AND", "matchers": [ { "match": "regex", "regex": "/users/\\d+" } ] },
+            "query": { "fields": { "matchers": [ { "match": "type" } ] } }
+          }
+        },
+        "response": {
+          "status": 200,
+          "body": { "content": { "id": 1 }, "contentType": "application/json", "encoded": false },
+          "matchingRules": { "body": { "$.id": { "matchers": [ { "match": "integer" } ] } } },
+          "generators": { "body": { "$.id": { "type": "RandomInt", "min": 1, "max": 10 } } }
+        }
+      },
+      {
+        "type": "Asynchronous/Messages",
+        "key": "002",
+        "description": "a user created event",
+        "contents": { "content": "AQID", "contentType": "application/octet-stream", "encoded": "base64" },
+        "metadata": { "destination": "users" }
+      },
+      {
+        "type": "Synchronous/Messages",
+        "key": "003",
+        "description": "a user request",
+        "request": { "contents": { "content": "get", "contentType": "text/plain" } },
+        "response": [ { "contents": { "content": "user", "contentType": "text/plain" } } ]
+      }
+    ]));
+    expect!(verify_json(&pact, None, true).iter()).to(be_empty());
+  }
+
+  #[test]
+  fn v4_interactions_with_invalid_attributes() {
+    let pact = v4_pact(json!([
+      {
+        "type": "Synchronous/HTTP",
+        "key": "001",
+        "description": "get a user",
+        "request": { "method": "GET ME", "path": "/" },
+        "response": { "status": 999 }
+      },
+      {
+        "type": "Synchronous/HTTP",
+        "key": "001",
+        "description": 100,
+        "comments": { "text": "a comment" },
+        "request": { "path": "/" },
+        "response": { "status": 200 }
+      },
+      { "description": "no type" }
+    ]));
+    expect!(errors(&verify_json(&pact, None, false))).to(be_equal_to(vec![
+      ("$.interactions[0].request.method".to_string(),
+        "'GET ME' is not a valid HTTP method, it contains an invalid character ' '".to_string()),
+      ("$.interactions[0].response.status".to_string(), "999 is not a valid HTTP status code".to_string()),
+      ("$.interactions[1].key".to_string(), "Duplicate interaction key '001', it is also used by $.interactions[0]".to_string()),
+      ("$.interactions[1].description".to_string(), "Must be a String, got Number".to_string()),
+      ("$.interactions[1].comments.text".to_string(), "Must be an Array, got String".to_string()),
+      ("$.interactions[1].request".to_string(), "Missing required attribute 'method'".to_string()),
+      ("$.interactions[2]".to_string(), "Missing required attribute 'type'".to_string())
+    ]));
+  }
+
+  #[test]
+  fn v4_unknown_interaction_types_are_warnings() {
+    let pact = v4_pact(json!([
+      { "type": "Synchronous/GraphQL", "key": "001", "description": "a query" }
+    ]));
+    let results = verify_json(&pact, None, false);
+    expect!(errors(&results).iter()).to(be_empty());
+    expect!(warnings(&results)).to(be_equal_to(vec![
+      ("$.interactions[0].type".to_string(),
+        "Unknown interaction type 'Synchronous/GraphQL', the interaction will be ignored".to_string())
+    ]));
+  }
+
+  #[test]
+  fn v4_contents_blocks_and_matching_rules() {
+    let pact = v4_pact(json!([
+      {
+        "type": "Asynchronous/Messages",
+        "key": "001",
+        "description": "an event",
+        "contents": { "content": "not base64!", "contentType": "text/plain", "encoded": "base64", "contentTypeHint": "TXT" },
+        "matchingRules": {
+          "body": {
+            "id": { "matchers": [ { "match": "integer" } ] },
+            "$.name": { "combine": "XOR", "matchers": [ { "match": "regex", "regex": "[a-z" }, { "match": "any" } ] }
+          },
+          "status": { "matchers": [] }
+        }
+      }
+    ]));
+    let results = verify_json(&pact, None, false);
+    let errors = errors(&results);
+    expect!(errors.iter().map(|(path, _)| path.as_str()).collect::<Vec<&str>>()).to(be_equal_to(vec![
+      "$.interactions[0].contents.content",
+      "$.interactions[0].contents.contentTypeHint",
+      "$.interactions[0].matchingRules.body['$.name'].combine",
+      "$.interactions[0].matchingRules.body['$.name'].matchers[0].regex",
+      "$.interactions[0].matchingRules.body['$.name'].matchers[1].match",
+      "$.interactions[0].matchingRules.body.id",
+      "$.interactions[0].matchingRules.status"
+    ]));
+    expect!(errors[4].1.as_str()).to(be_equal_to("'any' is not a valid matcher type"));
+  }
+
+  #[test]
+  fn unknown_attributes_are_errors_in_strict_mode() {
+    let pact = v4_pact(json!([
+      {
+        "type": "Synchronous/HTTP",
+        "key": "001",
+        "description": "get a user",
+        "request": { "method": "GET", "path": "/", "cookies": {} },
+        "response": { "status": 200 }
+      }
+    ]));
+    let unknown = vec![("$.interactions[0].request.cookies".to_string(),
+      "Unknown attribute 'cookies', it will be ignored".to_string())];
+    expect!(warnings(&verify_json(&pact, None, false))).to(be_equal_to(unknown.clone()));
+    expect!(errors(&verify_json(&pact, None, true))).to(be_equal_to(unknown));
+  }
+
+  #[test]
+  fn fixture_pacts_are_valid() {
+    for spec in &[PactSpecification::V1, PactSpecification::V1_1, PactSpecification::V2, PactSpecification::V3,
+      PactSpecification::V4] {
+      expect!(verify_json(&fixtures::pact_json(spec.clone()), None, true).iter()).to(be_empty());
+    }
+    for spec in &[PactSpecification::V3, PactSpecification::V4] {
+      expect!(verify_json(&fixtures::message_pact_json(spec.clone()), None, true).iter()).to(be_empty());
+    }
+  }
+
+  #[test]
+  fn verifies_older_spec_versions() {
+    let pact = json!({
+      "consumer": { "name": "consumer" },
+      "provider": { "name": "" },
+      "interactions": [
+        {
+          "description": "get a user",
+          "providerState": "user exists",
+          "request": { "method": "GET", "path": "/users", "query": "id=1" },
+          "response": {
+            "status": 200,
+            "matchingRules": { "$.body.id": { "match": "type" }, "body": { "regex": "\\d+" } }
+          }
+        }
+      ],
+      "metadata": { "pactSpecification": { "version": "2.0.0" } }
+    });
+    expect!(errors(&verify_json(&pact, None, false))).to(be_equal_to(vec![
+      ("$.provider.name".to_string(), "Name can not be empty".to_string()),
+      ("$.interactions[0].response.matchingRules.body".to_string(),
+        "'body' is not a valid matcher path, it must start with '$'".to_string())
+    ]));
+    expect!(errors(&verify_json(&pact, Some(PactSpecification::V4), false))).to(be_equal_to(vec![
+      ("$.provider.name".to_string(), "Name can not be empty".to_string()),
+      ("$.interactions[0]".to_string(), "Missing required attribute 'type'".to_string())
+    ]));
+  }
+}
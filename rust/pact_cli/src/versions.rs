@@ -0,0 +1,60 @@
+//! The `create-version-tag`, `record-deployment` and `record-release` commands: record what has
+//! happened to a version of a pacticipant in a Pact Broker
+
+use anyhow::anyhow;
+use clap::ArgMatches;
+
+use pact_matching::models::http_utils::TlsConfig;
+use pact_verifier::pact_broker::{create_version_tag, record_deployment, record_release};
+
+use crate::publish::http_auth;
+
+fn runtime() -> anyhow::Result<tokio::runtime::Runtime> {
+  tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()
+    .map_err(|err| anyhow!(err))
+}
+
+pub(crate) fn handle_create_version_tag_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let broker_url = args.value_of("broker-url").unwrap();
+  let pacticipant = args.value_of("pacticipant").unwrap();
+  let version = args.value_of("version").unwrap();
+  let auth = http_auth(args);
+  let runtime = runtime()?;
+
+  for tag in args.values_of("tag").unwrap() {
+    runtime.block_on(create_version_tag(broker_url, pacticipant, version, tag, auth.clone(), &TlsConfig::default()))
+      .map_err(|err| anyhow!("Failed to tag version {} of {} with '{}' - {}", version, pacticipant, tag, err))?;
+    println!("Tagged version {} of {} with '{}'", version, pacticipant, tag);
+  }
+  Ok(())
+}
+
+pub(crate) fn handle_record_deployment_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let broker_url = args.value_of("broker-url").unwrap();
+  let pacticipant = args.value_of("pacticipant").unwrap();
+  let version = args.value_of("version").unwrap();
+  let environment = args.value_of("environment").unwrap();
+
+  runtime()?.block_on(record_deployment(broker_url, pacticipant, version, environment,
+    args.value_of("application-instance"), http_auth(args), &TlsConfig::default()))
+    .map_err(|err| anyhow!("Failed to record the deployment of version {} of {} to {} - {}", version, pacticipant,
+      environment, err))?;
+  println!("Recorded the deployment of version {} of {} to {}", version, pacticipant, environment);
+  Ok(())
+}
+
+pub(crate) fn handle_record_release_command(args: &ArgMatches) -> anyhow::Result<()> {
+  let broker_url = args.value_of("broker-url").unwrap();
+  let pacticipant = args.value_of("pacticipant").unwrap();
+  let version = args.value_of("version").unwrap();
+  let environment = args.value_of("environment").unwrap();
+
+  runtime()?.block_on(record_release(broker_url, pacticipant, version, environment, http_auth(args),
+    &TlsConfig::default()))
+    .map_err(|err| anyhow!("Failed to record the release of version {} of {} to {} - {}", version, pacticipant,
+      environment, err))?;
+  println!("Recorded the release of version {} of {} to {}", version, pacticipant, environment);
+  Ok(())
+}
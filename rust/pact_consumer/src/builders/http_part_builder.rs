@@ -6,6 +6,7 @@ use env_logger;
 #[cfg(test)]
 use regex::Regex;
 
+use pact_matching::models::generators::{GeneratorCategory, Generators};
 use pact_matching::models::matchingrules::MatchingRules;
 use pact_models::OptionalBody;
 
@@ -33,6 +34,12 @@ pub trait HttpPartBuilder {
     #[doc(hidden)]
     fn body_and_matching_rules_mut(&mut self) -> (&mut OptionalBody, &mut MatchingRules);
 
+    /// (Implementation detail.) This function fetches the generators of this
+    /// builder, so the generators of patterns can be added to them. You should
+    /// not need to use this under normal circumstances.
+    #[doc(hidden)]
+    fn generators_mut(&mut self) -> &mut Generators;
+
     /// Specify a header pattern.
     ///
     /// ```
@@ -62,6 +69,11 @@ pub trait HttpPartBuilder {
         }
         value.extract_matching_rules(&name, rules.add_category("header"))
       }
+      let mut generators = HashMap::new();
+      value.extract_generators(&name, &mut generators);
+      for (key, generator) in generators {
+        self.generators_mut().add_generator_with_subcategory(&GeneratorCategory::HEADER, key, generator);
+      }
       self
     }
 
@@ -146,6 +158,9 @@ pub trait HttpPartBuilder {
             *body_ref = OptionalBody::Present(body.to_example().to_string().into(), Some("application/json".into()));
             body.extract_matching_rules("$", rules.add_category("body"));
         }
+        for (path, generator) in body.generators() {
+            self.generators_mut().add_generator_with_subcategory(&GeneratorCategory::BODY, path, generator);
+        }
         self
     }
 }
@@ -210,3 +225,26 @@ fn json_body_pattern() {
     assert_requests_match!(good, pattern);
     assert_requests_do_not_match!(bad, pattern);
 }
+
+#[test]
+fn json_body_pattern_generators() {
+    use maplit::*;
+    use pact_matching::models::generators::Generator;
+
+    let mut builder = crate::builders::ResponseBuilder::default();
+    builder
+        .header("X-Request-Id", term!("^[0-9a-f-]+$", "1234-abcd", generator = Generator::Uuid))
+        .json_body(json_pattern!({
+            "id": like!(10, generator = Generator::RandomInt(1, 100)),
+            "items": each_like!({ "code": term!("^[A-Z]{3}$", "ABC", generator = Generator::Regex("^[A-Z]{3}$".to_string())) })
+        }));
+    let response = builder.build();
+
+    assert_eq!(response.generators.categories.get(&GeneratorCategory::BODY), Some(&hashmap!{
+        "$.id".to_string() => Generator::RandomInt(1, 100),
+        "$.items[*].code".to_string() => Generator::Regex("^[A-Z]{3}$".to_string())
+    }));
+    assert_eq!(response.generators.categories.get(&GeneratorCategory::HEADER), Some(&hashmap!{
+        "X-Request-Id".to_string() => Generator::Uuid
+    }));
+}
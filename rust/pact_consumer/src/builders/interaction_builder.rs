@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use pact_matching::models::*;
 use pact_matching::models::provider_states::ProviderState;
 
@@ -12,6 +14,9 @@ pub struct InteractionBuilder {
     provider_states: Vec<ProviderState>,
     comments: Vec<String>,
     test_name: Option<String>,
+    timeout: Option<Duration>,
+    transport: Option<String>,
+    key: Option<String>,
 
     /// A builder for this interaction's `Request`.
     pub request: RequestBuilder,
@@ -28,6 +33,9 @@ impl InteractionBuilder {
       provider_states: vec![],
       comments: vec![],
       test_name: None,
+      timeout: None,
+      transport: None,
+      key: None,
       request: RequestBuilder::default(),
       response: ResponseBuilder::default(),
     }
@@ -56,6 +64,28 @@ impl InteractionBuilder {
     self
   }
 
+  /// Sets how long the provider has to respond to the request for this interaction. This is only
+  /// written to V4 pacts, where the verifier will fail the interaction if the provider takes
+  /// longer than this to respond.
+  pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// Sets the transport used to verify this interaction against the provider (i.e. `https`). This
+  /// is only written to V4 pacts. By default, the verifier uses the configured provider protocol.
+  pub fn transport<T: Into<String>>(&mut self, transport: T) -> &mut Self {
+    self.transport = Some(transport.into());
+    self
+  }
+
+  /// Sets the key of this interaction. This is only written to V4 pacts. By default, the key is
+  /// calculated from the description and provider states of the interaction.
+  pub fn key<K: Into<String>>(&mut self, key: K) -> &mut Self {
+    self.key = Some(key.into());
+    self
+  }
+
   /// The interaction we've built.
   pub fn build(&self) -> RequestResponseInteraction {
     RequestResponseInteraction {
@@ -69,14 +99,24 @@ impl InteractionBuilder {
 
   /// The interaction we've built (in V4 format).
   pub fn build_v4(&self) -> SynchronousHttp {
-    SynchronousHttp {
+    let mut interaction = SynchronousHttp {
       id: None,
-      key: None,
+      key: self.key.clone(),
       description: self.description.clone(),
       provider_states: self.provider_states.clone(),
       request: self.request.build().as_v4_request(),
       response: self.response.build().as_v4_response(),
-      comments: Default::default()
+      comments: Default::default(),
+      timeout: self.timeout,
+      transport: self.transport.clone(),
+      .. SynchronousHttp::default()
+    };
+    for comment in &self.comments {
+      interaction.add_text_comment(comment);
+    }
+    if let Some(test_name) = &self.test_name {
+      interaction.set_test_name(test_name);
     }
+    interaction
   }
 }
@@ -36,3 +36,24 @@ fn basic_builder_example() {
     assert_eq!(&interaction.description(), "GET /greeting/hello");
     assert_eq!(interaction.provider_states()[0].name, "a greeting named hello");
 }
+
+#[test]
+fn v4_builder_example() {
+    let pact = PactBuilder::new_v4("Consumer", "Provider")
+        .interaction("GET /greeting/hello", |i| {
+            i.given("a greeting named hello");
+            i.timeout(std::time::Duration::from_millis(500));
+            i.comment("greets the user");
+            i.request.path("/greeting/hello");
+            i.response.json_body(json_pattern!({ "message": like!("Hello!") }));
+        })
+        .build();
+
+    assert_eq!(pact.spec_version(), pact_models::PactSpecification::V4);
+    let interactions = pact.interactions();
+    let interaction = interactions[0].as_v4_http().unwrap();
+    assert_eq!(interaction.description, "GET /greeting/hello");
+    assert_eq!(interaction.timeout, Some(std::time::Duration::from_millis(500)));
+    assert_eq!(interaction.request.path, "/greeting/hello");
+    assert!(interaction.response.matching_rules.rules_for_category("body").is_some());
+}
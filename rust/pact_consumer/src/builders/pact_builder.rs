@@ -1,5 +1,6 @@
 use pact_matching::models::*;
-use pact_models::{Consumer, Provider};
+use pact_matching::models::v4::V4Pact;
+use pact_models::{Consumer, PactSpecification, Provider};
 
 use crate::prelude::*;
 
@@ -46,6 +47,38 @@ impl PactBuilder {
         PactBuilder { pact: pact.boxed() }
     }
 
+    /// Create a new `PactBuilder` for a V4 pact, specifying the names of the service consuming
+    /// the API and the service providing it. The interactions are built as V4 interactions, so
+    /// the V4 only attributes of the interactions (like the timeout and comments) are written to
+    /// the pact file.
+    ///
+    /// ```
+    /// use pact_consumer::prelude::*;
+    /// use pact_models::PactSpecification;
+    ///
+    /// let pact = PactBuilder::new_v4("Greeting Client", "Greeting Server")
+    ///     .interaction("asks for a greeting", |i| {
+    ///         i.request.path("/greeting/hello");
+    ///         i.response.body("hello");
+    ///     })
+    ///     .build();
+    ///
+    /// assert_eq!(pact.spec_version(), PactSpecification::V4);
+    /// assert!(pact.interactions()[0].is_v4());
+    /// ```
+    pub fn new_v4<C, P>(consumer: C, provider: P) -> Self
+    where
+        C: Into<String>,
+        P: Into<String>,
+    {
+        let pact = V4Pact {
+            consumer: Consumer { name: consumer.into() },
+            provider: Provider { name: provider.into() },
+            .. V4Pact::default()
+        };
+        PactBuilder { pact: pact.boxed() }
+    }
+
     /// Add a new `Interaction` to the `Pact`.
     pub fn interaction<D, F>(&mut self, description: D, build_fn: F) -> &mut Self
     where
@@ -54,7 +87,11 @@ impl PactBuilder {
     {
         let mut interaction = InteractionBuilder::new(description.into());
         build_fn(&mut interaction);
-        self.push_interaction(&interaction.build())
+        if self.pact.spec_version() == PactSpecification::V4 {
+            self.push_interaction(&interaction.build_v4())
+        } else {
+            self.push_interaction(&interaction.build())
+        }
     }
 
     /// Directly add a pre-built `Interaction` to our `Pact`. Normally it's
@@ -71,7 +108,7 @@ impl PactBuilder {
 }
 
 impl StartMockServer for PactBuilder {
-    fn start_mock_server(&self) -> ValidatingMockServer {
-        ValidatingMockServer::start(self.build())
+    fn start_mock_server_with_config(&self, config: MockServerConfig) -> ValidatingMockServer {
+        ValidatingMockServer::start_with_config(self.build(), config)
     }
 }
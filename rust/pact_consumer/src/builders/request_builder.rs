@@ -8,7 +8,9 @@ use maplit::*;
 use regex::Regex;
 
 use pact_matching::models::*;
-use pact_matching::models::matchingrules::MatchingRules;
+use pact_matching::models::generators::{Generator, GeneratorCategory, Generators};
+use pact_matching::models::matchingrules::{MatchingRule, MatchingRules, RuleLogic};
+use pact_matching::path_template::PathTemplate;
 use pact_models::OptionalBody;
 
 use crate::prelude::*;
@@ -29,8 +31,24 @@ impl RequestBuilder {
     /// let request = RequestBuilder::default().method("POST").build();
     /// assert_eq!(request.method, "POST");
     /// ```
-    pub fn method<M: Into<String>>(&mut self, method: M) -> &mut Self {
-        self.request.method = method.into();
+    ///
+    /// Extension methods (i.e. `PURGE`) are also supported. Methods are matched
+    /// ignoring case by default, use a pattern to match them exactly.
+    ///
+    /// ```
+    /// use pact_consumer::*;
+    /// use pact_consumer::builders::RequestBuilder;
+    ///
+    /// let request = RequestBuilder::default().method(term!("^PURGE$", "PURGE")).build();
+    /// assert_eq!(request.method, "PURGE");
+    /// ```
+    pub fn method<M: Into<StringPattern>>(&mut self, method: M) -> &mut Self {
+        let method = method.into();
+        self.request.method = method.to_example();
+        method.extract_matching_rules(
+            "",
+            self.request.matching_rules.add_category("method"),
+        );
         self
     }
 
@@ -66,6 +84,38 @@ impl RequestBuilder {
         self
     }
 
+    /// Specify the request path with a path template (i.e. `/users/{id}`). The
+    /// path is matched with a regex that has a named group for each parameter,
+    /// and the example path is built from the example values of the parameters.
+    /// The mock server makes the values captured from the actual path available
+    /// to the `PathParameter` generator of the response.
+    ///
+    /// ```
+    /// use pact_consumer::builders::RequestBuilder;
+    ///
+    /// let request = RequestBuilder::default()
+    ///     .path_template("/users/{id:\\d+}/orders/{orderId}", &[("id", "100"), ("orderId", "1")])
+    ///     .build();
+    /// assert_eq!(request.path, "/users/100/orders/1");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the template is not valid, or a parameter does not have an example value.
+    pub fn path_template(&mut self, template: &str, examples: &[(&str, &str)]) -> &mut Self {
+        let template = PathTemplate::parse(template)
+            .unwrap_or_else(|err| panic!("could not parse path template: {}", err));
+        let examples = examples.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let example = template.expand(&examples)
+            .unwrap_or_else(|err| panic!("could not expand path template: {}", err));
+        self.request.path = example;
+        self.request.matching_rules.add_category("path")
+            .add_rule("", MatchingRule::Regex(template.to_regex()), &RuleLogic::And);
+        self
+    }
+
     /// Specify a query parameter. You may pass either a single value or
     /// a list of values to represent a repeated parameter.
     ///
@@ -107,6 +157,28 @@ impl RequestBuilder {
         self
     }
 
+    /// Specify a query parameter with a generator. The example value is used in
+    /// the consumer test, and is replaced with a generated value (i.e. a random
+    /// UUID) when the request is replayed against the provider.
+    ///
+    /// ```
+    /// use pact_consumer::builders::RequestBuilder;
+    /// use pact_matching::models::generators::Generator;
+    ///
+    /// RequestBuilder::default()
+    ///     .query_param_generator("requestId", "a3b1c0e5-2d4f-4a6b-9c8d-7e6f5a4b3c2d", Generator::Uuid);
+    /// ```
+    pub fn query_param_generator<K, V>(&mut self, key: K, value: V, generator: Generator) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<StringPattern>,
+    {
+        let key = key.into();
+        self.query_param(key.clone(), value);
+        self.request.generators.add_generator_with_subcategory(&GeneratorCategory::QUERY, key, generator);
+        self
+    }
+
     /// Build the specified `Request` object.
     pub fn build(&self) -> Request {
          self.request.clone()
@@ -133,6 +205,10 @@ impl HttpPartBuilder for RequestBuilder {
           &mut self.request.matching_rules,
       )
   }
+
+  fn generators_mut(&mut self) -> &mut Generators {
+    &mut self.request.generators
+  }
 }
 
 #[test]
@@ -153,6 +229,23 @@ fn path_pattern() {
     assert_requests_do_not_match!(bad, pattern);
 }
 
+#[test]
+fn path_template_pattern() {
+    let pattern = PactBuilder::new("C", "P")
+        .interaction("I", |i| {
+            i.request.path_template("/users/{id:[0-9]+}/orders/{orderId}", &[("id", "100"), ("orderId", "a1")]);
+        })
+        .build();
+    let good = PactBuilder::new("C", "P")
+        .interaction("I", |i| { i.request.path("/users/2/orders/b2"); })
+        .build();
+    let bad = PactBuilder::new("C", "P")
+        .interaction("I", |i| { i.request.path("/users/two/orders/b2"); })
+        .build();
+    assert_requests_match!(good, pattern);
+    assert_requests_do_not_match!(bad, pattern);
+}
+
 #[test]
 fn query_param_pattern() {
     let pattern = PactBuilder::new("C", "P")
@@ -215,3 +308,18 @@ fn term_does_not_require_anchors() {
     assert_requests_do_not_match!(bad1, pattern);
     assert_requests_do_not_match!(bad2, pattern);
 }
+
+#[test]
+fn query_param_generator() {
+    let pact = PactBuilder::new("C", "P")
+        .interaction("get a user", |i| {
+            i.request
+                .path("/users")
+                .query_param_generator("requestId", "1234", Generator::Uuid);
+        })
+        .build();
+    let request = pact.interactions()[0].as_request_response().unwrap().request;
+    assert_eq!(request.query, Some(hashmap!{ "requestId".to_string() => vec!["1234".to_string()] }));
+    assert_eq!(request.generators.categories.get(&GeneratorCategory::QUERY),
+        Some(&hashmap!{ "requestId".to_string() => Generator::Uuid }));
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use maplit::*;
 
 use pact_matching::models::*;
+use pact_matching::models::generators::Generators;
 use pact_matching::models::matchingrules::MatchingRules;
 use pact_models::OptionalBody;
 
@@ -87,4 +88,8 @@ impl HttpPartBuilder for ResponseBuilder {
       &mut self.response.matching_rules,
     )
   }
+
+  fn generators_mut(&mut self) -> &mut Generators {
+    &mut self.response.generators
+  }
 }
@@ -214,6 +214,7 @@ pub mod util;
 pub mod prelude {
     pub use crate::builders::{HttpPartBuilder, PactBuilder};
     pub use crate::mock_server::{StartMockServer, ValidatingMockServer};
+    pub use pact_mock_server::mock_server::MockServerConfig;
     pub use crate::patterns::{EachLike, Like, Term};
     pub use crate::patterns::{JsonPattern, Pattern, StringPattern};
     pub use crate::util::strip_null_fields;
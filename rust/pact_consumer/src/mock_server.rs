@@ -1,6 +1,7 @@
 //! Support for mock HTTP servers that verify pacts.
 
 use pact_matching::models::*;
+use pact_matching::models::pact_files::PactFileLayout;
 use pact_mock_server::matching::MatchResult;
 use pact_mock_server::*;
 use std::{
@@ -10,18 +11,24 @@ use std::{
     thread,
 };
 use url::Url;
-use pact_mock_server::mock_server::{MockServerConfig, MockServerMetrics};
+use pact_mock_server::mock_server::{JournalEntry, MockServerConfig, MockServerMetrics};
 use std::sync::{Mutex, Arc};
 
 /// This trait is implemented by types which allow us to start a mock server.
 pub trait StartMockServer {
     /// Start a mock server running in a background thread.
-    fn start_mock_server(&self) -> ValidatingMockServer;
+    fn start_mock_server(&self) -> ValidatingMockServer {
+      self.start_mock_server_with_config(MockServerConfig::default())
+    }
+
+    /// Start a mock server running in a background thread, using the given configuration
+    /// (i.e. to respond to CORS pre-flight requests).
+    fn start_mock_server_with_config(&self, config: MockServerConfig) -> ValidatingMockServer;
 }
 
 impl StartMockServer for RequestResponsePact {
-  fn start_mock_server(&self) -> ValidatingMockServer {
-    ValidatingMockServer::start(self.boxed())
+  fn start_mock_server_with_config(&self, config: MockServerConfig) -> ValidatingMockServer {
+    ValidatingMockServer::start_with_config(self.boxed(), config)
   }
 }
 
@@ -46,10 +53,16 @@ impl ValidatingMockServer {
   /// Create a new mock server which handles requests as described in the
   /// pact, and runs in a background thread
   pub fn start(pact: Box<dyn Pact + Send>) -> ValidatingMockServer {
+    ValidatingMockServer::start_with_config(pact, MockServerConfig::default())
+  }
+
+  /// Create a new mock server with the given configuration which handles requests as described
+  /// in the pact, and runs in a background thread
+  pub fn start_with_config(pact: Box<dyn Pact + Send>, config: MockServerConfig) -> ValidatingMockServer {
     // Spawn new runtime in thread to prevent reactor execution context conflict
     let (pact_tx, pact_rx) = std::sync::mpsc::channel::<Box<dyn Pact + Send>>();
     pact_tx.send(pact);
-    let (mock_server, done_rx) = std::thread::spawn(|| {
+    let (mock_server, done_rx) = std::thread::spawn(move || {
       let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
@@ -57,7 +70,7 @@ impl ValidatingMockServer {
 
       let (mock_server, server_future) = runtime.block_on(async move {
         mock_server::MockServer::new("".into(), pact_rx.recv().unwrap(), ([0, 0, 0, 0], 0 as u16).into(),
-          MockServerConfig::default())
+          config)
           .await
           .unwrap()
       });
@@ -123,9 +136,9 @@ impl ValidatingMockServer {
     /// so that it can return `Err(message)` whenever needed without making the
     /// flow control in `drop` ultra-complex.
     fn drop_helper(&mut self) -> Result<(), String> {
-        // Kill the server
-        let mut ms = self.mock_server.lock().unwrap();
-        ms.shutdown()?;
+        // Kill the server. The lock is released before waiting, so any in-flight requests
+        // can complete and record their results.
+        self.mock_server.lock().unwrap().shutdown()?;
 
         if ::std::thread::panicking() {
             return Ok(());
@@ -137,15 +150,22 @@ impl ValidatingMockServer {
             .expect("mock server thread should not panic");
 
         // Look up any mismatches which occurred.
+        let ms = self.mock_server.lock().unwrap();
         let mismatches = ms.mismatches();
 
         if mismatches.is_empty() {
             // Success! Write out the generated pact file.
-            ms.write_pact(
-              &Some(
-                env::var("PACT_OUTPUT_DIR").unwrap_or_else(|_| "target/pacts".to_owned())),
-              env::var("PACT_OVERWRITE").unwrap_or_else(|_| "false".to_owned()) == "true")
-            .map_err(|err| format!("error writing pact: {}", err))?;
+            let output_dir = Some(
+              env::var("PACT_OUTPUT_DIR").unwrap_or_else(|_| "target/pacts".to_owned()));
+            let overwrite = env::var("PACT_OVERWRITE").unwrap_or_else(|_| "false".to_owned()) == "true";
+            match env::var("PACT_OUTPUT_LAYOUT") {
+              Ok(layout) => {
+                let layout = layout.parse::<PactFileLayout>()
+                  .map_err(|err| format!("error writing pact: {}", err))?;
+                ms.write_pact_with_layout(&output_dir, overwrite, layout).map(|_| ())
+              },
+              Err(_) => ms.write_pact(&output_dir, overwrite)
+            }.map_err(|err| format!("error writing pact: {}", err))?;
             Ok(())
         } else {
             // Failure. Format our errors.
@@ -182,6 +202,24 @@ impl ValidatingMockServer {
   pub fn metrics(&self) -> MockServerMetrics {
     self.mock_server.lock().unwrap().metrics.clone()
   }
+
+  /// Resets the state of the mock server, so it can be reused for another test case. The recorded
+  /// requests are cleared, so the interactions need to receive their requests again.
+  pub fn reset(&self) {
+    self.mock_server.lock().unwrap().reset()
+  }
+
+  /// Returns the journal of the requests that matched interactions, in the order they were
+  /// received. The journal is only recorded if `record_journal` is set in the config.
+  pub fn journal(&self) -> Vec<JournalEntry> {
+    self.mock_server.lock().unwrap().journal()
+  }
+
+  /// Verifies that the interactions with the given descriptions received their first requests
+  /// in the given order. The journal needs to be enabled with `record_journal` in the config.
+  pub fn verify_order(&self, descriptions: &[&str]) -> Result<(), String> {
+    self.mock_server.lock().unwrap().verify_order(descriptions)
+  }
 }
 
 /// Either panic with `msg`, or if we're already in the middle of a panic,
@@ -1,5 +1,6 @@
 //! Our `JsonPattern` type and supporting code.
 
+use pact_matching::models::generators::Generator;
 use pact_matching::models::matchingrules::MatchingRuleCategory;
 use std::borrow::Cow;
 use std::collections::HashMap as Map;
@@ -53,6 +54,22 @@ impl JsonPattern {
     {
         JsonPattern::Pattern(Box::new(pattern))
     }
+
+    /// The matching rules of this pattern when it is used as a body, for
+    /// checking the rules a pattern produces in tests.
+    pub fn matching_rules(&self) -> MatchingRuleCategory {
+        let mut rules = MatchingRuleCategory::empty("body");
+        self.extract_matching_rules("$", &mut rules);
+        rules
+    }
+
+    /// The generators of this pattern when it is used as a body, keyed by
+    /// their path.
+    pub fn generators(&self) -> Map<String, Generator> {
+        let mut generators = Map::new();
+        self.extract_generators("$", &mut generators);
+        generators
+    }
 }
 
 impl Pattern for JsonPattern {
@@ -92,6 +109,27 @@ impl Pattern for JsonPattern {
             }
         }
     }
+
+    fn extract_generators(&self, path: &str, generators_out: &mut Map<String, Generator>) {
+        match *self {
+            JsonPattern::Json(_) => {}
+            JsonPattern::Array(ref arr) => {
+                for (i, val) in arr.iter().enumerate() {
+                    let val_path = format!("{}[{}]", path, i);
+                    val.extract_generators(&val_path, generators_out);
+                }
+            }
+            JsonPattern::Object(ref obj) => {
+                for (key, val) in obj {
+                    let val_path = format!("{}{}", path, obj_key_for_path(key));
+                    val.extract_generators(&val_path, generators_out);
+                }
+            }
+            JsonPattern::Pattern(ref pattern) => {
+                pattern.extract_generators(path, generators_out);
+            }
+        }
+    }
 }
 
 #[test]
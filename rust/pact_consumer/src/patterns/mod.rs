@@ -1,7 +1,9 @@
 //! JSON "patterns", which can be used to either generate JSON documents or
 //! match them.
 
+use pact_matching::models::generators::Generator;
 use pact_matching::models::matchingrules::MatchingRuleCategory;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
 #[macro_use]
@@ -51,4 +53,9 @@ pub trait Pattern: Debug {
     /// [ruby]:
     /// https://github.com/pact-foundation/pact-support/blob/master/lib/pact/matching_rules/extract.rb
     fn extract_matching_rules(&self, path: &str, rules_out: &mut MatchingRuleCategory);
+
+    /// Extract the generators from this `Matchable`, and insert them into
+    /// `generators_out` keyed by their path, using `path` as the base path.
+    /// Patterns without generators do not need to implement this.
+    fn extract_generators(&self, _path: &str, _generators_out: &mut HashMap<String, Generator>) {}
 }
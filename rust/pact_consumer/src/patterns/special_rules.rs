@@ -1,7 +1,9 @@
 //! Special matching rules, including `Like`, `Term`, etc.
 
+use pact_matching::models::generators::Generator;
 use pact_matching::models::matchingrules::{MatchingRule, MatchingRuleCategory, RuleLogic};
 use regex::Regex;
+use std::collections::HashMap;
 use std::iter::repeat;
 use std::marker::PhantomData;
 
@@ -23,12 +25,21 @@ macro_rules! impl_from_for_pattern {
 #[derive(Debug)]
 pub struct Like<Nested: Pattern> {
     example: Nested,
+    generator: Option<Generator>,
 }
 
 impl<Nested: Pattern> Like<Nested> {
     /// Match all values which have the same type as `example`.
     pub fn new<E: Into<Nested>>(example: E) -> Self {
-        Like { example: example.into() }
+        Like { example: example.into(), generator: None }
+    }
+
+    /// Use this after `new` to replace the example with a generated value
+    /// when the pact is used (i.e. a random integer in the response from the
+    /// mock server).
+    pub fn with_generator(mut self, generator: Generator) -> Self {
+        self.generator = Some(generator);
+        self
     }
 }
 
@@ -43,6 +54,15 @@ impl<Nested: Pattern> Pattern for Like<Nested> {
         rules_out.add_rule(&path.to_string(), MatchingRule::Type, &RuleLogic::And);
         self.example.extract_matching_rules(path, rules_out);
     }
+
+    fn extract_generators(&self, path: &str, generators_out: &mut HashMap<String, Generator>) {
+        match &self.generator {
+            Some(generator) => {
+                generators_out.insert(path.to_string(), generator.clone());
+            },
+            None => self.example.extract_generators(path, generators_out)
+        }
+    }
 }
 
 impl_from_for_pattern!(Like<JsonPattern>, JsonPattern);
@@ -85,13 +105,59 @@ fn like_into() {
 /// # }
 /// ```
 ///
+/// A generator can be given after the pattern, to replace the example with a
+/// generated value when the pact is used:
+///
+/// ```
+/// use pact_consumer::*;
+/// use pact_matching::models::generators::Generator;
+///
+/// # fn main() {
+/// let pattern = json_pattern!({
+///   "id": like!(10, generator = Generator::RandomInt(1, 100)),
+/// });
+/// assert_eq!(pattern.generators().get("$.id"), Some(&Generator::RandomInt(1, 100)));
+/// # }
+/// ```
+///
 /// If you're building `StringPattern` values, you'll need to call
 /// `Like::new` manually instead.
 #[macro_export]
 macro_rules! like {
-    ($($json_pattern:tt)+) => {
-        $crate::patterns::Like::new(json_pattern!($($json_pattern)+))
-    }
+    ($($token:tt)+) => { $crate::like_helper!(@parse [] $($token)+) };
+}
+
+// A hidden macro which does the hard work of expanding `like!`, in the same
+// way as `each_like_helper!`.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! like_helper {
+    // We made it all the way to the end of our tokens without seeing a
+    // top-level comma.
+    (@parse [$($found:tt)*] ) => {
+        $crate::like_helper!(@expand [$($found)*] [])
+    };
+
+    // We saw a top-level comma, so we're done parsing the JSON pattern.
+    (@parse [$($found:tt)*] , $($rest:tt)* ) => {
+        $crate::like_helper!(@expand [$($found)*] [$($rest)*])
+    };
+
+    // We have some other token, so add it to what we've found and continue.
+    (@parse [$($found:tt)*] $next:tt $($rest:tt)* ) => {
+        $crate::like_helper!(@parse [$($found)* $next] $($rest)*)
+    };
+
+    // We're done parsing, and we didn't find `generator`.
+    (@expand [$($pattern:tt)*] []) => {
+        $crate::patterns::Like::new(json_pattern!($($pattern)*))
+    };
+
+    // We're done parsing, and we did find `generator`.
+    (@expand [$($pattern:tt)*] [generator = $generator:expr]) => {
+        $crate::patterns::Like::new(json_pattern!($($pattern)*))
+            .with_generator($generator)
+    };
 }
 
 /// Match an array with the specified "shape".
@@ -144,6 +210,10 @@ impl Pattern for EachLike {
             rules_out,
         );
     }
+
+    fn extract_generators(&self, path: &str, generators_out: &mut HashMap<String, Generator>) {
+        self.example_element.extract_generators(&format!("{}[*]", path), generators_out);
+    }
 }
 
 #[test]
@@ -273,6 +343,8 @@ pub struct Term<Nested: Pattern> {
     /// `Nested` type somewhere. We can do that using the zero-length
     /// `PhantomData` type.
     phantom: PhantomData<Nested>,
+    /// Generator to replace the example with when the pact is used.
+    generator: Option<Generator>,
 }
 
 impl<Nested: Pattern> Term<Nested> {
@@ -283,8 +355,16 @@ impl<Nested: Pattern> Term<Nested> {
             example: example.into(),
             regex,
             phantom: PhantomData,
+            generator: None,
         }
     }
+
+    /// Use this after `new` to replace the example with a generated value
+    /// when the pact is used.
+    pub fn with_generator(mut self, generator: Generator) -> Self {
+        self.generator = Some(generator);
+        self
+    }
 }
 
 impl<Nested> Pattern for Term<Nested>
@@ -305,6 +385,12 @@ where
             &RuleLogic::And
         );
     }
+
+    fn extract_generators(&self, path: &str, generators_out: &mut HashMap<String, Generator>) {
+        if let Some(generator) = &self.generator {
+            generators_out.insert(path.to_string(), generator.clone());
+        }
+    }
 }
 
 impl_from_for_pattern!(Term<JsonPattern>, JsonPattern);
@@ -360,11 +446,57 @@ pub fn build_regex<S: AsRef<str>>(regex_str: S) -> Regex {
 /// });
 /// # }
 /// ```
+///
+/// A generator can be given after the example, i.e. to generate values that
+/// match the regular expression with `Generator::Regex`:
+///
+/// ```
+/// use pact_consumer::*;
+/// use pact_matching::models::generators::Generator;
+///
+/// # fn main() {
+/// json_pattern!({
+///   "code": term!("^[0-9]{4}$", "1234", generator = Generator::Regex("^[0-9]{4}$".to_string()))
+/// });
+/// # }
+/// ```
 #[macro_export]
 macro_rules! term {
     ($regex:expr, $example:expr) => {
         {
             $crate::patterns::Term::new($crate::patterns::build_regex($regex), $example)
         }
-    }
+    };
+    ($regex:expr, $example:expr, generator = $generator:expr) => {
+        {
+            $crate::patterns::Term::new($crate::patterns::build_regex($regex), $example)
+                .with_generator($generator)
+        }
+    };
+}
+
+#[test]
+fn like_and_term_macros_with_generators() {
+    use maplit::*;
+    use pact_matching::s;
+    use serde_json::*;
+
+    let pattern = json_pattern!({
+        "id": like!(10, generator = Generator::RandomInt(1, 100)),
+        "name": like!("Fred"),
+        "code": term!("^[0-9]{4}$", "1234", generator = Generator::Regex("^[0-9]{4}$".to_string()))
+    });
+    assert_eq!(pattern.to_example(), json!({ "id": 10, "name": "Fred", "code": "1234" }));
+    assert_eq!(pattern.matching_rules().to_v2_json(), hashmap!(
+        s!("$.body.id") => json!({ "match": "type" }),
+        s!("$.body.name") => json!({ "match": "type" }),
+        s!("$.body.code") => json!({ "match": "regex", "regex": "^[0-9]{4}$" })
+    ));
+    assert_eq!(pattern.generators(), hashmap!(
+        s!("$.id") => Generator::RandomInt(1, 100),
+        s!("$.code") => Generator::Regex("^[0-9]{4}$".to_string())
+    ));
+
+    let objects: Like<JsonPattern> = like!({ "a": 1, "b": 2 });
+    assert_eq!(objects.to_example(), json!({ "a": 1, "b": 2 }));
 }
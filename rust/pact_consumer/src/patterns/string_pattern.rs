@@ -1,7 +1,9 @@
 //! Support for patterns which match only strings, not JSON.
 
+use pact_matching::models::generators::Generator;
 use pact_matching::models::matchingrules::MatchingRuleCategory;
 use std::borrow::Cow;
+use std::collections::HashMap;
 
 use super::Pattern;
 
@@ -42,6 +44,12 @@ impl Pattern for StringPattern {
             }
         }
     }
+
+    fn extract_generators(&self, path: &str, generators_out: &mut HashMap<String, Generator>) {
+        if let StringPattern::Pattern(ref p) = *self {
+            p.extract_generators(path, generators_out);
+        }
+    }
 }
 
 #[test]
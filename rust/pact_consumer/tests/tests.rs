@@ -9,10 +9,11 @@ use expectest::expect;
 use expectest::prelude::*;
 use reqwest::Client;
 
-use pact_consumer::{json_pattern, json_pattern_internal};
+use pact_consumer::{each_like, each_like_helper, json_pattern, json_pattern_internal, like};
 use pact_consumer::prelude::*;
 use pact_matching::models::ReadWritePact;
 use pact_matching::models::RequestResponsePact;
+use pact_mock_server::matching::MatchResult;
 
 /// This is supposed to be a doctest in lib.rs, but it's breaking there, so
 /// we have an executable copy here.
@@ -134,3 +135,82 @@ async fn duplicate_interactions() {
   let written_pact = RequestResponsePact::read_pact(path.as_path()).unwrap();
   expect!(written_pact.interactions.len()).to(be_equal_to(1));
 }
+
+#[tokio::test]
+async fn mock_server_responds_to_cors_preflight_requests_when_enabled() {
+  let _ = env_logger::builder().is_test(true).try_init();
+
+  let mock_service = PactBuilder::new("browser consumer", "cors provider")
+    .interaction("a post request", |i| {
+      i.request.post().path("/items");
+      i.response.status(201);
+    })
+    .start_mock_server_with_config(MockServerConfig { cors_preflight: true, .. MockServerConfig::default() });
+
+  let response = Client::new()
+    .request(reqwest::Method::OPTIONS, mock_service.path("/items"))
+    .header("Origin", "http://localhost:3000")
+    .header("Access-Control-Request-Method", "POST")
+    .send()
+    .await
+    .unwrap();
+  expect!(response.status().as_u16()).to(be_equal_to(204));
+  expect!(response.headers().get("access-control-allow-origin").unwrap().to_str().unwrap())
+    .to(be_equal_to("http://localhost:3000"));
+
+  let response = Client::new()
+    .post(mock_service.path("/items"))
+    .send()
+    .await
+    .unwrap();
+  expect!(response.status().as_u16()).to(be_equal_to(201));
+  expect!(mock_service.status().is_empty()).to(be_true());
+}
+
+#[test]
+#[should_panic(expected = "received unexpected request")]
+fn mock_server_reports_unexpected_options_requests_when_cors_is_not_enabled() {
+  let _ = env_logger::builder().is_test(true).try_init();
+
+  let mock_service = PactBuilder::new("browser consumer", "cors provider")
+    .interaction("a get request", |i| {
+      i.request.path("/items");
+    })
+    .start_mock_server();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.request(reqwest::Method::OPTIONS, mock_service.path("/items")).send().unwrap();
+  expect!(response.status().as_u16()).to(be_equal_to(500));
+  let _ = client.get(mock_service.path("/items")).send();
+
+  let unexpected_options_request = mock_service.status().iter().any(|result| matches!(result,
+    MatchResult::RequestNotFound(request) if request.method == "OPTIONS" && request.path == "/items"));
+  expect!(unexpected_options_request).to(be_true());
+}
+
+#[tokio::test]
+async fn mock_server_serves_the_interactions_of_a_v4_pact() {
+  let _ = env_logger::builder().is_test(true).try_init();
+
+  let mock_service = PactBuilder::new_v4("v4 consumer", "v4 provider")
+    .interaction("a request for the quotes", |i| {
+      i.request.path("/quotes");
+      i.response
+        .header("Content-Type", "application/json")
+        .json_body(json_pattern!({
+          "quotes": each_like!({ "quote": like!("Eureka!"), "by": like!("Archimedes") })
+        }));
+    })
+    .start_mock_server();
+
+  let response = Client::new()
+    .get(mock_service.path("/quotes"))
+    .send()
+    .await
+    .unwrap();
+  expect!(response.status().as_u16()).to(be_equal_to(200));
+  expect!(response.json::<serde_json::Value>().await.unwrap()).to(be_equal_to(serde_json::json!({
+    "quotes": [ { "quote": "Eureka!", "by": "Archimedes" } ]
+  })));
+  expect!(mock_service.status().is_empty()).to(be_true());
+}
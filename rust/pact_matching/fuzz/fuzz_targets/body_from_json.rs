@@ -0,0 +1,14 @@
+#![no_main]
+use std::collections::HashMap;
+
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+use pact_matching::fuzzing::body_from_json;
+
+fuzz_target!(|input: (&str, Option<HashMap<String, Vec<String>>>)| {
+  let (body, headers) = input;
+  if let Ok(json) = serde_json::from_str::<Value>(body) {
+    let _ = body_from_json(&json, "body", &headers);
+  }
+});
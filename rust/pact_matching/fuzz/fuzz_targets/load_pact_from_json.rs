@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+use pact_matching::models::load_pact_from_json;
+
+fuzz_target!(|data: &[u8]| {
+  if let Ok(json) = serde_json::from_slice::<Value>(data) {
+    let _ = load_pact_from_json("fuzz", &json);
+  }
+});
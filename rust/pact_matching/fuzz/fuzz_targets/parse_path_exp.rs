@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use pact_matching::fuzzing::parse_path_exp;
+
+fuzz_target!(|path: &str| {
+  let _ = parse_path_exp(path);
+});
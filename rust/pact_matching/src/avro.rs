@@ -0,0 +1,696 @@
+//! Support for Avro encoded contents (i.e. the messages of Kafka consumers).
+//!
+//! Avro contents can only be decoded with the schema they were written with. The schema can
+//! either be given directly, or fetched from a Confluent schema registry using the ID in the
+//! Confluent wire format (a zero byte followed by the 4 byte schema ID) of the contents.
+//! Registering an `AvroPlugin` (see the `plugins` module) allows Avro contents to be matched field
+//! by field, by decoding both the expected and actual records to JSON and then applying the JSON
+//! matching rules. `plugin_from_pact` creates a plugin with the schema or schema registry URL from
+//! the pact metadata.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use log::*;
+use maplit::hashmap;
+use serde_json::{json, Map, Number, Value};
+
+use pact_models::content_types::ContentType;
+use pact_models::OptionalBody;
+
+use crate::{DiffConfig, MatchingContext, Mismatch};
+use crate::models::generators::{apply_body_generators, Generator, GeneratorTestMode};
+use crate::models::http_utils::{fetch_json_from_url, TlsConfig};
+use crate::models::Pact;
+use crate::models::matchingrules::MatchingRuleCategory;
+use crate::plugins::{ContentPlugin, PluginMetadata};
+
+/// Content types of Avro encoded contents
+pub const AVRO_CONTENT_TYPES: [&str; 3] = ["avro/binary", "application/avro", "application/vnd.apache.avro+binary"];
+
+/// Key of the pact metadata with the Avro schema of the pact
+pub const AVRO_METADATA_KEY: &str = "avro";
+
+/// Type of an Avro value
+#[derive(Debug, Clone, PartialEq)]
+pub enum AvroType {
+  /// No value
+  Null,
+  /// Boolean
+  Boolean,
+  /// 32 bit signed integer
+  Int,
+  /// 64 bit signed integer
+  Long,
+  /// 32 bit floating point number
+  Float,
+  /// 64 bit floating point number
+  Double,
+  /// Sequence of bytes (Base64 encoded in the JSON form)
+  Bytes,
+  /// UTF-8 string
+  String,
+  /// Record with the fully qualified name and the fields
+  Record(String, Vec<(String, AvroType)>),
+  /// Enum with the fully qualified name and the symbols
+  Enum(String, Vec<String>),
+  /// Array of items
+  Array(Box<AvroType>),
+  /// Map with string keys
+  Map(Box<AvroType>),
+  /// Union of the types
+  Union(Vec<AvroType>),
+  /// Fixed number of bytes with the fully qualified name (Base64 encoded in the JSON form)
+  Fixed(String, usize),
+  /// Reference to a named type defined earlier in the schema
+  Named(String)
+}
+
+/// Avro schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvroSchema {
+  /// Type of the values written with the schema
+  pub root: AvroType,
+  /// Named types (records, enums and fixed) defined in the schema, keyed by fully qualified name
+  pub named_types: BTreeMap<String, AvroType>
+}
+
+impl AvroSchema {
+  /// Parses the JSON form of an Avro schema
+  pub fn parse(schema: &str) -> anyhow::Result<AvroSchema> {
+    let json: Value = serde_json::from_str(schema).context("Avro schema is not valid JSON")?;
+    AvroSchema::from_json(&json)
+  }
+
+  /// Loads the Avro schema from its JSON form
+  pub fn from_json(json: &Value) -> anyhow::Result<AvroSchema> {
+    let mut named_types = BTreeMap::new();
+    let root = parse_type(json, "", &mut named_types)?;
+    Ok(AvroSchema { root, named_types })
+  }
+
+  /// Decodes the Avro encoded value to its JSON form. Records are decoded to JSON objects, and
+  /// union values to the value of the branch.
+  pub fn decode(&self, data: &[u8]) -> anyhow::Result<Value> {
+    let mut reader = AvroReader { data, pos: 0 };
+    let value = self.decode_value(&self.root, &mut reader)?;
+    if reader.pos < data.len() {
+      return Err(anyhow!("There are {} unexpected bytes after the Avro value", data.len() - reader.pos));
+    }
+    Ok(value)
+  }
+
+  /// Encodes the JSON form of the value
+  pub fn encode(&self, json: &Value) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = vec![];
+    self.encode_value(&self.root, json, &mut buffer)?;
+    Ok(buffer)
+  }
+
+  fn resolve<'a>(&'a self, avro_type: &'a AvroType) -> anyhow::Result<&'a AvroType> {
+    match avro_type {
+      AvroType::Named(name) => self.named_types.get(name)
+        .ok_or_else(|| anyhow!("Type '{}' is not defined in the schema", name)),
+      _ => Ok(avro_type)
+    }
+  }
+
+  fn decode_value(&self, avro_type: &AvroType, reader: &mut AvroReader) -> anyhow::Result<Value> {
+    Ok(match self.resolve(avro_type)? {
+      AvroType::Null => Value::Null,
+      AvroType::Boolean => json!(reader.read_byte()? != 0),
+      AvroType::Int => json!(reader.read_long()? as i32),
+      AvroType::Long => json!(reader.read_long()?),
+      AvroType::Float => {
+        let mut bytes = [0_u8; 4];
+        bytes.copy_from_slice(reader.read_slice(4)?);
+        json_float(f32::from_le_bytes(bytes) as f64)
+      }
+      AvroType::Double => {
+        let mut bytes = [0_u8; 8];
+        bytes.copy_from_slice(reader.read_slice(8)?);
+        json_float(f64::from_le_bytes(bytes))
+      }
+      AvroType::Bytes => json!(base64::encode(reader.read_bytes()?)),
+      AvroType::String => json!(String::from_utf8(reader.read_bytes()?.to_vec())?),
+      AvroType::Record(_, fields) => {
+        let mut record = Map::new();
+        for (name, field_type) in fields {
+          record.insert(name.clone(), self.decode_value(field_type, reader)?);
+        }
+        Value::Object(record)
+      }
+      AvroType::Enum(name, symbols) => {
+        let index = reader.read_long()?;
+        let symbol = symbols.get(index as usize)
+          .ok_or_else(|| anyhow!("{} is not a valid index for enum '{}'", index, name))?;
+        json!(symbol)
+      }
+      AvroType::Array(items) => {
+        let mut values = vec![];
+        while let Some(count) = reader.read_block_count()? {
+          for _ in 0..count {
+            values.push(self.decode_value(items, reader)?);
+          }
+        }
+        Value::Array(values)
+      }
+      AvroType::Map(values) => {
+        let mut map = Map::new();
+        while let Some(count) = reader.read_block_count()? {
+          for _ in 0..count {
+            let key = String::from_utf8(reader.read_bytes()?.to_vec())?;
+            map.insert(key, self.decode_value(values, reader)?);
+          }
+        }
+        Value::Object(map)
+      }
+      AvroType::Union(types) => {
+        let index = reader.read_long()?;
+        let branch = types.get(index as usize)
+          .ok_or_else(|| anyhow!("{} is not a valid index for a union of {} types", index, types.len()))?;
+        self.decode_value(branch, reader)?
+      }
+      AvroType::Fixed(_, size) => json!(base64::encode(reader.read_slice(*size)?)),
+      AvroType::Named(name) => return Err(anyhow!("Type '{}' is not defined in the schema", name))
+    })
+  }
+
+  fn encode_value(&self, avro_type: &AvroType, value: &Value, buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+    let avro_type = self.resolve(avro_type)?;
+    let invalid = || anyhow!("{} is not a valid value for an Avro {:?} value", value, avro_type);
+    match avro_type {
+      AvroType::Null => if !value.is_null() {
+        return Err(invalid());
+      },
+      AvroType::Boolean => buffer.push(value.as_bool().ok_or_else(invalid)? as u8),
+      AvroType::Int | AvroType::Long => write_long(value.as_i64().ok_or_else(invalid)?, buffer),
+      AvroType::Float => buffer.extend(&(value.as_f64().ok_or_else(invalid)? as f32).to_le_bytes()),
+      AvroType::Double => buffer.extend(&value.as_f64().ok_or_else(invalid)?.to_le_bytes()),
+      AvroType::Bytes => write_bytes(&base64::decode(value.as_str().ok_or_else(invalid)?)?, buffer),
+      AvroType::String => write_bytes(value.as_str().ok_or_else(invalid)?.as_bytes(), buffer),
+      AvroType::Record(name, fields) => {
+        let record = value.as_object().ok_or_else(invalid)?;
+        if let Some(field) = record.keys().find(|key| !fields.iter().any(|(name, _)| name == *key)) {
+          return Err(anyhow!("Record '{}' does not have a field '{}'", name, field));
+        }
+        for (field, field_type) in fields {
+          self.encode_value(field_type, record.get(field).unwrap_or(&Value::Null), buffer)
+            .with_context(|| format!("Failed to encode field '{}' of record '{}'", field, name))?;
+        }
+      }
+      AvroType::Enum(_, symbols) => {
+        let symbol = value.as_str().ok_or_else(invalid)?;
+        let index = symbols.iter().position(|s| s == symbol).ok_or_else(invalid)?;
+        write_long(index as i64, buffer);
+      }
+      AvroType::Array(items) => {
+        let values = value.as_array().ok_or_else(invalid)?;
+        if !values.is_empty() {
+          write_long(values.len() as i64, buffer);
+          for value in values {
+            self.encode_value(items, value, buffer)?;
+          }
+        }
+        buffer.push(0);
+      }
+      AvroType::Map(values) => {
+        let entries = value.as_object().ok_or_else(invalid)?;
+        if !entries.is_empty() {
+          write_long(entries.len() as i64, buffer);
+          for (key, value) in entries {
+            write_bytes(key.as_bytes(), buffer);
+            self.encode_value(values, value, buffer)?;
+          }
+        }
+        buffer.push(0);
+      }
+      AvroType::Union(types) => {
+        let (index, encoded) = types.iter().enumerate()
+          .find_map(|(index, branch)| {
+            let mut encoded = vec![];
+            self.encode_value(branch, value, &mut encoded).ok().map(|_| (index, encoded))
+          })
+          .ok_or_else(invalid)?;
+        write_long(index as i64, buffer);
+        buffer.extend(encoded);
+      }
+      AvroType::Fixed(_, size) => {
+        let bytes = base64::decode(value.as_str().ok_or_else(invalid)?)?;
+        if bytes.len() != *size {
+          return Err(invalid());
+        }
+        buffer.extend(bytes);
+      }
+      AvroType::Named(name) => return Err(anyhow!("Type '{}' is not defined in the schema", name))
+    }
+    Ok(())
+  }
+}
+
+fn qualified_name(json: &Value, namespace: &str) -> anyhow::Result<(String, String)> {
+  let name = json.get("name").and_then(|v| v.as_str())
+    .ok_or_else(|| anyhow!("Named Avro types must have a name, got {}", json))?;
+  let namespace = json.get("namespace").and_then(|v| v.as_str()).unwrap_or(namespace);
+  if name.contains('.') {
+    let namespace = name.rsplit_once('.').map(|(ns, _)| ns).unwrap_or_default();
+    Ok((name.to_string(), namespace.to_string()))
+  } else if namespace.is_empty() {
+    Ok((name.to_string(), String::default()))
+  } else {
+    Ok((format!("{}.{}", namespace, name), namespace.to_string()))
+  }
+}
+
+fn parse_type(json: &Value, namespace: &str, named_types: &mut BTreeMap<String, AvroType>) -> anyhow::Result<AvroType> {
+  match json {
+    Value::String(name) => match name.as_str() {
+      "null" => Ok(AvroType::Null),
+      "boolean" => Ok(AvroType::Boolean),
+      "int" => Ok(AvroType::Int),
+      "long" => Ok(AvroType::Long),
+      "float" => Ok(AvroType::Float),
+      "double" => Ok(AvroType::Double),
+      "bytes" => Ok(AvroType::Bytes),
+      "string" => Ok(AvroType::String),
+      _ => {
+        let qualified = if name.contains('.') || namespace.is_empty() {
+          name.clone()
+        } else {
+          format!("{}.{}", namespace, name)
+        };
+        [qualified, name.clone()].iter()
+          .find(|name| named_types.contains_key(*name))
+          .map(|name| AvroType::Named(name.clone()))
+          .ok_or_else(|| anyhow!("Type '{}' is not defined in the schema", name))
+      }
+    },
+    Value::Array(types) => Ok(AvroType::Union(types.iter()
+      .map(|t| parse_type(t, namespace, named_types))
+      .collect::<anyhow::Result<_>>()?)),
+    Value::Object(attributes) => {
+      let type_name = attributes.get("type")
+        .ok_or_else(|| anyhow!("Avro type does not have a 'type' attribute, got {}", json))?;
+      match type_name.as_str() {
+        Some("record") | Some("error") => {
+          let (name, namespace) = qualified_name(json, namespace)?;
+          // Records can refer to themselves, so the name must be defined before the fields
+          named_types.insert(name.clone(), AvroType::Record(name.clone(), vec![]));
+          let fields = attributes.get("fields").and_then(|v| v.as_array())
+            .ok_or_else(|| anyhow!("Record '{}' does not have any fields", name))?;
+          let mut record_fields = vec![];
+          for field in fields {
+            let field_name = field.get("name").and_then(|v| v.as_str())
+              .ok_or_else(|| anyhow!("Field of record '{}' does not have a name", name))?;
+            let field_type = field.get("type")
+              .ok_or_else(|| anyhow!("Field '{}' of record '{}' does not have a type", field_name, name))?;
+            record_fields.push((field_name.to_string(), parse_type(field_type, &namespace, named_types)?));
+          }
+          let record = AvroType::Record(name.clone(), record_fields);
+          named_types.insert(name, record.clone());
+          Ok(record)
+        }
+        Some("enum") => {
+          let (name, _) = qualified_name(json, namespace)?;
+          let symbols = attributes.get("symbols").and_then(|v| v.as_array())
+            .ok_or_else(|| anyhow!("Enum '{}' does not have any symbols", name))?
+            .iter().map(|s| s.as_str().unwrap_or_default().to_string()).collect();
+          let avro_enum = AvroType::Enum(name.clone(), symbols);
+          named_types.insert(name, avro_enum.clone());
+          Ok(avro_enum)
+        }
+        Some("fixed") => {
+          let (name, _) = qualified_name(json, namespace)?;
+          let size = attributes.get("size").and_then(|v| v.as_u64())
+            .ok_or_else(|| anyhow!("Fixed type '{}' does not have a size", name))?;
+          let fixed = AvroType::Fixed(name.clone(), size as usize);
+          named_types.insert(name, fixed.clone());
+          Ok(fixed)
+        }
+        Some("array") => {
+          let items = attributes.get("items")
+            .ok_or_else(|| anyhow!("Array type does not have an 'items' attribute"))?;
+          Ok(AvroType::Array(Box::new(parse_type(items, namespace, named_types)?)))
+        }
+        Some("map") => {
+          let values = attributes.get("values")
+            .ok_or_else(|| anyhow!("Map type does not have a 'values' attribute"))?;
+          Ok(AvroType::Map(Box::new(parse_type(values, namespace, named_types)?)))
+        }
+        // Logical types (i.e. dates and decimals) are decoded as the underlying type
+        _ => parse_type(type_name, namespace, named_types)
+      }
+    },
+    _ => Err(anyhow!("{} is not a valid Avro type", json))
+  }
+}
+
+fn json_float(value: f64) -> Value {
+  Number::from_f64(value).map(Value::Number).unwrap_or_else(|| json!(value.to_string()))
+}
+
+fn write_long(value: i64, buffer: &mut Vec<u8>) {
+  let mut value = ((value << 1) ^ (value >> 63)) as u64;
+  while value >= 0x80 {
+    buffer.push((value as u8 & 0x7F) | 0x80);
+    value >>= 7;
+  }
+  buffer.push(value as u8);
+}
+
+fn write_bytes(bytes: &[u8], buffer: &mut Vec<u8>) {
+  write_long(bytes.len() as i64, buffer);
+  buffer.extend_from_slice(bytes);
+}
+
+struct AvroReader<'a> {
+  data: &'a [u8],
+  pos: usize
+}
+
+impl <'a> AvroReader<'a> {
+  fn read_byte(&mut self) -> anyhow::Result<u8> {
+    let byte = *self.data.get(self.pos).ok_or_else(|| anyhow!("Unexpected end of Avro value"))?;
+    self.pos += 1;
+    Ok(byte)
+  }
+
+  fn read_long(&mut self) -> anyhow::Result<i64> {
+    let mut value = 0_u64;
+    for shift in (0..64).step_by(7) {
+      let byte = self.read_byte()?;
+      value |= ((byte & 0x7F) as u64) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(((value >> 1) as i64) ^ -((value & 1) as i64));
+      }
+    }
+    Err(anyhow!("Invalid variable length integer"))
+  }
+
+  fn read_slice(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+    if self.pos + len > self.data.len() {
+      return Err(anyhow!("Unexpected end of Avro value"));
+    }
+    let slice = &self.data[self.pos..self.pos + len];
+    self.pos += len;
+    Ok(slice)
+  }
+
+  fn read_bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+    let len = self.read_long()?;
+    if len < 0 {
+      return Err(anyhow!("Invalid length {} of Avro bytes", len));
+    }
+    self.read_slice(len as usize)
+  }
+
+  /// Arrays and maps are encoded as blocks of items, ending with an empty block. A negative count
+  /// is followed by the size of the block in bytes.
+  fn read_block_count(&mut self) -> anyhow::Result<Option<usize>> {
+    match self.read_long()? {
+      0 => Ok(None),
+      count if count < 0 => {
+        self.read_long()?;
+        Ok(Some(count.unsigned_abs() as usize))
+      }
+      count => Ok(Some(count as usize))
+    }
+  }
+}
+
+/// Splits contents in the Confluent wire format into the schema ID and the Avro encoded value
+fn split_confluent_wire_format(data: &[u8]) -> anyhow::Result<(u32, &[u8])> {
+  if data.len() < 5 || data[0] != 0 {
+    return Err(anyhow!("Contents are not in the Confluent wire format (a zero byte followed by the schema ID)"));
+  }
+  Ok((u32::from_be_bytes([data[1], data[2], data[3], data[4]]), &data[5..]))
+}
+
+/// Plugin that matches and generates Avro encoded contents, using either a fixed writer schema,
+/// or the schemas from a Confluent schema registry
+#[derive(Debug)]
+pub struct AvroPlugin {
+  /// Schema used to decode and encode the contents, if not using a schema registry
+  pub schema: Option<AvroSchema>,
+  /// URL of the Confluent schema registry. Contents must then be in the Confluent wire format.
+  pub schema_registry_url: Option<String>,
+  registry_schemas: Mutex<HashMap<u32, AvroSchema>>
+}
+
+impl AvroPlugin {
+  /// Creates a plugin that uses the writer schema
+  pub fn new(schema: AvroSchema) -> AvroPlugin {
+    AvroPlugin { schema: Some(schema), schema_registry_url: None, registry_schemas: Mutex::new(hashmap!{}) }
+  }
+
+  /// Creates a plugin that fetches the writer schemas from a Confluent schema registry
+  pub fn with_schema_registry(url: &str) -> AvroPlugin {
+    AvroPlugin {
+      schema: None,
+      schema_registry_url: Some(url.trim_end_matches('/').to_string()),
+      registry_schemas: Mutex::new(hashmap!{})
+    }
+  }
+
+  fn registry_schema(&self, url: &str, id: u32) -> anyhow::Result<AvroSchema> {
+    if let Some(schema) = self.registry_schemas.lock().unwrap().get(&id) {
+      return Ok(schema.clone());
+    }
+
+    let schema_url = format!("{}/schemas/ids/{}", url, id);
+    debug!("Fetching Avro schema {} from the schema registry '{}'", id, schema_url);
+    // The blocking HTTP client can not be used from within an async runtime (i.e. the mock
+    // server), so the schema is fetched on a separate thread
+    let fetch_url = schema_url.clone();
+    let (_, json) = std::thread::spawn(move || fetch_json_from_url(&fetch_url, &None, &TlsConfig::default()))
+      .join()
+      .map_err(|_| anyhow!("Failed to fetch the Avro schema from '{}'", schema_url))??;
+    let schema = json.get("schema").and_then(|v| v.as_str())
+      .ok_or_else(|| anyhow!("Schema registry response from '{}' does not have a schema", schema_url))?;
+    let schema = AvroSchema::parse(schema)?;
+    self.registry_schemas.lock().unwrap().insert(id, schema.clone());
+    Ok(schema)
+  }
+
+  /// Decodes the contents, returning the schema used to decode them (and the schema ID if they are
+  /// in the Confluent wire format)
+  fn decode(&self, body: &OptionalBody) -> anyhow::Result<(Value, AvroSchema, Option<u32>)> {
+    let data = body.value().unwrap_or_default();
+    match (&self.schema_registry_url, &self.schema) {
+      (Some(url), _) => {
+        let (id, value) = split_confluent_wire_format(&data)?;
+        let schema = self.registry_schema(url, id)?;
+        Ok((schema.decode(value)?, schema, Some(id)))
+      }
+      (None, Some(schema)) => Ok((schema.decode(&data)?, schema.clone(), None)),
+      (None, None) => Err(anyhow!("Avro plugin does not have a schema or schema registry"))
+    }
+  }
+}
+
+impl ContentPlugin for AvroPlugin {
+  fn metadata(&self) -> PluginMetadata {
+    PluginMetadata {
+      name: "avro".to_string(),
+      version: env!("CARGO_PKG_VERSION").to_string()
+    }
+  }
+
+  fn content_types(&self) -> Vec<ContentType> {
+    AVRO_CONTENT_TYPES.iter().filter_map(|ct| ContentType::parse(*ct).ok()).collect()
+  }
+
+  fn match_contents(
+    &self,
+    expected: &OptionalBody,
+    actual: &OptionalBody,
+    rules: &MatchingRuleCategory,
+    allow_unexpected_keys: bool
+  ) -> anyhow::Result<Vec<Mismatch>> {
+    let (expected_json, _, _) = self.decode(expected).context("Failed to decode the expected Avro contents")?;
+    let actual_json = match self.decode(actual) {
+      Ok((json, _, _)) => json,
+      Err(err) => return Ok(vec![Mismatch::BodyMismatch {
+        path: "$".to_string(),
+        expected: expected.value(),
+        actual: actual.value(),
+        mismatch: format!("Failed to decode the actual Avro contents: {}", err)
+      }])
+    };
+    debug!("Comparing Avro contents: {} => {}", expected_json, actual_json);
+    let config = if allow_unexpected_keys { DiffConfig::AllowUnexpectedKeys } else { DiffConfig::NoUnexpectedKeys };
+    let context = MatchingContext::new(config, rules);
+    Ok(crate::json::compare(&["$"], &expected_json, &actual_json, &context).err().unwrap_or_default())
+  }
+
+  fn generate_contents(
+    &self,
+    body: &OptionalBody,
+    generators: &HashMap<String, Generator>,
+    mode: &GeneratorTestMode
+  ) -> anyhow::Result<OptionalBody> {
+    let (json, schema, id) = self.decode(body)?;
+    let json_body = OptionalBody::Present(Bytes::from(json.to_string()), ContentType::parse("application/json").ok());
+    let generated = apply_body_generators(mode, &json_body, json_body.content_type(), &hashmap!{}, generators);
+    let generated: Value = serde_json::from_slice(&generated.value().unwrap_or_default())?;
+    let mut contents = vec![];
+    if let Some(id) = id {
+      contents.push(0);
+      contents.extend(&id.to_be_bytes());
+    }
+    contents.extend(schema.encode(&generated)?);
+    Ok(OptionalBody::Present(Bytes::from(contents), body.content_type()))
+  }
+}
+
+/// Creates an Avro plugin with the schema from the pact metadata, so that Avro contents of the
+/// pact can be matched field by field with `plugins::with_plugins`. The metadata can either have the writer schema
+/// (`"metadata": { "avro": { "schema": { "type": "record", ... } } }`), or the URL of a Confluent
+/// schema registry (`"metadata": { "avro": { "schemaRegistryUrl": "http://localhost:8081" } }`).
+/// Returns None if the pact does not have an Avro schema.
+pub fn plugin_from_pact(pact: &dyn Pact) -> anyhow::Result<Option<AvroPlugin>> {
+  let metadata = pact.metadata();
+  let avro = match metadata.get(AVRO_METADATA_KEY) {
+    Some(avro) => avro,
+    None => return Ok(None)
+  };
+  let plugin = match (avro.get("schemaRegistryUrl"), avro.get("schema")) {
+    (Some(url), _) => AvroPlugin::with_schema_registry(url),
+    (None, Some(schema)) => AvroPlugin::new(AvroSchema::parse(schema)
+      .context("The Avro schema in the pact metadata is not valid")?),
+    (None, None) => return Err(anyhow!("The Avro metadata of the pact must have a 'schema' or 'schemaRegistryUrl'"))
+  };
+  debug!("Loaded the Avro schema from the pact metadata");
+  Ok(Some(plugin))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{Read, Write};
+  use std::net::TcpListener;
+
+  use expectest::prelude::*;
+
+  use crate::models::v4::V4Pact;
+
+  use super::*;
+
+  const ORDER_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "Order",
+    "namespace": "com.example",
+    "fields": [
+      { "name": "id", "type": "long" },
+      { "name": "customer", "type": ["null", "string"] },
+      { "name": "status", "type": { "type": "enum", "name": "Status", "symbols": ["NEW", "SHIPPED"] } },
+      { "name": "items", "type": { "type": "array", "items": {
+        "type": "record",
+        "name": "Item",
+        "fields": [
+          { "name": "sku", "type": "string" },
+          { "name": "price", "type": "double" }
+        ]
+      } } },
+      { "name": "attributes", "type": { "type": "map", "values": "string" } },
+      { "name": "next", "type": ["null", "Order"] }
+    ]
+  }"#;
+
+  fn order() -> Value {
+    json!({
+      "id": 100,
+      "customer": "Fred",
+      "status": "SHIPPED",
+      "items": [ { "sku": "A-1", "price": 9.5 } ],
+      "attributes": { "gift": "yes" },
+      "next": null
+    })
+  }
+
+  #[test]
+  fn parses_the_named_types_of_the_schema() {
+    let schema = AvroSchema::parse(ORDER_SCHEMA).unwrap();
+    expect!(schema.named_types.keys().cloned().collect::<Vec<_>>()).to(be_equal_to(vec![
+      "com.example.Item".to_string(), "com.example.Order".to_string(), "com.example.Status".to_string()
+    ]));
+    expect!(AvroSchema::parse(r#"{ "type": "record", "name": "A", "fields": [ { "name": "b", "type": "B" } ] }"#)).to(be_err());
+  }
+
+  #[test]
+  fn encodes_and_decodes_records() {
+    let schema = AvroSchema::parse(ORDER_SCHEMA).unwrap();
+    let encoded = schema.encode(&order()).unwrap();
+    expect!(schema.decode(&encoded)).to(be_ok().value(order()));
+  }
+
+  #[test]
+  fn decodes_known_bytes() {
+    let schema = AvroSchema::parse(r#"{ "type": "record", "name": "A", "fields": [
+      { "name": "a", "type": "long" }, { "name": "b", "type": "string" } ] }"#).unwrap();
+    expect!(schema.decode(&[0x36, 0x06, 0x66, 0x6f, 0x6f])).to(be_ok().value(json!({ "a": 27, "b": "foo" })));
+    expect!(schema.decode(&[0x36, 0x06, 0x66])).to(be_err());
+    expect!(schema.decode(&[0x36, 0x00, 0x01])).to(be_err());
+  }
+
+  fn order_body(schema: &AvroSchema, json: Value) -> OptionalBody {
+    OptionalBody::Present(Bytes::from(schema.encode(&json).unwrap()), ContentType::parse("avro/binary").ok())
+  }
+
+  #[test]
+  fn plugin_matches_the_records_field_by_field() {
+    let schema = AvroSchema::parse(ORDER_SCHEMA).unwrap();
+    let plugin = AvroPlugin::new(schema.clone());
+    let expected = order_body(&schema, order());
+    let mut actual_order = order();
+    actual_order["status"] = json!("NEW");
+    let actual = order_body(&schema, actual_order);
+
+    let result = plugin.match_contents(&expected, &expected, &MatchingRuleCategory::empty("body"), false);
+    expect!(result.unwrap().iter()).to(be_empty());
+    let result = plugin.match_contents(&expected, &actual, &MatchingRuleCategory::empty("body"), false).unwrap();
+    expect!(result.iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$.status -> Expected 'SHIPPED' to be equal to 'NEW'".to_string()
+    ]));
+  }
+
+  #[test]
+  fn plugin_fetches_the_schema_from_the_schema_registry() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+      // The schema is cached, so only one request should be made
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut request = [0_u8; 1024];
+      let len = stream.read(&mut request).unwrap();
+      let body = json!({ "schema": ORDER_SCHEMA }).to_string();
+      write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(), body).unwrap();
+      String::from_utf8_lossy(&request[..len]).to_string()
+    });
+
+    let schema = AvroSchema::parse(ORDER_SCHEMA).unwrap();
+    let plugin = AvroPlugin::with_schema_registry(&format!("http://127.0.0.1:{}/", port));
+    let mut contents = vec![0, 0, 0, 0, 42];
+    contents.extend(schema.encode(&order()).unwrap());
+    let body = OptionalBody::Present(Bytes::from(contents), ContentType::parse("avro/binary").ok());
+
+    let result = plugin.match_contents(&body, &body, &MatchingRuleCategory::empty("body"), false);
+    expect!(result.unwrap().iter()).to(be_empty());
+    expect!(server.join().unwrap().starts_with("GET /schemas/ids/42 ")).to(be_true());
+  }
+
+  #[test]
+  fn creates_a_plugin_with_the_schema_from_the_pact_metadata() {
+    let mut pact = V4Pact::default();
+    expect!(plugin_from_pact(&pact).unwrap().is_none()).to(be_true());
+
+    pact.metadata.insert(AVRO_METADATA_KEY.to_string(), json!({ "schema": serde_json::from_str::<Value>(ORDER_SCHEMA).unwrap() }));
+    let plugin = plugin_from_pact(&pact).unwrap();
+    expect!(plugin.map(|p| p.metadata().name)).to(be_some().value("avro"));
+
+    pact.metadata.insert(AVRO_METADATA_KEY.to_string(), json!({ "schema": "not a schema" }));
+    expect!(plugin_from_pact(&pact)).to(be_err());
+  }
+}
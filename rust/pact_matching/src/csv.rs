@@ -0,0 +1,387 @@
+//! Matching of CSV (`text/csv`) bodies. Bodies are parsed into rows of cells (RFC 4180), and the
+//! cells are compared with any matching rules defined for their column. Columns can be referred to
+//! by index (i.e. `$[*][2]`), or by name if the content type has a header row
+//! (`text/csv; header=present`, i.e. `$[*].price`).
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use log::*;
+
+use pact_models::OptionalBody;
+
+use crate::matchers::{match_values, Matches};
+use crate::MatchingContext;
+use crate::Mismatch;
+use crate::DiffConfig;
+use crate::models::HttpPart;
+use crate::models::matchingrules::MatchingRule;
+
+/// Parses the CSV data into rows of cells. Quoted cells can contain commas, line breaks and
+/// escaped (doubled) quotes. An empty last line is ignored.
+pub fn parse_csv(data: &str) -> anyhow::Result<Vec<Vec<String>>> {
+  let mut rows = vec![];
+  let mut row = vec![];
+  let mut cell = String::new();
+  let mut chars = data.chars().peekable();
+  let mut in_quotes = false;
+  let mut quoted = false;
+  while let Some(ch) = chars.next() {
+    if in_quotes {
+      if ch == '"' {
+        if chars.peek() == Some(&'"') {
+          chars.next();
+          cell.push('"');
+        } else {
+          in_quotes = false;
+        }
+      } else {
+        cell.push(ch);
+      }
+    } else {
+      match ch {
+        '"' if cell.is_empty() && !quoted => {
+          in_quotes = true;
+          quoted = true;
+        },
+        '"' => return Err(anyhow!("Unexpected quote in unquoted cell at row {}, column {}", rows.len(), row.len())),
+        ',' => {
+          row.push(std::mem::take(&mut cell));
+          quoted = false;
+        },
+        '\r' if chars.peek() == Some(&'\n') => (),
+        '\n' => {
+          row.push(std::mem::take(&mut cell));
+          rows.push(std::mem::take(&mut row));
+          quoted = false;
+        },
+        _ if quoted => return Err(anyhow!("Unexpected character '{}' after quoted cell at row {}, column {}", ch, rows.len(), row.len())),
+        _ => cell.push(ch)
+      }
+    }
+  }
+  if in_quotes {
+    return Err(anyhow!("Unterminated quoted cell at row {}, column {}", rows.len(), row.len()));
+  }
+  if !cell.is_empty() || quoted || !row.is_empty() {
+    row.push(cell);
+    rows.push(row);
+  }
+  Ok(rows)
+}
+
+/// If the content type of the part declares a header row (`header=present`)
+fn has_header(part: &dyn HttpPart) -> bool {
+  part.content_type()
+    .and_then(|content_type| content_type.attributes.get("header").cloned())
+    .map(|header| header.eq_ignore_ascii_case("present"))
+    .unwrap_or(false)
+}
+
+fn path_to_string(row: usize, column: &str) -> String {
+  if column.chars().all(|ch| ch.is_ascii_digit()) {
+    format!("$[{}][{}]", row, column)
+  } else if column.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-') {
+    format!("$[{}].{}", row, column)
+  } else {
+    format!("$[{}]['{}']", row, column)
+  }
+}
+
+/// Returns the minimum and maximum number of rows from any rules defined for the rows
+fn row_bounds(context: &MatchingContext) -> Option<(Option<usize>, Option<usize>)> {
+  if context.type_matcher_defined(&["$"]) {
+    let rules = context.select_best_matcher(&["$"]).map(|rules| rules.rules).unwrap_or_default();
+    Some(rules.iter().fold((None, None), |(min, max), rule| match rule {
+      MatchingRule::MinType(m) => (Some(*m), max),
+      MatchingRule::MaxType(m) => (min, Some(*m)),
+      MatchingRule::MinMaxType(mn, mx) => (Some(*mn), Some(*mx)),
+      _ => (min, max)
+    }))
+  } else {
+    None
+  }
+}
+
+struct Columns<'a> {
+  expected_header: Option<&'a Vec<String>>,
+  actual_header: Option<&'a Vec<String>>
+}
+
+fn compare_cell(row: usize, index: usize, name: Option<&str>, expected: &str, actual: &str,
+  context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+  let row_index = row.to_string();
+  let column_index = index.to_string();
+  let by_index = vec!["$", row_index.as_str(), column_index.as_str()];
+  let path = match name {
+    Some(name) => {
+      let by_name = vec!["$", row_index.as_str(), name];
+      if context.matcher_is_defined(&by_name) { by_name } else { by_index }
+    },
+    None => by_index
+  };
+  let result = if context.matcher_is_defined(&path) {
+    match_values(&path, context, &expected.to_string(), &actual.to_string())
+  } else {
+    expected.to_string().matches(&actual.to_string(), &MatchingRule::Equality).map_err(|err| vec![err])
+  };
+  debug!("Comparing '{}' to '{}' at path '{}' -> {:?}", expected, actual, path.iter().join("."), result);
+  result.map_err(|messages| messages.iter().map(|message| Mismatch::BodyMismatch {
+    path: path_to_string(row, name.unwrap_or(column_index.as_str())),
+    expected: Some(expected.to_string().into()),
+    actual: Some(actual.to_string().into()),
+    mismatch: message.clone()
+  }).collect())
+}
+
+fn compare_row(row: usize, expected: &[String], actual: &[String], columns: &Columns,
+  context: &MatchingContext) -> Vec<Mismatch> {
+  let mut mismatches = vec![];
+  match (columns.expected_header, columns.actual_header) {
+    (Some(expected_header), Some(actual_header)) => {
+      for (index, name) in expected_header.iter().enumerate() {
+        let expected_value = expected.get(index).map(|v| v.as_str()).unwrap_or_default();
+        match actual_header.iter().position(|header| header == name) {
+          Some(actual_index) => {
+            let actual_value = actual.get(actual_index).map(|v| v.as_str()).unwrap_or_default();
+            if let Err(err) = compare_cell(row, index, Some(name), expected_value, actual_value, context) {
+              mismatches.extend(err);
+            }
+          },
+          None => mismatches.push(Mismatch::BodyMismatch {
+            path: path_to_string(row, name),
+            expected: Some(expected_value.to_string().into()),
+            actual: None,
+            mismatch: format!("Expected column '{}' but was missing", name)
+          })
+        }
+      }
+    },
+    _ => {
+      if expected.len() > actual.len() || (expected.len() < actual.len() && context.config == DiffConfig::NoUnexpectedKeys) {
+        mismatches.push(Mismatch::BodyMismatch {
+          path: format!("$[{}]", row),
+          expected: Some(expected.join(",").into()),
+          actual: Some(actual.join(",").into()),
+          mismatch: format!("Expected {} column(s) but received {} at row {}", expected.len(), actual.len(), row)
+        });
+      }
+      for (index, (expected_value, actual_value)) in expected.iter().zip(actual.iter()).enumerate() {
+        if let Err(err) = compare_cell(row, index, None, expected_value, actual_value, context) {
+          mismatches.extend(err);
+        }
+      }
+    }
+  }
+  mismatches
+}
+
+/// Matches CSV (`text/csv`) bodies
+pub fn match_csv(expected: &dyn HttpPart, actual: &dyn HttpPart, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+  let (expected_body, actual_body) = match (expected.body(), actual.body()) {
+    (OptionalBody::Present(expected_body, _), OptionalBody::Present(actual_body, _)) => (expected_body, actual_body),
+    (OptionalBody::Present(_, _), _) => return Err(vec![Mismatch::BodyMismatch {
+      path: "$".into(),
+      expected: expected.body().value(),
+      actual: None,
+      mismatch: format!("Expected a CSV body {} but was missing", expected.body())
+    }]),
+    _ => return Ok(())
+  };
+
+  let expected_rows = parse_csv(&String::from_utf8_lossy(expected_body));
+  let actual_rows = parse_csv(&String::from_utf8_lossy(actual_body));
+  let (mut expected_rows, mut actual_rows) = match (expected_rows, actual_rows) {
+    (Ok(expected_rows), Ok(actual_rows)) => (expected_rows, actual_rows),
+    (expected_rows, actual_rows) => {
+      let mut mismatches = vec![];
+      if let Err(err) = expected_rows {
+        mismatches.push(Mismatch::BodyMismatch {
+          path: "$".to_string(),
+          expected: expected.body().value(),
+          actual: actual.body().value(),
+          mismatch: format!("Failed to parse the expected body: '{}'", err)
+        });
+      }
+      if let Err(err) = actual_rows {
+        mismatches.push(Mismatch::BodyMismatch {
+          path: "$".to_string(),
+          expected: expected.body().value(),
+          actual: actual.body().value(),
+          mismatch: format!("Failed to parse the actual body: '{}'", err)
+        });
+      }
+      return Err(mismatches);
+    }
+  };
+
+  let (expected_header, actual_header) = if has_header(expected) {
+    let expected_header = if expected_rows.is_empty() { vec![] } else { expected_rows.remove(0) };
+    let actual_header = if actual_rows.is_empty() { vec![] } else { actual_rows.remove(0) };
+    (Some(expected_header), Some(actual_header))
+  } else {
+    (None, None)
+  };
+
+  let mut mismatches = vec![];
+  if let (Some(expected_header), Some(actual_header)) = (&expected_header, &actual_header) {
+    let unexpected = actual_header.iter().filter(|name| !expected_header.contains(name)).collect_vec();
+    if !unexpected.is_empty() && context.config == DiffConfig::NoUnexpectedKeys {
+      mismatches.push(Mismatch::BodyMismatch {
+        path: "$".to_string(),
+        expected: Some(expected_header.join(",").into()),
+        actual: Some(actual_header.join(",").into()),
+        mismatch: format!("Received unexpected column(s): {}", unexpected.iter().join(", "))
+      });
+    }
+  }
+  let columns = Columns {
+    expected_header: expected_header.as_ref(),
+    actual_header: actual_header.as_ref()
+  };
+
+  match row_bounds(context) {
+    Some((min, max)) => {
+      if let Some(min) = min {
+        if actual_rows.len() < min {
+          mismatches.push(Mismatch::BodyMismatch {
+            path: "$".to_string(),
+            expected: expected.body().value(),
+            actual: actual.body().value(),
+            mismatch: format!("Expected at least {} row(s) but received {}", min, actual_rows.len())
+          });
+        }
+      }
+      if let Some(max) = max {
+        if actual_rows.len() > max {
+          mismatches.push(Mismatch::BodyMismatch {
+            path: "$".to_string(),
+            expected: expected.body().value(),
+            actual: actual.body().value(),
+            mismatch: format!("Expected at most {} row(s) but received {}", max, actual_rows.len())
+          });
+        }
+      }
+      if let Some(expected_row) = expected_rows.first() {
+        for (row, actual_row) in actual_rows.iter().enumerate() {
+          mismatches.extend(compare_row(row, expected_row, actual_row, &columns, context));
+        }
+      }
+    },
+    None => {
+      if expected_rows.len() != actual_rows.len() {
+        mismatches.push(Mismatch::BodyMismatch {
+          path: "$".to_string(),
+          expected: expected.body().value(),
+          actual: actual.body().value(),
+          mismatch: format!("Expected {} row(s) but received {}", expected_rows.len(), actual_rows.len())
+        });
+      }
+      for (row, (expected_row, actual_row)) in expected_rows.iter().zip(actual_rows.iter()).enumerate() {
+        mismatches.extend(compare_row(row, expected_row, actual_row, &columns, context));
+      }
+    }
+  }
+
+  if mismatches.is_empty() {
+    Ok(())
+  } else {
+    Err(mismatches)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_models::content_types::ContentType;
+
+  use crate::models::matchingrules::MatchingRule;
+  use crate::models::Request;
+
+  use super::*;
+
+  fn request(body: &str, content_type: &str) -> Request {
+    Request {
+      body: OptionalBody::Present(body.to_string().into(), ContentType::parse(content_type).ok()),
+      .. Request::default()
+    }
+  }
+
+  fn descriptions(result: Result<(), Vec<Mismatch>>) -> Vec<String> {
+    result.unwrap_err().iter().map(|m| m.description()).collect()
+  }
+
+  #[test]
+  fn parse_csv_test() {
+    expect!(parse_csv("a,b,c\r\n1,\"2,3\",\"say \"\"hi\"\"\"\n")).to(be_ok().value(vec![
+      vec!["a".to_string(), "b".to_string(), "c".to_string()],
+      vec!["1".to_string(), "2,3".to_string(), "say \"hi\"".to_string()]
+    ]));
+    expect!(parse_csv("a,\"multi\nline\"")).to(be_ok().value(vec![
+      vec!["a".to_string(), "multi\nline".to_string()]
+    ]));
+    expect!(parse_csv("a,,\n")).to(be_ok().value(vec![
+      vec!["a".to_string(), "".to_string(), "".to_string()]
+    ]));
+    expect!(parse_csv("")).to(be_ok().value(Vec::<Vec<String>>::new()));
+    expect!(parse_csv("a,\"b")).to(be_err());
+    expect!(parse_csv("a,b\"c")).to(be_err());
+  }
+
+  #[test]
+  fn match_csv_compares_the_cells_by_index() {
+    let context = MatchingContext::with_config(DiffConfig::NoUnexpectedKeys);
+    let expected = request("1,Apple,1.50\n2,Pear,2.00\n", "text/csv");
+    expect!(match_csv(&expected, &request("1,Apple,1.50\r\n2,Pear,2.00", "text/csv"), &context)).to(be_ok());
+    expect!(descriptions(match_csv(&expected, &request("1,Apple,1.50\n2,Plum,2.00\n3,Fig,1.00\n", "text/csv"), &context)))
+      .to(be_equal_to(vec![
+        "$ -> Expected 2 row(s) but received 3".to_string(),
+        "$[1][1] -> Expected 'Pear' to be equal to 'Plum'".to_string()
+      ]));
+    expect!(descriptions(match_csv(&expected, &request("1,Apple\n2,Pear,2.00\n", "text/csv"), &context)))
+      .to(be_equal_to(vec![
+        "$[0] -> Expected 3 column(s) but received 2 at row 0".to_string()
+      ]));
+  }
+
+  #[test]
+  fn match_csv_applies_column_rules_by_index_and_name() {
+    let context = MatchingContext::new(DiffConfig::NoUnexpectedKeys, &matchingrules!{
+      "body" => {
+        "$" => [ MatchingRule::MinType(1) ],
+        "$[*][0]" => [ MatchingRule::Integer ],
+        "$[*].name" => [ MatchingRule::Regex("^[A-Z][a-z]+$".to_string()) ],
+        "$[*]['unit price']" => [ MatchingRule::Decimal ]
+      }
+    }.rules_for_category("body").unwrap());
+    let expected = request("id,name,unit price\n1,Apple,1.50\n", "text/csv; header=present");
+    let actual = request("name,id,unit price\nPear,2,2.25\nPlum,3,0.75\n", "text/csv; header=present");
+    expect!(match_csv(&expected, &actual, &context)).to(be_ok());
+
+    let actual = request("id,name,unit price\n2,pear,two\n", "text/csv; header=present");
+    expect!(descriptions(match_csv(&expected, &actual, &context))).to(be_equal_to(vec![
+      "$[0].name -> Expected 'pear' to match '^[A-Z][a-z]+$'".to_string(),
+      "$[0]['unit price'] -> Expected 'two' to match a number".to_string()
+    ]));
+
+    let actual = request("id,name,unit price\n", "text/csv; header=present");
+    expect!(descriptions(match_csv(&expected, &actual, &context))).to(be_equal_to(vec![
+      "$ -> Expected at least 1 row(s) but received 0".to_string()
+    ]));
+  }
+
+  #[test]
+  fn match_csv_checks_the_header_row() {
+    let context = MatchingContext::with_config(DiffConfig::NoUnexpectedKeys);
+    let expected = request("id,name\n1,Apple\n", "text/csv; header=present");
+    let actual = request("id,description\n1,Apple\n", "text/csv; header=present");
+    expect!(descriptions(match_csv(&expected, &actual, &context))).to(be_equal_to(vec![
+      "$ -> Received unexpected column(s): description".to_string(),
+      "$[0].name -> Expected column 'name' but was missing".to_string()
+    ]));
+    expect!(descriptions(match_csv(&expected, &actual, &MatchingContext::with_config(DiffConfig::AllowUnexpectedKeys))))
+      .to(be_equal_to(vec![
+        "$[0].name -> Expected column 'name' but was missing".to_string()
+      ]));
+  }
+}
@@ -0,0 +1,125 @@
+//! The `diff` module provides functions to render the differences between the expected and actual
+//! values of a mismatch, either as a unified diff or as ANSI coloured output for a terminal
+
+use ansi_term::Colour::*;
+use difference::{Changeset, Difference};
+use serde_json::Value;
+
+/// Number of unchanged lines to include before and after each change in a unified diff
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineChange<'a> {
+  Same(&'a str),
+  Add(&'a str),
+  Rem(&'a str)
+}
+
+fn line_changes<'a>(changeset: &'a Changeset) -> Vec<LineChange<'a>> {
+  changeset.diffs.iter().flat_map(|change| {
+    match change {
+      Difference::Same(ref x) => x.split('\n').map(LineChange::Same).collect::<Vec<_>>(),
+      Difference::Add(ref x) => x.split('\n').map(LineChange::Add).collect(),
+      Difference::Rem(ref x) => x.split('\n').map(LineChange::Rem).collect()
+    }
+  }).collect()
+}
+
+/// Formats a value of a mismatch for display in a diff. JSON values are pretty printed so that the
+/// diff is line based, anything else is returned as is.
+pub fn format_value(value: &[u8]) -> String {
+  match serde_json::from_slice::<Value>(value) {
+    Ok(json @ Value::Object(_)) | Ok(json @ Value::Array(_)) =>
+      serde_json::to_string_pretty(&json).unwrap_or_default(),
+    _ => String::from_utf8_lossy(value).to_string()
+  }
+}
+
+/// Returns a unified diff of the expected versus the actual value. The expected value is the
+/// original (`---`) and the actual value the new one (`+++`), with each hunk including up to three
+/// unchanged lines of context. Returns an empty string if the values are the same.
+pub fn unified_diff(expected: &str, actual: &str) -> String {
+  let changeset = Changeset::new(expected, actual, "\n");
+  let changes = line_changes(&changeset);
+  if changes.iter().all(|change| matches!(change, LineChange::Same(_))) {
+    return String::default();
+  }
+
+  let mut output = "--- expected\n+++ actual\n".to_string();
+  let changed = changes.iter().enumerate()
+    .filter(|(_, change)| !matches!(change, LineChange::Same(_)))
+    .map(|(index, _)| index)
+    .collect::<Vec<usize>>();
+
+  // group the changed lines into hunks where the context lines overlap
+  let mut hunks: Vec<(usize, usize)> = vec![];
+  for index in changed {
+    let start = index.saturating_sub(CONTEXT_LINES);
+    let end = (index + CONTEXT_LINES + 1).min(changes.len());
+    match hunks.last_mut() {
+      Some(hunk) if start <= hunk.1 => hunk.1 = end,
+      _ => hunks.push((start, end))
+    }
+  }
+
+  for (start, end) in hunks {
+    let expected_start = changes[..start].iter().filter(|c| !matches!(c, LineChange::Add(_))).count();
+    let actual_start = changes[..start].iter().filter(|c| !matches!(c, LineChange::Rem(_))).count();
+    let hunk = &changes[start..end];
+    let expected_len = hunk.iter().filter(|c| !matches!(c, LineChange::Add(_))).count();
+    let actual_len = hunk.iter().filter(|c| !matches!(c, LineChange::Rem(_))).count();
+    output.push_str(&format!("@@ -{},{} +{},{} @@\n", expected_start + 1, expected_len,
+      actual_start + 1, actual_len));
+    for change in hunk {
+      match change {
+        LineChange::Same(x) => output.push_str(&format!(" {}\n", x)),
+        LineChange::Add(x) => output.push_str(&format!("+{}\n", x)),
+        LineChange::Rem(x) => output.push_str(&format!("-{}\n", x))
+      }
+    }
+  }
+
+  output
+}
+
+/// Returns the differences between the expected and actual values for display in a terminal. Each
+/// line is prefixed with the indent, with removed lines in red and added lines in green.
+pub fn ansi_diff(expected: &str, actual: &str, indent: &str) -> String {
+  let changeset = Changeset::new(expected, actual, "\n");
+  let mut output = String::new();
+  for change in line_changes(&changeset) {
+    match change {
+      LineChange::Same(x) => output.push_str(&format!("{}{}\n", indent, x)),
+      LineChange::Add(x) => output.push_str(&Green.paint(format!("{}+{}\n", indent, x)).to_string()),
+      LineChange::Rem(x) => output.push_str(&Red.paint(format!("{}-{}\n", indent, x)).to_string())
+    }
+  }
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  #[test]
+  fn unified_diff_returns_an_empty_string_when_there_are_no_differences() {
+    expect!(unified_diff("a\nb", "a\nb")).to(be_equal_to(""));
+  }
+
+  #[test]
+  fn unified_diff_includes_the_context_around_the_changes() {
+    let expected = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14";
+    let actual = "1\n2\n3\n4\n5\nsix\n7\n8\n9\n10\n11\n12\n13\n14\n15";
+    expect!(unified_diff(expected, actual)).to(be_equal_to(
+      "--- expected\n+++ actual\n@@ -3,7 +3,7 @@\n 3\n 4\n 5\n-6\n+six\n 7\n 8\n 9\n@@ -12,3 +12,4 @@\n 12\n 13\n 14\n+15\n"));
+  }
+
+  #[test]
+  fn format_value_pretty_prints_json() {
+    expect!(format_value(b"{\"a\":[1]}")).to(be_equal_to("{\n  \"a\": [\n    1\n  ]\n}"));
+    expect!(format_value(b"100")).to(be_equal_to("100"));
+    expect!(format_value(b"<a/>")).to(be_equal_to("<a/>"));
+  }
+}
@@ -0,0 +1,127 @@
+//! Error type returned from the public functions for loading, writing and merging pacts
+
+use std::io;
+use std::path::PathBuf;
+
+/// Errors that can occur when loading, writing or merging pacts. Library consumers can match on
+/// the variants to handle the different kinds of failures.
+#[derive(thiserror::Error, Debug)]
+pub enum PactError {
+  /// The pact could not be parsed
+  #[error("Failed to parse the pact from '{pact_source}'{} - {message}", location_suffix(.location))]
+  ParseError {
+    /// Where the pact was loaded from (i.e. the file path or URL)
+    pact_source: String,
+    /// Location of the error in the pact, if known (i.e. `line 3, column 10` or `$.interactions[1]`)
+    location: Option<String>,
+    /// Description of the error
+    message: String
+  },
+
+  /// The specification version of the pact is not supported for the operation
+  #[error("Specification version {version} is not supported - {message}")]
+  SpecVersionError {
+    /// Specification version of the pact
+    version: String,
+    /// Description of the error
+    message: String
+  },
+
+  /// The pacts could not be merged because of conflicting interactions
+  #[error("Unable to merge pacts, as there were {} conflict(s) between the interactions. Please clean out your pact directory before running the tests.", .conflicts.len())]
+  MergeConflict {
+    /// Descriptions of the conflicts between the interactions
+    conflicts: Vec<String>
+  },
+
+  /// The pacts could not be merged because they are not compatible (i.e. they are for different
+  /// consumers and providers, or the interactions can not be converted)
+  #[error("Unable to merge pacts - {0}")]
+  MergeError(String),
+
+  /// An IO error occurred reading or writing a pact file
+  #[error("{}{err}", path_prefix(.path))]
+  IoError {
+    /// Path to the file, if known
+    path: Option<PathBuf>,
+    /// The underlying IO error
+    #[source]
+    err: io::Error
+  },
+
+  /// A pact could not be fetched over HTTP
+  #[error("Failed to fetch the pact from '{url}' - {message}")]
+  HttpError {
+    /// URL the pact was fetched from
+    url: String,
+    /// Status code of the response, if one was received
+    status: Option<u16>,
+    /// Description of the error
+    message: String
+  },
+
+  /// Any other error
+  #[error(transparent)]
+  Other(#[from] anyhow::Error)
+}
+
+fn location_suffix(location: &Option<String>) -> String {
+  location.as_ref().map(|location| format!(" at {}", location)).unwrap_or_default()
+}
+
+fn path_prefix(path: &Option<PathBuf>) -> String {
+  path.as_ref().map(|path| format!("{}: ", path.to_string_lossy())).unwrap_or_default()
+}
+
+impl PactError {
+  /// Creates a parse error from a JSON error, with the line and column of the error
+  pub fn from_json_error(pact_source: &str, err: &serde_json::Error) -> Self {
+    PactError::ParseError {
+      pact_source: pact_source.to_string(),
+      location: if err.line() > 0 { Some(format!("line {}, column {}", err.line(), err.column())) } else { None },
+      message: err.to_string()
+    }
+  }
+
+  /// Creates a parse error with no location
+  pub fn parse_error<S: Into<String>>(pact_source: &str, message: S) -> Self {
+    PactError::ParseError {
+      pact_source: pact_source.to_string(),
+      location: None,
+      message: message.into()
+    }
+  }
+
+  /// Creates an IO error for the file
+  pub fn io_error<P: Into<PathBuf>>(path: P, err: io::Error) -> Self {
+    PactError::IoError { path: Some(path.into()), err }
+  }
+}
+
+impl From<io::Error> for PactError {
+  fn from(err: io::Error) -> Self {
+    PactError::IoError { path: None, err }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  #[test]
+  fn error_messages() {
+    expect!(PactError::parse_error("pact.json", "it is not a valid pact file").to_string())
+      .to(be_equal_to("Failed to parse the pact from 'pact.json' - it is not a valid pact file"));
+    let json_err = serde_json::from_str::<serde_json::Value>("{\n  \"a\": }").unwrap_err();
+    expect!(PactError::from_json_error("pact.json", &json_err).to_string())
+      .to(be_equal_to("Failed to parse the pact from 'pact.json' at line 2, column 8 - expected value at line 2 column 8"));
+    expect!(PactError::MergeConflict { conflicts: vec!["a".to_string(), "b".to_string()] }.to_string())
+      .to(be_equal_to("Unable to merge pacts, as there were 2 conflict(s) between the interactions. Please clean out your pact directory before running the tests."));
+    expect!(PactError::io_error("/tmp/pact.json", io::Error::new(io::ErrorKind::NotFound, "not found")).to_string())
+      .to(be_equal_to("/tmp/pact.json: not found"));
+    expect!(PactError::HttpError { url: "http://localhost".to_string(), status: Some(404), message: "Request failed with status - 404 Not Found".to_string() }.to_string())
+      .to(be_equal_to("Failed to fetch the pact from 'http://localhost' - Request failed with status - 404 Not Found"));
+  }
+}
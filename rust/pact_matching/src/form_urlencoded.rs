@@ -0,0 +1,175 @@
+//! Matching of `application/x-www-form-urlencoded` bodies. Bodies are parsed into a map of
+//! parameter names to values, and the parameters are compared in the same way as query parameters:
+//! the order of the parameters is ignored, and matching rules can be applied to the values of a
+//! parameter (i.e. `$.id`) or a particular value of a repeated parameter (i.e. `$.id[1]`).
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use log::*;
+
+use pact_models::OptionalBody;
+
+use crate::DiffConfig;
+use crate::matchers::{match_values, Matches};
+use crate::MatchingContext;
+use crate::Mismatch;
+use crate::models::HttpPart;
+use crate::models::matchingrules::MatchingRule;
+use crate::models::parse_query_string;
+
+fn parse_body(body: &[u8]) -> HashMap<String, Vec<String>> {
+  parse_query_string(String::from_utf8_lossy(body).trim()).unwrap_or_default()
+}
+
+fn path_to_string(key: &str, index: Option<usize>) -> String {
+  let key = if key.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-') {
+    format!("$.{}", key)
+  } else {
+    format!("$['{}']", key)
+  };
+  match index {
+    Some(index) => format!("{}[{}]", key, index),
+    None => key
+  }
+}
+
+fn compare_value(key: &str, index: usize, expected: &str, actual: &str, context: &MatchingContext) -> Vec<Mismatch> {
+  let index_str = index.to_string();
+  let path = vec!["$", key, index_str.as_str()];
+  let result = if context.matcher_is_defined(&path) {
+    match_values(&path, context, &expected.to_string(), &actual.to_string())
+  } else {
+    expected.to_string().matches(&actual.to_string(), &MatchingRule::Equality).map_err(|err| vec![err])
+  };
+  debug!("Comparing '{}' to '{}' at path '{}' -> {:?}", expected, actual, path.iter().join("."), result);
+  result.err().unwrap_or_default().iter().map(|message| Mismatch::BodyMismatch {
+    path: path_to_string(key, Some(index)),
+    expected: Some(expected.to_string().into()),
+    actual: Some(actual.to_string().into()),
+    mismatch: message.clone()
+  }).collect()
+}
+
+fn compare_values(key: &str, expected: &[String], actual: &[String], context: &MatchingContext) -> Vec<Mismatch> {
+  let mut mismatches = vec![];
+  if context.type_matcher_defined(&["$", key]) {
+    // With a type matcher, the parameter can be repeated any number of times, with each value
+    // matching the first expected one
+    if let Some(expected_value) = expected.first() {
+      for (index, actual_value) in actual.iter().enumerate() {
+        mismatches.extend(compare_value(key, index, expected_value, actual_value, context));
+      }
+    }
+  } else {
+    if expected.len() != actual.len() {
+      mismatches.push(Mismatch::BodyMismatch {
+        path: path_to_string(key, None),
+        expected: Some(format!("{:?}", expected).into()),
+        actual: Some(format!("{:?}", actual).into()),
+        mismatch: format!("Expected form post parameter '{}' with {} value(s) but received {} value(s)",
+          key, expected.len(), actual.len())
+      });
+    }
+    for (index, (expected_value, actual_value)) in expected.iter().zip(actual.iter()).enumerate() {
+      mismatches.extend(compare_value(key, index, expected_value, actual_value, context));
+    }
+  }
+  mismatches
+}
+
+/// Matches `application/x-www-form-urlencoded` bodies
+pub fn match_form_urlencoded(expected: &dyn HttpPart, actual: &dyn HttpPart, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+  let (expected_body, actual_body) = match (expected.body(), actual.body()) {
+    (OptionalBody::Present(expected_body, _), OptionalBody::Present(actual_body, _)) =>
+      (parse_body(expected_body), parse_body(actual_body)),
+    (OptionalBody::Present(expected_body, _), _) => (parse_body(expected_body), HashMap::new()),
+    _ => return Ok(())
+  };
+
+  let mut mismatches = vec![];
+  for (key, expected_values) in expected_body.iter().sorted_by_key(|(key, _)| key.as_str()) {
+    match actual_body.get(key) {
+      Some(actual_values) => mismatches.extend(compare_values(key, expected_values, actual_values, context)),
+      None => mismatches.push(Mismatch::BodyMismatch {
+        path: path_to_string(key, None),
+        expected: Some(format!("{:?}", expected_values).into()),
+        actual: None,
+        mismatch: format!("Expected form post parameter '{}' but was missing", key)
+      })
+    }
+  }
+  if context.config == DiffConfig::NoUnexpectedKeys {
+    for (key, actual_values) in actual_body.iter().sorted_by_key(|(key, _)| key.as_str()) {
+      if !expected_body.contains_key(key) {
+        mismatches.push(Mismatch::BodyMismatch {
+          path: path_to_string(key, None),
+          expected: None,
+          actual: Some(format!("{:?}", actual_values).into()),
+          mismatch: format!("Unexpected form post parameter '{}' received", key)
+        });
+      }
+    }
+  }
+
+  if mismatches.is_empty() {
+    Ok(())
+  } else {
+    Err(mismatches)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_models::content_types::ContentType;
+
+  use crate::models::Request;
+
+  use super::*;
+
+  fn request(body: &str) -> Request {
+    Request {
+      body: OptionalBody::Present(body.to_string().into(),
+        ContentType::parse("application/x-www-form-urlencoded").ok()),
+      .. Request::default()
+    }
+  }
+
+  fn descriptions(result: Result<(), Vec<Mismatch>>) -> Vec<String> {
+    result.unwrap_err().iter().map(|m| m.description()).collect()
+  }
+
+  #[test]
+  fn match_form_urlencoded_ignores_the_order_of_the_parameters() {
+    let context = MatchingContext::with_config(DiffConfig::NoUnexpectedKeys);
+    expect!(match_form_urlencoded(&request("a=1&b=two%20words&c=3"), &request("c=3&a=1&b=two%20words\n"), &context))
+      .to(be_ok());
+    expect!(descriptions(match_form_urlencoded(&request("a=1&b=2&b=3"), &request("b=3&b=2&d=4"), &context)))
+      .to(be_equal_to(vec![
+        "$.a -> Expected form post parameter 'a' but was missing".to_string(),
+        "$.b[0] -> Expected '2' to be equal to '3'".to_string(),
+        "$.b[1] -> Expected '3' to be equal to '2'".to_string(),
+        "$.d -> Unexpected form post parameter 'd' received".to_string()
+      ]));
+    expect!(match_form_urlencoded(&request("a=1"), &request("a=1&d=4"),
+      &MatchingContext::with_config(DiffConfig::AllowUnexpectedKeys))).to(be_ok());
+  }
+
+  #[test]
+  fn match_form_urlencoded_applies_matching_rules_per_parameter() {
+    let context = MatchingContext::new(DiffConfig::NoUnexpectedKeys, &matchingrules!{
+      "body" => {
+        "$.id" => [ MatchingRule::Regex("^\\d+$".to_string()) ],
+        "$.tag" => [ MatchingRule::Type ]
+      }
+    }.rules_for_category("body").unwrap());
+    expect!(match_form_urlencoded(&request("id=100&tag=a&name=bob"), &request("name=bob&tag=x&tag=y&tag=z&id=2001"), &context))
+      .to(be_ok());
+    expect!(descriptions(match_form_urlencoded(&request("id=100&tag=a"), &request("id=abc&tag=x"), &context)))
+      .to(be_equal_to(vec![
+        "$.id[0] -> Expected 'abc' to match '^\\d+$'".to_string()
+      ]));
+  }
+}
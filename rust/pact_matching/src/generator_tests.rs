@@ -8,7 +8,10 @@ use pact_models::content_types::{JSON, TEXT};
 use pact_models::OptionalBody;
 
 use crate::models::{Request, Response};
+use crate::models::message::Message;
+use crate::models::expression_parser::DataType;
 use crate::models::generators::{ContentTypeHandler, JsonHandler};
+use crate::models::v4::message_parts::MessageContents;
 
 use super::*;
 
@@ -86,6 +89,23 @@ fn applies_query_generator_for_query_parameters_to_the_copy_of_the_request() {
   expect!(query_val).to_not(be_equal_to("a"));
 }
 
+#[test]
+fn applies_provider_state_generator_for_query_parameters_to_the_copy_of_the_request() {
+  let request = Request { query: Some(hashmap!{
+      s!("id") => vec![ s!("1"), s!("2") ]
+    }), generators: generators! {
+      "QUERY" => {
+        "id" => Generator::ProviderStateGenerator(s!("userId"), None)
+      }
+    }, .. Request::default()
+  };
+  let context = hashmap!{ "userId" => Value::String(s!("100")) };
+  let query = generate_request(&request, &GeneratorTestMode::Provider, &context).query.unwrap();
+  expect!(query.get("id").unwrap().clone()).to(be_equal_to(vec![s!("100"), s!("100")]));
+  let query = generate_request(&request, &GeneratorTestMode::Consumer, &context).query.unwrap();
+  expect!(query.get("id").unwrap().clone()).to(be_equal_to(vec![s!("1"), s!("2")]));
+}
+
 #[test]
 fn apply_generator_to_empty_body_test() {
   let generators = Generators::default();
@@ -137,6 +157,23 @@ fn applies_body_generator_to_the_copy_of_the_response() {
   expect!(&body["b"]).to(be_equal_to(&json!("B")));
 }
 
+#[test]
+fn applies_body_generator_to_the_copy_of_the_message_contents() {
+  let message = Message { contents: OptionalBody::Present("{\"a\": 100, \"b\": \"B\"}".into(), None),
+    generators: generators! {
+      "BODY" => {
+        "$.a" => Generator::RandomInt(1, 10)
+      }
+    }, .. Message::default()
+  };
+  let generated_message = generate_message(&message, &GeneratorTestMode::Consumer, &hashmap!{});
+  let body: Value = serde_json::from_str(generated_message.contents.str_value()).unwrap();
+  expect!(&body["a"]).to_not(be_equal_to(&json!(100)));
+  expect!(&body["b"]).to(be_equal_to(&json!("B")));
+  expect!(generate_message(&Message::default(), &GeneratorTestMode::Consumer, &hashmap!{}))
+    .to(be_equal_to(Message::default()));
+}
+
 #[test]
 fn does_not_change_body_if_there_are_no_generators() {
   let body = OptionalBody::Present("{\"a\": 100, \"b\": \"B\"}".into(), None);
@@ -156,6 +193,17 @@ fn applies_the_generator_to_a_json_map_entry() {
   expect!(&json_handler.value["b"]).to_not(be_equal_to(&json!("B")));
 }
 
+#[test]
+fn applies_an_anchored_regex_generator_to_a_json_map_entry() {
+  let map = json!({"a": 100, "b": "B", "c": "C"});
+  let mut json_handler = JsonHandler { value: map };
+
+  json_handler.apply_key(&s!("$.b"), &Generator::Regex(s!("^[A-Z]{3}-\\d{4}$")), &hashmap!{});
+
+  let generated = json_handler.value["b"].as_str().unwrap().to_string();
+  expect!(onig::Regex::new("^[A-Z]{3}-\\d{4}$").unwrap().is_match(&generated)).to(be_true());
+}
+
 #[test]
 fn json_generator_handles_invalid_path_expressions() {
   let map = json!({"a": 100, "b": "B", "c": "C"});
@@ -306,3 +354,92 @@ fn applies_the_generator_to_the_object_graph_with_wildcard() {
   expect!(&json_handler.value["b"]).to(be_equal_to(&json!("B")));
   expect!(&json_handler.value["c"]).to(be_equal_to(&json!("C")));
 }
+
+#[test]
+fn generates_the_same_values_for_the_same_generator_seed() {
+  let response = Response { body: OptionalBody::Present("{\"a\": 100, \"b\": \"B\", \"c\": \"C\"}".into(), None),
+    headers: Some(hashmap!{ s!("X-Request-Id") => vec![s!("1234")] }),
+    generators: generators! {
+      "HEADER" => {
+        "X-Request-Id" => Generator::Uuid
+      },
+      "BODY" => {
+        "$.a" => Generator::RandomInt(1, 1000000),
+        "$.b" => Generator::RandomString(20),
+        "$.c" => Generator::RandomHexadecimal(20)
+      }
+    }, .. Response::default()
+  };
+  let context = hashmap!{ "generatorSeed" => json!(1234) };
+
+  let generated = generate_response(&response, &GeneratorTestMode::Consumer, &context);
+  expect!(generate_response(&response, &GeneratorTestMode::Consumer, &context)).to(be_equal_to(generated.clone()));
+  expect!(generate_response(&response, &GeneratorTestMode::Consumer, &hashmap!{ "generatorSeed" => json!(4321) }))
+    .to_not(be_equal_to(generated.clone()));
+  expect!(generate_response(&response, &GeneratorTestMode::Consumer, &hashmap!{})).to_not(be_equal_to(generated));
+}
+
+#[test]
+fn applies_provider_state_generators_to_the_path_headers_and_query_of_the_request() {
+  let mut generators = generators! {
+    "HEADER" => {
+      "authorization" => Generator::ProviderStateGenerator(s!("Bearer ${token}"), None)
+    },
+    "QUERY" => {
+      "id" => Generator::ProviderStateGenerator(s!("id"), None)
+    }
+  };
+  generators.add_generator(&GeneratorCategory::PATH, Generator::ProviderStateGenerator(s!("/users/${id}"), None));
+  let request = Request {
+    path: s!("/users/100"),
+    query: Some(hashmap!{ s!("id") => vec![s!("100")] }),
+    headers: Some(hashmap!{ s!("Authorization") => vec![s!("Bearer 1234")] }),
+    generators,
+    .. Request::default()
+  };
+  let context = hashmap!{ "id" => json!(42), "token" => json!("abcd") };
+
+  let generated = generate_request(&request, &GeneratorTestMode::Provider, &context);
+  expect!(generated.path).to(be_equal_to("/users/42"));
+  expect!(generated.headers).to(be_some().value(hashmap!{ s!("Authorization") => vec![s!("Bearer abcd")] }));
+  expect!(generated.query).to(be_some().value(hashmap!{ s!("id") => vec![s!("42")] }));
+
+  let generated = generate_request(&request, &GeneratorTestMode::Consumer, &context);
+  expect!(generated.path).to(be_equal_to("/users/100"));
+}
+
+#[test]
+fn applies_metadata_generators_to_the_copy_of_the_message() {
+  let message = Message {
+    metadata: hashmap!{ s!("requestId") => s!("1234"), s!("userId") => s!("100") },
+    generators: generators! {
+      "METADATA" => {
+        "requestId" => Generator::Uuid,
+        "userId" => Generator::ProviderStateGenerator(s!("userId"), None)
+      }
+    }, .. Message::default()
+  };
+  let generated = generate_message(&message, &GeneratorTestMode::Provider, &hashmap!{ "userId" => json!(42) });
+  expect!(generated.metadata.get("requestId").unwrap().len()).to(be_equal_to(36));
+  expect!(generated.metadata.get("userId")).to(be_some().value(&s!("42")));
+}
+
+#[test]
+fn applies_body_and_metadata_generators_to_the_copy_of_the_message_contents() {
+  let contents = MessageContents {
+    contents: OptionalBody::Present("{\"id\": 100, \"name\": \"Fred\"}".into(), Some(JSON.clone())),
+    metadata: hashmap!{ s!("x-user-id") => json!(100), s!("x-request") => json!("a") },
+    generators: generators! {
+      "BODY" => {
+        "$.id" => Generator::ProviderStateGenerator(s!("userId"), Some(DataType::INTEGER))
+      },
+      "METADATA" => {
+        "x-user-id" => Generator::ProviderStateGenerator(s!("userId"), Some(DataType::INTEGER))
+      }
+    }, .. MessageContents::default()
+  };
+  let generated = generate_message_contents(&contents, &GeneratorTestMode::Provider, &hashmap!{ "userId" => json!(42) });
+  let body: Value = serde_json::from_str(generated.contents.str_value()).unwrap();
+  expect!(body).to(be_equal_to(json!({"id": 42, "name": "Fred"})));
+  expect!(generated.metadata).to(be_equal_to(hashmap!{ s!("x-user-id") => json!(42), s!("x-request") => json!("a") }));
+}
@@ -0,0 +1,368 @@
+//! Matching of GraphQL requests, either as `application/graphql` query documents or as JSON
+//! requests with a `query` attribute. Query documents are normalised before they are compared, so
+//! differences in whitespace, commas, comments and the order of arguments, input object fields and
+//! variable definitions are ignored.
+
+use anyhow::anyhow;
+use log::*;
+use serde_json::Value;
+
+use pact_models::OptionalBody;
+
+use crate::matchers::match_values;
+use crate::MatchingContext;
+use crate::Mismatch;
+use crate::models::HttpPart;
+
+/// Attributes that a JSON GraphQL request can have
+const GRAPHQL_REQUEST_ATTRIBUTES: [&str; 4] = ["query", "variables", "operationName", "extensions"];
+
+fn tokenise(query: &str) -> anyhow::Result<Vec<String>> {
+  let chars: Vec<char> = query.chars().collect();
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < chars.len() {
+    let ch = chars[i];
+    if ch.is_whitespace() || ch == ',' || ch == '\u{feff}' {
+      i += 1;
+    } else if ch == '#' {
+      while i < chars.len() && chars[i] != '\n' && chars[i] != '\r' {
+        i += 1;
+      }
+    } else if ch == '"' {
+      let start = i;
+      if chars[i..].starts_with(&['"', '"', '"']) {
+        i += 3;
+        while i < chars.len() && !chars[i..].starts_with(&['"', '"', '"']) {
+          if chars[i..].starts_with(&['\\', '"', '"', '"']) {
+            i += 1;
+          }
+          i += 1;
+        }
+        if i >= chars.len() {
+          return Err(anyhow!("Unterminated block string"));
+        }
+        i += 3;
+      } else {
+        i += 1;
+        while i < chars.len() && chars[i] != '"' {
+          if chars[i] == '\n' {
+            return Err(anyhow!("Unterminated string"));
+          }
+          if chars[i] == '\\' {
+            i += 1;
+          }
+          i += 1;
+        }
+        if i >= chars.len() {
+          return Err(anyhow!("Unterminated string"));
+        }
+        i += 1;
+      }
+      tokens.push(chars[start..i].iter().collect());
+    } else if chars[i..].starts_with(&['.', '.', '.']) {
+      tokens.push("...".to_string());
+      i += 3;
+    } else if "!$&():=@[]{}|".contains(ch) {
+      tokens.push(ch.to_string());
+      i += 1;
+    } else if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+      let start = i;
+      i += 1;
+      while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' ||
+        chars[i] == '.' || chars[i] == '+' || chars[i] == '-') {
+        i += 1;
+      }
+      tokens.push(chars[start..i].iter().collect());
+    } else {
+      return Err(anyhow!("Unexpected character '{}' in GraphQL document", ch));
+    }
+  }
+  Ok(tokens)
+}
+
+fn is_name(token: &str) -> bool {
+  token.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_')
+}
+
+fn closing(token: &str) -> Option<&'static str> {
+  match token {
+    "(" => Some(")"),
+    "{" => Some("}"),
+    "[" => Some("]"),
+    _ => None
+  }
+}
+
+struct Normaliser {
+  tokens: Vec<String>,
+  index: usize
+}
+
+impl Normaliser {
+  // Renders the tokens up to the closing token (or the end), returning the rendered parts.
+  // Arguments, variable definitions and input object fields are rendered as sorted entries.
+  fn render(&mut self, close: Option<&str>, value_context: bool) -> anyhow::Result<Vec<String>> {
+    let mut parts = vec![];
+    while self.index < self.tokens.len() {
+      let token = self.tokens[self.index].clone();
+      self.index += 1;
+      if Some(token.as_str()) == close {
+        return Ok(parts);
+      }
+      match token.as_str() {
+        "(" => parts.push(format!("({})", self.render_entries(")")?)),
+        "{" if value_context => parts.push(format!("{{{}}}", self.render_entries("}")?)),
+        "[" if value_context => parts.push(format!("[{}]", self.render(Some("]"), true)?.join(", "))),
+        "{" | "[" => {
+          let inner = self.render(closing(&token), value_context)?;
+          let close = closing(&token).unwrap_or_default();
+          if inner.is_empty() {
+            parts.push(format!("{}{}", token, close));
+          } else {
+            parts.push(format!("{} {} {}", token, join_parts(&inner), close));
+          }
+        }
+        ")" | "}" | "]" => return Err(anyhow!("Unexpected '{}' in GraphQL document", token)),
+        _ => parts.push(token)
+      }
+    }
+    match close {
+      Some(close) => Err(anyhow!("Expected '{}' before the end of the GraphQL document", close)),
+      None => Ok(parts)
+    }
+  }
+
+  // Renders a list of `name: value` entries (i.e. arguments) up to the closing token, sorted by name
+  fn render_entries(&mut self, close: &str) -> anyhow::Result<String> {
+    let mut entries: Vec<Vec<String>> = vec![];
+    loop {
+      let token = self.tokens.get(self.index).cloned()
+        .ok_or_else(|| anyhow!("Expected '{}' before the end of the GraphQL document", close))?;
+      if token == close {
+        self.index += 1;
+        break;
+      }
+      let after_dollar = self.index > 0 && self.tokens[self.index - 1] == "$";
+      let starts_entry = (is_name(&token) && !after_dollar && self.tokens.get(self.index + 1).map(|t| t == ":").unwrap_or(false)) ||
+        (token == "$" && self.tokens.get(self.index + 2).map(|t| t == ":").unwrap_or(false));
+      if starts_entry || entries.is_empty() {
+        entries.push(vec![]);
+      }
+      let part = if let Some(inner_close) = closing(&token) {
+        self.index += 1;
+        match token.as_str() {
+          "(" => format!("({})", self.render_entries(inner_close)?),
+          "{" => format!("{{{}}}", self.render_entries(inner_close)?),
+          _ => format!("[{}]", self.render(Some("]"), true)?.join(", "))
+        }
+      } else {
+        self.index += 1;
+        token
+      };
+      if let Some(entry) = entries.last_mut() {
+        entry.push(part);
+      }
+    }
+    let mut rendered: Vec<String> = entries.iter().map(|entry| join_parts(entry)).collect();
+    rendered.sort();
+    Ok(rendered.join(", "))
+  }
+}
+
+fn join_parts(parts: &[String]) -> String {
+  let mut result = String::new();
+  for part in parts {
+    let attach = result.is_empty() || result.ends_with(' ') || result.ends_with('$') || result.ends_with('@') ||
+      part == ":" || part == "!" || part.starts_with('(');
+    if !attach {
+      result.push(' ');
+    }
+    result.push_str(part);
+    if part == ":" {
+      result.push(' ');
+    }
+  }
+  result
+}
+
+/// Normalises the GraphQL query document, so that documents that only differ in formatting or the
+/// order of arguments are equal
+pub fn normalise_query(query: &str) -> anyhow::Result<String> {
+  let mut normaliser = Normaliser { tokens: tokenise(query)?, index: 0 };
+  let parts = normaliser.render(None, false)?;
+  Ok(join_parts(&parts))
+}
+
+/// If the JSON is a GraphQL request, i.e. an object with a string `query` attribute and no
+/// other attributes than those of a GraphQL request
+pub fn is_graphql_request(json: &Value) -> bool {
+  match json {
+    Value::Object(map) => map.get("query").map(|q| q.is_string()).unwrap_or(false) &&
+      map.keys().all(|key| GRAPHQL_REQUEST_ATTRIBUTES.contains(&key.as_str())),
+    _ => false
+  }
+}
+
+/// Replaces the query of the JSON GraphQL request with its normalised form. If the query can
+/// not be parsed, it is left as is.
+pub fn normalise_request(json: &Value) -> Value {
+  let mut json = json.clone();
+  if let Some(Value::String(query)) = json.get("query").cloned() {
+    match normalise_query(&query) {
+      Ok(normalised) => json["query"] = Value::String(normalised),
+      Err(err) => warn!("Failed to parse the GraphQL query, so it will not be normalised: {}", err)
+    }
+  }
+  json
+}
+
+/// Matches GraphQL query documents (`application/graphql` bodies)
+pub fn match_graphql(expected: &dyn HttpPart, actual: &dyn HttpPart, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+  let (expected_body, actual_body) = match (expected.body(), actual.body()) {
+    (OptionalBody::Present(expected_body, _), OptionalBody::Present(actual_body, _)) => (expected_body, actual_body),
+    _ => return Ok(())
+  };
+  let mut mismatches = vec![];
+  let expected_query = normalise_query(&String::from_utf8_lossy(expected_body));
+  if let Err(err) = &expected_query {
+    mismatches.push(Mismatch::BodyMismatch {
+      path: "$".to_string(),
+      expected: expected.body().value(),
+      actual: actual.body().value(),
+      mismatch: format!("Failed to parse the expected body: '{}'", err)
+    });
+  }
+  let actual_query = normalise_query(&String::from_utf8_lossy(actual_body));
+  if let Err(err) = &actual_query {
+    mismatches.push(Mismatch::BodyMismatch {
+      path: "$".to_string(),
+      expected: expected.body().value(),
+      actual: actual.body().value(),
+      mismatch: format!("Failed to parse the actual body: '{}'", err)
+    });
+  }
+  if let (Ok(expected_query), Ok(actual_query)) = (expected_query, actual_query) {
+    debug!("Comparing GraphQL query '{}' to '{}'", expected_query, actual_query);
+    let path = vec!["$"];
+    let result = if context.matcher_is_defined(&path) {
+      match_values(&path, context, &expected_query, &actual_query)
+    } else if expected_query == actual_query {
+      Ok(())
+    } else {
+      Err(vec![format!("Expected query '{}' but received '{}'", expected_query, actual_query)])
+    };
+    if let Err(messages) = result {
+      mismatches.extend(messages.iter().map(|message| Mismatch::BodyMismatch {
+        path: "$".to_string(),
+        expected: Some(expected_query.clone().into()),
+        actual: Some(actual_query.clone().into()),
+        mismatch: message.clone()
+      }));
+    }
+  }
+  if mismatches.is_empty() {
+    Ok(())
+  } else {
+    Err(mismatches)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use pact_models::content_types::ContentType;
+
+  use crate::{DiffConfig, MatchingContext};
+  use crate::models::matchingrules::{MatchingRule, MatchingRuleCategory, RuleLogic};
+  use crate::models::Request;
+
+  use super::*;
+
+  #[test]
+  fn normalise_query_ignores_whitespace_commas_and_comments() {
+    let query = r#"
+      # Fetch the hero
+      query HeroName {
+        hero {
+          name,
+          friends { name }
+        }
+      }
+    "#;
+    expect!(normalise_query(query)).to(be_ok().value("query HeroName { hero { name friends { name } } }"));
+  }
+
+  #[test]
+  fn normalise_query_sorts_arguments_and_variable_definitions() {
+    let query_a = r#"query Hero($episode: Episode = JEDI, $withFriends: Boolean!) {
+      hero(episode: $episode, first: 10) { name friends @include(if: $withFriends) { name } }
+    }"#;
+    let query_b = r#"query Hero($withFriends: Boolean! $episode: Episode = JEDI) {
+      hero(first: 10 episode: $episode) { name friends @include(if: $withFriends) { name } }
+    }"#;
+    expect!(normalise_query(query_a).unwrap()).to(be_equal_to(normalise_query(query_b).unwrap()));
+    expect!(normalise_query(query_a)).to(be_ok().value(
+      "query Hero($episode: Episode = JEDI, $withFriends: Boolean!) { hero(episode: $episode, first: 10) { name friends @include(if: $withFriends) { name } } }"));
+  }
+
+  #[test]
+  fn normalise_query_sorts_input_object_fields_but_not_lists_or_selections() {
+    expect!(normalise_query(r#"mutation { add(input: {b: [2, 1], a: "x, y"}) { id name } }"#)).to(be_ok().value(
+      r#"mutation { add(input: {a: "x, y", b: [2, 1]}) { id name } }"#));
+    expect!(normalise_query("{ b a }").unwrap()).to_not(be_equal_to(normalise_query("{ a b }").unwrap()));
+  }
+
+  #[test]
+  fn normalise_query_returns_an_error_for_invalid_documents() {
+    expect!(normalise_query("{ hero { name }")).to(be_err());
+    expect!(normalise_query("{ hero(name: \"bob) }")).to(be_err());
+    expect!(normalise_query("{ hero } }")).to(be_err());
+  }
+
+  #[test]
+  fn is_graphql_request_test() {
+    expect!(is_graphql_request(&json!({ "query": "{ hero }" }))).to(be_true());
+    expect!(is_graphql_request(&json!({ "query": "{ hero }", "variables": { "id": 1 }, "operationName": "A" }))).to(be_true());
+    expect!(is_graphql_request(&json!({ "query": "{ hero }", "other": 1 }))).to(be_false());
+    expect!(is_graphql_request(&json!({ "query": 1 }))).to(be_false());
+    expect!(is_graphql_request(&json!(["query"]))).to(be_false());
+  }
+
+  fn request(body: &str) -> Request {
+    Request {
+      body: OptionalBody::Present(Bytes::from(body.to_string()), ContentType::parse("application/graphql").ok()),
+      .. Request::default()
+    }
+  }
+
+  #[test]
+  fn match_graphql_compares_the_normalised_queries() {
+    let context = MatchingContext::with_config(DiffConfig::AllowUnexpectedKeys);
+    expect!(match_graphql(&request("{ hero(a: 1, b: 2) { name } }"), &request("{\n  hero(b: 2 a: 1) {\n    name\n  }\n}"), &context))
+      .to(be_ok());
+    let result = match_graphql(&request("{ hero { name } }"), &request("{ hero { id } }"), &context);
+    expect!(result.unwrap_err().iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$ -> Expected query '{ hero { name } }' but received '{ hero { id } }'".to_string()
+    ]));
+  }
+
+  #[test]
+  fn match_graphql_applies_any_matcher_to_the_normalised_query() {
+    let context = MatchingContext::new(DiffConfig::AllowUnexpectedKeys, &MatchingRuleCategory {
+      name: "body".to_string(),
+      rules: hashmap!{
+        "$".to_string() => crate::models::matchingrules::RuleList {
+          rules: vec![MatchingRule::Regex(r"^\{ hero\(id: \d+\) \{ name \} \}$".to_string())],
+          rule_logic: RuleLogic::And
+        }
+      }
+    });
+    expect!(match_graphql(&request("{ hero(id: 1) { name } }"), &request("{ hero(id: 1000) { name } }"), &context))
+      .to(be_ok());
+  }
+}
@@ -1,8 +1,10 @@
 //! Matching functions for headers
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::iter::FromIterator;
 
+use itertools::Itertools;
 use maplit::*;
 
 use crate::{matchers, MatchingContext, Mismatch};
@@ -11,57 +13,118 @@ use crate::models::matchingrules::MatchingRule;
 
 static PARAMETERISED_HEADER_TYPES: [&str; 2] = ["accept", "content-type"];
 
+/// Headers that are set by the HTTP client or the transport, and are not treated as unexpected
+/// headers with strict header matching
+static TRANSPORT_HEADERS: [&str; 8] = ["host", "content-length", "connection", "keep-alive",
+  "transfer-encoding", "te", "trailer", "upgrade"];
+
 fn strip_whitespace<'a, T: FromIterator<&'a str>>(val: &'a str, split_by: &'a str) -> T {
   val.split(split_by).map(|v| v.trim()).collect()
 }
 
 fn parse_charset_parameters(parameters: &[&str]) -> HashMap<String, String> {
-  parameters.iter().map(|v| v.split("=").map(|p| p.trim()).collect::<Vec<&str>>())
-    .fold(HashMap::new(), |mut map, name_value| {
-      map.insert(name_value[0].to_string(), name_value[1].to_string());
-      map
+  parameters.iter()
+    .filter(|parameter| !parameter.is_empty())
+    .map(|parameter| match parameter.split_once('=') {
+      Some((name, value)) => (name.trim().to_ascii_lowercase(), value.trim().trim_matches('"').to_string()),
+      None => (parameter.trim().to_ascii_lowercase(), String::default())
     })
+    .collect()
 }
 
-pub(crate) fn match_parameter_header(expected: &str, actual: &str, header: &str, value_type: &str) -> Result<(), Vec<String>> {
+/// Quality value of a media range in an `Accept` header (defaults to 1)
+fn quality(parameters: &HashMap<String, String>) -> f64 {
+  parameters.get("q").and_then(|q| q.parse::<f64>().ok()).unwrap_or(1.0)
+}
+
+/// Compares a parameterised header value (i.e. `application/json;charset=UTF-8`), ignoring the
+/// order of the parameters. All the expected parameters must be in the actual value, with the same
+/// (case-insensitive) value. Quality values (`q`) are compared as numbers.
+fn parameterised_values_match(expected: &str, actual: &str) -> bool {
   let expected_values: Vec<&str> = strip_whitespace(expected, ";");
   let actual_values: Vec<&str> = strip_whitespace(actual, ";");
-  let expected_parameters = expected_values.as_slice().split_first().unwrap();
-  let actual_parameters = actual_values.as_slice().split_first().unwrap();
-  let header_mismatch = format!("Expected {} '{}' to have value '{}' but was '{}'", value_type, header, expected, actual);
-
-  let mut mismatches = vec![];
-  if expected_parameters.0 == actual_parameters.0 {
-    let expected_parameter_map = parse_charset_parameters(expected_parameters.1);
-    let actual_parameter_map = parse_charset_parameters(actual_parameters.1);
-    for (k, v) in expected_parameter_map {
-      if actual_parameter_map.contains_key(&k) {
-        if v.to_ascii_lowercase() != actual_parameter_map.get(&k).unwrap().to_ascii_lowercase() {
-          mismatches.push(header_mismatch.clone());
-        }
-      } else {
-        mismatches.push(header_mismatch.clone());
-      }
-    }
+  let (expected_value, expected_parameters) = expected_values.as_slice().split_first().unwrap();
+  let (actual_value, actual_parameters) = actual_values.as_slice().split_first().unwrap();
+
+  if expected_value == actual_value {
+    let expected_parameter_map = parse_charset_parameters(expected_parameters);
+    let actual_parameter_map = parse_charset_parameters(actual_parameters);
+    expected_parameter_map.iter().all(|(k, v)| if k == "q" {
+      (quality(&expected_parameter_map) - quality(&actual_parameter_map)).abs() < f64::EPSILON
+    } else {
+      actual_parameter_map.get(k)
+        .map(|actual| v.eq_ignore_ascii_case(actual))
+        .unwrap_or(false)
+    })
   } else {
-    mismatches.push(header_mismatch.clone());
+    false
   }
+}
 
-  if mismatches.is_empty() {
+pub(crate) fn match_parameter_header(expected: &str, actual: &str, header: &str, value_type: &str) -> Result<(), Vec<String>> {
+  if parameterised_values_match(expected, actual) {
     Ok(())
   } else {
-    Err(mismatches)
+    Err(vec![format!("Expected {} '{}' to have value '{}' but was '{}'", value_type, header, expected, actual)])
+  }
+}
+
+/// Splits an `Accept` header into its media ranges, ordered by preference (the quality value,
+/// and then the order in the header)
+fn accept_media_ranges(value: &str) -> Vec<&str> {
+  value.split(',')
+    .map(|range| range.trim())
+    .filter(|range| !range.is_empty())
+    .map(|range| {
+      let parameters: Vec<&str> = strip_whitespace(range, ";");
+      (range, quality(&parse_charset_parameters(&parameters[1..])))
+    })
+    .sorted_by(|(_, q1), (_, q2)| q2.partial_cmp(q1).unwrap_or(Ordering::Equal))
+    .map(|(range, _)| range)
+    .collect()
+}
+
+/// Compares `Accept` headers. The media ranges must be the same and in the same order of preference,
+/// but the ranges with different quality values can be in any order in the header.
+fn match_accept_header(expected: &str, actual: &str, header: &str) -> Result<(), Vec<String>> {
+  let expected_ranges = accept_media_ranges(expected);
+  let actual_ranges = accept_media_ranges(actual);
+  if expected_ranges.len() == actual_ranges.len() && !expected_ranges.is_empty() &&
+    expected_ranges.iter().zip(actual_ranges.iter())
+      .all(|(expected, actual)| parameterised_values_match(expected, actual)) {
+    Ok(())
+  } else {
+    Err(vec![format!("Expected header '{}' to have value '{}' but was '{}'", header, expected, actual)])
   }
 }
 
 pub(crate) fn match_header_value(key: &str, expected: &str, actual: &str, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
-  let path = vec!["$", key];
-  let expected: String = strip_whitespace(expected, ",");
-  let actual: String = strip_whitespace(actual, ",");
+  match_header_value_at(key, None, expected, actual, context)
+}
+
+/// Matches a header value. If the index is given, it is the index of the value of a header with
+/// multiple values, and matching rules for that index (i.e. `Accept[1]`) will be applied.
+fn match_header_value_at(
+  key: &str,
+  index: Option<usize>,
+  expected: &str,
+  actual: &str,
+  context: &MatchingContext
+) -> Result<(), Vec<Mismatch>> {
+  let index = index.map(|index| index.to_string());
+  let mut path = vec!["$", key];
+  if let Some(index) = &index {
+    path.push(index.as_str());
+  }
+  let expected = strip_whitespace::<Vec<&str>>(expected, ",").join(",");
+  let actual = strip_whitespace::<Vec<&str>>(actual, ",").join(",");
 
+  let header_type = key.to_lowercase();
   let matcher_result = if context.matcher_is_defined(&path) {
     matchers::match_values(&path, context, &expected, &actual)
-  } else if PARAMETERISED_HEADER_TYPES.contains(&key.to_lowercase().as_str()) {
+  } else if header_type == "accept" {
+    match_accept_header(expected.as_str(), actual.as_str(), key)
+  } else if PARAMETERISED_HEADER_TYPES.contains(&header_type.as_str()) {
     match_parameter_header(expected.as_str(), actual.as_str(), key, "header")
   } else {
     Matches::matches(&expected, &actual, &MatchingRule::Equality).map_err(|err| vec![err])
@@ -78,6 +141,22 @@ pub(crate) fn match_header_value(key: &str, expected: &str, actual: &str, contex
   })
 }
 
+/// Matches the values of a header. Each expected value is compared to the actual value at the same
+/// index, applying any matching rules for the header or for the index. Without any matching rules,
+/// the media ranges of `Accept` headers are compared in order of preference.
+fn match_header_values(key: &str, expected: &[String], actual: &[String], context: &MatchingContext) -> Vec<Mismatch> {
+  let matchers_defined = (0..expected.len().max(1))
+    .any(|index| context.matcher_is_defined(&["$", key, index.to_string().as_str()]));
+  if key.eq_ignore_ascii_case("accept") && !matchers_defined {
+    match_header_value(key, &expected.join(","), &actual.join(","), context).err().unwrap_or_default()
+  } else {
+    expected.iter().enumerate().flat_map(|(index, value)| {
+      let actual_value = actual.get(index).map(|value| value.as_str()).unwrap_or_default();
+      match_header_value_at(key, Some(index), value, actual_value, context).err().unwrap_or_default()
+    }).collect()
+  }
+}
+
 fn find_entry<T>(map: &HashMap<String, T>, key: &String) -> Option<(String, T)> where T: Clone {
   match map.keys().find(|k| k.to_lowercase() == key.to_lowercase() ) {
     Some(k) => map.get(k).map(|v| (key.clone(), v.clone()) ),
@@ -89,9 +168,8 @@ fn match_header_maps(expected: HashMap<String, Vec<String>>, actual: HashMap<Str
   let mut result = hashmap!{};
   for (key, value) in &expected {
     match find_entry(&actual, key) {
-      Some((_, actual_value)) => for (index, val) in value.iter().enumerate() {
-        result.insert(key.clone(), match_header_value(key, val,
-                                                      actual_value.get(index).unwrap_or(&String::default()), context).err().unwrap_or_default());
+      Some((_, actual_value)) => {
+        result.insert(key.clone(), match_header_values(key, value, &actual_value, context));
       },
       None => {
         result.insert(key.clone(), vec![Mismatch::HeaderMismatch { key: key.clone(),
@@ -121,13 +199,35 @@ pub fn match_headers(expected: Option<HashMap<String, Vec<String>>>,
   }
 }
 
+/// Returns a mismatch for each actual header that is not one of the expected headers. Header names
+/// are compared case-insensitively, and the headers set by the HTTP client or the transport (like
+/// `Host` and `Content-Length`) are ignored.
+pub fn match_unexpected_headers(expected: &Option<HashMap<String, Vec<String>>>,
+                                actual: &Option<HashMap<String, Vec<String>>>) -> HashMap<String, Vec<Mismatch>> {
+  let expected_keys = expected.as_ref()
+    .map(|headers| headers.keys().map(|key| key.to_lowercase()).collect::<Vec<String>>())
+    .unwrap_or_default();
+  actual.as_ref().map(|headers| {
+    headers.iter()
+      .filter(|(key, _)| {
+        let key = key.to_lowercase();
+        !expected_keys.contains(&key) && !TRANSPORT_HEADERS.contains(&key.as_str())
+      })
+      .map(|(key, value)| (key.clone(), vec![Mismatch::HeaderMismatch { key: key.clone(),
+        expected: "".to_string(),
+        actual: format!("{:?}", value.join(", ")),
+        mismatch: format!("Unexpected header '{}' was received", key) }]))
+      .collect()
+  }).unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
   use expectest::prelude::*;
   use maplit::*;
 
   use crate::{MatchingContext, Mismatch, DiffConfig};
-  use crate::headers::{match_header_value, match_headers};
+  use crate::headers::{match_header_value, match_headers, match_unexpected_headers};
   use crate::models::matchingrules::MatchingRule;
 
   #[test]
@@ -298,6 +398,55 @@ mod tests {
     expect!(result.values().flatten()).to(be_empty());
   }
 
+  #[test]
+  fn content_type_header_ignores_the_order_and_quoting_of_parameters() {
+    let mismatches = match_header_value("Content-Type", "multipart/form-data; boundary=\"abc\"; charset=utf-8",
+                                        "multipart/form-data;charset=UTF-8;boundary=abc", &MatchingContext::default());
+    expect!(mismatches).to(be_ok());
+    let mismatches = match_header_value("Content-Type", "text/plain;format", "text/plain",
+                                        &MatchingContext::default());
+    expect!(mismatches).to(be_err());
+  }
+
+  #[test]
+  fn accept_header_compares_the_media_ranges_in_order_of_preference() {
+    let expected = Some(hashmap! { "Accept".to_string() => vec!["text/plain;q=0.5".to_string(), "application/json".to_string()] });
+    let actual = Some(hashmap! { "Accept".to_string() => vec!["application/json".to_string(), "text/plain; q=0.50".to_string()] });
+    expect!(match_headers(expected.clone(), actual, &MatchingContext::default()).values().flatten()).to(be_empty());
+
+    let actual = Some(hashmap! { "Accept".to_string() => vec!["application/json".to_string(), "text/plain;q=0.8".to_string()] });
+    expect!(match_headers(expected.clone(), actual, &MatchingContext::default()).values().flatten()).to_not(be_empty());
+
+    let actual = Some(hashmap! { "Accept".to_string() => vec!["application/json".to_string()] });
+    expect!(match_headers(expected, actual, &MatchingContext::default()).values().flatten()).to_not(be_empty());
+
+    expect!(match_header_value("Accept", "application/json, application/xml", "application/xml, application/json",
+                               &MatchingContext::default())).to(be_err());
+  }
+
+  #[test]
+  fn matching_rules_can_be_applied_to_each_value_of_a_multi_value_header() {
+    let context = MatchingContext::new(
+      DiffConfig::AllowUnexpectedKeys,
+      &matchingrules! {
+        "header" => {
+          "X-Ids[1]" => [ MatchingRule::Regex(s!("^\\d+$")) ]
+        }
+      }.rules_for_category("header").unwrap_or_default()
+    );
+    let expected = Some(hashmap! { "X-Ids".to_string() => vec!["abc".to_string(), "100".to_string(), "def".to_string()] });
+    let actual = Some(hashmap! { "X-Ids".to_string() => vec!["abc".to_string(), "2001".to_string(), "def".to_string()] });
+    expect!(match_headers(expected.clone(), actual, &context).values().flatten()).to(be_empty());
+
+    let actual = Some(hashmap! { "X-Ids".to_string() => vec!["abd".to_string(), "x".to_string(), "deg".to_string()] });
+    let result = match_headers(expected, actual, &context);
+    expect!(result.get("X-Ids").unwrap().iter().map(|m| m.description()).collect::<Vec<String>>()).to(be_equal_to(vec![
+      "Mismatch with header 'X-Ids': Expected 'abc' to be equal to 'abd'".to_string(),
+      "Mismatch with header 'X-Ids': Expected 'x' to match '^\\d+$'".to_string(),
+      "Mismatch with header 'X-Ids': Expected 'def' to be equal to 'deg'".to_string()
+    ]));
+  }
+
   #[test]
   fn matching_headers_be_true_when_headers_match_by_matcher() {
     let context = MatchingContext::new(
@@ -330,4 +479,28 @@ mod tests {
       mismatch: s!(""),
     } ]));
   }
+
+  #[test]
+  fn match_unexpected_headers_returns_a_mismatch_for_headers_that_are_not_expected() {
+    let expected = Some(hashmap! {
+      "Content-Type".to_string() => vec!["application/json".to_string()]
+    });
+    let actual = Some(hashmap! {
+      "content-type".to_string() => vec!["application/json".to_string()],
+      "Cookie".to_string() => vec!["session=1234".to_string()],
+      "Host".to_string() => vec!["localhost:1234".to_string()],
+      "Content-Length".to_string() => vec!["100".to_string()]
+    });
+    let result = match_unexpected_headers(&expected, &actual);
+    expect!(result).to(be_equal_to(hashmap! {
+      "Cookie".to_string() => vec![Mismatch::HeaderMismatch {
+        key: "Cookie".to_string(),
+        expected: "".to_string(),
+        actual: "\"session=1234\"".to_string(),
+        mismatch: "Unexpected header 'Cookie' was received".to_string()
+      }]
+    }));
+    expect!(match_unexpected_headers(&expected, &None)).to(be_equal_to(hashmap! {}));
+    expect!(match_unexpected_headers(&None, &expected).len()).to(be_equal_to(1));
+  }
 }
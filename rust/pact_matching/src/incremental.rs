@@ -0,0 +1,473 @@
+//! The `incremental` module supports re-matching a request after small changes to the actual
+//! request (i.e. a changed header or an edited JSON field), without re-running the full match.
+//! This allows tooling like editors to check a request against a contract as it is being edited,
+//! even with large bodies.
+//!
+//! ```
+//! use pact_matching::incremental::{IncrementalRequestMatch, RequestChange};
+//! use pact_matching::models::{HttpPart, Request};
+//! use pact_models::OptionalBody;
+//! use serde_json::json;
+//!
+//! let expected = Request {
+//!   method: "POST".to_string(),
+//!   body: OptionalBody::from(json!({ "user": { "name": "Mallory", "age": 21 } }).to_string()),
+//!   .. Request::default()
+//! };
+//! let actual = Request {
+//!   method: "POST".to_string(),
+//!   body: OptionalBody::from(json!({ "user": { "name": "Fred", "age": 21 } }).to_string()),
+//!   .. Request::default()
+//! };
+//!
+//! let mut matcher = IncrementalRequestMatch::new(&expected, &actual);
+//! assert!(!matcher.result().all_matched());
+//!
+//! let result = matcher.apply(RequestChange::JsonField("$.user.name".to_string(),
+//!   Some(json!("Mallory")))).unwrap();
+//! assert!(result.all_matched());
+//! ```
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use log::*;
+use serde_json::Value;
+
+use pact_models::OptionalBody;
+
+use crate::{
+  DiffConfig,
+  match_body,
+  match_method_with_context,
+  match_path,
+  match_query,
+  BodyMatchResult,
+  MatchingContext,
+  Mismatch,
+  RequestMatchResult
+};
+use crate::headers::match_headers;
+use crate::json::compare;
+use crate::locale::LocaleOptions;
+use crate::models::{HttpPart, Request};
+use crate::path_exp::{parse_path_exp, PathToken};
+
+/// A change to the actual request
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestChange {
+  /// The method was changed
+  Method(String),
+  /// The path was changed
+  Path(String),
+  /// The query parameter was set to the values, or removed if `None`
+  QueryParameter(String, Option<Vec<String>>),
+  /// The header was set to the values, or removed if `None`. Header names are not case sensitive.
+  Header(String, Option<Vec<String>>),
+  /// The body was replaced
+  Body(OptionalBody),
+  /// A field in a JSON body was set to the value, or removed if `None`. The path to the field is
+  /// in the same form as the paths for matching rules (i.e. `$.user.name` or `$.items[1].id`).
+  /// Setting the index one past the end of an array appends the value to the array.
+  JsonField(String, Option<Value>)
+}
+
+/// Match result for a request that can be updated as changes are made to the actual request.
+/// Only the parts of the request affected by a change are re-matched. For JSON bodies, changing a
+/// field only re-compares that field (or the closest enclosing value that has a matching rule).
+#[derive(Debug, Clone)]
+pub struct IncrementalRequestMatch {
+  expected: Request,
+  actual: Request,
+  locale: LocaleOptions,
+  expected_json: Option<Value>,
+  actual_json: Option<Value>,
+  result: RequestMatchResult
+}
+
+impl IncrementalRequestMatch {
+  /// Matches the actual request against the expected one
+  pub fn new(expected: &Request, actual: &Request) -> Self {
+    Self::with_locale(expected, actual, &LocaleOptions::default())
+  }
+
+  /// Matches the actual request against the expected one, using the locale options to parse
+  /// numeric strings and dates
+  pub fn with_locale(expected: &Request, actual: &Request, locale: &LocaleOptions) -> Self {
+    let result = crate::match_request_with_locale(expected.clone(), actual.clone(), locale);
+    Self::from_result(expected, actual, result, locale)
+  }
+
+  /// Uses a previously computed match result of the actual request against the expected one.
+  /// The result must have been computed with the same locale options.
+  pub fn from_result(
+    expected: &Request,
+    actual: &Request,
+    result: RequestMatchResult,
+    locale: &LocaleOptions
+  ) -> Self {
+    IncrementalRequestMatch {
+      expected: expected.clone(),
+      actual: actual.clone(),
+      locale: locale.clone(),
+      expected_json: json_body(expected),
+      actual_json: json_body(actual),
+      result
+    }
+  }
+
+  /// The current match result
+  pub fn result(&self) -> &RequestMatchResult {
+    &self.result
+  }
+
+  /// The actual request, with all the changes applied
+  pub fn actual(&self) -> &Request {
+    &self.actual
+  }
+
+  /// Applies the change to the actual request, and re-matches the affected parts of it. Returns
+  /// an error if the change can not be applied (i.e. a JSON field is changed but the body is not
+  /// JSON, or the parent of the field does not exist).
+  pub fn apply(&mut self, change: RequestChange) -> Result<&RequestMatchResult, String> {
+    match change {
+      RequestChange::Method(method) => {
+        self.actual.method = method;
+        self.result.method = match_method_with_context(&self.expected.method, &self.actual.method,
+          &self.context("method")).err();
+      },
+      RequestChange::Path(path) => {
+        self.actual.path = path;
+        self.result.path = match_path(&self.expected.path, &self.actual.path, &self.context("path")).err();
+      },
+      RequestChange::QueryParameter(name, values) => {
+        let mut query = self.actual.query.clone().unwrap_or_default();
+        match values {
+          Some(values) => query.insert(name, values),
+          None => query.remove(&name)
+        };
+        self.actual.query = if query.is_empty() { None } else { Some(query) };
+        self.result.query = match_query(self.expected.query.clone(), self.actual.query.clone(),
+          &self.context("query"));
+      },
+      RequestChange::Header(name, values) => {
+        let mut headers = self.actual.headers.clone().unwrap_or_default();
+        headers.retain(|key, _| !key.eq_ignore_ascii_case(&name));
+        let content_type_changed = name.eq_ignore_ascii_case("content-type");
+        if let Some(values) = values {
+          headers.insert(name, values);
+        }
+        self.actual.headers = if headers.is_empty() { None } else { Some(headers) };
+        self.result.headers = match_headers(self.expected.headers.clone(), self.actual.headers.clone(),
+          &self.context("header"));
+        if content_type_changed {
+          self.actual_json = json_body(&self.actual);
+          self.rematch_body();
+        }
+      },
+      RequestChange::Body(body) => {
+        self.actual.body = body;
+        self.actual_json = json_body(&self.actual);
+        self.rematch_body();
+      },
+      RequestChange::JsonField(path, value) => self.apply_json_field_change(&path, value)?
+    }
+    Ok(&self.result)
+  }
+
+  fn context(&self, category: &str) -> MatchingContext {
+    MatchingContext::new(DiffConfig::NoUnexpectedKeys,
+      &self.expected.matching_rules.rules_for_category(category).unwrap_or_default())
+      .with_locale(&self.locale)
+  }
+
+  fn rematch_body(&mut self) {
+    self.result.body = match_body(&self.expected, &self.actual, &self.context("body"),
+      &self.context("header"));
+  }
+
+  fn apply_json_field_change(&mut self, path: &str, value: Option<Value>) -> Result<(), String> {
+    let segments = path_segments(path)?;
+    let actual_json = self.actual_json.as_mut()
+      .ok_or_else(|| "The actual body is not JSON".to_string())?;
+    let existed = find_value(actual_json, &segments).is_some();
+    let exists = value.is_some();
+    set_value(actual_json, &segments, value)?;
+
+    let body = Bytes::from(actual_json.to_string());
+    let content_type = self.actual.body.content_type().or_else(|| self.actual.content_type());
+    self.actual.body = OptionalBody::Present(body, content_type);
+
+    let root = if existed && exists || segments.is_empty() { segments.len() } else { segments.len() - 1 };
+    match self.compare_from(&segments, root) {
+      Some(result) => self.result.body = result,
+      None => self.rematch_body()
+    }
+    Ok(())
+  }
+
+  /// Re-compares the JSON bodies from the path with the number of segments, and merges the
+  /// mismatches into the current body result. Returns `None` if the full body has to be re-matched.
+  fn compare_from(&self, segments: &[String], root: usize) -> Option<BodyMatchResult> {
+    let expected_json = self.expected_json.as_ref()?;
+    let actual_json = self.actual_json.as_ref()?;
+    let content_types_are_json = self.expected.content_type().unwrap_or_default().is_json() &&
+      self.actual.content_type().unwrap_or_default().is_json();
+    let mut mismatches = match &self.result.body {
+      BodyMatchResult::Ok => HashMap::new(),
+      BodyMatchResult::BodyMismatches(mismatches) => mismatches.clone(),
+      BodyMatchResult::BodyTypeMismatch { .. } => return None
+    };
+    if !content_types_are_json || mismatches.values().flatten().any(|m| !matches!(m, Mismatch::BodyMismatch { .. })) {
+      return None;
+    }
+
+    let context = self.context("body");
+    let path = std::iter::once("$").chain(segments.iter().map(|s| s.as_str())).collect::<Vec<&str>>();
+    // Any matching rules on the parents of the field can change how the field is compared (i.e.
+    // array elements are compared against the first expected element), so compare from the first
+    // parent that has a rule
+    let mut root = (0..root).find(|index| context.matcher_is_defined(&path[..=*index])).unwrap_or(root);
+    // Mismatches for the parents include the actual value of the parent, so they also need to be
+    // re-compared
+    root = (0..root).find(|index| mismatches.contains_key(&path[..=*index].join("."))).unwrap_or(root);
+    while root > 0 && (find_value(expected_json, &segments[..root]).is_none() ||
+      find_value(actual_json, &segments[..root]).is_none()) {
+      root -= 1;
+    }
+
+    let root_path = &path[..=root];
+    let spath = root_path.join(".");
+    debug!("Re-comparing the JSON bodies from path {}", spath);
+    let prefix = format!("{}.", spath);
+    mismatches.retain(|key, _| key != &spath && !key.starts_with(&prefix) && !(root == 0 && key.is_empty()));
+    if let Err(result) = compare(root_path, find_value(expected_json, &segments[..root])?,
+                                 find_value(actual_json, &segments[..root])?, &context) {
+      for mismatch in result {
+        let key = match &mismatch {
+          Mismatch::BodyMismatch { path, .. } => path.clone(),
+          _ => String::default()
+        };
+        mismatches.entry(key).or_insert_with(Vec::new).push(mismatch);
+      }
+    }
+
+    if mismatches.is_empty() {
+      Some(BodyMatchResult::Ok)
+    } else {
+      Some(BodyMatchResult::BodyMismatches(mismatches))
+    }
+  }
+}
+
+fn json_body(request: &Request) -> Option<Value> {
+  match &request.body {
+    OptionalBody::Present(body, _) => serde_json::from_slice(body).ok(),
+    _ => None
+  }
+}
+
+fn path_segments(path: &str) -> Result<Vec<String>, String> {
+  parse_path_exp(path)?.iter()
+    .filter(|token| **token != PathToken::Root)
+    .map(|token| match token {
+      PathToken::Field(name) => Ok(name.clone()),
+      PathToken::Index(index) => Ok(index.to_string()),
+      _ => Err(format!("Path '{}' can not contain wildcards", path))
+    })
+    .collect()
+}
+
+fn find_value<'a>(json: &'a Value, segments: &[String]) -> Option<&'a Value> {
+  segments.iter().try_fold(json, |json, segment| match json {
+    Value::Object(map) => map.get(segment),
+    Value::Array(array) => segment.parse::<usize>().ok().and_then(|index| array.get(index)),
+    _ => None
+  })
+}
+
+fn set_value(json: &mut Value, segments: &[String], value: Option<Value>) -> Result<(), String> {
+  match segments.split_last() {
+    None => {
+      *json = value.unwrap_or(Value::Null);
+      Ok(())
+    },
+    Some((last, parents)) => {
+      let path = segments.join(".");
+      let parent = parents.iter().try_fold(json, |json, segment| match json {
+        Value::Object(map) => map.get_mut(segment),
+        Value::Array(array) => segment.parse::<usize>().ok().and_then(move |index| array.get_mut(index)),
+        _ => None
+      }).ok_or_else(|| format!("The parent of field '{}' does not exist in the actual body", path))?;
+      match parent {
+        Value::Object(map) => {
+          match value {
+            Some(value) => map.insert(last.clone(), value),
+            None => map.remove(last)
+          };
+          Ok(())
+        },
+        Value::Array(array) => {
+          let index = last.parse::<usize>()
+            .map_err(|_| format!("'{}' is not a valid index for the array in field '{}'", last, path))?;
+          match value {
+            Some(value) if index < array.len() => array[index] = value,
+            Some(value) if index == array.len() => array.push(value),
+            None if index < array.len() => { array.remove(index); },
+            _ => return Err(format!("Index {} is out of bounds for the array in field '{}'", index, path))
+          };
+          Ok(())
+        },
+        _ => Err(format!("The parent of field '{}' is not an object or array", path))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use crate::match_request;
+  use crate::models::matchingrules::*;
+
+  use super::*;
+
+  fn request(body: Value) -> Request {
+    Request {
+      method: "POST".to_string(),
+      path: "/users".to_string(),
+      headers: Some(hashmap!{ "Content-Type".to_string() => vec!["application/json".to_string()] }),
+      body: OptionalBody::Present(Bytes::from(body.to_string()), None),
+      .. Request::default()
+    }
+  }
+
+  fn check_against_full_match(matcher: &IncrementalRequestMatch, expected: &Request) {
+    let full = match_request(expected.clone(), matcher.actual().clone());
+    expect!(matcher.result()).to(be_equal_to(&full));
+  }
+
+  #[test]
+  fn changing_json_fields_gives_the_same_result_as_a_full_match() {
+    let expected = request(json!({
+      "user": { "name": "Mallory", "age": 21, "roles": ["admin", "user"] },
+      "active": true
+    }));
+    let actual = request(json!({
+      "user": { "name": "Fred", "age": 21, "roles": ["admin", "user"] },
+      "active": false
+    }));
+    let mut matcher = IncrementalRequestMatch::new(&expected, &actual);
+    expect!(matcher.result().mismatches().len()).to(be_equal_to(2));
+
+    let changes = vec![
+      RequestChange::JsonField("$.user.name".to_string(), Some(json!("Mallory"))),
+      RequestChange::JsonField("$.user.roles[1]".to_string(), Some(json!("other"))),
+      RequestChange::JsonField("$.user.roles[2]".to_string(), Some(json!("extra"))),
+      RequestChange::JsonField("$.user.email".to_string(), Some(json!("mallory@example.com"))),
+      RequestChange::JsonField("$.user.age".to_string(), None),
+      RequestChange::JsonField("$.user.roles[2]".to_string(), None),
+      RequestChange::JsonField("$.user.roles[1]".to_string(), Some(json!("user"))),
+      RequestChange::JsonField("$.user.email".to_string(), None),
+      RequestChange::JsonField("$.user.age".to_string(), Some(json!(21))),
+      RequestChange::JsonField("$.active".to_string(), Some(json!(true)))
+    ];
+    for change in changes {
+      matcher.apply(change).unwrap();
+      check_against_full_match(&matcher, &expected);
+    }
+    expect!(matcher.result().all_matched()).to(be_true());
+  }
+
+  #[test]
+  fn changing_json_fields_under_a_matching_rule_gives_the_same_result_as_a_full_match() {
+    let expected = Request {
+      matching_rules: matchingrules!{
+        "body" => {
+          "$.items" => [ MatchingRule::MinType(1) ],
+          "$.items[*].id" => [ MatchingRule::Type ]
+        }
+      },
+      .. request(json!({ "items": [ { "id": 1, "name": "a" } ] }))
+    };
+    let actual = request(json!({ "items": [ { "id": 1, "name": "a" }, { "id": 2, "name": "a" } ] }));
+    let mut matcher = IncrementalRequestMatch::new(&expected, &actual);
+    expect!(matcher.result().all_matched()).to(be_true());
+
+    let changes = vec![
+      RequestChange::JsonField("$.items[1].id".to_string(), Some(json!("2"))),
+      RequestChange::JsonField("$.items[1].name".to_string(), Some(json!("b"))),
+      RequestChange::JsonField("$.items[1].id".to_string(), Some(json!(3))),
+      RequestChange::JsonField("$.items[1].name".to_string(), Some(json!("a")))
+    ];
+    for change in changes {
+      matcher.apply(change).unwrap();
+      check_against_full_match(&matcher, &expected);
+    }
+    expect!(matcher.result().all_matched()).to(be_true());
+  }
+
+  #[test]
+  fn changing_the_other_parts_of_the_request_gives_the_same_result_as_a_full_match() {
+    let expected = Request {
+      query: Some(hashmap!{ "page".to_string() => vec!["1".to_string()] }),
+      .. request(json!({ "name": "Mallory" }))
+    };
+    let actual = request(json!({ "name": "Mallory" }));
+    let mut matcher = IncrementalRequestMatch::new(&expected, &actual);
+
+    let changes = vec![
+      RequestChange::Method("PUT".to_string()),
+      RequestChange::Path("/users/1".to_string()),
+      RequestChange::QueryParameter("page".to_string(), Some(vec!["1".to_string()])),
+      RequestChange::QueryParameter("size".to_string(), Some(vec!["10".to_string()])),
+      RequestChange::Header("content-type".to_string(), Some(vec!["text/plain".to_string()])),
+      RequestChange::Header("Content-Type".to_string(), Some(vec!["application/json".to_string()])),
+      RequestChange::Body(OptionalBody::from(json!({ "name": "Fred" }).to_string())),
+      RequestChange::Method("POST".to_string()),
+      RequestChange::Path("/users".to_string()),
+      RequestChange::QueryParameter("size".to_string(), None)
+    ];
+    for change in changes {
+      matcher.apply(change).unwrap();
+      check_against_full_match(&matcher, &expected);
+    }
+    expect!(matcher.result().mismatches().len()).to(be_equal_to(1));
+  }
+
+  #[test]
+  fn from_result_uses_the_previous_match_result() {
+    let expected = request(json!({ "name": "Mallory" }));
+    let actual = request(json!({ "name": "Fred" }));
+    let previous = match_request(expected.clone(), actual.clone());
+
+    let mut matcher = IncrementalRequestMatch::from_result(&expected, &actual, previous,
+      &LocaleOptions::default());
+    let result = matcher.apply(RequestChange::JsonField("$.name".to_string(), Some(json!("Mallory"))));
+    expect!(result.map(|r| r.all_matched())).to(be_ok().value(true));
+    expect!(matcher.actual().body.str_value()).to(be_equal_to("{\"name\":\"Mallory\"}"));
+  }
+
+  #[test]
+  fn json_field_changes_that_can_not_be_applied_return_an_error() {
+    let expected = request(json!({ "name": "Mallory", "roles": [] }));
+    let mut matcher = IncrementalRequestMatch::new(&expected, &expected);
+
+    expect!(matcher.apply(RequestChange::JsonField("$.user.name".to_string(), Some(json!("a")))))
+      .to(be_err());
+    expect!(matcher.apply(RequestChange::JsonField("$.roles[1]".to_string(), Some(json!("a")))))
+      .to(be_err());
+    expect!(matcher.apply(RequestChange::JsonField("$.roles[*]".to_string(), Some(json!("a")))))
+      .to(be_err());
+
+    let mut matcher = IncrementalRequestMatch::new(&expected, &Request {
+      body: OptionalBody::Present(Bytes::from("not json"), None),
+      .. expected.clone()
+    });
+    expect!(matcher.apply(RequestChange::JsonField("$.name".to_string(), Some(json!("a")))))
+      .to(be_err());
+  }
+}
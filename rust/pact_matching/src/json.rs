@@ -3,33 +3,42 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 
-use ansi_term::Colour::*;
-use difference::*;
+use itertools::Itertools;
 use log::*;
 use onig::{Captures, Regex};
 use rand::Rng;
 use serde_json::{json, Value};
-use uuid::Uuid;
-use chrono::prelude::*;
 
 use crate::{MatchingContext, merge_result};
 use crate::binary_utils::{convert_data, match_content_type};
+use crate::graphql;
 use crate::matchers::*;
+use crate::models::expression_parser::{
+  execute_date_expression,
+  execute_datetime_expression,
+  execute_time_expression
+};
 use crate::models::generators::{
+  date_time_from_expression,
   find_matching_variant,
   GenerateValue,
   Generator,
   generate_decimal,
+  generate_uuid,
+  generator_rng,
   generate_hexadecimal,
+  generate_regex_string,
   generate_ascii_string,
   generate_value_from_context,
+  path_parameter_from_context,
   JsonHandler,
   ContentTypeHandler
 };
 use crate::models::HttpPart;
 use crate::models::json_utils::{get_field_as_string, json_to_string};
 use crate::models::matchingrules::*;
-use crate::time_utils::{parse_pattern, validate_datetime, to_chrono_pattern};
+use crate::locale::LocaleOptions;
+use crate::time_utils::{parse_pattern, validate_datetime_with_locales, to_chrono_pattern};
 
 use super::Mismatch;
 
@@ -46,6 +55,14 @@ fn type_of(json: &Value) -> String {
 
 impl Matches<Value> for Value {
   fn matches(&self, actual: &Value, matcher: &MatchingRule) -> Result<(), String> {
+    self.matches_with_locale(actual, matcher, &LocaleOptions::default())
+  }
+
+  fn matches_with_locale(&self, actual: &Value, matcher: &MatchingRule, locale: &LocaleOptions) -> Result<(), String> {
+    let number_format = match (actual, locale.number_format) {
+      (Value::String(s), Some(format)) => Some((s, format)),
+      _ => None
+    };
     let result = match *matcher {
       MatchingRule::Regex(ref regex) => {
         match Regex::new(regex) {
@@ -143,31 +160,34 @@ impl Matches<Value> for Value {
         &Value::Null => Ok(()),
         _ => Err(format!("Expected '{}' to be a null value", json_to_string(actual)))
       },
-      MatchingRule::Integer => if actual.is_i64() || actual.is_u64() {
+      MatchingRule::Integer => if actual.is_i64() || actual.is_u64() ||
+        number_format.map(|(s, format)| format.parse_integer(s).is_some()).unwrap_or(false) {
         Ok(())
       } else {
         Err(format!("Expected '{}' to be an integer value", json_to_string(actual)))
       },
-      MatchingRule::Decimal => if actual.is_f64() {
+      MatchingRule::Decimal => if actual.is_f64() ||
+        number_format.map(|(s, format)| format.parse_decimal(s).is_some()).unwrap_or(false) {
         Ok(())
       } else {
         Err(format!("Expected '{}' to be a decimal value", json_to_string(actual)))
       },
-      MatchingRule::Number => if actual.is_number() {
+      MatchingRule::Number => if actual.is_number() ||
+        number_format.map(|(s, format)| format.parse_number(s).is_some()).unwrap_or(false) {
         Ok(())
       } else {
         Err(format!("Expected '{}' to be a number", json_to_string(actual)))
       },
       MatchingRule::Date(ref s) => {
-        validate_datetime(&json_to_string(actual), s)
+        validate_datetime_with_locales(&json_to_string(actual), s, &locale.date_locales)
           .map_err(|err| format!("Expected '{}' to match a date format of '{}': {}", actual, s, err))
       },
       MatchingRule::Time(ref s) => {
-        validate_datetime(&json_to_string(actual), s)
+        validate_datetime_with_locales(&json_to_string(actual), s, &locale.date_locales)
           .map_err(|err| format!("Expected '{}' to match a time format of '{}': {}", actual, s, err))
       },
       MatchingRule::Timestamp(ref s) => {
-        validate_datetime(&json_to_string(actual), s)
+        validate_datetime_with_locales(&json_to_string(actual), s, &locale.date_locales)
           .map_err(|err| format!("Expected '{}' to match a timestamp format of '{}': {}", actual, s, err))
       },
       MatchingRule::ContentType(ref expected_content_type) => {
@@ -212,7 +232,14 @@ pub fn match_json(expected: &dyn HttpPart, actual: &dyn HttpPart, context: &Matc
     }
     Err(mismatches.clone())
   } else {
-    compare(&vec!["$"], &expected_json.unwrap(), &actual_json.unwrap(), context)
+    let expected_json = expected_json.unwrap();
+    let actual_json = actual_json.unwrap();
+    if graphql::is_graphql_request(&expected_json) {
+      debug!("Expected body is a GraphQL request, normalising the queries before comparing them");
+      compare(&["$"], &graphql::normalise_request(&expected_json), &graphql::normalise_request(&actual_json), context)
+    } else {
+      compare(&["$"], &expected_json, &actual_json, context)
+    }
   }
 }
 
@@ -260,19 +287,69 @@ pub fn display_diff(expected: &String, actual: &String, path: &str, indent: &str
       None => s!("")
     }
   };
-  let changeset = Changeset::new(&expected_fragment, &actual_fragment, "\n");
-  let mut output = String::new();
-  for change in changeset.diffs {
-      match change {
-          Difference::Same(ref x) => output.push_str(&format!("{}{}\n", indent, x)),
-          Difference::Add(ref x) => output.push_str(&Green.paint(format!("{}+{}\n", indent, x)).to_string()),
-          Difference::Rem(ref x) => output.push_str(&Red.paint(format!("{}-{}\n", indent, x)).to_string())
+  crate::diff::ansi_diff(&expected_fragment, &actual_fragment, indent)
+}
+
+/// Converts the path of a body mismatch (i.e. `$.items.0.id`) to a JSON Pointer (RFC 6901), i.e.
+/// `/items/0/id`. The root path `$` is converted to the empty pointer.
+pub fn path_to_json_pointer(path: &str) -> String {
+  path.trim_start_matches('$').split('.')
+    .filter(|part| !part.is_empty())
+    .map(|part| format!("/{}", part.replace('~', "~0").replace('/', "~1")))
+    .collect()
+}
+
+/// Returns a JSON Patch (RFC 6902) describing how the actual body differs from the expected body at
+/// the paths of the body mismatches. Applying the patch to the expected body from the pact results
+/// in one that matches the actual body, so it can be used to suggest updates to the pact.
+///
+/// Only the differences that caused mismatches are included, so values that are allowed to differ
+/// by matching rules are left as they are. For maps and lists, only the keys or items that were
+/// added or removed are patched, as any mismatches in the values will have their own paths. Any
+/// mismatches that are not body mismatches are ignored.
+pub fn json_patch(mismatches: &[Mismatch], expected: &Value, actual: &Value) -> Value {
+  let mut pointers = mismatches.iter()
+    .filter_map(|mismatch| mismatch.json_pointer())
+    .collect::<Vec<String>>();
+  pointers.sort();
+  pointers.dedup();
+
+  let mut operations = vec![];
+  for pointer in pointers {
+    patch_operations(&pointer, expected.pointer(&pointer), actual.pointer(&pointer), &mut operations);
+  }
+  Value::Array(operations)
+}
+
+fn patch_operations(pointer: &str, expected: Option<&Value>, actual: Option<&Value>, operations: &mut Vec<Value>) {
+  let child = |key: &str| format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+  match (expected, actual) {
+    (Some(Value::Object(expected)), Some(Value::Object(actual))) => {
+      for key in expected.keys().filter(|key| !actual.contains_key(*key)) {
+        operations.push(json!({ "op": "remove", "path": child(key) }));
       }
+      for (key, value) in actual.iter().filter(|(key, _)| !expected.contains_key(*key)) {
+        operations.push(json!({ "op": "add", "path": child(key), "value": value }));
+      }
+    },
+    (Some(Value::Array(expected)), Some(Value::Array(actual))) => {
+      for index in (actual.len()..expected.len()).rev() {
+        operations.push(json!({ "op": "remove", "path": child(&index.to_string()) }));
+      }
+      for (index, value) in actual.iter().enumerate().skip(expected.len()) {
+        operations.push(json!({ "op": "add", "path": child(&index.to_string()), "value": value }));
+      }
+    },
+    (Some(expected), Some(actual)) => if expected != actual {
+      operations.push(json!({ "op": "replace", "path": pointer, "value": actual }));
+    },
+    (None, Some(actual)) => operations.push(json!({ "op": "add", "path": pointer, "value": actual })),
+    (Some(_), None) => operations.push(json!({ "op": "remove", "path": pointer })),
+    (None, None) => ()
   }
-  output
 }
 
-fn compare(path: &[&str], expected: &Value, actual: &Value, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+pub(crate) fn compare(path: &[&str], expected: &Value, actual: &Value, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
   debug!("compare: Comparing path {}", path.join("."));
   match (expected, actual) {
     (&Value::Object(ref emap), &Value::Object(ref amap)) => compare_maps(path, emap, amap, context),
@@ -417,7 +494,7 @@ impl GenerateValue<Value> for Generator {
     debug!("Generating value from {:?} with context {:?}", self, context);
     let result = match self {
       Generator::RandomInt(min, max) => {
-        let rand_int = rand::thread_rng().gen_range(*min..max.saturating_add(1));
+        let rand_int = generator_rng().gen_range(*min..max.saturating_add(1));
         match value {
           Value::String(_) => Ok(json!(format!("{}", rand_int))),
           Value::Number(_) => Ok(json!(rand_int)),
@@ -425,7 +502,7 @@ impl GenerateValue<Value> for Generator {
         }
       },
       Generator::Uuid => match value {
-        Value::String(_) => Ok(json!(Uuid::new_v4().to_simple().to_string())),
+        Value::String(_) => Ok(json!(generate_uuid().to_simple().to_string())),
         _ => Err(format!("Could not generate a UUID from {}", value))
       },
       Generator::RandomDecimal(digits) => match value {
@@ -444,50 +521,47 @@ impl GenerateValue<Value> for Generator {
         Value::String(_) => Ok(json!(generate_ascii_string(*size as usize))),
         _ => Err(format!("Could not generate a random string from {}", value))
       },
-      Generator::Regex(ref regex) => {
-        let mut parser = regex_syntax::ParserBuilder::new().unicode(false).build();
-        match parser.parse(regex) {
-          Ok(hir) => {
-            let gen = rand_regex::Regex::with_hir(hir, 20).unwrap();
-            Ok(json!(rand::thread_rng().sample::<String, _>(gen)))
-          },
-          Err(err) => {
-            log::warn!("'{}' is not a valid regular expression - {}", regex, err);
-            Err(format!("Could not generate a random string from {} - {}", regex, err))
-          }
+      Generator::Regex(ref regex) => match generate_regex_string(regex) {
+        Ok(value) => Ok(json!(value)),
+        Err(err) => {
+          log::warn!("{}", err);
+          Err(format!("Could not generate a random string from {} - {}", regex, err))
         }
       },
-      Generator::Date(ref format) => match format {
-        Some(pattern) => match parse_pattern(pattern) {
-          Ok(tokens) => Ok(json!(Local::now().date().format(&to_chrono_pattern(&tokens)).to_string())),
-          Err(err) => {
-            log::warn!("Date format {} is not valid - {}", pattern, err);
-            Err(format!("Could not generate a random date from {} - {}", pattern, err))
-          }
-        },
-        None => Ok(json!(Local::now().naive_local().date().to_string()))
-      },
-      Generator::Time(ref format) => match format {
-        Some(pattern) => match parse_pattern(pattern) {
-          Ok(tokens) => Ok(json!(Local::now().format(&to_chrono_pattern(&tokens)).to_string())),
-          Err(err) => {
-            log::warn!("Time format {} is not valid - {}", pattern, err);
-            Err(format!("Could not generate a random time from {} - {}", pattern, err))
-          }
-        },
-        None => Ok(json!(Local::now().time().format("%H:%M:%S").to_string()))
-      },
-      Generator::DateTime(ref format) => match format {
-        Some(pattern) => match parse_pattern(pattern) {
-          Ok(tokens) => Ok(json!(Local::now().format(&to_chrono_pattern(&tokens)).to_string())),
-          Err(err) => {
-            log::warn!("DateTime format {} is not valid - {}", pattern, err);
-            Err(format!("Could not generate a random date-time from {} - {}", pattern, err))
-          }
-        },
-        None => Ok(json!(Local::now().format("%Y-%m-%dT%H:%M:%S.%3f%z").to_string()))
-      },
-      Generator::RandomBoolean => Ok(json!(rand::thread_rng().gen::<bool>())),
+      Generator::Date(ref format, ref expression) => date_time_from_expression(expression, execute_date_expression)
+        .and_then(|date| match format {
+          Some(pattern) => match parse_pattern(pattern) {
+            Ok(tokens) => Ok(json!(date.date().format(&to_chrono_pattern(&tokens)).to_string())),
+            Err(err) => {
+              log::warn!("Date format {} is not valid - {}", pattern, err);
+              Err(format!("Could not generate a random date from {} - {}", pattern, err))
+            }
+          },
+          None => Ok(json!(date.naive_local().date().to_string()))
+        }),
+      Generator::Time(ref format, ref expression) => date_time_from_expression(expression, execute_time_expression)
+        .and_then(|time| match format {
+          Some(pattern) => match parse_pattern(pattern) {
+            Ok(tokens) => Ok(json!(time.format(&to_chrono_pattern(&tokens)).to_string())),
+            Err(err) => {
+              log::warn!("Time format {} is not valid - {}", pattern, err);
+              Err(format!("Could not generate a random time from {} - {}", pattern, err))
+            }
+          },
+          None => Ok(json!(time.time().format("%H:%M:%S").to_string()))
+        }),
+      Generator::DateTime(ref format, ref expression) => date_time_from_expression(expression, execute_datetime_expression)
+        .and_then(|date_time| match format {
+          Some(pattern) => match parse_pattern(pattern) {
+            Ok(tokens) => Ok(json!(date_time.format(&to_chrono_pattern(&tokens)).to_string())),
+            Err(err) => {
+              log::warn!("DateTime format {} is not valid - {}", pattern, err);
+              Err(format!("Could not generate a random date-time from {} - {}", pattern, err))
+            }
+          },
+          None => Ok(json!(date_time.format("%Y-%m-%dT%H:%M:%S.%3f%z").to_string()))
+        }),
+      Generator::RandomBoolean => Ok(json!(generator_rng().gen::<bool>())),
       Generator::ProviderStateGenerator(ref exp, ref dt) =>
         match generate_value_from_context(exp, context, dt) {
           Ok(val) => val.as_json(),
@@ -514,6 +588,12 @@ impl GenerateValue<Value> for Generator {
           Err("MockServerURL: can not generate a value as there is no mock server details in the test context".to_string())
         }
       }
+      Generator::PathParameter(name) => path_parameter_from_context(name, context)
+        .map(|parameter| match value {
+          Value::Number(_) => parameter.parse::<serde_json::Number>().map(Value::Number)
+            .unwrap_or(Value::String(parameter)),
+          _ => Value::String(parameter)
+        }),
       Generator::ArrayContains(variants) => match value {
         Value::Array(vec) => {
           let callback = |path: &Vec<&str>, value: &Value, context: &MatchingContext| {
@@ -524,8 +604,8 @@ impl GenerateValue<Value> for Generator {
             if let Some((variant, generators)) = find_matching_variant(value, variants, &callback) {
               debug!("Generating values for variant {} and value {}", variant, value);
               let mut handler = JsonHandler { value: value.clone() };
-              for (key, generator) in generators {
-                handler.apply_key(&key, &generator, context);
+              for (key, generator) in generators.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+                handler.apply_key(key, generator, context);
               };
               debug!("Generated value {}", handler.value);
               result[index] = handler.value.clone();
@@ -842,6 +922,27 @@ mod tests {
     expect!(result).to(be_ok());
   }
 
+  #[test]
+  fn match_json_normalises_the_query_of_graphql_requests() {
+    let expected = Request {
+      body: OptionalBody::Present(r#"{"query": "{ hero(id: $id, first: 10) { name } }", "variables": {"id": 1000}}"#.into(), None),
+      .. Request::default()
+    };
+    let actual = Request {
+      body: OptionalBody::Present(r#"{"query": "{\n  hero(first: 10, id: $id) {\n    name\n  }\n}", "variables": {"id": 2001}}"#.into(), None),
+      .. Request::default()
+    };
+    let result = match_json(&expected, &actual, &MatchingContext::new(DiffConfig::AllowUnexpectedKeys, &matchingrules!{
+      "body" => {
+        "$.variables.id" => [ MatchingRule::Integer ]
+      }
+    }.rules_for_category("body").unwrap()));
+    expect!(result).to(be_ok());
+
+    let result = match_json(&expected, &actual, &MatchingContext::with_config(DiffConfig::AllowUnexpectedKeys));
+    expect!(mismatch_message(&result)).to(be_equal_to(s!("Expected '1000' to be equal to '2001'")));
+  }
+
     #[test]
     fn equality_matcher_test() {
         let matcher = MatchingRule::Equality;
@@ -1171,4 +1272,47 @@ mod tests {
     let result = match_json(&expected, &actual, &context);
     expect!(result).to(be_ok());
   }
+
+  #[test]
+  fn path_to_json_pointer_test() {
+    expect!(path_to_json_pointer("$")).to(be_equal_to(""));
+    expect!(path_to_json_pointer("$.items.0.id")).to(be_equal_to("/items/0/id"));
+    expect!(path_to_json_pointer("$.a/b.c~d")).to(be_equal_to("/a~1b/c~0d"));
+  }
+
+  #[test]
+  fn json_patch_describes_how_the_actual_body_differs_from_the_expected_body() {
+    let expected_body = json!({ "id": 100, "name": "Fred", "roles": ["admin", "user"], "address": { "city": "Sydney" } });
+    let actual_body = json!({ "id": 200, "name": "Freddy", "roles": ["admin"], "address": { "town": "Sydney" } });
+    let expected = request!(expected_body.to_string());
+    let actual = request!(actual_body.to_string());
+    let context = MatchingContext::new(DiffConfig::NoUnexpectedKeys, &matchingrules!{
+      "body" => {
+        "$.id" => [ MatchingRule::Type ]
+      }
+    }.rules_for_category("body").unwrap());
+    let mismatches = match_json(&expected, &actual, &context).unwrap_err();
+
+    expect!(json_patch(&mismatches, &expected_body, &actual_body)).to(be_equal_to(json!([
+      { "op": "remove", "path": "/address/city" },
+      { "op": "add", "path": "/address/town", "value": "Sydney" },
+      { "op": "replace", "path": "/name", "value": "Freddy" },
+      { "op": "remove", "path": "/roles/1" }
+    ])));
+  }
+
+  #[test]
+  fn json_patch_replaces_values_of_a_different_type_and_ignores_other_mismatches() {
+    let expected_body = json!({ "items": [1, 2] });
+    let actual_body = json!({ "items": "none" });
+    let mismatches = vec![
+      Mismatch::StatusMismatch { expected: 200, actual: 404 },
+      Mismatch::BodyMismatch { path: s!("$.items"), expected: None, actual: None, mismatch: s!("") }
+    ];
+
+    expect!(json_patch(&mismatches, &expected_body, &actual_body)).to(be_equal_to(json!([
+      { "op": "replace", "path": "/items", "value": "none" }
+    ])));
+    expect!(json_patch(&mismatches[0..1], &expected_body, &actual_body)).to(be_equal_to(json!([])));
+  }
 }
@@ -186,6 +186,10 @@
 //! pairs can be in any order, but when the same key appears more than once the values
 //! are compared in the order they appear in the query string.
 //!
+//! The keys and values are percent-decoded (RFC 3986) before they are compared, with `+` treated
+//! the same as `%20`. A `%` that is not followed by two hex digits, or an encoding that is not valid
+//! UTF-8, is a malformed encoding. The mock server reports these as query mismatches.
+//!
 //! ### Matching Headers
 //!
 //! 1. Do a case-insensitive sort of the headers by keys
@@ -335,6 +339,7 @@
 #![warn(missing_docs)]
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt::{Debug, Display};
 use std::fmt::Formatter;
 use std::hash::Hash;
@@ -342,20 +347,24 @@ use std::str;
 use std::str::from_utf8;
 
 use ansi_term::*;
+use anyhow::anyhow;
 use ansi_term::Colour::*;
 use bytes::Bytes;
 use lazy_static::*;
 use log::*;
 use maplit::hashmap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Value};
 
 use pact_models::content_types::ContentType;
 use pact_models::{PactSpecification, OptionalBody};
 
-use crate::headers::{match_header_value, match_headers};
+use crate::headers::{match_header_value, match_headers, match_unexpected_headers};
+use crate::locale::LocaleOptions;
 use crate::matchers::*;
 use crate::models::{HttpPart, Interaction};
 use crate::models::generators::*;
+use crate::models::json_utils::json_to_string;
 use crate::models::matchingrules::*;
 
 /// Simple macro to convert a string slice to a `String` struct.
@@ -368,11 +377,43 @@ macro_rules! s {
 mod path_exp;
 mod timezone_db;
 pub mod time_utils;
+pub mod locale;
+pub mod error;
+pub mod spec_runner;
+pub mod transcoders;
+pub mod plugins;
+pub mod protobuf;
+pub mod avro;
+pub mod incremental;
 mod matchers;
 pub mod json;
+pub mod diff;
 mod xml;
+mod graphql;
+mod csv;
+pub mod sse;
+mod form_urlencoded;
 mod binary_utils;
 mod headers;
+pub mod path_template;
+
+/// Internal parsing functions that are exposed for the fuzz targets (see the `fuzz` directory)
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing {
+  use std::collections::HashMap;
+
+  use serde_json::Value;
+
+  use pact_models::OptionalBody;
+
+  pub use crate::path_exp::{parse_path_exp, PathToken};
+
+  /// Parses the body from the attribute of the JSON value
+  pub fn body_from_json(json: &Value, attr_name: &str, headers: &Option<HashMap<String, Vec<String>>>) -> OptionalBody {
+    crate::models::body_from_json(json, attr_name, headers)
+  }
+}
 
 #[derive(Debug, Clone)]
 /// Context used to apply matching logic
@@ -382,7 +423,9 @@ pub struct MatchingContext {
   /// Configuration to apply when matching with the context
   pub config: DiffConfig,
   /// Specification version to apply when matching with the context
-  pub matching_spec: PactSpecification
+  pub matching_spec: PactSpecification,
+  /// Options for parsing numeric strings and dates when matching with the context
+  pub locale: LocaleOptions
 }
 
 impl MatchingContext {
@@ -408,7 +451,16 @@ impl MatchingContext {
     MatchingContext {
       matchers: matchers.clone(),
       config: self.config.clone(),
-      matching_spec: self.matching_spec.clone()
+      matching_spec: self.matching_spec.clone(),
+      locale: self.locale.clone()
+    }
+  }
+
+  /// Returns a copy of this context with the locale options used to parse numeric strings and dates
+  pub fn with_locale(&self, locale: &LocaleOptions) -> Self {
+    MatchingContext {
+      locale: locale.clone(),
+      .. self.clone()
     }
   }
 
@@ -487,7 +539,8 @@ impl Default for MatchingContext {
     MatchingContext {
       matchers: Default::default(),
       config: DiffConfig::AllowUnexpectedKeys,
-      matching_spec: PactSpecification::V3
+      matching_spec: PactSpecification::V3,
+      locale: LocaleOptions::default()
     }
   }
 }
@@ -495,12 +548,16 @@ impl Default for MatchingContext {
 lazy_static! {
   static ref BODY_MATCHERS: [
     (fn(content_type: &ContentType) -> bool,
-    fn(expected: &dyn models::HttpPart, actual: &dyn models::HttpPart, context: &MatchingContext) -> Result<(), Vec<Mismatch>>); 4]
+    fn(expected: &dyn models::HttpPart, actual: &dyn models::HttpPart, context: &MatchingContext) -> Result<(), Vec<Mismatch>>); 8]
      = [
       (|content_type| { content_type.is_json() }, json::match_json),
       (|content_type| { content_type.is_xml() }, xml::match_xml),
       (|content_type| { content_type.base_type() == "application/octet-stream" }, binary_utils::match_octet_stream),
-      (|content_type| { content_type.base_type() == "multipart/form-data" }, binary_utils::match_mime_multipart)
+      (|content_type| { content_type.base_type() == "multipart/form-data" }, binary_utils::match_mime_multipart),
+      (|content_type| { content_type.base_type() == "application/graphql" }, graphql::match_graphql),
+      (|content_type| { content_type.base_type() == "text/csv" }, csv::match_csv),
+      (|content_type| { content_type.base_type() == "text/event-stream" }, sse::match_event_stream),
+      (|content_type| { content_type.base_type() == "application/x-www-form-urlencoded" }, form_urlencoded::match_form_urlencoded)
   ];
 }
 
@@ -654,7 +711,8 @@ impl Mismatch {
             Some(v) => serde_json::Value::String(str::from_utf8(v)
               .unwrap_or("ERROR: could not convert to UTF-8 from bytes").into()),
             None => serde_json::Value::Null
-          }
+          },
+          "diff": self.diff()
         })
       },
       Mismatch::BodyMismatch { path, expected, actual, mismatch } => {
@@ -669,7 +727,8 @@ impl Mismatch {
             Some(v) => serde_json::Value::String(str::from_utf8(v).unwrap_or("ERROR: could not convert from bytes").into()),
             None => serde_json::Value::Null
           },
-          "mismatch" : mismatch
+          "mismatch" : mismatch,
+          "diff": self.diff()
         })
       }
       Mismatch::MetadataMismatch { key, expected, actual, mismatch } => {
@@ -684,6 +743,60 @@ impl Mismatch {
     }
   }
 
+    /// Converts the JSON form of a mismatch (as returned from `to_json`) back to a mismatch. Any
+    /// diff in the JSON is ignored, as it is derived from the expected and actual values.
+    pub fn from_json(json: &Value) -> anyhow::Result<Mismatch> {
+      let mismatch_type = json.get("type").and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Mismatch JSON does not have a 'type' attribute"))?;
+      match mismatch_type {
+        "MethodMismatch" => Ok(Mismatch::MethodMismatch {
+          expected: json_string(json, "expected"),
+          actual: json_string(json, "actual")
+        }),
+        "PathMismatch" => Ok(Mismatch::PathMismatch {
+          expected: json_string(json, "expected"),
+          actual: json_string(json, "actual"),
+          mismatch: json_string(json, "mismatch")
+        }),
+        "StatusMismatch" => Ok(Mismatch::StatusMismatch {
+          expected: json_status(json, "expected")?,
+          actual: json_status(json, "actual")?
+        }),
+        "QueryMismatch" => Ok(Mismatch::QueryMismatch {
+          parameter: json_string(json, "parameter"),
+          expected: json_string(json, "expected"),
+          actual: json_string(json, "actual"),
+          mismatch: json_string(json, "mismatch")
+        }),
+        "HeaderMismatch" => Ok(Mismatch::HeaderMismatch {
+          key: json_string(json, "key"),
+          expected: json_string(json, "expected"),
+          actual: json_string(json, "actual"),
+          mismatch: json_string(json, "mismatch")
+        }),
+        "BodyTypeMismatch" => Ok(Mismatch::BodyTypeMismatch {
+          expected: json_string(json, "expected"),
+          actual: json_string(json, "actual"),
+          mismatch: json_string(json, "mismatch"),
+          expected_body: json_bytes(json, "expectedBody"),
+          actual_body: json_bytes(json, "actualBody")
+        }),
+        "BodyMismatch" => Ok(Mismatch::BodyMismatch {
+          path: json_string(json, "path"),
+          expected: json_bytes(json, "expected"),
+          actual: json_bytes(json, "actual"),
+          mismatch: json_string(json, "mismatch")
+        }),
+        "MetadataMismatch" => Ok(Mismatch::MetadataMismatch {
+          key: json_string(json, "key"),
+          expected: json_string(json, "expected"),
+          actual: json_string(json, "actual"),
+          mismatch: json_string(json, "mismatch")
+        }),
+        _ => Err(anyhow!("'{}' is not a valid mismatch type", mismatch_type))
+      }
+    }
+
     /// Returns the type of the mismatch as a string
     pub fn mismatch_type(&self) -> String {
       match *self {
@@ -698,6 +811,39 @@ impl Mismatch {
       }
     }
 
+    /// Returns the JSON Pointer (RFC 6901) to where a body mismatch occurred in the body. Returns
+    /// `None` for all the other types of mismatch.
+    pub fn json_pointer(&self) -> Option<String> {
+      match *self {
+        Mismatch::BodyMismatch { ref path, .. } => Some(json::path_to_json_pointer(path)),
+        _ => None
+      }
+    }
+
+    /// Returns a unified diff of the expected and actual values for body mismatches where both
+    /// values are known and differ. Returns `None` for all the other types of mismatch.
+    pub fn diff(&self) -> Option<String> {
+      let (expected, actual) = match *self {
+        Mismatch::BodyTypeMismatch { expected_body: Some(ref e), actual_body: Some(ref a), .. } => (e, a),
+        Mismatch::BodyMismatch { expected: Some(ref e), actual: Some(ref a), .. } => (e, a),
+        _ => return None
+      };
+      let diff = diff::unified_diff(&diff::format_value(expected), &diff::format_value(actual));
+      if diff.is_empty() { None } else { Some(diff) }
+    }
+
+    /// Returns the differences between the expected and actual values of body mismatches with
+    /// ANSI escape codes for display in a terminal, with each line prefixed with the indent.
+    /// Returns `None` for all the other types of mismatch.
+    pub fn ansi_diff(&self, indent: &str) -> Option<String> {
+      match *self {
+        Mismatch::BodyTypeMismatch { expected_body: Some(ref e), actual_body: Some(ref a), .. } |
+        Mismatch::BodyMismatch { expected: Some(ref e), actual: Some(ref a), .. } if e != a =>
+          Some(diff::ansi_diff(&diff::format_value(e), &diff::format_value(a), indent)),
+        _ => None
+      }
+    }
+
     /// Returns a summary string for this mismatch
     pub fn summary(&self) -> String {
       match *self {
@@ -784,6 +930,43 @@ impl PartialEq for Mismatch {
   }
 }
 
+fn json_string(json: &Value, field: &str) -> String {
+  match json.get(field) {
+    Some(Value::String(s)) => s.clone(),
+    Some(Value::Null) | None => String::default(),
+    Some(v) => v.to_string()
+  }
+}
+
+fn json_status(json: &Value, field: &str) -> anyhow::Result<u16> {
+  json.get(field).and_then(|v| v.as_u64())
+    .and_then(|v| u16::try_from(v).ok())
+    .ok_or_else(|| anyhow!("Mismatch attribute '{}' is not a valid status code", field))
+}
+
+fn json_bytes(json: &Value, field: &str) -> Option<Bytes> {
+  match json.get(field) {
+    Some(Value::String(s)) => Some(Bytes::from(s.clone())),
+    _ => None
+  }
+}
+
+/// Mismatches are serialised in the same form as returned from `Mismatch::to_json`, which is the
+/// stable schema used by the mock server and verifier. Each mismatch is a JSON object with a `type`
+/// attribute (i.e. `BodyMismatch`) and the attributes of the mismatch, with body values as strings.
+impl Serialize for Mismatch {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    self.to_json().serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for Mismatch {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+    let json = Value::deserialize(deserializer)?;
+    Mismatch::from_json(&json).map_err(serde::de::Error::custom)
+  }
+}
+
 impl Display for Mismatch {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
     write!(f, "{}", self.description())
@@ -803,12 +986,32 @@ fn merge_result(res1: Result<(), Vec<Mismatch>>, res2: Result<(), Vec<Mismatch>>
   }
 }
 
-/// Result of matching a request body
-#[derive(Debug, Clone, PartialEq)]
+/// Serialises optional bodies as UTF-8 strings, in the same way as the mismatch JSON
+mod body_as_string {
+  use bytes::Bytes;
+  use serde::{Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S>(body: &Option<Bytes>, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    match body {
+      Some(body) => serializer.serialize_some(&String::from_utf8_lossy(body)),
+      None => serializer.serialize_none()
+    }
+  }
+
+  pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Bytes>, D::Error> where D: Deserializer<'de> {
+    Option::<String>::deserialize(deserializer).map(|body| body.map(Bytes::from))
+  }
+}
+
+/// Result of matching a request body. This is serialised as a JSON object with a `type` attribute
+/// of `Ok`, `BodyTypeMismatch` or `BodyMismatches`, with the mismatches keyed by the path in the body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum BodyMatchResult {
   /// Matched OK
   Ok,
   /// Mismatch in the content type of the body
+  #[serde(rename_all = "camelCase")]
   BodyTypeMismatch {
     /// Expected content type
     expected_type: String,
@@ -817,8 +1020,10 @@ pub enum BodyMatchResult {
     /// Message
     message: String,
     /// Expected body
+    #[serde(with = "body_as_string")]
     expected: Option<Bytes>,
     /// Actual body
+    #[serde(with = "body_as_string")]
     actual: Option<Bytes>
   },
   /// Mismatches with the body contents
@@ -856,7 +1061,7 @@ impl BodyMatchResult {
 }
 
 /// Result of matching a request
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RequestMatchResult {
   /// Method match result
   pub method: Option<Mismatch>,
@@ -1023,6 +1228,20 @@ pub fn match_method(expected: &String, actual: &String) -> Result<(), Mismatch>
   }
 }
 
+/// Matches the actual request method to the expected one, using the matching rules for the method
+/// if there are any (i.e. an equality matcher for a case sensitive match). Without any matching
+/// rules, the methods are compared ignoring case.
+#[allow(clippy::result_large_err)]
+pub fn match_method_with_context(expected: &String, actual: &String, context: &MatchingContext) -> Result<(), Mismatch> {
+  let path = vec![];
+  if context.matcher_is_defined(&path) {
+    match_values(&path, context, expected, actual)
+      .map_err(|_| Mismatch::MethodMismatch { expected: expected.clone(), actual: actual.clone() })
+  } else {
+    match_method(expected, actual)
+  }
+}
+
 /// Matches the actual request path to the expected one.
 pub fn match_path(expected: &String, actual: &String, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
   let path = vec![];
@@ -1166,20 +1385,34 @@ fn group_by<I, F, K>(items: I, f: F) -> HashMap<K, Vec<I::Item>>
 
 fn compare_bodies(content_type: &ContentType, expected: &dyn models::HttpPart, actual: &dyn models::HttpPart, context: &MatchingContext) -> BodyMatchResult {
   let mut mismatches = vec![];
-  match BODY_MATCHERS.iter().find(|mt| mt.0(&content_type)) {
-    Some(ref match_fn) => {
-      debug!("Using body matcher for content type '{}'", content_type);
-      if let Err(m) = match_fn.1(expected, actual, &context) {
-        mismatches.extend_from_slice(&*m);
-      }
-    },
-    None => {
-      debug!("No body matcher defined for content type '{}', using plain text matcher", content_type);
-      if let Err(m) = match_text(&expected.body().value(), &actual.body().value(), &context) {
-        mismatches.extend_from_slice(&*m);
+  if let Some(plugin) = plugins::find_plugin(content_type) {
+    debug!("Using plugin '{}' for content type '{}'", plugin.metadata().name, content_type);
+    let allow_unexpected_keys = context.config == DiffConfig::AllowUnexpectedKeys;
+    match plugin.match_contents(expected.body(), actual.body(), &context.matchers, allow_unexpected_keys) {
+      Ok(m) => mismatches.extend(m),
+      Err(err) => mismatches.push(Mismatch::BodyMismatch {
+        path: "$".to_string(),
+        expected: expected.body().value(),
+        actual: actual.body().value(),
+        mismatch: format!("Plugin '{}' failed to match the contents: {}", plugin.metadata().name, err)
+      })
+    }
+  } else {
+    match BODY_MATCHERS.iter().find(|mt| mt.0(content_type)) {
+      Some(match_fn) => {
+        debug!("Using body matcher for content type '{}'", content_type);
+        if let Err(m) = match_fn.1(expected, actual, context) {
+          mismatches.extend_from_slice(&m);
+        }
+      },
+      None => {
+        debug!("No body matcher defined for content type '{}', using plain text matcher", content_type);
+        if let Err(m) = match_text(&expected.body().value(), &actual.body().value(), context) {
+          mismatches.extend_from_slice(&m);
+        }
       }
     }
-  };
+  }
   if mismatches.is_empty() {
     BodyMatchResult::Ok
   } else {
@@ -1225,7 +1458,38 @@ fn match_body_content(content_type: &ContentType, expected: &dyn models::HttpPar
   }
 }
 
+fn match_transcoded_body(
+  expected_content_type: &ContentType,
+  actual_content_type: &ContentType,
+  expected: &dyn models::HttpPart,
+  actual: &dyn models::HttpPart,
+  context: &MatchingContext
+) -> BodyMatchResult {
+  let actual_body = actual.body().value().unwrap_or_default();
+  match transcoders::transcode(&actual_body, actual_content_type, expected_content_type) {
+    Ok(body) => {
+      debug!("Transcoded the actual body from {} to {}", actual_content_type, expected_content_type);
+      let transcoded = models::Request {
+        headers: Some(hashmap!{ "Content-Type".to_string() => vec![expected_content_type.to_string()] }),
+        body: OptionalBody::Present(Bytes::from(body), Some(expected_content_type.clone())),
+        .. models::Request::default()
+      };
+      compare_bodies(expected_content_type, expected, &transcoded, context)
+    },
+    Err(err) => BodyMatchResult::BodyTypeMismatch {
+      expected_type: expected_content_type.to_string(),
+      actual_type: actual_content_type.to_string(),
+      message: format!("Expected body with content type {} but was {}, and it could not be transcoded: {}",
+                       expected_content_type, actual_content_type, err),
+      expected: expected.body().value(),
+      actual: actual.body().value()
+    }
+  }
+}
+
 /// Matches the actual body to the expected one. This takes into account the content type of each.
+/// If the content types are different but have been declared compatible (see the `transcoders`
+/// module), the actual body is transcoded to the expected content type first.
 pub fn match_body(
   expected: &dyn models::HttpPart,
   actual: &dyn models::HttpPart,
@@ -1244,6 +1508,9 @@ pub fn match_body(
       match_header_value("Content-Type", expected_content_type.to_string().as_str(),
                          actual_content_type.to_string().as_str(), header_context).is_ok()) {
     match_body_content(&expected_content_type, expected, actual, context)
+  } else if expected.body().is_present() && actual.body().is_present() &&
+    transcoders::content_types_are_compatible(&expected_content_type, &actual_content_type) {
+    match_transcoded_body(&expected_content_type, &actual_content_type, expected, actual, context)
   } else if expected.body().is_present() {
     BodyMatchResult::BodyTypeMismatch {
       expected_type: expected_content_type.to_string(),
@@ -1260,31 +1527,73 @@ pub fn match_body(
 
 /// Matches the expected and actual requests
 pub fn match_request(expected: models::Request, actual: models::Request) -> RequestMatchResult {
+  match_request_with_locale(expected, actual, &LocaleOptions::default())
+}
+
+/// Matches the expected and actual requests, using the locale options to parse numeric strings and dates
+pub fn match_request_with_locale(expected: models::Request, actual: models::Request, locale: &LocaleOptions) -> RequestMatchResult {
+  match_request_with_options(expected, actual, locale, false, &DiffConfig::NoUnexpectedKeys)
+}
+
+/// Matches the expected and actual requests, using the locale options to parse numeric strings and
+/// dates. `body_config` controls if the actual body may have keys that are not in the expected
+/// body (by default requests must not have any). If `strict_headers` is set, the headers are
+/// matched strictly as with `match_request_with_strict_headers`.
+pub fn match_request_with_options(
+  expected: models::Request,
+  actual: models::Request,
+  locale: &LocaleOptions,
+  strict_headers: bool,
+  body_config: &DiffConfig
+) -> RequestMatchResult {
   log::info!("comparing to expected {}", expected);
   log::debug!("     body: '{}'", expected.body.str_value());
   log::debug!("     matching_rules: {:?}", expected.matching_rules);
   log::debug!("     generators: {:?}", expected.generators);
 
+  let method_context = MatchingContext::new(DiffConfig::NoUnexpectedKeys,
+                                          &expected.matching_rules.rules_for_category("method").unwrap_or_default());
   let path_context = MatchingContext::new(DiffConfig::NoUnexpectedKeys,
-                                          &expected.matching_rules.rules_for_category("path").unwrap_or_default());
-  let body_context = MatchingContext::new(DiffConfig::NoUnexpectedKeys,
-                                          &expected.matching_rules.rules_for_category("body").unwrap_or_default());
+                                          &expected.matching_rules.rules_for_category("path").unwrap_or_default())
+    .with_locale(locale);
+  let body_context = MatchingContext::new(body_config.clone(),
+                                          &expected.matching_rules.rules_for_category("body").unwrap_or_default())
+    .with_locale(locale);
   let query_context = MatchingContext::new(DiffConfig::NoUnexpectedKeys,
-                                          &expected.matching_rules.rules_for_category("query").unwrap_or_default());
+                                          &expected.matching_rules.rules_for_category("query").unwrap_or_default())
+    .with_locale(locale);
   let header_context = MatchingContext::new(DiffConfig::NoUnexpectedKeys,
-                                          &expected.matching_rules.rules_for_category("header").unwrap_or_default());
-  let result = RequestMatchResult {
-    method: match_method(&expected.method, &actual.method).err(),
+                                          &expected.matching_rules.rules_for_category("header").unwrap_or_default())
+    .with_locale(locale);
+  let unexpected_headers = if strict_headers {
+    match_unexpected_headers(&expected.headers, &actual.headers)
+  } else {
+    hashmap!{}
+  };
+  let mut result = RequestMatchResult {
+    method: match_method_with_context(&expected.method, &actual.method, &method_context).err(),
     path: match_path(&expected.path, &actual.path, &path_context).err(),
     body: match_body(&expected, &actual, &body_context, &header_context),
     query: match_query(expected.query, actual.query, &query_context),
     headers: match_headers(expected.headers, actual.headers, &header_context)
   };
+  for (key, mismatches) in unexpected_headers {
+    result.headers.entry(key).or_default().extend(mismatches);
+  }
 
   log::debug!("--> Mismatches: {:?}", result.mismatches());
   result
 }
 
+/// Matches the expected and actual requests, using the locale options to parse numeric strings and
+/// dates. The headers are matched strictly, so the actual request must not have any headers that
+/// are not in the expected request (apart from the ones set by the HTTP client or transport, like
+/// `Host` and `Content-Length`). This is for contracts that must guarantee that no additional
+/// headers (i.e. cookies) are sent.
+pub fn match_request_with_strict_headers(expected: models::Request, actual: models::Request, locale: &LocaleOptions) -> RequestMatchResult {
+  match_request_with_options(expected, actual, locale, true, &DiffConfig::NoUnexpectedKeys)
+}
+
 /// Matches the actual response status to the expected one.
 pub fn match_status(expected: u16, actual: u16) -> Result<(), Mismatch> {
   if expected != actual {
@@ -1296,14 +1605,33 @@ pub fn match_status(expected: u16, actual: u16) -> Result<(), Mismatch> {
 
 /// Matches the actual and expected responses.
 pub fn match_response(expected: models::Response, actual: models::Response) -> Vec<Mismatch> {
+  match_response_with_locale(expected, actual, &LocaleOptions::default())
+}
+
+/// Matches the actual and expected responses, using the locale options to parse numeric strings and dates
+pub fn match_response_with_locale(expected: models::Response, actual: models::Response, locale: &LocaleOptions) -> Vec<Mismatch> {
+  match_response_with_body_config(expected, actual, locale, &DiffConfig::AllowUnexpectedKeys)
+}
+
+/// Matches the actual and expected responses, using the locale options to parse numeric strings
+/// and dates. `body_config` controls if the actual body may have keys that are not in the expected
+/// body (by default responses can have additional keys).
+pub fn match_response_with_body_config(
+  expected: models::Response,
+  actual: models::Response,
+  locale: &LocaleOptions,
+  body_config: &DiffConfig
+) -> Vec<Mismatch> {
   let mut mismatches = vec![];
 
   log::info!("comparing to expected response: {}", expected);
 
-  let body_context = MatchingContext::new(DiffConfig::AllowUnexpectedKeys,
-                                          &expected.matching_rules.rules_for_category("body").unwrap_or_default());
+  let body_context = MatchingContext::new(body_config.clone(),
+                                          &expected.matching_rules.rules_for_category("body").unwrap_or_default())
+    .with_locale(locale);
   let header_context = MatchingContext::new(DiffConfig::AllowUnexpectedKeys,
-                                            &expected.matching_rules.rules_for_category("header").unwrap_or_default());
+                                            &expected.matching_rules.rules_for_category("header").unwrap_or_default())
+    .with_locale(locale);
 
   mismatches.extend_from_slice(match_body(&expected, &actual, &body_context, &header_context)
     .mismatches().as_slice());
@@ -1391,8 +1719,11 @@ pub fn match_message_metadata(
   if !expected_metadata.is_empty() || context.config == DiffConfig::NoUnexpectedKeys {
     for (key, value) in &expected_metadata {
       match actual_metadata.get(key) {
+        Some(actual_value) if key == models::kafka::KAFKA_HEADERS => {
+          result.insert(key.clone(), match_kafka_headers(value, actual_value, context));
+        },
         Some(actual_value) => {
-          result.insert(key.clone(), match_metadata_value(key, value,
+          result.insert(key.clone(), match_metadata_value(&[key.as_str()], value,
             actual_value, context).err().unwrap_or_default());
         },
         None => {
@@ -1407,22 +1738,49 @@ pub fn match_message_metadata(
   result
 }
 
-fn match_metadata_value(key: &str, expected: &Value, actual: &Value, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+/// Matches the Kafka record headers individually, so matching rules can be applied to each header
+/// (i.e. with a `kafka_headers.traceId` path)
+fn match_kafka_headers(expected: &Value, actual: &Value, context: &MatchingContext) -> Vec<Mismatch> {
+  let key = models::kafka::KAFKA_HEADERS;
+  match (models::kafka::normalise_headers(expected.clone()), models::kafka::normalise_headers(actual.clone())) {
+    (Value::Object(expected_headers), Value::Object(actual_headers)) => {
+      expected_headers.iter().flat_map(|(name, value)| {
+        match actual_headers.get(name) {
+          Some(actual_value) => match_metadata_value(&[key, name.as_str()], value, actual_value, context)
+            .err().unwrap_or_default(),
+          None => vec![Mismatch::MetadataMismatch { key: format!("{}.{}", key, name),
+            expected: json_to_string(value),
+            actual: "".to_string(),
+            mismatch: format!("Expected Kafka header '{}' but was missing", name) }]
+        }
+      }).collect()
+    },
+    (expected, actual) => match_metadata_value(&[key], &expected, &actual, context)
+      .err().unwrap_or_default()
+  }
+}
+
+fn match_metadata_value(path: &[&str], expected: &Value, actual: &Value, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+  let key = path.join(".");
   debug!("Comparing metadata values for key '{}'", key);
-  let path = vec![key];
-  let matcher_result = if context.matcher_is_defined(&path) {
-    matchers::match_values(&path, context, &expected.to_string(), &actual.to_string())
+  let rule_path = [&["$"], path].concat();
+  let matcher_result = if context.matcher_is_defined(&rule_path) {
+    matchers::match_values(&rule_path, context, &json_to_string(expected), &json_to_string(actual))
   } else if key.to_ascii_lowercase() == "contenttype" || key.to_ascii_lowercase() == "content-type" {
     debug!("Comparing message context type '{}' => '{}'", expected, actual);
     headers::match_parameter_header(expected.as_str().unwrap_or_default(),
-                                    actual.as_str().unwrap_or_default(), key, "metadata")
+                                    actual.as_str().unwrap_or_default(), &key, "metadata")
   } else {
-    expected.matches(actual, &MatchingRule::Equality).map_err(|err| vec![err])
+    match (expected, actual) {
+      (Value::String(_), Value::Number(_)) | (Value::Number(_), Value::String(_)) =>
+        json_to_string(expected).matches(&json_to_string(actual), &MatchingRule::Equality),
+      _ => expected.matches(actual, &MatchingRule::Equality)
+    }.map_err(|err| vec![err])
   };
   matcher_result.map_err(|messages| {
     messages.iter().map(|message| {
       Mismatch::MetadataMismatch {
-        key: key.to_string(),
+        key: key.clone(),
         expected: expected.to_string(),
         actual: actual.to_string(),
         mismatch: format!("Expected metadata key '{}' to have value '{}' but was '{}' - {}", key, expected, actual, message)
@@ -1433,6 +1791,16 @@ fn match_metadata_value(key: &str, expected: &Value, actual: &Value, context: &M
 
 /// Matches the actual and expected messages.
 pub fn match_message(expected: &Box<dyn Interaction + Send>, actual: &Box<dyn Interaction + Send>) -> Vec<Mismatch> {
+  match_message_with_locale(expected, actual, &LocaleOptions::default())
+}
+
+/// Matches the actual and expected messages, using the locale options to parse numeric strings and dates
+#[allow(clippy::borrowed_box)]
+pub fn match_message_with_locale(
+  expected: &Box<dyn Interaction + Send>,
+  actual: &Box<dyn Interaction + Send>,
+  locale: &LocaleOptions
+) -> Vec<Mismatch> {
   let mut mismatches = vec![];
 
   if expected.is_message() && actual.is_message() {
@@ -1442,14 +1810,17 @@ pub fn match_message(expected: &Box<dyn Interaction + Send>, actual: &Box<dyn In
       MatchingContext {
         matchers: matching_rules.rules_for_category("content").unwrap_or_default(),
         config: DiffConfig::AllowUnexpectedKeys,
-        matching_spec: PactSpecification::V4
+        matching_spec: PactSpecification::V4,
+        locale: locale.clone()
       }
     } else {
       MatchingContext::new(DiffConfig::AllowUnexpectedKeys,
                            &matching_rules.rules_for_category("body").unwrap_or_default())
+        .with_locale(locale)
     };
     let metadata_context = MatchingContext::new(DiffConfig::AllowUnexpectedKeys,
-                                                &matching_rules.rules_for_category("metadata").unwrap_or_default());
+                                                &matching_rules.rules_for_category("metadata").unwrap_or_default())
+      .with_locale(locale);
     mismatches.extend_from_slice(match_message_contents(expected, actual, &body_context).err().unwrap_or_default().as_slice());
     for values in match_message_metadata(expected, actual, &metadata_context).values() {
       mismatches.extend_from_slice(values.as_slice());
@@ -1467,98 +1838,219 @@ pub fn match_message(expected: &Box<dyn Interaction + Send>, actual: &Box<dyn In
   mismatches
 }
 
-/// Generates the request by applying any defined generators
-pub fn generate_request(request: &models::Request, mode: &GeneratorTestMode, context: &HashMap<&str, Value>) -> models::Request {
-  let mut request = request.clone();
-
-  let generators = request.build_generators(&GeneratorCategory::PATH);
-  if !generators.is_empty() {
-    debug!("Applying path generator...");
-    apply_generators(mode, &generators, &mut |_, generator| {
-      if let Ok(v) = generator.generate_value(&request.path, context) {
-        request.path = v;
-      }
+/// Matches the actual response messages of a synchronous message interaction against the
+/// expected ones, in order
+pub fn match_sync_message_response(
+  expected: &models::v4::SynchronousMessages,
+  actual_responses: &[models::v4::message_parts::MessageContents]
+) -> Vec<Mismatch> {
+  debug!("Matching response messages for '{}'", expected.description);
+  let mut mismatches = vec![];
+  if expected.response.len() != actual_responses.len() {
+    mismatches.push(Mismatch::BodyMismatch {
+      path: "$".to_string(),
+      expected: None,
+      actual: None,
+      mismatch: format!("Expected {} response messages but received {}", expected.response.len(),
+        actual_responses.len())
     });
   }
+  for (expected_message, actual_message) in expected.response.iter().zip(actual_responses) {
+    mismatches.extend(match_message(&contents_as_message(expected, expected_message),
+      &contents_as_message(expected, actual_message)));
+  }
+  mismatches
+}
 
-  let generators = request.build_generators(&GeneratorCategory::HEADER);
-  if !generators.is_empty() {
-    debug!("Applying header generators...");
-    apply_generators(mode, &generators, &mut |key, generator| {
-      if let Some(ref mut headers) = request.headers {
-        if headers.contains_key(key) {
-          if let Ok(v) = generator.generate_value(&headers.get(key).unwrap().clone(), context) {
-            headers.insert(key.clone(), v);
-          }
+/// Matches the actual request message of a synchronous message interaction (i.e. the frame a
+/// WebSocket client sent) against the expected one
+pub fn match_sync_message_request(
+  expected: &models::v4::SynchronousMessages,
+  actual_request: &models::v4::message_parts::MessageContents
+) -> Vec<Mismatch> {
+  debug!("Matching request message for '{}'", expected.description);
+  match_message(&contents_as_message(expected, &expected.request),
+    &contents_as_message(expected, actual_request))
+}
+
+fn contents_as_message(
+  interaction: &models::v4::SynchronousMessages,
+  contents: &models::v4::message_parts::MessageContents
+) -> Box<dyn Interaction + Send> {
+  models::v4::AsynchronousMessage {
+    description: interaction.description.clone(),
+    contents: contents.contents.clone(),
+    metadata: contents.metadata.clone(),
+    matching_rules: contents.matching_rules.clone(),
+    generators: contents.generators.clone(),
+    .. models::v4::AsynchronousMessage::default()
+  }.boxed()
+}
+
+/// Generates the request by applying any defined generators. If the context has a `generatorSeed`,
+/// the random generators are seeded with it so the generated values are reproducible
+pub fn generate_request(request: &models::Request, mode: &GeneratorTestMode, context: &HashMap<&str, Value>) -> models::Request {
+  with_generator_seed_from_context(context, || {
+    let mut request = request.clone();
+
+    let generators = request.build_generators(&GeneratorCategory::PATH);
+    if !generators.is_empty() {
+      debug!("Applying path generator...");
+      apply_generators(mode, &generators, &mut |_, generator| {
+        if let Ok(v) = generator.generate_value(&request.path, context) {
+          request.path = v;
         }
-      }
-    });
-  }
+      });
+    }
 
-  let generators = request.build_generators(&GeneratorCategory::QUERY);
-  if !generators.is_empty() {
-    debug!("Applying query generators...");
-    apply_generators(mode, &generators, &mut |key, generator| {
-      if let Some(ref mut parameters) = request.query {
-        if let Some(parameter) = parameters.get_mut(key) {
-          let mut generated = parameter.clone();
-          for (index, val) in parameter.iter().enumerate() {
-            if let Ok(v) = generator.generate_value(val, context) {
-              generated[index] = v;
+    let generators = request.build_generators(&GeneratorCategory::HEADER);
+    if !generators.is_empty() {
+      debug!("Applying header generators...");
+      apply_generators(mode, &generators, &mut |key, generator| {
+        if let Some(ref mut headers) = request.headers {
+          generate_header_values(headers, key, generator, context);
+        }
+      });
+    }
+
+    let generators = request.build_generators(&GeneratorCategory::QUERY);
+    if !generators.is_empty() {
+      debug!("Applying query generators...");
+      apply_generators(mode, &generators, &mut |key, generator| {
+        if let Some(ref mut parameters) = request.query {
+          if let Some(parameter) = parameters.get_mut(key) {
+            let mut generated = parameter.clone();
+            for (index, val) in parameter.iter().enumerate() {
+              if let Ok(v) = generator.generate_value(val, context) {
+                generated[index] = v;
+              }
             }
+            *parameter = generated;
           }
-          *parameter = generated;
         }
-      }
-    });
-  }
+      });
+    }
 
-  let generators = request.build_generators(&GeneratorCategory::BODY);
-  if !generators.is_empty() && request.body.is_present() {
-    debug!("Applying body generators...");
-    request.body = apply_body_generators(mode, &request.body, request.content_type(),
-                                         context, &generators);
-  }
+    let generators = request.build_generators(&GeneratorCategory::BODY);
+    if !generators.is_empty() && request.body.is_present() {
+      debug!("Applying body generators...");
+      request.body = apply_body_generators(mode, &request.body, request.content_type(),
+                                           context, &generators);
+    }
 
-  request
+    request
+  })
 }
 
-/// Generates the response by applying any defined generators
+/// Generates the response by applying any defined generators. If the context has a `generatorSeed`,
+/// the random generators are seeded with it so the generated values are reproducible
 pub fn generate_response(response: &models::Response, mode: &GeneratorTestMode, context: &HashMap<&str, Value>) -> models::Response {
-  let mut response = response.clone();
-  let generators = response.build_generators(&GeneratorCategory::STATUS);
-  if !generators.is_empty() {
-    debug!("Applying status generator...");
-    apply_generators(mode, &generators, &mut |_, generator| {
-      if let Ok(v) = generator.generate_value(&response.status, context) {
-        debug!("Generated value for status: {}", v);
-        response.status = v;
-      }
-    });
-  }
-  let generators = response.build_generators(&GeneratorCategory::HEADER);
-  if !generators.is_empty() {
-    debug!("Applying header generators...");
-    apply_generators(mode, &generators, &mut |key, generator| {
-      if let Some(ref mut headers) = response.headers {
-        if headers.contains_key(key) {
-          match generator.generate_value(&headers.get(key).unwrap().clone(), context) {
-            Ok(v) => {
-              debug!("Generated value for header: {} -> {:?}", key, v);
-              headers.insert(key.clone(), v)
-            },
-            Err(_) => None
-          };
+  with_generator_seed_from_context(context, || {
+    let mut response = response.clone();
+    let generators = response.build_generators(&GeneratorCategory::STATUS);
+    if !generators.is_empty() {
+      debug!("Applying status generator...");
+      apply_generators(mode, &generators, &mut |_, generator| {
+        if let Ok(v) = generator.generate_value(&response.status, context) {
+          debug!("Generated value for status: {}", v);
+          response.status = v;
         }
-      }
-    });
-  }
-  let generators = response.build_generators(&GeneratorCategory::BODY);
-  if !generators.is_empty() && response.body.is_present() {
-    debug!("Applying body generators...");
-    response.body = apply_body_generators(mode, &response.body, response.content_type(), context, &generators);
+      });
+    }
+    let generators = response.build_generators(&GeneratorCategory::HEADER);
+    if !generators.is_empty() {
+      debug!("Applying header generators...");
+      apply_generators(mode, &generators, &mut |key, generator| {
+        if let Some(ref mut headers) = response.headers {
+          generate_header_values(headers, key, generator, context);
+        }
+      });
+    }
+    let generators = response.build_generators(&GeneratorCategory::BODY);
+    if !generators.is_empty() && response.body.is_present() {
+      debug!("Applying body generators...");
+      response.body = apply_body_generators(mode, &response.body, response.content_type(), context, &generators);
+    }
+    response
+  })
+}
+
+/// Generates the message by applying any defined generators to the contents. If the context has a `generatorSeed`,
+/// the random generators are seeded with it so the generated values are reproducible
+pub fn generate_message(message: &models::message::Message, mode: &GeneratorTestMode, context: &HashMap<&str, Value>) -> models::message::Message {
+  with_generator_seed_from_context(context, || {
+    let mut message = message.clone();
+    let generators = message.build_generators(&GeneratorCategory::BODY);
+    if !generators.is_empty() && message.contents.is_present() {
+      debug!("Applying contents generators...");
+      message.contents = apply_body_generators(mode, &message.contents,
+        Interaction::content_type(&message), context, &generators);
+    }
+    let generators = message.build_generators(&GeneratorCategory::METADATA);
+    if !generators.is_empty() {
+      debug!("Applying metadata generators...");
+      apply_generators(mode, &generators, &mut |key, generator| {
+        if let Some(value) = message.metadata.get_mut(key) {
+          let current: &String = value;
+          if let Ok(v) = generator.generate_value(current, context) {
+            *value = v;
+          }
+        }
+      });
+    }
+    message
+  })
+}
+
+/// Generates the contents of a synchronous message (i.e. the request message) by applying any
+/// defined generators to the contents and metadata. If the context has a `generatorSeed`, the
+/// random generators are seeded with it so the generated values are reproducible
+pub fn generate_message_contents(
+  contents: &models::v4::message_parts::MessageContents,
+  mode: &GeneratorTestMode,
+  context: &HashMap<&str, Value>
+) -> models::v4::message_parts::MessageContents {
+  with_generator_seed_from_context(context, || {
+    let mut contents = contents.clone();
+    let generators = contents.build_generators(&GeneratorCategory::BODY);
+    if !generators.is_empty() && contents.contents.is_present() {
+      debug!("Applying contents generators...");
+      contents.contents = apply_body_generators(mode, &contents.contents, contents.content_type(),
+        context, &generators);
+    }
+    let generators = contents.build_generators(&GeneratorCategory::METADATA);
+    if !generators.is_empty() {
+      debug!("Applying metadata generators...");
+      apply_generators(mode, &generators, &mut |key, generator| {
+        if let Some(value) = contents.metadata.get_mut(key) {
+          let current: &Value = value;
+          if let Ok(v) = generator.generate_value(current, context) {
+            *value = v;
+          }
+        }
+      });
+    }
+    contents
+  })
+}
+
+/// Applies the generator to the values of the header, matching the header name case-insensitively
+fn generate_header_values(
+  headers: &mut HashMap<String, Vec<String>>,
+  key: &str,
+  generator: &Generator,
+  context: &HashMap<&str, Value>
+) {
+  if let Some((name, values)) = headers.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case(key)) {
+    let current: &Vec<String> = values;
+    match generator.generate_value(current, context) {
+      Ok(v) => {
+        debug!("Generated value for header: {} -> {:?}", name, v);
+        *values = v;
+      },
+      Err(err) => debug!("Failed to generate a value for header '{}' - {}", name, err)
+    }
   }
-  response
 }
 
 /// Matches the request part of the interaction
@@ -1598,3 +2090,5 @@ pub fn match_interaction(expected: Box<dyn Interaction + Send>, actual: Box<dyn
 mod tests;
 #[cfg(test)]
 mod generator_tests;
+#[cfg(test)]
+mod property_tests;
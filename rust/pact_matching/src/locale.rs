@@ -0,0 +1,253 @@
+//! The `locale` module provides the options that control how numeric strings and dates are parsed
+//! when matching values from services that format them for a particular locale.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Format of numbers in string values, i.e. `1.234,56` uses a comma as the decimal separator and
+/// a period as the grouping (thousands) separator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+  /// Character separating the integer and fractional parts of the number
+  pub decimal_separator: char,
+  /// Character separating groups of thousands, if grouping is used
+  pub grouping_separator: Option<char>
+}
+
+impl NumberFormat {
+  /// Creates a new number format with the given separators
+  pub fn new(decimal_separator: char, grouping_separator: Option<char>) -> Self {
+    NumberFormat { decimal_separator, grouping_separator }
+  }
+
+  /// Returns the number format used by the locale (i.e. `de` or `en-GB`). Only the language part
+  /// of the locale is used.
+  pub fn for_locale(locale: &str) -> Option<NumberFormat> {
+    match locale.split(['-', '_']).next().unwrap_or_default().to_lowercase().as_str() {
+      "en" => Some(NumberFormat::new('.', Some(','))),
+      "de" | "es" | "it" | "nl" | "pt" => Some(NumberFormat::new(',', Some('.'))),
+      "fr" => Some(NumberFormat::new(',', Some(' '))),
+      _ => None
+    }
+  }
+
+  /// Converts the string to a number in the standard format (no grouping and a `.` decimal
+  /// separator). Returns `None` if the string is not a number in this format. Grouping separators
+  /// are optional, but if used must separate groups of three digits.
+  pub fn normalise(&self, s: &str) -> Option<String> {
+    let (sign, unsigned) = match s.strip_prefix('-') {
+      Some(rest) => ("-", rest),
+      None => ("", s.strip_prefix('+').unwrap_or(s))
+    };
+    let mut parts = unsigned.splitn(2, self.decimal_separator);
+    let integer = parts.next().unwrap_or_default();
+    let fraction = parts.next();
+
+    let integer = match self.grouping_separator {
+      Some(separator) if integer.contains(separator) => {
+        let groups = integer.split(separator).collect::<Vec<&str>>();
+        let first = groups[0];
+        if first.is_empty() || first.len() > 3 || groups[1..].iter().any(|group| group.len() != 3) {
+          return None;
+        }
+        groups.concat()
+      },
+      _ => integer.to_string()
+    };
+
+    if integer.is_empty() || !integer.chars().all(|c| c.is_ascii_digit()) {
+      return None;
+    }
+    match fraction {
+      Some(fraction) if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) => None,
+      Some(fraction) => Some(format!("{}{}.{}", sign, integer, fraction)),
+      None => Some(format!("{}{}", sign, integer))
+    }
+  }
+
+  /// Parses the string as a number in this format
+  pub fn parse_number(&self, s: &str) -> Option<f64> {
+    self.normalise(s).and_then(|n| n.parse().ok())
+  }
+
+  /// Parses the string as an integer in this format. Integers can not have a fractional part.
+  pub fn parse_integer(&self, s: &str) -> Option<i64> {
+    self.normalise(s).and_then(|n| n.parse().ok())
+  }
+
+  /// Parses the string as a decimal number in this format. Decimal numbers must have a fractional part.
+  pub fn parse_decimal(&self, s: &str) -> Option<f64> {
+    if s.contains(self.decimal_separator) {
+      self.parse_number(s)
+    } else {
+      None
+    }
+  }
+}
+
+impl Default for NumberFormat {
+  fn default() -> Self {
+    NumberFormat::new('.', None)
+  }
+}
+
+/// Locales whose month and day names are accepted in date, time and timestamp values. English
+/// names are always accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateLocale {
+  /// English
+  En,
+  /// German
+  De,
+  /// French
+  Fr,
+  /// Spanish
+  Es
+}
+
+impl DateLocale {
+  /// Full month names, from January to December
+  pub fn month_names(&self) -> [&'static str; 12] {
+    match self {
+      DateLocale::En => ["January", "February", "March", "April", "May", "June", "July", "August",
+        "September", "October", "November", "December"],
+      DateLocale::De => ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August",
+        "September", "Oktober", "November", "Dezember"],
+      DateLocale::Fr => ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août",
+        "septembre", "octobre", "novembre", "décembre"],
+      DateLocale::Es => ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto",
+        "septiembre", "octubre", "noviembre", "diciembre"]
+    }
+  }
+
+  /// Abbreviated month names, from January to December
+  pub fn short_month_names(&self) -> [&'static str; 12] {
+    match self {
+      DateLocale::En => ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"],
+      DateLocale::De => ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"],
+      DateLocale::Fr => ["janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+        "nov.", "déc."],
+      DateLocale::Es => ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic"]
+    }
+  }
+
+  /// Full day names, from Monday to Sunday
+  pub fn day_names(&self) -> [&'static str; 7] {
+    match self {
+      DateLocale::En => ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"],
+      DateLocale::De => ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"],
+      DateLocale::Fr => ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+      DateLocale::Es => ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"]
+    }
+  }
+
+  /// Abbreviated day names, from Monday to Sunday
+  pub fn short_day_names(&self) -> [&'static str; 7] {
+    match self {
+      DateLocale::En => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+      DateLocale::De => ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+      DateLocale::Fr => ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+      DateLocale::Es => ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"]
+    }
+  }
+}
+
+impl FromStr for DateLocale {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.split(['-', '_']).next().unwrap_or_default().to_lowercase().as_str() {
+      "en" => Ok(DateLocale::En),
+      "de" => Ok(DateLocale::De),
+      "fr" => Ok(DateLocale::Fr),
+      "es" => Ok(DateLocale::Es),
+      _ => Err(format!("'{}' is not a supported date locale", s))
+    }
+  }
+}
+
+impl Display for DateLocale {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DateLocale::En => write!(f, "en"),
+      DateLocale::De => write!(f, "de"),
+      DateLocale::Fr => write!(f, "fr"),
+      DateLocale::Es => write!(f, "es")
+    }
+  }
+}
+
+/// Options that control how numeric strings and dates are parsed when matching. The defaults
+/// only accept plain numbers (`.` decimal separator and no grouping) and English month and day names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocaleOptions {
+  /// Format of numbers in string values. When set, numeric strings in JSON bodies will also match
+  /// the number, integer and decimal matchers.
+  pub number_format: Option<NumberFormat>,
+  /// Additional locales whose month and day names are accepted by the date, time and timestamp matchers
+  pub date_locales: Vec<DateLocale>
+}
+
+impl LocaleOptions {
+  /// Returns the options for the locale (i.e. `de-DE`), using its number format and month and day names
+  pub fn for_locale(locale: &str) -> Result<LocaleOptions, String> {
+    Ok(LocaleOptions {
+      number_format: NumberFormat::for_locale(locale),
+      date_locales: vec![DateLocale::from_str(locale)?]
+    })
+  }
+
+  /// Number format to use for numeric strings
+  pub fn number_format(&self) -> NumberFormat {
+    self.number_format.unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  #[test]
+  fn normalise_with_the_default_format() {
+    let format = NumberFormat::default();
+    expect!(format.normalise("100")).to(be_some().value("100"));
+    expect!(format.normalise("-100.25")).to(be_some().value("-100.25"));
+    expect!(format.normalise("+1")).to(be_some().value("1"));
+    expect!(format.normalise("1,000")).to(be_none());
+    expect!(format.normalise("1.")).to(be_none());
+    expect!(format.normalise("")).to(be_none());
+    expect!(format.normalise("1e5")).to(be_none());
+  }
+
+  #[test]
+  fn normalise_with_grouping_and_decimal_separators() {
+    let format = NumberFormat::new(',', Some('.'));
+    expect!(format.normalise("1.234.567,89")).to(be_some().value("1234567.89"));
+    expect!(format.normalise("1234,5")).to(be_some().value("1234.5"));
+    expect!(format.normalise("-12.345")).to(be_some().value("-12345"));
+    expect!(format.normalise("12.34")).to(be_none());
+    expect!(format.normalise("1234.567")).to(be_none());
+    expect!(format.normalise(".123")).to(be_none());
+    expect!(format.normalise("1,2,3")).to(be_none());
+  }
+
+  #[test]
+  fn parse_integer_and_decimal() {
+    let format = NumberFormat::for_locale("fr-FR").unwrap();
+    expect!(format.parse_integer("1 000")).to(be_some().value(1000));
+    expect!(format.parse_integer("1 000,5")).to(be_none());
+    expect!(format.parse_decimal("1 000,5")).to(be_some().value(1000.5));
+    expect!(format.parse_decimal("1 000")).to(be_none());
+  }
+
+  #[test]
+  fn locale_options_for_locale() {
+    expect!(LocaleOptions::for_locale("de_DE")).to(be_ok().value(LocaleOptions {
+      number_format: Some(NumberFormat::new(',', Some('.'))),
+      date_locales: vec![DateLocale::De]
+    }));
+    expect!(LocaleOptions::for_locale("xx")).to(be_err());
+  }
+}
@@ -7,27 +7,46 @@ use log::*;
 
 use crate::MatchingContext;
 use crate::models::matchingrules::*;
-use crate::time_utils::validate_datetime;
+use crate::locale::LocaleOptions;
+use crate::time_utils::validate_datetime_with_locales;
 use crate::binary_utils::match_content_type;
 
 pub trait Matches<A> {
   fn matches(&self, actual: &A, matcher: &MatchingRule) -> Result<(), String>;
+
+  /// Matches using the locale options for parsing numeric strings and dates. Only values that
+  /// can be formatted for a locale override this.
+  fn matches_with_locale(&self, actual: &A, matcher: &MatchingRule, _locale: &LocaleOptions) -> Result<(), String> {
+    self.matches(actual, matcher)
+  }
 }
 
 impl Matches<String> for String {
   fn matches(&self, actual: &String, matcher: &MatchingRule) -> Result<(), String> {
     self.matches(&actual.as_str(), matcher)
   }
+
+  fn matches_with_locale(&self, actual: &String, matcher: &MatchingRule, locale: &LocaleOptions) -> Result<(), String> {
+    self.matches_with_locale(&actual.as_str(), matcher, locale)
+  }
 }
 
 impl Matches<&str> for &str {
   fn matches(&self, actual: &&str, matcher: &MatchingRule) -> Result<(), String> {
     self.to_string().matches(actual, matcher)
   }
+
+  fn matches_with_locale(&self, actual: &&str, matcher: &MatchingRule, locale: &LocaleOptions) -> Result<(), String> {
+    self.to_string().matches_with_locale(actual, matcher, locale)
+  }
 }
 
 impl Matches<&str> for String {
   fn matches(&self, actual: &&str, matcher: &MatchingRule) -> Result<(), String> {
+    self.matches_with_locale(actual, matcher, &LocaleOptions::default())
+  }
+
+  fn matches_with_locale(&self, actual: &&str, matcher: &MatchingRule, locale: &LocaleOptions) -> Result<(), String> {
     log::debug!("String -> String: comparing '{}' to '{}' using {:?}", self, actual, matcher);
     match *matcher {
       MatchingRule::Regex(ref regex) => {
@@ -60,32 +79,40 @@ impl Matches<&str> for String {
           Err(format!("Expected '{}' to include '{}'", actual, substr))
         }
       },
-      MatchingRule::Number | MatchingRule::Decimal => {
-        match actual.parse::<f64>() {
+      MatchingRule::Number | MatchingRule::Decimal => match locale.number_format {
+        Some(format) => match format.parse_number(actual) {
+          Some(_) => Ok(()),
+          None => Err(format!("Expected '{}' to match a number", actual))
+        },
+        None => match actual.parse::<f64>() {
           Ok(_) => Ok(()),
           Err(_) => Err(format!("Expected '{}' to match a number", actual))
         }
       },
-      MatchingRule::Integer => {
-        match actual.parse::<u64>() {
+      MatchingRule::Integer => match locale.number_format {
+        Some(format) => match format.parse_integer(actual) {
+          Some(_) => Ok(()),
+          None => Err(format!("Expected '{}' to match an integer number", actual))
+        },
+        None => match actual.parse::<u64>() {
           Ok(_) => Ok(()),
           Err(_) => Err(format!("Expected '{}' to match an integer number", actual))
         }
       },
       MatchingRule::Date(ref s) => {
-        match validate_datetime(&actual.to_string(), s) {
+        match validate_datetime_with_locales(actual, s, &locale.date_locales) {
           Ok(_) => Ok(()),
           Err(_) => Err(format!("Expected '{}' to match a date format of '{}'", actual, s))
         }
       },
       MatchingRule::Time(ref s) => {
-        match validate_datetime(&actual.to_string(), s) {
+        match validate_datetime_with_locales(actual, s, &locale.date_locales) {
           Ok(_) => Ok(()),
           Err(_) => Err(format!("Expected '{}' to match a time format of '{}'", actual, s))
         }
       },
       MatchingRule::Timestamp(ref s) => {
-        match validate_datetime(&actual.to_string(), s) {
+        match validate_datetime_with_locales(actual, s, &locale.date_locales) {
           Ok(_) => Ok(()),
           Err(_) => Err(format!("Expected '{}' to match a timestamp format of '{}'", actual, s))
         }
@@ -341,7 +368,7 @@ pub fn match_values<E, A>(path: &[&str], context: &MatchingContext, expected: &E
         None => Err(vec![format!("No matcher found for path '{}'", path.iter().join("."))]),
         Some(ref rulelist) => {
           let results = rulelist.rules.iter().map(|rule| {
-            expected.matches(actual, rule)
+            expected.matches_with_locale(actual, rule, &context.locale)
           }).collect::<Vec<Result<(), String>>>();
           match rulelist.rule_logic {
             RuleLogic::And => {
@@ -0,0 +1,367 @@
+//! Loosens the exact value expectations of a pact, by replacing the values of volatile fields
+//! (timestamps, UUIDs and numeric IDs) with matchers and generators. Pacts generated from recorded
+//! traffic often have these values, which then only match the exact recorded value.
+
+use std::fmt::{Display, Formatter};
+
+use lazy_static::*;
+use onig::Regex;
+use serde_json::Value;
+
+use pact_models::{OptionalBody, PactSpecification};
+use pact_models::content_types::ContentType;
+
+use crate::models::generators::{Generator, GeneratorCategory, Generators};
+use crate::models::matchingrules::{MatchingRule, MatchingRules, RuleLogic};
+
+lazy_static! {
+  static ref UUID_REGEX: Regex = Regex::new(UUID_PATTERN).unwrap();
+  static ref DATE_REGEX: Regex = Regex::new(DATE_PATTERN).unwrap();
+  static ref TIMESTAMP_REGEX: Regex = Regex::new(TIMESTAMP_PATTERN).unwrap();
+  static ref IDENTIFIER_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9_\-]+$").unwrap();
+}
+
+const UUID_PATTERN: &str = "^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$";
+const DATE_PATTERN: &str = r"^\d{4}-\d{2}-\d{2}$";
+const TIMESTAMP_PATTERN: &str = r"^\d{4}-\d{2}-\d{2}([T ])\d{2}:\d{2}:\d{2}(\.\d{3})?(Z|[+-]\d{2}:\d{2}|[+-]\d{4})?$";
+
+/// Type of volatile value detected in a body
+#[derive(Debug, Clone, PartialEq)]
+pub enum VolatileValue {
+  /// UUID string
+  Uuid,
+  /// Date and time string, with the format of the value
+  Timestamp(String),
+  /// Date string, with the format of the value
+  Date(String),
+  /// Integer value of an ID attribute (i.e. `id`, `userId` or `user_id`)
+  Id
+}
+
+impl VolatileValue {
+  /// Detects if the value (with the given attribute name) is a volatile value
+  pub fn detect(name: Option<&str>, value: &Value) -> Option<VolatileValue> {
+    match value {
+      Value::String(s) => if UUID_REGEX.is_match(s) {
+        Some(VolatileValue::Uuid)
+      } else if DATE_REGEX.is_match(s) {
+        Some(VolatileValue::Date("yyyy-MM-dd".to_string()))
+      } else {
+        TIMESTAMP_REGEX.captures(s).map(|captures| {
+          let mut format = "yyyy-MM-dd".to_string();
+          format.push_str(if captures.at(1) == Some("T") { "'T'" } else { " " });
+          format.push_str("HH:mm:ss");
+          if captures.at(2).is_some() {
+            format.push_str(".SSS");
+          }
+          match captures.at(3) {
+            Some(zone) if zone.len() == 5 => format.push('Z'),
+            Some(_) => format.push_str("XXX"),
+            None => ()
+          }
+          VolatileValue::Timestamp(format)
+        })
+      },
+      Value::Number(n) if n.is_i64() || n.is_u64() => match name {
+        Some(name) if is_id_attribute(name) => Some(VolatileValue::Id),
+        _ => None
+      },
+      _ => None
+    }
+  }
+
+  /// Matching rule to use for the value. Pacts before V3 only support regex and type matchers.
+  pub fn matching_rule(&self, spec_version: &PactSpecification) -> MatchingRule {
+    if spec_version < &PactSpecification::V3 {
+      match self {
+        VolatileValue::Uuid => MatchingRule::Regex(UUID_PATTERN.to_string()),
+        VolatileValue::Timestamp(_) => MatchingRule::Regex(TIMESTAMP_PATTERN.to_string()),
+        VolatileValue::Date(_) => MatchingRule::Regex(DATE_PATTERN.to_string()),
+        VolatileValue::Id => MatchingRule::Type
+      }
+    } else {
+      match self {
+        VolatileValue::Uuid => MatchingRule::Regex(UUID_PATTERN.to_string()),
+        VolatileValue::Timestamp(format) => MatchingRule::Timestamp(format.clone()),
+        VolatileValue::Date(format) => MatchingRule::Date(format.clone()),
+        VolatileValue::Id => MatchingRule::Integer
+      }
+    }
+  }
+
+  /// Generator to use for the value. Pacts before V3 do not support generators.
+  pub fn generator(&self, spec_version: &PactSpecification) -> Option<Generator> {
+    if spec_version < &PactSpecification::V3 {
+      None
+    } else {
+      Some(match self {
+        VolatileValue::Uuid => Generator::Uuid,
+        VolatileValue::Timestamp(format) => Generator::DateTime(Some(format.clone()), None),
+        VolatileValue::Date(format) => Generator::Date(Some(format.clone()), None),
+        VolatileValue::Id => Generator::RandomInt(1, i32::MAX)
+      })
+    }
+  }
+}
+
+impl Display for VolatileValue {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      VolatileValue::Uuid => write!(f, "UUID"),
+      VolatileValue::Timestamp(format) => write!(f, "timestamp ({})", format),
+      VolatileValue::Date(format) => write!(f, "date ({})", format),
+      VolatileValue::Id => write!(f, "numeric ID")
+    }
+  }
+}
+
+fn is_id_attribute(name: &str) -> bool {
+  name.eq_ignore_ascii_case("id") || name.ends_with("Id") || name.ends_with("ID") ||
+    name.to_lowercase().ends_with("_id") || name.to_lowercase().ends_with("-id")
+}
+
+/// Field of a body that was loosened
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvolvedField {
+  /// Path to the field (i.e. `$.items[0].id`)
+  pub path: String,
+  /// Type of volatile value that was detected
+  pub value: VolatileValue,
+  /// Matching rule that was added
+  pub rule: MatchingRule,
+  /// Generator that was added (if any)
+  pub generator: Option<Generator>
+}
+
+/// Replaces the exact value expectations on the volatile fields of the body with matchers and
+/// generators. Only JSON bodies are supported, and fields that already have a matcher (or a parent
+/// with a matcher) are left as they are. Returns the fields that were loosened.
+pub fn evolve_body(
+  body: &OptionalBody,
+  content_type: Option<ContentType>,
+  matching_rules: &mut MatchingRules,
+  generators: &mut Generators,
+  spec_version: &PactSpecification
+) -> Vec<EvolvedField> {
+  let is_json = content_type.map(|ct| ct.is_json()).unwrap_or(false);
+  match body {
+    OptionalBody::Present(bytes, _) if is_json => match serde_json::from_slice(bytes) {
+      Ok(json) => evolve_json_body(&json, matching_rules, generators, spec_version),
+      Err(err) => {
+        log::warn!("Not evolving the body as it is not valid JSON - {}", err);
+        vec![]
+      }
+    },
+    _ => vec![]
+  }
+}
+
+/// Replaces the exact value expectations on the volatile fields of the JSON body with matchers and
+/// generators. See `evolve_body`.
+pub fn evolve_json_body(
+  json: &Value,
+  matching_rules: &mut MatchingRules,
+  generators: &mut Generators,
+  spec_version: &PactSpecification
+) -> Vec<EvolvedField> {
+  let mut fields = vec![];
+  walk_json(json, None, &mut vec!["$".to_string()], &mut |path, value| {
+    let path_segments = path.iter().map(|s| s.as_str()).collect();
+    if !matching_rules.matcher_is_defined("body", &path_segments) {
+      fields.push(EvolvedField {
+        path: path_expression(path),
+        value: value.clone(),
+        rule: value.matching_rule(spec_version),
+        generator: value.generator(spec_version)
+      });
+    }
+  });
+
+  for field in &fields {
+    matching_rules.add_category("body").add_rule(field.path.as_str(), field.rule.clone(), &RuleLogic::And);
+    if let Some(generator) = &field.generator {
+      let exists = generators.categories.get(&GeneratorCategory::BODY)
+        .map(|body| body.contains_key(&field.path))
+        .unwrap_or(false);
+      if !exists {
+        generators.add_generator_with_subcategory(&GeneratorCategory::BODY, field.path.as_str(), generator.clone());
+      }
+    }
+  }
+  fields
+}
+
+fn walk_json<F>(json: &Value, name: Option<&str>, path: &mut Vec<String>, callback: &mut F)
+  where F: FnMut(&[String], &VolatileValue) {
+  match json {
+    Value::Object(map) => for (key, value) in map {
+      path.push(key.clone());
+      walk_json(value, Some(key), path, callback);
+      path.pop();
+    },
+    Value::Array(items) => for (index, value) in items.iter().enumerate() {
+      path.push(index.to_string());
+      walk_json(value, name, path, callback);
+      path.pop();
+    },
+    _ => if let Some(value) = VolatileValue::detect(name, json) {
+      callback(path, &value);
+    }
+  }
+}
+
+fn path_expression(path: &[String]) -> String {
+  let mut expression = String::new();
+  for (index, segment) in path.iter().enumerate() {
+    if index == 0 {
+      expression.push_str(segment);
+    } else if segment.chars().all(|ch| ch.is_ascii_digit()) {
+      expression.push_str(&format!("[{}]", segment));
+    } else if IDENTIFIER_REGEX.is_match(segment) {
+      expression.push('.');
+      expression.push_str(segment);
+    } else {
+      expression.push_str(&format!("['{}']", segment.replace('\\', "\\\\").replace('\'', "\\'")));
+    }
+  }
+  expression
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use crate::models::generators::GenerateValue;
+  use crate::time_utils::validate_datetime;
+
+  use super::*;
+
+  #[test]
+  fn detect_volatile_values() {
+    expect!(VolatileValue::detect(None, &json!("f2e62b6b-5c8a-4b8c-9c2e-2b6b5c8a4b8c")))
+      .to(be_some().value(VolatileValue::Uuid));
+    expect!(VolatileValue::detect(None, &json!("2021-05-04")))
+      .to(be_some().value(VolatileValue::Date("yyyy-MM-dd".to_string())));
+    expect!(VolatileValue::detect(None, &json!("2021-05-04T10:11:12")))
+      .to(be_some().value(VolatileValue::Timestamp("yyyy-MM-dd'T'HH:mm:ss".to_string())));
+    expect!(VolatileValue::detect(None, &json!("2021-05-04T10:11:12.123Z")))
+      .to(be_some().value(VolatileValue::Timestamp("yyyy-MM-dd'T'HH:mm:ss.SSSXXX".to_string())));
+    expect!(VolatileValue::detect(None, &json!("2021-05-04 10:11:12+1000")))
+      .to(be_some().value(VolatileValue::Timestamp("yyyy-MM-dd HH:mm:ssZ".to_string())));
+    expect!(VolatileValue::detect(Some("id"), &json!(100))).to(be_some().value(VolatileValue::Id));
+    expect!(VolatileValue::detect(Some("userId"), &json!(100))).to(be_some().value(VolatileValue::Id));
+    expect!(VolatileValue::detect(Some("user_id"), &json!(100))).to(be_some().value(VolatileValue::Id));
+    expect!(VolatileValue::detect(Some("count"), &json!(100))).to(be_none());
+    expect!(VolatileValue::detect(Some("id"), &json!(1.5))).to(be_none());
+    expect!(VolatileValue::detect(Some("name"), &json!("Fred"))).to(be_none());
+    expect!(VolatileValue::detect(None, &json!("2021-05-04 is a date"))).to(be_none());
+    expect!(VolatileValue::detect(None, &json!("2021-05-04T10:11:12.1"))).to(be_none());
+  }
+
+  #[test]
+  fn detected_timestamp_formats_match_the_values() {
+    for value in &["2021-05-04T10:11:12", "2021-05-04T10:11:12.123Z", "2021-05-04 10:11:12+1000",
+      "2021-05-04T10:11:12.123+10:00"] {
+      match VolatileValue::detect(None, &json!(value)) {
+        Some(VolatileValue::Timestamp(format)) => {
+          expect!(validate_datetime(&value.to_string(), &format)).to(be_ok());
+          let generated = Generator::DateTime(Some(format.clone()), None)
+            .generate_value(&value.to_string(), &hashmap!{}).unwrap();
+          expect!(validate_datetime(&generated, &format)).to(be_ok());
+        },
+        result => panic!("Expected a timestamp for {}, got {:?}", value, result)
+      }
+    }
+  }
+
+  #[test]
+  fn evolve_json_body_adds_matchers_and_generators_for_volatile_fields() {
+    let body = json!({
+      "id": 1234,
+      "name": "Fred",
+      "created": "2021-05-04T10:11:12",
+      "items": [
+        { "itemId": 10, "ref": "f2e62b6b-5c8a-4b8c-9c2e-2b6b5c8a4b8c" }
+      ],
+      "due date": "2021-06-01"
+    });
+    let mut matching_rules = MatchingRules::default();
+    let mut generators = Generators::default();
+
+    let fields = evolve_json_body(&body, &mut matching_rules, &mut generators, &PactSpecification::V3);
+
+    expect!(fields.iter().map(|f| f.path.clone()).collect::<Vec<String>>()).to(be_equal_to(vec![
+      "$.created".to_string(),
+      "$['due date']".to_string(),
+      "$.id".to_string(),
+      "$.items[0].itemId".to_string(),
+      "$.items[0].ref".to_string()
+    ]));
+    expect!(matching_rules).to(be_equal_to(matchingrules! {
+      "body" => {
+        "$.created" => [ MatchingRule::Timestamp("yyyy-MM-dd'T'HH:mm:ss".to_string()) ],
+        "$['due date']" => [ MatchingRule::Date("yyyy-MM-dd".to_string()) ],
+        "$.id" => [ MatchingRule::Integer ],
+        "$.items[0].itemId" => [ MatchingRule::Integer ],
+        "$.items[0].ref" => [ MatchingRule::Regex(UUID_PATTERN.to_string()) ]
+      }
+    }));
+    let body_generators = &generators.categories[&GeneratorCategory::BODY];
+    expect!(body_generators.len()).to(be_equal_to(5));
+    expect!(body_generators.get("$.items[0].ref")).to(be_some().value(&Generator::Uuid));
+    expect!(body_generators.get("$.id")).to(be_some().value(&Generator::RandomInt(1, i32::MAX)));
+  }
+
+  #[test]
+  fn evolve_json_body_does_not_change_fields_with_matchers() {
+    let body = json!({
+      "id": 1234,
+      "items": [ { "id": 10 }, { "id": 11 } ]
+    });
+    let mut matching_rules = matchingrules! {
+      "body" => {
+        "$.id" => [ MatchingRule::Type ],
+        "$.items" => [ MatchingRule::MinType(1) ]
+      }
+    };
+    let expected_rules = matching_rules.clone();
+    let mut generators = Generators::default();
+
+    let fields = evolve_json_body(&body, &mut matching_rules, &mut generators, &PactSpecification::V3);
+
+    expect!(fields.iter()).to(be_empty());
+    expect!(matching_rules).to(be_equal_to(expected_rules));
+    expect!(generators.is_empty()).to(be_true());
+  }
+
+  #[test]
+  fn evolve_json_body_only_uses_v2_matchers_for_v2_pacts() {
+    let body = json!({ "id": 1234, "created": "2021-05-04T10:11:12" });
+    let mut matching_rules = MatchingRules::default();
+    let mut generators = Generators::default();
+
+    let fields = evolve_json_body(&body, &mut matching_rules, &mut generators, &PactSpecification::V2);
+
+    expect!(fields.len()).to(be_equal_to(2));
+    expect!(matching_rules).to(be_equal_to(matchingrules! {
+      "body" => {
+        "$.created" => [ MatchingRule::Regex(TIMESTAMP_PATTERN.to_string()) ],
+        "$.id" => [ MatchingRule::Type ]
+      }
+    }));
+    expect!(generators.is_empty()).to(be_true());
+  }
+
+  #[test]
+  fn evolve_body_ignores_bodies_that_are_not_json() {
+    let body = OptionalBody::Present("2021-05-04".into(), None);
+    let mut matching_rules = MatchingRules::default();
+    let mut generators = Generators::default();
+    expect!(evolve_body(&body, Some(ContentType::from("text/plain")), &mut matching_rules,
+      &mut generators, &PactSpecification::V3).iter()).to(be_empty());
+    expect!(evolve_body(&OptionalBody::Missing, Some(ContentType::from("application/json")),
+      &mut matching_rules, &mut generators, &PactSpecification::V3).iter()).to(be_empty());
+  }
+}
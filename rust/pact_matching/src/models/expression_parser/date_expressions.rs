@@ -0,0 +1,352 @@
+//! Date and time expressions for the `Date`, `Time` and `DateTime` generators, which generate
+//! values relative to the current date and time (i.e. `today + 2 weeks @ midnight`).
+//!
+//! A date expression starts with `today`, `now`, `tomorrow`, `yesterday` or `next`/`last` followed
+//! by a period (`day`, `week`, `fortnight`, `month`, `year`), a day of the week or a month, and then
+//! any number of offsets (`+ 2 days - 1 month`). A time expression starts with `now`, `midnight`,
+//! `noon` or an hour (`2 o'clock pm`), followed by any number of offsets in hours, minutes, seconds
+//! or milliseconds. A date-time expression is a date expression and a time expression separated
+//! with `@`.
+
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Weekday};
+
+const MONTHS: [&str; 12] = [
+  "january", "february", "march", "april", "may", "june", "july", "august", "september",
+  "october", "november", "december"
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Number(i64),
+  Word(String),
+  Plus,
+  Minus
+}
+
+impl Display for Token {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Number(number) => write!(f, "{}", number),
+      Token::Word(word) => write!(f, "'{}'", word),
+      Token::Plus => write!(f, "'+'"),
+      Token::Minus => write!(f, "'-'")
+    }
+  }
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+/// Evaluates the date expression (i.e. `today + 2 days` or `next monday`) relative to the base
+/// date-time. The time of day of the base is not changed.
+pub fn execute_date_expression(base: &DateTime<Local>, expression: &str) -> Result<DateTime<Local>, String> {
+  evaluate_date(&base.naive_local(), expression)
+    .and_then(to_local)
+    .map_err(|err| format!("'{}' is not a valid date expression - {}", expression, err))
+}
+
+/// Evaluates the time expression (i.e. `now + 2 hours` or `midnight`) relative to the base
+/// date-time.
+pub fn execute_time_expression(base: &DateTime<Local>, expression: &str) -> Result<DateTime<Local>, String> {
+  evaluate_time(&base.naive_local(), expression)
+    .and_then(to_local)
+    .map_err(|err| format!("'{}' is not a valid time expression - {}", expression, err))
+}
+
+/// Evaluates the date-time expression (i.e. `tomorrow @ 2 o'clock pm`) relative to the base
+/// date-time. Expressions without a `@` can be either a date or a time expression.
+pub fn execute_datetime_expression(base: &DateTime<Local>, expression: &str) -> Result<DateTime<Local>, String> {
+  let base = base.naive_local();
+  let result = match expression.split_once('@') {
+    Some((date, time)) => evaluate_date(&base, date)
+      .and_then(|date_time| evaluate_time(&date_time, time)),
+    None => evaluate_date(&base, expression).or_else(|date_err| {
+      evaluate_time(&base, expression).map_err(|time_err| {
+        format!("it is neither a valid date ({}) nor a valid time ({})", date_err, time_err)
+      })
+    })
+  };
+  result.and_then(to_local)
+    .map_err(|err| format!("'{}' is not a valid date-time expression - {}", expression, err))
+}
+
+fn to_local(date_time: NaiveDateTime) -> Result<DateTime<Local>, String> {
+  Local.from_local_datetime(&date_time).earliest()
+    .ok_or_else(|| format!("{} does not exist in the local time zone", date_time))
+}
+
+fn evaluate_date(base: &NaiveDateTime, expression: &str) -> Result<NaiveDateTime, String> {
+  let mut tokens = tokenise(expression)?;
+  let date = date_base(base.date(), &mut tokens)?;
+  let date = apply_offsets(date, &mut tokens, offset_date)?;
+  Ok(date.and_time(base.time()))
+}
+
+fn evaluate_time(base: &NaiveDateTime, expression: &str) -> Result<NaiveDateTime, String> {
+  let mut tokens = tokenise(expression)?;
+  let time = time_base(base, &mut tokens)?;
+  apply_offsets(time, &mut tokens, offset_time)
+}
+
+fn tokenise(expression: &str) -> Result<Tokens, String> {
+  let mut tokens = vec![];
+  let mut chars = expression.char_indices().peekable();
+  while let Some((index, ch)) = chars.next() {
+    match ch {
+      '+' => tokens.push(Token::Plus),
+      '-' => tokens.push(Token::Minus),
+      _ if ch.is_whitespace() => (),
+      _ if ch.is_ascii_digit() => {
+        let mut number = ch.to_string();
+        while let Some((_, ch)) = chars.next_if(|(_, ch)| ch.is_ascii_digit()) {
+          number.push(ch);
+        }
+        let value = number.parse::<u32>()
+          .map_err(|err| format!("'{}' is not a valid number - {}", number, err))?;
+        tokens.push(Token::Number(value as i64));
+      },
+      _ if ch.is_alphabetic() => {
+        let mut word = ch.to_string();
+        while let Some((_, ch)) = chars.next_if(|(_, ch)| ch.is_alphabetic() || *ch == '\'') {
+          word.push(ch);
+        }
+        tokens.push(Token::Word(word.to_lowercase()));
+      },
+      _ => return Err(format!("unexpected character '{}' at index {}", ch, index))
+    }
+  }
+  Ok(tokens.into_iter().peekable())
+}
+
+fn apply_offsets<T>(
+  value: T,
+  tokens: &mut Tokens,
+  offset: fn(T, i64, &str) -> Result<T, String>
+) -> Result<T, String> {
+  let mut value = value;
+  while let Some(token) = tokens.next() {
+    let sign = match token {
+      Token::Plus => 1,
+      Token::Minus => -1,
+      _ => return Err(format!("expected '+' or '-' but found {}", token))
+    };
+    let amount = match tokens.next() {
+      Some(Token::Number(amount)) => amount,
+      Some(token) => return Err(format!("expected a number but found {}", token)),
+      None => return Err("expected a number at the end of the expression".to_string())
+    };
+    let unit = match tokens.next() {
+      Some(Token::Word(unit)) => unit,
+      Some(token) => return Err(format!("expected a unit but found {}", token)),
+      None => return Err("expected a unit at the end of the expression".to_string())
+    };
+    value = offset(value, sign * amount, &unit)?;
+  }
+  Ok(value)
+}
+
+fn date_base(today: NaiveDate, tokens: &mut Tokens) -> Result<NaiveDate, String> {
+  let base = match tokens.peek() {
+    Some(Token::Word(base)) => base.clone(),
+    _ => return Ok(today)
+  };
+  tokens.next();
+  match base.as_str() {
+    "today" | "now" => Ok(today),
+    "tomorrow" => offset_date(today, 1, "day"),
+    "yesterday" => offset_date(today, -1, "day"),
+    "next" | "last" => {
+      let direction = if base == "next" { 1 } else { -1 };
+      match tokens.next() {
+        Some(Token::Word(period)) => relative_date(today, direction, &period),
+        Some(token) => Err(format!("expected a period, day or month after '{}' but found {}", base, token)),
+        None => Err(format!("expected a period, day or month after '{}'", base))
+      }
+    },
+    _ => Err(format!("'{}' is not a valid date", base))
+  }
+}
+
+/// The next (or last) day of the week or month after (or before) today, otherwise today offset by
+/// one period
+fn relative_date(today: NaiveDate, direction: i64, period: &str) -> Result<NaiveDate, String> {
+  if let Ok(weekday) = period.parse::<Weekday>() {
+    let days = i64::from(weekday.num_days_from_monday()) - i64::from(today.weekday().num_days_from_monday());
+    offset_date(today, direction * ((direction * days - 1).rem_euclid(7) + 1), "day")
+  } else if let Some(month) = MONTHS.iter()
+    .position(|month| *month == period || (period.len() == 3 && month.starts_with(period))) {
+    let months = month as i64 - i64::from(today.month0());
+    offset_date(today, direction * ((direction * months - 1).rem_euclid(12) + 1), "month")
+  } else {
+    offset_date(today, direction, period)
+  }
+}
+
+fn offset_date(date: NaiveDate, amount: i64, unit: &str) -> Result<NaiveDate, String> {
+  let result = match unit.strip_suffix('s').unwrap_or(unit) {
+    "day" => date.checked_add_signed(Duration::days(amount)),
+    "week" => date.checked_add_signed(Duration::weeks(amount)),
+    "fortnight" => date.checked_add_signed(Duration::weeks(2 * amount)),
+    "month" => add_months(date, amount),
+    "year" => add_months(date, 12 * amount),
+    _ => return Err(format!("'{}' is not a valid date unit, expected day, week, fortnight, month or year", unit))
+  };
+  result.ok_or_else(|| format!("{} {} from {} is out of range", amount, unit, date))
+}
+
+/// Adds the months to the date, using the last day of the month if the day is not in the month
+fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+  let total = i64::from(date.year()) * 12 + i64::from(date.month0()) + months;
+  let year = i32::try_from(total.div_euclid(12)).ok()?;
+  let month = total.rem_euclid(12) as u32 + 1;
+  let next_month = if month == 12 {
+    year.checked_add(1).and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1))
+  } else {
+    NaiveDate::from_ymd_opt(year, month + 1, 1)
+  };
+  let days_in_month = next_month.and_then(|date| date.pred_opt())?.day();
+  NaiveDate::from_ymd_opt(year, month, date.day().min(days_in_month))
+}
+
+fn time_base(now: &NaiveDateTime, tokens: &mut Tokens) -> Result<NaiveDateTime, String> {
+  match tokens.peek().cloned() {
+    Some(Token::Word(base)) => {
+      tokens.next();
+      match base.as_str() {
+        "now" => Ok(*now),
+        "midnight" => Ok(now.date().and_hms(0, 0, 0)),
+        "noon" => Ok(now.date().and_hms(12, 0, 0)),
+        _ => Err(format!("'{}' is not a valid time", base))
+      }
+    },
+    Some(Token::Number(hour)) => {
+      tokens.next();
+      match tokens.next() {
+        Some(Token::Word(word)) if word == "o'clock" || word == "oclock" => (),
+        _ => return Err(format!("expected o'clock after {}", hour))
+      }
+      let hour = match tokens.peek().cloned() {
+        Some(Token::Word(am_pm)) if am_pm == "am" || am_pm == "pm" => {
+          tokens.next();
+          if !(1..=12).contains(&hour) {
+            return Err(format!("{} o'clock {} is not a valid hour", hour, am_pm));
+          }
+          if am_pm == "pm" { hour % 12 + 12 } else { hour % 12 }
+        },
+        _ if hour > 23 => return Err(format!("{} o'clock is not a valid hour", hour)),
+        _ => hour
+      };
+      Ok(now.date().and_hms(hour as u32, 0, 0))
+    },
+    _ => Ok(*now)
+  }
+}
+
+fn offset_time(time: NaiveDateTime, amount: i64, unit: &str) -> Result<NaiveDateTime, String> {
+  let duration = match unit.strip_suffix('s').unwrap_or(unit) {
+    "hour" => Duration::hours(amount),
+    "minute" => Duration::minutes(amount),
+    "second" => Duration::seconds(amount),
+    "millisecond" => Duration::milliseconds(amount),
+    _ => return Err(format!("'{}' is not a valid time unit, expected hour, minute, second or millisecond", unit))
+  };
+  time.checked_add_signed(duration)
+    .ok_or_else(|| format!("{} {} from {} is out of range", amount, unit, time))
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  fn date_time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd(year, month, day).and_hms(hour, minute, 0)
+  }
+
+  #[test]
+  fn evaluate_date_test() {
+    // Friday, 2021-01-29 at 10:30
+    let base = date_time(2021, 1, 29, 10, 30);
+    expect!(evaluate_date(&base, "")).to(be_ok().value(base));
+    expect!(evaluate_date(&base, "today")).to(be_ok().value(base));
+    expect!(evaluate_date(&base, "now")).to(be_ok().value(base));
+    expect!(evaluate_date(&base, "tomorrow")).to(be_ok().value(date_time(2021, 1, 30, 10, 30)));
+    expect!(evaluate_date(&base, "yesterday")).to(be_ok().value(date_time(2021, 1, 28, 10, 30)));
+    expect!(evaluate_date(&base, "today + 2 days")).to(be_ok().value(date_time(2021, 1, 31, 10, 30)));
+    expect!(evaluate_date(&base, "+ 2 weeks")).to(be_ok().value(date_time(2021, 2, 12, 10, 30)));
+    expect!(evaluate_date(&base, "today - 1 fortnight")).to(be_ok().value(date_time(2021, 1, 15, 10, 30)));
+    expect!(evaluate_date(&base, "today + 1 month")).to(be_ok().value(date_time(2021, 2, 28, 10, 30)));
+    expect!(evaluate_date(&base, "today - 13 months")).to(be_ok().value(date_time(2019, 12, 29, 10, 30)));
+    expect!(evaluate_date(&base, "Tomorrow+1 YEAR-2days")).to(be_ok().value(date_time(2022, 1, 28, 10, 30)));
+    expect!(evaluate_date(&base, "next week")).to(be_ok().value(date_time(2021, 2, 5, 10, 30)));
+    expect!(evaluate_date(&base, "last month")).to(be_ok().value(date_time(2020, 12, 29, 10, 30)));
+    expect!(evaluate_date(&base, "next friday")).to(be_ok().value(date_time(2021, 2, 5, 10, 30)));
+    expect!(evaluate_date(&base, "next monday")).to(be_ok().value(date_time(2021, 2, 1, 10, 30)));
+    expect!(evaluate_date(&base, "last fri")).to(be_ok().value(date_time(2021, 1, 22, 10, 30)));
+    expect!(evaluate_date(&base, "last sunday")).to(be_ok().value(date_time(2021, 1, 24, 10, 30)));
+    expect!(evaluate_date(&base, "next january")).to(be_ok().value(date_time(2022, 1, 29, 10, 30)));
+    expect!(evaluate_date(&base, "next feb + 1 day")).to(be_ok().value(date_time(2021, 3, 1, 10, 30)));
+    expect!(evaluate_date(&base, "last december")).to(be_ok().value(date_time(2020, 12, 29, 10, 30)));
+
+    expect!(evaluate_date(&base, "someday")).to(be_err());
+    expect!(evaluate_date(&base, "today + 2")).to(be_err());
+    expect!(evaluate_date(&base, "today + days")).to(be_err());
+    expect!(evaluate_date(&base, "today + 2 hours")).to(be_err());
+    expect!(evaluate_date(&base, "today 2 days")).to(be_err());
+    expect!(evaluate_date(&base, "next")).to(be_err());
+    expect!(evaluate_date(&base, "today * 2 days")).to(be_err());
+  }
+
+  #[test]
+  fn evaluate_time_test() {
+    let base = date_time(2021, 1, 29, 10, 30);
+    expect!(evaluate_time(&base, "")).to(be_ok().value(base));
+    expect!(evaluate_time(&base, "now")).to(be_ok().value(base));
+    expect!(evaluate_time(&base, "midnight")).to(be_ok().value(date_time(2021, 1, 29, 0, 0)));
+    expect!(evaluate_time(&base, "noon")).to(be_ok().value(date_time(2021, 1, 29, 12, 0)));
+    expect!(evaluate_time(&base, "2 o'clock")).to(be_ok().value(date_time(2021, 1, 29, 2, 0)));
+    expect!(evaluate_time(&base, "2 o'clock pm")).to(be_ok().value(date_time(2021, 1, 29, 14, 0)));
+    expect!(evaluate_time(&base, "12 o'clock am")).to(be_ok().value(date_time(2021, 1, 29, 0, 0)));
+    expect!(evaluate_time(&base, "12 o'clock pm")).to(be_ok().value(date_time(2021, 1, 29, 12, 0)));
+    expect!(evaluate_time(&base, "now + 2 hours - 10 minutes")).to(be_ok().value(date_time(2021, 1, 29, 12, 20)));
+    expect!(evaluate_time(&base, "midnight - 1 second")).to(be_ok().value(
+      NaiveDate::from_ymd(2021, 1, 28).and_hms(23, 59, 59)));
+    expect!(evaluate_time(&base, "+ 1500 milliseconds")).to(be_ok().value(
+      NaiveDate::from_ymd(2021, 1, 29).and_hms_milli(10, 30, 1, 500)));
+
+    expect!(evaluate_time(&base, "today")).to(be_err());
+    expect!(evaluate_time(&base, "13 o'clock pm")).to(be_err());
+    expect!(evaluate_time(&base, "24 o'clock")).to(be_err());
+    expect!(evaluate_time(&base, "2 pm")).to(be_err());
+    expect!(evaluate_time(&base, "now + 2 days")).to(be_err());
+  }
+
+  #[test]
+  fn execute_datetime_expression_test() {
+    let base = Local.from_local_datetime(&date_time(2021, 1, 29, 10, 30)).unwrap();
+    expect!(execute_datetime_expression(&base, "tomorrow @ 2 o'clock pm").map(|dt| dt.naive_local()))
+      .to(be_ok().value(date_time(2021, 1, 30, 14, 0)));
+    expect!(execute_datetime_expression(&base, "today + 2 weeks @ midnight").map(|dt| dt.naive_local()))
+      .to(be_ok().value(date_time(2021, 2, 12, 0, 0)));
+    expect!(execute_datetime_expression(&base, "@ now + 1 hour").map(|dt| dt.naive_local()))
+      .to(be_ok().value(date_time(2021, 1, 29, 11, 30)));
+    expect!(execute_datetime_expression(&base, "next monday").map(|dt| dt.naive_local()))
+      .to(be_ok().value(date_time(2021, 2, 1, 10, 30)));
+    expect!(execute_datetime_expression(&base, "now - 30 minutes").map(|dt| dt.naive_local()))
+      .to(be_ok().value(date_time(2021, 1, 29, 10, 0)));
+    expect!(execute_datetime_expression(&base, "today + 2 fish")).to(be_err());
+    expect!(execute_datetime_expression(&base, "tomorrow @ today")).to(be_err());
+
+    expect!(execute_date_expression(&base, "today + 1 day").map(|dt| dt.naive_local()))
+      .to(be_ok().value(date_time(2021, 1, 30, 10, 30)));
+    expect!(execute_date_expression(&base, "today + 1 hour")).to(be_err().value(
+      "'today + 1 hour' is not a valid date expression - 'hour' is not a valid date unit, expected day, week, fortnight, month or year".to_string()));
+    expect!(execute_time_expression(&base, "noon + 1 hour").map(|dt| dt.naive_local()))
+      .to(be_ok().value(date_time(2021, 1, 29, 13, 0)));
+  }
+}
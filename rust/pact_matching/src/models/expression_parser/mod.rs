@@ -6,6 +6,10 @@ use std::collections::HashMap;
 use nom::lib::std::convert::TryFrom;
 use crate::models::json_utils::json_to_string;
 
+mod date_expressions;
+
+pub use date_expressions::{execute_date_expression, execute_datetime_expression, execute_time_expression};
+
 /// Data type to cast to for provider state context values
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Hash)]
 pub enum DataType {
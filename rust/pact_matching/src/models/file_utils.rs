@@ -1,5 +1,6 @@
-use std::fs::File;
-use std::path::Path;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use log::*;
 use fs2::FileExt;
 use std::thread::sleep;
@@ -81,3 +82,117 @@ pub(crate) fn with_write_lock<T>(
   error!("{}", msg);
   bail!(msg);
 }
+
+/// Name of the directory the lock files are kept in, in the directory of the files they lock
+const LOCK_DIR: &str = ".pact-locks";
+
+/// Returns the path of the lock file for the file at the given path (i.e. `.pact-locks/pact.json.lock`
+/// for `pact.json`)
+pub(crate) fn lock_file_path(path: &Path) -> PathBuf {
+  let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+  let dir = path.parent().unwrap_or_else(|| Path::new(""));
+  dir.join(LOCK_DIR).join(format!("{}.lock", file_name))
+}
+
+/// Runs the closure while holding an exclusive lock on the lock file for the given path. The lock
+/// can not be held on the file itself, as it is replaced when it is written atomically, and another
+/// process opening the path afterwards would then lock the new file. The lock files are kept in the
+/// `.pact-locks` directory so they are not mixed in with the files. They are not removed
+/// afterwards, as a process waiting for the lock on a removed lock file would get it at the same
+/// time as a process that creates a new one.
+pub(crate) fn with_lock_file<T>(
+  path: &Path,
+  attempts: u32,
+  cl: &mut dyn FnMut() -> anyhow::Result<T>
+) -> anyhow::Result<T> {
+  let lock_path = lock_file_path(path);
+  if let Some(lock_dir) = lock_path.parent() {
+    fs::create_dir_all(lock_dir)?;
+  }
+  let mut lock_file = OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path)?;
+  with_write_lock(&lock_path, &mut lock_file, attempts, &mut |_| cl())
+}
+
+/// Writes the contents to the file at the given path by first writing them to a temporary file in
+/// the same directory, and then renaming the temporary file over the target file. This means the
+/// target file either has the previous contents or the new contents, even if the process dies
+/// part way through the write.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+  let dir = path.parent().unwrap_or_else(|| Path::new("."));
+  let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+  let tmp_path = dir.join(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+  trace!("Writing '{}' via temporary file '{}'", path.to_string_lossy(), tmp_path.to_string_lossy());
+
+  let write_file = || -> io::Result<()> {
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    if let Ok(metadata) = fs::metadata(path) {
+      fs::set_permissions(&tmp_path, metadata.permissions())?;
+    }
+    fs::rename(&tmp_path, path)
+  };
+
+  let result = write_file();
+  if result.is_err() {
+    if let Err(err) = fs::remove_file(&tmp_path) {
+      warn!("Failed to remove temporary file '{}' - {}", tmp_path.to_string_lossy(), err);
+    }
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{env, fs};
+  use std::fs::OpenOptions;
+
+  use expectest::prelude::*;
+  use fs2::FileExt;
+
+  use super::{lock_file_path, with_lock_file, write_atomically};
+
+  #[test]
+  fn write_atomically_replaces_the_file_and_does_not_leave_temporary_files() {
+    let dir = env::temp_dir().join(format!("pact_test_{}", rand::random::<u16>()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("pact.json");
+    fs::write(&path, "existing contents that are longer than the new contents").unwrap();
+
+    let result = write_atomically(&path, b"new contents");
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let files: Vec<String> = fs::read_dir(&dir).unwrap()
+      .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+      .collect();
+    fs::remove_dir_all(&dir).unwrap_or(());
+
+    expect!(result).to(be_ok());
+    expect!(contents).to(be_equal_to("new contents"));
+    expect!(files).to(be_equal_to(vec!["pact.json".to_string()]));
+  }
+
+  #[test]
+  fn with_lock_file_locks_a_separate_lock_file() {
+    let dir = env::temp_dir().join(format!("pact_test_{}", rand::random::<u16>()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("pact.json");
+
+    let result = with_lock_file(&path, 1, &mut || {
+      write_atomically(&path, b"first")?;
+      write_atomically(&path, b"second")?;
+      Ok(())
+    });
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+
+    let lock_file = OpenOptions::new().write(true).open(lock_file_path(&path)).unwrap();
+    lock_file.lock_exclusive().unwrap();
+    let locked_result = with_lock_file(&path, 1, &mut || Ok(()));
+    lock_file.unlock().unwrap();
+    fs::remove_dir_all(&dir).unwrap_or(());
+
+    expect!(result).to(be_ok());
+    expect!(contents).to(be_equal_to("second"));
+    expect!(lock_file_path(&path)).to(be_equal_to(dir.join(".pact-locks").join("pact.json.lock")));
+    expect!(locked_result).to(be_err());
+  }
+}
@@ -1,6 +1,7 @@
 //! `generators` module includes all the classes to deal with V3 format generators
 
 use std::{collections::HashMap, hash::{Hash, Hasher}, mem, ops::Index, str::FromStr};
+use std::cell::RefCell;
 #[cfg(test)]
 use std::collections::hash_map::DefaultHasher;
 use std::convert::TryFrom;
@@ -16,6 +17,7 @@ use onig::{Captures, Regex};
 use rand::distributions::Alphanumeric;
 use rand::prelude::*;
 use rand::seq::SliceRandom;
+use regex_syntax::hir::{Class, ClassUnicode, ClassUnicodeRange, Group, Hir, HirKind, Repetition};
 use serde::{Deserialize, Serialize};
 use serde_json::{self, json, Value};
 use sxd_document::dom::Document;
@@ -29,6 +31,9 @@ use crate::models::expression_parser::{
   contains_expressions,
   DataType,
   DataValue,
+  execute_date_expression,
+  execute_datetime_expression,
+  execute_time_expression,
   MapValueResolver,
   parse_expression
 };
@@ -54,18 +59,24 @@ pub enum Generator {
   RandomString(u16),
   /// Generates a random string that matches the provided regex
   Regex(String),
-  /// Generates a random date that matches either the provided format or the ISO format
-  Date(Option<String>),
-  /// Generates a random time that matches either the provided format or the ISO format
-  Time(Option<String>),
-  /// Generates a random timestamp that matches either the provided format or the ISO format
-  DateTime(Option<String>),
+  /// Generates a date that matches either the provided format or the ISO format. The optional
+  /// expression (i.e. `today + 2 days`) is evaluated relative to the current date.
+  Date(Option<String>, Option<String>),
+  /// Generates a time that matches either the provided format or the ISO format. The optional
+  /// expression (i.e. `now + 2 hours`) is evaluated relative to the current time.
+  Time(Option<String>, Option<String>),
+  /// Generates a timestamp that matches either the provided format or the ISO format. The
+  /// optional expression (i.e. `tomorrow @ midnight`) is evaluated relative to the current date
+  /// and time.
+  DateTime(Option<String>, Option<String>),
   /// Generates a random boolean value
   RandomBoolean,
   /// Generates a value that is looked up from the provider state context
   ProviderStateGenerator(String, Option<DataType>),
   /// Generates a URL with the mock server as the base URL
   MockServerURL(String, String),
+  /// Generates a value from a parameter captured from the request path by a path template
+  PathParameter(String),
   /// List of variants which can have embedded generators
   ArrayContains(Vec<(usize, MatchingRuleCategory, HashMap<String, Generator>)>)
 }
@@ -80,18 +91,9 @@ impl Generator {
       Generator::RandomHexadecimal(digits) => Some(json!({ "type": "RandomHexadecimal", "digits": digits })),
       Generator::RandomString(size) => Some(json!({ "type": "RandomString", "size": size })),
       Generator::Regex(ref regex) => Some(json!({ "type": "Regex", "regex": regex })),
-      Generator::Date(ref format) => match format {
-        Some(ref format) => Some(json!({ "type": "Date", "format": format })),
-        None => Some(json!({ "type": "Date" }))
-      },
-      Generator::Time(ref format) => match format {
-        Some(ref format) => Some(json!({ "type": "Time", "format": format })),
-        None => Some(json!({ "type": "Time" }))
-      },
-      Generator::DateTime(ref format) => match format {
-        Some(ref format) => Some(json!({ "type": "DateTime", "format": format })),
-        None => Some(json!({ "type": "DateTime" }))
-      },
+      Generator::Date(ref format, ref expression) => Some(date_time_json("Date", format, expression)),
+      Generator::Time(ref format, ref expression) => Some(date_time_json("Time", format, expression)),
+      Generator::DateTime(ref format, ref expression) => Some(date_time_json("DateTime", format, expression)),
       Generator::RandomBoolean => Some(json!({ "type": "RandomBoolean" })),
       Generator::ProviderStateGenerator(ref expression, ref data_type) => {
         if let Some(data_type) = data_type {
@@ -101,6 +103,7 @@ impl Generator {
         }
       }
       Generator::MockServerURL(example, regex) => Some(json!({ "type": "MockServerURL", "example": example, "regex": regex })),
+      Generator::PathParameter(name) => Some(json!({ "type": "PathParameter", "name": name })),
       _ => None
     }
   }
@@ -118,15 +121,16 @@ impl Generator {
       "RandomHexadecimal" => Some(Generator::RandomHexadecimal(<u16>::json_to_number(map, "digits", 10))),
       "RandomString" => Some(Generator::RandomString(<u16>::json_to_number(map, "size", 10))),
       "Regex" => map.get("regex").map(|val| Generator::Regex(json_to_string(val))),
-      "Date" => Some(Generator::Date(get_field_as_string("format", map))),
-      "Time" => Some(Generator::Time(get_field_as_string("format", map))),
-      "DateTime" => Some(Generator::DateTime(get_field_as_string("format", map))),
+      "Date" => Some(Generator::Date(get_field_as_string("format", map), get_field_as_string("expression", map))),
+      "Time" => Some(Generator::Time(get_field_as_string("format", map), get_field_as_string("expression", map))),
+      "DateTime" => Some(Generator::DateTime(get_field_as_string("format", map), get_field_as_string("expression", map))),
       "RandomBoolean" => Some(Generator::RandomBoolean),
       "ProviderState" => map.get("expression").map(|f|
         Generator::ProviderStateGenerator(json_to_string(f), map.get("dataType")
           .map(|dt| DataType::from(dt.clone())))),
       "MockServerURL" => Some(Generator::MockServerURL(get_field_as_string("example", map).unwrap_or_default(),
                                                        get_field_as_string("regex", map).unwrap_or_default())),
+      "PathParameter" => map.get("name").map(|name| Generator::PathParameter(json_to_string(name))),
       _ => {
         log::warn!("'{}' is not a valid generator type", gen_type);
         None
@@ -139,6 +143,7 @@ impl Generator {
     match self {
       Generator::ProviderStateGenerator(_, _) => mode == &GeneratorTestMode::Provider,
       Generator::MockServerURL(_, _) => mode == &GeneratorTestMode::Consumer,
+      Generator::PathParameter(_) => mode == &GeneratorTestMode::Consumer,
       _ => true
     }
   }
@@ -156,9 +161,11 @@ impl Hash for Generator {
       Generator::RandomHexadecimal(digits) => digits.hash(state),
       Generator::RandomString(size) => size.hash(state),
       Generator::Regex(re) => re.hash(state),
-      Generator::DateTime(format) => format.hash(state),
-      Generator::Time(format) => format.hash(state),
-      Generator::Date(format) => format.hash(state),
+      Generator::DateTime(format, expression) | Generator::Time(format, expression) |
+      Generator::Date(format, expression) => {
+        format.hash(state);
+        expression.hash(state);
+      },
       Generator::ProviderStateGenerator(str, datatype) => {
         str.hash(state);
         datatype.hash(state);
@@ -167,6 +174,7 @@ impl Hash for Generator {
         str1.hash(state);
         str2.hash(state);
       },
+      Generator::PathParameter(name) => name.hash(state),
       Generator::ArrayContains(variants) => {
         for (index, rules, generators) in variants {
           index.hash(state);
@@ -190,11 +198,12 @@ impl PartialEq for Generator {
       (Generator::RandomHexadecimal(digits1), Generator::RandomHexadecimal(digits2)) => digits1 == digits2,
       (Generator::RandomString(size1), Generator::RandomString(size2)) => size1 == size2,
       (Generator::Regex(re1), Generator::Regex(re2)) => re1 == re2,
-      (Generator::DateTime(format1), Generator::DateTime(format2)) => format1 == format2,
-      (Generator::Time(format1), Generator::Time(format2)) => format1 == format2,
-      (Generator::Date(format1), Generator::Date(format2)) => format1 == format2,
+      (Generator::DateTime(format1, exp1), Generator::DateTime(format2, exp2)) => format1 == format2 && exp1 == exp2,
+      (Generator::Time(format1, exp1), Generator::Time(format2, exp2)) => format1 == format2 && exp1 == exp2,
+      (Generator::Date(format1, exp1), Generator::Date(format2, exp2)) => format1 == format2 && exp1 == exp2,
       (Generator::ProviderStateGenerator(str1, data1), Generator::ProviderStateGenerator(str2, data2)) => str1 == str2 && data1 == data2,
       (Generator::MockServerURL(ex1, re1), Generator::MockServerURL(ex2, re2)) => ex1 == ex2 && re1 == re2,
+      (Generator::PathParameter(name1), Generator::PathParameter(name2)) => name1 == name2,
       (Generator::ArrayContains(variants1), Generator::ArrayContains(variants2)) => variants1 == variants2,
       _ => mem::discriminant(self) == mem::discriminant(other)
     }
@@ -257,29 +266,32 @@ fn hash_and_partial_eq_for_matching_rule() {
   expect!(h(&regex1)).to_not(be_equal_to(h(&regex2)));
   expect!(&regex1).to_not(be_equal_to(&regex2));
 
-  let datetime1 = Generator::DateTime(Some("yyyy-MM-dd HH:mm:ss".into()));
-  let datetime2 = Generator::DateTime(Some("yyyy-MM-ddTHH:mm:ss".into()));
+  let datetime1 = Generator::DateTime(Some("yyyy-MM-dd HH:mm:ss".into()), None);
+  let datetime2 = Generator::DateTime(Some("yyyy-MM-ddTHH:mm:ss".into()), None);
 
   expect!(h(&datetime1)).to(be_equal_to(h(&datetime1)));
   expect!(&datetime1).to(be_equal_to(&datetime1));
   expect!(h(&datetime1)).to_not(be_equal_to(h(&datetime2)));
   expect!(&datetime1).to_not(be_equal_to(&datetime2));
 
-  let date1 = Generator::Date(Some("yyyy-MM-dd".into()));
-  let date2 = Generator::Date(Some("yy-MM-dd".into()));
+  let date1 = Generator::Date(Some("yyyy-MM-dd".into()), None);
+  let date2 = Generator::Date(Some("yy-MM-dd".into()), None);
 
   expect!(h(&date1)).to(be_equal_to(h(&date1)));
   expect!(&date1).to(be_equal_to(&date1));
   expect!(h(&date1)).to_not(be_equal_to(h(&date2)));
   expect!(&date1).to_not(be_equal_to(&date2));
 
-  let time1 = Generator::Time(Some("HH:mm:ss".into()));
-  let time2 = Generator::Time(Some("hh:mm:ss".into()));
+  let time1 = Generator::Time(Some("HH:mm:ss".into()), None);
+  let time2 = Generator::Time(Some("hh:mm:ss".into()), None);
+  let time3 = Generator::Time(Some("HH:mm:ss".into()), Some("now + 1 hour".into()));
 
   expect!(h(&time1)).to(be_equal_to(h(&time1)));
   expect!(&time1).to(be_equal_to(&time1));
   expect!(h(&time1)).to_not(be_equal_to(h(&time2)));
   expect!(&time1).to_not(be_equal_to(&time2));
+  expect!(h(&time1)).to_not(be_equal_to(h(&time3)));
+  expect!(&time1).to_not(be_equal_to(&time3));
 
   let psg1 = Generator::ProviderStateGenerator("string one".into(), Some(DataType::BOOLEAN));
   let psg2 = Generator::ProviderStateGenerator("string two".into(), None);
@@ -428,7 +440,7 @@ pub trait GenerateValue<T> {
 impl GenerateValue<u16> for Generator {
   fn generate_value(&self, value: &u16, context: &HashMap<&str, Value>) -> Result<u16, String> {
     match self {
-      &Generator::RandomInt(min, max) => Ok(rand::thread_rng().gen_range(min as u16..(max as u16).saturating_add(1))),
+      &Generator::RandomInt(min, max) => Ok(generator_rng().gen_range(min as u16..(max as u16).saturating_add(1))),
       &Generator::ProviderStateGenerator(ref exp, ref dt) =>
         match generate_value_from_context(exp, context, dt) {
           Ok(val) => u16::try_from(val),
@@ -439,9 +451,77 @@ impl GenerateValue<u16> for Generator {
   }
 }
 
+thread_local! {
+  static SEEDED_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Runs the callback with all the random generators (RandomInt, RandomString, Uuid, etc.) on the
+/// current thread using a random number generator seeded with the seed, so that the generated
+/// values are reproducible (for the same version of this library).
+pub fn with_generator_seed<T>(seed: u64, callback: impl FnOnce() -> T) -> T {
+  let previous = SEEDED_RNG.with(|rng| rng.replace(Some(StdRng::seed_from_u64(seed))));
+  let result = callback();
+  SEEDED_RNG.with(|rng| rng.replace(previous));
+  result
+}
+
+/// Runs the callback with the random generators seeded from the `generatorSeed` value in the test
+/// context, if there is one
+pub fn with_generator_seed_from_context<T>(context: &HashMap<&str, Value>, callback: impl FnOnce() -> T) -> T {
+  match context.get("generatorSeed").and_then(Value::as_u64) {
+    Some(seed) => with_generator_seed(seed, callback),
+    None => callback()
+  }
+}
+
+/// Random number generator for the generators. Uses the seeded generator if one has been set for
+/// the current thread with `with_generator_seed`, otherwise the thread-local random generator.
+pub(crate) struct GeneratorRng;
+
+pub(crate) fn generator_rng() -> GeneratorRng {
+  GeneratorRng
+}
+
+impl GeneratorRng {
+  fn with_rng<T>(&mut self, callback: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+    SEEDED_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+      Some(rng) => callback(rng),
+      None => callback(&mut rand::thread_rng())
+    })
+  }
+}
+
+impl RngCore for GeneratorRng {
+  fn next_u32(&mut self) -> u32 {
+    self.with_rng(|rng| rng.next_u32())
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    self.with_rng(|rng| rng.next_u64())
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    self.with_rng(|rng| rng.fill_bytes(dest))
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+    self.with_rng(|rng| rng.try_fill_bytes(dest))
+  }
+}
+
+/// Generates a random (version 4) UUID
+pub(crate) fn generate_uuid() -> Uuid {
+  let mut bytes = [0_u8; 16];
+  generator_rng().fill_bytes(&mut bytes);
+  uuid::Builder::from_bytes(bytes)
+    .set_variant(uuid::Variant::RFC4122)
+    .set_version(uuid::Version::Random)
+    .build()
+}
+
 const DIGIT_CHARSET: &str = "0123456789";
 pub(crate) fn generate_decimal(digits: usize) -> String {
-  let mut rnd = rand::thread_rng();
+  let mut rnd = generator_rng();
   let chars: Vec<char> = DIGIT_CHARSET.chars().collect();
   match digits {
     0 => "".to_string(),
@@ -472,77 +552,127 @@ pub(crate) fn generate_decimal(digits: usize) -> String {
 
 const HEX_CHARSET: &str = "0123456789ABCDEF";
 pub(crate) fn generate_hexadecimal(digits: usize) -> String {
-  let mut rnd = rand::thread_rng();
+  let mut rnd = generator_rng();
   HEX_CHARSET.chars().choose_multiple(&mut rnd, digits).iter().join("")
 }
 
 pub(crate) fn generate_ascii_string(size: usize) -> String {
-  rand::thread_rng().sample_iter(&Alphanumeric).map(char::from).take(size).collect()
+  generator_rng().sample_iter(&Alphanumeric).map(char::from).take(size).collect()
+}
+
+fn date_time_json(gen_type: &str, format: &Option<String>, expression: &Option<String>) -> Value {
+  let mut json = json!({ "type": gen_type });
+  if let Some(format) = format {
+    json["format"] = json!(format);
+  }
+  if let Some(expression) = expression {
+    json["expression"] = json!(expression);
+  }
+  json
+}
+
+/// Evaluates the date/time expression of a `Date`, `Time` or `DateTime` generator relative to the
+/// current system clock, or returns the current date and time if there is no expression
+pub(crate) fn date_time_from_expression(
+  expression: &Option<String>,
+  execute: fn(&DateTime<Local>, &str) -> Result<DateTime<Local>, String>
+) -> Result<DateTime<Local>, String> {
+  let now = Local::now();
+  match expression {
+    Some(expression) => execute(&now, expression).map_err(|err| {
+      log::warn!("{}", err);
+      err
+    }),
+    None => Ok(now)
+  }
+}
+
+/// Generates a random string that matches the regular expression. Anchors and word boundaries
+/// are ignored, as the generated string is the whole value, and the characters are restricted to
+/// printable ASCII where the character classes allow it (so `.` or `\w` do not generate arbitrary
+/// Unicode characters).
+pub(crate) fn generate_regex_string(regex: &str) -> Result<String, String> {
+  let hir = regex_syntax::Parser::new().parse(regex)
+    .map_err(|err| format!("'{}' is not a valid regular expression - {}", regex, err))?;
+  let generator = rand_regex::Regex::with_hir(generatable_hir(hir), 20)
+    .map_err(|err| format!("Failed to generate a value from regular expression '{}' - {}", regex, err))?;
+  Ok(generator_rng().sample(generator))
 }
 
-fn strip_anchors(regex: &str) -> &str {
-  regex
-    .strip_prefix('^').unwrap_or(regex)
-    .strip_suffix('$').unwrap_or(regex)
+fn generatable_hir(hir: Hir) -> Hir {
+  match hir.into_kind() {
+    HirKind::Empty | HirKind::Anchor(_) | HirKind::WordBoundary(_) => Hir::empty(),
+    HirKind::Literal(literal) => Hir::literal(literal),
+    HirKind::Class(Class::Unicode(class)) => {
+      let mut printable = ClassUnicode::new(vec![ClassUnicodeRange::new(' ', '~')]);
+      printable.intersect(&class);
+      if printable.ranges().is_empty() {
+        Hir::class(Class::Unicode(class))
+      } else {
+        Hir::class(Class::Unicode(printable))
+      }
+    },
+    HirKind::Class(class) => Hir::class(class),
+    HirKind::Repetition(repetition) => Hir::repetition(Repetition {
+      kind: repetition.kind,
+      greedy: repetition.greedy,
+      hir: Box::new(generatable_hir(*repetition.hir))
+    }),
+    HirKind::Group(group) => Hir::group(Group {
+      kind: group.kind,
+      hir: Box::new(generatable_hir(*group.hir))
+    }),
+    HirKind::Concat(hirs) => Hir::concat(hirs.into_iter().map(generatable_hir).collect()),
+    HirKind::Alternation(hirs) => Hir::alternation(hirs.into_iter().map(generatable_hir).collect())
+  }
 }
 
 impl GenerateValue<String> for Generator {
   fn generate_value(&self, _: &String, context: &HashMap<&str, Value>) -> Result<String, String> {
-    let mut rnd = rand::thread_rng();
+    let mut rnd = generator_rng();
     let result = match self {
       Generator::RandomInt(min, max) => Ok(format!("{}", rnd.gen_range(*min..max.saturating_add(1)))),
-      Generator::Uuid => Ok(Uuid::new_v4().to_hyphenated().to_string()),
+      Generator::Uuid => Ok(generate_uuid().to_hyphenated().to_string()),
       Generator::RandomDecimal(digits) => Ok(generate_decimal(*digits as usize)),
       Generator::RandomHexadecimal(digits) => Ok(generate_hexadecimal(*digits as usize)),
       Generator::RandomString(size) => Ok(generate_ascii_string(*size as usize)),
-      Generator::Regex(ref regex) => {
-        let mut parser = regex_syntax::ParserBuilder::new().unicode(false).build();
-        match parser.parse(strip_anchors(regex)) {
-          Ok(hir) => {
-            match rand_regex::Regex::with_hir(hir, 20) {
-              Ok(gen) => Ok(rnd.sample(gen)),
-              Err(err) => {
-                log::warn!("Failed to generate a value from regular expression - {}", err);
-                Err(format!("Failed to generate a value from regular expression - {}", err))
-              }
+      Generator::Regex(ref regex) => generate_regex_string(regex).map_err(|err| {
+        log::warn!("{}", err);
+        err
+      }),
+      Generator::Date(ref format, ref expression) => date_time_from_expression(expression, execute_date_expression)
+        .and_then(|date| match format {
+          Some(pattern) => match parse_pattern(pattern) {
+            Ok(tokens) => Ok(date.date().format(&to_chrono_pattern(&tokens)).to_string()),
+            Err(err) => {
+              log::warn!("Date format {} is not valid - {}", pattern, err);
+              Err(format!("Date format {} is not valid - {}", pattern, err))
             }
           },
-          Err(err) => {
-            log::warn!("'{}' is not a valid regular expression - {}", regex, err);
-            Err(format!("'{}' is not a valid regular expression - {}", regex, err))
-          }
-        }
-      },
-      Generator::Date(ref format) => match format {
-        Some(pattern) => match parse_pattern(pattern) {
-          Ok(tokens) => Ok(Local::now().date().format(&to_chrono_pattern(&tokens)).to_string()),
-          Err(err) => {
-            log::warn!("Date format {} is not valid - {}", pattern, err);
-            Err(format!("Date format {} is not valid - {}", pattern, err))
-          }
-        },
-        None => Ok(Local::now().naive_local().date().to_string())
-      },
-      Generator::Time(ref format) => match format {
-        Some(pattern) => match parse_pattern(pattern) {
-          Ok(tokens) => Ok(Local::now().format(&to_chrono_pattern(&tokens)).to_string()),
-          Err(err) => {
-            log::warn!("Time format {} is not valid - {}", pattern, err);
-            Err(format!("Time format {} is not valid - {}", pattern, err))
-          }
-        },
-        None => Ok(Local::now().time().format("%H:%M:%S").to_string())
-      },
-      Generator::DateTime(ref format) => match format {
-        Some(pattern) => match parse_pattern(pattern) {
-          Ok(tokens) => Ok(Local::now().format(&to_chrono_pattern(&tokens)).to_string()),
-          Err(err) => {
-            log::warn!("DateTime format {} is not valid - {}", pattern, err);
-            Err(format!("DateTime format {} is not valid - {}", pattern, err))
-          }
-        },
-        None => Ok(Local::now().format("%Y-%m-%dT%H:%M:%S.%3f%z").to_string())
-      },
+          None => Ok(date.naive_local().date().to_string())
+        }),
+      Generator::Time(ref format, ref expression) => date_time_from_expression(expression, execute_time_expression)
+        .and_then(|time| match format {
+          Some(pattern) => match parse_pattern(pattern) {
+            Ok(tokens) => Ok(time.format(&to_chrono_pattern(&tokens)).to_string()),
+            Err(err) => {
+              log::warn!("Time format {} is not valid - {}", pattern, err);
+              Err(format!("Time format {} is not valid - {}", pattern, err))
+            }
+          },
+          None => Ok(time.time().format("%H:%M:%S").to_string())
+        }),
+      Generator::DateTime(ref format, ref expression) => date_time_from_expression(expression, execute_datetime_expression)
+        .and_then(|date_time| match format {
+          Some(pattern) => match parse_pattern(pattern) {
+            Ok(tokens) => Ok(date_time.format(&to_chrono_pattern(&tokens)).to_string()),
+            Err(err) => {
+              log::warn!("DateTime format {} is not valid - {}", pattern, err);
+              Err(format!("DateTime format {} is not valid - {}", pattern, err))
+            }
+          },
+          None => Ok(date_time.format("%Y-%m-%dT%H:%M:%S.%3f%z").to_string())
+        }),
       Generator::RandomBoolean => Ok(format!("{}", rnd.gen::<bool>())),
       Generator::ProviderStateGenerator(ref exp, ref dt) =>
         match generate_value_from_context(exp, context, dt) {
@@ -553,7 +683,8 @@ impl GenerateValue<String> for Generator {
         debug!("Generating URL from Mock Server details");
         match mock_server_details.as_object() {
           Some(mock_server_details) => {
-            match get_field_as_string("url", mock_server_details) {
+            match get_field_as_string("href", mock_server_details)
+              .or_else(|| get_field_as_string("url", mock_server_details)) {
               Some(url) => match Regex::new(regex) {
                 Ok(re) => Ok(re.replace(example, |caps: &Captures| {
                   format!("{}{}", url, caps.at(1).unwrap())
@@ -568,6 +699,7 @@ impl GenerateValue<String> for Generator {
       } else {
         Err("MockServerURL: can not generate a value as there is no mock server details in the test context".to_string())
       },
+      Generator::PathParameter(name) => path_parameter_from_context(name, context),
       Generator::ArrayContains(_) => Err("can only use ArrayContains with lists".to_string())
     };
     debug!("Generator = {:?}, Generated value = {:?}", self, result);
@@ -613,7 +745,9 @@ pub enum GeneratorCategory {
   /// Body
   BODY,
   /// Response Status
-  STATUS
+  STATUS,
+  /// Message metadata
+  METADATA
 }
 
 impl FromStr for GeneratorCategory {
@@ -627,6 +761,7 @@ impl FromStr for GeneratorCategory {
       "query" => Ok(GeneratorCategory::QUERY),
       "body" => Ok(GeneratorCategory::BODY),
       "status" => Ok(GeneratorCategory::STATUS),
+      "metadata" => Ok(GeneratorCategory::METADATA),
       _ => Err(format!("'{}' is not a valid GeneratorCategory", s))
     }
   }
@@ -640,7 +775,8 @@ impl <'a> Into<&'a str> for GeneratorCategory {
       GeneratorCategory::HEADER => "header",
       GeneratorCategory::QUERY => "query",
       GeneratorCategory::BODY => "body",
-      GeneratorCategory::STATUS => "status"
+      GeneratorCategory::STATUS => "status",
+      GeneratorCategory::METADATA => "metadata"
     }
   }
 }
@@ -744,7 +880,7 @@ impl ContentTypeHandler<Value> for JsonHandler {
     mode: &GeneratorTestMode,
     context: &HashMap<&str, Value>
   ) -> Result<OptionalBody, String> {
-    for (key, generator) in generators {
+    for (key, generator) in generators.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
       if generator.corresponds_to_mode(mode) {
         debug!("Applying generator {:?} to key {}", generator, key);
         self.apply_key(key, generator, context);
@@ -970,13 +1106,14 @@ impl Default for Generators {
   }
 }
 
-/// If the mode applies, invoke the callback for each of the generators
+/// If the mode applies, invoke the callback for each of the generators. The generators are
+/// applied in the order of their keys, so that seeded generators are reproducible.
 pub fn apply_generators<F>(
   mode: &GeneratorTestMode,
   generators: &HashMap<String, Generator>,
   closure: &mut F
 ) where F: FnMut(&String, &Generator) {
-  for (key, value) in generators {
+  for (key, value) in generators.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
     if value.corresponds_to_mode(mode) {
       closure(&key, &value)
     }
@@ -992,7 +1129,16 @@ pub fn apply_body_generators(
   generators: &HashMap<String, Generator>
 ) -> OptionalBody {
   match content_type {
-    Some(content_type) => if content_type.is_json() {
+    Some(content_type) => if let Some(plugin) = crate::plugins::find_plugin(&content_type) {
+      debug!("apply_body_generators: using plugin '{}'", plugin.metadata().name);
+      match plugin.generate_contents(body, generators, mode) {
+        Ok(body) => body,
+        Err(err) => {
+          error!("Failed to generate the body with plugin '{}': {}", plugin.metadata().name, err);
+          body.clone()
+        }
+      }
+    } else if content_type.is_json() {
       debug!("apply_body_generators: JSON content type");
       let result: Result<Value, serde_json::Error> = serde_json::from_slice(&body.value().unwrap_or_default());
       match result {
@@ -1105,6 +1251,15 @@ pub(crate) fn generate_value_from_context(expression: &str, context: &HashMap<&s
   data_type.clone().unwrap_or(DataType::RAW).wrap(result)
 }
 
+/// Looks up the value of a path parameter captured from the request path in the test context
+pub(crate) fn path_parameter_from_context(name: &str, context: &HashMap<&str, Value>) -> Result<String, String> {
+  match context.get("pathParameters") {
+    Some(Value::Object(parameters)) => parameters.get(name).map(json_to_string)
+      .ok_or(format!("PathParameter: can not generate a value as there is no path parameter '{}' in the test context", name)),
+    _ => Err("PathParameter: can not generate a value as there are no path parameters in the test context".to_string())
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use std::str::FromStr;
@@ -1224,26 +1379,29 @@ mod tests {
 
   #[test]
   fn date_generator_from_json_test() {
-    expect!(Generator::from_map(&s!("Date"), &serde_json::Map::new())).to(be_some().value(Generator::Date(None)));
-    expect!(Generator::from_map(&s!("Date"), &json!({ "min": 5 }).as_object().unwrap())).to(be_some().value(Generator::Date(None)));
-    expect!(Generator::from_map(&s!("Date"), &json!({ "format": "yyyy-MM-dd" }).as_object().unwrap())).to(be_some().value(Generator::Date(Some(s!("yyyy-MM-dd")))));
-    expect!(Generator::from_map(&s!("Date"), &json!({ "format": 5 }).as_object().unwrap())).to(be_some().value(Generator::Date(Some(s!("5")))));
+    expect!(Generator::from_map(&s!("Date"), &serde_json::Map::new())).to(be_some().value(Generator::Date(None, None)));
+    expect!(Generator::from_map(&s!("Date"), &json!({ "min": 5 }).as_object().unwrap())).to(be_some().value(Generator::Date(None, None)));
+    expect!(Generator::from_map(&s!("Date"), &json!({ "format": "yyyy-MM-dd" }).as_object().unwrap())).to(be_some().value(Generator::Date(Some(s!("yyyy-MM-dd")), None)));
+    expect!(Generator::from_map(&s!("Date"), &json!({ "format": 5 }).as_object().unwrap())).to(be_some().value(Generator::Date(Some(s!("5")), None)));
+    expect!(Generator::from_map(&s!("Date"), &json!({ "expression": "today + 2 days" }).as_object().unwrap())).to(be_some().value(Generator::Date(None, Some(s!("today + 2 days")))));
   }
 
   #[test]
   fn time_generator_from_json_test() {
-    expect!(Generator::from_map(&s!("Time"), &serde_json::Map::new())).to(be_some().value(Generator::Time(None)));
-    expect!(Generator::from_map(&s!("Time"), &json!({ "min": 5 }).as_object().unwrap())).to(be_some().value(Generator::Time(None)));
-    expect!(Generator::from_map(&s!("Time"), &json!({ "format": "yyyy-MM-dd" }).as_object().unwrap())).to(be_some().value(Generator::Time(Some(s!("yyyy-MM-dd")))));
-    expect!(Generator::from_map(&s!("Time"), &json!({ "format": 5 }).as_object().unwrap())).to(be_some().value(Generator::Time(Some(s!("5")))));
+    expect!(Generator::from_map(&s!("Time"), &serde_json::Map::new())).to(be_some().value(Generator::Time(None, None)));
+    expect!(Generator::from_map(&s!("Time"), &json!({ "min": 5 }).as_object().unwrap())).to(be_some().value(Generator::Time(None, None)));
+    expect!(Generator::from_map(&s!("Time"), &json!({ "format": "yyyy-MM-dd" }).as_object().unwrap())).to(be_some().value(Generator::Time(Some(s!("yyyy-MM-dd")), None)));
+    expect!(Generator::from_map(&s!("Time"), &json!({ "format": 5 }).as_object().unwrap())).to(be_some().value(Generator::Time(Some(s!("5")), None)));
+    expect!(Generator::from_map(&s!("Time"), &json!({ "format": "HH:mm", "expression": "noon" }).as_object().unwrap())).to(be_some().value(Generator::Time(Some(s!("HH:mm")), Some(s!("noon")))));
   }
 
   #[test]
   fn datetime_generator_from_json_test() {
-    expect!(Generator::from_map(&s!("DateTime"), &serde_json::Map::new())).to(be_some().value(Generator::DateTime(None)));
-    expect!(Generator::from_map(&s!("DateTime"), &json!({ "min": 5 }).as_object().unwrap())).to(be_some().value(Generator::DateTime(None)));
-    expect!(Generator::from_map(&s!("DateTime"), &json!({ "format": "yyyy-MM-dd" }).as_object().unwrap())).to(be_some().value(Generator::DateTime(Some(s!("yyyy-MM-dd")))));
-    expect!(Generator::from_map(&s!("DateTime"), &json!({ "format": 5 }).as_object().unwrap())).to(be_some().value(Generator::DateTime(Some(s!("5")))));
+    expect!(Generator::from_map(&s!("DateTime"), &serde_json::Map::new())).to(be_some().value(Generator::DateTime(None, None)));
+    expect!(Generator::from_map(&s!("DateTime"), &json!({ "min": 5 }).as_object().unwrap())).to(be_some().value(Generator::DateTime(None, None)));
+    expect!(Generator::from_map(&s!("DateTime"), &json!({ "format": "yyyy-MM-dd" }).as_object().unwrap())).to(be_some().value(Generator::DateTime(Some(s!("yyyy-MM-dd")), None)));
+    expect!(Generator::from_map(&s!("DateTime"), &json!({ "format": 5 }).as_object().unwrap())).to(be_some().value(Generator::DateTime(Some(s!("5")), None)));
+    expect!(Generator::from_map(&s!("DateTime"), &json!({ "expression": "tomorrow @ midnight" }).as_object().unwrap())).to(be_some().value(Generator::DateTime(None, Some(s!("tomorrow @ midnight")))));
   }
 
   #[test]
@@ -1285,27 +1443,36 @@ mod tests {
       "type": "RandomBoolean"
     })));
 
-    expect!(Generator::Date(Some(s!("yyyyMMdd"))).to_json().unwrap()).to(be_equal_to(json!({
+    expect!(Generator::Date(Some(s!("yyyyMMdd")), None).to_json().unwrap()).to(be_equal_to(json!({
       "type": "Date",
       "format": "yyyyMMdd"
     })));
-    expect!(Generator::Date(None).to_json().unwrap()).to(be_equal_to(json!({
+    expect!(Generator::Date(None, None).to_json().unwrap()).to(be_equal_to(json!({
       "type": "Date"
     })));
-    expect!(Generator::Time(Some(s!("yyyyMMdd"))).to_json().unwrap()).to(be_equal_to(json!({
+    expect!(Generator::Time(Some(s!("yyyyMMdd")), None).to_json().unwrap()).to(be_equal_to(json!({
       "type": "Time",
       "format": "yyyyMMdd"
     })));
-    expect!(Generator::Time(None).to_json().unwrap()).to(be_equal_to(json!({
+    expect!(Generator::Time(None, None).to_json().unwrap()).to(be_equal_to(json!({
       "type": "Time"
     })));
-    expect!(Generator::DateTime(Some(s!("yyyyMMdd"))).to_json().unwrap()).to(be_equal_to(json!({
+    expect!(Generator::DateTime(Some(s!("yyyyMMdd")), None).to_json().unwrap()).to(be_equal_to(json!({
       "type": "DateTime",
       "format": "yyyyMMdd"
     })));
-    expect!(Generator::DateTime(None).to_json().unwrap()).to(be_equal_to(json!({
+    expect!(Generator::DateTime(None, None).to_json().unwrap()).to(be_equal_to(json!({
       "type": "DateTime"
     })));
+    expect!(Generator::DateTime(Some(s!("yyyyMMdd")), Some(s!("today @ noon"))).to_json().unwrap()).to(be_equal_to(json!({
+      "type": "DateTime",
+      "format": "yyyyMMdd",
+      "expression": "today @ noon"
+    })));
+    expect!(Generator::Date(None, Some(s!("today + 1 day"))).to_json().unwrap()).to(be_equal_to(json!({
+      "type": "Date",
+      "expression": "today + 1 day"
+    })));
     expect!(Generator::ProviderStateGenerator("$a".into(), Some(DataType::INTEGER)).to_json().unwrap()).to(be_equal_to(json!({
       "type": "ProviderState",
       "expression": "$a",
@@ -1320,6 +1487,10 @@ mod tests {
       "example": "http://localhost:1234/path",
       "regex": "(.*)/path"
     })));
+    expect!(Generator::PathParameter("id".into()).to_json().unwrap()).to(be_equal_to(json!({
+      "type": "PathParameter",
+      "name": "id"
+    })));
   }
 
   #[test]
@@ -1374,31 +1545,48 @@ mod tests {
 
   #[test]
   fn date_generator_test() {
-    let generated = Generator::Date(None).generate_value(&"".to_string(), &hashmap!{});
+    let generated = Generator::Date(None, None).generate_value(&"".to_string(), &hashmap!{});
     assert_that!(generated.unwrap(), matches_regex(r"^\d{4}-\d{2}-\d{2}$"));
 
-    let generated2 = Generator::Date(Some("yyyy-MM-ddZ".into())).generate_value(&"".to_string(), &hashmap!{});
+    let generated2 = Generator::Date(Some("yyyy-MM-ddZ".into()), None).generate_value(&"".to_string(), &hashmap!{});
     assert_that!(generated2.unwrap(), matches_regex(r"^\d{4}-\d{2}-\d{2}[-+]\d{4}$"));
   }
 
   #[test]
   fn time_generator_test() {
-    let generated = Generator::Time(None).generate_value(&"".to_string(), &hashmap!{});
+    let generated = Generator::Time(None, None).generate_value(&"".to_string(), &hashmap!{});
     assert_that!(generated.unwrap(), matches_regex(r"^\d{2}:\d{2}:\d{2}$"));
 
-    let generated2 = Generator::Time(Some("HH:mm:ssZ".into())).generate_value(&"".to_string(), &hashmap!{});
+    let generated2 = Generator::Time(Some("HH:mm:ssZ".into()), None).generate_value(&"".to_string(), &hashmap!{});
     assert_that!(generated2.unwrap(), matches_regex(r"^\d{2}:\d{2}:\d{2}[-+]\d+$"));
   }
 
   #[test]
   fn datetime_generator_test() {
-    let generated = Generator::DateTime(None).generate_value(&"".to_string(), &hashmap!{});
+    let generated = Generator::DateTime(None, None).generate_value(&"".to_string(), &hashmap!{});
     assert_that!(generated.unwrap(), matches_regex(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\.\d{3}[-+]\d+$"));
 
-    let generated2 = Generator::DateTime(Some("yyyy-MM-dd HH:mm:ssZ".into())).generate_value(&"".to_string(), &hashmap!{});
+    let generated2 = Generator::DateTime(Some("yyyy-MM-dd HH:mm:ssZ".into()), None).generate_value(&"".to_string(), &hashmap!{});
     assert_that!(generated2.unwrap(), matches_regex(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}[-+]\d+$"));
   }
 
+  #[test]
+  fn date_and_time_generators_with_expressions_test() {
+    let tomorrow = Local::now().naive_local().date().succ().to_string();
+    expect!(Generator::Date(None, Some("today + 1 day".into())).generate_value(&"".to_string(), &hashmap!{}))
+      .to(be_ok().value(tomorrow.clone()));
+    expect!(Generator::Date(None, Some("today + 1 hour".into())).generate_value(&"".to_string(), &hashmap!{}))
+      .to(be_err());
+
+    expect!(Generator::Time(Some("HH:mm:ss".into()), Some("midnight + 90 minutes".into())).generate_value(&"".to_string(), &hashmap!{}))
+      .to(be_ok().value("01:30:00".to_string()));
+    expect!(Generator::Time(None, Some("2 o'clock pm".into())).generate_value(&"".to_string(), &hashmap!{}))
+      .to(be_ok().value("14:00:00".to_string()));
+
+    expect!(Generator::DateTime(Some("yyyy-MM-dd HH:mm".into()), Some("tomorrow @ noon".into())).generate_value(&"".to_string(), &hashmap!{}))
+      .to(be_ok().value(format!("{} 12:00", tomorrow)));
+  }
+
   #[test]
   fn regex_generator_test() {
     let generated = Generator::Regex(r"\d{4}\w{1,4}".into()).generate_value(&"".to_string(), &hashmap!{});
@@ -1411,6 +1599,33 @@ mod tests {
     assert_that!(generated.unwrap(), matches_regex(r"^\d{1,2}/\d{1,2}$"));
   }
 
+  #[test]
+  fn regex_generator_ignores_anchors_and_word_boundaries() {
+    for regex in &[r"^\d{4}", r"\d{4}$", r"\A\d{4}\z", r"\b\d{4}\b", r"(?m)^\d{4}$"] {
+      let generated = Generator::Regex(regex.to_string()).generate_value(&"".to_string(), &hashmap!{});
+      assert_that!(generated.unwrap(), matches_regex(r"^\d{4}$"));
+    }
+
+    let generated = Generator::Regex(r"^(?:red|green)$|^blue$".into()).generate_value(&"".to_string(), &hashmap!{});
+    assert_that!(generated.unwrap(), matches_regex(r"^(red|green|blue)$"));
+
+    let generated = Generator::Regex(r"^\$\d+\.\d{2}\^$".into()).generate_value(&"".to_string(), &hashmap!{});
+    assert_that!(generated.unwrap(), matches_regex(r"^\$\d+\.\d{2}\^$"));
+  }
+
+  #[test]
+  fn regex_generator_generates_printable_ascii_characters_where_the_classes_allow_it() {
+    for _ in 0..10 {
+      let generated = Generator::Regex(r".{10}\w{10}[^a-z]{10}\s\D".into()).generate_value(&"".to_string(), &hashmap!{});
+      assert_that!(generated.unwrap(), matches_regex(r"^[ -~]{10}[a-zA-Z0-9_]{10}[^a-z]{10} [^0-9]$"));
+    }
+
+    let generated = Generator::Regex(r"[a-z]+-\p{Greek}{3}".into()).generate_value(&"".to_string(), &hashmap!{});
+    assert_that!(generated.unwrap(), matches_regex(r"^[a-z]+-\p{Greek}{3}$"));
+
+    expect!(Generator::Regex(r"[a-z".into()).generate_value(&"".to_string(), &hashmap!{})).to(be_err());
+  }
+
   #[test]
   fn uuid_generator_test() {
     let generated = Generator::Uuid.generate_value(&"".to_string(), &hashmap!{});
@@ -1453,10 +1668,30 @@ mod tests {
         })
       });
     expect!(generated.unwrap()).to(be_equal_to("http://192.168.2.1:2345/p/path"));
+    let generated = generator.generate_value(&"".to_string(), &hashmap!{
+        "mockServer" => json!({
+          "href": "http://127.0.0.1:2345",
+          "port": 2345
+        })
+      });
+    expect!(generated.unwrap()).to(be_equal_to("http://127.0.0.1:2345/path"));
     let generated = generator.generate_value(&"".to_string(), &hashmap!{});
     expect!(generated).to(be_err());
   }
 
+  #[test]
+  fn path_parameter_generator_test() {
+    let generator = Generator::PathParameter("id".into());
+    let context = hashmap!{
+      "pathParameters" => json!({ "id": "100" })
+    };
+    expect!(generator.generate_value(&"1".to_string(), &context)).to(be_ok().value("100".to_string()));
+    expect!(generator.generate_value(&json!(1), &context)).to(be_ok().value(json!(100)));
+    expect!(generator.generate_value(&json!("1"), &context)).to(be_ok().value(json!("100")));
+    expect!(Generator::PathParameter("orderId".into()).generate_value(&"1".to_string(), &context)).to(be_err());
+    expect!(generator.generate_value(&"1".to_string(), &hashmap!{})).to(be_err());
+  }
+
   #[test]
   fn array_contains_generator_test() {
     let generator = Generator::ArrayContains(vec![
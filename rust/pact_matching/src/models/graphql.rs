@@ -0,0 +1,237 @@
+//! Builder for GraphQL interactions. GraphQL requests are JSON `POST` requests with the query
+//! document, variables and operation name, and the responses are JSON objects with the `data` and
+//! `errors` of the query.
+
+use maplit::*;
+use serde_json::{Map, Value};
+
+use pact_models::OptionalBody;
+
+use crate::graphql::normalise_query;
+use crate::models::{Request, RequestResponseInteraction, Response};
+use crate::models::matchingrules::{MatchingRule, MatchingRules, RuleLogic};
+use crate::models::provider_states::ProviderState;
+
+/// Builds a request/response interaction for a GraphQL query and its expected response.
+///
+/// The request matches any JSON content type, and the query is compared after it has been
+/// normalised, so it can be formatted differently by the consumer. The values of the expected
+/// `data` are matched by type, and if `errors` are expected, at least one error with a message is
+/// required.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQLInteractionBuilder {
+  description: String,
+  provider_states: Vec<ProviderState>,
+  path: String,
+  query: String,
+  operation_name: Option<String>,
+  variables: Option<Value>,
+  data: Option<Value>,
+  errors: Option<Value>
+}
+
+impl GraphQLInteractionBuilder {
+  /// Creates a builder for an interaction with the description and query document. The request is
+  /// sent to `/graphql` by default.
+  pub fn new(description: &str, query: &str) -> Self {
+    GraphQLInteractionBuilder {
+      description: description.to_string(),
+      provider_states: vec![],
+      path: "/graphql".to_string(),
+      query: query.to_string(),
+      operation_name: None,
+      variables: None,
+      data: None,
+      errors: None
+    }
+  }
+
+  /// Adds a provider state the interaction requires
+  pub fn given(&mut self, state: &str) -> &mut Self {
+    self.provider_states.push(ProviderState::default(&state.to_string()));
+    self
+  }
+
+  /// Sets the path of the GraphQL endpoint
+  pub fn path(&mut self, path: &str) -> &mut Self {
+    self.path = path.to_string();
+    self
+  }
+
+  /// Sets the name of the operation in the query document to execute
+  pub fn operation_name(&mut self, name: &str) -> &mut Self {
+    self.operation_name = Some(name.to_string());
+    self
+  }
+
+  /// Sets the variables of the query
+  pub fn variables(&mut self, variables: Value) -> &mut Self {
+    self.variables = Some(variables);
+    self
+  }
+
+  /// Sets the expected `data` of the response
+  pub fn data(&mut self, data: Value) -> &mut Self {
+    self.data = Some(data);
+    self
+  }
+
+  /// Sets the expected `errors` of the response. This can be either an array of errors, or a
+  /// single error.
+  pub fn errors(&mut self, errors: Value) -> &mut Self {
+    self.errors = Some(match errors {
+      Value::Array(_) => errors,
+      _ => Value::Array(vec![errors])
+    });
+    self
+  }
+
+  fn request_body(&self) -> Value {
+    let mut body = Map::new();
+    body.insert("query".to_string(), Value::String(self.query.clone()));
+    if let Some(operation_name) = &self.operation_name {
+      body.insert("operationName".to_string(), Value::String(operation_name.clone()));
+    }
+    if let Some(variables) = &self.variables {
+      body.insert("variables".to_string(), variables.clone());
+    }
+    Value::Object(body)
+  }
+
+  fn response_body(&self) -> Value {
+    let mut body = Map::new();
+    if self.data.is_some() || self.errors.is_none() {
+      body.insert("data".to_string(), self.data.clone().unwrap_or(Value::Null));
+    }
+    if let Some(errors) = &self.errors {
+      body.insert("errors".to_string(), errors.clone());
+    }
+    Value::Object(body)
+  }
+
+  /// Builds the interaction. Returns an error if the query document is not valid.
+  pub fn build(&self) -> anyhow::Result<RequestResponseInteraction> {
+    normalise_query(&self.query)
+      .map_err(|err| anyhow::anyhow!("'{}' is not a valid GraphQL query document - {}", self.query, err))?;
+
+    let mut request_rules = MatchingRules::default();
+    request_rules.add_category("header")
+      .add_rule("Content-Type", MatchingRule::Regex("^application/(.*\\+)?json(;.*)?$".to_string()), &RuleLogic::And);
+
+    let mut response_rules = MatchingRules::default();
+    let body_rules = response_rules.add_category("body");
+    if matches!(&self.data, Some(data) if !data.is_null()) {
+      body_rules.add_rule("$.data", MatchingRule::Type, &RuleLogic::And);
+    }
+    if self.errors.is_some() {
+      body_rules.add_rule("$.errors", MatchingRule::MinType(1), &RuleLogic::And);
+      body_rules.add_rule("$.errors[*].message", MatchingRule::Type, &RuleLogic::And);
+    }
+
+    Ok(RequestResponseInteraction {
+      description: self.description.clone(),
+      provider_states: self.provider_states.clone(),
+      request: Request {
+        method: "POST".to_string(),
+        path: self.path.clone(),
+        headers: Some(hashmap!{ "Content-Type".to_string() => vec!["application/json".to_string()] }),
+        body: OptionalBody::Present(self.request_body().to_string().into(), Some("application/json".into())),
+        matching_rules: request_rules,
+        .. Request::default()
+      },
+      response: Response {
+        status: 200,
+        headers: Some(hashmap!{ "Content-Type".to_string() => vec!["application/json".to_string()] }),
+        body: OptionalBody::Present(self.response_body().to_string().into(), Some("application/json".into())),
+        matching_rules: response_rules,
+        .. Response::default()
+      },
+      .. RequestResponseInteraction::default()
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::json;
+
+  use crate::{match_request, match_response};
+
+  use super::*;
+
+  fn json_request(body: Value, content_type: &str) -> Request {
+    Request {
+      method: "POST".to_string(),
+      path: "/graphql".to_string(),
+      headers: Some(hashmap!{ "Content-Type".to_string() => vec![content_type.to_string()] }),
+      body: OptionalBody::Present(body.to_string().into(), Some("application/json".into())),
+      .. Request::default()
+    }
+  }
+
+  fn json_response(body: Value) -> Response {
+    Response {
+      headers: Some(hashmap!{ "Content-Type".to_string() => vec!["application/json".to_string()] }),
+      body: OptionalBody::Present(body.to_string().into(), Some("application/json".into())),
+      .. Response::default()
+    }
+  }
+
+  #[test]
+  fn builds_a_graphql_interaction() {
+    let interaction = GraphQLInteractionBuilder::new("a request for a hero", "query Hero($id: ID!) { hero(id: $id) { name } }")
+      .given("there is a hero")
+      .operation_name("Hero")
+      .variables(json!({ "id": "1000" }))
+      .data(json!({ "hero": { "name": "Luke" } }))
+      .build()
+      .unwrap();
+
+    expect!(interaction.provider_states.iter().map(|s| s.name.clone()).collect::<Vec<_>>())
+      .to(be_equal_to(vec!["there is a hero".to_string()]));
+    expect!(interaction.request.method.as_str()).to(be_equal_to("POST"));
+    expect!(interaction.request.path.as_str()).to(be_equal_to("/graphql"));
+    expect!(serde_json::from_slice::<Value>(&interaction.request.body.value().unwrap()).unwrap()).to(be_equal_to(json!({
+      "query": "query Hero($id: ID!) { hero(id: $id) { name } }",
+      "operationName": "Hero",
+      "variables": { "id": "1000" }
+    })));
+    expect!(serde_json::from_slice::<Value>(&interaction.response.body.value().unwrap()).unwrap()).to(be_equal_to(json!({
+      "data": { "hero": { "name": "Luke" } }
+    })));
+
+    let actual_request = json_request(json!({
+      "query": "query Hero($id: ID!) {\n  hero(id: $id) {\n    name\n  }\n}",
+      "operationName": "Hero",
+      "variables": { "id": "1000" }
+    }), "application/json; charset=utf-8");
+    expect!(match_request(interaction.request.clone(), actual_request).all_matched()).to(be_true());
+    expect!(match_response(interaction.response.clone(), json_response(json!({ "data": { "hero": { "name": "Leia" } } }))).iter())
+      .to(be_empty());
+    expect!(match_response(interaction.response.clone(), json_response(json!({ "data": { "hero": { "name": 1 } } }))).iter())
+      .to_not(be_empty());
+  }
+
+  #[test]
+  fn builds_a_graphql_interaction_with_errors() {
+    let interaction = GraphQLInteractionBuilder::new("a request for a missing hero", "{ hero(id: \"1\") { name } }")
+      .path("/api/graphql")
+      .errors(json!({ "message": "Hero not found" }))
+      .build()
+      .unwrap();
+
+    expect!(interaction.request.path.as_str()).to(be_equal_to("/api/graphql"));
+    expect!(serde_json::from_slice::<Value>(&interaction.response.body.value().unwrap()).unwrap()).to(be_equal_to(json!({
+      "errors": [ { "message": "Hero not found" } ]
+    })));
+    expect!(match_response(interaction.response.clone(), json_response(json!({
+      "errors": [ { "message": "No hero with ID 1" }, { "message": "Another error" } ]
+    }))).iter()).to(be_empty());
+  }
+
+  #[test]
+  fn build_fails_for_an_invalid_query() {
+    expect!(GraphQLInteractionBuilder::new("an invalid query", "{ hero { name }").build()).to(be_err());
+  }
+}
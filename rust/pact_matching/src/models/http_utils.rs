@@ -1,45 +1,171 @@
 //! Module for fetching documents via HTTP
 
 use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use anyhow::anyhow;
-use reqwest::blocking::Client;
-use reqwest::Error;
+use anyhow::{anyhow, Context};
+use reqwest::{Certificate, Error, Identity};
+use reqwest::blocking::{Client, Response};
 use serde_json::Value;
 
+use crate::error::PactError;
+
 /// Type of authentication to use
 #[derive(Debug, Clone)]
 pub enum HttpAuth {
   /// Username and Password
   User(String, Option<String>),
   /// Bearer token
-  Token(String)
+  Token(String),
+  /// Static headers (i.e. an API key) to add to the requests, as name and value pairs
+  Headers(Vec<(String, String)>)
+}
+
+/// TLS configuration for HTTP clients
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsConfig {
+  /// PEM file with the root CA certificates to trust, in addition to the built-in ones
+  pub ca_certificate: Option<PathBuf>,
+  /// PEM file with the client certificate to authenticate with (mutual TLS)
+  pub client_certificate: Option<PathBuf>,
+  /// PEM file with the private key of the client certificate. Not required if the key is in the
+  /// client certificate file.
+  pub client_key: Option<PathBuf>,
+  /// Accept invalid (i.e. self-signed or expired) certificates. Only use this in test environments.
+  pub accept_invalid_certs: bool
+}
+
+impl TlsConfig {
+  /// Loads the root CA certificates, if configured
+  pub fn root_certificate(&self) -> anyhow::Result<Option<Certificate>> {
+    match &self.ca_certificate {
+      Some(path) => Certificate::from_pem(&read_pem(path)?)
+        .map(Some)
+        .map_err(|err| anyhow!("'{}' is not a valid PEM certificate - {}", path.to_string_lossy(), err)),
+      None => Ok(None)
+    }
+  }
+
+  /// Loads the client certificate and private key, if configured
+  pub fn identity(&self) -> anyhow::Result<Option<Identity>> {
+    match &self.client_certificate {
+      Some(path) => {
+        let mut pem = read_pem(path)?;
+        if let Some(key) = &self.client_key {
+          pem.push(b'\n');
+          pem.extend(read_pem(key)?);
+        }
+        Identity::from_pem(&pem)
+          .map(Some)
+          .map_err(|err| anyhow!("Could not load the client certificate from '{}' - {}", path.to_string_lossy(), err))
+      },
+      None if self.client_key.is_some() =>
+        Err(anyhow!("A client key was given without a client certificate")),
+      None => Ok(None)
+    }
+  }
+
+  /// Configures the builder of an async HTTP client with this TLS configuration
+  pub fn configure_client(&self, builder: reqwest::ClientBuilder) -> anyhow::Result<reqwest::ClientBuilder> {
+    if *self == TlsConfig::default() {
+      return Ok(builder);
+    }
+
+    let mut builder = builder.use_rustls_tls();
+    if let Some(certificate) = self.root_certificate()? {
+      builder = builder.add_root_certificate(certificate);
+    }
+    if let Some(identity) = self.identity()? {
+      builder = builder.identity(identity);
+    }
+    if self.accept_invalid_certs {
+      builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+  }
+
+  /// Configures the builder of a blocking HTTP client with this TLS configuration
+  pub fn configure_blocking_client(
+    &self,
+    builder: reqwest::blocking::ClientBuilder
+  ) -> anyhow::Result<reqwest::blocking::ClientBuilder> {
+    if *self == TlsConfig::default() {
+      return Ok(builder);
+    }
+
+    let mut builder = builder.use_rustls_tls();
+    if let Some(certificate) = self.root_certificate()? {
+      builder = builder.add_root_certificate(certificate);
+    }
+    if let Some(identity) = self.identity()? {
+      builder = builder.identity(identity);
+    }
+    if self.accept_invalid_certs {
+      builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+  }
+}
+
+fn read_pem(path: &Path) -> anyhow::Result<Vec<u8>> {
+  fs::read(path).with_context(|| format!("Could not read '{}'", path.to_string_lossy()))
+}
+
+/// Fetches the JSON from a URL, using the TLS configuration for HTTPS URLs
+pub fn fetch_json_from_url(url: &str, auth: &Option<HttpAuth>, tls: &TlsConfig) -> Result<(String, Value), PactError> {
+  let res = send_get_request(url, auth, tls)?;
+  let pact_json: Result<Value, Error> = res.json();
+  match pact_json {
+    Ok(ref json) => Ok((url.to_string(), json.clone())),
+    Err(err) => Err(PactError::parse_error(url, format!("Failed to parse JSON - {}", err)))
+  }
 }
 
-/// Fetches the JSON from a URL
-pub fn fetch_json_from_url(url: &String, auth: &Option<HttpAuth>) -> anyhow::Result<(String, Value)> {
-  let client = Client::new();
+/// Fetches the body of a URL as text, using the TLS configuration for HTTPS URLs
+pub fn fetch_text_from_url(url: &str, auth: &Option<HttpAuth>, tls: &TlsConfig) -> Result<String, PactError> {
+  send_get_request(url, auth, tls)?.text().map_err(|err| PactError::HttpError {
+    url: url.to_string(),
+    status: None,
+    message: format!("Failed to read the response body - {}", err)
+  })
+}
+
+fn send_get_request(url: &str, auth: &Option<HttpAuth>, tls: &TlsConfig) -> Result<Response, PactError> {
+  let client = tls.configure_blocking_client(Client::builder())
+    .and_then(|builder| builder.build().map_err(|err| anyhow!(err)))
+    .map_err(|err| PactError::HttpError {
+      url: url.to_string(),
+      status: None,
+      message: format!("Invalid TLS configuration - {}", err)
+    })?;
   let request = match auth {
-    &Some(ref auth) => {
+    Some(auth) => {
       match auth {
-        &HttpAuth::User(ref username, ref password) => client.get(url).basic_auth(username.clone(), password.clone()),
-        &HttpAuth::Token(ref token) => client.get(url).bearer_auth(token.clone())
+        HttpAuth::User(username, password) => client.get(url).basic_auth(username.clone(), password.clone()),
+        HttpAuth::Token(token) => client.get(url).bearer_auth(token.clone()),
+        HttpAuth::Headers(headers) => headers.iter()
+          .fold(client.get(url), |request, (name, value)| request.header(name.as_str(), value.as_str()))
       }
     },
-    &None => client.get(url)
+    None => client.get(url)
   };
 
   match request.send() {
     Ok(res) => if res.status().is_success() {
-      let pact_json: Result<Value, Error> = res.json();
-      match pact_json {
-        Ok(ref json) => Ok((url.clone(), json.clone())),
-        Err(err) => Err(anyhow!("Failed to parse JSON - {}", err))
-      }
+      Ok(res)
     } else {
-      Err(anyhow!("Request failed with status - {}", res.status()))
+      Err(PactError::HttpError {
+        url: url.to_string(),
+        status: Some(res.status().as_u16()),
+        message: format!("Request failed with status - {}", res.status())
+      })
     },
-    Err(err) => Err(anyhow!("Request failed - {}", err))
+    Err(err) => Err(PactError::HttpError {
+      url: url.to_string(),
+      status: err.status().map(|status| status.as_u16()),
+      message: format!("Request failed - {}", err)
+    })
   }
 }
 
@@ -54,7 +180,73 @@ impl Display for HttpAuth {
         } else {
           write!(f, "User({}, [no password])", u)
         }
+      },
+      HttpAuth::Headers(ref headers) => {
+        write!(f, "Headers(")?;
+        for (index, (name, value)) in headers.iter().enumerate() {
+          if index > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}: {:*<width$}", name, value.get(0..4).unwrap_or(""), width = value.len())?;
+        }
+        write!(f, ")")
       }
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  fn test_file(name: &str) -> Option<PathBuf> {
+    Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/tls").join(name))
+  }
+
+  #[test]
+  fn http_auth_display_masks_the_secrets() {
+    expect!(HttpAuth::Token("abcdefgh".to_string()).to_string()).to(be_equal_to("Token(abcd****)"));
+    expect!(HttpAuth::User("bob".to_string(), Some("secret".to_string())).to_string())
+      .to(be_equal_to("User(bob, secr**)"));
+    expect!(HttpAuth::Headers(vec![
+      ("X-Api-Key".to_string(), "1234567890".to_string()),
+      ("X-Tenant".to_string(), "acme".to_string())
+    ]).to_string()).to(be_equal_to("Headers(X-Api-Key: 1234******, X-Tenant: acme)"));
+  }
+
+  #[test]
+  fn tls_config_loads_the_certificates() {
+    let tls = TlsConfig {
+      ca_certificate: test_file("ca.pem"),
+      client_certificate: test_file("client.crt"),
+      client_key: test_file("client.key"),
+      accept_invalid_certs: false
+    };
+    expect!(tls.root_certificate().unwrap()).to(be_some());
+    expect!(tls.identity().unwrap()).to(be_some());
+    expect!(tls.configure_client(reqwest::Client::builder()).unwrap().build()).to(be_ok());
+    expect!(tls.configure_blocking_client(Client::builder()).unwrap().build()).to(be_ok());
+
+    let tls = TlsConfig::default();
+    expect!(tls.root_certificate().unwrap()).to(be_none());
+    expect!(tls.identity().unwrap()).to(be_none());
+  }
+
+  #[test]
+  fn tls_config_reports_invalid_certificates() {
+    let tls = TlsConfig { client_certificate: test_file("client.crt"), .. TlsConfig::default() };
+    expect!(tls.identity().unwrap_err().to_string().starts_with(&format!(
+      "Could not load the client certificate from '{}' - ", test_file("client.crt").unwrap().to_string_lossy())))
+      .to(be_true());
+
+    let tls = TlsConfig { client_key: test_file("client.key"), .. TlsConfig::default() };
+    expect!(tls.identity().unwrap_err().to_string())
+      .to(be_equal_to("A client key was given without a client certificate"));
+
+    let tls = TlsConfig { ca_certificate: test_file("missing.pem"), .. TlsConfig::default() };
+    expect!(tls.root_certificate().unwrap_err().to_string()).to(be_equal_to(format!(
+      "Could not read '{}'", test_file("missing.pem").unwrap().to_string_lossy())));
+  }
+}
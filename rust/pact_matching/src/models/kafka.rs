@@ -0,0 +1,182 @@
+//! Conventions for the metadata of messages that are published to Kafka topics.
+//!
+//! The topic, key and partition of a Kafka record are stored in the message metadata under the
+//! `kafka_topic`, `kafka_key` and `kafka_partition` keys, and the record headers as a JSON object
+//! under the `kafka_headers` key. V3 messages can only store string metadata values, so for these
+//! the partition is stored as a numeric string and the headers as a JSON encoded string.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::models::json_utils::json_to_string;
+use crate::models::message::Message;
+use crate::models::v4::AsynchronousMessage;
+
+/// Metadata key for the Kafka topic the message is published to
+pub const KAFKA_TOPIC: &str = "kafka_topic";
+/// Metadata key for the key of the Kafka record
+pub const KAFKA_KEY: &str = "kafka_key";
+/// Metadata key for the partition of the Kafka topic the message is published to
+pub const KAFKA_PARTITION: &str = "kafka_partition";
+/// Metadata key for the headers of the Kafka record
+pub const KAFKA_HEADERS: &str = "kafka_headers";
+
+/// Typed access to the Kafka metadata of a message
+pub trait KafkaMetadata {
+  /// Returns the raw metadata value for the key
+  fn metadata_value(&self, key: &str) -> Option<Value>;
+
+  /// Sets the raw metadata value for the key
+  fn set_metadata_value(&mut self, key: &str, value: Value);
+
+  /// Topic the message is published to
+  fn kafka_topic(&self) -> Option<String> {
+    self.metadata_value(KAFKA_TOPIC).map(|v| json_to_string(&v))
+  }
+
+  /// Sets the topic the message is published to
+  fn set_kafka_topic(&mut self, topic: &str) {
+    self.set_metadata_value(KAFKA_TOPIC, Value::String(topic.to_string()));
+  }
+
+  /// Key of the Kafka record
+  fn kafka_key(&self) -> Option<String> {
+    self.metadata_value(KAFKA_KEY).map(|v| json_to_string(&v))
+  }
+
+  /// Sets the key of the Kafka record
+  fn set_kafka_key(&mut self, key: &str) {
+    self.set_metadata_value(KAFKA_KEY, Value::String(key.to_string()));
+  }
+
+  /// Partition the message is published to. Returns None if the partition is not set or is not
+  /// a valid partition number.
+  fn kafka_partition(&self) -> Option<i32> {
+    self.metadata_value(KAFKA_PARTITION).and_then(|v| match v {
+      Value::Number(n) => n.as_i64().map(|n| n as i32),
+      Value::String(s) => s.trim().parse().ok(),
+      _ => None
+    })
+  }
+
+  /// Sets the partition the message is published to
+  fn set_kafka_partition(&mut self, partition: i32) {
+    self.set_metadata_value(KAFKA_PARTITION, json!(partition));
+  }
+
+  /// Headers of the Kafka record
+  fn kafka_headers(&self) -> HashMap<String, String> {
+    headers_from_value(self.metadata_value(KAFKA_HEADERS).as_ref())
+  }
+
+  /// Sets a header of the Kafka record, replacing any existing value
+  fn set_kafka_header(&mut self, name: &str, value: &str) {
+    let mut headers = match self.metadata_value(KAFKA_HEADERS).map(normalise_headers) {
+      Some(Value::Object(map)) => map,
+      _ => serde_json::Map::new()
+    };
+    headers.insert(name.to_string(), Value::String(value.to_string()));
+    self.set_metadata_value(KAFKA_HEADERS, Value::Object(headers));
+  }
+}
+
+/// Converts the Kafka headers metadata value into a JSON object. V3 messages store the headers
+/// as a JSON encoded string, which will be parsed. Any other value is returned as is.
+pub fn normalise_headers(value: Value) -> Value {
+  match &value {
+    Value::String(s) => match serde_json::from_str::<Value>(s) {
+      Ok(Value::Object(map)) => Value::Object(map),
+      _ => value
+    },
+    _ => value
+  }
+}
+
+fn headers_from_value(value: Option<&Value>) -> HashMap<String, String> {
+  match value.cloned().map(normalise_headers) {
+    Some(Value::Object(map)) => map.iter()
+      .map(|(k, v)| (k.clone(), json_to_string(v)))
+      .collect(),
+    _ => HashMap::new()
+  }
+}
+
+impl KafkaMetadata for Message {
+  fn metadata_value(&self, key: &str) -> Option<Value> {
+    self.metadata.get(key).map(|v| Value::String(v.clone()))
+  }
+
+  fn set_metadata_value(&mut self, key: &str, value: Value) {
+    let value = match value {
+      Value::String(s) => s,
+      _ => value.to_string()
+    };
+    self.metadata.insert(key.to_string(), value);
+  }
+}
+
+impl KafkaMetadata for AsynchronousMessage {
+  fn metadata_value(&self, key: &str) -> Option<Value> {
+    self.metadata.get(key).cloned()
+  }
+
+  fn set_metadata_value(&mut self, key: &str, value: Value) {
+    self.metadata.insert(key.to_string(), value);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use crate::models::message::Message;
+  use crate::models::v4::AsynchronousMessage;
+
+  use super::*;
+
+  #[test]
+  fn kafka_metadata_for_v3_messages_is_stored_as_strings() {
+    let mut message = Message::default();
+    message.set_kafka_topic("orders");
+    message.set_kafka_key("order-1");
+    message.set_kafka_partition(3);
+    message.set_kafka_header("traceId", "abc");
+    message.set_kafka_header("source", "web");
+
+    expect!(message.metadata.get(KAFKA_PARTITION).cloned()).to(be_some().value("3"));
+    expect!(message.kafka_topic()).to(be_some().value("orders"));
+    expect!(message.kafka_key()).to(be_some().value("order-1"));
+    expect!(message.kafka_partition()).to(be_some().value(3));
+    expect!(message.kafka_headers()).to(be_equal_to(hashmap!{
+      "traceId".to_string() => "abc".to_string(),
+      "source".to_string() => "web".to_string()
+    }));
+  }
+
+  #[test]
+  fn kafka_metadata_for_v4_messages_is_stored_as_json() {
+    let mut message = AsynchronousMessage::default();
+    message.set_kafka_topic("orders");
+    message.set_kafka_partition(3);
+    message.set_kafka_header("traceId", "abc");
+
+    expect!(message.metadata.get(KAFKA_PARTITION).cloned()).to(be_some().value(json!(3)));
+    expect!(message.metadata.get(KAFKA_HEADERS).cloned()).to(be_some().value(json!({"traceId": "abc"})));
+    expect!(message.kafka_topic()).to(be_some().value("orders"));
+    expect!(message.kafka_key()).to(be_none());
+    expect!(message.kafka_partition()).to(be_some().value(3));
+    expect!(message.kafka_headers()).to(be_equal_to(hashmap!{ "traceId".to_string() => "abc".to_string() }));
+  }
+
+  #[test]
+  fn kafka_partition_ignores_invalid_values() {
+    let mut message = AsynchronousMessage::default();
+    message.metadata.insert(KAFKA_PARTITION.to_string(), json!("not a number"));
+    expect!(message.kafka_partition()).to(be_none());
+    message.metadata.insert(KAFKA_PARTITION.to_string(), json!("7"));
+    expect!(message.kafka_partition()).to(be_some().value(7));
+  }
+}
@@ -0,0 +1,472 @@
+//! Parser for matching rule definition expressions. These are a compact way for integrations
+//! (i.e. FFI callers and plugins) to set the example value, matching rules and generator for a
+//! value from a single string, for example `matching(type, 'Name')`,
+//! `matching(datetime, 'yyyy-MM-dd HH:mm:ss', '2000-01-01 12:00:00')`, `notEmpty('test')` or
+//! `eachLike(matching(integer, 100), 2)`.
+//!
+//! Multiple expressions can be separated with commas, i.e. `matching(type, 'Name'), notEmpty('Name')`.
+//! The example value is taken from the first expression, and the matching rules of all of them
+//! are combined.
+
+use anyhow::anyhow;
+use serde_json::{json, Value};
+
+use crate::models::generators::{Generator, GeneratorCategory, Generators};
+use crate::models::matchingrules::{MatchingRule, MatchingRuleCategory, RuleLogic};
+
+/// Example value, matching rules and generator parsed from a matching rule definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchingRuleDefinition {
+  /// Example value
+  pub value: Value,
+  /// Matching rules to apply to the value
+  pub rules: Vec<MatchingRule>,
+  /// Generator for the value
+  pub generator: Option<Generator>,
+  /// Definition for each item of the value, when it is a collection (from `eachLike`)
+  pub each_item: Option<Box<MatchingRuleDefinition>>
+}
+
+impl MatchingRuleDefinition {
+  fn new(value: Value, rule: Option<MatchingRule>, generator: Option<Generator>) -> Self {
+    MatchingRuleDefinition {
+      value,
+      rules: rule.into_iter().collect(),
+      generator,
+      each_item: None
+    }
+  }
+
+  fn merge(mut self, other: MatchingRuleDefinition) -> Self {
+    self.rules.extend(other.rules);
+    if self.generator.is_none() {
+      self.generator = other.generator;
+    }
+    if self.each_item.is_none() {
+      self.each_item = other.each_item;
+    }
+    self
+  }
+
+  /// Adds the matching rules and generator of this definition for the path (i.e. `$.name`) to
+  /// the matching rule category and generators. The definition for the items of a collection is
+  /// added for all the items of the path (i.e. `$.name[*]`).
+  pub fn add_to(
+    &self,
+    path: &str,
+    rules: &mut MatchingRuleCategory,
+    generators: &mut Generators,
+    category: &GeneratorCategory
+  ) {
+    for rule in &self.rules {
+      rules.add_rule(path, rule.clone(), &RuleLogic::And);
+    }
+    if let Some(generator) = &self.generator {
+      generators.add_generator_with_subcategory(category, path, generator.clone());
+    }
+    if let Some(item) = &self.each_item {
+      item.add_to(format!("{}[*]", path).as_str(), rules, generators, category);
+    }
+  }
+}
+
+/// If the string value is a matching rule definition expression
+pub fn is_matcher_def(value: &str) -> bool {
+  let value = value.trim_start();
+  ["matching(", "notEmpty(", "eachLike("].iter().any(|prefix| value.starts_with(prefix))
+}
+
+/// Parses the matching rule definition expression into the example value, matching rules and
+/// generator it defines
+pub fn parse_matcher_def(expression: &str) -> anyhow::Result<MatchingRuleDefinition> {
+  let mut parser = Parser { input: expression, position: 0 };
+  let definition = parser.definitions()?;
+  parser.skip_whitespace();
+  if parser.position < expression.len() {
+    Err(parser.error("end of the expression"))
+  } else {
+    Ok(definition)
+  }
+}
+
+struct Parser<'a> {
+  input: &'a str,
+  position: usize
+}
+
+impl <'a> Parser<'a> {
+  fn remaining(&self) -> &'a str {
+    &self.input[self.position..]
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.remaining().chars().next()
+  }
+
+  fn skip_whitespace(&mut self) {
+    let remaining = self.remaining();
+    self.position += remaining.len() - remaining.trim_start().len();
+  }
+
+  fn error(&self, expected: &str) -> anyhow::Error {
+    anyhow!("Expected {} at index {} of matching rule definition '{}'", expected, self.position, self.input)
+  }
+
+  fn expect(&mut self, ch: char) -> anyhow::Result<()> {
+    self.skip_whitespace();
+    if self.peek() == Some(ch) {
+      self.position += 1;
+      Ok(())
+    } else {
+      Err(self.error(format!("'{}'", ch).as_str()))
+    }
+  }
+
+  fn next_is(&mut self, ch: char) -> bool {
+    self.skip_whitespace();
+    self.peek() == Some(ch)
+  }
+
+  fn identifier(&mut self) -> anyhow::Result<&'a str> {
+    self.skip_whitespace();
+    let remaining = self.remaining();
+    let len = remaining.find(|ch: char| !ch.is_ascii_alphanumeric()).unwrap_or(remaining.len());
+    if len == 0 || !remaining.starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+      Err(self.error("an identifier"))
+    } else {
+      self.position += len;
+      Ok(&remaining[..len])
+    }
+  }
+
+  fn string(&mut self) -> anyhow::Result<String> {
+    self.expect('\'').map_err(|_| self.error("a quoted string"))?;
+    let mut result = String::new();
+    let mut chars = self.remaining().char_indices();
+    while let Some((index, ch)) = chars.next() {
+      match ch {
+        '\'' => {
+          self.position += index + 1;
+          return Ok(result);
+        },
+        '\\' => match chars.next() {
+          Some((_, 'n')) => result.push('\n'),
+          Some((_, 't')) => result.push('\t'),
+          Some((_, 'r')) => result.push('\r'),
+          Some((_, escaped)) => result.push(escaped),
+          None => break
+        },
+        _ => result.push(ch)
+      }
+    }
+    self.position = self.input.len();
+    Err(self.error("a closing quote"))
+  }
+
+  fn number(&mut self) -> anyhow::Result<Value> {
+    self.skip_whitespace();
+    let remaining = self.remaining();
+    let len = remaining.find(|ch: char| !(ch.is_ascii_digit() || "-+.eE".contains(ch)))
+      .unwrap_or(remaining.len());
+    match serde_json::from_str::<Value>(&remaining[..len]) {
+      Ok(value) if value.is_number() => {
+        self.position += len;
+        Ok(value)
+      },
+      _ => Err(self.error("a number"))
+    }
+  }
+
+  fn integer(&mut self) -> anyhow::Result<Value> {
+    self.skip_whitespace();
+    let start = self.position;
+    let value = self.number()?;
+    if value.is_i64() || value.is_u64() {
+      Ok(value)
+    } else {
+      self.position = start;
+      Err(self.error("an integer"))
+    }
+  }
+
+  fn length(&mut self) -> anyhow::Result<usize> {
+    self.skip_whitespace();
+    let start = self.position;
+    match self.integer()?.as_u64() {
+      Some(value) => Ok(value as usize),
+      None => {
+        self.position = start;
+        Err(self.error("a positive integer"))
+      }
+    }
+  }
+
+  fn boolean(&mut self) -> anyhow::Result<Value> {
+    self.skip_whitespace();
+    let start = self.position;
+    match self.identifier() {
+      Ok("true") => Ok(json!(true)),
+      Ok("false") => Ok(json!(false)),
+      _ => {
+        self.position = start;
+        Err(self.error("a boolean"))
+      }
+    }
+  }
+
+  fn primitive(&mut self) -> anyhow::Result<Value> {
+    self.skip_whitespace();
+    match self.peek() {
+      Some('\'') => self.string().map(Value::String),
+      Some(ch) if ch.is_ascii_digit() || ch == '-' => self.number(),
+      _ => {
+        let start = self.position;
+        match self.identifier() {
+          Ok("true") => Ok(json!(true)),
+          Ok("false") => Ok(json!(false)),
+          Ok("null") => Ok(Value::Null),
+          _ => {
+            self.position = start;
+            Err(self.error("a string, number, boolean or null value"))
+          }
+        }
+      }
+    }
+  }
+
+  // definitions: definition (',' definition)*
+  fn definitions(&mut self) -> anyhow::Result<MatchingRuleDefinition> {
+    let mut result = self.definition()?;
+    while self.next_is(',') {
+      self.position += 1;
+      result = result.merge(self.definition()?);
+    }
+    Ok(result)
+  }
+
+  // definition: 'matching' '(' ... ')' | 'notEmpty' '(' primitive ')' | 'eachLike' '(' ... ')'
+  fn definition(&mut self) -> anyhow::Result<MatchingRuleDefinition> {
+    let start = self.position;
+    let definition = match self.identifier() {
+      Ok("matching") => {
+        self.expect('(')?;
+        self.matching()?
+      },
+      Ok("notEmpty") => {
+        self.expect('(')?;
+        let value = self.primitive()?;
+        MatchingRuleDefinition::new(value, Some(MatchingRule::MinType(1)), None)
+      },
+      Ok("eachLike") => {
+        self.expect('(')?;
+        self.each_like()?
+      },
+      _ => {
+        self.position = start;
+        self.skip_whitespace();
+        return Err(self.error("one of 'matching', 'notEmpty' or 'eachLike'"));
+      }
+    };
+    self.expect(')')?;
+    Ok(definition)
+  }
+
+  // matching: type ',' value (',' value)*
+  fn matching(&mut self) -> anyhow::Result<MatchingRuleDefinition> {
+    self.skip_whitespace();
+    let start = self.position;
+    let matcher_type = self.identifier()?;
+    self.expect(',')?;
+    match matcher_type {
+      "type" => Ok(MatchingRuleDefinition::new(self.primitive()?, Some(MatchingRule::Type), None)),
+      "equalTo" => Ok(MatchingRuleDefinition::new(self.primitive()?, Some(MatchingRule::Equality), None)),
+      "number" => Ok(MatchingRuleDefinition::new(self.number()?, Some(MatchingRule::Number), None)),
+      "integer" => Ok(MatchingRuleDefinition::new(self.integer()?, Some(MatchingRule::Integer),
+        Some(Generator::RandomInt(0, 10)))),
+      "decimal" => Ok(MatchingRuleDefinition::new(self.number()?, Some(MatchingRule::Decimal),
+        Some(Generator::RandomDecimal(10)))),
+      "boolean" => Ok(MatchingRuleDefinition::new(self.boolean()?, Some(MatchingRule::Type), None)),
+      "datetime" | "timestamp" | "date" | "time" => {
+        let format = self.string()?;
+        self.expect(',')?;
+        let value = Value::String(self.string()?);
+        let (rule, generator) = match matcher_type {
+          "date" => (MatchingRule::Date(format.clone()), Generator::Date(Some(format), None)),
+          "time" => (MatchingRule::Time(format.clone()), Generator::Time(Some(format), None)),
+          _ => (MatchingRule::Timestamp(format.clone()), Generator::DateTime(Some(format), None))
+        };
+        Ok(MatchingRuleDefinition::new(value, Some(rule), Some(generator)))
+      },
+      "regex" => {
+        let regex = self.string()?;
+        if let Err(err) = onig::Regex::new(regex.as_str()) {
+          return Err(anyhow!("'{}' is not a valid regular expression - {}", regex, err));
+        }
+        self.expect(',')?;
+        let value = Value::String(self.string()?);
+        Ok(MatchingRuleDefinition::new(value, Some(MatchingRule::Regex(regex.clone())),
+          Some(Generator::Regex(regex))))
+      },
+      "include" => {
+        let value = self.string()?;
+        Ok(MatchingRuleDefinition::new(Value::String(value.clone()), Some(MatchingRule::Include(value)), None))
+      },
+      "contentType" => {
+        let content_type = self.string()?;
+        self.expect(',')?;
+        let value = Value::String(self.string()?);
+        Ok(MatchingRuleDefinition::new(value, Some(MatchingRule::ContentType(content_type)), None))
+      },
+      _ => {
+        self.position = start;
+        Err(anyhow!("'{}' is not a valid matching rule type (at index {} of matching rule definition '{}')",
+          matcher_type, start, self.input))
+      }
+    }
+  }
+
+  // each_like: (definitions | primitive) (',' min (',' max)?)?
+  fn each_like(&mut self) -> anyhow::Result<MatchingRuleDefinition> {
+    self.skip_whitespace();
+    let item = if self.peek().map(|ch| ch.is_ascii_alphabetic()).unwrap_or(false)
+      && is_matcher_def(self.remaining()) {
+      let mut item = self.definition()?;
+      while self.next_is(',') && !self.after_comma_is_number() {
+        self.position += 1;
+        item = item.merge(self.definition()?);
+      }
+      item
+    } else {
+      MatchingRuleDefinition::new(self.primitive()?, None, None)
+    };
+
+    let mut min = 1;
+    let mut max = None;
+    if self.next_is(',') {
+      self.position += 1;
+      min = self.length()?;
+      if self.next_is(',') {
+        self.position += 1;
+        let max_value = self.length()?;
+        if max_value < min {
+          return Err(anyhow!("The maximum ({}) must not be less than the minimum ({}) in matching rule definition '{}'",
+            max_value, min, self.input));
+        }
+        max = Some(max_value);
+      }
+    }
+
+    let rule = match max {
+      Some(max) => MatchingRule::MinMaxType(min, max),
+      None => MatchingRule::MinType(min)
+    };
+    let value = Value::Array(vec![item.value.clone(); min.max(1)]);
+    Ok(MatchingRuleDefinition {
+      value,
+      rules: vec![rule],
+      generator: None,
+      each_item: if item.rules.is_empty() && item.generator.is_none() && item.each_item.is_none() {
+        None
+      } else {
+        Some(Box::new(item))
+      }
+    })
+  }
+
+  fn after_comma_is_number(&self) -> bool {
+    self.remaining().trim_start().strip_prefix(',')
+      .map(|rest| rest.trim_start().starts_with(|ch: char| ch.is_ascii_digit()))
+      .unwrap_or(false)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::hashmap;
+
+  use super::*;
+
+  #[test]
+  fn parses_matching_definitions() {
+    expect!(parse_matcher_def("matching(type, 'Name')").unwrap()).to(be_equal_to(MatchingRuleDefinition {
+      value: json!("Name"), rules: vec![MatchingRule::Type], generator: None, each_item: None
+    }));
+    expect!(parse_matcher_def(" matching( integer , -100 ) ").unwrap()).to(be_equal_to(MatchingRuleDefinition {
+      value: json!(-100), rules: vec![MatchingRule::Integer], generator: Some(Generator::RandomInt(0, 10)), each_item: None
+    }));
+    expect!(parse_matcher_def("matching(decimal, 100.5)").unwrap().rules).to(be_equal_to(vec![MatchingRule::Decimal]));
+    expect!(parse_matcher_def("matching(boolean, false)").unwrap().value).to(be_equal_to(json!(false)));
+    expect!(parse_matcher_def("matching(datetime, 'yyyy-MM-dd HH:mm:ss', '2000-01-01 12:00:00')").unwrap())
+      .to(be_equal_to(MatchingRuleDefinition {
+        value: json!("2000-01-01 12:00:00"),
+        rules: vec![MatchingRule::Timestamp("yyyy-MM-dd HH:mm:ss".to_string())],
+        generator: Some(Generator::DateTime(Some("yyyy-MM-dd HH:mm:ss".to_string()), None)),
+        each_item: None
+      }));
+    expect!(parse_matcher_def(r"matching(regex, '\\d+', 'it\'s 100')").unwrap()).to(be_equal_to(MatchingRuleDefinition {
+      value: json!("it's 100"),
+      rules: vec![MatchingRule::Regex("\\d+".to_string())],
+      generator: Some(Generator::Regex("\\d+".to_string())),
+      each_item: None
+    }));
+    expect!(parse_matcher_def("matching(type, 'Name'), notEmpty('Name')").unwrap().rules)
+      .to(be_equal_to(vec![MatchingRule::Type, MatchingRule::MinType(1)]));
+  }
+
+  #[test]
+  fn parses_each_like_definitions() {
+    expect!(parse_matcher_def("eachLike('a')").unwrap()).to(be_equal_to(MatchingRuleDefinition {
+      value: json!(["a"]), rules: vec![MatchingRule::MinType(1)], generator: None, each_item: None
+    }));
+    expect!(parse_matcher_def("eachLike(matching(integer, 1), notEmpty(1), 2, 4)").unwrap()).to(be_equal_to(MatchingRuleDefinition {
+      value: json!([1, 1]),
+      rules: vec![MatchingRule::MinMaxType(2, 4)],
+      generator: None,
+      each_item: Some(Box::new(MatchingRuleDefinition {
+        value: json!(1),
+        rules: vec![MatchingRule::Integer, MatchingRule::MinType(1)],
+        generator: Some(Generator::RandomInt(0, 10)),
+        each_item: None
+      }))
+    }));
+  }
+
+  #[test]
+  fn returns_an_error_for_invalid_definitions() {
+    expect!(parse_matcher_def("matching(integer, 1.5)").unwrap_err().to_string()).to(
+      be_equal_to("Expected an integer at index 18 of matching rule definition 'matching(integer, 1.5)'"));
+    expect!(parse_matcher_def("matching(other, 1)").unwrap_err().to_string()).to(
+      be_equal_to("'other' is not a valid matching rule type (at index 9 of matching rule definition 'matching(other, 1)')"));
+    expect!(parse_matcher_def("matching(type, 'a'").unwrap_err().to_string()).to(
+      be_equal_to("Expected ')' at index 18 of matching rule definition 'matching(type, 'a''"));
+    expect!(parse_matcher_def("matching(type, 'a) ").unwrap_err().to_string()).to(
+      be_equal_to("Expected a closing quote at index 19 of matching rule definition 'matching(type, 'a) '"));
+    expect!(parse_matcher_def("eachLike('a', 2, 1)")).to(be_err());
+    expect!(parse_matcher_def("other('a')")).to(be_err());
+    expect!(parse_matcher_def("matching(type, 'a') x")).to(be_err());
+  }
+
+  #[test]
+  fn add_to_sets_the_rules_and_generators_for_the_path_and_the_collection_items() {
+    let definition = parse_matcher_def("eachLike(matching(integer, 1))").unwrap();
+    let mut rules = MatchingRuleCategory::empty("body");
+    let mut generators = Generators::default();
+    definition.add_to("$.ids", &mut rules, &mut generators, &GeneratorCategory::BODY);
+
+    expect!(rules.rules.get("$.ids").map(|rules| rules.rules.clone())).to(be_some().value(vec![MatchingRule::MinType(1)]));
+    expect!(rules.rules.get("$.ids[*]").map(|rules| rules.rules.clone())).to(be_some().value(vec![MatchingRule::Integer]));
+    expect!(generators.categories.get(&GeneratorCategory::BODY).cloned()).to(be_some().value(hashmap!{
+      "$.ids[*]".to_string() => Generator::RandomInt(0, 10)
+    }));
+  }
+
+  #[test]
+  fn is_matcher_def_test() {
+    expect!(is_matcher_def("matching(type, 'a')")).to(be_true());
+    expect!(is_matcher_def(" eachLike('a')")).to(be_true());
+    expect!(is_matcher_def("notEmpty('a')")).to(be_true());
+    expect!(is_matcher_def("matching")).to(be_false());
+    expect!(is_matcher_def("'matching(type, 1)'")).to(be_false());
+  }
+}
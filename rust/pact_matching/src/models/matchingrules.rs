@@ -982,6 +982,19 @@ impl MatchingRuleCategory {
     rules.rules.push(matcher);
   }
 
+  /// Adds the rules from the other category to this category. Rules that are already defined for a
+  /// path are not added again.
+  pub fn add_rules_from(&mut self, category: &MatchingRuleCategory) {
+    for (path, rule_list) in &category.rules {
+      let rules = self.rules.entry(path.clone()).or_insert_with(|| RuleList::empty(&rule_list.rule_logic));
+      for rule in &rule_list.rules {
+        if !rules.rules.contains(rule) {
+          rules.add_rule(rule);
+        }
+      }
+    }
+  }
+
   /// Filters the matchers in the category by the predicate, and returns a new category
   pub fn filter<F>(&self, predicate: F) -> MatchingRuleCategory
     where F : Fn(&(&String, &RuleList)) -> bool {
@@ -1056,7 +1069,7 @@ impl MatchingRuleCategory {
 
   /// Selects the best matcher for the given path by calculating a weighting for each one
   pub fn select_best_matcher(&self, path: &[&str]) -> Option<RuleList> {
-    if self.name == "body" || self.name == "content" {
+    if self.name == "body" || self.name == "content" || self.name == "header" {
       self.max_by_path(path)
     } else {
       self.resolve_matchers_for_path(path).as_rule_list()
@@ -1070,7 +1083,7 @@ impl MatchingRuleCategory {
 
   /// Adds the rules to the category from the provided JSON
   pub fn add_rules_from_json(&mut self, rules: &Value) {
-    if self.name == "path" && rules.get("matchers").is_some() {
+    if (self.name == "path" || self.name == "method") && rules.get("matchers").is_some() {
       let rule_logic = match rules.get("combine") {
         Some(val) => if json_to_string(val).to_uppercase() == "OR" {
           RuleLogic::Or
@@ -1204,6 +1217,14 @@ impl MatchingRules {
       self.rules.get_mut(&category).unwrap()
     }
 
+    /// Adds the rules from the other matching rules to these rules (the union of both). Rules that
+    /// are already defined for a category and path are not added again.
+    pub fn add_rules_from(&mut self, rules: &MatchingRules) {
+      for (name, category) in &rules.rules {
+        self.add_category(name.clone()).add_rules_from(category);
+      }
+    }
+
     /// Returns all the category names in this rule set
     pub fn categories(&self) -> HashSet<String> {
       self.rules.keys().cloned().collect()
@@ -1280,9 +1301,14 @@ impl MatchingRules {
             self.add_v2_rule(s!("body"), format!("${}", s!(key[6..])), v);
           }
         } else if key.starts_with("$.headers") {
-          self.add_v2_rule(s!("header"), path[2].clone(), v);
-        } else {
+          match path.get(2) {
+            Some(header) => self.add_v2_rule(s!("header"), header.clone(), v),
+            None => warn!("Ignoring V2 matching rule with invalid path '{}'", key)
+          }
+        } else if path.len() > 1 {
           self.add_v2_rule(path[1].clone(), if path.len() > 2 { path[2].clone() } else { s!("") }, v);
+        } else {
+          warn!("Ignoring V2 matching rule with invalid path '{}'", key);
         }
       }
     }
@@ -1305,7 +1331,7 @@ impl MatchingRules {
 
   fn to_v3_json(&self) -> Value {
     Value::Object(self.rules.iter().fold(serde_json::Map::new(), |mut map, (name, sub_category)| {
-      if name == "path" {
+      if name == "path" || name == "method" {
         if let Some(rules) = sub_category.rules.get("") {
           map.insert(name.clone(), rules.to_v3_json());
         }
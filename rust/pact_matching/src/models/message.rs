@@ -1,18 +1,19 @@
 //! The `message` module provides all functionality to deal with messages.
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use maplit::*;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use pact_models::OptionalBody;
 
 use crate::models::generators::Generators;
 use crate::models::matchingrules::MatchingRules;
 use crate::models::provider_states::ProviderState;
-use crate::models::v4::AsynchronousMessage;
+use crate::models::v4::{AsynchronousMessage, SynchronousMessages};
 
 use super::*;
 use super::body_from_json;
@@ -130,6 +131,10 @@ impl Interaction for Message {
     })
   }
 
+  fn as_v4_sync_message(&self) -> Option<SynchronousMessages> {
+    None
+  }
+
   fn boxed(&self) -> Box<dyn Interaction + Send> {
     Box::new(self.clone())
   }
@@ -145,6 +150,10 @@ impl Interaction for Message {
   fn matching_rules(&self) -> Option<MatchingRules> {
     Some(self.matching_rules.clone())
   }
+
+  fn timeout(&self) -> Option<Duration> {
+    None
+  }
 }
 
 impl Message {
@@ -174,7 +183,8 @@ impl Message {
                     },
                     None => format!("Message {}", index)
                 };
-                let provider_states = ProviderState::from_json(json);
+                let provider_states = ProviderState::from_json(json)
+                  .map_err(|err| interaction_error(index, &description, err))?;
                 let metadata = match json.get("metaData") {
                     Some(&Value::Object(ref v)) => v.iter().map(|(k, v)| {
                         (k.clone(), match v {
@@ -191,12 +201,56 @@ impl Message {
                   contents: body_from_json(json, "contents", &None),
                   matching_rules: matchingrules::matchers_from_json(json, &None),
                   metadata,
-                  generators: Generators::default()
+                  generators: generators::generators_from_json(json)
                 })
             },
             _ => Err(anyhow!("Messages require Pact Specification version 3"))
         }
     }
+
+    /// Converts this message to a `Value` struct.
+    pub fn to_json(&self, spec_version: &PactSpecification) -> Value {
+      let mut json = json!({
+        "description": self.description.clone()
+      });
+      let map = json.as_object_mut().unwrap();
+      if !self.provider_states.is_empty() {
+        map.insert("providerStates".to_string(), Value::Array(
+          self.provider_states.iter().map(|p| p.to_json()).collect()));
+      }
+      match &self.contents {
+        OptionalBody::Present(body, _) => if Interaction::content_type(self).unwrap_or_default().is_json() {
+          match serde_json::from_slice(body) {
+            Ok(json_body) => { map.insert("contents".to_string(), json_body); },
+            Err(err) => {
+              log::warn!("Failed to parse json body: {}", err);
+              map.insert("contents".to_string(), Value::String(base64::encode(body)));
+            }
+          }
+        } else {
+          match std::str::from_utf8(body) {
+            Ok(s) => map.insert("contents".to_string(), Value::String(s.to_string())),
+            Err(_) => map.insert("contents".to_string(), Value::String(base64::encode(body)))
+          };
+        },
+        OptionalBody::Empty => { map.insert("contents".to_string(), Value::String(String::default())); },
+        OptionalBody::Missing => (),
+        OptionalBody::Null => { map.insert("contents".to_string(), Value::Null); }
+      }
+      if !self.metadata.is_empty() {
+        map.insert("metaData".to_string(), Value::Object(
+          self.metadata.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect()));
+      }
+      if self.matching_rules.is_not_empty() {
+        map.insert("matchingRules".to_string(), matchingrules::matchers_to_json(
+          &self.matching_rules, spec_version));
+      }
+      if self.generators.is_not_empty() {
+        map.insert("generators".to_string(), generators::generators_to_json(
+          &self.generators, spec_version));
+      }
+      json
+    }
 }
 
 impl HttpPart for Message {
@@ -241,12 +295,15 @@ fn missing_body() -> OptionalBody {
 
 #[cfg(test)]
 mod tests {
+  use std::str::FromStr;
+
   use expectest::expect;
   use expectest::prelude::*;
   use serde_json;
 
   use super::*;
   use super::super::matchingrules::MatchingRule;
+  use super::super::generators::Generator;
 
   #[test]
     fn loading_message_from_json() {
@@ -420,4 +477,35 @@ mod tests {
       matchingrules! { "content" => { "user_id" => [ MatchingRule::Regex("^[0-9]+$".into()) ] }}
     ));
   }
+
+    #[test]
+    fn message_to_json_includes_the_matching_rules() {
+      let message = Message {
+        description: s!("a message"),
+        contents: OptionalBody::Present("{\"id\": 100}".into(), None),
+        matching_rules: matchingrules! {
+          "body" => { "$.id" => [ MatchingRule::Integer ] }
+        },
+        .. Message::default()
+      };
+      expect!(message.to_json(&PactSpecification::V3)).to(be_equal_to(json!({
+        "description": "a message",
+        "contents": { "id": 100 },
+        "metaData": { "contentType": "application/json" },
+        "matchingRules": { "body": { "$.id": { "combine": "AND", "matchers": [ { "match": "integer" } ] } } }
+      })));
+    }
+
+    #[test]
+    fn loading_message_from_json_with_generators() {
+      let message_json = json!({
+        "description": "a message",
+        "contents": { "id": 100 },
+        "generators": { "body": { "$.id": { "type": "RandomInt", "min": 1, "max": 10 } } }
+      });
+      let message = Message::from_json(0, &message_json, &PactSpecification::V3).unwrap();
+      expect!(message.generators).to(be_equal_to(generators! {
+        "BODY" => { "$.id" => Generator::RandomInt(1, 10) }
+      }));
+    }
 }
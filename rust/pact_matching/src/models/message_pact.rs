@@ -9,7 +9,6 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, bail};
 use itertools::EitherOrBoth::{Both, Left, Right};
 use itertools::Itertools;
 use log::*;
@@ -21,9 +20,10 @@ use pact_models::PactSpecification;
 
 use crate::models::{Consumer, Interaction, Pact, ReadWritePact, RequestResponsePact};
 use crate::models::determine_spec_version;
-use crate::models::file_utils::with_read_lock;
+use crate::error::PactError;
+use crate::models::read_json_with_lock;
 use crate::models::http_utils;
-use crate::models::http_utils::HttpAuth;
+use crate::models::http_utils::{HttpAuth, TlsConfig};
 use crate::models::message;
 use crate::models::message::Message;
 use crate::models::PACT_RUST_VERSION;
@@ -71,9 +71,7 @@ impl Pact for MessagePact {
       s!("consumer"): self.consumer.to_json(),
       s!("provider"): self.provider.to_json(),
       s!("messages"):
-        Value::Array(self.messages.iter().map(
-            |i| serde_json::to_value(i).unwrap())
-            .collect()),
+        Value::Array(self.messages.iter().map(|m| m.to_json(&pact_spec)).collect()),
       s!("metadata"): json!(self.metadata_to_json(&pact_spec))
     })
   }
@@ -129,7 +127,7 @@ impl MessagePact {
     }
 
     /// Creates a `MessagePact` from a `Value` struct.
-    pub fn from_json(file: &str, pact_json: &Value) -> anyhow::Result<MessagePact> {
+    pub fn from_json(file: &str, pact_json: &Value) -> Result<MessagePact, PactError> {
         let metadata = parse_meta_data(pact_json);
         let spec_version = determine_spec_version(file, &metadata);
 
@@ -147,12 +145,21 @@ impl MessagePact {
                 let mut messages = Vec::with_capacity(msg_arr.len());
                 for (ix, msg) in msg_arr.iter().enumerate() {
                     messages.push(
-                        Message::from_json(ix, msg, &spec_version)?
+                        Message::from_json(ix, msg, &spec_version)
+                            .map_err(|err| PactError::ParseError {
+                                pact_source: file.to_string(),
+                                location: Some(format!("$.messages[{}]", ix)),
+                                message: err.to_string()
+                            })?
                     );
                 }
                 messages
             }
-            Some(_) => bail!("Expecting 'messages' field to be Array"),
+            Some(_) => return Err(PactError::ParseError {
+                pact_source: file.to_string(),
+                location: Some("$.messages".to_string()),
+                message: "Expecting 'messages' field to be Array".to_string()
+            }),
             None => vec![],
         };
 
@@ -197,8 +204,8 @@ impl MessagePact {
 
     /// Reads the pact file from a URL and parses the resulting JSON
     /// into a `MessagePact` struct
-    pub fn from_url(url: &String, auth: &Option<HttpAuth>) -> anyhow::Result<MessagePact> {
-        let (url, json) = http_utils::fetch_json_from_url(url, auth)?;
+    pub fn from_url(url: &str, auth: &Option<HttpAuth>) -> Result<MessagePact, PactError> {
+        let (url, json) = http_utils::fetch_json_from_url(url, auth, &TlsConfig::default())?;
         MessagePact::from_json(&url, &json)
     }
 
@@ -209,18 +216,19 @@ impl MessagePact {
         &self,
         path: &Path,
         pact_spec: PactSpecification,
-    ) -> anyhow::Result<()> {
-        fs::create_dir_all(path.parent().unwrap())?;
+    ) -> Result<(), PactError> {
+        let parent = path.parent().unwrap();
+        fs::create_dir_all(parent).map_err(|err| PactError::io_error(parent, err))?;
 
         debug!("Writing new pact file to {:?}", path);
-        let mut file = File::create(path)?;
+        let mut file = File::create(path).map_err(|err| PactError::io_error(path, err))?;
 
         file.write_all(
             format!("{}",
                 serde_json::to_string_pretty(
                     &self.to_json(pact_spec)).unwrap()
             ).as_bytes()
-        )?;
+        ).map_err(|err| PactError::io_error(path, err))?;
 
         Ok(())
     }
@@ -251,15 +259,12 @@ impl MessagePact {
 }
 
 impl ReadWritePact for MessagePact {
-  fn read_pact(path: &Path) -> anyhow::Result<MessagePact> {
-    with_read_lock(path, 3, &mut |f| {
-      let pact_json: Value = serde_json::from_reader(f)?;
-      MessagePact::from_json(&format!("{:?}", path), &pact_json)
-        .map_err(|e| anyhow!(e))
-    })
+  fn read_pact(path: &Path) -> Result<MessagePact, PactError> {
+    let pact_json = read_json_with_lock(path)?;
+    MessagePact::from_json(&format!("{:?}", path), &pact_json)
   }
 
-  fn merge(&self, pact: &dyn Pact) -> anyhow::Result<Box<dyn Pact>> {
+  fn merge(&self, pact: &dyn Pact) -> Result<Box<dyn Pact>, PactError> {
     if self.consumer.name == pact.consumer().name && self.provider.name == pact.provider().name {
       let messages: Vec<Result<Message, String>> = self.messages.iter()
         .merge_join_by(pact.interactions().iter(), |a, b| {
@@ -294,10 +299,10 @@ impl ReadWritePact for MessagePact {
           specification_version: self.specification_version.clone()
         }))
       } else {
-        Err(anyhow!("Unable to merge pacts: {}", errors.join(", ")))
+        Err(PactError::MergeError(errors.join(", ")))
       }
     } else {
-      Err(anyhow!("Unable to merge pacts, as they have different consumers or providers"))
+      Err(PactError::MergeError("they have different consumers or providers".to_string()))
     }
   }
 
@@ -464,4 +469,16 @@ mod tests {
         expect!(pact.specification_version).to(be_equal_to(PactSpecification::V3));
         expect!(pact.metadata.iter()).to(have_count(0));
     }
+
+    #[test]
+    fn write_pact_returns_an_error_for_messages_with_a_specification_before_v3() {
+        let pact = MessagePact {
+            messages: vec![ Message::default() ],
+            .. MessagePact::default()
+        };
+        let path = std::env::temp_dir().join("write_message_pact_v2_test.json");
+        let result = crate::models::write_pact(pact.boxed(), &path, PactSpecification::V2, true, crate::models::MergeStrategy::default());
+        expect!(matches!(result, Err(PactError::SpecVersionError { .. }))).to(be_true());
+        expect!(path.exists()).to(be_false());
+    }
 }
@@ -5,21 +5,21 @@ use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::default::Default;
 use std::fmt::{Debug, Display, Formatter};
 use std::fs::File;
 use std::hash::{Hash, Hasher};
+use std::io;
 use std::io::prelude::*;
-use std::io::SeekFrom;
 use std::path::Path;
 use std::str;
 use std::str::from_utf8;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::anyhow;
-use anyhow::Context as _;
 use base64::{decode, encode};
-use fs2::FileExt;
 use hex::FromHex;
 use itertools::{iproduct, Itertools};
 use itertools::EitherOrBoth::{Both, Left, Right};
@@ -33,15 +33,17 @@ use serde_json::{json, Value};
 use pact_models::{Consumer, OptionalBody, PactSpecification, Provider};
 use pact_models::content_types::*;
 
-use crate::models::file_utils::{with_read_lock, with_read_lock_for_open_file, with_write_lock};
+use crate::error::PactError;
+use crate::Mismatch;
+use crate::models::file_utils::{with_lock_file, with_read_lock, with_read_lock_for_open_file, write_atomically};
 use crate::models::generators::{Generator, GeneratorCategory};
-use crate::models::http_utils::HttpAuth;
+use crate::models::http_utils::{HttpAuth, TlsConfig};
 use crate::models::json_utils::json_to_string;
 use crate::models::matchingrules::MatchingRules;
 use crate::models::message::Message;
 use crate::models::message_pact::MessagePact;
 use crate::models::provider_states::ProviderState;
-use crate::models::v4::{AsynchronousMessage, interaction_from_json, SynchronousHttp, V4Interaction, V4Pact};
+use crate::models::v4::{AsynchronousMessage, interaction_from_json, SynchronousHttp, SynchronousMessages, V4Interaction, V4Pact};
 use crate::models::v4::http_parts::{HttpRequest, HttpResponse};
 
 pub mod json_utils;
@@ -49,7 +51,10 @@ pub mod xml_utils;
 #[macro_use] pub mod matchingrules;
 #[macro_use] pub mod generators;
 pub mod http_utils;
-mod expression_parser;
+pub mod evolve;
+pub mod redact;
+pub mod matching_rule_definition;
+pub(crate) mod expression_parser;
 mod file_utils;
 
 /// Version of the library
@@ -62,9 +67,11 @@ lazy_static! {
     static ref XMLREGEXP2: Regex = Regex::new(r#"^\s*<\w+\s*(:\w+=["”][^"”]+["”])?.*"#).unwrap();
 }
 
-fn detect_content_type_from_string(s: &String) -> Option<ContentType> {
+pub(crate) fn detect_content_type_from_string(s: &String) -> Option<ContentType> {
   log::debug!("Detecting content type from contents: '{}'", s);
-  if is_match(&XMLREGEXP, s.as_str()) {
+  if let Some(content_type) = xml_utils::detect_soap_content_type(s) {
+    Some(content_type)
+  } else if is_match(&XMLREGEXP, s.as_str()) {
     Some(XML.clone())
   } else if is_match(&HTMLREGEXP, s.to_uppercase().as_str()) {
     Some(HTML.clone())
@@ -79,6 +86,11 @@ fn detect_content_type_from_string(s: &String) -> Option<ContentType> {
 
 fn detect_content_type_from_bytes(s: &[u8]) -> Option<ContentType> {
   debug!("Detecting content type from byte contents");
+  // SOAP envelopes are detected from the namespace, which is normally past the XML declaration
+  let soap_header = String::from_utf8_lossy(if s.len() > 512 { &s[0..512] } else { s });
+  if let Some(content_type) = xml_utils::detect_soap_content_type(&soap_header) {
+    return Some(content_type);
+  }
   let header = if s.len() > 32 {
     &s[0..32]
   } else {
@@ -367,15 +379,25 @@ enum JsonParsable {
     KeyValue(HashMap<String, Value>)
 }
 
-fn body_from_json(request: &Value, fieldname: &str, headers: &Option<HashMap<String, Vec<String>>>) -> OptionalBody {
+/// Parses the status of a response, defaulting to 200 if there is no status
+pub(crate) fn status_from_json(response: &Value) -> anyhow::Result<u16> {
+  match response.get("status") {
+    Some(v) => v.as_u64()
+      .and_then(|status| u16::try_from(status).ok())
+      .ok_or_else(|| anyhow!("status must be a valid HTTP status code, got '{}'", v)),
+    None => Ok(200)
+  }
+}
+
+/// Adds the index and description of the interaction to an error from parsing the interaction
+pub(crate) fn interaction_error(index: usize, description: &str, err: anyhow::Error) -> anyhow::Error {
+  anyhow!("Failed to parse interaction {} ('{}') - {}", index, description, err)
+}
+
+pub(crate) fn body_from_json(request: &Value, fieldname: &str, headers: &Option<HashMap<String, Vec<String>>>) -> OptionalBody {
   let content_type = match headers {
     &Some(ref h) => match h.iter().find(|kv| kv.0.to_lowercase() == s!("content-type")) {
-      Some(kv) => {
-        match ContentType::parse(kv.1[0].as_str()) {
-          Ok(v) => Some(v),
-          Err(_) => None
-        }
-      },
+      Some(kv) => kv.1.first().and_then(|value| ContentType::parse(value.as_str()).ok()),
       None => None
     },
     &None => None
@@ -468,16 +490,50 @@ fn query_to_json(query: HashMap<String, Vec<String>>, spec_version: &PactSpecifi
   }
 }
 
+/// Standard HTTP methods (RFC 7231 and RFC 5789)
+const STANDARD_METHODS: [&str; 9] = ["GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH"];
+
+/// Normalises the HTTP method. The standard methods are converted to upper case, while extension
+/// methods (i.e. `PURGE` or `REPORT`) are case sensitive so are returned as is.
+pub fn normalise_method(method: &str) -> String {
+  let upper = method.to_uppercase();
+  if STANDARD_METHODS.contains(&upper.as_str()) {
+    upper
+  } else {
+    method.to_string()
+  }
+}
+
+/// Validates that the HTTP method is a valid token (RFC 7230). Any valid token can be used as a
+/// method, not just the standard ones.
+pub fn validate_method(method: &str) -> Result<(), String> {
+  if method.is_empty() {
+    Err("HTTP method can not be empty".to_string())
+  } else if let Some(ch) = method.chars().find(|ch| !(ch.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(*ch))) {
+    Err(format!("'{}' is not a valid HTTP method, it contains an invalid character '{}'", method, ch))
+  } else {
+    Ok(())
+  }
+}
+
+pub(crate) fn method_from_json(request_json: &Value) -> String {
+  let method = match request_json.get("method") {
+    Some(v) => match *v {
+      Value::String(ref s) => normalise_method(s),
+      _ => normalise_method(&v.to_string())
+    },
+    None => "GET".to_string()
+  };
+  if let Err(err) = validate_method(&method) {
+    warn!("{}", err);
+  }
+  method
+}
+
 impl Request {
     /// Builds a `Request` from a `Value` struct.
     pub fn from_json(request_json: &Value, spec_version: &PactSpecification) -> Request {
-        let method_val = match request_json.get("method") {
-            Some(v) => match *v {
-                Value::String(ref s) => s.to_uppercase(),
-                _ => v.to_string().to_uppercase()
-            },
-            None => "GET".to_string()
-        };
+        let method_val = method_from_json(request_json);
         let path_val = match request_json.get("path") {
             Some(v) => match *v {
                 Value::String(ref s) => s.clone(),
@@ -507,7 +563,7 @@ impl Request {
     /// Converts this `Request` to a `Value` struct.
     pub fn to_json(&self, spec_version: &PactSpecification) -> Value {
         let mut json = json!({
-            s!("method") : Value::String(self.method.to_uppercase()),
+            s!("method") : Value::String(normalise_method(&self.method)),
             s!("path") : Value::String(self.path.clone())
         });
         {
@@ -541,7 +597,7 @@ impl Request {
                 map.insert(s!("matchingRules"), matchingrules::matchers_to_json(
                 &self.matching_rules.clone(), spec_version));
             }
-            if self.generators.is_not_empty() {
+            if self.generators.is_not_empty() && spec_version >= &PactSpecification::V3 {
               map.insert(s!("generators"), generators::generators_to_json(
                 &self.generators.clone(), spec_version));
             }
@@ -614,20 +670,18 @@ pub struct Response {
 
 impl Response {
 
-    /// Build a `Response` from a `Value` struct.
-    pub fn from_json(response: &Value, _: &PactSpecification) -> Response {
-        let status_val = match response.get("status") {
-            Some(v) => v.as_u64().unwrap() as u16,
-            None => 200
-        };
+    /// Build a `Response` from a `Value` struct. Returns an error if the status is not a valid
+    /// HTTP status code.
+    pub fn from_json(response: &Value, _: &PactSpecification) -> anyhow::Result<Response> {
+        let status_val = status_from_json(response)?;
         let headers = headers_from_json(response);
-        Response {
+        Ok(Response {
             status: status_val,
             headers: headers.clone(),
             body: body_from_json(response, "body", &headers),
             matching_rules:  matchingrules::matchers_from_json(response, &Some(s!("responseMatchingRules"))),
             generators:  generators::generators_from_json(response)
-        }
+        })
     }
 
     /// Returns a default response: Status 200
@@ -672,7 +726,7 @@ impl Response {
           map.insert(s!("matchingRules"), matchingrules::matchers_to_json(
             &self.matching_rules.clone(), spec_version));
         }
-        if self.generators.is_not_empty() {
+        if self.generators.is_not_empty() && spec_version >= &PactSpecification::V3 {
           map.insert(s!("generators"), generators::generators_to_json(
             &self.generators.clone(), spec_version));
         }
@@ -828,6 +882,8 @@ pub trait Interaction: Debug {
   fn as_v4_http(&self) -> Option<SynchronousHttp>;
   /// Returns the interaction in V4 format
   fn as_v4_async_message(&self) -> Option<AsynchronousMessage>;
+  /// Returns the interaction in V4 format
+  fn as_v4_sync_message(&self) -> Option<SynchronousMessages>;
   /// Clones this interaction and wraps it in a Box
   fn boxed(&self) -> Box<dyn Interaction + Send>;
   /// Clones this interaction and wraps it in an Arc
@@ -836,6 +892,9 @@ pub trait Interaction: Debug {
   fn thread_safe(&self) -> Arc<Mutex<dyn Interaction + Send + Sync>>;
   /// Returns the matching rules associated with this interaction (if there are any)
   fn matching_rules(&self) -> Option<MatchingRules>;
+  /// Maximum time the provider should take to respond to this interaction (if there is one).
+  /// Only V4 interactions can have a timeout.
+  fn timeout(&self) -> Option<Duration>;
 }
 
 // impl Debug for dyn Interaction {
@@ -864,6 +923,8 @@ impl Display for dyn Interaction {
       std::fmt::Display::fmt(&mp, f)
     } else if let Some(mp) = self.as_v4_async_message() {
       std::fmt::Display::fmt(&mp, f)
+    } else if let Some(mp) = self.as_v4_sync_message() {
+      std::fmt::Display::fmt(&mp, f)
     } else {
       Err(fmt::Error)
     }
@@ -877,6 +938,8 @@ impl Clone for Box<dyn Interaction> {
         Box::new(http)
       } else if let Some(message) = self.as_v4_async_message() {
         Box::new(message)
+      } else if let Some(message) = self.as_v4_sync_message() {
+        Box::new(message)
       } else {
         panic!("Internal Error - Tried to clone an interaction that was not valid")
       }
@@ -971,6 +1034,10 @@ impl Interaction for RequestResponseInteraction {
     None
   }
 
+  fn as_v4_sync_message(&self) -> Option<SynchronousMessages> {
+    None
+  }
+
 
   fn boxed(&self) -> Box<dyn Interaction + Send> {
     Box::new(self.clone())
@@ -987,11 +1054,15 @@ impl Interaction for RequestResponseInteraction {
   fn matching_rules(&self) -> Option<MatchingRules> {
     None
   }
+
+  fn timeout(&self) -> Option<Duration> {
+    None
+  }
 }
 
 impl RequestResponseInteraction {
     /// Constructs an `Interaction` from the `Value` struct.
-    pub fn from_json(index: usize, pact_json: &Value, spec_version: &PactSpecification) -> RequestResponseInteraction {
+    pub fn from_json(index: usize, pact_json: &Value, spec_version: &PactSpecification) -> anyhow::Result<RequestResponseInteraction> {
         let id = pact_json.get("_id").map(|id| json_to_string(id));
         let description = match pact_json.get("description") {
             Some(v) => match *v {
@@ -1000,22 +1071,24 @@ impl RequestResponseInteraction {
             },
             None => format!("Interaction {}", index)
         };
-        let provider_states = provider_states::ProviderState::from_json(pact_json);
+        let provider_states = provider_states::ProviderState::from_json(pact_json)
+          .map_err(|err| interaction_error(index, &description, err))?;
         let request = match pact_json.get("request") {
             Some(v) => Request::from_json(v, spec_version),
             None => Request::default()
         };
         let response = match pact_json.get("response") {
-            Some(v) => Response::from_json(v, spec_version),
+            Some(v) => Response::from_json(v, spec_version)
+              .map_err(|err| interaction_error(index, &description, anyhow!("response.{}", err)))?,
             None => Response::default()
         };
-      RequestResponseInteraction {
+      Ok(RequestResponseInteraction {
           id,
           description,
           provider_states,
           request,
           response
-        }
+        })
     }
 
     /// Converts this interaction to a `Value` struct.
@@ -1068,6 +1141,62 @@ impl RequestResponseInteraction {
         }]
       }
     }
+
+    /// Combines this interaction with the other interaction, which must have the same description
+    /// and provider states, by taking the union of their headers and matching rules. Returns the
+    /// list of conflicts if the requests and responses are not compatible (they have different
+    /// methods, paths, query parameters, bodies or statuses, or a header has different values).
+    pub fn union_with(&self, other: &dyn Interaction) -> Result<RequestResponseInteraction, Vec<PactConflict>> {
+      if let Some(other) = other.as_request_response() {
+        let mut conflicts = self.request.differences_from(&other.request).iter()
+          .chain(self.response.differences_from(&other.response).iter())
+          .filter(|difference| !matches!(difference.0, DifferenceType::Headers | DifferenceType::MatchingRules))
+          .map(|difference| difference.1.clone())
+          .collect::<Vec<String>>();
+
+        let mut interaction = self.clone();
+        conflicts.extend(union_headers("Request", &mut interaction.request.headers, &other.request.headers));
+        conflicts.extend(union_headers("Response", &mut interaction.response.headers, &other.response.headers));
+        interaction.request.matching_rules.add_rules_from(&other.request.matching_rules);
+        interaction.response.matching_rules.add_rules_from(&other.response.matching_rules);
+
+        if conflicts.is_empty() {
+          Ok(interaction)
+        } else {
+          Err(conflicts.iter()
+            .map(|conflict| PactConflict { interaction: self.description.clone(), description: conflict.clone() })
+            .collect())
+        }
+      } else {
+        Err(vec![PactConflict {
+          interaction: self.description.clone(),
+          description: "You can not combine message and request/response interactions".to_string()
+        }])
+      }
+    }
+}
+
+/// Adds the headers to the existing headers, returning the names of any headers that have
+/// different values. Header names are compared case-insensitively.
+fn union_headers(
+  part: &str,
+  headers: &mut Option<HashMap<String, Vec<String>>>,
+  other_headers: &Option<HashMap<String, Vec<String>>>
+) -> Vec<String> {
+  let mut conflicts = vec![];
+  for (name, values) in other_headers.iter().flatten() {
+    let headers = headers.get_or_insert_with(HashMap::new);
+    match headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+      Some((key, existing_values)) => if existing_values != values {
+        conflicts.push(format!("{} header '{}' has different values {:?} != {:?}", part, key,
+          existing_values, values));
+      },
+      None => {
+        headers.insert(name.clone(), values.clone());
+      }
+    }
+  }
+  conflicts
 }
 
 impl Default for RequestResponseInteraction {
@@ -1123,7 +1252,10 @@ pub trait Pact: Debug + ReadWritePact {
 
 pub mod message;
 pub mod message_pact;
+pub mod kafka;
+pub mod graphql;
 pub mod v4;
+pub mod pact_files;
 
 /// Struct that represents a pact between the consumer and provider of a service.
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -1236,15 +1368,20 @@ fn parse_meta_data(pact_json: &Value) -> BTreeMap<String, BTreeMap<String, Strin
     }
 }
 
-fn parse_interactions(pact_json: &Value, spec_version: PactSpecification) -> Vec<RequestResponseInteraction> {
+fn parse_interactions(source: &str, pact_json: &Value, spec_version: PactSpecification) -> Result<Vec<RequestResponseInteraction>, PactError> {
     match pact_json.get("interactions") {
         Some(v) => match *v {
             Value::Array(ref array) => array.iter().enumerate().map(|(index, ijson)| {
               RequestResponseInteraction::from_json(index, ijson, &spec_version)
+                .map_err(|err| PactError::ParseError {
+                  pact_source: source.to_string(),
+                  location: Some(format!("$.interactions[{}]", index)),
+                  message: err.to_string()
+                })
             }).collect(),
-            _ => vec![]
+            _ => Ok(vec![])
         },
-        None => vec![]
+        None => Ok(vec![])
     }
 }
 
@@ -1301,7 +1438,7 @@ impl RequestResponsePact {
     }
 
     /// Creates a `Pact` from a `Value` struct.
-    pub fn from_json(file: &str, pact_json: &Value) -> RequestResponsePact {
+    pub fn from_json(file: &str, pact_json: &Value) -> Result<RequestResponsePact, PactError> {
         let metadata = parse_meta_data(pact_json);
         let spec_version = determine_spec_version(file, &metadata);
 
@@ -1313,13 +1450,13 @@ impl RequestResponsePact {
             Some(v) => Provider::from_json(v),
             None => Provider { name: s!("provider") }
         };
-        RequestResponsePact {
+        Ok(RequestResponsePact {
             consumer,
             provider,
-            interactions: parse_interactions(pact_json, spec_version.clone()),
+            interactions: parse_interactions(file, pact_json, spec_version.clone())?,
             metadata,
             specification_version: spec_version
-        }
+        })
     }
 
     /// Creates a BTreeMap of the metadata of this pact.
@@ -1343,8 +1480,8 @@ impl RequestResponsePact {
     }
 
     /// Reads the pact file from a URL and parses the resulting JSON into a `Pact` struct
-    pub fn from_url(url: &str, auth: &Option<HttpAuth>) -> anyhow::Result<RequestResponsePact> {
-      http_utils::fetch_json_from_url(&url.to_string(), auth).map(|(ref url, ref json)| RequestResponsePact::from_json(url, json))
+    pub fn from_url(url: &str, auth: &Option<HttpAuth>) -> Result<RequestResponsePact, PactError> {
+      http_utils::fetch_json_from_url(url, auth, &TlsConfig::default()).and_then(|(ref url, ref json)| RequestResponsePact::from_json(url, json))
     }
 
     /// Returns a default RequestResponsePact struct
@@ -1368,31 +1505,44 @@ impl RequestResponsePact {
 }
 
 impl ReadWritePact for RequestResponsePact {
-  fn read_pact(path: &Path) -> anyhow::Result<RequestResponsePact> {
-    with_read_lock(path, 3, &mut |f| {
-      let pact_json = serde_json::from_reader(f)
-        .context("Failed to parse Pact JSON")?;
-      Ok(RequestResponsePact::from_json(&format!("{:?}", path), &pact_json))
-    })
+  fn read_pact(path: &Path) -> Result<RequestResponsePact, PactError> {
+    let pact_json = read_json_with_lock(path)?;
+    RequestResponsePact::from_json(&format!("{:?}", path), &pact_json)
   }
 
-  fn merge(&self, pact: &dyn Pact) -> anyhow::Result<Box<dyn Pact>> {
+  fn merge(&self, pact: &dyn Pact) -> Result<Box<dyn Pact>, PactError> {
+    self.merge_with_strategy(pact, MergeStrategy::FailOnConflict)
+  }
+
+  fn merge_with_strategy(&self, pact: &dyn Pact, strategy: MergeStrategy) -> Result<Box<dyn Pact>, PactError> {
     if self.consumer.name == pact.consumer().name && self.provider.name == pact.provider().name {
       let conflicts = iproduct!(self.interactions.clone(), pact.interactions().clone())
-        .map(|i| i.0.conflicts_with(i.1))
+        .map(|i| match strategy {
+          MergeStrategy::FailOnConflict => i.0.conflicts_with(i.1),
+          MergeStrategy::Union => if i.0.description == i.1.description() &&
+            i.0.provider_states == i.1.provider_states() {
+            i.0.union_with(i.1).err().unwrap_or_default()
+          } else {
+            vec![]
+          }
+        })
         .filter(|conflicts| !conflicts.is_empty())
         .collect::<Vec<Vec<PactConflict>>>();
       let num_conflicts = conflicts.len();
       if num_conflicts > 0 {
         warn!("The following conflicting interactions where found:");
-        for interaction_conflicts in conflicts {
+        for interaction_conflicts in &conflicts {
           warn!(" Interaction '{}':", interaction_conflicts.first().unwrap().interaction);
           for conflict in interaction_conflicts {
             warn!("   {}", conflict.description);
           }
         }
-        Err(anyhow!("Unable to merge pacts, as there were {} conflict(s) between the interactions. Please clean out your pact directory before running the tests.",
-                    num_conflicts))
+        Err(PactError::MergeConflict {
+          conflicts: conflicts.iter()
+            .flatten()
+            .map(|conflict| format!("Interaction '{}': {}", conflict.interaction, conflict.description))
+            .collect()
+        })
       } else {
         let interactions: Vec<Result<RequestResponseInteraction, String>> = self.interactions.iter()
           .merge_join_by(pact.interactions().iter(), |a, b| {
@@ -1408,8 +1558,12 @@ impl ReadWritePact for RequestResponsePact {
             Left(i) => Ok(i.clone()),
             Right(i) => i.as_request_response()
               .ok_or(format!("Can't convert interaction of type {} to V3 Synchronous/HTTP", i.type_of())),
-            Both(_, i) => i.as_request_response()
-              .ok_or(format!("Can't convert interaction of type {} to V3 Synchronous/HTTP", i.type_of()))
+            Both(a, i) => match strategy {
+              MergeStrategy::FailOnConflict => i.as_request_response()
+                .ok_or(format!("Can't convert interaction of type {} to V3 Synchronous/HTTP", i.type_of())),
+              MergeStrategy::Union => a.union_with(*i)
+                .map_err(|_| format!("Can't combine interaction '{}' with interaction of type {}", a.description, i.type_of()))
+            }
           })
           .collect();
 
@@ -1428,11 +1582,11 @@ impl ReadWritePact for RequestResponsePact {
             specification_version: self.specification_version.clone()
           }))
         } else {
-          Err(anyhow!("Unable to merge pacts: {}", errors.join(", ")))
+          Err(PactError::MergeError(errors.join(", ")))
         }
       }
     } else {
-      Err(anyhow!("Unable to merge pacts, as they have different consumers or providers"))
+      Err(PactError::MergeError("they have different consumers or providers".to_string()))
     }
   }
 
@@ -1441,78 +1595,54 @@ impl ReadWritePact for RequestResponsePact {
   }
 }
 
+/// Decodes a percent-encoded (RFC 3986) query string component. As query strings are also used for
+/// form data, `+` is decoded as a space, the same as `%20`. Returns an error if a `%` is not
+/// followed by two hex digits, or the decoded bytes are not valid UTF-8.
 fn decode_query(query: &str) -> Result<String, String> {
-  let mut chars = query.chars();
-  let mut ch = chars.next();
-  let mut buffer = vec![];
-
-  while ch.is_some() {
-    let c = ch.unwrap();
-    trace!("ch = '{:?}'", ch);
-    if c == '%' {
-      let c1 = chars.next();
-      let c2 = chars.next();
-      match (c1, c2) {
-        (Some(v1), Some(v2)) => {
-          let mut s = String::new();
-          s.push(v1);
-          s.push(v2);
-          let decoded: Result<Vec<u8>, _> = FromHex::from_hex(s.into_bytes());
-          match decoded {
-            Ok(n) => {
-              trace!("decoded = '{:?}'", n);
-              buffer.extend_from_slice(&n);
-            },
-            Err(err) => {
-              error!("Failed to decode '%{}{}' to as HEX - {}", v1, v2, err);
-              buffer.push('%' as u8);
-              buffer.push(v1 as u8);
-              buffer.push(v2 as u8);
-            }
+  let bytes = query.as_bytes();
+  let mut buffer = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+
+  while index < bytes.len() {
+    match bytes[index] {
+      b'%' => {
+        let encoded = bytes.get(index + 1..index + 3)
+          .and_then(|hex| <[u8; 1]>::from_hex(hex).ok());
+        match encoded {
+          Some(byte) => {
+            buffer.push(byte[0]);
+            index += 3;
+          },
+          None => {
+            let end = (index + 3).min(bytes.len());
+            return Err(format!("Malformed percent-encoding '{}' at index {} of '{}'",
+              String::from_utf8_lossy(&bytes[index..end]), index, query));
           }
-        },
-        (Some(v1), None) => {
-          buffer.push('%' as u8);
-          buffer.push(v1 as u8);
-        },
-        _ => buffer.push('%' as u8)
+        }
+      },
+      b'+' => {
+        buffer.push(b' ');
+        index += 1;
+      },
+      byte => {
+        buffer.push(byte);
+        index += 1;
       }
-    } else if c == '+' {
-      buffer.push(' ' as u8);
-    } else {
-      buffer.push(c as u8);
     }
-
-    ch = chars.next();
   }
 
-  match str::from_utf8(&buffer) {
-    Ok(s) => Ok(s.to_owned()),
-    Err(err) => {
-      error!("Failed to decode '{}' to UTF-8 - {}", query, err);
-      Err(format!("Failed to decode '{}' to UTF-8 - {}", query, err))
-    }
-  }
+  String::from_utf8(buffer)
+    .map_err(|err| format!("Failed to decode '{}' to UTF-8 - {}", query, err))
 }
 
+/// Percent-encodes (RFC 3986) a query string component. All the characters apart from the
+/// unreserved ones (letters, digits, `-`, `.`, `_` and `~`) are encoded as the hex values of their UTF-8 bytes, and spaces are encoded as `+`.
 fn encode_query(query: &str) -> String {
-  query.chars().map(|ch| {
-    match ch {
-      ' ' => s!("+"),
-      '-' => ch.to_string(),
-      'a'..='z' => ch.to_string(),
-      'A'..='Z' => ch.to_string(),
-      '0'..='9' => ch.to_string(),
-      _ => ch.escape_unicode()
-          .filter(|u| u.is_digit(16))
-          .batching(|it| {
-              match it.next() {
-                  None => None,
-                  Some(x) => Some((x, it.next().unwrap()))
-              }
-          })
-          .map(|u| format!("%{}{}", u.0, u.1))
-          .collect()
+  query.bytes().map(|byte| {
+    match byte {
+      b' ' => "+".to_string(),
+      b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+      _ => format!("%{:02X}", byte)
     }
   }).collect()
 }
@@ -1535,9 +1665,15 @@ pub fn parse_query_string(query: &str) -> Option<HashMap<String, Vec<String>>> {
       trace!("name_value = '{:?}'", name_value);
       if !name_value.is_empty() {
         let name = decode_query(name_value[0])
-          .unwrap_or_else(|_| name_value[0].to_owned());
+          .unwrap_or_else(|err| {
+            warn!("{}", err);
+            name_value[0].to_owned()
+          });
         let value = if name_value.len() > 1 {
-          decode_query(name_value[1]).unwrap_or_else(|_| name_value[1].to_owned())
+          decode_query(name_value[1]).unwrap_or_else(|err| {
+            warn!("{}", err);
+            name_value[1].to_owned()
+          })
         } else {
           String::default()
         };
@@ -1551,12 +1687,34 @@ pub fn parse_query_string(query: &str) -> Option<HashMap<String, Vec<String>>> {
   }
 }
 
+/// Returns a mismatch for each parameter of the query string that is not correctly percent-encoded
+/// (i.e. a `%` not followed by two hex digits, or an encoding of bytes that are not valid UTF-8).
+/// `parse_query_string` passes the values of these parameters through as they were received.
+pub fn query_string_encoding_mismatches(query: &str) -> Vec<Mismatch> {
+  query.split('&')
+    .filter(|kv| !kv.is_empty())
+    .flat_map(|kv| {
+      let (name, value) = kv.split_once('=').unwrap_or((kv, ""));
+      let parameter = decode_query(name).unwrap_or_else(|_| name.to_string());
+      vec![decode_query(name), decode_query(value)].into_iter()
+        .filter_map(|result| result.err())
+        .map(|err| Mismatch::QueryMismatch {
+          parameter: parameter.clone(),
+          expected: String::default(),
+          actual: kv.to_string(),
+          mismatch: format!("Query parameter '{}' is not correctly encoded - {}", parameter, err)
+        })
+        .collect::<Vec<Mismatch>>()
+    })
+    .collect()
+}
+
 /// Converts the JSON struct into an HTTP Interaction
 pub fn http_interaction_from_json(source: &str, json: &Value, spec: &PactSpecification) -> anyhow::Result<Box<dyn Interaction + Send>> {
   match spec {
     PactSpecification::V4 => interaction_from_json(source, 0, json)
       .map(|i| i.boxed()),
-    _ => Ok(Box::new(RequestResponseInteraction::from_json(0, json, spec)))
+    _ => Ok(Box::new(RequestResponseInteraction::from_json(0, json, spec)?))
   }
 }
 
@@ -1569,38 +1727,59 @@ pub fn message_interaction_from_json(source: &str, json: &Value, spec: &PactSpec
   }
 }
 
+/// Converts an error from one of the file locking functions, keeping any IO error
+fn file_error(path: &Path, err: anyhow::Error) -> PactError {
+  match err.downcast::<io::Error>() {
+    Ok(err) => PactError::io_error(path, err),
+    Err(err) => PactError::Other(err)
+  }
+}
+
+fn parse_pact_json(path: &Path, buf: &str) -> Result<Value, PactError> {
+  serde_json::from_str(buf)
+    .map_err(|err| {
+      error!("Failed to parse Pact JSON from {:?}: {}", path, err);
+      debug!("File contents = '{}'", buf);
+      PactError::from_json_error(&path.to_string_lossy(), &err)
+    })
+}
+
+/// Reads the JSON from the file while holding a shared lock on it
+pub(crate) fn read_json_with_lock(path: &Path) -> Result<Value, PactError> {
+  let buf = with_read_lock(path, 3, &mut |f| {
+    let mut buf = String::new();
+    f.read_to_string(&mut buf)?;
+    Ok(buf)
+  }).map_err(|err| file_error(path, err))?;
+  parse_pact_json(path, &buf)
+}
+
 /// Reads the pact file and parses the resulting JSON into a `Pact` struct
-pub fn read_pact(file: &Path) -> anyhow::Result<Box<dyn Pact>> {
-  let mut f = File::open(file)?;
+pub fn read_pact(file: &Path) -> Result<Box<dyn Pact>, PactError> {
+  let mut f = File::open(file).map_err(|err| PactError::io_error(file, err))?;
   read_pact_from_file(&mut f, file)
 }
 
 /// Reads the pact from the file and parses the resulting JSON into a `Pact` struct
-pub fn read_pact_from_file(file: &mut File, path: &Path) -> anyhow::Result<Box<dyn Pact>> {
+pub fn read_pact_from_file(file: &mut File, path: &Path) -> Result<Box<dyn Pact>, PactError> {
   let buf = with_read_lock_for_open_file(path, file, 3, &mut |f| {
     let mut buf = String::new();
     f.read_to_string(&mut buf)?;
     Ok(buf)
-  })?;
-  let pact_json = serde_json::from_str(&buf)
-    .context("Failed to parse Pact JSON")
-    .map_err(|err| {
-      error!("read_pact_from_file: {}", err);
-      debug!("read_pact_from_file: file contents = '{}'", buf);
-      err
-    })?;
+  }).map_err(|err| file_error(path, err))?;
+  let pact_json = parse_pact_json(path, &buf)?;
   load_pact_from_json(&*path.to_string_lossy(), &pact_json)
-    .map_err(|e| anyhow!(e))
 }
 
-/// Reads the pact file from a URL and parses the resulting JSON into a `Pact` struct
-pub fn load_pact_from_url(url: &str, auth: &Option<HttpAuth>) -> anyhow::Result<Box<dyn Pact>> {
-  let (url, pact_json) = http_utils::fetch_json_from_url(&url.to_string(), auth)?;
+/// Reads the pact file from a URL (using the TLS configuration for HTTPS URLs) and parses the
+/// resulting JSON into a `Pact` struct
+pub fn load_pact_from_url(url: &str, auth: &Option<HttpAuth>, tls: &TlsConfig) -> Result<Box<dyn Pact>, PactError> {
+  let (url, pact_json) = http_utils::fetch_json_from_url(url, auth, tls)?;
   load_pact_from_json(&url, &pact_json)
 }
 
 /// Loads a Pact model from a JSON Value
-pub fn load_pact_from_json(source: &str, json: &Value) -> anyhow::Result<Box<dyn Pact>> {
+pub fn load_pact_from_json(source: &str, json: &Value) -> Result<Box<dyn Pact>, PactError> {
   match json {
     Value::Object(map) => if map.contains_key("messages") {
       let pact = MessagePact::from_json(source, json)?;
@@ -1610,47 +1789,95 @@ pub fn load_pact_from_json(source: &str, json: &Value) -> anyhow::Result<Box<dyn
       let spec_version = determine_spec_version(source, &metadata);
       match spec_version {
         PactSpecification::V4 => v4::from_json(&source, json),
-        _ => Ok(Box::new(RequestResponsePact::from_json(source, json)))
+        _ => Ok(Box::new(RequestResponsePact::from_json(source, json)?))
       }
     },
-    _ => Err(anyhow!("Failed to parse Pact JSON from source '{}' - it is not a valid pact file", source))
+    _ => Err(PactError::parse_error(source, "it is not a valid pact file"))
   }
 }
 
 /// Trait for objects that can represent Pacts and can be read and written
 pub trait ReadWritePact {
   /// Reads the pact file and parses the resulting JSON into a `Pact` struct
-  fn read_pact(path: &Path) -> anyhow::Result<Self> where Self: std::marker::Sized;
+  fn read_pact(path: &Path) -> Result<Self, PactError> where Self: std::marker::Sized;
 
   /// Merges this pact with the other pact, and returns a new Pact with the interactions sorted.
   /// Returns an error if there is a merge conflict, which will occur if the other pact is a different
   /// type, or if a V3 Pact then if any interaction has the
   /// same description and provider state and the requests and responses are different.
-  fn merge(&self, other: &dyn Pact) -> anyhow::Result<Box<dyn Pact>>;
+  fn merge(&self, other: &dyn Pact) -> Result<Box<dyn Pact>, PactError>;
+
+  /// Merges this pact with the other pact using the merge strategy. Only request/response pacts
+  /// can combine interactions, so other pacts return an error for the union strategy.
+  fn merge_with_strategy(&self, other: &dyn Pact, strategy: MergeStrategy) -> Result<Box<dyn Pact>, PactError> {
+    match strategy {
+      MergeStrategy::FailOnConflict => self.merge(other),
+      MergeStrategy::Union => Err(PactError::MergeError(
+        "the union merge strategy is only supported for V3 request/response pacts".to_string()))
+    }
+  }
 
   /// Determines the default file name for the pact. This is based on the consumer and
   /// provider names.
   fn default_file_name(&self) -> String;
 }
 
+/// Strategy used to merge a pact with an existing pact file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+  /// Identical interactions are deduplicated, but the merge fails if interactions with the same
+  /// description and provider states have different requests or responses
+  #[default]
+  FailOnConflict,
+  /// Identical interactions are deduplicated, and interactions with the same description and
+  /// provider states are combined by taking the union of their headers and matching rules. The
+  /// merge only fails if the requests and responses are not compatible. Only supported for V3
+  /// request/response pacts.
+  Union
+}
+
 lazy_static!{
   static ref WRITE_LOCK: Mutex<()> = Mutex::new(());
 }
 
 /// Writes the pact out to the provided path. If there is an existing pact at the path, the two
-/// pacts will be merged together unless overwrite is true. Returns an error if the file can not
-/// be written or the pacts can not be merged.
+/// pacts will be merged together using the merge strategy unless overwrite is true. Returns an
+/// error if the file can not be written or the pacts can not be merged, or if the pact has messages
+/// and the specification version is before V3.
 pub fn write_pact(
   pact: Box<dyn Pact>,
   path: &Path,
   pact_spec: PactSpecification,
-  overwrite: bool
-) -> anyhow::Result<()> {
-  fs::create_dir_all(path.parent().unwrap())?;
+  overwrite: bool,
+  merge_strategy: MergeStrategy
+) -> Result<(), PactError> {
+  if pact_spec != PactSpecification::Unknown && pact_spec < PactSpecification::V3 &&
+    pact.interactions().iter().any(|i| i.is_message()) {
+    return Err(PactError::SpecVersionError {
+      version: pact_spec.version_str(),
+      message: "message pacts require V3 or later".to_string()
+    });
+  }
+
+  let parent = path.parent().unwrap();
+  fs::create_dir_all(parent).map_err(|err| PactError::io_error(parent, err))?;
   let _lock = WRITE_LOCK.lock().unwrap();
+  // The lock is held on a separate lock file for the whole read, merge and write, so processes
+  // writing to the same pact file are serialised even though the file is replaced by a rename
+  with_lock_file(path, 3, &mut || Ok(write_or_merge_pact(pact.as_ref(), path, pact_spec.clone(), overwrite, merge_strategy)))
+    .map_err(|err| file_error(path, err))?
+}
+
+fn write_or_merge_pact(
+  pact: &dyn Pact,
+  path: &Path,
+  pact_spec: PactSpecification,
+  overwrite: bool,
+  merge_strategy: MergeStrategy
+) -> Result<(), PactError> {
   if !overwrite && path.exists() {
     debug!("Merging pact with file {:?}", path);
-    let mut f = fs::OpenOptions::new().read(true).write(true).open(&path)?;
+    let mut f = File::open(path).map_err(|err| PactError::io_error(path, err))?;
     let existing_pact = read_pact_from_file(&mut f, path)?;
 
     if existing_pact.specification_version() < pact.specification_version() {
@@ -1658,23 +1885,15 @@ pub fn write_pact(
             existing_pact.specification_version());
     }
 
-    let merged_pact = pact.merge(existing_pact.borrow())?;
-    let pact_json = serde_json::to_string_pretty(&merged_pact.to_json(pact_spec))?;
-
-    with_write_lock(path, &mut f, 3, &mut |f| {
-      f.set_len(0)?;
-      f.seek(SeekFrom::Start(0))?;
-      f.write_all(pact_json.as_bytes())?;
-      Ok(())
-    })
+    let merged_pact = pact.merge_with_strategy(existing_pact.borrow(), merge_strategy)?;
+    let pact_json = serde_json::to_string_pretty(&merged_pact.to_json(pact_spec))
+      .map_err(|err| PactError::Other(err.into()))?;
+    write_atomically(path, pact_json.as_bytes()).map_err(|err| PactError::io_error(path, err))
   } else {
     debug!("Writing new pact file to {:?}", path);
-    let result = serde_json::to_string_pretty(&pact.to_json(pact_spec))?;
-    let mut file = File::create(path)?;
-    file.lock_exclusive()?;
-    let result = file.write_all(result.as_bytes());
-    file.unlock()?;
-    result.map_err(|e| e.into())
+    let result = serde_json::to_string_pretty(&pact.to_json(pact_spec))
+      .map_err(|err| PactError::Other(err.into()))?;
+    write_atomically(path, result.as_bytes()).map_err(|err| PactError::io_error(path, err))
   }
 }
 
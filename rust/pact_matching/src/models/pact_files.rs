@@ -0,0 +1,337 @@
+//! Functions for writing pact files to an output directory, with a layout for organising the files
+//! and an index file listing the written pacts, and for loading all the pact files in a directory
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use fs2::FileExt;
+use log::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use pact_models::PactSpecification;
+
+use crate::error::PactError;
+use crate::models::{MergeStrategy, Pact, read_pact, write_pact};
+
+/// Name of the index file written to the output directory
+pub const PACT_INDEX_FILE: &str = "pact-index.json";
+
+/// Layout of the pact files written to an output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PactFileLayout {
+  /// All the pact files in the output directory, named `{consumer}-{provider}.json`
+  #[default]
+  Flat,
+  /// A sub-directory for each provider, with the pact files named `{provider}/{consumer}.json`.
+  /// This is the same layout that the Pact Broker uses.
+  ByProvider
+}
+
+impl PactFileLayout {
+  /// Returns the path of the file for the pact in the output directory
+  pub fn pact_file_path(&self, dir: &Path, pact: &dyn Pact) -> PathBuf {
+    match self {
+      PactFileLayout::Flat => dir.join(format!("{}-{}.json", pact.consumer().name, pact.provider().name)),
+      PactFileLayout::ByProvider => dir.join(pact.provider().name).join(format!("{}.json", pact.consumer().name))
+    }
+  }
+}
+
+impl FromStr for PactFileLayout {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "flat" => Ok(PactFileLayout::Flat),
+      "provider" | "by-provider" => Ok(PactFileLayout::ByProvider),
+      _ => Err(format!("'{}' is not a valid pact file layout, it must be either 'flat' or 'provider'", s))
+    }
+  }
+}
+
+impl Display for PactFileLayout {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      PactFileLayout::Flat => write!(f, "flat"),
+      PactFileLayout::ByProvider => write!(f, "provider")
+    }
+  }
+}
+
+/// Entry in the index file for a written pact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PactIndexEntry {
+  /// Name of the consumer
+  pub consumer: String,
+  /// Name of the provider
+  pub provider: String,
+  /// Path to the pact file, relative to the output directory and with `/` separators
+  pub path: String,
+  /// SHA-256 hash of the contents of the pact file, as a hex string
+  pub sha256: String
+}
+
+/// Index of the pact files written to an output directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PactIndex {
+  /// Pacts in the output directory, sorted by path
+  pub pacts: Vec<PactIndexEntry>
+}
+
+impl PactIndex {
+  /// Reads the index file from the output directory. Returns an empty index if there is no index
+  /// file.
+  pub fn read(dir: &Path) -> Result<PactIndex, PactError> {
+    let path = dir.join(PACT_INDEX_FILE);
+    if path.exists() {
+      let contents = fs::read_to_string(&path).map_err(|err| PactError::io_error(&path, err))?;
+      serde_json::from_str(&contents)
+        .map_err(|err| PactError::from_json_error(&path.to_string_lossy(), &err))
+    } else {
+      Ok(PactIndex::default())
+    }
+  }
+
+  /// Adds the entry to the index, replacing any existing entry with the same path
+  pub fn add(&mut self, entry: PactIndexEntry) {
+    self.pacts.retain(|existing| existing.path != entry.path);
+    self.pacts.push(entry);
+    self.pacts.sort_by(|a, b| a.path.cmp(&b.path));
+  }
+}
+
+/// Returns the SHA-256 hash of the bytes as a hex string
+fn sha256_hex(bytes: &[u8]) -> String {
+  hex::encode(Sha256::digest(bytes))
+}
+
+/// Adds the written pact file to the index file in the output directory. The index file is locked
+/// while it is updated, so tests writing pacts in parallel do not lose each others entries.
+fn update_index(dir: &Path, pact: &dyn Pact, pact_file: &Path) -> Result<(), PactError> {
+  let contents = fs::read(pact_file).map_err(|err| PactError::io_error(pact_file, err))?;
+  let relative_path = pact_file.strip_prefix(dir).unwrap_or(pact_file);
+  let entry = PactIndexEntry {
+    consumer: pact.consumer().name,
+    provider: pact.provider().name,
+    path: relative_path.components()
+      .map(|component| component.as_os_str().to_string_lossy().to_string())
+      .collect::<Vec<String>>()
+      .join("/"),
+    sha256: sha256_hex(&contents)
+  };
+
+  let index_path = dir.join(PACT_INDEX_FILE);
+  let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false)
+    .open(&index_path)
+    .map_err(|err| PactError::io_error(&index_path, err))?;
+  file.lock_exclusive().map_err(|err| PactError::io_error(&index_path, err))?;
+  let result = (|| {
+    let mut json = String::new();
+    file.read_to_string(&mut json).map_err(|err| PactError::io_error(&index_path, err))?;
+    let mut index = if json.trim().is_empty() {
+      PactIndex::default()
+    } else {
+      serde_json::from_str::<PactIndex>(&json)
+        .map_err(|err| PactError::from_json_error(&index_path.to_string_lossy(), &err))?
+    };
+    index.add(entry);
+    let json = serde_json::to_string_pretty(&index).map_err(|err| PactError::Other(err.into()))?;
+    file.set_len(0)
+      .and_then(|_| file.seek(SeekFrom::Start(0)))
+      .and_then(|_| file.write_all(json.as_bytes()))
+      .map_err(|err| PactError::io_error(&index_path, err))
+  })();
+  if let Err(err) = file.unlock() {
+    warn!("Failed to release the lock on '{}' - {}", index_path.display(), err);
+  }
+  result
+}
+
+/// Writes the pact to the output directory, using the layout to determine the path of the pact
+/// file. If there is an existing pact file, the two pacts will be merged together unless overwrite
+/// is true. The index file in the output directory is updated with the path and hash of the pact
+/// file. Returns the path to the pact file.
+pub fn write_pact_to_directory(
+  pact: Box<dyn Pact>,
+  dir: &Path,
+  pact_spec: PactSpecification,
+  overwrite: bool,
+  layout: PactFileLayout
+) -> Result<PathBuf, PactError> {
+  let path = layout.pact_file_path(dir, pact.as_ref());
+  info!("Writing pact out to '{}'", path.display());
+  write_pact(pact.boxed(), &path, pact_spec, overwrite, MergeStrategy::default())?;
+  update_index(dir, pact.as_ref(), &path)?;
+  Ok(path)
+}
+
+/// Pacts loaded from the pact files in a directory
+#[derive(Debug, Default)]
+pub struct DirectoryPacts {
+  /// Pacts that were loaded, grouped by provider name. The pacts for each provider are in file
+  /// path order.
+  pub pacts: BTreeMap<String, Vec<Box<dyn Pact>>>,
+  /// Files that could not be loaded as pacts, with the reason
+  pub invalid_files: Vec<(PathBuf, PactError)>
+}
+
+impl DirectoryPacts {
+  /// All the loaded pacts, ordered by provider name and then file path
+  pub fn all_pacts(self) -> Vec<Box<dyn Pact>> {
+    self.pacts.into_values().flatten().collect()
+  }
+}
+
+/// Returns the JSON files in the directory (and its sub-directories if recursive), sorted by path.
+/// The index file written by `write_pact_to_directory` is not included.
+fn json_files_in_dir(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, PactError> {
+  let mut files = vec![];
+  for entry in fs::read_dir(dir).map_err(|err| PactError::io_error(dir, err))? {
+    let path = entry.map_err(|err| PactError::io_error(dir, err))?.path();
+    if path.is_dir() {
+      if recursive {
+        files.extend(json_files_in_dir(&path, recursive)?);
+      }
+    } else if path.extension().map(|ext| ext == "json").unwrap_or(false) &&
+      path.file_name().map(|name| name != PACT_INDEX_FILE).unwrap_or(false) {
+      files.push(path);
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+/// Loads all the pact files (files with a `.json` extension) in the directory, and in all its
+/// sub-directories if recursive is true. Files that can not be loaded as a pact are skipped with a
+/// warning, and returned in `invalid_files`. Returns an error if the directory can not be read.
+pub fn load_pacts_from_dir(dir: &Path, recursive: bool) -> Result<DirectoryPacts, PactError> {
+  debug!("Scanning '{}' for pact files", dir.display());
+  let mut result = DirectoryPacts::default();
+  for file in json_files_in_dir(dir, recursive)? {
+    match read_pact(&file) {
+      Ok(pact) => {
+        debug!("Loaded pact file '{}'", file.display());
+        result.pacts.entry(pact.provider().name).or_default().push(pact);
+      },
+      Err(err) => {
+        warn!("Skipping '{}' as it could not be loaded as a pact - {}", file.display(), err);
+        result.invalid_files.push((file, err));
+      }
+    }
+  }
+  Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use crate::models::{read_pact, RequestResponsePact};
+  use pact_models::{Consumer, Provider};
+
+  use super::*;
+
+  fn pact(consumer: &str, provider: &str) -> Box<dyn Pact> {
+    Box::new(RequestResponsePact {
+      consumer: Consumer { name: consumer.to_string() },
+      provider: Provider { name: provider.to_string() },
+      .. RequestResponsePact::default()
+    })
+  }
+
+  #[test]
+  fn pact_file_path_for_each_layout() {
+    let pact = pact("web", "users");
+    let dir = Path::new("pacts");
+    expect!(PactFileLayout::Flat.pact_file_path(dir, pact.as_ref()))
+      .to(be_equal_to(PathBuf::from("pacts/web-users.json")));
+    expect!(PactFileLayout::ByProvider.pact_file_path(dir, pact.as_ref()))
+      .to(be_equal_to(PathBuf::from("pacts/users/web.json")));
+  }
+
+  #[test]
+  fn parse_pact_file_layout() {
+    expect!("flat".parse::<PactFileLayout>()).to(be_ok().value(PactFileLayout::Flat));
+    expect!("Provider".parse::<PactFileLayout>()).to(be_ok().value(PactFileLayout::ByProvider));
+    expect!("by-provider".parse::<PactFileLayout>()).to(be_ok().value(PactFileLayout::ByProvider));
+    expect!("consumer".parse::<PactFileLayout>()).to(be_err());
+  }
+
+  #[test]
+  fn write_pact_to_directory_writes_the_pact_files_and_the_index() {
+    let dir = std::env::temp_dir().join("pact_matching_write_pact_to_directory");
+    fs::remove_dir_all(&dir).unwrap_or_default();
+
+    let users = write_pact_to_directory(pact("web", "users"), &dir, PactSpecification::V3, true,
+      PactFileLayout::ByProvider).unwrap();
+    let orders = write_pact_to_directory(pact("web", "orders"), &dir, PactSpecification::V3, true,
+      PactFileLayout::ByProvider).unwrap();
+    let mobile = write_pact_to_directory(pact("mobile", "users"), &dir, PactSpecification::V3, true,
+      PactFileLayout::ByProvider).unwrap();
+    // Writing the same pact again replaces the index entry
+    write_pact_to_directory(pact("web", "users"), &dir, PactSpecification::V3, true,
+      PactFileLayout::ByProvider).unwrap();
+
+    let index = PactIndex::read(&dir).unwrap();
+    let pact = read_pact(&users).unwrap();
+    let users_hash = sha256_hex(&fs::read(&users).unwrap());
+    let paths_exist = orders.exists() && mobile.exists();
+    fs::remove_dir_all(&dir).unwrap_or_default();
+
+    expect!(users).to(be_equal_to(dir.join("users").join("web.json")));
+    expect!(paths_exist).to(be_true());
+    expect!(pact.consumer().name).to(be_equal_to("web"));
+    expect!(index.pacts.iter().map(|entry| entry.path.clone()).collect::<Vec<String>>())
+      .to(be_equal_to(vec!["orders/web.json".to_string(), "users/mobile.json".to_string(),
+        "users/web.json".to_string()]));
+    expect!(index.pacts[2].clone()).to(be_equal_to(PactIndexEntry {
+      consumer: "web".to_string(),
+      provider: "users".to_string(),
+      path: "users/web.json".to_string(),
+      sha256: users_hash
+    }));
+  }
+
+  #[test]
+  fn load_pacts_from_dir_groups_the_pacts_by_provider_and_skips_invalid_files() {
+    let dir = std::env::temp_dir().join("pact_matching_load_pacts_from_dir");
+    fs::remove_dir_all(&dir).unwrap_or_default();
+    write_pact_to_directory(pact("web", "users"), &dir, PactSpecification::V3, true,
+      PactFileLayout::Flat).unwrap();
+    write_pact_to_directory(pact("mobile", "users"), &dir, PactSpecification::V3, true,
+      PactFileLayout::Flat).unwrap();
+    write_pact_to_directory(pact("web", "orders"), &dir.join("orders"), PactSpecification::V3, true,
+      PactFileLayout::Flat).unwrap();
+    fs::write(dir.join("invalid.json"), "{ not json").unwrap();
+    fs::write(dir.join("README.md"), "not a pact").unwrap();
+
+    let pacts = load_pacts_from_dir(&dir, false).unwrap();
+    let recursive_pacts = load_pacts_from_dir(&dir, true).unwrap();
+    fs::remove_dir_all(&dir).unwrap_or_default();
+
+    let names = |pacts: &DirectoryPacts| pacts.pacts.iter()
+      .map(|(provider, pacts)| (provider.clone(), pacts.iter().map(|pact| pact.consumer().name).collect()))
+      .collect::<Vec<(String, Vec<String>)>>();
+    expect!(names(&pacts)).to(be_equal_to(vec![
+      ("users".to_string(), vec!["mobile".to_string(), "web".to_string()])
+    ]));
+    expect!(pacts.invalid_files.iter().map(|(path, _)| path.clone()).collect::<Vec<PathBuf>>())
+      .to(be_equal_to(vec![dir.join("invalid.json")]));
+    expect!(names(&recursive_pacts)).to(be_equal_to(vec![
+      ("orders".to_string(), vec!["web".to_string()]),
+      ("users".to_string(), vec!["mobile".to_string(), "web".to_string()])
+    ]));
+    expect!(recursive_pacts.all_pacts().len()).to(be_equal_to(3));
+  }
+
+  #[test]
+  fn read_index_returns_an_empty_index_if_there_is_no_index_file() {
+    let dir = std::env::temp_dir().join("pact_matching_read_index_with_no_file");
+    expect!(PactIndex::read(&dir)).to(be_ok().value(PactIndex::default()));
+  }
+}
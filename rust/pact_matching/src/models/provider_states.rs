@@ -2,6 +2,7 @@
 //! See https://docs.pact.io/getting_started/provider_states for more info on provider states.
 
 use std::collections::HashMap;
+use anyhow::anyhow;
 use serde::{Serialize, Deserialize};
 use serde_json::*;
 use maplit::*;
@@ -27,8 +28,12 @@ impl ProviderState {
         }
     }
 
-    /// Constructs a provider state from the `Json` struct
-    pub fn from_json_v3(pact_json: &Value) -> ProviderState {
+    /// Constructs a provider state from the `Json` struct. Returns an error if the provider state
+    /// is not a JSON object, or the parameters are not a map.
+    pub fn from_json_v3(pact_json: &Value) -> anyhow::Result<ProviderState> {
+        if !pact_json.is_object() {
+            return Err(anyhow!("Provider state must be a JSON object, got '{}'", pact_json));
+        }
         let state = match pact_json.get("name") {
             Some(v) => match *v {
                 Value::String(ref s) => s.clone(),
@@ -42,37 +47,40 @@ impl ProviderState {
         let params = match pact_json.get("params") {
             Some(v) => match *v {
                 Value::Object(ref map) => map.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
-                _ => {
-                    log::warn!("Provider state parameters must be a map");
-                    hashmap!{}
-                }
+                Value::Null => hashmap!{},
+                _ => return Err(anyhow!("Provider state parameters must be a map, got '{}'", v))
             },
             None => hashmap!{}
         };
-        ProviderState{
+        Ok(ProviderState{
             name: state,
             params
-        }
+        })
     }
 
-    /// Constructs a list of provider states from the `Json` struct
-    pub fn from_json(pact_json: &Value) -> Vec<ProviderState> {
+    /// Constructs a list of provider states from the `Json` struct. Returns an error if the
+    /// `providerStates` field is not a list of provider states.
+    pub fn from_json(pact_json: &Value) -> anyhow::Result<Vec<ProviderState>> {
         match pact_json.get("providerStates") {
             Some(v) => match *v {
-                Value::Array(ref a) => a.iter().map(|i| ProviderState::from_json_v3(i)).collect(),
-                _ => vec![]
+                Value::Array(ref a) => a.iter().enumerate()
+                  .map(|(index, i)| ProviderState::from_json_v3(i)
+                    .map_err(|err| anyhow!("providerStates[{}]: {}", index, err)))
+                  .collect(),
+                Value::Null => Ok(vec![]),
+                _ => Err(anyhow!("providerStates must be a list of provider states, got '{}'", v))
             },
             None => match pact_json.get("providerState").or(pact_json.get("provider_state")) {
                 Some(v) => match *v {
                     Value::String(ref s) => if s.is_empty() {
-                        vec![]
+                        Ok(vec![])
                     } else {
-                        vec![ProviderState{ name: s.clone(), params: hashmap!{} }]
+                        Ok(vec![ProviderState{ name: s.clone(), params: hashmap!{} }])
                     },
-                    Value::Null => vec![],
-                    _ => vec![ProviderState{ name: v.to_string(), params: hashmap!{} }]
+                    Value::Null => Ok(vec![]),
+                    _ => Ok(vec![ProviderState{ name: v.to_string(), params: hashmap!{} }])
                 },
-                None => vec![]
+                None => Ok(vec![])
             }
         }
     }
@@ -146,7 +154,7 @@ mod tests {
             ],
             "description" : "test interaction"
         }"#;
-        let provider_states = ProviderState::from_json(&serde_json::from_str(json).unwrap());
+        let provider_states = ProviderState::from_json(&serde_json::from_str(json).unwrap()).unwrap();
         expect!(provider_states.iter()).to(have_count(2));
         expect!(&provider_states[0]).to(be_equal_to(&ProviderState {
             name: s!("test state"),
@@ -164,7 +172,7 @@ mod tests {
             "providerState": "test state",
             "description" : "test interaction"
         }"#;
-        let provider_states = ProviderState::from_json(&serde_json::from_str(json).unwrap());
+        let provider_states = ProviderState::from_json(&serde_json::from_str(json).unwrap()).unwrap();
         expect!(provider_states.iter()).to(have_count(1));
         expect!(&provider_states[0]).to(be_equal_to(&ProviderState {
             name: s!("test state"),
@@ -177,8 +185,28 @@ mod tests {
         let json = r#"{
             "description" : "test interaction"
         }"#;
-        let provider_states = ProviderState::from_json(&serde_json::from_str(json).unwrap());
+        let provider_states = ProviderState::from_json(&serde_json::from_str(json).unwrap()).unwrap();
         expect!(provider_states.iter()).to(be_empty());
     }
 
+    #[test]
+    fn provider_states_must_be_a_list() {
+        let json = json!({ "providerStates": "test state" });
+        let result = ProviderState::from_json(&json);
+        expect!(result.unwrap_err().to_string()).to(
+          be_equal_to("providerStates must be a list of provider states, got '\"test state\"'"));
+    }
+
+    #[test]
+    fn provider_states_must_be_json_objects() {
+        let json = json!({ "providerStates": [ { "name": "test state" }, 100 ] });
+        let result = ProviderState::from_json(&json);
+        expect!(result.unwrap_err().to_string()).to(
+          be_equal_to("providerStates[1]: Provider state must be a JSON object, got '100'"));
+
+        let json = json!({ "providerStates": [ { "name": "test state", "params": [1, 2] } ] });
+        let result = ProviderState::from_json(&json);
+        expect!(result.unwrap_err().to_string()).to(
+          be_equal_to("providerStates[0]: Provider state parameters must be a map, got '[1,2]'"));
+    }
 }
@@ -0,0 +1,296 @@
+//! Redacts sensitive values (tokens, passwords or personal information) from the interactions of a
+//! pact, by replacing the values of headers, query parameters and body fields with placeholder
+//! values and type matchers. The redacted pact can then be published without leaking the values.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use pact_models::OptionalBody;
+use pact_models::content_types::ContentType;
+
+use crate::models::matchingrules::{MatchingRule, MatchingRules, RuleList};
+use crate::path_exp::{parse_path_exp, PathToken};
+
+/// Placeholder value used for redacted string values if none is given
+pub const DEFAULT_PLACEHOLDER: &str = "REDACTED";
+
+/// The values to redact from a pact
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redactions {
+  /// Names of the headers to redact (case-insensitive)
+  pub headers: Vec<String>,
+  /// Names of the query parameters to redact
+  pub query: Vec<String>,
+  /// Path expressions of the body fields to redact (i.e. `$.user.token` or `$.items[*].email`)
+  pub body: Vec<String>,
+  /// Placeholder to replace string values with
+  pub placeholder: String
+}
+
+impl Default for Redactions {
+  fn default() -> Self {
+    Redactions {
+      headers: vec![],
+      query: vec![],
+      body: vec![],
+      placeholder: DEFAULT_PLACEHOLDER.to_string()
+    }
+  }
+}
+
+/// Value that was redacted
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedactedField {
+  /// Category of the value (header, query or body)
+  pub category: String,
+  /// Name of the header or query parameter, or the path expression of the body field
+  pub path: String
+}
+
+impl RedactedField {
+  fn new(category: &str, path: &str) -> RedactedField {
+    RedactedField { category: category.to_string(), path: path.to_string() }
+  }
+}
+
+impl Redactions {
+  /// Checks that the body path expressions are valid
+  pub fn validate(&self) -> Result<(), String> {
+    for path in &self.body {
+      parse_path_exp(path)?;
+    }
+    Ok(())
+  }
+
+  /// Replaces the values of the redacted headers with the placeholder, and sets a type matcher
+  /// on them. Returns the headers that were redacted.
+  pub fn redact_headers(
+    &self,
+    headers: &mut Option<HashMap<String, Vec<String>>>,
+    matching_rules: &mut MatchingRules
+  ) -> Vec<RedactedField> {
+    let names = |name: &str| self.headers.iter().any(|header| header.eq_ignore_ascii_case(name));
+    self.redact_map(headers, names, "header", matching_rules)
+  }
+
+  /// Replaces the values of the redacted query parameters with the placeholder, and sets a type
+  /// matcher on them. Returns the query parameters that were redacted.
+  pub fn redact_query(
+    &self,
+    query: &mut Option<HashMap<String, Vec<String>>>,
+    matching_rules: &mut MatchingRules
+  ) -> Vec<RedactedField> {
+    let names = |name: &str| self.query.iter().any(|param| param == name);
+    self.redact_map(query, names, "query", matching_rules)
+  }
+
+  fn redact_map<F>(
+    &self,
+    values: &mut Option<HashMap<String, Vec<String>>>,
+    redacted: F,
+    category: &str,
+    matching_rules: &mut MatchingRules
+  ) -> Vec<RedactedField> where F: Fn(&str) -> bool {
+    let mut fields = vec![];
+    if let Some(values) = values {
+      for (name, values) in values.iter_mut().filter(|(name, _)| redacted(name)) {
+        for value in values.iter_mut() {
+          *value = self.placeholder.clone();
+        }
+        matching_rules.add_category(category).rules.insert(name.clone(), RuleList::new(MatchingRule::Type));
+        fields.push(RedactedField::new(category, name));
+      }
+    }
+    fields.sort_by(|a, b| a.path.cmp(&b.path));
+    fields
+  }
+
+  /// Replaces the values of the redacted fields of the body with placeholder values of the same
+  /// type, and sets a type matcher on them. Only JSON bodies are supported. Returns the path
+  /// expressions that matched a field in the body.
+  pub fn redact_body(
+    &self,
+    body: &mut OptionalBody,
+    content_type: Option<ContentType>,
+    matching_rules: &mut MatchingRules
+  ) -> Vec<RedactedField> {
+    let is_json = content_type.map(|ct| ct.is_json()).unwrap_or(false);
+    if self.body.is_empty() || !is_json {
+      return vec![];
+    }
+
+    if let OptionalBody::Present(bytes, ct) = body {
+      let mut json: Value = match serde_json::from_slice(bytes) {
+        Ok(json) => json,
+        Err(err) => {
+          log::warn!("Not redacting the body as it is not valid JSON - {}", err);
+          return vec![];
+        }
+      };
+      let fields = self.redact_json_body(&mut json, matching_rules);
+      if !fields.is_empty() {
+        *body = OptionalBody::Present(json.to_string().into(), ct.clone());
+      }
+      fields
+    } else {
+      vec![]
+    }
+  }
+
+  /// Replaces the values of the redacted fields of the JSON body with placeholder values. See
+  /// `redact_body`.
+  pub fn redact_json_body(&self, json: &mut Value, matching_rules: &mut MatchingRules) -> Vec<RedactedField> {
+    let mut fields = vec![];
+    for path in &self.body {
+      match parse_path_exp(path) {
+        Ok(tokens) => if redact_path(json, &tokens, &self.placeholder) > 0 {
+          matching_rules.add_category("body").rules.insert(path.clone(), RuleList::new(MatchingRule::Type));
+          fields.push(RedactedField::new("body", path));
+        },
+        Err(err) => log::warn!("Ignoring invalid body path '{}' - {}", path, err)
+      }
+    }
+    fields
+  }
+}
+
+/// Replaces the values matching the path tokens with placeholders, returning the number of values
+/// that were replaced
+fn redact_path(json: &mut Value, tokens: &[PathToken], placeholder: &str) -> usize {
+  match tokens.split_first() {
+    None => {
+      *json = placeholder_value(json, placeholder);
+      1
+    },
+    Some((token, rest)) => match (token, json) {
+      (PathToken::Root, json) => redact_path(json, rest, placeholder),
+      (PathToken::Field(name), Value::Object(map)) => map.get_mut(name)
+        .map(|value| redact_path(value, rest, placeholder))
+        .unwrap_or_default(),
+      (PathToken::Index(index), Value::Array(items)) => items.get_mut(*index)
+        .map(|value| redact_path(value, rest, placeholder))
+        .unwrap_or_default(),
+      (PathToken::Star, Value::Object(map)) => map.values_mut()
+        .map(|value| redact_path(value, rest, placeholder))
+        .sum(),
+      (PathToken::Star, Value::Array(items)) | (PathToken::StarIndex, Value::Array(items)) => items.iter_mut()
+        .map(|value| redact_path(value, rest, placeholder))
+        .sum(),
+      _ => 0
+    }
+  }
+}
+
+fn placeholder_value(value: &Value, placeholder: &str) -> Value {
+  match value {
+    Value::String(_) => Value::String(placeholder.to_string()),
+    Value::Number(n) if n.is_f64() => serde_json::json!(0.0),
+    Value::Number(_) => serde_json::json!(0),
+    Value::Bool(_) => Value::Bool(false),
+    Value::Array(_) => Value::Array(vec![]),
+    Value::Object(_) => Value::Object(serde_json::Map::new()),
+    Value::Null => Value::Null
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use super::*;
+
+  fn redactions() -> Redactions {
+    Redactions {
+      headers: vec!["authorization".to_string()],
+      query: vec!["api_key".to_string()],
+      body: vec!["$.token".to_string(), "$.users[*].email".to_string(), "$.account.balance".to_string()],
+      .. Redactions::default()
+    }
+  }
+
+  #[test]
+  fn redact_headers_replaces_the_values_case_insensitively() {
+    let mut headers = Some(hashmap! {
+      "Authorization".to_string() => vec!["Bearer 1234".to_string()],
+      "Accept".to_string() => vec!["application/json".to_string()]
+    });
+    let mut matching_rules = MatchingRules::default();
+
+    let fields = redactions().redact_headers(&mut headers, &mut matching_rules);
+
+    expect!(fields).to(be_equal_to(vec![RedactedField::new("header", "Authorization")]));
+    expect!(headers).to(be_some().value(hashmap! {
+      "Authorization".to_string() => vec!["REDACTED".to_string()],
+      "Accept".to_string() => vec!["application/json".to_string()]
+    }));
+    expect!(matching_rules).to(be_equal_to(matchingrules! {
+      "header" => { "Authorization" => [ MatchingRule::Type ] }
+    }));
+  }
+
+  #[test]
+  fn redact_query_replaces_all_the_values() {
+    let mut query = Some(hashmap! {
+      "api_key".to_string() => vec!["abc".to_string(), "def".to_string()],
+      "page".to_string() => vec!["1".to_string()]
+    });
+    let mut matching_rules = MatchingRules::default();
+
+    let fields = redactions().redact_query(&mut query, &mut matching_rules);
+
+    expect!(fields).to(be_equal_to(vec![RedactedField::new("query", "api_key")]));
+    expect!(query.unwrap()["api_key"].clone()).to(be_equal_to(vec!["REDACTED".to_string(), "REDACTED".to_string()]));
+  }
+
+  #[test]
+  fn redact_json_body_replaces_the_values_with_placeholders_of_the_same_type() {
+    let mut body = json!({
+      "token": "secret",
+      "users": [ { "email": "a@example.com" }, { "email": "b@example.com" } ],
+      "account": { "balance": 100, "currency": "AUD" }
+    });
+    let mut matching_rules = matchingrules! {
+      "body" => { "$.token" => [ MatchingRule::Regex("^s.*".to_string()) ] }
+    };
+
+    let fields = redactions().redact_json_body(&mut body, &mut matching_rules);
+
+    expect!(fields.len()).to(be_equal_to(3));
+    expect!(body).to(be_equal_to(json!({
+      "token": "REDACTED",
+      "users": [ { "email": "REDACTED" }, { "email": "REDACTED" } ],
+      "account": { "balance": 0, "currency": "AUD" }
+    })));
+    expect!(matching_rules).to(be_equal_to(matchingrules! {
+      "body" => {
+        "$.token" => [ MatchingRule::Type ],
+        "$.users[*].email" => [ MatchingRule::Type ],
+        "$.account.balance" => [ MatchingRule::Type ]
+      }
+    }));
+  }
+
+  #[test]
+  fn redact_body_ignores_paths_that_do_not_match_and_bodies_that_are_not_json() {
+    let mut matching_rules = MatchingRules::default();
+    let mut body = OptionalBody::Present("{\"name\":\"Fred\"}".into(), None);
+    expect!(redactions().redact_body(&mut body, Some(ContentType::from("application/json")),
+      &mut matching_rules).iter()).to(be_empty());
+    expect!(body.clone()).to(be_equal_to(OptionalBody::Present("{\"name\":\"Fred\"}".into(), None)));
+
+    let mut body = OptionalBody::Present("token=secret".into(), None);
+    expect!(redactions().redact_body(&mut body, Some(ContentType::from("text/plain")),
+      &mut matching_rules).iter()).to(be_empty());
+    expect!(matching_rules.is_empty()).to(be_true());
+  }
+
+  #[test]
+  fn validate_checks_the_body_paths() {
+    expect!(redactions().validate()).to(be_ok());
+    let redactions = Redactions { body: vec!["$.a[".to_string()], .. Redactions::default() };
+    expect!(redactions.validate()).to(be_err());
+  }
+}
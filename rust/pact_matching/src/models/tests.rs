@@ -54,7 +54,7 @@ fn response_from_json_defaults_to_status_200() {
           "headers": {}
       }
      "#).unwrap();
-    let response = Response::from_json(&response_json, &PactSpecification::V1_1);
+    let response = Response::from_json(&response_json, &PactSpecification::V1_1).unwrap();
     assert_eq!(response.status, 200);
 }
 
@@ -118,6 +118,42 @@ fn parse_query_string_decodes_non_ascii_values() {
   expect!(result).to(be_some().value(expected));
 }
 
+#[test]
+fn parse_query_string_treats_plus_and_encoded_spaces_the_same() {
+  let result = parse_query_string("a=a+b%20c&b%5B%5D=%7E");
+  expect!(result).to(be_some().value(hashmap! {
+    "a".to_string() => vec!["a b c".to_string()],
+    "b[]".to_string() => vec!["~".to_string()]
+  }));
+}
+
+#[test]
+fn parse_query_string_keeps_malformed_values_as_received() {
+  let result = parse_query_string("a=100%&b=%zz&c=%FF");
+  expect!(result).to(be_some().value(hashmap! {
+    "a".to_string() => vec!["100%".to_string()],
+    "b".to_string() => vec!["%zz".to_string()],
+    "c".to_string() => vec!["%FF".to_string()]
+  }));
+}
+
+#[test]
+fn query_string_encoding_mismatches_returns_a_mismatch_for_malformed_encodings() {
+  expect!(query_string_encoding_mismatches("a=a+b%20c&b=%E6%96%87").iter()).to(be_empty());
+  let mismatches = query_string_encoding_mismatches("a=100%&b=ok&c=%FF");
+  expect!(mismatches.iter().map(|m| m.description()).collect::<Vec<String>>()).to(be_equal_to(vec![
+    "Query parameter 'a' is not correctly encoded - Malformed percent-encoding '%' at index 3 of '100%'".to_string(),
+    "Query parameter 'c' is not correctly encoded - Failed to decode '%FF' to UTF-8 - invalid utf-8 sequence of 1 bytes from index 0".to_string()
+  ]));
+}
+
+#[test]
+fn encode_and_decode_query_round_trip() {
+  let value = "a b+c=d&e/f?g~h.i_j-k❤";
+  expect!(encode_query(value)).to(be_equal_to("a+b%2Bc%3Dd%26e%2Ff%3Fg~h.i_j-k%E2%9D%A4".to_string()));
+  expect!(decode_query(&encode_query(value))).to(be_ok().value(value.to_string()));
+}
+
 #[test]
 #[ignore]
 fn quickcheck_parse_query_string() {
@@ -209,6 +245,20 @@ fn request_content_type_is_based_on_the_content_type_header() {
       .to(be_equal_to("text/html"));
 }
 
+#[test]
+fn content_type_test_detects_soap_envelopes() {
+    let request = Request { body: OptionalBody::Missing, .. Request::default() };
+    expect!(Request {
+        body: OptionalBody::Present(r#"<?xml version="1.0" encoding="UTF-8"?>
+          <soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope"><soap:Body/></soap:Envelope>"#.into(), None),
+        .. request.clone() }.content_type().unwrap_or_default().to_string())
+      .to(be_equal_to("application/soap+xml"));
+    expect!(Request {
+        body: OptionalBody::Present(r#"<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/"><SOAP-ENV:Body/></SOAP-ENV:Envelope>"#.into(), None),
+        .. request.clone() }.content_type().unwrap_or_default().to_string())
+      .to(be_equal_to("text/xml"));
+}
+
 #[test]
 fn content_type_struct_test() {
     let request = Request {
@@ -254,7 +304,7 @@ fn loading_interaction_from_json() {
         "description": "String",
         "providerState": "provider state"
     }"#;
-    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1_1);
+    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1_1).unwrap();
     expect!(interaction.description).to(be_equal_to("String"));
     expect!(interaction.provider_states).to(be_equal_to(vec![
         ProviderState { name: s!("provider state"), params: hashmap!{} } ]));
@@ -265,7 +315,7 @@ fn defaults_to_number_if_no_description() {
     let interaction_json = r#"{
         "providerState": "provider state"
     }"#;
-    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1_1);
+    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1_1).unwrap();
     expect!(interaction.description).to(be_equal_to("Interaction 0"));
     expect!(interaction.provider_states).to(be_equal_to(vec![
         ProviderState { name: s!("provider state"), params: hashmap!{} } ]));
@@ -275,7 +325,7 @@ fn defaults_to_number_if_no_description() {
 fn defaults_to_empty_if_no_provider_state() {
     let interaction_json = r#"{
     }"#;
-    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1);
+    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1).unwrap();
     expect!(interaction.provider_states.iter()).to(be_empty());
 }
 
@@ -284,14 +334,14 @@ fn defaults_to_none_if_provider_state_null() {
     let interaction_json = r#"{
         "providerState": null
     }"#;
-    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1);
+    let interaction = RequestResponseInteraction::from_json(0, &serde_json::from_str(interaction_json).unwrap(), &PactSpecification::V1).unwrap();
     expect!(interaction.provider_states.iter()).to(be_empty());
 }
 
 #[test]
 fn load_empty_pact() {
     let pact_json = r#"{}"#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.provider.name).to(be_equal_to("provider"));
     expect!(pact.consumer.name).to(be_equal_to("consumer"));
     expect!(pact.interactions.iter()).to(have_count(0));
@@ -302,7 +352,7 @@ fn load_empty_pact() {
 #[test]
 fn missing_metadata() {
     let pact_json = r#"{}"#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.specification_version).to(be_equal_to(PactSpecification::V3));
 }
 
@@ -312,7 +362,7 @@ fn missing_spec_version() {
         "metadata" : {
         }
     }"#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.specification_version).to(be_equal_to(PactSpecification::V3));
 }
 
@@ -325,7 +375,7 @@ fn missing_version_in_spec_version() {
             }
         }
     }"#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.specification_version).to(be_equal_to(PactSpecification::V3));
 }
 
@@ -338,7 +388,7 @@ fn empty_version_in_spec_version() {
             }
         }
     }"#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.specification_version).to(be_equal_to(PactSpecification::Unknown));
 }
 
@@ -351,7 +401,7 @@ fn correct_version_in_spec_version() {
             }
         }
     }"#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.specification_version).to(be_equal_to(PactSpecification::V1));
 }
 
@@ -364,7 +414,7 @@ fn invalid_version_in_spec_version() {
             }
         }
     }"#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.specification_version).to(be_equal_to(PactSpecification::Unknown));
 }
 
@@ -398,7 +448,7 @@ fn load_basic_pact() {
         ]
     }
     "#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(&pact.provider.name).to(be_equal_to("Alice Service"));
     expect!(&pact.consumer.name).to(be_equal_to("Consumer"));
     expect!(pact.interactions.iter()).to(have_count(1));
@@ -467,7 +517,7 @@ fn load_pact() {
       }
     }
     "#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(&pact.provider.name).to(be_equal_to("test_provider"));
     expect!(&pact.consumer.name).to(be_equal_to("test_consumer"));
     expect!(pact.metadata.iter()).to(have_count(2));
@@ -541,7 +591,7 @@ fn load_v3_pact() {
       }
     }
     "#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(&pact.provider.name).to(be_equal_to("test_provider"));
     expect!(&pact.consumer.name).to(be_equal_to("test_consumer"));
     expect!(pact.metadata.iter()).to(have_count(2));
@@ -612,7 +662,7 @@ fn load_pact_encoded_query_string() {
       }
     }
     "#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.interactions.iter()).to(have_count(1));
     let interaction = pact.interactions[0].clone();
     expect!(interaction.request).to(be_equal_to(Request {
@@ -642,7 +692,7 @@ fn load_pact_converts_methods_to_uppercase() {
       "metadata" : {}
     }
     "#;
-    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap());
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
     expect!(pact.interactions.iter()).to(have_count(1));
     let interaction = pact.interactions[0].clone();
     expect!(interaction.request).to(be_equal_to(Request {
@@ -655,6 +705,56 @@ fn load_pact_converts_methods_to_uppercase() {
     }));
 }
 
+#[test]
+fn load_pact_keeps_the_case_of_extension_methods() {
+    let pact_json = r#"
+    {
+      "interactions" : [ {
+        "description" : "test interaction",
+        "request" : {
+          "method" : "purge",
+          "matchingRules" : {
+            "method" : {
+              "matchers" : [ { "match" : "equality" } ]
+            }
+          }
+        },
+        "response" : {
+          "status" : 200
+        }
+      } ],
+      "metadata" : {}
+    }
+    "#;
+    let pact = RequestResponsePact::from_json(&s!(""), &serde_json::from_str(pact_json).unwrap()).unwrap();
+    let request = pact.interactions[0].request.clone();
+    expect!(request.method.as_str()).to(be_equal_to("purge"));
+    expect!(request.matching_rules.rules_for_category("method")).to(be_some());
+    let json = request.to_json(&PactSpecification::V3);
+    expect!(json["method"].clone()).to(be_equal_to(json!("purge")));
+    expect!(json["matchingRules"].clone()).to(be_equal_to(json!({
+      "method": { "matchers": [ { "match": "equality" } ], "combine": "AND" }
+    })));
+}
+
+#[test]
+fn normalise_method_test() {
+    expect!(normalise_method("get")).to(be_equal_to("GET"));
+    expect!(normalise_method("Patch")).to(be_equal_to("PATCH"));
+    expect!(normalise_method("PURGE")).to(be_equal_to("PURGE"));
+    expect!(normalise_method("report")).to(be_equal_to("report"));
+}
+
+#[test]
+fn validate_method_test() {
+    expect!(validate_method("GET")).to(be_ok());
+    expect!(validate_method("PURGE")).to(be_ok());
+    expect!(validate_method("X-CUSTOM_1")).to(be_ok());
+    expect!(validate_method("")).to(be_err().value("HTTP method can not be empty".to_string()));
+    expect!(validate_method("GET ME")).to(be_err().value(
+      "'GET ME' is not a valid HTTP method, it contains an invalid character ' '".to_string()));
+}
+
 #[test]
 fn request_to_json_with_defaults() {
     let request = Request::default();
@@ -685,7 +785,7 @@ fn request_to_json_with_a_query_must_encode_the_query() {
         s!("datetime") => vec![s!("2011-12-03T10:15:30+01:00")],
         s!("description") => vec![s!("hello world!")] }), .. Request::default() };
     expect!(request.to_json(&PactSpecification::V2).to_string()).to(
-        be_equal_to(r#"{"method":"GET","path":"/","query":"datetime=2011-12-03T10%3a15%3a30%2b01%3a00&description=hello+world%21"}"#)
+        be_equal_to(r#"{"method":"GET","path":"/","query":"datetime=2011-12-03T10%3A15%3A30%2B01%3A00&description=hello+world%21"}"#)
     );
 }
 
@@ -695,7 +795,7 @@ fn request_to_json_with_a_query_must_encode_the_query_with_utf8_chars() {
         s!("a") => vec![s!("b=c&d❤")]
     }), .. Request::default() };
     expect!(request.to_json(&PactSpecification::V2).to_string()).to(
-        be_equal_to(r#"{"method":"GET","path":"/","query":"a=b%3dc%26d%27%64"}"#)
+        be_equal_to(r#"{"method":"GET","path":"/","query":"a=b%3Dc%26d%E2%9D%A4"}"#)
     );
 }
 
@@ -843,7 +943,113 @@ fn interaction_from_json_sets_the_id_if_loaded_from_broker() {
       "status": 200
     }
   });
-  expect!(RequestResponseInteraction::from_json(0, &json, &PactSpecification::V3).id).to(be_some().value("123456789".to_string()));
+  expect!(RequestResponseInteraction::from_json(0, &json, &PactSpecification::V3).unwrap().id).to(be_some().value("123456789".to_string()));
+}
+
+#[test]
+fn load_pact_from_json_returns_an_error_if_the_response_status_is_not_a_number() {
+  let json = json!({
+    "interactions": [
+      {
+        "description": "Test Interaction",
+        "response": {
+          "status": "200"
+        }
+      }
+    ]
+  });
+  expect!(load_pact_from_json("test", &json)).to(be_err());
+
+  let json = json!({ "type": "Synchronous/HTTP", "response": { "status": 100000 } });
+  expect!(v4::interaction_from_json("test", 0, &json)).to(be_err());
+}
+
+#[test]
+fn load_pact_from_json_errors_include_the_interaction_index_and_field() {
+  let json = json!({
+    "interactions": [
+      { "description": "a", "response": { "status": 200 } },
+      { "description": "b", "response": { "status": "200" } }
+    ]
+  });
+  expect!(load_pact_from_json("test", &json).unwrap_err().to_string()).to(
+    be_equal_to("Failed to parse the pact from 'test' at $.interactions[1] - Failed to parse interaction 1 ('b') - response.status must be a valid HTTP status code, got '\"200\"'"));
+
+  let json = json!({
+    "interactions": [
+      { "description": "a", "providerStates": { "name": "state" } }
+    ]
+  });
+  expect!(load_pact_from_json("test", &json).unwrap_err().to_string()).to(
+    be_equal_to("Failed to parse the pact from 'test' at $.interactions[0] - Failed to parse interaction 0 ('a') - providerStates must be a list of provider states, got '{\"name\":\"state\"}'"));
+
+  let json = json!({
+    "messages": [
+      { "description": "a message", "providerStates": [ "state" ] }
+    ],
+    "metadata": { "pactSpecification": { "version": "3.0.0" } }
+  });
+  expect!(load_pact_from_json("test", &json).unwrap_err().to_string()).to(
+    be_equal_to("Failed to parse the pact from 'test' at $.messages[0] - Failed to parse interaction 0 ('a message') - providerStates[0]: Provider state must be a JSON object, got '\"state\"'"));
+
+  let json = json!({ "type": "Synchronous/HTTP", "description": "v4", "response": { "status": -1 } });
+  expect!(v4::interaction_from_json("test", 2, &json).unwrap_err().to_string()).to(
+    be_equal_to("Failed to parse interaction 2 ('v4') - response.status must be a valid HTTP status code, got '-1'"));
+}
+
+#[test]
+fn load_pact_from_json_returns_a_parse_error_with_the_location() {
+  let result = load_pact_from_json("test", &json!([]));
+  expect!(matches!(result, Err(PactError::ParseError { location: None, .. }))).to(be_true());
+
+  let json = json!({ "messages": { "description": "a message" } });
+  match load_pact_from_json("test", &json) {
+    Err(PactError::ParseError { pact_source, location, .. }) => {
+      expect!(pact_source).to(be_equal_to("test"));
+      expect!(location).to(be_some().value("$.messages"));
+    },
+    _ => panic!("Expected a parse error")
+  }
+}
+
+#[test]
+fn read_pact_returns_an_io_error_if_the_file_does_not_exist() {
+  let path = Path::new("/does/not/exist/pact.json");
+  match read_pact(path) {
+    Err(PactError::IoError { path: Some(err_path), err }) => {
+      expect!(err_path).to(be_equal_to(path.to_path_buf()));
+      expect!(err.kind()).to(be_equal_to(io::ErrorKind::NotFound));
+    },
+    _ => panic!("Expected an IO error")
+  }
+}
+
+#[test]
+fn read_pact_returns_a_parse_error_with_the_line_and_column_for_invalid_json() {
+  let mut path = env::temp_dir();
+  path.push(format!("pact_test_{}.json", rand::random::<u16>()));
+  fs::write(&path, "{\n  \"consumer\": \n}").unwrap();
+  let result = read_pact(&path);
+  fs::remove_file(&path).unwrap_or(());
+
+  match result {
+    Err(PactError::ParseError { location, .. }) => {
+      expect!(location).to(be_some().value("line 3, column 1"));
+    },
+    _ => panic!("Expected a parse error")
+  }
+}
+
+#[test]
+fn matchers_from_json_ignores_invalid_v2_paths() {
+  let json = json!({
+    "matchingRules": {
+      "$": { "match": "type" },
+      "$.headers": { "match": "type" }
+    }
+  });
+  let matchers = matchers_from_json(&json, &None);
+  expect!(matchers.is_empty()).to(be_true());
 }
 
 #[test]
@@ -881,7 +1087,7 @@ fn write_pact_test() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, true);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, true, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -949,8 +1155,8 @@ fn write_pact_test_should_merge_pacts() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, false);
-    let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V2, false);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, false, MergeStrategy::default());
+    let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V2, false, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -1031,14 +1237,14 @@ fn write_pact_test_should_not_merge_pacts_with_conflicts() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, false);
-    let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V2, false);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, false, MergeStrategy::default());
+    let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V2, false, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
 
     expect!(result).to(be_ok());
-    expect!(result2).to(be_err());
+    expect!(matches!(result2, Err(PactError::MergeConflict { .. }))).to(be_true());
     expect!(pact_file).to(be_equal_to(format!(r#"{{
   "consumer": {{
     "name": "write_pact_test_consumer"
@@ -1101,8 +1307,8 @@ fn write_pact_test_should_upgrade_older_pacts_when_merging() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, false);
-    let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V3, false);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, false, MergeStrategy::default());
+    let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V3, false, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -1193,8 +1399,8 @@ fn write_pact_test_upgrades_older_pacts_to_v4_when_merging() {
   dir.push(format!("pact_test_{}", x));
   dir.push(pact.default_file_name());
 
-  let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, false);
-  let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V4, false);
+  let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, false, MergeStrategy::default());
+  let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V4, false, MergeStrategy::default());
 
   let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
   fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -1208,7 +1414,7 @@ fn write_pact_test_upgrades_older_pacts_to_v4_when_merging() {
   "interactions": [
     {{
       "description": "Test Interaction",
-      "key": "53d3170820ad2160",
+      "key": "b775b449942b3933d336bf0bc6f37831",
       "providerStates": [
         {{
           "name": "Good state to be in"
@@ -1225,7 +1431,7 @@ fn write_pact_test_upgrades_older_pacts_to_v4_when_merging() {
     }},
     {{
       "description": "Test Interaction 2",
-      "key": "4da93913a351bb8c",
+      "key": "d5eb004cb547ea57a19ac9b46a8043af",
       "providerStates": [
         {{
           "name": "Good state to be in"
@@ -1269,7 +1475,7 @@ fn pact_merge_does_not_merge_different_consumers() {
         metadata: btreemap!{},
         specification_version: PactSpecification::V1_1
     };
-    expect!(pact.merge(&pact2)).to(be_err());
+    expect!(matches!(pact.merge(&pact2), Err(PactError::MergeError(_)))).to(be_true());
 }
 
 #[test]
@@ -1316,7 +1522,14 @@ fn pact_merge_does_not_merge_where_there_are_conflicting_interactions() {
         metadata: btreemap!{},
         specification_version: PactSpecification::V1_1
     };
-    expect!(pact.merge(&pact2)).to(be_err());
+    match pact.merge(&pact2) {
+      Err(PactError::MergeConflict { conflicts }) => {
+        expect!(conflicts).to(be_equal_to(vec![
+          "Interaction 'Test Interaction': Request path / != /other".to_string()
+        ]));
+      },
+      _ => panic!("Expected a merge conflict")
+    }
 }
 
 #[test]
@@ -1356,6 +1569,154 @@ fn pact_merge_removes_duplicates() {
     expect!(merged_pact2.unwrap().interactions().len()).to(be_equal_to(1));
 }
 
+#[test]
+fn pact_merge_with_the_union_strategy_combines_the_headers_and_matching_rules() {
+    let pact = RequestResponsePact { consumer: Consumer { name: s!("test_consumer") },
+        provider: Provider { name: s!("test_provider") },
+        interactions: vec![
+            RequestResponseInteraction {
+                description: s!("Test Interaction"),
+                response: Response {
+                    headers: Some(hashmap!{ s!("Content-Type") => vec![s!("application/json")] }),
+                    matching_rules: matchingrules!{ "body" => { "$.id" => [ MatchingRule::Type ] } },
+                    .. Response::default()
+                },
+                .. RequestResponseInteraction::default()
+            }
+        ],
+        .. RequestResponsePact::default()
+    };
+    let pact2 = RequestResponsePact { consumer: Consumer { name: s!("test_consumer") },
+        provider: Provider { name: s!("test_provider") },
+        interactions: vec![
+            RequestResponseInteraction {
+                description: s!("Test Interaction"),
+                response: Response {
+                    headers: Some(hashmap!{
+                        s!("content-type") => vec![s!("application/json")],
+                        s!("X-Request-Id") => vec![s!("1234")]
+                    }),
+                    matching_rules: matchingrules!{
+                        "body" => { "$.id" => [ MatchingRule::Type ], "$.name" => [ MatchingRule::Type ] }
+                    },
+                    .. Response::default()
+                },
+                .. RequestResponseInteraction::default()
+            }
+        ],
+        .. RequestResponsePact::default()
+    };
+
+    expect!(pact.merge(&pact2)).to(be_err());
+
+    let merged_pact = pact.merge_with_strategy(&pact2, MergeStrategy::Union).unwrap();
+    let interactions = merged_pact.interactions();
+    expect!(interactions.len()).to(be_equal_to(1));
+    let response = interactions[0].as_request_response().unwrap().response;
+    expect!(response.headers).to(be_some().value(hashmap!{
+        s!("Content-Type") => vec![s!("application/json")],
+        s!("X-Request-Id") => vec![s!("1234")]
+    }));
+    expect!(response.matching_rules).to(be_equal_to(matchingrules!{
+        "body" => { "$.id" => [ MatchingRule::Type ], "$.name" => [ MatchingRule::Type ] }
+    }));
+}
+
+#[test]
+fn pact_merge_with_the_union_strategy_fails_if_the_interactions_are_not_compatible() {
+    let pact = RequestResponsePact { consumer: Consumer { name: s!("test_consumer") },
+        provider: Provider { name: s!("test_provider") },
+        interactions: vec![
+            RequestResponseInteraction {
+                description: s!("Test Interaction"),
+                response: Response {
+                    headers: Some(hashmap!{ s!("Content-Type") => vec![s!("application/json")] }),
+                    .. Response::default()
+                },
+                .. RequestResponseInteraction::default()
+            }
+        ],
+        .. RequestResponsePact::default()
+    };
+    let pact2 = RequestResponsePact { consumer: Consumer { name: s!("test_consumer") },
+        provider: Provider { name: s!("test_provider") },
+        interactions: vec![
+            RequestResponseInteraction {
+                description: s!("Test Interaction"),
+                response: Response {
+                    status: 201,
+                    headers: Some(hashmap!{ s!("Content-Type") => vec![s!("text/plain")] }),
+                    .. Response::default()
+                },
+                .. RequestResponseInteraction::default()
+            }
+        ],
+        .. RequestResponsePact::default()
+    };
+
+    match pact.merge_with_strategy(&pact2, MergeStrategy::Union) {
+      Err(PactError::MergeConflict { conflicts }) => {
+        expect!(conflicts).to(be_equal_to(vec![
+          "Interaction 'Test Interaction': Response status 200 != 201".to_string(),
+          "Interaction 'Test Interaction': Response header 'Content-Type' has different values [\"application/json\"] != [\"text/plain\"]".to_string()
+        ]));
+      },
+      _ => panic!("Expected a merge conflict")
+    }
+}
+
+#[test]
+fn pact_merge_with_the_union_strategy_fails_if_the_bodies_are_different() {
+    let pact = RequestResponsePact { consumer: Consumer { name: s!("test_consumer") },
+        provider: Provider { name: s!("test_provider") },
+        interactions: vec![
+            RequestResponseInteraction {
+                description: s!("Test Interaction"),
+                response: Response { body: OptionalBody::Present("{\"id\":1}".into(), None), .. Response::default() },
+                .. RequestResponseInteraction::default()
+            }
+        ],
+        .. RequestResponsePact::default()
+    };
+    let pact2 = RequestResponsePact { consumer: Consumer { name: s!("test_consumer") },
+        provider: Provider { name: s!("test_provider") },
+        interactions: vec![
+            RequestResponseInteraction {
+                description: s!("Test Interaction"),
+                response: Response { body: OptionalBody::Present("{\"id\":2}".into(), None), .. Response::default() },
+                .. RequestResponseInteraction::default()
+            }
+        ],
+        .. RequestResponsePact::default()
+    };
+
+    match pact.merge_with_strategy(&pact2, MergeStrategy::Union) {
+      Err(PactError::MergeConflict { conflicts }) => {
+        expect!(conflicts.len()).to(be_equal_to(1));
+        expect!(conflicts[0].starts_with("Interaction 'Test Interaction': Response body")).to(be_true());
+      },
+      _ => panic!("Expected a merge conflict")
+    }
+}
+
+#[test]
+fn pact_merge_with_the_union_strategy_is_not_supported_for_v4_and_message_pacts() {
+    let pact = V4Pact { consumer: Consumer { name: s!("test_consumer") },
+      provider: Provider { name: s!("test_provider") }, .. V4Pact::default() };
+    expect!(pact.merge_with_strategy(&pact.clone(), MergeStrategy::FailOnConflict)).to(be_ok());
+    expect!(pact.merge_with_strategy(&pact.clone(), MergeStrategy::Union)).to(be_err());
+
+    let message_pact = crate::models::message_pact::MessagePact {
+      consumer: Consumer { name: s!("test_consumer") },
+      provider: Provider { name: s!("test_provider") },
+      messages: vec![],
+      metadata: btreemap!{},
+      specification_version: PactSpecification::V3
+    };
+    expect!(message_pact.merge_with_strategy(&message_pact.clone(), MergeStrategy::FailOnConflict)).to(be_ok());
+    expect!(message_pact.merge_with_strategy(&message_pact.clone(), MergeStrategy::Union)).to(be_err());
+}
+
 #[test]
 fn interactions_do_not_conflict_if_they_have_different_descriptions() {
     let interaction1 = RequestResponseInteraction {
@@ -1628,7 +1989,7 @@ fn write_pact_test_with_matchers() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, true);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V2, true, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -1698,7 +2059,7 @@ fn write_pact_v3_test_with_matchers() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, true);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, true, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -1939,7 +2300,7 @@ fn write_v3_pact_test() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, true);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, true, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -2094,6 +2455,29 @@ fn generators_from_json_loads_generators_correctly() {
     }));
 }
 
+#[test]
+fn request_query_generators_are_serialised_for_v3_and_not_v2() {
+    let json = json!({
+      "method": "GET",
+      "path": "/users",
+      "query": { "requestId": ["1234"] },
+      "generators": {
+        "query": {
+          "requestId": { "type": "Uuid" }
+        }
+      }
+    });
+    let request = Request::from_json(&json, &PactSpecification::V3);
+    expect!(request.generators.clone()).to(be_equal_to(generators!{
+        "QUERY" => {
+            "requestId" => Generator::Uuid
+        }
+    }));
+    expect!(request.to_json(&PactSpecification::V3)["generators"].clone())
+      .to(be_equal_to(json!({ "query": { "requestId": { "type": "Uuid" } } })));
+    expect!(request.to_json(&PactSpecification::V2).get("generators")).to(be_none());
+}
+
 #[test]
 fn write_pact_test_with_generators() {
     let pact = RequestResponsePact { consumer: Consumer { name: s!("write_pact_test_consumer") },
@@ -2122,7 +2506,7 @@ fn write_pact_test_with_generators() {
     dir.push(format!("pact_test_{}", x));
     dir.push(pact.default_file_name());
 
-    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, true);
+    let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V3, true, MergeStrategy::default());
 
     let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
     fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
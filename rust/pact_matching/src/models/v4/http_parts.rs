@@ -12,7 +12,7 @@ use serde_json::{json, Value};
 use pact_models::{OptionalBody, PactSpecification};
 use pact_models::content_types::ContentType;
 
-use crate::models::{detect_content_type_from_bytes, generators, headers_from_json, matchingrules, query_to_json, Request, Response, v3_query_from_json};
+use crate::models::{detect_content_type_from_bytes, generators, headers_from_json, matchingrules, method_from_json, normalise_method, query_to_json, Request, Response, status_from_json, v3_query_from_json};
 use crate::models::json_utils::json_to_string;
 use crate::models::v4::calc_content_type;
 
@@ -38,13 +38,7 @@ pub struct HttpRequest {
 impl HttpRequest {
   /// Builds a `HttpRequest` from a JSON `Value` struct.
   pub fn from_json(request_json: &Value) -> Self {
-    let method_val = match request_json.get("method") {
-      Some(v) => match *v {
-        Value::String(ref s) => s.to_uppercase(),
-        _ => v.to_string().to_uppercase()
-      },
-      None => "GET".to_string()
-    };
+    let method_val = method_from_json(request_json);
     let path_val = match request_json.get("path") {
       Some(v) => match *v {
         Value::String(ref s) => s.clone(),
@@ -71,7 +65,7 @@ impl HttpRequest {
   /// Converts this `HttpRequest` to a `Value` struct.
   pub fn to_json(&self) -> Value {
     let mut json = json!({
-      "method": Value::String(self.method.to_uppercase()),
+      "method": Value::String(normalise_method(&self.method)),
       "path": Value::String(self.path.clone())
     });
     {
@@ -179,14 +173,15 @@ pub(crate) fn body_from_json(json: &Value, attr_name: &str, headers: &Option<Has
                 warn!("Body has no content type set, will default to any headers or metadata");
                 match headers {
                   Some(ref h) => match h.iter().find(|kv| kv.0.to_lowercase() == "content-type") {
-                    Some((_, v)) => {
-                      match ContentType::parse(v[0].as_str()) {
+                    Some((_, v)) => match v.first() {
+                      Some(value) => match ContentType::parse(value.as_str()) {
                         Ok(v) => Some(v),
                         Err(err) => {
-                          warn!("Failed to parse body content type '{}' - {}", v[0], err);
+                          warn!("Failed to parse body content type '{}' - {}", value, err);
                           None
                         }
-                      }
+                      },
+                      None => None
                     },
                     None => None
                   },
@@ -329,20 +324,18 @@ impl Hash for HttpResponse {
 }
 
 impl HttpResponse {
-  /// Build an `HttpResponse` from a JSON `Value` struct.
-  pub fn from_json(response: &Value) -> Self {
-    let status_val = match response.get("status") {
-      Some(v) => v.as_u64().unwrap() as u16,
-      None => 200
-    };
+  /// Build an `HttpResponse` from a JSON `Value` struct. Returns an error if the status is not
+  /// a valid HTTP status code.
+  pub fn from_json(response: &Value) -> anyhow::Result<Self> {
+    let status_val = status_from_json(response)?;
     let headers = headers_from_json(response);
-    HttpResponse {
+    Ok(HttpResponse {
       status: status_val,
       headers: headers.clone(),
       body: body_from_json(response, "body", &headers),
       matching_rules:  matchingrules::matchers_from_json(response, &None),
       generators:  generators::generators_from_json(response)
-    }
+    })
   }
 
   /// Converts this response to a `Value` struct.
@@ -0,0 +1,151 @@
+//! V4 specification models - Message parts for SynchronousMessages
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+
+use maplit::*;
+use serde_json::{json, Value};
+
+use pact_models::{OptionalBody, PactSpecification};
+use pact_models::content_types::ContentType;
+
+use crate::models::{detect_content_type_from_string, generators, matchingrules};
+use crate::models::generators::{Generator, GeneratorCategory, Generators};
+use crate::models::json_utils::hash_json;
+use crate::models::matchingrules::MatchingRules;
+use crate::models::v4::metadata_to_headers;
+use crate::models::v4::http_parts::body_from_json;
+
+/// Contents of a message that is part of a synchronous message interaction (i.e. the request
+/// message, or one of the response messages).
+#[derive(Debug, Clone, Eq)]
+pub struct MessageContents {
+  /// The contents of the message
+  pub contents: OptionalBody,
+  /// Metadata associated with this message.
+  pub metadata: HashMap<String, Value>,
+  /// Matching rules
+  pub matching_rules: MatchingRules,
+  /// Generators
+  pub generators: Generators
+}
+
+impl MessageContents {
+  /// Builds the message contents from a JSON `Value` struct.
+  pub fn from_json(json: &Value) -> MessageContents {
+    let metadata = match json.get("metadata") {
+      Some(Value::Object(values)) => values.iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect(),
+      _ => hashmap!{}
+    };
+    let as_headers = metadata_to_headers(&metadata);
+    MessageContents {
+      contents: body_from_json(json, "contents", &as_headers),
+      metadata,
+      matching_rules: matchingrules::matchers_from_json(json, &None),
+      generators: generators::generators_from_json(json)
+    }
+  }
+
+  /// Converts the message contents to a `Value` struct.
+  pub fn to_json(&self) -> Value {
+    let mut json = json!({});
+
+    if let Value::Object(body) = self.contents.to_v4_json() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("contents".to_string(), Value::Object(body));
+    }
+
+    if !self.metadata.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("metadata".to_string(), Value::Object(
+        self.metadata.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+      ));
+    }
+
+    if self.matching_rules.is_not_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("matchingRules".to_string(), matchingrules::matchers_to_json(
+        &self.matching_rules, &PactSpecification::V4));
+    }
+
+    if self.generators.is_not_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("generators".to_string(), generators::generators_to_json(
+        &self.generators, &PactSpecification::V4));
+    }
+
+    json
+  }
+
+  /// Determine the content type of the message. If the body does not have a content type, the
+  /// `contentType` metadata value will be used. Otherwise, the body will be inspected.
+  pub fn content_type(&self) -> Option<ContentType> {
+    if self.contents.has_content_type() {
+      self.contents.content_type()
+    } else {
+      let from_metadata = self.metadata.iter().find(|(k, _)| {
+        let key = k.to_ascii_lowercase();
+        key == "contenttype" || key == "content-type"
+      }).and_then(|(_, v)| ContentType::parse(v.as_str().unwrap_or_default()).ok());
+      from_metadata.or_else(|| match self.contents {
+        OptionalBody::Present(_, _) => detect_content_type_from_string(&self.contents.str_value().to_string()),
+        _ => None
+      })
+    }
+  }
+
+  /// Builds a map of generators from the generators and matching rules
+  pub fn build_generators(&self, category: &GeneratorCategory) -> HashMap<String, Generator> {
+    let mut generators = hashmap!{};
+    if let Some(generators_for_category) = self.generators.categories.get(category) {
+      for (path, generator) in generators_for_category {
+        generators.insert(path.clone(), generator.clone());
+      }
+    }
+    if let Some(rules) = self.matching_rules.rules_for_category(category.clone().into()) {
+      for (path, generator) in rules.generators() {
+        generators.insert(path.clone(), generator.clone());
+      }
+    }
+    generators
+  }
+}
+
+impl Default for MessageContents {
+  fn default() -> Self {
+    MessageContents {
+      contents: OptionalBody::Missing,
+      metadata: Default::default(),
+      matching_rules: Default::default(),
+      generators: Default::default()
+    }
+  }
+}
+
+impl PartialEq for MessageContents {
+  fn eq(&self, other: &Self) -> bool {
+    self.contents == other.contents && self.metadata == other.metadata &&
+      self.matching_rules == other.matching_rules && self.generators == other.generators
+  }
+}
+
+impl Hash for MessageContents {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.contents.hash(state);
+    for (k, v) in &self.metadata {
+      k.hash(state);
+      hash_json(v, state);
+    }
+    self.matching_rules.hash(state);
+    self.generators.hash(state);
+  }
+}
+
+impl Display for MessageContents {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    write!(f, "Message Contents ( contents: {}, metadata: {:?} )", self.contents, self.metadata)
+  }
+}
@@ -2,39 +2,42 @@
 
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap};
-use std::collections::hash_map::DefaultHasher;
 use std::fmt;
 use std::fmt::{Debug, Display};
 use std::hash::{Hash, Hasher};
 use std::path::Path;
 use std::string::ToString;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::{anyhow, Context as _};
+use anyhow::anyhow;
 use itertools::EitherOrBoth::{Both, Left, Right};
 use itertools::Itertools;
 use log::*;
 use maplit::*;
 use nom::lib::std::fmt::Formatter;
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 
 use pact_models::{Consumer, OptionalBody, PactSpecification, Provider};
 use pact_models::content_types::ContentType;
 
+use crate::error::PactError;
 use crate::models::{
   detect_content_type_from_bytes,
   generators,
   HttpPart,
   Interaction,
+  interaction_error,
   matchingrules,
   Pact,
   PACT_RUST_VERSION,
   provider_states,
+  read_json_with_lock,
   ReadWritePact,
   RequestResponseInteraction,
   RequestResponsePact
 };
-use crate::models::file_utils::with_read_lock;
 use crate::models::generators::{Generators, generators_to_json};
 use crate::models::json_utils::{hash_json, json_to_string};
 use crate::models::matchingrules::{matchers_to_json, MatchingRules};
@@ -42,6 +45,7 @@ use crate::models::message::Message;
 use crate::models::message_pact::MessagePact;
 use crate::models::provider_states::ProviderState;
 use crate::models::v4::http_parts::{body_from_json, HttpRequest, HttpResponse};
+use crate::models::v4::message_parts::MessageContents;
 
 /// V4 Interaction Type
 #[derive(Debug, Clone)]
@@ -84,6 +88,47 @@ impl V4InteractionType {
 }
 
 pub mod http_parts;
+pub mod message_parts;
+pub mod websocket;
+
+/// Markup added to an interaction, i.e. by a protocol plugin, which describes the
+/// interaction so it can be displayed (i.e. in the Pact Broker)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InteractionMarkup {
+  /// The markup
+  pub markup: String,
+  /// Type of markup (`COMMON_MARK` or `HTML`)
+  pub markup_type: String
+}
+
+impl InteractionMarkup {
+  /// Loads the interaction markup from the `interactionMarkup` JSON attribute
+  pub fn from_json(json: &Value) -> InteractionMarkup {
+    match json {
+      Value::Object(values) => InteractionMarkup {
+        markup: values.get("markup").map(json_to_string).unwrap_or_default(),
+        markup_type: values.get("markupType").map(json_to_string).unwrap_or_default()
+      },
+      _ => {
+        warn!("Interaction markup must be a JSON Object, but received {}. Ignoring", json);
+        Default::default()
+      }
+    }
+  }
+
+  /// If there is no markup
+  pub fn is_empty(&self) -> bool {
+    self.markup.is_empty()
+  }
+
+  /// Converts the interaction markup to the JSON form used in the pact file
+  pub fn to_json(&self) -> Value {
+    json!({
+      "markup": self.markup,
+      "markupType": self.markup_type
+    })
+  }
+}
 
 /// V4 Interaction trait
 pub trait V4Interaction: Interaction + Send + Sync {
@@ -104,6 +149,49 @@ pub trait V4Interaction: Interaction + Send + Sync {
 
   /// Mutable access to the annotations and comments associated with this interaction
   fn comments_mut(&mut self) -> &mut HashMap<String, Value>;
+
+  /// Adds a text comment to this interaction. Text comments are stored as an array under the
+  /// `text` key of the comments.
+  fn add_text_comment(&mut self, comment: &str) {
+    let comments = self.comments_mut();
+    match comments.get_mut("text") {
+      Some(Value::Array(text)) => text.push(json!(comment)),
+      Some(text) => *text = json!([text.clone(), comment]),
+      None => {
+        comments.insert("text".to_string(), json!([comment]));
+      }
+    }
+  }
+
+  /// Sets the name of the test that generated this interaction. This is stored under the
+  /// `testname` key of the comments.
+  fn set_test_name(&mut self, name: &str) {
+    self.comments_mut().insert("testname".to_string(), json!(name));
+  }
+
+  /// Configuration of the plugins used to create this interaction, keyed by plugin name
+  fn plugin_config(&self) -> HashMap<String, Value>;
+
+  /// Mutable access to the plugin configuration of this interaction
+  fn plugin_config_mut(&mut self) -> &mut HashMap<String, Value>;
+
+  /// Markup used to display this interaction
+  fn interaction_markup(&self) -> InteractionMarkup;
+
+  /// Mutable access to the markup used to display this interaction
+  fn interaction_markup_mut(&mut self) -> &mut InteractionMarkup;
+
+  /// Transport used to verify this interaction against the provider
+  fn transport(&self) -> Option<String>;
+
+  /// Sets the transport used to verify this interaction against the provider
+  fn set_transport(&mut self, transport: Option<String>);
+
+  /// If this interaction is pending. Failures of pending interactions do not fail the verification.
+  fn pending(&self) -> bool;
+
+  /// Sets if this interaction is pending
+  fn set_pending(&mut self, pending: bool);
 }
 
 // impl Debug for dyn V4Interaction {
@@ -124,6 +212,8 @@ impl Display for dyn V4Interaction {
       std::fmt::Display::fmt(&i, f)
     } else if let Some(i) = self.as_v4_async_message() {
       std::fmt::Display::fmt(&i, f)
+    } else if let Some(i) = self.as_v4_sync_message() {
+      std::fmt::Display::fmt(&i, f)
     } else {
       Err(fmt::Error)
     }
@@ -136,6 +226,8 @@ impl Clone for Box<dyn V4Interaction> {
       Box::new(http)
     } else if let Some(message) = self.as_v4_async_message() {
       Box::new(message)
+    } else if let Some(message) = self.as_v4_sync_message() {
+      Box::new(message)
     } else {
       panic!("Internal Error - Tried to clone an interaction that was not valid")
     }
@@ -159,20 +251,29 @@ pub struct SynchronousHttp {
   /// Response of the interaction
   pub response: HttpResponse,
   /// Annotations and comments associated with this interaction
-  pub comments: HashMap<String, Value>
+  pub comments: HashMap<String, Value>,
+  /// Maximum time the provider should take to respond to the request
+  pub timeout: Option<Duration>,
+  /// Configuration of the plugins used to create this interaction, keyed by plugin name
+  pub plugin_config: HashMap<String, Value>,
+  /// Markup used to display this interaction
+  pub interaction_markup: InteractionMarkup,
+  /// Transport used to verify this interaction against the provider (i.e. `http` or `grpc`)
+  pub transport: Option<String>,
+  /// If this interaction is pending (i.e. a new interaction the provider does not support yet)
+  pub pending: bool
 }
 
 impl SynchronousHttp {
   fn calc_hash(&self) -> String {
-    let mut s = DefaultHasher::new();
-    self.hash(&mut s);
-    format!("{:x}", s.finish())
+    interaction_key(&V4InteractionType::Synchronous_HTTP, &self.description, &self.provider_states)
   }
 
-  /// Creates a new version with a calculated key
+  /// Creates a new version with the key set. The key is only calculated if the interaction does
+  /// not already have one (i.e. one assigned by the user).
   pub fn with_key(&self) -> SynchronousHttp {
     SynchronousHttp {
-      key: Some(self.calc_hash()),
+      key: Some(self.key.clone().unwrap_or_else(|| self.calc_hash())),
       .. self.clone()
     }
   }
@@ -200,6 +301,32 @@ impl V4Interaction for SynchronousHttp {
         .map(|(k, v)| (k.clone(), v.clone())).collect());
     }
 
+    if let Some(timeout) = self.timeout {
+      let map = json.as_object_mut().unwrap();
+      map.insert("timeout".to_string(), json!(timeout.as_millis() as u64));
+    }
+
+    if !self.plugin_config.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("pluginConfiguration".to_string(), self.plugin_config.iter()
+        .map(|(k, v)| (k.clone(), v.clone())).collect());
+    }
+
+    if !self.interaction_markup.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("interactionMarkup".to_string(), self.interaction_markup.to_json());
+    }
+
+    if let Some(transport) = &self.transport {
+      let map = json.as_object_mut().unwrap();
+      map.insert("transport".to_string(), json!(transport));
+    }
+
+    if self.pending {
+      let map = json.as_object_mut().unwrap();
+      map.insert("pending".to_string(), json!(true));
+    }
+
     json
   }
 
@@ -222,6 +349,38 @@ impl V4Interaction for SynchronousHttp {
   fn comments_mut(&mut self) -> &mut HashMap<String, Value> {
     &mut self.comments
   }
+
+  fn plugin_config(&self) -> HashMap<String, Value> {
+    self.plugin_config.clone()
+  }
+
+  fn plugin_config_mut(&mut self) -> &mut HashMap<String, Value> {
+    &mut self.plugin_config
+  }
+
+  fn interaction_markup(&self) -> InteractionMarkup {
+    self.interaction_markup.clone()
+  }
+
+  fn interaction_markup_mut(&mut self) -> &mut InteractionMarkup {
+    &mut self.interaction_markup
+  }
+
+  fn transport(&self) -> Option<String> {
+    self.transport.clone()
+  }
+
+  fn set_transport(&mut self, transport: Option<String>) {
+    self.transport = transport;
+  }
+
+  fn pending(&self) -> bool {
+    self.pending
+  }
+
+  fn set_pending(&mut self, pending: bool) {
+    self.pending = pending;
+  }
 }
 
 impl Interaction for SynchronousHttp {
@@ -287,6 +446,10 @@ impl Interaction for SynchronousHttp {
     None
   }
 
+  fn as_v4_sync_message(&self) -> Option<SynchronousMessages> {
+    None
+  }
+
   fn boxed(&self) -> Box<dyn Interaction + Send> {
     Box::new(self.clone())
   }
@@ -302,6 +465,10 @@ impl Interaction for SynchronousHttp {
   fn matching_rules(&self) -> Option<MatchingRules> {
     None
   }
+
+  fn timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
 }
 
 impl Default for SynchronousHttp {
@@ -313,7 +480,12 @@ impl Default for SynchronousHttp {
       provider_states: vec![],
       request: HttpRequest::default(),
       response: HttpResponse::default(),
-      comments: Default::default()
+      comments: Default::default(),
+      timeout: None,
+      plugin_config: Default::default(),
+      interaction_markup: Default::default(),
+      transport: None,
+      pending: false
     }
   }
 }
@@ -321,7 +493,8 @@ impl Default for SynchronousHttp {
 impl PartialEq for SynchronousHttp {
   fn eq(&self, other: &Self) -> bool {
     self.description == other.description && self.provider_states == other.provider_states &&
-      self.request == other.request && self.response == other.response
+      self.request == other.request && self.response == other.response && self.timeout == other.timeout &&
+      self.transport == other.transport && self.pending == other.pending
   }
 }
 
@@ -362,20 +535,29 @@ pub struct AsynchronousMessage {
   /// Generators
   pub generators: generators::Generators,
   /// Annotations and comments associated with this interaction
-  pub comments: HashMap<String, Value>
+  pub comments: HashMap<String, Value>,
+  /// Maximum time the provider should take to produce the message
+  pub timeout: Option<Duration>,
+  /// Configuration of the plugins used to create this interaction, keyed by plugin name
+  pub plugin_config: HashMap<String, Value>,
+  /// Markup used to display this interaction
+  pub interaction_markup: InteractionMarkup,
+  /// Transport used to verify this interaction against the provider (i.e. `http` or `grpc`)
+  pub transport: Option<String>,
+  /// If this interaction is pending (i.e. a new interaction the provider does not support yet)
+  pub pending: bool
 }
 
 impl AsynchronousMessage {
   fn calc_hash(&self) -> String {
-    let mut s = DefaultHasher::new();
-    self.hash(&mut s);
-    format!("{:x}", s.finish())
+    interaction_key(&V4InteractionType::Asynchronous_Messages, &self.description, &self.provider_states)
   }
 
-  /// Creates a new version with a calculated key
+  /// Creates a new version with the key set. The key is only calculated if the interaction does
+  /// not already have one (i.e. one assigned by the user).
   pub fn with_key(&self) -> AsynchronousMessage {
     AsynchronousMessage {
-      key: Some(self.calc_hash()),
+      key: Some(self.key.clone().unwrap_or_else(|| self.calc_hash())),
       .. self.clone()
     }
   }
@@ -423,6 +605,32 @@ impl V4Interaction for AsynchronousMessage {
         .map(|(k, v)| (k.clone(), v.clone())).collect());
     }
 
+    if let Some(timeout) = self.timeout {
+      let map = json.as_object_mut().unwrap();
+      map.insert("timeout".to_string(), json!(timeout.as_millis() as u64));
+    }
+
+    if !self.plugin_config.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("pluginConfiguration".to_string(), self.plugin_config.iter()
+        .map(|(k, v)| (k.clone(), v.clone())).collect());
+    }
+
+    if !self.interaction_markup.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("interactionMarkup".to_string(), self.interaction_markup.to_json());
+    }
+
+    if let Some(transport) = &self.transport {
+      let map = json.as_object_mut().unwrap();
+      map.insert("transport".to_string(), json!(transport));
+    }
+
+    if self.pending {
+      let map = json.as_object_mut().unwrap();
+      map.insert("pending".to_string(), json!(true));
+    }
+
     json
   }
 
@@ -445,6 +653,38 @@ impl V4Interaction for AsynchronousMessage {
   fn comments_mut(&mut self) -> &mut HashMap<String, Value> {
     &mut self.comments
   }
+
+  fn plugin_config(&self) -> HashMap<String, Value> {
+    self.plugin_config.clone()
+  }
+
+  fn plugin_config_mut(&mut self) -> &mut HashMap<String, Value> {
+    &mut self.plugin_config
+  }
+
+  fn interaction_markup(&self) -> InteractionMarkup {
+    self.interaction_markup.clone()
+  }
+
+  fn interaction_markup_mut(&mut self) -> &mut InteractionMarkup {
+    &mut self.interaction_markup
+  }
+
+  fn transport(&self) -> Option<String> {
+    self.transport.clone()
+  }
+
+  fn set_transport(&mut self, transport: Option<String>) {
+    self.transport = transport;
+  }
+
+  fn pending(&self) -> bool {
+    self.pending
+  }
+
+  fn set_pending(&mut self, pending: bool) {
+    self.pending = pending;
+  }
 }
 
 impl Interaction for AsynchronousMessage {
@@ -512,6 +752,10 @@ impl Interaction for AsynchronousMessage {
     Some(self.clone())
   }
 
+  fn as_v4_sync_message(&self) -> Option<SynchronousMessages> {
+    None
+  }
+
   fn boxed(&self) -> Box<dyn Interaction + Send> {
     Box::new(self.clone())
   }
@@ -527,6 +771,10 @@ impl Interaction for AsynchronousMessage {
   fn matching_rules(&self) -> Option<MatchingRules> {
     Some(self.matching_rules.clone())
   }
+
+  fn timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
 }
 
 impl Default for AsynchronousMessage {
@@ -540,7 +788,12 @@ impl Default for AsynchronousMessage {
       metadata: Default::default(),
       matching_rules: Default::default(),
       generators: Default::default(),
-      comments: Default::default()
+      comments: Default::default(),
+      timeout: None,
+      plugin_config: Default::default(),
+      interaction_markup: Default::default(),
+      transport: None,
+      pending: false
     }
   }
 }
@@ -550,7 +803,8 @@ impl PartialEq for AsynchronousMessage {
     self.description == other.description && self.provider_states == other.provider_states &&
       self.contents == other.contents && self.metadata == other.metadata &&
       self.matching_rules == other.matching_rules &&
-      self.generators == other.generators
+      self.generators == other.generators && self.timeout == other.timeout &&
+      self.transport == other.transport && self.pending == other.pending
   }
 }
 
@@ -604,6 +858,280 @@ impl HttpPart for AsynchronousMessage {
   }
 }
 
+/// Synchronous interactions as a request message to a sequence of response messages
+#[derive(Debug, Clone, Eq)]
+pub struct SynchronousMessages {
+  /// Interaction ID. This will only be set if the Pact file was fetched from a Pact Broker
+  pub id: Option<String>,
+  /// Unique key for this interaction
+  pub key: Option<String>,
+  /// A description for the interaction. Must be unique within the Pact file
+  pub description: String,
+  /// Optional provider state for the interaction.
+  /// See https://docs.pact.io/getting_started/provider_states for more info on provider states.
+  pub provider_states: Vec<ProviderState>,
+  /// Annotations and comments associated with this interaction
+  pub comments: HashMap<String, Value>,
+  /// Request message
+  pub request: MessageContents,
+  /// Response messages
+  pub response: Vec<MessageContents>,
+  /// Maximum time the provider should take to respond to the request message
+  pub timeout: Option<Duration>,
+  /// Configuration of the plugins used to create this interaction, keyed by plugin name
+  pub plugin_config: HashMap<String, Value>,
+  /// Markup used to display this interaction
+  pub interaction_markup: InteractionMarkup,
+  /// Transport used to verify this interaction against the provider (i.e. `http` or `grpc`)
+  pub transport: Option<String>,
+  /// If this interaction is pending (i.e. a new interaction the provider does not support yet)
+  pub pending: bool
+}
+
+impl SynchronousMessages {
+  fn calc_hash(&self) -> String {
+    interaction_key(&V4InteractionType::Synchronous_Messages, &self.description, &self.provider_states)
+  }
+
+  /// Creates a new version with the key set. The key is only calculated if the interaction does
+  /// not already have one (i.e. one assigned by the user).
+  pub fn with_key(&self) -> SynchronousMessages {
+    SynchronousMessages {
+      key: Some(self.key.clone().unwrap_or_else(|| self.calc_hash())),
+      .. self.clone()
+    }
+  }
+}
+
+impl V4Interaction for SynchronousMessages {
+  fn to_json(&self) -> Value {
+    let mut json = json!({
+      "type": V4InteractionType::Synchronous_Messages.to_string(),
+      "key": self.key.clone().unwrap_or_else(|| self.calc_hash()),
+      "description": self.description.clone(),
+      "request": self.request.to_json(),
+      "response": self.response.iter().map(|m| m.to_json()).collect_vec()
+    });
+
+    if !self.provider_states.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("providerStates".to_string(), Value::Array(
+        self.provider_states.iter().map(|p| p.to_json()).collect()));
+    }
+
+    if !self.comments.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("comments".to_string(), self.comments.iter()
+        .map(|(k, v)| (k.clone(), v.clone())).collect());
+    }
+
+    if let Some(timeout) = self.timeout {
+      let map = json.as_object_mut().unwrap();
+      map.insert("timeout".to_string(), json!(timeout.as_millis() as u64));
+    }
+
+    if !self.plugin_config.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("pluginConfiguration".to_string(), self.plugin_config.iter()
+        .map(|(k, v)| (k.clone(), v.clone())).collect());
+    }
+
+    if !self.interaction_markup.is_empty() {
+      let map = json.as_object_mut().unwrap();
+      map.insert("interactionMarkup".to_string(), self.interaction_markup.to_json());
+    }
+
+    if let Some(transport) = &self.transport {
+      let map = json.as_object_mut().unwrap();
+      map.insert("transport".to_string(), json!(transport));
+    }
+
+    if self.pending {
+      let map = json.as_object_mut().unwrap();
+      map.insert("pending".to_string(), json!(true));
+    }
+
+    json
+  }
+
+  fn to_super(&self) -> &dyn Interaction {
+    self
+  }
+
+  fn key(&self) -> Option<String> {
+    self.key.clone()
+  }
+
+  fn boxed_v4(&self) -> Box<dyn V4Interaction> {
+    Box::new(self.clone())
+  }
+
+  fn comments(&self) -> HashMap<String, Value> {
+    self.comments.clone()
+  }
+
+  fn comments_mut(&mut self) -> &mut HashMap<String, Value> {
+    &mut self.comments
+  }
+
+  fn plugin_config(&self) -> HashMap<String, Value> {
+    self.plugin_config.clone()
+  }
+
+  fn plugin_config_mut(&mut self) -> &mut HashMap<String, Value> {
+    &mut self.plugin_config
+  }
+
+  fn interaction_markup(&self) -> InteractionMarkup {
+    self.interaction_markup.clone()
+  }
+
+  fn interaction_markup_mut(&mut self) -> &mut InteractionMarkup {
+    &mut self.interaction_markup
+  }
+
+  fn transport(&self) -> Option<String> {
+    self.transport.clone()
+  }
+
+  fn set_transport(&mut self, transport: Option<String>) {
+    self.transport = transport;
+  }
+
+  fn pending(&self) -> bool {
+    self.pending
+  }
+
+  fn set_pending(&mut self, pending: bool) {
+    self.pending = pending;
+  }
+}
+
+impl Interaction for SynchronousMessages {
+  fn type_of(&self) -> String {
+    format!("V4 {}", V4InteractionType::Synchronous_Messages)
+  }
+
+  fn is_request_response(&self) -> bool {
+    false
+  }
+
+  fn as_request_response(&self) -> Option<RequestResponseInteraction> {
+    None
+  }
+
+  fn is_message(&self) -> bool {
+    false
+  }
+
+  fn as_message(&self) -> Option<Message> {
+    None
+  }
+
+  fn id(&self) -> Option<String> {
+    self.id.clone()
+  }
+
+  fn description(&self) -> String {
+    self.description.clone()
+  }
+
+  fn provider_states(&self) -> Vec<ProviderState> {
+    self.provider_states.clone()
+  }
+
+  fn contents(&self) -> OptionalBody {
+    self.response.first().map(|m| m.contents.clone()).unwrap_or(OptionalBody::Missing)
+  }
+
+  fn content_type(&self) -> Option<ContentType> {
+    self.response.first().and_then(|m| m.content_type())
+  }
+
+  fn is_v4(&self) -> bool {
+    true
+  }
+
+  fn as_v4(&self) -> Option<Box<dyn V4Interaction>> {
+    Some(self.boxed_v4())
+  }
+
+  fn as_v4_http(&self) -> Option<SynchronousHttp> {
+    None
+  }
+
+  fn as_v4_async_message(&self) -> Option<AsynchronousMessage> {
+    None
+  }
+
+  fn as_v4_sync_message(&self) -> Option<SynchronousMessages> {
+    Some(self.clone())
+  }
+
+  fn boxed(&self) -> Box<dyn Interaction + Send> {
+    Box::new(self.clone())
+  }
+
+  fn arced(&self) -> Arc<dyn Interaction + Send> {
+    Arc::new(self.clone())
+  }
+
+  fn thread_safe(&self) -> Arc<Mutex<dyn Interaction + Send + Sync>> {
+    Arc::new(Mutex::new(self.clone()))
+  }
+
+  fn matching_rules(&self) -> Option<MatchingRules> {
+    None
+  }
+
+  fn timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
+}
+
+impl Default for SynchronousMessages {
+  fn default() -> Self {
+    SynchronousMessages {
+      id: None,
+      key: None,
+      description: "Synchronous/Message Interaction".to_string(),
+      provider_states: vec![],
+      comments: Default::default(),
+      request: Default::default(),
+      response: vec![],
+      timeout: None,
+      plugin_config: Default::default(),
+      interaction_markup: Default::default(),
+      transport: None,
+      pending: false
+    }
+  }
+}
+
+impl PartialEq for SynchronousMessages {
+  fn eq(&self, other: &Self) -> bool {
+    self.description == other.description && self.provider_states == other.provider_states &&
+      self.request == other.request && self.response == other.response &&
+      self.timeout == other.timeout && self.transport == other.transport && self.pending == other.pending
+  }
+}
+
+impl Hash for SynchronousMessages {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.description.hash(state);
+    self.provider_states.hash(state);
+    self.request.hash(state);
+    self.response.hash(state);
+  }
+}
+
+impl Display for SynchronousMessages {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    write!(f, "V4 Synchronous Messages Interaction ( id: {:?}, description: \"{}\", provider_states: {:?}, request: {}, response: {:?} )",
+           self.id, self.description, self.provider_states, self.request, self.response)
+  }
+}
+
 fn calc_content_type(body: &OptionalBody, headers: &Option<HashMap<String, Vec<String>>>) -> Option<ContentType> {
   body.content_type()
     .or_else(|| headers.as_ref().map(|h| {
@@ -649,6 +1177,23 @@ impl V4Pact {
     md_map.insert("pactRust".to_string(), json!({"version" : PACT_RUST_VERSION.unwrap_or("unknown")}));
     Value::Object(md_map)
   }
+
+  /// Records a plugin that is required to verify this pact in the metadata, replacing any entry
+  /// for the plugin with the same name
+  pub fn add_plugin(&mut self, name: &str, version: &str, configuration: Option<Value>) {
+    let mut plugin = json!({ "name": name, "version": version });
+    if let Some(configuration) = configuration {
+      plugin["configuration"] = configuration;
+    }
+    let plugins = self.metadata.entry("plugins".to_string()).or_insert_with(|| json!([]));
+    match plugins {
+      Value::Array(entries) => {
+        entries.retain(|entry| entry.get("name").and_then(|n| n.as_str()) != Some(name));
+        entries.push(plugin);
+      }
+      _ => *plugins = json!([plugin])
+    }
+  }
 }
 
 impl Pact for V4Pact {
@@ -686,7 +1231,18 @@ impl Pact for V4Pact {
   }
 
   fn as_request_response_pact(&self) -> Result<RequestResponsePact, String> {
-    unimplemented!()
+    let interactions = self.interactions.iter()
+      .map(|i| i.as_request_response().ok_or_else(|| format!(
+        "Interaction '{}' is not a HTTP interaction, and can not be converted to a request/response interaction",
+        i.description())))
+      .collect::<Result<Vec<RequestResponseInteraction>, String>>()?;
+    Ok(RequestResponsePact {
+      consumer: self.consumer.clone(),
+      provider: self.provider.clone(),
+      interactions,
+      metadata: self.metadata(),
+      specification_version: PactSpecification::V3
+    })
   }
 
   fn as_message_pact(&self) -> Result<MessagePact, String> {
@@ -740,10 +1296,8 @@ impl Default for V4Pact {
 }
 
 impl ReadWritePact for V4Pact {
-  fn read_pact(path: &Path) -> anyhow::Result<V4Pact> {
-    let json = with_read_lock(path, 3, &mut |f| {
-      serde_json::from_reader::<_, Value>(f).context("Failed to parse Pact JSON")
-    })?;
+  fn read_pact(path: &Path) -> Result<V4Pact, PactError> {
+    let json = read_json_with_lock(path)?;
     let metadata = meta_data_from_json(&json);
     let consumer = match json.get("consumer") {
       Some(v) => Consumer::from_json(v),
@@ -753,20 +1307,20 @@ impl ReadWritePact for V4Pact {
       Some(v) => Provider::from_json(v),
       None => Provider { name: "provider".into() }
     };
+    let source = path.to_string_lossy();
+    let interactions = interactions_from_json(&json, &source);
+    warn_duplicate_keys(&interactions, &source);
     Ok(V4Pact {
       consumer,
       provider,
-      interactions: interactions_from_json(&json, &*path.to_string_lossy()),
+      interactions,
       metadata
     })
   }
 
-  fn merge(&self, other: &dyn Pact) -> anyhow::Result<Box<dyn Pact>> {
+  fn merge(&self, other: &dyn Pact) -> Result<Box<dyn Pact>, PactError> {
     if self.consumer.name == other.consumer().name && self.provider.name == other.provider().name {
-      Ok(Box::new(V4Pact {
-        consumer: self.consumer.clone(),
-        provider: self.provider.clone(),
-        interactions: self.interactions.iter()
+      let interactions = self.interactions.iter()
           .merge_join_by(other.interactions().iter().map(|i| i.as_v4().unwrap()), |a, b| {
             match (a.key(), b.key()) {
               (Some(key_a), Some(key_b)) => Ord::cmp(&key_a, &key_b),
@@ -795,11 +1349,25 @@ impl ReadWritePact for V4Pact {
               Both(i, _) => i.clone()
             }
           })
-          .collect(),
-        metadata: self.metadata.clone()
-      }))
+          .collect::<Vec<_>>();
+      let duplicates = duplicate_keys(&interactions);
+      if duplicates.is_empty() {
+        Ok(Box::new(V4Pact {
+          consumer: self.consumer.clone(),
+          provider: self.provider.clone(),
+          interactions,
+          metadata: self.metadata.clone()
+        }))
+      } else {
+        Err(PactError::MergeConflict {
+          conflicts: duplicates.iter()
+            .map(|(index, other, key)| format!("Interaction '{}': has the same key '{}' as interaction '{}'",
+              interactions[*index].description(), key, interactions[*other].description()))
+            .collect()
+        })
+      }
     } else {
-      Err(anyhow!("Unable to merge pacts, as they have different consumers or providers"))
+      Err(PactError::MergeError("they have different consumers or providers".to_string()))
     }
   }
 
@@ -808,8 +1376,56 @@ impl ReadWritePact for V4Pact {
   }
 }
 
+/// Calculates the key of a V4 interaction. Keys only depend on what identifies the interaction in
+/// the pact (the interaction type, description and provider states), so the key does not change
+/// when other details of the interaction (like the request or response) are changed.
+///
+/// The key is the first 16 bytes, as lowercase hex, of the SHA-256 digest of the compact JSON
+/// `{"description":...,"providerStates":[...],"type":...}`, where the provider states are in their
+/// pact JSON form and all object keys are sorted. Interactions can also be given their own key,
+/// which is used instead of the calculated one.
+pub fn interaction_key(interaction_type: &V4InteractionType, description: &str, provider_states: &[ProviderState]) -> String {
+  let json = json!({
+    "type": interaction_type.to_string(),
+    "description": description,
+    "providerStates": provider_states.iter().map(|state| state.to_json()).collect::<Vec<Value>>()
+  });
+  hex::encode(&Sha256::digest(json.to_string().as_bytes())[..16])
+}
+
+/// The key of the interaction, calculating it if the interaction does not have one
+fn effective_key(interaction: &dyn V4Interaction) -> String {
+  interaction.key().unwrap_or_else(|| json_to_string(&interaction.to_json()["key"]))
+}
+
+/// Returns the interactions that have the same key as an earlier interaction, as pairs of the
+/// index of the interaction and the index of the earlier one
+pub fn duplicate_keys(interactions: &[Box<dyn V4Interaction>]) -> Vec<(usize, usize, String)> {
+  let mut keys: HashMap<String, usize> = HashMap::new();
+  let mut duplicates = vec![];
+  for (index, interaction) in interactions.iter().enumerate() {
+    let key = effective_key(interaction.as_ref());
+    match keys.get(&key) {
+      Some(other) => duplicates.push((index, *other, key)),
+      None => {
+        keys.insert(key, index);
+      }
+    }
+  }
+  duplicates
+}
+
+/// Logs a warning for each interaction that has the same key as an earlier one. Existing pacts
+/// can have interactions with the same key, so they are still loaded, but merging them will fail.
+fn warn_duplicate_keys(interactions: &[Box<dyn V4Interaction>], source: &str) {
+  for (index, other, key) in duplicate_keys(interactions) {
+    warn!("Interaction '{}' ($.interactions[{}]) in the pact from '{}' has the same key '{}' as interaction '{}'",
+      interactions[index].description(), index, source, key, interactions[other].description());
+  }
+}
+
 /// Creates a V4 Pact from the provided JSON struct
-pub fn from_json(source: &str, pact_json: &Value) -> anyhow::Result<Box<dyn Pact>> {
+pub fn from_json(source: &str, pact_json: &Value) -> Result<Box<dyn Pact>, PactError> {
   let metadata = meta_data_from_json(pact_json);
   let consumer = match pact_json.get("consumer") {
     Some(v) => Consumer::from_json(v),
@@ -819,10 +1435,12 @@ pub fn from_json(source: &str, pact_json: &Value) -> anyhow::Result<Box<dyn Pact
     Some(v) => Provider::from_json(v),
     None => Provider { name: "provider".into() }
   };
+  let interactions = interactions_from_json(pact_json, source);
+  warn_duplicate_keys(&interactions, source);
   Ok(Box::new(V4Pact {
     consumer,
     provider,
-    interactions: interactions_from_json(pact_json, source),
+    interactions,
     metadata
   }))
 }
@@ -866,19 +1484,61 @@ pub fn interaction_from_json(source: &str, index: usize, ijson: &Value) -> anyho
           },
           None => Default::default()
         };
-        let provider_states = provider_states::ProviderState::from_json(ijson);
+        let timeout = match ijson.get("timeout") {
+          Some(v) => match v.as_u64() {
+            Some(millis) => Some(Duration::from_millis(millis)),
+            None => {
+              warn!("Interaction timeout must be a number of milliseconds, but received {}. Ignoring", v);
+              None
+            }
+          },
+          None => None
+        };
+        let plugin_config = match ijson.get("pluginConfiguration") {
+          Some(v) => match v {
+            Value::Object(map) => map.iter()
+              .map(|(k, v)| (k.clone(), v.clone())).collect(),
+            _ => {
+              warn!("Interaction plugin configuration must be a JSON Object, but received {}. Ignoring", v);
+              Default::default()
+            }
+          },
+          None => Default::default()
+        };
+        let interaction_markup = ijson.get("interactionMarkup")
+          .map(InteractionMarkup::from_json)
+          .unwrap_or_default();
+        let transport = ijson.get("transport").map(json_to_string);
+        let pending = ijson.get("pending").and_then(Value::as_bool).unwrap_or_default();
+        let provider_states = provider_states::ProviderState::from_json(ijson)
+          .map_err(|err| {
+            let err = interaction_error(index, &description, err);
+            warn!("{}. It will be ignored. Source: {}", err, source);
+            err
+          })?;
         match i_type {
           V4InteractionType::Synchronous_HTTP => {
             let request = ijson.get("request").cloned().unwrap_or_default();
             let response = ijson.get("response").cloned().unwrap_or_default();
+            let response = HttpResponse::from_json(&response)
+              .map_err(|err| {
+                let err = interaction_error(index, &description, anyhow!("response.{}", err));
+                warn!("{}. It will be ignored. Source: {}", err, source);
+                err
+              })?;
             Ok(Box::new(SynchronousHttp {
               id,
               key,
               description,
               provider_states,
               request: HttpRequest::from_json(&request),
-              response: HttpResponse::from_json(&response),
-              comments
+              response,
+              comments,
+              timeout,
+              plugin_config,
+              interaction_markup,
+              transport,
+              pending
             }))
           }
           V4InteractionType::Asynchronous_Messages => {
@@ -898,12 +1558,32 @@ pub fn interaction_from_json(source: &str, index: usize, ijson: &Value) -> anyho
               contents: body_from_json(ijson, "contents", &as_headers),
               matching_rules: matchingrules::matchers_from_json(ijson, &None),
               generators: generators::generators_from_json(ijson),
-              comments
+              comments,
+              timeout,
+              plugin_config,
+              interaction_markup,
+              transport,
+              pending
             }))
           }
           V4InteractionType::Synchronous_Messages => {
-            warn!("Interaction type '{}' is currently unimplemented. It will be ignored. Source: {}", i_type, source);
-            Err(anyhow!("Interaction type '{}' is currently unimplemented. It will be ignored. Source: {}", i_type, source))
+            Ok(Box::new(SynchronousMessages {
+              id,
+              key,
+              description,
+              provider_states,
+              comments,
+              request: ijson.get("request").map(MessageContents::from_json).unwrap_or_default(),
+              response: match ijson.get("response") {
+                Some(Value::Array(messages)) => messages.iter().map(MessageContents::from_json).collect(),
+                _ => vec![]
+              },
+              timeout,
+              plugin_config,
+              interaction_markup,
+              transport,
+              pending
+            }))
           }
         }
       },
@@ -919,7 +1599,7 @@ pub fn interaction_from_json(source: &str, index: usize, ijson: &Value) -> anyho
   }
 }
 
-fn metadata_to_headers(metadata: &HashMap<String, Value>) -> Option<HashMap<String, Vec<String>>> {
+pub(crate) fn metadata_to_headers(metadata: &HashMap<String, Value>) -> Option<HashMap<String, Vec<String>>> {
   if let Some(content_type) = metadata.get("contentType") {
     Some(hashmap! {
       "Content-Type".to_string() => vec![ json_to_string(content_type) ]
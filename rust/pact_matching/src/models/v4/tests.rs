@@ -3,7 +3,9 @@ use std::collections::hash_map::DefaultHasher;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io::Read;
+use std::time::Duration;
 
+use bytes::Bytes;
 use expectest::prelude::*;
 use maplit::*;
 use serde_json::json;
@@ -11,12 +13,15 @@ use serde_json::json;
 use pact_models::{Consumer, OptionalBody, Provider};
 use pact_models::content_types::JSON;
 
-use crate::models::{headers_from_json, Interaction, PACT_RUST_VERSION, PactSpecification, ReadWritePact, write_pact, Pact};
+use crate::models::{headers_from_json, Interaction, MergeStrategy, PACT_RUST_VERSION, PactSpecification, ReadWritePact, write_pact, Pact};
+use crate::models::generators::{Generator, GeneratorCategory};
 use crate::models::matchingrules::MatchingRule;
 use crate::models::provider_states::ProviderState;
-use crate::models::v4::{AsynchronousMessage, from_json, interaction_from_json, SynchronousHttp, V4Pact};
+use crate::error::PactError;
+use crate::models::v4::{AsynchronousMessage, from_json, interaction_from_json, interaction_key, InteractionMarkup, SynchronousHttp, V4Interaction, V4InteractionType, V4Pact};
 use crate::models::v4::http_parts::{HttpRequest, HttpResponse};
 use crate::models::v4::http_parts::body_from_json;
+use crate::models::v4::message_parts::MessageContents;
 
 #[test]
 fn synchronous_http_request_from_json_defaults_to_get() {
@@ -51,7 +56,7 @@ fn synchronous_http_response_from_json_defaults_to_status_200() {
         "headers": {}
     }
    "#).unwrap();
-  let response = HttpResponse::from_json(&response_json);
+  let response = HttpResponse::from_json(&response_json).unwrap();
   assert_eq!(response.status, 200);
 }
 
@@ -314,6 +319,24 @@ fn http_request_to_json_with_a_query() {
   );
 }
 
+#[test]
+fn http_request_query_generators_round_trip_through_json() {
+  let json = json!({
+    "method": "GET",
+    "path": "/users",
+    "query": { "requestId": ["1234"] },
+    "generators": {
+      "query": {
+        "requestId": { "type": "Uuid" }
+      }
+    }
+  });
+  let request = HttpRequest::from_json(&json);
+  expect!(request.generators.categories.get(&GeneratorCategory::QUERY).cloned())
+    .to(be_some().value(hashmap!{ s!("requestId") => Generator::Uuid }));
+  expect!(request.to_json()).to(be_equal_to(json));
+}
+
 #[test]
 fn http_request_to_json_with_headers() {
   let request = HttpRequest { headers: Some(hashmap!{
@@ -430,6 +453,158 @@ fn interaction_from_json_sets_the_id_if_loaded_from_broker() {
   expect!(interaction.id()).to(be_some().value("123456789".to_string()));
 }
 
+#[test]
+fn interaction_from_json_sets_the_timeout() {
+  let json = json!({
+    "type": "Synchronous/HTTP",
+    "description": "Test Interaction",
+    "timeout": 1500,
+    "request": {
+      "method": "GET",
+      "path": "/"
+    },
+    "response": {
+      "status": 200
+    }
+  });
+  let interaction = interaction_from_json("", 0, &json).unwrap();
+  expect!(interaction.timeout()).to(be_some().value(Duration::from_millis(1500)));
+  expect!(interaction.to_json()["timeout"].clone()).to(be_equal_to(json!(1500)));
+
+  let json = json!({
+    "type": "Asynchronous/Messages",
+    "description": "Test Message",
+    "timeout": "1500"
+  });
+  let interaction = interaction_from_json("", 0, &json).unwrap();
+  expect!(interaction.timeout()).to(be_none());
+  expect!(interaction.to_json().get("timeout")).to(be_none());
+}
+
+#[test]
+fn interaction_from_json_sets_the_transport() {
+  let json = json!({
+    "type": "Synchronous/Messages",
+    "description": "Test Interaction",
+    "transport": "grpc"
+  });
+  let interaction = interaction_from_json("", 0, &json).unwrap();
+  expect!(interaction.transport()).to(be_some().value("grpc".to_string()));
+  expect!(interaction.to_json()["transport"].clone()).to(be_equal_to(json!("grpc")));
+
+  let json = json!({
+    "type": "Asynchronous/Messages",
+    "description": "Test Message"
+  });
+  let interaction = interaction_from_json("", 0, &json).unwrap();
+  expect!(interaction.transport()).to(be_none());
+  expect!(interaction.to_json().get("transport")).to(be_none());
+}
+
+#[test]
+fn interaction_from_json_sets_the_pending_flag() {
+  for interaction_type in &["Synchronous/HTTP", "Asynchronous/Messages", "Synchronous/Messages"] {
+    let json = json!({
+      "type": interaction_type,
+      "description": "Test Interaction",
+      "pending": true
+    });
+    let interaction = interaction_from_json("", 0, &json).unwrap();
+    expect!(interaction.pending()).to(be_true());
+    expect!(interaction.to_json()["pending"].clone()).to(be_equal_to(json!(true)));
+  }
+
+  let json = json!({
+    "type": "Synchronous/HTTP",
+    "description": "Test Interaction",
+    "pending": false
+  });
+  let mut interaction = interaction_from_json("", 0, &json).unwrap();
+  expect!(interaction.pending()).to(be_false());
+  expect!(interaction.to_json().get("pending")).to(be_none());
+  interaction.set_pending(true);
+  expect!(interaction.pending()).to(be_true());
+}
+
+#[test]
+fn interaction_from_json_sets_the_plugin_configuration_and_markup() {
+  let json = json!({
+    "type": "Asynchronous/Messages",
+    "description": "Test Message",
+    "contents": {
+      "content": "CgNiYXI=",
+      "contentType": "application/protobuf;message=Foo",
+      "encoded": "base64"
+    },
+    "pluginConfiguration": {
+      "protobuf": {
+        "descriptorKey": "d58838959e37498cddf51805bedf4dca",
+        "message": ".Foo"
+      }
+    },
+    "interactionMarkup": {
+      "markup": "```protobuf\nmessage Foo {\n    string bar = 1;\n}\n```\n",
+      "markupType": "COMMON_MARK"
+    }
+  });
+  let interaction = interaction_from_json("", 0, &json).unwrap();
+  expect!(interaction.plugin_config()).to(be_equal_to(hashmap!{
+    "protobuf".to_string() => json!({
+      "descriptorKey": "d58838959e37498cddf51805bedf4dca",
+      "message": ".Foo"
+    })
+  }));
+  expect!(interaction.interaction_markup()).to(be_equal_to(InteractionMarkup {
+    markup: "```protobuf\nmessage Foo {\n    string bar = 1;\n}\n```\n".to_string(),
+    markup_type: "COMMON_MARK".to_string()
+  }));
+
+  let interaction_json = interaction.to_json();
+  expect!(interaction_json["pluginConfiguration"].clone()).to(be_equal_to(json["pluginConfiguration"].clone()));
+  expect!(interaction_json["interactionMarkup"].clone()).to(be_equal_to(json["interactionMarkup"].clone()));
+}
+
+#[test]
+fn interaction_from_json_ignores_invalid_plugin_configuration_and_markup() {
+  let json = json!({
+    "type": "Synchronous/HTTP",
+    "description": "Test Interaction",
+    "pluginConfiguration": ["protobuf"],
+    "interactionMarkup": "<p>markup</p>"
+  });
+  let interaction = interaction_from_json("", 0, &json).unwrap();
+  expect!(interaction.plugin_config().iter()).to(be_empty());
+  expect!(interaction.interaction_markup().is_empty()).to(be_true());
+  expect!(interaction.to_json().get("pluginConfiguration")).to(be_none());
+  expect!(interaction.to_json().get("interactionMarkup")).to(be_none());
+}
+
+#[test]
+fn as_request_response_pact_converts_http_interactions() {
+  let pact = V4Pact {
+    consumer: Consumer { name: s!("consumer") },
+    provider: Provider { name: s!("provider") },
+    interactions: vec![
+      Box::new(SynchronousHttp {
+        description: s!("Test Interaction"),
+        timeout: Some(Duration::from_secs(1)),
+        .. Default::default()
+      })
+    ],
+    .. V4Pact::default()
+  };
+  let converted = pact.as_request_response_pact().unwrap();
+  expect!(converted.interactions.iter().map(|i| i.description.clone()).collect::<Vec<String>>())
+    .to(be_equal_to(vec![s!("Test Interaction")]));
+  expect!(converted.specification_version).to(be_equal_to(PactSpecification::V3));
+
+  let pact = V4Pact {
+    interactions: vec![ Box::new(AsynchronousMessage::default()) ],
+    .. V4Pact::default()
+  };
+  expect!(pact.as_request_response_pact()).to(be_err());
+}
+
 fn read_pact_file(file: &str) -> io::Result<String> {
   let mut f = File::open(file)?;
   let mut buffer = String::new();
@@ -456,7 +631,7 @@ fn write_pact_test() {
   dir.push(format!("pact_test_{}", x));
   dir.push(pact.default_file_name());
 
-  let result = write_pact(pact.boxed(), &dir, PactSpecification::V4, true);
+  let result = write_pact(pact.boxed(), &dir, PactSpecification::V4, true, MergeStrategy::default());
 
   let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or_default();
   fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -469,7 +644,7 @@ fn write_pact_test() {
   "interactions": [
     {{
       "description": "Test Interaction",
-      "key": "53d3170820ad2160",
+      "key": "b775b449942b3933d336bf0bc6f37831",
       "providerStates": [
         {{
           "name": "Good state to be in"
@@ -530,8 +705,8 @@ fn write_pact_test_should_merge_pacts() {
   dir.push(format!("pact_test_{}", x));
   dir.push(pact.default_file_name());
 
-  let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V4, true);
-  let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V4, false);
+  let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V4, true, MergeStrategy::default());
+  let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V4, false, MergeStrategy::default());
 
   let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or(s!(""));
   fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -545,7 +720,7 @@ fn write_pact_test_should_merge_pacts() {
   "interactions": [
     {{
       "description": "Test Interaction",
-      "key": "53d3170820ad2160",
+      "key": "b775b449942b3933d336bf0bc6f37831",
       "providerStates": [
         {{
           "name": "Good state to be in"
@@ -562,7 +737,7 @@ fn write_pact_test_should_merge_pacts() {
     }},
     {{
       "description": "Test Interaction 2",
-      "key": "4da93913a351bb8c",
+      "key": "d5eb004cb547ea57a19ac9b46a8043af",
       "providerStates": [
         {{
           "name": "Good state to be in"
@@ -626,8 +801,8 @@ fn write_pact_test_should_overwrite_pact_with_same_key() {
   dir.push(format!("pact_test_{}", x));
   dir.push(pact.default_file_name());
 
-  let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V4, true);
-  let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V4, false);
+  let result = write_pact(pact.boxed(), dir.as_path(), PactSpecification::V4, true, MergeStrategy::default());
+  let result2 = write_pact(pact2.boxed(), dir.as_path(), PactSpecification::V4, false, MergeStrategy::default());
 
   let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or_default();
   fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -1375,7 +1550,7 @@ fn write_v4_pact_test_with_comments() {
   dir.push(format!("pact_test_{}", x));
   dir.push(pact.default_file_name());
 
-  let result = write_pact(pact.boxed(), &dir, PactSpecification::V4, true);
+  let result = write_pact(pact.boxed(), &dir, PactSpecification::V4, true, MergeStrategy::default());
 
   let pact_file = read_pact_file(dir.as_path().to_str().unwrap()).unwrap_or_default();
   fs::remove_dir_all(dir.parent().unwrap()).unwrap_or(());
@@ -1396,7 +1571,7 @@ fn write_v4_pact_test_with_comments() {
         ]
       }},
       "description": "Test Interaction",
-      "key": "7e7114bdaf9a0be2",
+      "key": "79f6bf26c1a74de18923249affe54cc3",
       "request": {{
         "method": "GET",
         "path": "/"
@@ -1420,3 +1595,171 @@ fn write_v4_pact_test_with_comments() {
   }}
 }}"#, super::PACT_RUST_VERSION.unwrap())));
 }
+
+#[test]
+fn add_text_comment_and_set_test_name_update_the_interaction_comments() {
+  let mut interaction = SynchronousHttp::default();
+  interaction.add_text_comment("a comment");
+  interaction.add_text_comment("another comment");
+  interaction.set_test_name("user_test");
+  expect!(interaction.comments()).to(be_equal_to(hashmap! {
+    "text".to_string() => json!(["a comment", "another comment"]),
+    "testname".to_string() => json!("user_test")
+  }));
+
+  let mut interaction = AsynchronousMessage {
+    comments: hashmap! { "text".to_string() => json!("an existing comment") },
+    .. AsynchronousMessage::default()
+  };
+  interaction.add_text_comment("a comment");
+  expect!(interaction.comments()).to(be_equal_to(hashmap! {
+    "text".to_string() => json!(["an existing comment", "a comment"])
+  }));
+}
+
+#[test]
+fn add_plugin_records_the_plugin_in_the_metadata() {
+  let mut pact = V4Pact::default();
+  pact.add_plugin("protobuf", "0.0.1", None);
+  pact.add_plugin("csv", "0.0.1", Some(json!({ "delimiter": ";" })));
+  pact.add_plugin("protobuf", "0.0.2", None);
+  expect!(pact.metadata.get("plugins").cloned()).to(be_some().value(json!([
+    { "name": "csv", "version": "0.0.1", "configuration": { "delimiter": ";" } },
+    { "name": "protobuf", "version": "0.0.2" }
+  ])));
+  let json = pact.to_json(PactSpecification::V4);
+  expect!(json["metadata"]["plugins"].as_array().map(|a| a.len())).to(be_some().value(2));
+}
+
+#[test]
+fn loading_synchronous_messages_from_json() {
+  let json = json!({
+    "type": "Synchronous/Messages",
+    "description": "get a feature",
+    "request": {
+      "contents": {
+        "content": "CJYBEAE=",
+        "contentType": "application/protobuf;message=routeguide.Point",
+        "encoded": "base64"
+      }
+    },
+    "response": [
+      {
+        "contents": {
+          "content": "CghCaWcgVHJlZQ==",
+          "contentType": "application/protobuf;message=routeguide.Feature",
+          "encoded": "base64"
+        },
+        "metadata": { "grpc-status": "OK" }
+      }
+    ],
+    "pluginConfiguration": {
+      "grpc": { "service": "routeguide.RouteGuide", "method": "GetFeature" }
+    }
+  });
+  let interaction = interaction_from_json("", 0, &json).unwrap();
+  let message = interaction.as_v4_sync_message().unwrap();
+  expect!(message.description.as_str()).to(be_equal_to("get a feature"));
+  expect!(message.request.contents.value()).to(be_some().value(Bytes::from(vec![0x08, 0x96, 0x01, 0x10, 0x01])));
+  expect!(message.response.len()).to(be_equal_to(1));
+  expect!(message.response[0].metadata.get("grpc-status").cloned()).to(be_some().value(json!("OK")));
+  expect!(interaction.content_type().map(|ct| ct.to_string()))
+    .to(be_some().value("application/protobuf;message=routeguide.Feature".to_string()));
+
+  let round_trip = interaction_from_json("", 0, &message.to_json()).unwrap();
+  expect!(round_trip.as_v4_sync_message()).to(be_some().value(message));
+}
+
+#[test]
+fn interaction_keys_only_depend_on_the_type_description_and_provider_states() {
+  let interaction = SynchronousHttp {
+    description: "Test Interaction".into(),
+    provider_states: vec![ProviderState { name: "Good state to be in".into(), params: hashmap!{} }],
+    .. SynchronousHttp::default()
+  };
+  let key = interaction.with_key().key.unwrap();
+  expect!(key.clone()).to(be_equal_to("b775b449942b3933d336bf0bc6f37831"));
+
+  let changed = SynchronousHttp { response: HttpResponse { status: 400, .. HttpResponse::default() }, .. interaction.clone() };
+  expect!(changed.with_key().key).to(be_some().value(key.clone()));
+
+  let other_state = SynchronousHttp {
+    provider_states: vec![ProviderState { name: "Good state to be in".into(), params: hashmap!{ "id".to_string() => json!(1) } }],
+    .. interaction.clone()
+  };
+  expect!(other_state.with_key().key).to_not(be_some().value(key.clone()));
+  expect!(interaction_key(&V4InteractionType::Asynchronous_Messages, "Test Interaction",
+    &interaction.provider_states)).to_not(be_equal_to(key));
+
+  let user_key = SynchronousHttp { key: Some("my-key".into()), .. interaction };
+  expect!(user_key.with_key().key).to(be_some().value("my-key"));
+}
+
+#[test]
+fn loading_a_pact_with_duplicate_interaction_keys_keeps_all_the_interactions() {
+  let pact_json = json!({
+    "consumer": { "name": "consumer" },
+    "provider": { "name": "provider" },
+    "interactions": [
+      { "type": "Synchronous/HTTP", "description": "one", "key": "1234" },
+      { "type": "Synchronous/HTTP", "description": "two", "key": "5678" },
+      { "type": "Asynchronous/Messages", "description": "three", "key": "1234" }
+    ],
+    "metadata": { "pactSpecification": { "version": "4.0" } }
+  });
+  let pact = from_json("pact.json", &pact_json).unwrap();
+  expect!(pact.interactions().iter().map(|i| i.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+    "one".to_string(), "two".to_string(), "three".to_string()
+  ]));
+}
+
+#[test]
+fn merging_pacts_fails_if_the_merged_interactions_have_duplicate_keys() {
+  let pact = V4Pact {
+    interactions: vec![
+      Box::new(SynchronousHttp { description: "one".into(), .. SynchronousHttp::default() }),
+      Box::new(SynchronousHttp { description: "two".into(), .. SynchronousHttp::default() })
+    ],
+    .. V4Pact::default()
+  };
+  let key = pact.interactions[0].to_json()["key"].as_str().unwrap().to_string();
+  let pact2 = V4Pact {
+    interactions: vec![
+      Box::new(SynchronousHttp { description: "three".into(), key: Some(key.clone()), .. SynchronousHttp::default() })
+    ],
+    .. V4Pact::default()
+  };
+  match pact.merge(&pact2) {
+    Err(PactError::MergeConflict { conflicts }) => {
+      expect!(conflicts).to(be_equal_to(vec![
+        format!("Interaction 'three': has the same key '{}' as interaction 'one'", key)
+      ]));
+    },
+    result => panic!("Expected a merge conflict, got {:?}", result.map(|pact| pact.interactions().len()))
+  }
+}
+
+#[test]
+fn message_contents_content_type_test() {
+  let contents = MessageContents {
+    contents: OptionalBody::Present("<a/>".into(), None),
+    metadata: hashmap!{ "contentType".to_string() => json!("application/xml") },
+    .. MessageContents::default()
+  };
+  expect!(contents.content_type().unwrap().to_string()).to(be_equal_to("application/xml"));
+
+  let contents = MessageContents {
+    contents: OptionalBody::Present("{\"a\": 1}".into(), Some(JSON.clone())),
+    metadata: hashmap!{ "contentType".to_string() => json!("application/xml") },
+    .. MessageContents::default()
+  };
+  expect!(contents.content_type()).to(be_some().value(JSON.clone()));
+
+  let contents = MessageContents {
+    contents: OptionalBody::Present("{\"a\": 1}".into(), None),
+    .. MessageContents::default()
+  };
+  expect!(contents.content_type()).to(be_some().value(JSON.clone()));
+
+  expect!(MessageContents::default().content_type()).to(be_none());
+}
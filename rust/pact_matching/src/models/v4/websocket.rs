@@ -0,0 +1,198 @@
+//! V4 specification models - WebSocket interactions.
+//!
+//! WebSocket interactions are synchronous message interactions with a `websocket` transport. The
+//! `websocket` plugin configuration of the interaction is the HTTP request that upgrades the
+//! connection, the request message is the frame the client sends once the connection has been
+//! upgraded (it is not sent if it has no contents), and the response messages are the frames the
+//! server sends back, in order.
+
+use bytes::Bytes;
+use serde_json::Value;
+
+use pact_models::OptionalBody;
+
+use crate::models::Interaction;
+use crate::models::json_utils::json_to_string;
+use crate::models::v4::http_parts::HttpRequest;
+use crate::models::v4::message_parts::MessageContents;
+use crate::models::v4::SynchronousMessages;
+
+/// Name of the transport and plugin configuration of WebSocket interactions
+pub const WEBSOCKET_TRANSPORT: &str = "websocket";
+
+/// Metadata key that can be used to set the type of frame a message is sent with
+pub const FRAME_TYPE_KEY: &str = "frameType";
+
+/// Type of WebSocket data frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+  /// Text (UTF-8) frame
+  Text,
+  /// Binary frame
+  Binary
+}
+
+/// If the interaction is a WebSocket interaction
+pub fn is_websocket_interaction(interaction: &dyn Interaction) -> bool {
+  interaction.as_v4_sync_message()
+    .map(|interaction| interaction.transport.as_ref()
+      .map(|transport| transport.eq_ignore_ascii_case(WEBSOCKET_TRANSPORT))
+      .unwrap_or(false))
+    .unwrap_or(false)
+}
+
+/// Returns the request that upgrades the connection for a WebSocket interaction. Returns None if
+/// the interaction is not a WebSocket interaction. If the interaction does not configure the
+/// upgrade request, a GET request to the root path is used.
+pub fn upgrade_request(interaction: &SynchronousMessages) -> Option<HttpRequest> {
+  if is_websocket_interaction(interaction) {
+    Some(match interaction.plugin_config.get(WEBSOCKET_TRANSPORT) {
+      Some(config) if config.is_object() => HttpRequest::from_json(config),
+      _ => HttpRequest::default()
+    })
+  } else {
+    None
+  }
+}
+
+/// Makes the interaction a WebSocket interaction, with the request that upgrades the connection
+pub fn set_upgrade_request(interaction: &mut SynchronousMessages, request: &HttpRequest) {
+  interaction.transport = Some(WEBSOCKET_TRANSPORT.to_string());
+  interaction.plugin_config.insert(WEBSOCKET_TRANSPORT.to_string(), request.to_json());
+}
+
+/// Type of frame the message is sent with. This is the `frameType` metadata value (`text` or
+/// `binary`) if it is set, otherwise text frames are used for textual content types and binary
+/// frames for everything else.
+pub fn frame_type(message: &MessageContents) -> FrameType {
+  match message.metadata.get(FRAME_TYPE_KEY).map(json_to_string) {
+    Some(frame_type) if frame_type.eq_ignore_ascii_case("binary") => FrameType::Binary,
+    Some(frame_type) if frame_type.eq_ignore_ascii_case("text") => FrameType::Text,
+    _ => match message.contents.content_type() {
+      Some(content_type) if content_type.is_text() || content_type.is_json() || content_type.is_xml() =>
+        FrameType::Text,
+      Some(_) => FrameType::Binary,
+      None => if std::str::from_utf8(&message.contents.value().unwrap_or_default()).is_ok() {
+        FrameType::Text
+      } else {
+        FrameType::Binary
+      }
+    }
+  }
+}
+
+/// Converts the data of a received frame to message contents, so it can be matched against the
+/// expected message. The content type and frame type of the expected message are used for the
+/// received frame, as WebSocket frames do not have any metadata.
+pub fn received_frame(data: Bytes, expected: Option<&MessageContents>) -> MessageContents {
+  let content_type = expected.and_then(|expected| expected.content_type());
+  let metadata = expected.map(|expected| expected.metadata.iter()
+    .filter(|(key, _)| {
+      let key = key.to_lowercase();
+      key == "contenttype" || key == "content-type" || key == FRAME_TYPE_KEY.to_lowercase()
+    })
+    .map(|(k, v)| (k.clone(), v.clone()))
+    .collect())
+    .unwrap_or_default();
+  MessageContents {
+    contents: if data.is_empty() {
+      OptionalBody::Empty
+    } else {
+      OptionalBody::Present(data, content_type)
+    },
+    metadata,
+    .. MessageContents::default()
+  }
+}
+
+/// If the client should send the request message of the interaction once the connection is
+/// upgraded
+pub fn sends_request_frame(interaction: &SynchronousMessages) -> bool {
+  interaction.request.contents.is_present()
+}
+
+/// Metadata value for the frame type
+pub fn frame_type_value(frame_type: FrameType) -> Value {
+  match frame_type {
+    FrameType::Text => Value::String("text".to_string()),
+    FrameType::Binary => Value::String("binary".to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use pact_models::content_types::JSON;
+
+  use crate::models::v4::V4Interaction;
+  use crate::models::v4::interaction_from_json;
+
+  use super::*;
+
+  #[test]
+  fn upgrade_request_is_loaded_from_the_plugin_config() {
+    let interaction = interaction_from_json("", 0, &json!({
+      "type": "Synchronous/Messages",
+      "description": "a price feed",
+      "transport": "websocket",
+      "pluginConfiguration": {
+        "websocket": { "method": "GET", "path": "/prices", "headers": { "Sec-WebSocket-Protocol": ["prices.v1"] } }
+      },
+      "request": { "contents": { "content": { "subscribe": "AAPL" } } },
+      "response": [ { "contents": { "content": { "symbol": "AAPL", "price": 123.4 } } } ]
+    })).unwrap();
+    let interaction = interaction.as_v4_sync_message().unwrap();
+
+    expect!(is_websocket_interaction(&interaction)).to(be_true());
+    let request = upgrade_request(&interaction).unwrap();
+    expect!(request.path).to(be_equal_to("/prices"));
+    expect!(request.headers).to(be_some().value(hashmap!{
+      "Sec-WebSocket-Protocol".to_string() => vec!["prices.v1".to_string()]
+    }));
+    expect!(sends_request_frame(&interaction)).to(be_true());
+    expect!(interaction.to_json()["pluginConfiguration"]["websocket"]["path"].clone()).to(be_equal_to(json!("/prices")));
+  }
+
+  #[test]
+  fn set_upgrade_request_makes_the_interaction_a_websocket_interaction() {
+    let mut interaction = SynchronousMessages::default();
+    expect!(is_websocket_interaction(&interaction)).to(be_false());
+    expect!(upgrade_request(&interaction)).to(be_none());
+
+    set_upgrade_request(&mut interaction, &HttpRequest { path: "/ws".to_string(), .. HttpRequest::default() });
+    expect!(is_websocket_interaction(&interaction)).to(be_true());
+    expect!(upgrade_request(&interaction).map(|request| request.path)).to(be_some().value("/ws"));
+  }
+
+  #[test]
+  fn frame_type_is_determined_from_the_metadata_or_content_type() {
+    let message = |body: &'static [u8], content_type: Option<&str>| MessageContents {
+      contents: OptionalBody::Present(Bytes::from_static(body), content_type.map(|ct| ct.into())),
+      .. MessageContents::default()
+    };
+    expect!(frame_type(&message(b"{}", Some("application/json")))).to(be_equal_to(FrameType::Text));
+    expect!(frame_type(&message(b"abc", Some("application/octet-stream")))).to(be_equal_to(FrameType::Binary));
+    expect!(frame_type(&message(b"abc", None))).to(be_equal_to(FrameType::Text));
+    expect!(frame_type(&message(&[0xff, 0xfe], None))).to(be_equal_to(FrameType::Binary));
+
+    let mut binary = message(b"{}", Some("application/json"));
+    binary.metadata.insert(FRAME_TYPE_KEY.to_string(), frame_type_value(FrameType::Binary));
+    expect!(frame_type(&binary)).to(be_equal_to(FrameType::Binary));
+  }
+
+  #[test]
+  fn received_frame_uses_the_content_type_of_the_expected_message() {
+    let expected = MessageContents {
+      contents: OptionalBody::Present("{}".into(), Some(JSON.clone())),
+      metadata: hashmap!{ "contentType".to_string() => json!("application/json"), "other".to_string() => json!(1) },
+      .. MessageContents::default()
+    };
+    let frame = received_frame(Bytes::from("{\"a\":1}"), Some(&expected));
+    expect!(frame.contents.content_type()).to(be_some().value(JSON.clone()));
+    expect!(frame.metadata).to(be_equal_to(hashmap!{ "contentType".to_string() => json!("application/json") }));
+    expect!(received_frame(Bytes::new(), None).contents).to(be_equal_to(OptionalBody::Empty));
+  }
+}
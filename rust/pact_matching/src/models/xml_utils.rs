@@ -1,10 +1,148 @@
 //! Collection of utilities for working with XML
 
-use sxd_document::*;
 use std::str;
 
+use lazy_static::*;
+use onig::Regex;
+use sxd_document::*;
+use sxd_document::dom::Element;
+
+use pact_models::content_types::{ContentType, SOAP};
+
+/// Namespace of SOAP 1.1 envelopes
+pub const SOAP_1_1_NAMESPACE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+/// Namespace of SOAP 1.2 envelopes
+pub const SOAP_1_2_NAMESPACE: &str = "http://www.w3.org/2003/05/soap-envelope";
+
+lazy_static! {
+  static ref SOAP_ENVELOPE_REGEX: Regex = Regex::new(
+    r#"^\s*(<\?xml[^>]*\?>\s*)?(<!--.*?-->\s*)*<([\w.-]+:)?Envelope\s[^>]*"(http://schemas\.xmlsoap\.org/soap/envelope/|http://www\.w3\.org/2003/05/soap-envelope)""#
+  ).unwrap();
+}
+
 /// Parses a vector of bytes into a XML document
 pub fn parse_bytes(bytes: &[u8]) -> Result<Package, String> {
   let string = str::from_utf8(bytes).map_err(|_| format!("{:?}", bytes))?;
   parser::parse(string).map_err(|e| format!("{:?}", e))
 }
+
+/// Strips the namespace URI from a namespace qualified name (i.e. `urn:ns:foo` -> `foo`), keeping
+/// any attribute (`@`) prefix
+pub fn local_name(name: &str) -> String {
+  match name.rsplit_once(':') {
+    Some((qualifier, local)) if qualifier.starts_with('@') => format!("@{}", local),
+    Some((_, local)) => local.to_string(),
+    None => name.to_string()
+  }
+}
+
+fn is_soap_element(element: &Element, local_name: &str) -> bool {
+  let name = element.name();
+  name.local_part() == local_name && matches!(name.namespace_uri(),
+    Some(SOAP_1_1_NAMESPACE) | Some(SOAP_1_2_NAMESPACE))
+}
+
+fn soap_child<'a>(envelope: &Element<'a>, local_name: &str) -> Option<Element<'a>> {
+  if is_soap_envelope(envelope) {
+    envelope.children().iter()
+      .filter_map(|child| child.element())
+      .find(|child| is_soap_element(child, local_name))
+  } else {
+    None
+  }
+}
+
+/// If the element is a SOAP 1.1 or 1.2 envelope
+pub fn is_soap_envelope(element: &Element) -> bool {
+  is_soap_element(element, "Envelope")
+}
+
+/// If the element is the header of a SOAP envelope
+pub fn is_soap_header(element: &Element) -> bool {
+  is_soap_element(element, "Header")
+}
+
+/// Returns the header of the SOAP envelope, if it has one
+pub fn soap_header<'a>(envelope: &Element<'a>) -> Option<Element<'a>> {
+  soap_child(envelope, "Header")
+}
+
+/// Returns the body of the SOAP envelope
+pub fn soap_body<'a>(envelope: &Element<'a>) -> Option<Element<'a>> {
+  soap_child(envelope, "Body")
+}
+
+/// Returns the payload of the SOAP envelope (the first element of the body)
+pub fn soap_payload<'a>(envelope: &Element<'a>) -> Option<Element<'a>> {
+  soap_body(envelope).and_then(|body| body.children().iter().find_map(|child| child.element()))
+}
+
+/// If the SOAP header entry has the `mustUnderstand` attribute set. These are normally
+/// infrastructure headers (i.e. WS-Security) with values that change with every request.
+pub fn must_understand(header_entry: &Element) -> bool {
+  header_entry.attributes().iter().any(|attr| {
+    let name = attr.name();
+    name.local_part() == "mustUnderstand" &&
+      matches!(name.namespace_uri(), Some(SOAP_1_1_NAMESPACE) | Some(SOAP_1_2_NAMESPACE)) &&
+      (attr.value() == "1" || attr.value() == "true")
+  })
+}
+
+/// Detects if the contents are a SOAP envelope, returning `text/xml` for SOAP 1.1 and
+/// `application/soap+xml` for SOAP 1.2 envelopes
+pub fn detect_soap_content_type(contents: &str) -> Option<ContentType> {
+  SOAP_ENVELOPE_REGEX.captures(contents).and_then(|captures| match captures.at(4) {
+    Some(SOAP_1_1_NAMESPACE) => Some(ContentType {
+      main_type: "text".into(),
+      sub_type: "xml".into(),
+      .. ContentType::default()
+    }),
+    Some(SOAP_1_2_NAMESPACE) => Some(SOAP.clone()),
+    _ => None
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  const SOAP_1_2: &str = r#"<?xml version="1.0"?>
+    <env:Envelope xmlns:env="http://www.w3.org/2003/05/soap-envelope">
+      <env:Header>
+        <wsse:Security xmlns:wsse="urn:wsse" env:mustUnderstand="true"><wsse:Nonce>abc</wsse:Nonce></wsse:Security>
+        <t:Transaction xmlns:t="urn:t">5</t:Transaction>
+      </env:Header>
+      <env:Body><m:GetPrice xmlns:m="urn:m"><m:Item>Apples</m:Item></m:GetPrice></env:Body>
+    </env:Envelope>"#;
+
+  #[test]
+  fn detect_soap_content_type_test() {
+    expect!(detect_soap_content_type(SOAP_1_2)).to(be_some().value(SOAP.clone()));
+    expect!(detect_soap_content_type(r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/"><soap:Body/></soap:Envelope>"#))
+      .to(be_some().value(ContentType::parse("text/xml").unwrap()));
+    expect!(detect_soap_content_type(r#"<?xml version="1.0"?><Envelope xmlns="urn:other"/>"#)).to(be_none());
+    expect!(detect_soap_content_type(r#"{"Envelope": "http://www.w3.org/2003/05/soap-envelope"}"#)).to(be_none());
+  }
+
+  #[test]
+  fn soap_envelope_helpers() {
+    let package = parse_bytes(SOAP_1_2.as_bytes()).unwrap();
+    let document = package.as_document();
+    let envelope = document.root().children().iter().find_map(|child| child.element()).unwrap();
+    expect!(is_soap_envelope(&envelope)).to(be_true());
+
+    let header = soap_header(&envelope).unwrap();
+    expect!(is_soap_header(&header)).to(be_true());
+    let entries: Vec<bool> = header.children().iter()
+      .filter_map(|child| child.element())
+      .map(|entry| must_understand(&entry))
+      .collect();
+    expect!(entries).to(be_equal_to(vec![true, false]));
+
+    expect!(soap_body(&envelope).map(|body| body.name().local_part().to_string())).to(be_some().value("Body"));
+    expect!(soap_payload(&envelope).map(|payload| payload.name().local_part().to_string())).to(be_some().value("GetPrice"));
+    expect!(soap_header(&soap_payload(&envelope).unwrap())).to(be_none());
+  }
+}
@@ -116,7 +116,9 @@ fn index_path<I>(chars: &mut Peekable<I>, tokens: &mut Vec<PathToken>, path: &st
     }
   }
 
-  tokens.push(PathToken::Index(id.parse().unwrap()));
+  let index = id.parse().map_err(|err| format!("\"{}\" is not a valid index in path expression \"{}\" - {}",
+    id, path, err))?;
+  tokens.push(PathToken::Index(index));
   Ok(())
 }
 
@@ -312,6 +314,14 @@ mod tests {
                          PathToken::Field(s!("c"))]));
   }
 
+  #[test]
+  fn parse_path_exp_with_invalid_indexes() {
+    expect!(parse_path_exp("$.a[99999999999999999999999]")).to(
+      be_err().value(s!("\"99999999999999999999999\" is not a valid index in path expression \"$.a[99999999999999999999999]\" - number too large to fit in target type")));
+    expect!(parse_path_exp("$.a[١٢]")).to(
+      be_err().value(s!("\"١٢\" is not a valid index in path expression \"$.a[١٢]\" - invalid digit found in string")));
+  }
+
   #[test]
   fn parse_path_exp_with_invalid_bracket_notation() {
     expect!(parse_path_exp("$[")).to(
@@ -0,0 +1,265 @@
+//! Path templates (i.e. `/users/{id}/orders/{orderId}`). A template expands to a regex matcher for
+//! the request path with a named group for each parameter, and the values captured by the named
+//! groups from the actual request path can be used by the `PathParameter` generator.
+//!
+//! Each parameter matches a single path segment by default. A regex can be given after the name
+//! to restrict the values, i.e. `/users/{id:\d+}`.
+
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use log::*;
+use onig::Regex;
+
+use crate::models::Request;
+use crate::models::matchingrules::MatchingRule;
+
+/// Regex used for parameters that do not specify one
+const DEFAULT_PARAMETER_REGEX: &str = "[^/]+";
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplateSegment {
+  Literal(String),
+  Parameter { name: String, regex: Option<String> }
+}
+
+/// Parsed path template
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTemplate {
+  /// Template the path was parsed from
+  pub template: String,
+  segments: Vec<TemplateSegment>
+}
+
+impl PathTemplate {
+  /// Parses the path template. Parameter names must be valid identifiers and can only be used
+  /// once in the template.
+  pub fn parse(template: &str) -> anyhow::Result<PathTemplate> {
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut chars = template.char_indices();
+    while let Some((index, ch)) = chars.next() {
+      match ch {
+        '{' => {
+          if !literal.is_empty() {
+            segments.push(TemplateSegment::Literal(literal.clone()));
+            literal.clear();
+          }
+          let mut parameter = String::new();
+          let mut depth = 1;
+          loop {
+            match chars.next() {
+              Some((_, '{')) => {
+                depth += 1;
+                parameter.push('{');
+              },
+              Some((_, '}')) => {
+                depth -= 1;
+                if depth == 0 {
+                  break;
+                }
+                parameter.push('}');
+              },
+              Some((_, ch)) => parameter.push(ch),
+              None => return Err(anyhow!("Path template '{}' has an unclosed parameter at index {}", template, index))
+            }
+          }
+          segments.push(parse_parameter(template, index, &parameter, &segments)?);
+        },
+        '}' => return Err(anyhow!("Path template '{}' has an unexpected '}}' at index {}", template, index)),
+        _ => literal.push(ch)
+      }
+    }
+    if !literal.is_empty() {
+      segments.push(TemplateSegment::Literal(literal));
+    }
+    Ok(PathTemplate { template: template.to_string(), segments })
+  }
+
+  /// Names of the parameters in the template, in the order they appear
+  pub fn parameters(&self) -> Vec<&str> {
+    self.segments.iter().filter_map(|segment| match segment {
+      TemplateSegment::Parameter { name, .. } => Some(name.as_str()),
+      _ => None
+    }).collect()
+  }
+
+  /// Regex that matches paths for this template, with a named group for each parameter
+  pub fn to_regex(&self) -> String {
+    let mut regex = "^".to_string();
+    for segment in &self.segments {
+      match segment {
+        TemplateSegment::Literal(literal) => regex.push_str(&escape_regex(literal)),
+        TemplateSegment::Parameter { name, regex: parameter_regex } =>
+          regex.push_str(&format!("(?<{}>{})", name, parameter_regex.as_deref().unwrap_or(DEFAULT_PARAMETER_REGEX)))
+      }
+    }
+    regex.push('$');
+    regex
+  }
+
+  /// Expands the template with the given parameter values. All the parameters must have a value.
+  pub fn expand(&self, values: &HashMap<String, String>) -> anyhow::Result<String> {
+    let mut path = String::new();
+    for segment in &self.segments {
+      match segment {
+        TemplateSegment::Literal(literal) => path.push_str(literal),
+        TemplateSegment::Parameter { name, .. } => match values.get(name) {
+          Some(value) => path.push_str(value),
+          None => return Err(anyhow!("No value was provided for parameter '{}' of path template '{}'", name, self.template))
+        }
+      }
+    }
+    Ok(path)
+  }
+}
+
+fn parse_parameter(
+  template: &str,
+  index: usize,
+  parameter: &str,
+  segments: &[TemplateSegment]
+) -> anyhow::Result<TemplateSegment> {
+  let (name, regex) = match parameter.split_once(':') {
+    Some((name, regex)) => (name.trim(), Some(regex.to_string())),
+    None => (parameter.trim(), None)
+  };
+  if !is_identifier(name) {
+    return Err(anyhow!("Path template '{}' has an invalid parameter name '{}' at index {}", template, name, index));
+  }
+  if segments.iter().any(|segment| matches!(segment, TemplateSegment::Parameter { name: n, .. } if n == name)) {
+    return Err(anyhow!("Path template '{}' uses the parameter '{}' more than once", template, name));
+  }
+  if let Some(regex) = &regex {
+    if regex.is_empty() {
+      return Err(anyhow!("Path template '{}' has an empty regex for parameter '{}'", template, name));
+    }
+    Regex::new(regex)
+      .map_err(|err| anyhow!("Path template '{}' has an invalid regex for parameter '{}' - {}", template, name, err))?;
+  }
+  Ok(TemplateSegment::Parameter { name: name.to_string(), regex })
+}
+
+fn is_identifier(name: &str) -> bool {
+  let mut chars = name.chars();
+  match chars.next() {
+    Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_'),
+    _ => false
+  }
+}
+
+fn escape_regex(literal: &str) -> String {
+  let mut escaped = String::with_capacity(literal.len());
+  for ch in literal.chars() {
+    if "\\.^$|?*+()[]{}".contains(ch) {
+      escaped.push('\\');
+    }
+    escaped.push(ch);
+  }
+  escaped
+}
+
+/// If the path contains template parameters (i.e. `/users/{id}`)
+pub fn is_path_template(path: &str) -> bool {
+  PathTemplate::parse(path).map(|template| !template.parameters().is_empty()).unwrap_or(false)
+}
+
+/// Returns the values captured by the named groups of the regex from the path. Returns an empty
+/// map if the regex is not valid or does not match the path.
+pub fn path_parameters(regex: &str, path: &str) -> HashMap<String, String> {
+  let mut parameters = HashMap::new();
+  match Regex::new(regex) {
+    Ok(re) => if let Some(captures) = re.captures(path) {
+      re.foreach_name(|name, groups| {
+        if let Some(value) = groups.iter().find_map(|group| captures.at(*group as usize)) {
+          parameters.insert(name.to_string(), value.to_string());
+        }
+        true
+      });
+    },
+    Err(err) => warn!("'{}' is not a valid regular expression - {}", regex, err)
+  }
+  parameters
+}
+
+/// Returns the values captured from the actual path by the regex matchers of the path of the
+/// expected request
+pub fn path_parameters_for_request(expected: &Request, actual_path: &str) -> HashMap<String, String> {
+  expected.matching_rules.rules_for_category("path")
+    .map(|category| category.rules.values()
+      .flat_map(|rules| rules.rules.iter())
+      .filter_map(|rule| match rule {
+        MatchingRule::Regex(regex) => Some(path_parameters(regex, actual_path)),
+        _ => None
+      })
+      .flatten()
+      .collect())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+
+  use crate::models::matchingrules::MatchingRules;
+
+  use super::*;
+
+  #[test]
+  fn parse_path_templates() {
+    let template = PathTemplate::parse("/users/{id}/orders/{orderId}").unwrap();
+    expect!(template.parameters()).to(be_equal_to(vec!["id", "orderId"]));
+    expect!(template.to_regex()).to(be_equal_to("^/users/(?<id>[^/]+)/orders/(?<orderId>[^/]+)$".to_string()));
+
+    let template = PathTemplate::parse("/files/{name:[a-z]{3}}.txt").unwrap();
+    expect!(template.parameters()).to(be_equal_to(vec!["name"]));
+    expect!(template.to_regex()).to(be_equal_to("^/files/(?<name>[a-z]{3})\\.txt$".to_string()));
+
+    expect!(PathTemplate::parse("/users").unwrap().parameters().is_empty()).to(be_true());
+    expect!(is_path_template("/users/{id}")).to(be_true());
+    expect!(is_path_template("/users/100")).to(be_false());
+
+    expect!(PathTemplate::parse("/users/{id").unwrap_err().to_string())
+      .to(be_equal_to("Path template '/users/{id' has an unclosed parameter at index 7"));
+    expect!(PathTemplate::parse("/users/id}").unwrap_err().to_string())
+      .to(be_equal_to("Path template '/users/id}' has an unexpected '}' at index 9"));
+    expect!(PathTemplate::parse("/users/{1d}").unwrap_err().to_string())
+      .to(be_equal_to("Path template '/users/{1d}' has an invalid parameter name '1d' at index 7"));
+    expect!(PathTemplate::parse("/users/{id}/{id}").unwrap_err().to_string())
+      .to(be_equal_to("Path template '/users/{id}/{id}' uses the parameter 'id' more than once"));
+    expect!(PathTemplate::parse("/users/{id:[0-9}").is_err()).to(be_true());
+  }
+
+  #[test]
+  fn expand_path_templates() {
+    let template = PathTemplate::parse("/users/{id}/orders/{orderId}").unwrap();
+    expect!(template.expand(&hashmap!{ "id".to_string() => "100".to_string(), "orderId".to_string() => "2".to_string() }).unwrap())
+      .to(be_equal_to("/users/100/orders/2"));
+    expect!(template.expand(&hashmap!{ "id".to_string() => "100".to_string() }).unwrap_err().to_string())
+      .to(be_equal_to("No value was provided for parameter 'orderId' of path template '/users/{id}/orders/{orderId}'"));
+  }
+
+  #[test]
+  fn path_parameters_are_captured_by_the_named_groups() {
+    let regex = PathTemplate::parse("/users/{id:\\d+}/orders/{orderId}").unwrap().to_regex();
+    expect!(path_parameters(&regex, "/users/100/orders/abc")).to(be_equal_to(hashmap!{
+      "id".to_string() => "100".to_string(),
+      "orderId".to_string() => "abc".to_string()
+    }));
+    expect!(path_parameters(&regex, "/users/abc/orders/abc")).to(be_equal_to(hashmap!{}));
+    expect!(path_parameters(&regex, "/users/100/orders/abc/items")).to(be_equal_to(hashmap!{}));
+
+    let request = Request {
+      path: "/users/100/orders/1".to_string(),
+      matching_rules: matchingrules!{ "path" => { "" => [ MatchingRule::Regex(regex) ] } },
+      .. Request::default()
+    };
+    expect!(path_parameters_for_request(&request, "/users/22/orders/5")).to(be_equal_to(hashmap!{
+      "id".to_string() => "22".to_string(),
+      "orderId".to_string() => "5".to_string()
+    }));
+    expect!(path_parameters_for_request(&Request { matching_rules: MatchingRules::default(), .. request },
+      "/users/22/orders/5")).to(be_equal_to(hashmap!{}));
+  }
+}
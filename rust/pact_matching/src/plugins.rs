@@ -0,0 +1,493 @@
+//! Plugins that provide matching and generation of contents for content types that are not
+//! supported natively (i.e. protobuf, Avro or CSV).
+//!
+//! A plugin is registered for the content types it handles with `register_plugin`. When bodies with
+//! one of those content types are compared, or have generators applied to them, the calls are
+//! delegated to the plugin instead of the built-in matchers. Plugins can be implemented in-process
+//! by implementing the `ContentPlugin` trait, or run as an external process with `ExternalPlugin`.
+//! Plugins that only apply to one pact (i.e. with the protobuf descriptors from its metadata) are
+//! not registered, but used for the calls made in `with_plugins`.
+//!
+//! Pacts that require a plugin to be verified should record it with `V4Pact::add_plugin`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use log::*;
+use serde_json::{json, Value};
+
+use pact_models::content_types::ContentType;
+use pact_models::OptionalBody;
+
+use crate::Mismatch;
+use crate::models::generators::{Generator, GeneratorTestMode};
+use crate::models::json_utils::json_to_string;
+use crate::models::matchingrules::MatchingRuleCategory;
+
+/// Name of the manifest file that describes an external plugin
+pub const PLUGIN_MANIFEST_FILE: &str = "pact-plugin.json";
+
+/// Name and version of a plugin, which is recorded in the pact file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginMetadata {
+  /// Name of the plugin
+  pub name: String,
+  /// Version of the plugin
+  pub version: String
+}
+
+/// Plugin that can match and generate contents of one or more content types
+pub trait ContentPlugin: Send + Sync {
+  /// Name and version of the plugin
+  fn metadata(&self) -> PluginMetadata;
+
+  /// Content types the plugin supports
+  fn content_types(&self) -> Vec<ContentType>;
+
+  /// If this plugin can match and generate bodies of the given content type
+  fn handles(&self, content_type: &ContentType) -> bool {
+    let base_type = content_type.base_type();
+    self.content_types().iter().any(|ct| ct.base_type() == base_type)
+  }
+
+  /// Compares the actual contents against the expected ones using the matching rules, returning
+  /// the body mismatches
+  fn match_contents(
+    &self,
+    expected: &OptionalBody,
+    actual: &OptionalBody,
+    rules: &MatchingRuleCategory,
+    allow_unexpected_keys: bool
+  ) -> anyhow::Result<Vec<Mismatch>>;
+
+  /// Applies the generators to the contents, returning the new contents
+  fn generate_contents(
+    &self,
+    body: &OptionalBody,
+    generators: &HashMap<String, Generator>,
+    mode: &GeneratorTestMode
+  ) -> anyhow::Result<OptionalBody>;
+}
+
+impl fmt::Debug for dyn ContentPlugin {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let metadata = self.metadata();
+    write!(f, "ContentPlugin({} {})", metadata.name, metadata.version)
+  }
+}
+
+lazy_static! {
+  static ref PLUGINS: RwLock<Vec<Arc<dyn ContentPlugin>>> = RwLock::new(vec![]);
+}
+
+thread_local! {
+  /// Plugins set with `with_plugins` for the current thread
+  static SCOPED_PLUGINS: RefCell<Vec<Arc<dyn ContentPlugin>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Registers a plugin. Plugins registered later take precedence over earlier ones for the
+/// same content type.
+pub fn register_plugin(plugin: Arc<dyn ContentPlugin>) {
+  let metadata = plugin.metadata();
+  debug!("Registering plugin '{}' version {}", metadata.name, metadata.version);
+  PLUGINS.write().unwrap().insert(0, plugin);
+}
+
+/// Removes the plugin with the given name, returning true if it was registered
+pub fn unregister_plugin(name: &str) -> bool {
+  let mut plugins = PLUGINS.write().unwrap();
+  let len = plugins.len();
+  plugins.retain(|plugin| plugin.metadata().name != name);
+  plugins.len() != len
+}
+
+/// Runs the function with the plugins taking precedence over the registered ones for any contents
+/// matched or generated on the current thread. This allows a mock server or verification to use
+/// the plugins for its pact without affecting any others running in the same process.
+pub fn with_plugins<T>(plugins: &[Arc<dyn ContentPlugin>], f: impl FnOnce() -> T) -> T {
+  struct Restore(usize);
+
+  impl Drop for Restore {
+    fn drop(&mut self) {
+      SCOPED_PLUGINS.with(|scoped| scoped.borrow_mut().drain(..self.0).for_each(drop));
+    }
+  }
+
+  SCOPED_PLUGINS.with(|scoped| scoped.borrow_mut().splice(0..0, plugins.iter().cloned()).for_each(drop));
+  let _restore = Restore(plugins.len());
+  f()
+}
+
+/// Returns the plugin for the content type, if one has been set with `with_plugins` or registered
+pub fn find_plugin(content_type: &ContentType) -> Option<Arc<dyn ContentPlugin>> {
+  SCOPED_PLUGINS.with(|scoped| scoped.borrow().iter().find(|p| p.handles(content_type)).cloned())
+    .or_else(|| PLUGINS.read().unwrap().iter().find(|p| p.handles(content_type)).cloned())
+}
+
+/// Returns the metadata of all the registered plugins
+pub fn registered_plugins() -> Vec<PluginMetadata> {
+  PLUGINS.read().unwrap().iter().map(|p| p.metadata()).collect()
+}
+
+/// Manifest that describes an external plugin, loaded from a `pact-plugin.json` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginManifest {
+  /// Name of the plugin
+  pub name: String,
+  /// Version of the plugin
+  pub version: String,
+  /// Executable to run. Relative paths are resolved against the directory of the manifest.
+  pub executable: String,
+  /// Arguments to pass to the executable
+  pub args: Vec<String>,
+  /// Content types the plugin supports
+  pub content_types: Vec<String>
+}
+
+impl PluginManifest {
+  /// Parses the manifest from its JSON form
+  pub fn from_json(json: &Value) -> anyhow::Result<PluginManifest> {
+    let string_attr = |name: &str| json.get(name)
+      .and_then(|v| v.as_str())
+      .map(|v| v.to_string())
+      .ok_or_else(|| anyhow!("Plugin manifest is missing the '{}' attribute", name));
+    let string_list = |name: &str| match json.get(name) {
+      Some(Value::Array(values)) => values.iter().map(json_to_string).collect(),
+      _ => vec![]
+    };
+    Ok(PluginManifest {
+      name: string_attr("name")?,
+      version: string_attr("version")?,
+      executable: string_attr("executable")?,
+      args: string_list("args"),
+      content_types: string_list("contentTypes")
+    })
+  }
+}
+
+/// Plugin that is run as an external process.
+///
+/// The process is started for each call. A JSON request is written to its standard input, and it
+/// must write a JSON response to its standard output before exiting. Contents are always Base64
+/// encoded. The requests are:
+///
+/// * `{"request": "MatchContents", "expected": <contents>, "actual": <contents>, "rules": <matching rules>, "allowUnexpectedKeys": bool}`,
+///   and the response is `{"mismatches": [{"path", "expected", "actual", "mismatch"}]}`.
+/// * `{"request": "GenerateContents", "contents": <contents>, "generators": <generators>, "mode": "Consumer" | "Provider"}`,
+///   and the response is `{"contents": <contents>}`.
+///
+/// where `<contents>` is `{"content": <Base64 string>, "contentType": <content type>}`. A plugin
+/// can fail a request by responding with `{"error": <message>}`.
+#[derive(Debug, Clone)]
+pub struct ExternalPlugin {
+  /// Manifest of the plugin
+  pub manifest: PluginManifest,
+  /// Directory the plugin was loaded from
+  pub dir: PathBuf
+}
+
+impl ExternalPlugin {
+  /// Creates a plugin from the manifest, resolving the executable against the directory
+  pub fn new(manifest: PluginManifest, dir: &Path) -> ExternalPlugin {
+    ExternalPlugin { manifest, dir: dir.to_path_buf() }
+  }
+
+  /// Loads the plugin from the manifest file in the directory
+  pub fn load(dir: &Path) -> anyhow::Result<ExternalPlugin> {
+    let path = dir.join(PLUGIN_MANIFEST_FILE);
+    let contents = fs::read_to_string(&path)
+      .with_context(|| format!("Failed to read plugin manifest {}", path.display()))?;
+    let json: Value = serde_json::from_str(&contents)
+      .with_context(|| format!("Plugin manifest {} is not valid JSON", path.display()))?;
+    Ok(ExternalPlugin::new(PluginManifest::from_json(&json)?, dir))
+  }
+
+  fn executable(&self) -> PathBuf {
+    let executable = Path::new(&self.manifest.executable);
+    if executable.is_relative() && executable.components().count() > 1 {
+      self.dir.join(executable)
+    } else {
+      executable.to_path_buf()
+    }
+  }
+
+  fn call(&self, request: &Value) -> anyhow::Result<Value> {
+    debug!("Sending request to plugin '{}': {}", self.manifest.name, request);
+    let mut child = Command::new(self.executable())
+      .args(&self.manifest.args)
+      .current_dir(&self.dir)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::inherit())
+      .spawn()
+      .with_context(|| format!("Failed to start plugin '{}'", self.manifest.name))?;
+    if let Some(mut stdin) = child.stdin.take() {
+      stdin.write_all(request.to_string().as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+      return Err(anyhow!("Plugin '{}' failed with {}", self.manifest.name, output.status));
+    }
+    let response: Value = serde_json::from_slice(&output.stdout)
+      .with_context(|| format!("Plugin '{}' returned an invalid response", self.manifest.name))?;
+    debug!("Plugin '{}' returned: {}", self.manifest.name, response);
+    match response.get("error") {
+      Some(error) => Err(anyhow!("Plugin '{}' returned an error: {}", self.manifest.name, json_to_string(error))),
+      None => Ok(response)
+    }
+  }
+}
+
+fn contents_to_json(body: &OptionalBody) -> Value {
+  json!({
+    "content": base64::encode(body.value().unwrap_or_default()),
+    "contentType": body.content_type().map(|ct| ct.to_string())
+  })
+}
+
+fn contents_from_json(json: &Value, body: &OptionalBody) -> anyhow::Result<OptionalBody> {
+  let content = json.get("content").and_then(|c| c.as_str())
+    .ok_or_else(|| anyhow!("Plugin response contents are missing the 'content' attribute"))?;
+  let content = base64::decode(content).context("Plugin response contents are not Base64 encoded")?;
+  let content_type = json.get("contentType").and_then(|ct| ct.as_str())
+    .and_then(|ct| ContentType::parse(ct).ok())
+    .or_else(|| body.content_type());
+  Ok(OptionalBody::Present(Bytes::from(content), content_type))
+}
+
+fn mismatch_from_json(json: &Value) -> Mismatch {
+  let attr = |name: &str| json.get(name).map(json_to_string);
+  Mismatch::BodyMismatch {
+    path: attr("path").unwrap_or_else(|| "$".to_string()),
+    expected: attr("expected").map(Bytes::from),
+    actual: attr("actual").map(Bytes::from),
+    mismatch: attr("mismatch").unwrap_or_default()
+  }
+}
+
+impl ContentPlugin for ExternalPlugin {
+  fn metadata(&self) -> PluginMetadata {
+    PluginMetadata {
+      name: self.manifest.name.clone(),
+      version: self.manifest.version.clone()
+    }
+  }
+
+  fn content_types(&self) -> Vec<ContentType> {
+    self.manifest.content_types.iter()
+      .filter_map(|ct| match ContentType::parse(ct.as_str()) {
+        Ok(ct) => Some(ct),
+        Err(err) => {
+          warn!("Ignoring invalid content type '{}' for plugin '{}': {}", ct, self.manifest.name, err);
+          None
+        }
+      })
+      .collect()
+  }
+
+  fn match_contents(
+    &self,
+    expected: &OptionalBody,
+    actual: &OptionalBody,
+    rules: &MatchingRuleCategory,
+    allow_unexpected_keys: bool
+  ) -> anyhow::Result<Vec<Mismatch>> {
+    let response = self.call(&json!({
+      "request": "MatchContents",
+      "expected": contents_to_json(expected),
+      "actual": contents_to_json(actual),
+      "rules": rules.to_v3_json(),
+      "allowUnexpectedKeys": allow_unexpected_keys
+    }))?;
+    match response.get("mismatches") {
+      Some(Value::Array(mismatches)) => Ok(mismatches.iter().map(mismatch_from_json).collect()),
+      None | Some(Value::Null) => Ok(vec![]),
+      Some(_) => Err(anyhow!("Plugin '{}' returned invalid mismatches", self.manifest.name))
+    }
+  }
+
+  fn generate_contents(
+    &self,
+    body: &OptionalBody,
+    generators: &HashMap<String, Generator>,
+    mode: &GeneratorTestMode
+  ) -> anyhow::Result<OptionalBody> {
+    let generators: serde_json::Map<String, Value> = generators.iter()
+      .filter_map(|(path, generator)| generator.to_json().map(|json| (path.clone(), json)))
+      .collect();
+    let response = self.call(&json!({
+      "request": "GenerateContents",
+      "contents": contents_to_json(body),
+      "generators": generators,
+      "mode": format!("{:?}", mode)
+    }))?;
+    match response.get("contents") {
+      Some(contents) => contents_from_json(contents, body),
+      None => Err(anyhow!("Plugin '{}' did not return any contents", self.manifest.name))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+
+  use crate::{BodyMatchResult, DiffConfig, match_body, MatchingContext};
+  use crate::models::generators::apply_body_generators;
+  use crate::models::Request;
+
+  use super::*;
+
+  struct TestPlugin;
+
+  impl ContentPlugin for TestPlugin {
+    fn metadata(&self) -> PluginMetadata {
+      PluginMetadata { name: "test".to_string(), version: "0.0.1".to_string() }
+    }
+
+    fn content_types(&self) -> Vec<ContentType> {
+      vec![ContentType::parse("application/x-test-plugin").unwrap()]
+    }
+
+    fn match_contents(
+      &self,
+      expected: &OptionalBody,
+      actual: &OptionalBody,
+      _rules: &MatchingRuleCategory,
+      _allow_unexpected_keys: bool
+    ) -> anyhow::Result<Vec<Mismatch>> {
+      if expected.value() == actual.value() {
+        Ok(vec![])
+      } else {
+        Ok(vec![Mismatch::BodyMismatch {
+          path: "$.row".to_string(),
+          expected: expected.value(),
+          actual: actual.value(),
+          mismatch: "rows differ".to_string()
+        }])
+      }
+    }
+
+    fn generate_contents(
+      &self,
+      body: &OptionalBody,
+      _generators: &HashMap<String, Generator>,
+      _mode: &GeneratorTestMode
+    ) -> anyhow::Result<OptionalBody> {
+      Ok(OptionalBody::Present("generated".into(), body.content_type()))
+    }
+  }
+
+  fn request_with_body(body: &str) -> Request {
+    Request {
+      headers: Some(hashmap!{ "Content-Type".to_string() => vec!["application/x-test-plugin".to_string()] }),
+      body: OptionalBody::Present(body.to_string().into(), ContentType::parse("application/x-test-plugin").ok()),
+      .. Request::default()
+    }
+  }
+
+  #[test]
+  fn registered_plugin_is_used_to_match_and_generate_contents() {
+    register_plugin(Arc::new(TestPlugin));
+    let context = MatchingContext::with_config(DiffConfig::AllowUnexpectedKeys);
+
+    let result = match_body(&request_with_body("a,b"), &request_with_body("a,b"), &context, &context);
+    expect!(result).to(be_equal_to(BodyMatchResult::Ok));
+    let result = match_body(&request_with_body("a,b"), &request_with_body("a,c"), &context, &context);
+    expect!(result.mismatches().iter().map(|m| m.description()).collect::<Vec<_>>())
+      .to(be_equal_to(vec!["$.row -> rows differ".to_string()]));
+
+    let body = request_with_body("a,b").body;
+    let generated = apply_body_generators(&GeneratorTestMode::Consumer, &body, body.content_type(),
+      &hashmap!{}, &hashmap!{});
+    expect!(generated.value()).to(be_some().value(Bytes::from("generated")));
+
+    expect!(unregister_plugin("test")).to(be_true());
+  }
+
+  #[test]
+  fn plugins_are_only_used_within_with_plugins() {
+    let content_type = ContentType::parse("application/x-test-plugin").unwrap();
+    let plugin: Arc<dyn ContentPlugin> = Arc::new(TestPlugin);
+    let found = with_plugins(&[plugin], || find_plugin(&content_type));
+    expect!(found.map(|p| p.metadata().name)).to(be_some().value("test"));
+    expect!(SCOPED_PLUGINS.with(|scoped| scoped.borrow().len())).to(be_equal_to(0));
+  }
+
+  #[test]
+  fn manifest_from_json_requires_name_version_and_executable() {
+    let manifest = PluginManifest::from_json(&json!({
+      "name": "csv",
+      "version": "0.0.1",
+      "executable": "bin/pact-csv-plugin",
+      "args": ["--quiet"],
+      "contentTypes": ["text/csv"]
+    })).unwrap();
+    expect!(manifest).to(be_equal_to(PluginManifest {
+      name: "csv".to_string(),
+      version: "0.0.1".to_string(),
+      executable: "bin/pact-csv-plugin".to_string(),
+      args: vec!["--quiet".to_string()],
+      content_types: vec!["text/csv".to_string()]
+    }));
+    expect!(PluginManifest::from_json(&json!({ "name": "csv", "version": "0.0.1" }))).to(be_err());
+  }
+
+  #[test]
+  fn external_plugin_handles_the_content_types_from_the_manifest() {
+    let plugin = ExternalPlugin::new(PluginManifest {
+      name: "csv".to_string(),
+      version: "0.0.1".to_string(),
+      executable: "pact-csv-plugin".to_string(),
+      args: vec![],
+      content_types: vec!["text/csv".to_string()]
+    }, Path::new("."));
+    expect!(plugin.handles(&ContentType::parse("text/csv; charset=UTF-8").unwrap())).to(be_true());
+    expect!(plugin.handles(&ContentType::parse("application/json").unwrap())).to(be_false());
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn external_plugin_parses_the_response_from_the_process() {
+    let plugin = ExternalPlugin::new(PluginManifest {
+      name: "test".to_string(),
+      version: "0.0.1".to_string(),
+      executable: "sh".to_string(),
+      args: vec!["-c".to_string(), r#"cat > /dev/null; echo '{"mismatches": [{"path": "$.a", "mismatch": "bad"}]}'"#.to_string()],
+      content_types: vec!["text/csv".to_string()]
+    }, Path::new("."));
+    let body = OptionalBody::Present("a,b".into(), ContentType::parse("text/csv").ok());
+    let result = plugin.match_contents(&body, &body, &MatchingRuleCategory::empty("body"), false);
+    expect!(result.unwrap()).to(be_equal_to(vec![Mismatch::BodyMismatch {
+      path: "$.a".to_string(),
+      expected: None,
+      actual: None,
+      mismatch: "bad".to_string()
+    }]));
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn external_plugin_returns_an_error_if_the_plugin_fails() {
+    let plugin = ExternalPlugin::new(PluginManifest {
+      name: "test".to_string(),
+      version: "0.0.1".to_string(),
+      executable: "sh".to_string(),
+      args: vec!["-c".to_string(), r#"cat > /dev/null; echo '{"error": "boom"}'"#.to_string()],
+      content_types: vec!["text/csv".to_string()]
+    }, Path::new("."));
+    let body = OptionalBody::Present("a,b".into(), ContentType::parse("text/csv").ok());
+    let result = plugin.generate_contents(&body, &hashmap!{}, &GeneratorTestMode::Consumer);
+    expect!(result.unwrap_err().to_string()).to(be_equal_to("Plugin 'test' returned an error: boom"));
+  }
+}
@@ -0,0 +1,212 @@
+//! Property based tests that feed generated (and mostly malformed) pact JSON documents into the
+//! functions that load pact files, to make sure untrusted input results in errors and not panics.
+
+use std::collections::HashMap;
+
+use proptest::collection::{hash_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use serde_json::{json, Map, Value};
+
+use crate::models::{body_from_json, load_pact_from_json};
+use crate::path_exp::{parse_path_exp, PathToken};
+
+/// Any JSON value, nested up to a few levels deep
+fn any_json() -> impl Strategy<Value = Value> {
+  let leaf = prop_oneof![
+    Just(Value::Null),
+    any::<bool>().prop_map(Value::Bool),
+    any::<i64>().prop_map(|n| json!(n)),
+    any::<u64>().prop_map(|n| json!(n)),
+    any::<f64>().prop_map(|n| json!(n)),
+    ".*".prop_map(Value::String)
+  ];
+  leaf.prop_recursive(3, 32, 6, |inner| prop_oneof![
+    vec(inner.clone(), 0..6).prop_map(Value::Array),
+    hash_map(".*", inner, 0..6).prop_map(|map| Value::Object(map.into_iter().collect()))
+  ])
+}
+
+/// Either a value from the given strategy or any other JSON value
+fn json_or_junk(strategy: impl Strategy<Value = Value>) -> impl Strategy<Value = Value> {
+  prop_oneof![
+    3 => strategy,
+    1 => any_json()
+  ]
+}
+
+fn object(fields: Vec<(&'static str, Option<Value>)>) -> Value {
+  Value::Object(fields.into_iter()
+    .filter_map(|(key, value)| value.map(|value| (key.to_string(), value)))
+    .collect::<Map<String, Value>>())
+}
+
+fn spec_version() -> impl Strategy<Value = Value> {
+  json_or_junk(prop_oneof![
+    Just(json!("1.0.0")), Just(json!("1.1.0")), Just(json!("2.0.0")), Just(json!("3.0.0")),
+    Just(json!("4.0")), Just(json!("4.0.0")), "[0-9]{1,2}(\\.[0-9x]{0,3}){0,3}".prop_map(|s| json!(s))
+  ])
+}
+
+fn metadata() -> impl Strategy<Value = Value> {
+  json_or_junk((spec_version(), prop_oneof![Just("pactSpecification"), Just("pact-specification"),
+      Just("pactSpecificationVersion")])
+    .prop_map(|(version, key)| json!({ key: { "version": version } })))
+}
+
+fn path_expression() -> impl Strategy<Value = String> {
+  prop_oneof![
+    "\\$(\\.[a-z*]{1,5}|\\[[0-9*]{1,3}\\]|\\['[a-z]{0,3}'\\])*",
+    "\\$\\.(body|headers|query|path|status)(\\.[a-z*]{1,5})?",
+    "[$.\\[\\]'*a-z0-9]{0,12}"
+  ]
+}
+
+fn matcher() -> impl Strategy<Value = Value> {
+  json_or_junk(prop_oneof![
+    Just(json!({ "match": "type" })),
+    any::<i64>().prop_map(|n| json!({ "match": "type", "min": n })),
+    Just(json!({ "match": "regex", "regex": "\\d+" })),
+    any_json().prop_map(|v| json!({ "match": "regex", "regex": v })),
+    any_json().prop_map(|v| json!({ "match": "arrayContains", "variants": v })),
+    any_json().prop_map(|v| json!({ "match": "timestamp", "format": v }))
+  ])
+}
+
+fn matching_rules() -> impl Strategy<Value = Value> {
+  let v3_rules = hash_map(prop_oneof![Just("body".to_string()), Just("header".to_string()),
+      Just("query".to_string()), Just("path".to_string())],
+    json_or_junk(hash_map(path_expression(), vec(matcher(), 0..3)
+      .prop_map(|matchers| json!({ "matchers": matchers })), 0..3)
+      .prop_map(|map| json!(map))), 0..3)
+    .prop_map(|map| json!(map));
+  let v2_rules = hash_map(path_expression(), matcher(), 0..3).prop_map(|map| json!(map));
+  json_or_junk(prop_oneof![v3_rules, v2_rules])
+}
+
+fn generators() -> impl Strategy<Value = Value> {
+  json_or_junk(hash_map(prop_oneof![Just("body".to_string()), Just("header".to_string()),
+      Just("status".to_string()), Just("path".to_string())],
+    hash_map(path_expression(), any_json().prop_map(|v| json!({ "type": "RandomInt", "min": v })), 0..3)
+      .prop_map(|map| json!(map)), 0..3)
+    .prop_map(|map| json!(map)))
+}
+
+fn headers() -> impl Strategy<Value = Value> {
+  json_or_junk(hash_map(prop_oneof![Just("Content-Type".to_string()), ".*"],
+    json_or_junk(prop_oneof![
+      Just(json!("application/json")), Just(json!("text/plain")), Just(json!("application/xml")),
+      vec(".*", 0..3).prop_map(|v| json!(v))
+    ]), 0..3)
+    .prop_map(|map| json!(map)))
+}
+
+fn provider_states() -> impl Strategy<Value = Value> {
+  json_or_junk(vec(json_or_junk((".*", option::of(any_json()))
+    .prop_map(|(name, params)| object(vec![("name", Some(json!(name))), ("params", params)]))), 0..3)
+    .prop_map(Value::Array))
+}
+
+fn http_part(request: bool) -> impl Strategy<Value = Value> {
+  (
+    option::of(json_or_junk(prop_oneof![Just(json!("GET")), Just(json!("post"))])),
+    option::of(json_or_junk(".*".prop_map(Value::String))),
+    option::of(json_or_junk(any::<u16>().prop_map(|n| json!(n)))),
+    option::of(json_or_junk(".*".prop_map(Value::String))),
+    option::of(headers()),
+    option::of(any_json()),
+    option::of(matching_rules()),
+    option::of(generators())
+  ).prop_map(move |(method, path, status, query, headers, body, rules, generators)| {
+    if request {
+      object(vec![("method", method), ("path", path), ("query", query), ("headers", headers),
+        ("body", body), ("matchingRules", rules), ("generators", generators)])
+    } else {
+      object(vec![("status", status), ("headers", headers), ("body", body),
+        ("matchingRules", rules), ("generators", generators)])
+    }
+  })
+}
+
+fn interaction() -> impl Strategy<Value = Value> {
+  json_or_junk((
+    option::of(json_or_junk(".*".prop_map(Value::String))),
+    option::of(json_or_junk(prop_oneof![Just(json!("Synchronous/HTTP")),
+      Just(json!("Asynchronous/Messages"))])),
+    option::of(provider_states()),
+    option::of(json_or_junk(".*".prop_map(|s| json!(s)))),
+    option::of(json_or_junk(http_part(true))),
+    option::of(json_or_junk(http_part(false))),
+    option::of(any_json()),
+    option::of(matching_rules())
+  ).prop_map(|(description, interaction_type, states, state, request, response, contents, rules)| {
+    object(vec![("description", description), ("type", interaction_type), ("providerStates", states),
+      ("providerState", state), ("request", request), ("response", response), ("contents", contents),
+      ("matchingRules", rules)])
+  }))
+}
+
+/// JSON documents that look like pact files, with random fields replaced with junk
+fn pact_json() -> impl Strategy<Value = Value> {
+  json_or_junk((
+    option::of(json_or_junk(Just(json!({ "name": "consumer" })))),
+    option::of(json_or_junk(Just(json!({ "name": "provider" })))),
+    option::of(json_or_junk(vec(interaction(), 0..3).prop_map(Value::Array))),
+    option::of(json_or_junk(vec(interaction(), 0..3).prop_map(Value::Array))),
+    option::of(metadata())
+  ).prop_map(|(consumer, provider, interactions, messages, metadata)| {
+    object(vec![("consumer", consumer), ("provider", provider), ("interactions", interactions),
+      ("messages", messages), ("metadata", metadata)])
+  }))
+}
+
+fn content_type_header() -> impl Strategy<Value = Option<HashMap<String, Vec<String>>>> {
+  option::of(hash_map(prop_oneof![Just("Content-Type".to_string()), Just("content-type".to_string()), ".*"],
+    vec(prop_oneof![Just("application/json".to_string()), Just("text/plain".to_string()), ".*"], 0..2), 0..2))
+}
+
+proptest! {
+  #[test]
+  fn load_pact_from_json_does_not_panic(json in pact_json()) {
+    let _ = load_pact_from_json("proptest", &json);
+  }
+
+  #[test]
+  fn load_pact_from_json_does_not_panic_with_any_json(json in any_json()) {
+    let _ = load_pact_from_json("proptest", &json);
+  }
+
+  #[test]
+  fn body_from_json_does_not_panic(body in any_json(), headers in content_type_header()) {
+    let _ = body_from_json(&json!({ "body": body }), "body", &headers);
+  }
+
+  #[test]
+  fn parse_path_exp_does_not_panic(path in "\\PC*") {
+    let _ = parse_path_exp(&path);
+  }
+
+  #[test]
+  fn parse_path_exp_does_not_panic_with_path_like_strings(path in path_expression()) {
+    let _ = parse_path_exp(&path);
+  }
+
+  #[test]
+  fn parse_path_exp_parses_valid_paths(fields in vec(prop_oneof![
+    "[a-zA-Z_][a-zA-Z0-9_]{0,8}".prop_map(PathToken::Field),
+    (0usize..10000).prop_map(PathToken::Index),
+    Just(PathToken::Star),
+    Just(PathToken::StarIndex)
+  ], 0..6)) {
+    let path = fields.iter().fold("$".to_string(), |path, token| match token {
+      PathToken::Field(name) => format!("{}.{}", path, name),
+      PathToken::Index(index) => format!("{}[{}]", path, index),
+      PathToken::Star => format!("{}.*", path),
+      PathToken::StarIndex => format!("{}[*]", path),
+      PathToken::Root => path
+    });
+    let mut expected = vec![PathToken::Root];
+    expected.extend(fields);
+    prop_assert_eq!(parse_path_exp(&path), Ok(expected));
+  }
+}
@@ -0,0 +1,1107 @@
+//! Support for protobuf encoded contents (i.e. the messages of gRPC interactions).
+//!
+//! Protobuf messages can only be decoded with the descriptor of the message, so the messages
+//! and services are loaded from a `.proto` file with `ProtoFile`. Registering a `ProtobufPlugin`
+//! with the descriptors (see the `plugins` module) allows protobuf contents to be matched field by
+//! field, by decoding both the expected and actual messages to JSON and then applying the JSON
+//! matching rules. The message type is taken from the `message` parameter of the content type,
+//! i.e. `application/protobuf; message=routeguide.Feature`.
+//!
+//! With the `protobuf` feature, the descriptors can also be loaded from a `FileDescriptorSet`
+//! embedded in the pact metadata (see the `descriptors` module).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use log::*;
+use maplit::hashmap;
+use serde_json::{json, Map, Number, Value};
+
+use pact_models::content_types::ContentType;
+use pact_models::OptionalBody;
+
+use crate::{DiffConfig, MatchingContext, Mismatch};
+use crate::models::generators::{apply_body_generators, Generator, GeneratorTestMode};
+use crate::models::json_utils::json_to_string;
+use crate::models::matchingrules::MatchingRuleCategory;
+use crate::plugins::{ContentPlugin, PluginMetadata};
+
+#[cfg(feature = "protobuf")]
+pub mod descriptors;
+
+/// Content type of gRPC requests and responses
+pub const GRPC_CONTENT_TYPE: &str = "application/grpc";
+
+/// Content types of protobuf encoded contents
+pub const PROTOBUF_CONTENT_TYPES: [&str; 3] = ["application/protobuf", "application/x-protobuf", GRPC_CONTENT_TYPE];
+
+/// Type of a field of a protobuf message
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+  /// 64 bit floating point number
+  Double,
+  /// 32 bit floating point number
+  Float,
+  /// Signed 32 bit integer
+  Int32,
+  /// Signed 64 bit integer
+  Int64,
+  /// Unsigned 32 bit integer
+  UInt32,
+  /// Unsigned 64 bit integer
+  UInt64,
+  /// Signed 32 bit integer with zig-zag encoding
+  SInt32,
+  /// Signed 64 bit integer with zig-zag encoding
+  SInt64,
+  /// Unsigned 32 bit integer with fixed width encoding
+  Fixed32,
+  /// Unsigned 64 bit integer with fixed width encoding
+  Fixed64,
+  /// Signed 32 bit integer with fixed width encoding
+  SFixed32,
+  /// Signed 64 bit integer with fixed width encoding
+  SFixed64,
+  /// Boolean
+  Bool,
+  /// UTF-8 string
+  String,
+  /// Bytes (Base64 encoded in the JSON form)
+  Bytes,
+  /// Embedded message with the fully qualified name
+  Message(String),
+  /// Enum with the fully qualified name
+  Enum(String),
+  /// Map with the key and value types
+  Map(Box<FieldType>, Box<FieldType>)
+}
+
+impl FieldType {
+  fn scalar(name: &str) -> Option<FieldType> {
+    match name {
+      "double" => Some(FieldType::Double),
+      "float" => Some(FieldType::Float),
+      "int32" => Some(FieldType::Int32),
+      "int64" => Some(FieldType::Int64),
+      "uint32" => Some(FieldType::UInt32),
+      "uint64" => Some(FieldType::UInt64),
+      "sint32" => Some(FieldType::SInt32),
+      "sint64" => Some(FieldType::SInt64),
+      "fixed32" => Some(FieldType::Fixed32),
+      "fixed64" => Some(FieldType::Fixed64),
+      "sfixed32" => Some(FieldType::SFixed32),
+      "sfixed64" => Some(FieldType::SFixed64),
+      "bool" => Some(FieldType::Bool),
+      "string" => Some(FieldType::String),
+      "bytes" => Some(FieldType::Bytes),
+      _ => None
+    }
+  }
+
+  fn wire_type(&self) -> u64 {
+    match self {
+      FieldType::Double | FieldType::Fixed64 | FieldType::SFixed64 => 1,
+      FieldType::Float | FieldType::Fixed32 | FieldType::SFixed32 => 5,
+      FieldType::String | FieldType::Bytes | FieldType::Message(_) | FieldType::Map(_, _) => 2,
+      _ => 0
+    }
+  }
+
+  fn is_packable(&self) -> bool {
+    self.wire_type() != 2
+  }
+}
+
+/// Field of a protobuf message
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescriptor {
+  /// Name of the field
+  pub name: String,
+  /// Field number
+  pub number: u64,
+  /// Type of the field
+  pub field_type: FieldType,
+  /// If the field is repeated
+  pub repeated: bool
+}
+
+/// Protobuf message type
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageDescriptor {
+  /// Fully qualified name of the message
+  pub name: String,
+  /// Fields of the message
+  pub fields: Vec<FieldDescriptor>
+}
+
+/// Protobuf enum type
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDescriptor {
+  /// Fully qualified name of the enum
+  pub name: String,
+  /// Names and numbers of the enum values
+  pub values: Vec<(String, i32)>
+}
+
+/// Method of a gRPC service
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDescriptor {
+  /// Name of the method
+  pub name: String,
+  /// Fully qualified name of the request message type
+  pub input_type: String,
+  /// Fully qualified name of the response message type
+  pub output_type: String,
+  /// If the server streams the response messages
+  pub server_streaming: bool
+}
+
+/// gRPC service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceDescriptor {
+  /// Fully qualified name of the service
+  pub name: String,
+  /// Methods of the service
+  pub methods: Vec<MethodDescriptor>
+}
+
+/// Messages, enums and services defined in a `.proto` file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProtoFile {
+  /// Package of the file
+  pub package: String,
+  /// Message types, keyed by fully qualified name
+  pub messages: BTreeMap<String, MessageDescriptor>,
+  /// Enum types, keyed by fully qualified name
+  pub enums: BTreeMap<String, EnumDescriptor>,
+  /// Services
+  pub services: Vec<ServiceDescriptor>
+}
+
+impl ProtoFile {
+  /// Loads the descriptors from a `.proto` file
+  pub fn load(path: &Path) -> anyhow::Result<ProtoFile> {
+    let source = fs::read_to_string(path)
+      .with_context(|| format!("Failed to read proto file {}", path.display()))?;
+    ProtoFile::parse(&source)
+  }
+
+  /// Parses the descriptors from the source of a `.proto` file. Imports are not followed.
+  pub fn parse(source: &str) -> anyhow::Result<ProtoFile> {
+    let mut parser = Parser { tokens: tokenise(source)?, index: 0, unresolved: vec![] };
+    let mut proto = ProtoFile::default();
+    parser.parse_file(&mut proto)?;
+    let unresolved = std::mem::take(&mut parser.unresolved);
+    for (message, field, scope, type_name) in unresolved {
+      let resolved = proto.resolve_type(&scope, &type_name)?;
+      if let Some(descriptor) = proto.messages.get_mut(&message) {
+        for f in descriptor.fields.iter_mut().filter(|f| f.name == field) {
+          f.field_type = match &f.field_type {
+            FieldType::Map(key, _) => FieldType::Map(key.clone(), Box::new(resolved.clone())),
+            _ => resolved.clone()
+          };
+        }
+      }
+    }
+    for service in proto.services.clone() {
+      for (index, method) in service.methods.iter().enumerate() {
+        let scope = proto.package.clone();
+        let input_type = proto.resolve_message_name(&scope, &method.input_type)?;
+        let output_type = proto.resolve_message_name(&scope, &method.output_type)?;
+        let descriptor = proto.services.iter_mut().find(|s| s.name == service.name).unwrap();
+        descriptor.methods[index].input_type = input_type;
+        descriptor.methods[index].output_type = output_type;
+      }
+    }
+    Ok(proto)
+  }
+
+  fn resolve_type(&self, scope: &str, type_name: &str) -> anyhow::Result<FieldType> {
+    let candidates = candidate_names(scope, type_name);
+    candidates.iter()
+      .find_map(|name| if self.messages.contains_key(name) {
+        Some(FieldType::Message(name.clone()))
+      } else if self.enums.contains_key(name) {
+        Some(FieldType::Enum(name.clone()))
+      } else {
+        None
+      })
+      .ok_or_else(|| anyhow!("Type '{}' is not defined", type_name))
+  }
+
+  fn resolve_message_name(&self, scope: &str, type_name: &str) -> anyhow::Result<String> {
+    match self.resolve_type(scope, type_name)? {
+      FieldType::Message(name) => Ok(name),
+      _ => Err(anyhow!("Type '{}' is not a message", type_name))
+    }
+  }
+
+  /// Returns the message type with the given name
+  pub fn find_message(&self, name: &str) -> Option<&MessageDescriptor> {
+    self.messages.get(name.trim_start_matches('.'))
+  }
+
+  /// Returns the method of the service. The service can be given with or without its package.
+  pub fn find_method(&self, service: &str, method: &str) -> Option<&MethodDescriptor> {
+    let service = service.trim_start_matches('.');
+    self.services.iter()
+      .find(|s| s.name == service || s.name == format!("{}.{}", self.package, service))
+      .and_then(|s| s.methods.iter().find(|m| m.name == method))
+  }
+
+  /// Decodes the protobuf encoded message to its JSON form
+  pub fn decode(&self, message_type: &str, data: &[u8]) -> anyhow::Result<Value> {
+    let descriptor = self.find_message(message_type)
+      .ok_or_else(|| anyhow!("Message type '{}' is not defined", message_type))?;
+    self.decode_message(descriptor, data)
+  }
+
+  /// Encodes the JSON form of the message
+  pub fn encode(&self, message_type: &str, json: &Value) -> anyhow::Result<Vec<u8>> {
+    let descriptor = self.find_message(message_type)
+      .ok_or_else(|| anyhow!("Message type '{}' is not defined", message_type))?;
+    let mut buffer = vec![];
+    self.encode_message(descriptor, json, &mut buffer)?;
+    Ok(buffer)
+  }
+
+  fn decode_message(&self, descriptor: &MessageDescriptor, data: &[u8]) -> anyhow::Result<Value> {
+    let mut fields = Map::new();
+    for field in &descriptor.fields {
+      if let Some(default) = self.default_value(field) {
+        fields.insert(field.name.clone(), default);
+      }
+    }
+
+    let mut reader = WireReader { data, pos: 0 };
+    while !reader.is_empty() {
+      let tag = reader.read_varint()?;
+      let (number, wire_type) = (tag >> 3, tag & 7);
+      let field = match descriptor.fields.iter().find(|f| f.number == number) {
+        Some(field) => field,
+        None => {
+          trace!("Skipping unknown field {} of message '{}'", number, descriptor.name);
+          reader.skip(wire_type)?;
+          continue;
+        }
+      };
+      match &field.field_type {
+        FieldType::Map(key_type, value_type) => {
+          let entry = reader.read_length_delimited()?;
+          let (key, value) = self.decode_map_entry(key_type, value_type, entry)?;
+          if let Some(Value::Object(map)) = fields.get_mut(&field.name) {
+            map.insert(key, value);
+          }
+        }
+        field_type if field.repeated && wire_type == 2 && field_type.is_packable() => {
+          let mut packed = WireReader { data: reader.read_length_delimited()?, pos: 0 };
+          while !packed.is_empty() {
+            let value = self.decode_value(field_type, field_type.wire_type(), &mut packed)?;
+            push_value(&mut fields, field, value);
+          }
+        }
+        field_type => {
+          let value = self.decode_value(field_type, wire_type, &mut reader)?;
+          push_value(&mut fields, field, value);
+        }
+      }
+    }
+    Ok(Value::Object(fields))
+  }
+
+  fn decode_map_entry(&self, key_type: &FieldType, value_type: &FieldType, data: &[u8]) -> anyhow::Result<(String, Value)> {
+    let mut reader = WireReader { data, pos: 0 };
+    let mut key = None;
+    let mut value = None;
+    while !reader.is_empty() {
+      let tag = reader.read_varint()?;
+      match tag >> 3 {
+        1 => key = Some(self.decode_value(key_type, tag & 7, &mut reader)?),
+        2 => value = Some(self.decode_value(value_type, tag & 7, &mut reader)?),
+        _ => reader.skip(tag & 7)?
+      }
+    }
+    let key = key.or_else(|| self.scalar_default(key_type)).map(|k| json_to_string(&k)).unwrap_or_default();
+    let value = value.or_else(|| self.scalar_default(value_type)).unwrap_or(Value::Null);
+    Ok((key, value))
+  }
+
+  fn decode_value(&self, field_type: &FieldType, wire_type: u64, reader: &mut WireReader) -> anyhow::Result<Value> {
+    if wire_type != field_type.wire_type() {
+      return Err(anyhow!("Expected wire type {} for a {:?} field, but got {}", field_type.wire_type(),
+        field_type, wire_type));
+    }
+    Ok(match field_type {
+      FieldType::Double => json_float(f64::from_bits(reader.read_fixed64()?)),
+      FieldType::Float => json_float(f32::from_bits(reader.read_fixed32()?) as f64),
+      FieldType::Int32 => json!(reader.read_varint()? as i32),
+      FieldType::Int64 => json!(reader.read_varint()? as i64),
+      FieldType::UInt32 => json!(reader.read_varint()? as u32),
+      FieldType::UInt64 => json!(reader.read_varint()?),
+      FieldType::SInt32 => json!(zigzag_decode(reader.read_varint()?) as i32),
+      FieldType::SInt64 => json!(zigzag_decode(reader.read_varint()?)),
+      FieldType::Fixed32 => json!(reader.read_fixed32()?),
+      FieldType::Fixed64 => json!(reader.read_fixed64()?),
+      FieldType::SFixed32 => json!(reader.read_fixed32()? as i32),
+      FieldType::SFixed64 => json!(reader.read_fixed64()? as i64),
+      FieldType::Bool => json!(reader.read_varint()? != 0),
+      FieldType::String => json!(String::from_utf8(reader.read_length_delimited()?.to_vec())?),
+      FieldType::Bytes => json!(base64::encode(reader.read_length_delimited()?)),
+      FieldType::Enum(name) => {
+        let number = reader.read_varint()? as i32;
+        self.enums.get(name)
+          .and_then(|e| e.values.iter().find(|(_, n)| *n == number))
+          .map(|(value, _)| json!(value))
+          .unwrap_or_else(|| json!(number))
+      }
+      FieldType::Message(name) => {
+        let descriptor = self.messages.get(name)
+          .ok_or_else(|| anyhow!("Message type '{}' is not defined", name))?;
+        self.decode_message(descriptor, reader.read_length_delimited()?)?
+      }
+      FieldType::Map(_, _) => return Err(anyhow!("Map fields can not be nested"))
+    })
+  }
+
+  fn default_value(&self, field: &FieldDescriptor) -> Option<Value> {
+    match &field.field_type {
+      FieldType::Map(_, _) => Some(json!({})),
+      _ if field.repeated => Some(json!([])),
+      field_type => self.scalar_default(field_type)
+    }
+  }
+
+  fn scalar_default(&self, field_type: &FieldType) -> Option<Value> {
+    match field_type {
+      FieldType::Double | FieldType::Float => Some(json!(0.0)),
+      FieldType::Bool => Some(json!(false)),
+      FieldType::String | FieldType::Bytes => Some(json!("")),
+      FieldType::Enum(name) => Some(self.enums.get(name)
+        .and_then(|e| e.values.iter().find(|(_, n)| *n == 0))
+        .map(|(value, _)| json!(value))
+        .unwrap_or_else(|| json!(0))),
+      FieldType::Message(_) | FieldType::Map(_, _) => None,
+      _ => Some(json!(0))
+    }
+  }
+
+  fn encode_message(&self, descriptor: &MessageDescriptor, json: &Value, buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+    let fields = json.as_object()
+      .ok_or_else(|| anyhow!("Expected a JSON object for message '{}', but got {}", descriptor.name, json))?;
+    for (name, value) in fields {
+      let field = descriptor.fields.iter().find(|f| &f.name == name)
+        .ok_or_else(|| anyhow!("Message '{}' does not have a field '{}'", descriptor.name, name))?;
+      match (&field.field_type, value) {
+        (_, Value::Null) => (),
+        (FieldType::Map(key_type, value_type), Value::Object(entries)) => {
+          for (key, value) in entries {
+            let mut entry = vec![];
+            let key = match key_type.as_ref() {
+              FieldType::String => json!(key),
+              FieldType::Bool => json!(key == "true"),
+              _ => Value::Number(key.parse::<Number>()
+                .map_err(|_| anyhow!("Map key '{}' of field '{}' is not a number", key, name))?)
+            };
+            self.encode_field(1, key_type, &key, &mut entry)?;
+            self.encode_field(2, value_type, value, &mut entry)?;
+            write_varint(field.number << 3 | 2, buffer);
+            write_varint(entry.len() as u64, buffer);
+            buffer.extend(entry);
+          }
+        }
+        (field_type, Value::Array(values)) if field.repeated => {
+          for value in values {
+            self.encode_field(field.number, field_type, value, buffer)?;
+          }
+        }
+        (field_type, value) => self.encode_field(field.number, field_type, value, buffer)?
+      }
+    }
+    Ok(())
+  }
+
+  fn encode_field(&self, number: u64, field_type: &FieldType, value: &Value, buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+    let invalid = || anyhow!("{} is not a valid value for a {:?} field", value, field_type);
+    write_varint(number << 3 | field_type.wire_type(), buffer);
+    match field_type {
+      FieldType::Double => buffer.extend(&value.as_f64().ok_or_else(invalid)?.to_bits().to_le_bytes()),
+      FieldType::Float => buffer.extend(&(value.as_f64().ok_or_else(invalid)? as f32).to_bits().to_le_bytes()),
+      FieldType::Int32 | FieldType::Int64 => write_varint(value.as_i64().ok_or_else(invalid)? as u64, buffer),
+      FieldType::UInt32 | FieldType::UInt64 => write_varint(value.as_u64().ok_or_else(invalid)?, buffer),
+      FieldType::SInt32 | FieldType::SInt64 => write_varint(zigzag_encode(value.as_i64().ok_or_else(invalid)?), buffer),
+      FieldType::Fixed32 => buffer.extend(&(value.as_u64().ok_or_else(invalid)? as u32).to_le_bytes()),
+      FieldType::Fixed64 => buffer.extend(&value.as_u64().ok_or_else(invalid)?.to_le_bytes()),
+      FieldType::SFixed32 => buffer.extend(&(value.as_i64().ok_or_else(invalid)? as i32).to_le_bytes()),
+      FieldType::SFixed64 => buffer.extend(&value.as_i64().ok_or_else(invalid)?.to_le_bytes()),
+      FieldType::Bool => write_varint(value.as_bool().ok_or_else(invalid)? as u64, buffer),
+      FieldType::String => write_bytes(value.as_str().ok_or_else(invalid)?.as_bytes(), buffer),
+      FieldType::Bytes => write_bytes(&base64::decode(value.as_str().ok_or_else(invalid)?)?, buffer),
+      FieldType::Enum(name) => {
+        let number = match value {
+          Value::String(s) => self.enums.get(name)
+            .and_then(|e| e.values.iter().find(|(value, _)| value == s))
+            .map(|(_, n)| *n)
+            .ok_or_else(invalid)?,
+          _ => value.as_i64().ok_or_else(invalid)? as i32
+        };
+        write_varint(number as i64 as u64, buffer)
+      }
+      FieldType::Message(name) => {
+        let descriptor = self.messages.get(name)
+          .ok_or_else(|| anyhow!("Message type '{}' is not defined", name))?;
+        let mut message = vec![];
+        self.encode_message(descriptor, value, &mut message)?;
+        write_bytes(&message, buffer)
+      }
+      FieldType::Map(_, _) => return Err(anyhow!("Map fields can not be nested"))
+    }
+    Ok(())
+  }
+}
+
+fn candidate_names(scope: &str, type_name: &str) -> Vec<String> {
+  if let Some(name) = type_name.strip_prefix('.') {
+    return vec![name.to_string()];
+  }
+  let mut candidates = vec![];
+  let mut scope = scope.to_string();
+  loop {
+    if scope.is_empty() {
+      candidates.push(type_name.to_string());
+      break;
+    }
+    candidates.push(format!("{}.{}", scope, type_name));
+    scope = match scope.rfind('.') {
+      Some(index) => scope[..index].to_string(),
+      None => String::default()
+    };
+  }
+  candidates
+}
+
+fn push_value(fields: &mut Map<String, Value>, field: &FieldDescriptor, value: Value) {
+  if field.repeated {
+    if let Some(Value::Array(values)) = fields.get_mut(&field.name) {
+      values.push(value);
+    }
+  } else {
+    fields.insert(field.name.clone(), value);
+  }
+}
+
+fn json_float(value: f64) -> Value {
+  Number::from_f64(value).map(Value::Number).unwrap_or_else(|| json!(value.to_string()))
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+  ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn zigzag_encode(value: i64) -> u64 {
+  ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn write_varint(mut value: u64, buffer: &mut Vec<u8>) {
+  while value >= 0x80 {
+    buffer.push((value as u8 & 0x7F) | 0x80);
+    value >>= 7;
+  }
+  buffer.push(value as u8);
+}
+
+fn write_bytes(bytes: &[u8], buffer: &mut Vec<u8>) {
+  write_varint(bytes.len() as u64, buffer);
+  buffer.extend_from_slice(bytes);
+}
+
+struct WireReader<'a> {
+  data: &'a [u8],
+  pos: usize
+}
+
+impl <'a> WireReader<'a> {
+  fn is_empty(&self) -> bool {
+    self.pos >= self.data.len()
+  }
+
+  fn read_varint(&mut self) -> anyhow::Result<u64> {
+    let mut value = 0_u64;
+    for shift in (0..64).step_by(7) {
+      let byte = *self.data.get(self.pos).ok_or_else(|| anyhow!("Unexpected end of message"))?;
+      self.pos += 1;
+      value |= ((byte & 0x7F) as u64) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+    Err(anyhow!("Invalid varint"))
+  }
+
+  fn read_slice(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+    // The length can come from the message, so it is checked without adding it to the position
+    if len > self.data.len() - self.pos {
+      return Err(anyhow!("Unexpected end of message"));
+    }
+    let slice = &self.data[self.pos..self.pos + len];
+    self.pos += len;
+    Ok(slice)
+  }
+
+  fn read_fixed32(&mut self) -> anyhow::Result<u32> {
+    let mut bytes = [0_u8; 4];
+    bytes.copy_from_slice(self.read_slice(4)?);
+    Ok(u32::from_le_bytes(bytes))
+  }
+
+  fn read_fixed64(&mut self) -> anyhow::Result<u64> {
+    let mut bytes = [0_u8; 8];
+    bytes.copy_from_slice(self.read_slice(8)?);
+    Ok(u64::from_le_bytes(bytes))
+  }
+
+  fn read_length_delimited(&mut self) -> anyhow::Result<&'a [u8]> {
+    let len = self.read_varint()? as usize;
+    self.read_slice(len)
+  }
+
+  fn skip(&mut self, wire_type: u64) -> anyhow::Result<()> {
+    match wire_type {
+      0 => self.read_varint().map(|_| ()),
+      1 => self.read_slice(8).map(|_| ()),
+      2 => self.read_length_delimited().map(|_| ()),
+      5 => self.read_slice(4).map(|_| ()),
+      _ => Err(anyhow!("Unsupported wire type {}", wire_type))
+    }
+  }
+}
+
+fn tokenise(source: &str) -> anyhow::Result<Vec<String>> {
+  let chars: Vec<char> = source.chars().collect();
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < chars.len() {
+    let ch = chars[i];
+    if ch.is_whitespace() {
+      i += 1;
+    } else if ch == '/' && chars.get(i + 1) == Some(&'/') {
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+    } else if ch == '/' && chars.get(i + 1) == Some(&'*') {
+      i += 2;
+      while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+        i += 1;
+      }
+      i += 2;
+    } else if ch == '"' || ch == '\'' {
+      let start = i;
+      i += 1;
+      while i < chars.len() && chars[i] != ch {
+        if chars[i] == '\\' {
+          i += 1;
+        }
+        i += 1;
+      }
+      if i >= chars.len() {
+        return Err(anyhow!("Unterminated string in proto file"));
+      }
+      i += 1;
+      tokens.push(chars[start..i].iter().collect());
+    } else if ch.is_alphanumeric() || ch == '_' || ch == '.' || ch == '-' || ch == '+' {
+      let start = i;
+      while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.' ||
+        ((chars[i] == '-' || chars[i] == '+') && i == start)) {
+        i += 1;
+      }
+      tokens.push(chars[start..i].iter().collect());
+    } else {
+      tokens.push(ch.to_string());
+      i += 1;
+    }
+  }
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<String>,
+  index: usize,
+  // Field types that need to be resolved once all the types are known, as
+  // (message, field, scope, type name)
+  unresolved: Vec<(String, String, String, String)>
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&str> {
+    self.tokens.get(self.index).map(|t| t.as_str())
+  }
+
+  fn next(&mut self) -> anyhow::Result<String> {
+    let token = self.tokens.get(self.index).cloned()
+      .ok_or_else(|| anyhow!("Unexpected end of proto file"))?;
+    self.index += 1;
+    Ok(token)
+  }
+
+  fn expect(&mut self, expected: &str) -> anyhow::Result<()> {
+    let token = self.next()?;
+    if token == expected {
+      Ok(())
+    } else {
+      Err(anyhow!("Expected '{}' in proto file but found '{}'", expected, token))
+    }
+  }
+
+  fn skip_statement(&mut self) -> anyhow::Result<()> {
+    let mut depth = 0;
+    loop {
+      match self.next()?.as_str() {
+        ";" if depth == 0 => return Ok(()),
+        "{" => depth += 1,
+        "}" => {
+          depth -= 1;
+          if depth == 0 {
+            return Ok(());
+          }
+        }
+        _ => ()
+      }
+    }
+  }
+
+  fn skip_options(&mut self) -> anyhow::Result<()> {
+    if self.peek() == Some("[") {
+      while self.next()? != "]" {}
+    }
+    Ok(())
+  }
+
+  fn parse_file(&mut self, proto: &mut ProtoFile) -> anyhow::Result<()> {
+    while let Some(token) = self.peek() {
+      match token {
+        "package" => {
+          self.next()?;
+          proto.package = self.next()?;
+          self.expect(";")?;
+        }
+        "message" => {
+          let scope = proto.package.clone();
+          self.parse_message(proto, &scope)?;
+        }
+        "enum" => {
+          let scope = proto.package.clone();
+          self.parse_enum(proto, &scope)?;
+        }
+        "service" => self.parse_service(proto)?,
+        "import" => {
+          warn!("Imports in proto files are not supported, so any imported types will not be defined");
+          self.skip_statement()?;
+        }
+        ";" => {
+          self.next()?;
+        }
+        _ => self.skip_statement()?
+      }
+    }
+    Ok(())
+  }
+
+  fn qualified_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+      name.to_string()
+    } else {
+      format!("{}.{}", scope, name)
+    }
+  }
+
+  fn parse_message(&mut self, proto: &mut ProtoFile, scope: &str) -> anyhow::Result<()> {
+    self.expect("message")?;
+    let name = Parser::qualified_name(scope, &self.next()?);
+    self.expect("{")?;
+    let mut fields = vec![];
+    loop {
+      match self.peek().ok_or_else(|| anyhow!("Unexpected end of proto file"))? {
+        "}" => {
+          self.next()?;
+          break;
+        }
+        "message" => self.parse_message(proto, &name)?,
+        "enum" => self.parse_enum(proto, &name)?,
+        "oneof" => {
+          self.next()?;
+          self.next()?;
+          self.expect("{")?;
+          while self.peek() != Some("}") {
+            if self.peek() == Some("option") {
+              self.skip_statement()?;
+            } else {
+              fields.push(self.parse_field(&name)?);
+            }
+          }
+          self.expect("}")?;
+        }
+        "option" | "reserved" | "extensions" | "extend" => self.skip_statement()?,
+        ";" => {
+          self.next()?;
+        }
+        _ => fields.push(self.parse_field(&name)?)
+      }
+    }
+    proto.messages.insert(name.clone(), MessageDescriptor { name, fields });
+    Ok(())
+  }
+
+  fn parse_field(&mut self, message: &str) -> anyhow::Result<FieldDescriptor> {
+    let mut token = self.next()?;
+    let mut repeated = false;
+    if token == "repeated" || token == "optional" || token == "required" {
+      repeated = token == "repeated";
+      token = self.next()?;
+    }
+    let (field_type, type_name) = if token == "map" {
+      self.expect("<")?;
+      let key_type = self.next()?;
+      let key_type = FieldType::scalar(&key_type)
+        .ok_or_else(|| anyhow!("'{}' is not a valid map key type", key_type))?;
+      self.expect(",")?;
+      let value_type = self.next()?;
+      self.expect(">")?;
+      match FieldType::scalar(&value_type) {
+        Some(value_type) => (FieldType::Map(Box::new(key_type), Box::new(value_type)), None),
+        None => (FieldType::Map(Box::new(key_type), Box::new(FieldType::Bytes)), Some(value_type))
+      }
+    } else {
+      match FieldType::scalar(&token) {
+        Some(field_type) => (field_type, None),
+        None => (FieldType::Bytes, Some(token))
+      }
+    };
+    let name = self.next()?;
+    self.expect("=")?;
+    let number = self.next()?;
+    let number = number.parse()
+      .map_err(|_| anyhow!("'{}' is not a valid field number for field '{}'", number, name))?;
+    self.skip_options()?;
+    self.expect(";")?;
+    if let Some(type_name) = type_name {
+      self.unresolved.push((message.to_string(), name.clone(), message.to_string(), type_name));
+    }
+    Ok(FieldDescriptor { name, number, field_type, repeated })
+  }
+
+  fn parse_enum(&mut self, proto: &mut ProtoFile, scope: &str) -> anyhow::Result<()> {
+    self.expect("enum")?;
+    let name = Parser::qualified_name(scope, &self.next()?);
+    self.expect("{")?;
+    let mut values = vec![];
+    loop {
+      match self.peek().ok_or_else(|| anyhow!("Unexpected end of proto file"))? {
+        "}" => {
+          self.next()?;
+          break;
+        }
+        "option" | "reserved" => self.skip_statement()?,
+        ";" => {
+          self.next()?;
+        }
+        _ => {
+          let value = self.next()?;
+          self.expect("=")?;
+          let number = self.next()?;
+          let number = number.parse()
+            .map_err(|_| anyhow!("'{}' is not a valid number for enum value '{}'", number, value))?;
+          self.skip_options()?;
+          self.expect(";")?;
+          values.push((value, number));
+        }
+      }
+    }
+    proto.enums.insert(name.clone(), EnumDescriptor { name, values });
+    Ok(())
+  }
+
+  fn parse_service(&mut self, proto: &mut ProtoFile) -> anyhow::Result<()> {
+    self.expect("service")?;
+    let name = Parser::qualified_name(&proto.package, &self.next()?);
+    self.expect("{")?;
+    let mut methods = vec![];
+    loop {
+      match self.peek().ok_or_else(|| anyhow!("Unexpected end of proto file"))? {
+        "}" => {
+          self.next()?;
+          break;
+        }
+        "rpc" => {
+          self.next()?;
+          let method = self.next()?;
+          self.expect("(")?;
+          let mut input_type = self.next()?;
+          if input_type == "stream" {
+            input_type = self.next()?;
+          }
+          self.expect(")")?;
+          self.expect("returns")?;
+          self.expect("(")?;
+          let mut output_type = self.next()?;
+          let server_streaming = output_type == "stream";
+          if server_streaming {
+            output_type = self.next()?;
+          }
+          self.expect(")")?;
+          if self.peek() == Some("{") {
+            self.skip_statement()?;
+          } else {
+            self.expect(";")?;
+          }
+          methods.push(MethodDescriptor { name: method, input_type, output_type, server_streaming });
+        }
+        _ => self.skip_statement()?
+      }
+    }
+    proto.services.push(ServiceDescriptor { name, methods });
+    Ok(())
+  }
+}
+
+/// Plugin that matches and generates protobuf encoded contents, using the message types from
+/// a `.proto` file
+#[derive(Debug, Clone)]
+pub struct ProtobufPlugin {
+  /// Descriptors used to decode and encode the messages
+  pub proto: ProtoFile
+}
+
+impl ProtobufPlugin {
+  /// Creates a plugin using the descriptors from the proto file
+  pub fn new(proto: ProtoFile) -> ProtobufPlugin {
+    ProtobufPlugin { proto }
+  }
+
+  fn message_type(&self, body: &OptionalBody) -> anyhow::Result<String> {
+    body.content_type()
+      .and_then(|ct| ct.attributes.get("message").cloned())
+      .ok_or_else(|| anyhow!("The content type of protobuf contents must have a 'message' parameter with the message type"))
+  }
+
+  fn decode(&self, message_type: &str, body: &OptionalBody) -> anyhow::Result<Value> {
+    let data = body.value().unwrap_or_default();
+    self.proto.decode(message_type, unframe_grpc_message(&data))
+  }
+}
+
+/// The bodies of gRPC requests and responses have a 5 byte prefix with the compression flag and
+/// length of the message. As a protobuf message can not start with a zero byte (field number 0 is
+/// not valid), a body with an uncompressed prefix that matches the length is a framed message.
+fn unframe_grpc_message(data: &[u8]) -> &[u8] {
+  if data.len() >= 5 && data[0] == 0 {
+    let len = u32::from_be_bytes([data[1], data[2], data[3], data[4]]) as usize;
+    if len == data.len() - 5 {
+      return &data[5..];
+    }
+  }
+  data
+}
+
+impl ContentPlugin for ProtobufPlugin {
+  fn metadata(&self) -> PluginMetadata {
+    PluginMetadata {
+      name: "protobuf".to_string(),
+      version: env!("CARGO_PKG_VERSION").to_string()
+    }
+  }
+
+  fn content_types(&self) -> Vec<ContentType> {
+    PROTOBUF_CONTENT_TYPES.iter().filter_map(|ct| ContentType::parse(*ct).ok()).collect()
+  }
+
+  fn match_contents(
+    &self,
+    expected: &OptionalBody,
+    actual: &OptionalBody,
+    rules: &MatchingRuleCategory,
+    allow_unexpected_keys: bool
+  ) -> anyhow::Result<Vec<Mismatch>> {
+    let message_type = self.message_type(expected)?;
+    let expected_json = self.decode(&message_type, expected)
+      .context("Failed to decode the expected message")?;
+    let actual_json = match self.decode(&message_type, actual) {
+      Ok(json) => json,
+      Err(err) => return Ok(vec![Mismatch::BodyMismatch {
+        path: "$".to_string(),
+        expected: expected.value(),
+        actual: actual.value(),
+        mismatch: format!("Failed to decode the actual contents as a '{}' message: {}", message_type, err)
+      }])
+    };
+    debug!("Comparing protobuf message '{}': {} => {}", message_type, expected_json, actual_json);
+    let config = if allow_unexpected_keys { DiffConfig::AllowUnexpectedKeys } else { DiffConfig::NoUnexpectedKeys };
+    let context = MatchingContext::new(config, rules);
+    Ok(crate::json::compare(&["$"], &expected_json, &actual_json, &context).err().unwrap_or_default())
+  }
+
+  fn generate_contents(
+    &self,
+    body: &OptionalBody,
+    generators: &HashMap<String, Generator>,
+    mode: &GeneratorTestMode
+  ) -> anyhow::Result<OptionalBody> {
+    let message_type = self.message_type(body)?;
+    let json = self.decode(&message_type, body)?;
+    let json_body = OptionalBody::Present(Bytes::from(json.to_string()), ContentType::parse("application/json").ok());
+    let generated = apply_body_generators(mode, &json_body, json_body.content_type(), &hashmap!{}, generators);
+    let generated: Value = serde_json::from_slice(&generated.value().unwrap_or_default())?;
+    let contents = self.proto.encode(&message_type, &generated)?;
+    Ok(OptionalBody::Present(Bytes::from(contents), body.content_type()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  pub(super) const ROUTE_GUIDE: &str = r#"
+    syntax = "proto3";
+
+    package routeguide;
+
+    // Interface exported by the server.
+    service RouteGuide {
+      rpc GetFeature(Point) returns (Feature) {}
+      rpc ListFeatures(Rectangle) returns (stream Feature) {}
+    }
+
+    message Point {
+      int32 latitude = 1;
+      int32 longitude = 2;
+    }
+
+    message Rectangle {
+      Point lo = 1;
+      Point hi = 2;
+    }
+
+    /* A feature names something at a given point. */
+    message Feature {
+      string name = 1;
+      Point location = 2;
+      repeated string tags = 3;
+      map<string, int64> counts = 4;
+      Kind kind = 5;
+
+      enum Kind {
+        UNKNOWN = 0;
+        LANDMARK = 1;
+      }
+    }
+  "#;
+
+  #[test]
+  fn parses_the_messages_and_services_from_the_proto_file() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    expect!(proto.package.as_str()).to(be_equal_to("routeguide"));
+    expect!(proto.messages.keys().cloned().collect::<Vec<_>>()).to(be_equal_to(vec![
+      "routeguide.Feature".to_string(), "routeguide.Point".to_string(), "routeguide.Rectangle".to_string()
+    ]));
+    let feature = proto.find_message("routeguide.Feature").unwrap();
+    expect!(feature.fields[1].field_type.clone()).to(be_equal_to(FieldType::Message("routeguide.Point".to_string())));
+    expect!(feature.fields[4].field_type.clone()).to(be_equal_to(FieldType::Enum("routeguide.Feature.Kind".to_string())));
+    let method = proto.find_method("RouteGuide", "ListFeatures").unwrap();
+    expect!(method.clone()).to(be_equal_to(MethodDescriptor {
+      name: "ListFeatures".to_string(),
+      input_type: "routeguide.Rectangle".to_string(),
+      output_type: "routeguide.Feature".to_string(),
+      server_streaming: true
+    }));
+  }
+
+  #[test]
+  fn returns_an_error_for_undefined_types() {
+    expect!(ProtoFile::parse("message A { B b = 1; }")).to(be_err());
+  }
+
+  #[test]
+  fn encodes_and_decodes_messages() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    let feature = json!({
+      "name": "Big Tree",
+      "location": { "latitude": 409146138, "longitude": -746188906 },
+      "tags": ["tree", "big"],
+      "counts": { "visits": 100 },
+      "kind": "LANDMARK"
+    });
+    let encoded = proto.encode("routeguide.Feature", &feature).unwrap();
+    expect!(proto.decode("routeguide.Feature", &encoded)).to(be_ok().value(feature));
+  }
+
+  #[test]
+  fn decoding_sets_the_default_values_for_missing_fields() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    expect!(proto.decode("routeguide.Feature", &[])).to(be_ok().value(json!({
+      "name": "",
+      "tags": [],
+      "counts": {},
+      "kind": "UNKNOWN"
+    })));
+  }
+
+  #[test]
+  fn decodes_known_bytes() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    expect!(proto.decode("routeguide.Point", &[0x08, 0x96, 0x01, 0x10, 0x01])).to(be_ok().value(json!({
+      "latitude": 150,
+      "longitude": 1
+    })));
+    expect!(proto.decode("routeguide.Point", &[0x08, 0x96])).to(be_err());
+  }
+
+  pub(super) fn feature_body(proto: &ProtoFile, json: Value) -> OptionalBody {
+    OptionalBody::Present(Bytes::from(proto.encode("routeguide.Feature", &json).unwrap()),
+      ContentType::parse("application/protobuf; message=routeguide.Feature").ok())
+  }
+
+  #[test]
+  fn plugin_matches_the_messages_field_by_field() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    let plugin = ProtobufPlugin::new(proto.clone());
+    let expected = feature_body(&proto, json!({ "name": "Big Tree", "location": { "latitude": 1, "longitude": 2 } }));
+    let actual = feature_body(&proto, json!({ "name": "Big Tree", "location": { "latitude": 1, "longitude": 3 } }));
+
+    let result = plugin.match_contents(&expected, &expected, &MatchingRuleCategory::empty("body"), false);
+    expect!(result.unwrap().iter()).to(be_empty());
+    let result = plugin.match_contents(&expected, &actual, &MatchingRuleCategory::empty("body"), false).unwrap();
+    expect!(result.iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$.location.longitude -> Expected '2' to be equal to '3'".to_string()
+    ]));
+  }
+
+  #[test]
+  fn plugin_decodes_framed_grpc_messages() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    let plugin = ProtobufPlugin::new(proto);
+    let content_type = ContentType::parse("application/grpc; message=routeguide.Point").ok();
+    let framed = OptionalBody::Present(Bytes::from_static(&[0, 0, 0, 0, 5, 0x08, 0x96, 0x01, 0x10, 0x01]), content_type.clone());
+    let unframed = OptionalBody::Present(Bytes::from_static(&[0x08, 0x96, 0x01, 0x10, 0x01]), content_type);
+    let result = plugin.match_contents(&unframed, &framed, &MatchingRuleCategory::empty("body"), false);
+    expect!(result.unwrap().iter()).to(be_empty());
+  }
+
+  #[test]
+  fn decode_fails_with_truncated_or_oversized_fields() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    expect!(proto.decode("routeguide.Feature", &[0x0A, 0x05, b'a'])).to(be_err());
+    expect!(proto.decode("routeguide.Feature", &[0x0A, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01])).to(be_err());
+    expect!(proto.decode("routeguide.Point", &[0x08])).to(be_err());
+  }
+
+  #[test]
+  fn plugin_requires_the_message_type() {
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    let plugin = ProtobufPlugin::new(proto);
+    let body = OptionalBody::Present(Bytes::new(), ContentType::parse("application/protobuf").ok());
+    expect!(plugin.match_contents(&body, &body, &MatchingRuleCategory::empty("body"), false)).to(be_err());
+  }
+}
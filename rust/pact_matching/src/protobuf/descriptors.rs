@@ -0,0 +1,341 @@
+//! Loading of the protobuf descriptors embedded in the pact metadata. This module is only
+//! available with the `protobuf` feature.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use log::*;
+use serde_json::{json, Value};
+
+use crate::models::Pact;
+use crate::models::v4::V4Pact;
+use crate::protobuf::{
+  EnumDescriptor, FieldDescriptor, FieldType, MessageDescriptor, MethodDescriptor, Parser, ProtobufPlugin,
+  ProtoFile, ServiceDescriptor
+};
+
+/// Key of the pact metadata with the protobuf descriptors of the pact
+pub const PROTOBUF_METADATA_KEY: &str = "protobuf";
+
+/// Subset of `google/protobuf/descriptor.proto` needed to load the messages, enums and services
+/// from a `FileDescriptorSet`. Any other fields in the descriptors are skipped when decoding.
+const DESCRIPTOR_PROTO: &str = r#"
+  syntax = "proto2";
+  package google.protobuf;
+
+  message FileDescriptorSet {
+    repeated FileDescriptorProto file = 1;
+  }
+
+  message FileDescriptorProto {
+    optional string name = 1;
+    optional string package = 2;
+    repeated DescriptorProto message_type = 4;
+    repeated EnumDescriptorProto enum_type = 5;
+    repeated ServiceDescriptorProto service = 6;
+  }
+
+  message DescriptorProto {
+    optional string name = 1;
+    repeated FieldDescriptorProto field = 2;
+    repeated DescriptorProto nested_type = 3;
+    repeated EnumDescriptorProto enum_type = 4;
+    optional MessageOptions options = 7;
+  }
+
+  message MessageOptions {
+    optional bool map_entry = 7;
+  }
+
+  message FieldDescriptorProto {
+    optional string name = 1;
+    optional int32 number = 3;
+    optional int32 label = 4;
+    optional int32 type = 5;
+    optional string type_name = 6;
+  }
+
+  message EnumDescriptorProto {
+    optional string name = 1;
+    repeated EnumValueDescriptorProto value = 2;
+  }
+
+  message EnumValueDescriptorProto {
+    optional string name = 1;
+    optional int32 number = 2;
+  }
+
+  message ServiceDescriptorProto {
+    optional string name = 1;
+    repeated MethodDescriptorProto method = 2;
+  }
+
+  message MethodDescriptorProto {
+    optional string name = 1;
+    optional string input_type = 2;
+    optional string output_type = 3;
+    optional bool server_streaming = 6;
+  }
+"#;
+
+lazy_static::lazy_static! {
+  static ref DESCRIPTORS: ProtoFile = ProtoFile::parse(DESCRIPTOR_PROTO)
+    .expect("descriptor.proto subset is not valid");
+}
+
+impl ProtoFile {
+  /// Loads the descriptors from a `FileDescriptorSet` (i.e. as generated by
+  /// `protoc --descriptor_set_out`). The messages of all the files in the set are loaded, and the
+  /// package of the first file is used as the package.
+  pub fn from_descriptor_set(data: &[u8]) -> anyhow::Result<ProtoFile> {
+    let descriptor_set = DESCRIPTORS.decode("google.protobuf.FileDescriptorSet", data)
+      .context("Failed to decode the file descriptor set")?;
+    let files = json_array(&descriptor_set, "file");
+
+    // Map fields are encoded as repeated map entry messages, so the entry types are needed to
+    // convert them to map fields
+    let mut map_entries = HashMap::new();
+    for file in files {
+      let package = json_str(file, "package");
+      collect_map_entries(&package, json_array(file, "message_type"), &mut map_entries);
+    }
+
+    let mut proto = ProtoFile {
+      package: files.first().map(|file| json_str(file, "package")).unwrap_or_default(),
+      .. ProtoFile::default()
+    };
+    for file in files {
+      let package = json_str(file, "package");
+      for message in json_array(file, "message_type") {
+        proto.add_descriptor_message(&package, message, &map_entries)?;
+      }
+      for descriptor in json_array(file, "enum_type") {
+        proto.add_descriptor_enum(&package, descriptor);
+      }
+      for service in json_array(file, "service") {
+        proto.services.push(ServiceDescriptor {
+          name: Parser::qualified_name(&package, &json_str(service, "name")),
+          methods: json_array(service, "method").iter().map(|method| MethodDescriptor {
+            name: json_str(method, "name"),
+            input_type: json_str(method, "input_type").trim_start_matches('.').to_string(),
+            output_type: json_str(method, "output_type").trim_start_matches('.').to_string(),
+            server_streaming: method.get("server_streaming").and_then(|v| v.as_bool()).unwrap_or(false)
+          }).collect()
+        });
+      }
+    }
+    Ok(proto)
+  }
+
+  fn add_descriptor_message(
+    &mut self,
+    scope: &str,
+    message: &Value,
+    map_entries: &HashMap<String, (FieldType, FieldType)>
+  ) -> anyhow::Result<()> {
+    let name = Parser::qualified_name(scope, &json_str(message, "name"));
+    for nested in json_array(message, "nested_type") {
+      self.add_descriptor_message(&name, nested, map_entries)?;
+    }
+    for descriptor in json_array(message, "enum_type") {
+      self.add_descriptor_enum(&name, descriptor);
+    }
+    if map_entries.contains_key(&name) {
+      return Ok(());
+    }
+
+    let mut fields = vec![];
+    for field in json_array(message, "field") {
+      let field_type = descriptor_field_type(field)?;
+      let (field_type, repeated) = match &field_type {
+        FieldType::Message(type_name) if map_entries.contains_key(type_name) => {
+          let (key, value) = map_entries[type_name].clone();
+          (FieldType::Map(Box::new(key), Box::new(value)), false)
+        }
+        _ => (field_type, field.get("label").and_then(|v| v.as_i64()) == Some(3))
+      };
+      fields.push(FieldDescriptor {
+        name: json_str(field, "name"),
+        number: field.get("number").and_then(|v| v.as_u64()).unwrap_or_default(),
+        field_type,
+        repeated
+      });
+    }
+    self.messages.insert(name.clone(), MessageDescriptor { name, fields });
+    Ok(())
+  }
+
+  fn add_descriptor_enum(&mut self, scope: &str, descriptor: &Value) {
+    let name = Parser::qualified_name(scope, &json_str(descriptor, "name"));
+    let values = json_array(descriptor, "value").iter()
+      .map(|value| (json_str(value, "name"), value.get("number").and_then(|v| v.as_i64()).unwrap_or_default() as i32))
+      .collect();
+    self.enums.insert(name.clone(), EnumDescriptor { name, values });
+  }
+}
+
+fn json_array<'a>(json: &'a Value, field: &str) -> &'a [Value] {
+  json.get(field).and_then(|v| v.as_array()).map(|v| v.as_slice()).unwrap_or_default()
+}
+
+fn json_str(json: &Value, field: &str) -> String {
+  json.get(field).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+}
+
+fn collect_map_entries(scope: &str, messages: &[Value], map_entries: &mut HashMap<String, (FieldType, FieldType)>) {
+  for message in messages {
+    let name = Parser::qualified_name(scope, &json_str(message, "name"));
+    let map_entry = message.pointer("/options/map_entry").and_then(|v| v.as_bool()).unwrap_or(false);
+    if map_entry {
+      let fields = json_array(message, "field");
+      let field_type = |number: u64| fields.iter()
+        .find(|f| f.get("number").and_then(|v| v.as_u64()) == Some(number))
+        .and_then(|f| descriptor_field_type(f).ok())
+        .unwrap_or(FieldType::Bytes);
+      map_entries.insert(name.clone(), (field_type(1), field_type(2)));
+    }
+    collect_map_entries(&name, json_array(message, "nested_type"), map_entries);
+  }
+}
+
+/// Converts the type of a `FieldDescriptorProto` (see the `Type` enum in `descriptor.proto`)
+fn descriptor_field_type(field: &Value) -> anyhow::Result<FieldType> {
+  let type_name = || json_str(field, "type_name").trim_start_matches('.').to_string();
+  match field.get("type").and_then(|v| v.as_i64()).unwrap_or_default() {
+    1 => Ok(FieldType::Double),
+    2 => Ok(FieldType::Float),
+    3 => Ok(FieldType::Int64),
+    4 => Ok(FieldType::UInt64),
+    5 => Ok(FieldType::Int32),
+    6 => Ok(FieldType::Fixed64),
+    7 => Ok(FieldType::Fixed32),
+    8 => Ok(FieldType::Bool),
+    9 => Ok(FieldType::String),
+    11 => Ok(FieldType::Message(type_name())),
+    12 => Ok(FieldType::Bytes),
+    13 => Ok(FieldType::UInt32),
+    14 => Ok(FieldType::Enum(type_name())),
+    15 => Ok(FieldType::SFixed32),
+    16 => Ok(FieldType::SFixed64),
+    17 => Ok(FieldType::SInt32),
+    18 => Ok(FieldType::SInt64),
+    field_type => Err(anyhow!("Field '{}' has an unsupported type {}", json_str(field, "name"), field_type))
+  }
+}
+
+/// Loads the protobuf descriptors embedded in the pact metadata, if the pact has any. The
+/// descriptors are a Base64 encoded `FileDescriptorSet` in the `descriptorSet` attribute of the
+/// `protobuf` metadata, i.e. `"metadata": { "protobuf": { "descriptorSet": "CqgCCg..." } }`.
+pub fn descriptors_from_pact(pact: &dyn Pact) -> Option<anyhow::Result<ProtoFile>> {
+  pact.metadata().get(PROTOBUF_METADATA_KEY)
+    .and_then(|metadata| metadata.get("descriptorSet").cloned())
+    .map(|descriptor_set| base64::decode(descriptor_set)
+      .context("The protobuf descriptor set in the pact metadata is not valid Base64")
+      .and_then(|data| ProtoFile::from_descriptor_set(&data)))
+}
+
+/// Creates a protobuf plugin with the descriptors embedded in the pact metadata (see
+/// `descriptors_from_pact`), so that protobuf message contents and gRPC bodies of the pact can be
+/// matched field by field with `plugins::with_plugins`. Returns None if the pact does not have any
+/// descriptors.
+pub fn plugin_from_pact(pact: &dyn Pact) -> anyhow::Result<Option<ProtobufPlugin>> {
+  match descriptors_from_pact(pact) {
+    Some(proto) => {
+      let proto = proto?;
+      debug!("Loaded the {} protobuf message types from the pact metadata", proto.messages.len());
+      Ok(Some(ProtobufPlugin::new(proto)))
+    }
+    None => Ok(None)
+  }
+}
+
+/// Embeds the protobuf descriptors (a `FileDescriptorSet`) in the metadata of the pact, so the
+/// protobuf contents of the pact can be matched when it is verified.
+pub fn add_descriptor_set(pact: &mut V4Pact, descriptor_set: &[u8]) {
+  pact.metadata.insert(PROTOBUF_METADATA_KEY.to_string(), json!({
+    "descriptorSet": base64::encode(descriptor_set)
+  }));
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::json;
+
+  use crate::models::matchingrules::MatchingRuleCategory;
+  use crate::plugins::ContentPlugin;
+  use crate::protobuf::tests::{feature_body, ROUTE_GUIDE};
+
+  use super::*;
+
+  fn route_guide_descriptor_set() -> Vec<u8> {
+    let field = |name: &str, number: i32, label: i32, field_type: i32, type_name: Option<&str>| {
+      let mut field = json!({ "name": name, "number": number, "label": label, "type": field_type });
+      if let Some(type_name) = type_name {
+        field["type_name"] = json!(type_name);
+      }
+      field
+    };
+    let descriptor_set = json!({
+      "file": [{
+        "name": "route_guide.proto",
+        "package": "routeguide",
+        "message_type": [
+          { "name": "Point", "field": [ field("latitude", 1, 1, 5, None), field("longitude", 2, 1, 5, None) ] },
+          { "name": "Rectangle", "field": [
+            field("lo", 1, 1, 11, Some(".routeguide.Point")),
+            field("hi", 2, 1, 11, Some(".routeguide.Point"))
+          ] },
+          {
+            "name": "Feature",
+            "field": [
+              field("name", 1, 1, 9, None),
+              field("location", 2, 1, 11, Some(".routeguide.Point")),
+              field("tags", 3, 3, 9, None),
+              field("counts", 4, 3, 11, Some(".routeguide.Feature.CountsEntry")),
+              field("kind", 5, 1, 14, Some(".routeguide.Feature.Kind"))
+            ],
+            "nested_type": [{
+              "name": "CountsEntry",
+              "field": [ field("key", 1, 1, 9, None), field("value", 2, 1, 3, None) ],
+              "options": { "map_entry": true }
+            }],
+            "enum_type": [{ "name": "Kind", "value": [ { "name": "UNKNOWN", "number": 0 }, { "name": "LANDMARK", "number": 1 } ] }]
+          }
+        ],
+        "service": [{
+          "name": "RouteGuide",
+          "method": [
+            { "name": "GetFeature", "input_type": ".routeguide.Point", "output_type": ".routeguide.Feature" },
+            { "name": "ListFeatures", "input_type": ".routeguide.Rectangle", "output_type": ".routeguide.Feature", "server_streaming": true }
+          ]
+        }]
+      }]
+    });
+    DESCRIPTORS.encode("google.protobuf.FileDescriptorSet", &descriptor_set).unwrap()
+  }
+
+  #[test]
+  fn loads_the_descriptors_from_a_file_descriptor_set() {
+    let proto = ProtoFile::from_descriptor_set(&route_guide_descriptor_set()).unwrap();
+    expect!(proto).to(be_equal_to(ProtoFile::parse(ROUTE_GUIDE).unwrap()));
+    expect!(ProtoFile::from_descriptor_set(&[0x0a, 0x05])).to(be_err());
+  }
+
+  #[test]
+  fn creates_a_plugin_with_the_descriptors_embedded_in_the_pact_metadata() {
+    let mut pact = V4Pact::default();
+    expect!(plugin_from_pact(&pact).unwrap().is_none()).to(be_true());
+
+    add_descriptor_set(&mut pact, &route_guide_descriptor_set());
+    let plugin = plugin_from_pact(&pact).unwrap();
+
+    let proto = ProtoFile::parse(ROUTE_GUIDE).unwrap();
+    let expected = feature_body(&proto, json!({ "name": "Big Tree", "counts": { "visits": 1 } }));
+    let actual = feature_body(&proto, json!({ "name": "Big Tree", "counts": { "visits": 2 } }));
+    let result = plugin.unwrap().match_contents(&expected, &actual, &MatchingRuleCategory::empty("body"), false).unwrap();
+    expect!(result.iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$.counts.visits -> Expected '1' to be equal to '2'".to_string()
+    ]));
+  }
+}
@@ -0,0 +1,344 @@
+//! The `spec_runner` module runs the pact-specification test cases against the matching engine.
+//!
+//! Each test case is a JSON file with the expected and actual request, response or message, and
+//! whether they should match:
+//!
+//! ```json
+//! {
+//!   "match": false,
+//!   "comment": "Methods is incorrect",
+//!   "expected": { "method": "POST", "path": "/" },
+//!   "actual": { "method": "GET", "path": "/" }
+//! }
+//! ```
+//!
+//! Test cases are laid out in the same directory structure as the
+//! [pact-specification project](https://github.com/pact-foundation/pact-specification), i.e.
+//! `v3/request/method/different method.json`. The specification version (`v1`, `v1_1`, `v2`, `v3`
+//! or `v4`) and the type of test case (`request`, `response` or `message`) are taken from the
+//! directory names in the path of the file.
+
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use log::*;
+use serde_json::{json, Value};
+
+use pact_models::PactSpecification;
+
+use crate::{match_interaction, match_interaction_request, match_interaction_response, Mismatch};
+use crate::models::{http_interaction_from_json, message_interaction_from_json};
+
+/// Type of the pact-specification test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestCaseType {
+  /// Compares two HTTP requests
+  Request,
+  /// Compares two HTTP responses
+  Response,
+  /// Compares two messages
+  Message
+}
+
+impl TestCaseType {
+  /// Returns the test case type from a directory name
+  pub fn from_dir_name(name: &str) -> Option<TestCaseType> {
+    match name {
+      "request" => Some(TestCaseType::Request),
+      "response" => Some(TestCaseType::Response),
+      "message" => Some(TestCaseType::Message),
+      _ => None
+    }
+  }
+}
+
+impl Display for TestCaseType {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TestCaseType::Request => write!(f, "request"),
+      TestCaseType::Response => write!(f, "response"),
+      TestCaseType::Message => write!(f, "message")
+    }
+  }
+}
+
+/// Returns the specification version from a directory name (i.e. `v1_1` or `v3`)
+pub fn spec_from_dir_name(name: &str) -> Option<PactSpecification> {
+  match name.to_lowercase().as_str() {
+    "v1" => Some(PactSpecification::V1),
+    "v1_1" | "v1.1" => Some(PactSpecification::V1_1),
+    "v2" => Some(PactSpecification::V2),
+    "v3" => Some(PactSpecification::V3),
+    "v4" => Some(PactSpecification::V4),
+    _ => None
+  }
+}
+
+/// A single pact-specification test case
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecTestCase {
+  /// Source of the test case (normally the path to the file)
+  pub source: String,
+  /// Specification version to use to load and match the test case
+  pub spec: PactSpecification,
+  /// Type of test case
+  pub case_type: TestCaseType,
+  /// If the actual value is expected to match the expected one
+  pub expected_match: bool,
+  /// Comment describing the test case
+  pub comment: Option<String>,
+  /// Expected request, response or message
+  pub expected: Value,
+  /// Actual request, response or message
+  pub actual: Value
+}
+
+impl SpecTestCase {
+  /// Loads the test case from its JSON form
+  pub fn from_json(
+    source: &str,
+    spec: PactSpecification,
+    case_type: TestCaseType,
+    json: &Value
+  ) -> anyhow::Result<SpecTestCase> {
+    let expected_match = json.get("match")
+      .and_then(|m| m.as_bool())
+      .ok_or_else(|| anyhow!("Test case '{}' does not have a boolean 'match' attribute", source))?;
+    let expected = json.get("expected")
+      .ok_or_else(|| anyhow!("Test case '{}' does not have an 'expected' attribute", source))?;
+    let actual = json.get("actual")
+      .ok_or_else(|| anyhow!("Test case '{}' does not have an 'actual' attribute", source))?;
+    Ok(SpecTestCase {
+      source: source.to_string(),
+      spec,
+      case_type,
+      expected_match,
+      comment: json.get("comment").and_then(|c| c.as_str()).map(|c| c.to_string()),
+      expected: expected.clone(),
+      actual: actual.clone()
+    })
+  }
+
+  /// Loads the test case from a JSON file. The specification version and type of test case are
+  /// taken from the directory names in the path, unless a specification version is given.
+  pub fn load(path: &Path, spec: Option<PactSpecification>) -> anyhow::Result<SpecTestCase> {
+    let source = path.to_string_lossy().to_string();
+    let dirs = path.parent().map(|parent| parent.iter()
+      .map(|name| name.to_string_lossy().to_string())
+      .collect::<Vec<String>>())
+      .unwrap_or_default();
+    let spec = spec.or_else(|| dirs.iter().rev().find_map(|name| spec_from_dir_name(name)))
+      .ok_or_else(|| anyhow!("Could not determine the specification version for test case '{}'", source))?;
+    let case_type = dirs.iter().rev().find_map(|name| TestCaseType::from_dir_name(name))
+      .ok_or_else(|| anyhow!("Could not determine the type of test case for '{}', it must be in a request, response or message directory", source))?;
+    let contents = fs::read_to_string(path)?;
+    // Some of the test case files from the pact-specification project start with a byte order mark
+    let json = serde_json::from_str(contents.trim_start_matches('\u{feff}'))
+      .map_err(|err| anyhow!("Failed to parse test case '{}' - {}", source, err))?;
+    SpecTestCase::from_json(&source, spec, case_type, &json)
+  }
+
+  /// Name of the test case (the file name without the extension)
+  pub fn name(&self) -> String {
+    Path::new(&self.source).file_stem()
+      .map(|name| name.to_string_lossy().to_string())
+      .unwrap_or_else(|| self.source.clone())
+  }
+
+  fn interaction_json(&self, value: &Value) -> Value {
+    match self.case_type {
+      TestCaseType::Request => json!({ "type": "Synchronous/HTTP", "request": value }),
+      TestCaseType::Response => json!({ "type": "Synchronous/HTTP", "response": value }),
+      TestCaseType::Message => {
+        let mut json = value.clone();
+        if let Some(map) = json.as_object_mut() {
+          map.insert("type".to_string(), json!("Asynchronous/Messages"));
+        }
+        json
+      }
+    }
+  }
+
+  fn mismatches(&self) -> anyhow::Result<Vec<Mismatch>> {
+    let expected_json = self.interaction_json(&self.expected);
+    let actual_json = self.interaction_json(&self.actual);
+    match self.case_type {
+      TestCaseType::Request | TestCaseType::Response => {
+        let expected = http_interaction_from_json(&self.source, &expected_json, &self.spec)?;
+        let actual = http_interaction_from_json(&self.source, &actual_json, &self.spec)?;
+        if self.case_type == TestCaseType::Request {
+          match_interaction_request(expected, actual, &self.spec)
+            .map(|result| result.mismatches())
+            .map_err(|err| anyhow!(err))
+        } else {
+          match_interaction_response(expected, actual, &self.spec).map_err(|err| anyhow!(err))
+        }
+      },
+      TestCaseType::Message => {
+        let expected = message_interaction_from_json(&self.source, &expected_json, &self.spec)?;
+        let actual = message_interaction_from_json(&self.source, &actual_json, &self.spec)?;
+        match_interaction(expected, actual, &self.spec).map_err(|err| anyhow!(err))
+      }
+    }
+  }
+
+  /// Runs the test case against the matching engine
+  pub fn run(&self) -> SpecTestResult {
+    debug!("Running test case '{}'", self.source);
+    match self.mismatches() {
+      Ok(mismatches) => SpecTestResult {
+        test_case: self.clone(),
+        mismatches,
+        error: None
+      },
+      Err(err) => SpecTestResult {
+        test_case: self.clone(),
+        mismatches: vec![],
+        error: Some(err.to_string())
+      }
+    }
+  }
+}
+
+/// Result of running a pact-specification test case
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecTestResult {
+  /// Test case that was run
+  pub test_case: SpecTestCase,
+  /// Mismatches returned by the matching engine
+  pub mismatches: Vec<Mismatch>,
+  /// Error if the test case could not be run (i.e. the expected or actual values could not be loaded)
+  pub error: Option<String>
+}
+
+impl SpecTestResult {
+  /// If the result of the matching engine agrees with the test case
+  pub fn passed(&self) -> bool {
+    self.error.is_none() && self.mismatches.is_empty() == self.test_case.expected_match
+  }
+
+  /// Converts the result to JSON
+  pub fn to_json(&self) -> Value {
+    json!({
+      "source": self.test_case.source,
+      "spec": self.test_case.spec.to_string(),
+      "type": self.test_case.case_type.to_string(),
+      "comment": self.test_case.comment,
+      "match": self.test_case.expected_match,
+      "passed": self.passed(),
+      "mismatches": self.mismatches.iter().map(|m| m.to_json()).collect::<Vec<Value>>(),
+      "error": self.error
+    })
+  }
+}
+
+fn find_test_case_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+  let mut entries = fs::read_dir(dir)?
+    .map(|entry| entry.map(|e| e.path()))
+    .collect::<Result<Vec<PathBuf>, _>>()?;
+  entries.sort();
+  for path in entries {
+    if path.is_dir() {
+      find_test_case_files(&path, files)?;
+    } else if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+      files.push(path);
+    }
+  }
+  Ok(())
+}
+
+/// Loads all the test cases (`*.json` files) in the directory and its sub-directories, sorted by
+/// path. If a specification version is given, it is used for all the test cases instead of the
+/// version from the directory names.
+pub fn load_test_cases(dir: &Path, spec: Option<PactSpecification>) -> anyhow::Result<Vec<SpecTestCase>> {
+  let mut files = vec![];
+  find_test_case_files(dir, &mut files)?;
+  files.iter()
+    .map(|file| SpecTestCase::load(file, spec.clone()))
+    .collect()
+}
+
+/// Loads and runs all the test cases in the directory (see `load_test_cases`)
+pub fn run_test_cases(dir: &Path, spec: Option<PactSpecification>) -> anyhow::Result<Vec<SpecTestResult>> {
+  Ok(load_test_cases(dir, spec)?.iter().map(|test_case| test_case.run()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  fn test_case(case_type: TestCaseType, json: Value) -> SpecTestCase {
+    SpecTestCase::from_json("test.json", PactSpecification::V3, case_type, &json).unwrap()
+  }
+
+  #[test]
+  fn spec_from_dir_name_test() {
+    expect!(spec_from_dir_name("v1_1")).to(be_some().value(PactSpecification::V1_1));
+    expect!(spec_from_dir_name("V4")).to(be_some().value(PactSpecification::V4));
+    expect!(spec_from_dir_name("request")).to(be_none());
+  }
+
+  #[test]
+  fn from_json_requires_the_match_expected_and_actual_attributes() {
+    expect!(SpecTestCase::from_json("test.json", PactSpecification::V3, TestCaseType::Request,
+      &json!({ "expected": {}, "actual": {} }))).to(be_err());
+    expect!(SpecTestCase::from_json("test.json", PactSpecification::V3, TestCaseType::Request,
+      &json!({ "match": true, "actual": {} }))).to(be_err());
+    expect!(SpecTestCase::from_json("test.json", PactSpecification::V3, TestCaseType::Request,
+      &json!({ "match": true, "expected": {} }))).to(be_err());
+  }
+
+  #[test]
+  fn run_request_test_case() {
+    let case = test_case(TestCaseType::Request, json!({
+      "match": false,
+      "comment": "Methods is incorrect",
+      "expected": { "method": "POST", "path": "/" },
+      "actual": { "method": "GET", "path": "/" }
+    }));
+    let result = case.run();
+    expect!(result.mismatches.len()).to(be_equal_to(1));
+    expect!(result.passed()).to(be_true());
+
+    let case = SpecTestCase { expected_match: true, .. case };
+    expect!(case.run().passed()).to(be_false());
+  }
+
+  #[test]
+  fn run_response_and_message_test_cases() {
+    let case = test_case(TestCaseType::Response, json!({
+      "match": true,
+      "expected": { "status": 200 },
+      "actual": { "status": 200, "headers": { "X-Extra": "1" } }
+    }));
+    expect!(case.run().passed()).to(be_true());
+
+    let case = test_case(TestCaseType::Message, json!({
+      "match": false,
+      "expected": { "contents": { "a": 1 }, "metaData": { "contentType": "application/json" } },
+      "actual": { "contents": { "a": 2 }, "metaData": { "contentType": "application/json" } }
+    }));
+    expect!(case.run().passed()).to(be_true());
+  }
+
+  #[test]
+  fn load_takes_the_spec_and_type_from_the_directories() {
+    let dir = std::env::temp_dir().join("pact_spec_runner_test").join("v2").join("response").join("status");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("different status.json");
+    fs::write(&file, r#"{"match": false, "expected": {"status": 202}, "actual": {"status": 200}}"#).unwrap();
+
+    let case = SpecTestCase::load(&file, None).unwrap();
+    expect!(case.spec.clone()).to(be_equal_to(PactSpecification::V2));
+    expect!(case.case_type).to(be_equal_to(TestCaseType::Response));
+    expect!(case.name()).to(be_equal_to("different status".to_string()));
+    expect!(SpecTestCase::load(&file, Some(PactSpecification::V4)).unwrap().spec).to(be_equal_to(PactSpecification::V4));
+
+    let results = run_test_cases(&std::env::temp_dir().join("pact_spec_runner_test"), None).unwrap();
+    expect!(results.iter().all(|result| result.passed())).to(be_true());
+  }
+}
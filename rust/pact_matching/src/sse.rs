@@ -0,0 +1,282 @@
+//! Matching of server-sent event (`text/event-stream`) bodies. Bodies are parsed into a sequence of
+//! events, and the `id`, `event`, `data` and `retry` fields of each event are compared individually.
+//! Matching rules can be defined for the fields of the events (i.e. `$[*].data` or `$[0].id`).
+
+use itertools::Itertools;
+use log::*;
+
+use pact_models::OptionalBody;
+
+use crate::matchers::{match_values, Matches};
+use crate::MatchingContext;
+use crate::Mismatch;
+use crate::DiffConfig;
+use crate::models::HttpPart;
+use crate::models::matchingrules::MatchingRule;
+
+/// Names of the fields of an event, in the order they are compared
+const EVENT_FIELDS: [&str; 4] = ["id", "event", "data", "retry"];
+
+/// Server-sent event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+  /// Event ID (`id` field)
+  pub id: Option<String>,
+  /// Event type (`event` field)
+  pub event: Option<String>,
+  /// Event data. Multiple `data` fields are joined with line breaks.
+  pub data: Option<String>,
+  /// Reconnection time (`retry` field)
+  pub retry: Option<String>
+}
+
+impl Event {
+  /// Returns the value of the field with the given name
+  pub fn field(&self, name: &str) -> Option<&String> {
+    match name {
+      "id" => self.id.as_ref(),
+      "event" => self.event.as_ref(),
+      "data" => self.data.as_ref(),
+      "retry" => self.retry.as_ref(),
+      _ => None
+    }
+  }
+}
+
+/// Splits the event stream into the blocks of lines of each event, including the blank line that
+/// ends the event. Blocks that only contain comments are kept, so the stream can be sent on as is.
+pub fn split_events(data: &str) -> Vec<String> {
+  let mut events = vec![];
+  let mut event = String::new();
+  let mut lines = data.split_inclusive('\n').peekable();
+  while let Some(line) = lines.next() {
+    event.push_str(line);
+    if line.trim_end_matches(['\r', '\n']).is_empty() {
+      // Multiple blank lines between events belong to the previous event
+      while let Some(next) = lines.peek() {
+        if next.trim_end_matches(['\r', '\n']).is_empty() {
+          event.push_str(next);
+          lines.next();
+        } else {
+          break;
+        }
+      }
+      events.push(event);
+      event = String::new();
+    }
+  }
+  if !event.is_empty() {
+    events.push(event);
+  }
+  events
+}
+
+/// Parses the event stream into events. Comment lines (starting with a colon) and unknown fields
+/// are ignored, as are blocks that do not contain any fields.
+pub fn parse_events(data: &str) -> Vec<Event> {
+  let mut events = vec![];
+  let mut event = Event::default();
+  let mut has_fields = false;
+  for line in data.lines().map(|line| line.trim_end_matches('\r')) {
+    if line.is_empty() {
+      if has_fields {
+        events.push(event);
+      }
+      event = Event::default();
+      has_fields = false;
+    } else if !line.starts_with(':') {
+      let (name, value) = match line.find(':') {
+        Some(index) => {
+          let value = &line[index + 1..];
+          (&line[..index], value.strip_prefix(' ').unwrap_or(value))
+        },
+        None => (line, "")
+      };
+      match name {
+        "id" => event.id = Some(value.to_string()),
+        "event" => event.event = Some(value.to_string()),
+        "data" => event.data = Some(match event.data {
+          Some(data) => format!("{}\n{}", data, value),
+          None => value.to_string()
+        }),
+        "retry" => event.retry = Some(value.to_string()),
+        _ => {
+          trace!("Ignoring unknown event field '{}'", name);
+          continue;
+        }
+      }
+      has_fields = true;
+    }
+  }
+  if has_fields {
+    events.push(event);
+  }
+  events
+}
+
+fn compare_field(index: usize, name: &str, expected: &str, actual: &str,
+  context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+  let event_index = index.to_string();
+  let path = vec!["$", event_index.as_str(), name];
+  let result = if context.matcher_is_defined(&path) {
+    match_values(&path, context, &expected.to_string(), &actual.to_string())
+  } else {
+    expected.to_string().matches(&actual.to_string(), &MatchingRule::Equality).map_err(|err| vec![err])
+  };
+  debug!("Comparing '{}' to '{}' at path '{}' -> {:?}", expected, actual, path.iter().join("."), result);
+  result.map_err(|messages| messages.iter().map(|message| Mismatch::BodyMismatch {
+    path: format!("$[{}].{}", index, name),
+    expected: Some(expected.to_string().into()),
+    actual: Some(actual.to_string().into()),
+    mismatch: message.clone()
+  }).collect())
+}
+
+fn compare_event(index: usize, expected: &Event, actual: &Event, context: &MatchingContext) -> Vec<Mismatch> {
+  let mut mismatches = vec![];
+  for name in EVENT_FIELDS.iter() {
+    match (expected.field(name), actual.field(name)) {
+      (Some(expected_value), Some(actual_value)) => {
+        if let Err(err) = compare_field(index, name, expected_value, actual_value, context) {
+          mismatches.extend(err);
+        }
+      },
+      (Some(expected_value), None) => mismatches.push(Mismatch::BodyMismatch {
+        path: format!("$[{}].{}", index, name),
+        expected: Some(expected_value.clone().into()),
+        actual: None,
+        mismatch: format!("Expected event field '{}' but was missing", name)
+      }),
+      (None, Some(actual_value)) if context.config == DiffConfig::NoUnexpectedKeys => mismatches.push(Mismatch::BodyMismatch {
+        path: format!("$[{}].{}", index, name),
+        expected: None,
+        actual: Some(actual_value.clone().into()),
+        mismatch: format!("Received unexpected event field '{}'", name)
+      }),
+      _ => ()
+    }
+  }
+  mismatches
+}
+
+/// Matches server-sent event (`text/event-stream`) bodies
+pub fn match_event_stream(expected: &dyn HttpPart, actual: &dyn HttpPart, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
+  let (expected_body, actual_body) = match (expected.body(), actual.body()) {
+    (OptionalBody::Present(expected_body, _), OptionalBody::Present(actual_body, _)) => (expected_body, actual_body),
+    (OptionalBody::Present(_, _), _) => return Err(vec![Mismatch::BodyMismatch {
+      path: "$".into(),
+      expected: expected.body().value(),
+      actual: None,
+      mismatch: format!("Expected an event stream body {} but was missing", expected.body())
+    }]),
+    _ => return Ok(())
+  };
+
+  let expected_events = parse_events(&String::from_utf8_lossy(expected_body));
+  let actual_events = parse_events(&String::from_utf8_lossy(actual_body));
+
+  let mut mismatches = vec![];
+  if expected_events.len() != actual_events.len() {
+    mismatches.push(Mismatch::BodyMismatch {
+      path: "$".to_string(),
+      expected: expected.body().value(),
+      actual: actual.body().value(),
+      mismatch: format!("Expected {} event(s) but received {}", expected_events.len(), actual_events.len())
+    });
+  }
+  for (index, (expected_event, actual_event)) in expected_events.iter().zip(actual_events.iter()).enumerate() {
+    mismatches.extend(compare_event(index, expected_event, actual_event, context));
+  }
+
+  if mismatches.is_empty() {
+    Ok(())
+  } else {
+    Err(mismatches)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_models::content_types::ContentType;
+
+  use crate::models::matchingrules::MatchingRule;
+  use crate::models::Response;
+
+  use super::*;
+
+  fn response(body: &str) -> Response {
+    Response {
+      body: OptionalBody::Present(body.to_string().into(), ContentType::parse("text/event-stream").ok()),
+      .. Response::default()
+    }
+  }
+
+  fn descriptions(result: Result<(), Vec<Mismatch>>) -> Vec<String> {
+    result.unwrap_err().iter().map(|m| m.description()).collect()
+  }
+
+  #[test]
+  fn parse_events_test() {
+    let stream = ": keep-alive\n\nid: 1\nevent: price\ndata: {\"price\": 100}\n\nid:2\r\ndata: line 1\r\ndata: line 2\r\nretry: 1000\r\n\ndata\n";
+    expect!(parse_events(stream)).to(be_equal_to(vec![
+      Event {
+        id: Some("1".to_string()),
+        event: Some("price".to_string()),
+        data: Some("{\"price\": 100}".to_string()),
+        retry: None
+      },
+      Event {
+        id: Some("2".to_string()),
+        event: None,
+        data: Some("line 1\nline 2".to_string()),
+        retry: Some("1000".to_string())
+      },
+      Event { data: Some("".to_string()), .. Event::default() }
+    ]));
+    expect!(parse_events("")).to(be_equal_to(Vec::<Event>::new()));
+  }
+
+  #[test]
+  fn split_events_test() {
+    expect!(split_events(": keep-alive\n\nid: 1\ndata: a\n\n\ndata: b")).to(be_equal_to(vec![
+      ": keep-alive\n\n".to_string(),
+      "id: 1\ndata: a\n\n\n".to_string(),
+      "data: b".to_string()
+    ]));
+    expect!(split_events("")).to(be_equal_to(Vec::<String>::new()));
+  }
+
+  #[test]
+  fn match_event_stream_compares_the_fields_of_the_events() {
+    let context = MatchingContext::with_config(DiffConfig::NoUnexpectedKeys);
+    let expected = response("id: 1\nevent: price\ndata: 100\n\nid: 2\nevent: price\ndata: 101\n\n");
+    expect!(match_event_stream(&expected, &response("id:1\nevent:price\ndata:100\n\n: comment\nid:2\nevent:price\ndata:101\n"), &context))
+      .to(be_ok());
+    expect!(descriptions(match_event_stream(&expected, &response("id: 1\nevent: price\ndata: 99\n\nid: 2\ndata: 101\nretry: 10\n\nid: 3\n\n"), &context)))
+      .to(be_equal_to(vec![
+        "$ -> Expected 2 event(s) but received 3".to_string(),
+        "$[0].data -> Expected '100' to be equal to '99'".to_string(),
+        "$[1].event -> Expected event field 'event' but was missing".to_string(),
+        "$[1].retry -> Received unexpected event field 'retry'".to_string()
+      ]));
+  }
+
+  #[test]
+  fn match_event_stream_applies_rules_to_the_fields() {
+    let context = MatchingContext::new(DiffConfig::AllowUnexpectedKeys, &matchingrules!{
+      "body" => {
+        "$[*].id" => [ MatchingRule::Integer ],
+        "$[*].data" => [ MatchingRule::Regex("^\\{.*\\}$".to_string()) ]
+      }
+    }.rules_for_category("body").unwrap());
+    let expected = response("id: 1\ndata: {\"price\": 100}\n\n");
+    expect!(match_event_stream(&expected, &response("id: 42\ndata: {\"price\": 120}\nretry: 10\n\n"), &context)).to(be_ok());
+    expect!(descriptions(match_event_stream(&expected, &response("id: abc\ndata: price\n\n"), &context)))
+      .to(be_equal_to(vec![
+        "$[0].id -> Expected 'abc' to match an integer number".to_string(),
+        "$[0].data -> Expected 'price' to match '^\\{.*\\}$'".to_string()
+      ]));
+  }
+}
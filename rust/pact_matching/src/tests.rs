@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use expectest::prelude::*;
 
-use pact_models::content_types::TEXT;
+use pact_models::content_types::{JSON, TEXT};
 use pact_models::OptionalBody;
 
 use crate::models::Request;
@@ -303,6 +303,42 @@ fn body_matches_with_extended_mime_types() {
   expect!(result.mismatches().iter()).to(be_empty());
 }
 
+#[test]
+fn body_matches_with_a_compatible_content_type_by_transcoding_the_actual_body() {
+  let expected = Request {
+    headers: Some(hashmap! { s!("Content-Type") => vec![s!("application/json")] }),
+    body: OptionalBody::Present(r#"{"id":100,"name":"Mary"}"#.into(), None),
+    matching_rules: matchingrules! { "body" => { "$.id" => [ MatchingRule::Integer ] } },
+    ..Request::default()
+  };
+  // {"id": 200, "name": "Mary"} in MessagePack
+  let msgpack = vec![0x82, 0xa2, b'i', b'd', 0xcc, 0xc8, 0xa4, b'n', b'a', b'm', b'e', 0xa4, b'M', b'a', b'r', b'y'];
+  let actual = Request {
+    headers: Some(hashmap! { s!("Content-Type") => vec![s!("application/x-msgpack")] }),
+    body: OptionalBody::Present(msgpack.into(), None),
+    ..Request::default()
+  };
+  let context = MatchingContext::new(DiffConfig::AllowUnexpectedKeys, &expected.matching_rules.rules_for_category("body").unwrap());
+
+  let result = match_body(&expected, &actual, &context, &MatchingContext::default());
+  expect!(result.mismatches().iter().map(|m| m.mismatch_type()).collect::<Vec<String>>())
+    .to(be_equal_to(vec!["BodyTypeMismatch".to_string()]));
+
+  transcoders::declare_compatible_content_types(&ContentType::parse("application/json").unwrap(),
+    &ContentType::parse("application/x-msgpack").unwrap());
+  let result = match_body(&expected, &actual, &context, &MatchingContext::default());
+  expect!(result.mismatches().iter()).to(be_empty());
+
+  let invalid = Request { body: OptionalBody::Present(vec![0x82, 0xa2].into(), None), .. actual };
+  let result = match_body(&expected, &invalid, &context, &MatchingContext::default());
+  match result {
+    BodyMatchResult::BodyTypeMismatch { message, .. } => {
+      expect!(message).to(be_equal_to("Expected body with content type application/json but was application/x-msgpack, and it could not be transcoded: MessagePack body ended unexpectedly at byte 2"));
+    },
+    _ => panic!("Expected a body type mismatch, got {:?}", result)
+  }
+}
+
 #[test]
 fn partial_equal_for_method_mismatch() {
   let mismatch = Mismatch::MethodMismatch { expected: s!("get"), actual: s!("post") };
@@ -441,6 +477,86 @@ fn partial_equal_for_body_mismatch() {
   expect!(&mismatch).to_not(be_equal_to(&Mismatch::QueryMismatch { parameter: s!(""), expected: s!("get"), actual: s!("post"), mismatch: s!("") }));
 }
 
+#[test]
+fn body_mismatch_json_includes_a_diff_of_the_expected_and_actual_values() {
+  let mismatch = Mismatch::BodyMismatch {
+    path: s!("$.item"),
+    expected: Some("{\"a\":1,\"b\":2}".into()),
+    actual: Some("{\"a\":1,\"b\":3}".into()),
+    mismatch: s!("")
+  };
+  expect!(mismatch.to_json()).to(be_equal_to(json!({
+    "type": "BodyMismatch",
+    "path": "$.item",
+    "expected": "{\"a\":1,\"b\":2}",
+    "actual": "{\"a\":1,\"b\":3}",
+    "mismatch": "",
+    "diff": "--- expected\n+++ actual\n@@ -1,4 +1,4 @@\n {\n   \"a\": 1,\n-  \"b\": 2\n+  \"b\": 3\n }\n"
+  })));
+
+  let mismatch = Mismatch::BodyMismatch { path: s!("$"), expected: None, actual: Some("v2".into()), mismatch: s!("") };
+  expect!(mismatch.to_json()["diff"].clone()).to(be_equal_to(json!(null)));
+}
+
+#[test]
+fn mismatches_serialise_to_the_same_json_as_to_json() {
+  let mismatches = vec![
+    Mismatch::MethodMismatch { expected: s!("GET"), actual: s!("POST") },
+    Mismatch::StatusMismatch { expected: 200, actual: 404 },
+    Mismatch::HeaderMismatch { key: s!("A"), expected: s!("a"), actual: s!("b"), mismatch: s!("mismatch") },
+    Mismatch::BodyTypeMismatch { expected: s!("application/json"), actual: s!("text/plain"),
+      mismatch: s!("mismatch"), expected_body: Some("{}".into()), actual_body: None },
+    Mismatch::BodyMismatch { path: s!("$.a"), expected: Some("1".into()), actual: Some("2".into()), mismatch: s!("mismatch") }
+  ];
+  for mismatch in mismatches {
+    let json = serde_json::to_value(&mismatch).unwrap();
+    expect!(&json).to(be_equal_to(&mismatch.to_json()));
+    let result: Mismatch = serde_json::from_value(json).unwrap();
+    expect!(result).to(be_equal_to(mismatch));
+  }
+}
+
+#[test]
+fn mismatch_from_json_returns_an_error_for_invalid_mismatches() {
+  expect!(Mismatch::from_json(&json!({ "expected": "a" }))).to(be_err());
+  expect!(Mismatch::from_json(&json!({ "type": "OtherMismatch" }))).to(be_err());
+  expect!(Mismatch::from_json(&json!({ "type": "StatusMismatch", "expected": "200", "actual": 404 }))).to(be_err());
+}
+
+#[test]
+fn request_match_result_can_be_serialised_and_deserialised() {
+  let result = RequestMatchResult {
+    method: None,
+    path: Some(vec![ Mismatch::PathMismatch { expected: s!("/a"), actual: s!("/b"), mismatch: s!("") } ]),
+    body: BodyMatchResult::BodyTypeMismatch {
+      expected_type: s!("application/json"),
+      actual_type: s!("text/plain"),
+      message: s!("mismatch"),
+      expected: Some("{}".into()),
+      actual: None
+    },
+    query: hashmap!{},
+    headers: hashmap!{}
+  };
+  let json = serde_json::to_value(&result).unwrap();
+  expect!(json["body"].clone()).to(be_equal_to(json!({
+    "type": "BodyTypeMismatch",
+    "expectedType": "application/json",
+    "actualType": "text/plain",
+    "message": "mismatch",
+    "expected": "{}",
+    "actual": null
+  })));
+  let deserialised: RequestMatchResult = serde_json::from_value(json).unwrap();
+  expect!(deserialised).to(be_equal_to(result));
+
+  let body = BodyMatchResult::BodyMismatches(hashmap!{
+    s!("$.a") => vec![ Mismatch::BodyMismatch { path: s!("$.a"), expected: None, actual: None, mismatch: s!("") } ]
+  });
+  let deserialised: BodyMatchResult = serde_json::from_value(serde_json::to_value(&body).unwrap()).unwrap();
+  expect!(deserialised).to(be_equal_to(body));
+}
+
 #[test]
 fn match_path_returns_nothing_if_the_path_matches() {
   let context = MatchingContext::default();
@@ -612,3 +728,221 @@ fn values_matcher_defined() {
   expect!(context.values_matcher_defined(&["$", "x", "0", "z"])).to(be_false());
   expect!(context.values_matcher_defined(&["$", "y", "0", "y"])).to(be_false());
 }
+
+#[test]
+fn match_response_with_locale_parses_localised_numbers_and_dates() {
+  let expected = models::Response {
+    headers: Some(hashmap!{ "Content-Type".to_string() => vec!["application/json".to_string()] }),
+    body: OptionalBody::Present(r#"{"amount": "1.234,56", "date": "3. März 2021"}"#.into(), None),
+    matching_rules: matchingrules! {
+      "body" => {
+        "$.amount" => [ MatchingRule::Decimal ],
+        "$.date" => [ MatchingRule::Date("d. MMMM yyyy".to_string()) ]
+      }
+    },
+    .. models::Response::default()
+  };
+  let actual = models::Response {
+    headers: Some(hashmap!{ "Content-Type".to_string() => vec!["application/json".to_string()] }),
+    body: OptionalBody::Present(r#"{"amount": "10.000,01", "date": "14. Oktober 2021"}"#.into(), None),
+    .. models::Response::default()
+  };
+
+  expect!(match_response(expected.clone(), actual.clone()).iter()).to_not(be_empty());
+  let locale = locale::LocaleOptions::for_locale("de-DE").unwrap();
+  expect!(match_response_with_locale(expected.clone(), actual.clone(), &locale).iter()).to(be_empty());
+
+  let actual = models::Response {
+    body: OptionalBody::Present(r#"{"amount": "10,000.01", "date": "14. Oktober 2021"}"#.into(), None),
+    .. actual
+  };
+  expect!(match_response_with_locale(expected, actual, &locale).iter()).to_not(be_empty());
+}
+
+#[test]
+fn match_method_with_context_uses_the_method_matching_rules() {
+  let context = MatchingContext::default();
+  expect!(match_method_with_context(&"PURGE".to_string(), &"purge".to_string(), &context)).to(be_ok());
+
+  let context = MatchingContext::new(DiffConfig::NoUnexpectedKeys, &matchingrules! {
+    "method" => { "" => [ MatchingRule::Equality ] }
+  }.rules_for_category("method").unwrap());
+  expect!(match_method_with_context(&"PURGE".to_string(), &"PURGE".to_string(), &context)).to(be_ok());
+  expect!(match_method_with_context(&"PURGE".to_string(), &"purge".to_string(), &context)).to(
+    be_err().value(Mismatch::MethodMismatch { expected: "PURGE".to_string(), actual: "purge".to_string() }));
+
+  let context = MatchingContext::new(DiffConfig::NoUnexpectedKeys, &matchingrules! {
+    "method" => { "" => [ MatchingRule::Regex("^(REPORT|PROPFIND)$".to_string()) ] }
+  }.rules_for_category("method").unwrap());
+  expect!(match_method_with_context(&"REPORT".to_string(), &"PROPFIND".to_string(), &context)).to(be_ok());
+  expect!(match_method_with_context(&"REPORT".to_string(), &"GET".to_string(), &context)).to(be_err());
+}
+
+#[test]
+fn match_request_with_an_extension_method() {
+  let expected = Request { method: "REPORT".to_string(), .. Request::default() };
+  expect!(match_request(expected.clone(), Request { method: "report".to_string(), .. Request::default() }).all_matched())
+    .to(be_true());
+  expect!(match_request(expected.clone(), Request { method: "PURGE".to_string(), .. Request::default() }).all_matched())
+    .to(be_false());
+
+  let expected = Request {
+    matching_rules: matchingrules! { "method" => { "" => [ MatchingRule::Equality ] } },
+    .. expected
+  };
+  expect!(match_request(expected.clone(), Request { method: "report".to_string(), .. Request::default() }).all_matched())
+    .to(be_false());
+}
+
+#[test]
+fn match_request_with_strict_headers_fails_if_there_are_unexpected_headers() {
+  let expected = Request {
+    headers: Some(hashmap! { "Accept".to_string() => vec!["application/json".to_string()] }),
+    .. Request::default()
+  };
+  let actual = Request {
+    headers: Some(hashmap! {
+      "accept".to_string() => vec!["application/json".to_string()],
+      "Host".to_string() => vec!["localhost".to_string()]
+    }),
+    .. Request::default()
+  };
+  let with_cookie = Request {
+    headers: Some(hashmap! {
+      "accept".to_string() => vec!["application/json".to_string()],
+      "Cookie".to_string() => vec!["session=1234".to_string()]
+    }),
+    .. Request::default()
+  };
+
+  expect!(match_request_with_strict_headers(expected.clone(), actual, &LocaleOptions::default()).all_matched())
+    .to(be_true());
+  expect!(match_request(expected.clone(), with_cookie.clone()).all_matched()).to(be_true());
+  let result = match_request_with_strict_headers(expected, with_cookie, &LocaleOptions::default());
+  expect!(result.all_matched()).to(be_false());
+  expect!(result.headers.get("Cookie").cloned().unwrap_or_default().len()).to(be_equal_to(1));
+}
+
+#[test]
+fn match_request_with_options_can_allow_unexpected_keys_in_the_body() {
+  let expected = Request {
+    method: "POST".to_string(),
+    body: OptionalBody::Present(r#"{"a": 1}"#.into(), Some(JSON.clone())),
+    .. Request::default()
+  };
+  let actual = Request {
+    body: OptionalBody::Present(r#"{"a": 1, "b": 2}"#.into(), Some(JSON.clone())),
+    .. expected.clone()
+  };
+
+  expect!(match_request(expected.clone(), actual.clone()).all_matched()).to(be_false());
+  expect!(match_request_with_options(expected, actual, &LocaleOptions::default(), false,
+    &DiffConfig::AllowUnexpectedKeys).all_matched()).to(be_true());
+}
+
+#[test]
+fn match_response_with_body_config_can_reject_unexpected_keys_in_the_body() {
+  let expected = models::Response {
+    body: OptionalBody::Present(r#"{"a": 1}"#.into(), Some(JSON.clone())),
+    .. models::Response::default()
+  };
+  let actual = models::Response {
+    body: OptionalBody::Present(r#"{"a": 1, "b": 2}"#.into(), Some(JSON.clone())),
+    .. models::Response::default()
+  };
+
+  expect!(match_response(expected.clone(), actual.clone()).iter()).to(be_empty());
+  expect!(match_response_with_body_config(expected, actual, &LocaleOptions::default(),
+    &DiffConfig::NoUnexpectedKeys).iter()).to_not(be_empty());
+}
+
+#[test]
+fn match_sync_message_response_compares_each_response_message() {
+  use crate::models::v4::SynchronousMessages;
+  use crate::models::v4::message_parts::MessageContents;
+
+  let message = |body: &str| MessageContents {
+    contents: OptionalBody::Present(body.to_string().into(), Some(JSON.clone())),
+    .. MessageContents::default()
+  };
+  let expected = SynchronousMessages {
+    response: vec![message(r#"{"a": 1}"#), message(r#"{"b": 2}"#)],
+    .. SynchronousMessages::default()
+  };
+
+  expect!(match_sync_message_response(&expected, &[message(r#"{"a": 1}"#), message(r#"{"b": 2}"#)]).iter())
+    .to(be_empty());
+  let mismatches = match_sync_message_response(&expected, &[message(r#"{"a": 1}"#), message(r#"{"b": 3}"#)]);
+  expect!(mismatches.iter().map(|m| m.description()).collect::<Vec<_>>())
+    .to(be_equal_to(vec!["$.b -> Expected '2' to be equal to '3'".to_string()]));
+  let mismatches = match_sync_message_response(&expected, &[message(r#"{"a": 1}"#)]);
+  expect!(mismatches.iter().map(|m| m.description()).collect::<Vec<_>>())
+    .to(be_equal_to(vec!["$ -> Expected 2 response messages but received 1".to_string()]));
+}
+
+#[test]
+fn match_message_metadata_applies_rules_to_kafka_metadata() {
+  use crate::models::kafka::KafkaMetadata;
+  use crate::models::message::Message;
+  use crate::models::v4::AsynchronousMessage;
+
+  let mut expected = AsynchronousMessage {
+    matching_rules: matchingrules! {
+      "metadata" => {
+        "kafka_key" => [ MatchingRule::Regex("^order-\\d+$".to_string()) ],
+        "kafka_headers.traceId" => [ MatchingRule::Type ]
+      }
+    },
+    .. AsynchronousMessage::default()
+  };
+  expected.set_kafka_topic("orders");
+  expected.set_kafka_key("order-1");
+  expected.set_kafka_partition(0);
+  expected.set_kafka_header("traceId", "abc");
+  expected.set_kafka_header("source", "web");
+
+  let mut actual = Message::default();
+  actual.set_kafka_topic("orders");
+  actual.set_kafka_key("order-100");
+  actual.set_kafka_partition(0);
+  actual.set_kafka_header("traceId", "xyz");
+  actual.set_kafka_header("source", "web");
+  expect!(match_message(&expected.boxed(), &actual.boxed()).iter()).to(be_empty());
+
+  let mut actual = Message::default();
+  actual.set_kafka_topic("orders");
+  actual.set_kafka_key("invoice-100");
+  actual.set_kafka_partition(1);
+  actual.set_kafka_header("traceId", "xyz");
+  let mismatches = match_message(&expected.boxed(), &actual.boxed());
+  let mut keys = mismatches.iter().map(|m| match m {
+    Mismatch::MetadataMismatch { key, .. } => key.clone(),
+    _ => m.description()
+  }).collect::<Vec<_>>();
+  keys.sort();
+  expect!(keys).to(be_equal_to(vec![
+    "kafka_headers.source".to_string(),
+    "kafka_key".to_string(),
+    "kafka_partition".to_string()
+  ]));
+}
+
+#[test]
+fn match_sync_message_request_compares_the_request_message() {
+  use crate::models::v4::SynchronousMessages;
+  use crate::models::v4::message_parts::MessageContents;
+
+  let message = |body: &str| MessageContents {
+    contents: OptionalBody::Present(body.to_string().into(), Some(JSON.clone())),
+    .. MessageContents::default()
+  };
+  let expected = SynchronousMessages {
+    request: message(r#"{"subscribe": "AAPL"}"#),
+    .. SynchronousMessages::default()
+  };
+
+  expect!(match_sync_message_request(&expected, &message(r#"{"subscribe": "AAPL"}"#)).iter()).to(be_empty());
+  let mismatches = match_sync_message_request(&expected, &message(r#"{"subscribe": "MSFT"}"#));
+  expect!(mismatches.iter().map(|m| m.description()).collect::<Vec<_>>())
+    .to(be_equal_to(vec!["$.subscribe -> Expected 'AAPL' to be equal to 'MSFT'".to_string()]));
+}
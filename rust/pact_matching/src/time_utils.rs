@@ -50,6 +50,7 @@ use nom::error::{ErrorKind, ParseError};
 use nom::IResult;
 use nom::multi::many1;
 use nom::sequence::{delimited, preceded, terminated, tuple, separated_pair};
+use crate::locale::DateLocale;
 use crate::timezone_db::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -706,6 +707,48 @@ fn day_of_week_name(s: &str, count: usize) -> IResult<&str, String, DateTimeErro
   }.map(|(remaining, result)| (remaining, result.into()))
 }
 
+/// Matches the longest of the names (ignoring case) at the start of the input
+fn localised_name<'a>(s: &'a str, names: Vec<&'static str>) -> Option<(&'a str, String)> {
+  names.iter()
+    .sorted_by_key(|name| std::cmp::Reverse(name.chars().count()))
+    .find_map(|name| {
+      let len = name.chars().count();
+      let end = s.char_indices().nth(len).map(|(i, _)| i).unwrap_or_else(|| s.len());
+      let prefix = &s[..end];
+      if prefix.chars().count() == len && prefix.to_lowercase() == name.to_lowercase() {
+        Some((&s[end..], prefix.to_string()))
+      } else {
+        None
+      }
+    })
+}
+
+fn localised_month<'a>(s: &'a str, count: usize, locales: &[DateLocale]) -> IResult<&'a str, String, DateTimeError<&'a str>> {
+  let result = month(s, count);
+  if result.is_err() && count >= 3 {
+    let names = locales.iter()
+      .flat_map(|locale| if count == 3 { locale.short_month_names() } else { locale.month_names() }.to_vec())
+      .collect();
+    if let Some(result) = localised_name(s, names) {
+      return Ok(result);
+    }
+  }
+  result
+}
+
+fn localised_day_of_week_name<'a>(s: &'a str, count: usize, locales: &[DateLocale]) -> IResult<&'a str, String, DateTimeError<&'a str>> {
+  let result = day_of_week_name(s, count);
+  if result.is_err() {
+    let names = locales.iter()
+      .flat_map(|locale| if count <= 3 { locale.short_day_names() } else { locale.day_names() }.to_vec())
+      .collect();
+    if let Some(result) = localised_name(s, names) {
+      return Ok(result);
+    }
+  }
+  result
+}
+
 fn quarter_num(s: &str, _count: usize) -> IResult<&str, String, DateTimeError<&str>> {
   take_while_m_n(1, 2, is_digit)(s).and_then(|(remaining, result)|{
     match validate_number(result, "quarter".into(), 1, 4) {
@@ -769,8 +812,8 @@ pub fn parse_pattern(s: &str) -> Result<Vec<DateTimePatternToken>, String> {
   }
 }
 
-fn validate_datetime_string(value: &String, pattern_tokens: &Vec<DateTimePatternToken>) -> Result<(), String> {
-  let mut buffer = value.as_str();
+fn validate_datetime_string(value: &str, pattern_tokens: &[DateTimePatternToken], locales: &[DateLocale]) -> Result<(), String> {
+  let mut buffer = value;
   for token in pattern_tokens {
     let result = match token {
       DateTimePatternToken::Era(count) => era(buffer, *count),
@@ -779,11 +822,15 @@ fn validate_datetime_string(value: &String, pattern_tokens: &Vec<DateTimePattern
       DateTimePatternToken::WeekInMonth(from_one) => week_in_month(buffer, *from_one),
       DateTimePatternToken::DayInYear => day_in_year(buffer),
       DateTimePatternToken::DayInMonth => day_in_month(buffer),
-      DateTimePatternToken::Month(count) => month(buffer, *count),
+      DateTimePatternToken::Month(count) => localised_month(buffer, *count, locales),
       DateTimePatternToken::MonthNum(count) => month_num(buffer, *count),
       DateTimePatternToken::Text(t) => tag(t.as_str())(buffer).map(|(remaining, result)| (remaining, result.into())),
-      DateTimePatternToken::DayName(count) => day_of_week_name(buffer, *count),
-      DateTimePatternToken::DayOfWeek(count) => day_of_week(buffer, *count),
+      DateTimePatternToken::DayName(count) => localised_day_of_week_name(buffer, *count, locales),
+      DateTimePatternToken::DayOfWeek(count) => if *count > 2 {
+        localised_day_of_week_name(buffer, *count, locales)
+      } else {
+        day_of_week(buffer, *count)
+      },
       DateTimePatternToken::Hour24 => hour_24(buffer),
       DateTimePatternToken::Hour24ZeroBased => hour_24_0(buffer),
       DateTimePatternToken::Hour12 => hour_12(buffer),
@@ -816,8 +863,14 @@ fn validate_datetime_string(value: &String, pattern_tokens: &Vec<DateTimePattern
 
 /// Validates the given datetime against the pattern
 pub fn validate_datetime(value: &String, format: &String) -> Result<(), String> {
-  match parse_pattern(format.as_str()) {
-    Ok(pattern_tokens) => validate_datetime_string(value, &pattern_tokens),
+  validate_datetime_with_locales(value, format, &[])
+}
+
+/// Validates the given datetime against the pattern, also accepting the month and day names
+/// from the given locales
+pub fn validate_datetime_with_locales(value: &str, format: &str, locales: &[DateLocale]) -> Result<(), String> {
+  match parse_pattern(format) {
+    Ok(pattern_tokens) => validate_datetime_string(value, &pattern_tokens, locales),
     Err(err) => Err(format!("Error parsing '{}': {:?}", value, err))
   }
 }
@@ -1224,4 +1277,19 @@ mod tests {
     expect!(validate_tz_abbreviation("AEDT")).to(be_true());
     expect!(validate_tz_abbreviation("XXX")).to(be_false());
   }
+
+  #[test]
+  fn validate_datetime_with_localised_names() {
+    let de = [DateLocale::De];
+    expect!(validate_datetime(&"3. März 2021".into(), &"d. MMMM yyyy".into())).to(be_err());
+    expect!(validate_datetime_with_locales("3. März 2021", "d. MMMM yyyy", &de)).to(be_ok());
+    expect!(validate_datetime_with_locales("3. MÄRZ 2021", "d. MMMM yyyy", &de)).to(be_ok());
+    expect!(validate_datetime_with_locales("Mi, 03 Okt 2021", "EEE, dd MMM yyyy", &de)).to(be_ok());
+    expect!(validate_datetime_with_locales("Mittwoch", "EEEE", &de)).to(be_ok());
+    expect!(validate_datetime_with_locales("Wednesday", "EEEE", &de)).to(be_ok());
+    expect!(validate_datetime_with_locales("mercredi 3 mars", "EEEE d MMMM", &de)).to(be_err());
+    expect!(validate_datetime_with_locales("mercredi 3 mars", "EEEE d MMMM",
+      &[DateLocale::De, DateLocale::Fr])).to(be_ok());
+    expect!(validate_datetime_with_locales("3 juil. 2021", "d MMM yyyy", &[DateLocale::Fr])).to(be_ok());
+  }
 }
@@ -0,0 +1,397 @@
+//! Body transcoders that convert bodies between equivalent content types (i.e. JSON and MessagePack).
+//!
+//! By default, a body with a different content type to the expected one is a mismatch. If the two
+//! content types have been declared compatible with `declare_compatible_content_types`, the actual
+//! body is transcoded to the expected content type before it is matched. JSON is used as the common
+//! form, so a transcoder only needs to be able to convert its content type to and from JSON.
+//! A MessagePack transcoder is registered by default, and others (like YAML) can be added with
+//! `register_transcoder`.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::sync::{Arc, RwLock};
+
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use log::*;
+use serde_json::{json, Map, Number, Value};
+
+use pact_models::content_types::ContentType;
+
+/// Converts bodies of a content type to and from JSON
+pub trait BodyTranscoder: Send + Sync {
+  /// Name of the transcoder, used for logging
+  fn name(&self) -> String;
+
+  /// If this transcoder can convert bodies of the given content type
+  fn handles(&self, content_type: &ContentType) -> bool;
+
+  /// Decodes the body into JSON
+  fn decode(&self, body: &[u8]) -> anyhow::Result<Value>;
+
+  /// Encodes the JSON as a body with the content type of this transcoder
+  fn encode(&self, json: &Value) -> anyhow::Result<Vec<u8>>;
+}
+
+lazy_static! {
+  static ref TRANSCODERS: RwLock<Vec<Arc<dyn BodyTranscoder>>> = RwLock::new(vec![Arc::new(MessagePackTranscoder)]);
+  static ref COMPATIBLE_CONTENT_TYPES: RwLock<HashSet<(ContentType, ContentType)>> = RwLock::new(HashSet::new());
+}
+
+/// Registers a transcoder. Transcoders registered later take precedence over earlier ones for the
+/// same content type.
+pub fn register_transcoder(transcoder: Arc<dyn BodyTranscoder>) {
+  debug!("Registering body transcoder '{}'", transcoder.name());
+  TRANSCODERS.write().unwrap().insert(0, transcoder);
+}
+
+/// Returns the transcoder for the content type, if one has been registered
+pub fn find_transcoder(content_type: &ContentType) -> Option<Arc<dyn BodyTranscoder>> {
+  TRANSCODERS.read().unwrap().iter().find(|t| t.handles(content_type)).cloned()
+}
+
+/// Declares that bodies of the two content types are equivalent, and an actual body with one of the
+/// content types can be matched against an expected body with the other one.
+pub fn declare_compatible_content_types(a: &ContentType, b: &ContentType) {
+  let mut compatible = COMPATIBLE_CONTENT_TYPES.write().unwrap();
+  compatible.insert((a.base_type(), b.base_type()));
+  compatible.insert((b.base_type(), a.base_type()));
+}
+
+/// Removes all the content types that have been declared compatible
+pub fn clear_compatible_content_types() {
+  COMPATIBLE_CONTENT_TYPES.write().unwrap().clear();
+}
+
+/// If the two content types have been declared compatible
+pub fn content_types_are_compatible(a: &ContentType, b: &ContentType) -> bool {
+  COMPATIBLE_CONTENT_TYPES.read().unwrap().contains(&(a.base_type(), b.base_type()))
+}
+
+fn decode_body(body: &[u8], content_type: &ContentType) -> anyhow::Result<Value> {
+  if content_type.is_json() {
+    serde_json::from_slice(body).map_err(|err| anyhow!("Failed to parse the body as JSON: {}", err))
+  } else {
+    let transcoder = find_transcoder(content_type)
+      .ok_or_else(|| anyhow!("There is no transcoder registered for content type {}", content_type))?;
+    transcoder.decode(body)
+  }
+}
+
+fn encode_body(json: &Value, content_type: &ContentType) -> anyhow::Result<Vec<u8>> {
+  if content_type.is_json() {
+    Ok(json.to_string().into_bytes())
+  } else {
+    let transcoder = find_transcoder(content_type)
+      .ok_or_else(|| anyhow!("There is no transcoder registered for content type {}", content_type))?;
+    transcoder.encode(json)
+  }
+}
+
+/// Transcodes the body from one content type to another, using JSON as the common form
+pub fn transcode(body: &[u8], from: &ContentType, to: &ContentType) -> anyhow::Result<Vec<u8>> {
+  if from.base_type() == to.base_type() {
+    Ok(body.to_vec())
+  } else {
+    let json = decode_body(body, from)?;
+    encode_body(&json, to)
+  }
+}
+
+/// Transcoder for MessagePack bodies (application/msgpack or application/x-msgpack). Binary values
+/// are converted to Base64 encoded strings.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackTranscoder;
+
+impl BodyTranscoder for MessagePackTranscoder {
+  fn name(&self) -> String {
+    "MessagePack".to_string()
+  }
+
+  fn handles(&self, content_type: &ContentType) -> bool {
+    content_type.main_type == "application" &&
+      (content_type.sub_type == "msgpack" || content_type.sub_type == "x-msgpack")
+  }
+
+  fn decode(&self, body: &[u8]) -> anyhow::Result<Value> {
+    let mut reader = MessagePackReader { buffer: body, position: 0 };
+    let value = reader.read_value()?;
+    if reader.position < body.len() {
+      Err(anyhow!("MessagePack body has {} unexpected trailing byte(s)", body.len() - reader.position))
+    } else {
+      Ok(value)
+    }
+  }
+
+  fn encode(&self, json: &Value) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = vec![];
+    write_message_pack(json, &mut buffer)?;
+    Ok(buffer)
+  }
+}
+
+fn float_value(value: f64) -> anyhow::Result<Value> {
+  Number::from_f64(value).map(Value::Number)
+    .ok_or_else(|| anyhow!("MessagePack float {} can not be represented in JSON", value))
+}
+
+struct MessagePackReader<'a> {
+  buffer: &'a [u8],
+  position: usize
+}
+
+impl <'a> MessagePackReader<'a> {
+  fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+    if self.position + len > self.buffer.len() {
+      Err(anyhow!("MessagePack body ended unexpectedly at byte {}", self.buffer.len()))
+    } else {
+      let bytes = &self.buffer[self.position..self.position + len];
+      self.position += len;
+      Ok(bytes)
+    }
+  }
+
+  fn read_uint(&mut self, len: usize) -> anyhow::Result<u64> {
+    Ok(self.read_bytes(len)?.iter().fold(0_u64, |acc, b| (acc << 8) | *b as u64))
+  }
+
+  fn read_int(&mut self, len: usize) -> anyhow::Result<i64> {
+    let value = self.read_uint(len)?;
+    let shift = 64 - (len * 8) as u32;
+    Ok(((value << shift) as i64) >> shift)
+  }
+
+  fn read_str(&mut self, len: usize) -> anyhow::Result<Value> {
+    let position = self.position;
+    let bytes = self.read_bytes(len)?;
+    std::str::from_utf8(bytes)
+      .map(|s| Value::String(s.to_string()))
+      .map_err(|err| anyhow!("MessagePack string at byte {} is not valid UTF-8: {}", position, err))
+  }
+
+  fn read_array(&mut self, len: usize) -> anyhow::Result<Value> {
+    let mut array = Vec::with_capacity(len.min(self.buffer.len()));
+    for _ in 0..len {
+      array.push(self.read_value()?);
+    }
+    Ok(Value::Array(array))
+  }
+
+  fn read_map(&mut self, len: usize) -> anyhow::Result<Value> {
+    let mut map = Map::new();
+    for _ in 0..len {
+      let key = match self.read_value()? {
+        Value::String(s) => s,
+        key => key.to_string()
+      };
+      map.insert(key, self.read_value()?);
+    }
+    Ok(Value::Object(map))
+  }
+
+  fn read_value(&mut self) -> anyhow::Result<Value> {
+    let position = self.position;
+    let marker = self.read_bytes(1)?[0];
+    match marker {
+      0x00..=0x7f => Ok(json!(marker)),
+      0x80..=0x8f => self.read_map((marker & 0x0f) as usize),
+      0x90..=0x9f => self.read_array((marker & 0x0f) as usize),
+      0xa0..=0xbf => self.read_str((marker & 0x1f) as usize),
+      0xc0 => Ok(Value::Null),
+      0xc2 => Ok(Value::Bool(false)),
+      0xc3 => Ok(Value::Bool(true)),
+      0xc4..=0xc6 => {
+        let len = self.read_uint(1 << (marker - 0xc4))? as usize;
+        Ok(Value::String(base64::encode(self.read_bytes(len)?)))
+      },
+      0xca => {
+        let bits = self.read_uint(4)? as u32;
+        float_value(f32::from_bits(bits) as f64)
+      },
+      0xcb => {
+        let bits = self.read_uint(8)?;
+        float_value(f64::from_bits(bits))
+      },
+      0xcc..=0xcf => Ok(json!(self.read_uint(1 << (marker - 0xcc))?)),
+      0xd0..=0xd3 => Ok(json!(self.read_int(1 << (marker - 0xd0))?)),
+      0xd9..=0xdb => {
+        let len = self.read_uint(1 << (marker - 0xd9))? as usize;
+        self.read_str(len)
+      },
+      0xdc | 0xdd => {
+        let len = self.read_uint(if marker == 0xdc { 2 } else { 4 })? as usize;
+        self.read_array(len)
+      },
+      0xde | 0xdf => {
+        let len = self.read_uint(if marker == 0xde { 2 } else { 4 })? as usize;
+        self.read_map(len)
+      },
+      0xe0..=0xff => Ok(json!(marker as i8)),
+      _ => Err(anyhow!("MessagePack extension type 0x{:02x} at byte {} is not supported", marker, position))
+    }
+  }
+}
+
+fn write_length(len: usize, markers: [u8; 3], buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+  if len <= u8::MAX as usize && markers[0] != 0 {
+    buffer.push(markers[0]);
+    buffer.push(len as u8);
+  } else if len <= u16::MAX as usize {
+    buffer.push(markers[1]);
+    buffer.extend_from_slice(&(len as u16).to_be_bytes());
+  } else {
+    let len = u32::try_from(len).map_err(|_| anyhow!("Length {} is too large for MessagePack", len))?;
+    buffer.push(markers[2]);
+    buffer.extend_from_slice(&len.to_be_bytes());
+  }
+  Ok(())
+}
+
+fn write_integer(value: i64, buffer: &mut Vec<u8>) {
+  if (0..=0x7f).contains(&value) || (-32..0).contains(&value) {
+    buffer.push(value as u8);
+  } else if value >= 0 {
+    if value <= u8::MAX as i64 {
+      buffer.push(0xcc);
+      buffer.push(value as u8);
+    } else if value <= u16::MAX as i64 {
+      buffer.push(0xcd);
+      buffer.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as i64 {
+      buffer.push(0xce);
+      buffer.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+      buffer.push(0xcf);
+      buffer.extend_from_slice(&(value as u64).to_be_bytes());
+    }
+  } else if value >= i8::MIN as i64 {
+    buffer.push(0xd0);
+    buffer.push(value as i8 as u8);
+  } else if value >= i16::MIN as i64 {
+    buffer.push(0xd1);
+    buffer.extend_from_slice(&(value as i16).to_be_bytes());
+  } else if value >= i32::MIN as i64 {
+    buffer.push(0xd2);
+    buffer.extend_from_slice(&(value as i32).to_be_bytes());
+  } else {
+    buffer.push(0xd3);
+    buffer.extend_from_slice(&value.to_be_bytes());
+  }
+}
+
+fn write_message_pack(json: &Value, buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+  match json {
+    Value::Null => buffer.push(0xc0),
+    Value::Bool(b) => buffer.push(if *b { 0xc3 } else { 0xc2 }),
+    Value::Number(n) => if let Some(i) = n.as_i64() {
+      write_integer(i, buffer);
+    } else if let Some(u) = n.as_u64() {
+      buffer.push(0xcf);
+      buffer.extend_from_slice(&u.to_be_bytes());
+    } else {
+      buffer.push(0xcb);
+      buffer.extend_from_slice(&n.as_f64().unwrap_or_default().to_bits().to_be_bytes());
+    },
+    Value::String(s) => {
+      if s.len() < 32 {
+        buffer.push(0xa0 | s.len() as u8);
+      } else {
+        write_length(s.len(), [0xd9, 0xda, 0xdb], buffer)?;
+      }
+      buffer.extend_from_slice(s.as_bytes());
+    },
+    Value::Array(array) => {
+      if array.len() < 16 {
+        buffer.push(0x90 | array.len() as u8);
+      } else {
+        write_length(array.len(), [0, 0xdc, 0xdd], buffer)?;
+      }
+      for item in array {
+        write_message_pack(item, buffer)?;
+      }
+    },
+    Value::Object(map) => {
+      if map.len() < 16 {
+        buffer.push(0x80 | map.len() as u8);
+      } else {
+        write_length(map.len(), [0, 0xde, 0xdf], buffer)?;
+      }
+      for (key, value) in map {
+        write_message_pack(&Value::String(key.clone()), buffer)?;
+        write_message_pack(value, buffer)?;
+      }
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use super::*;
+
+  fn msgpack() -> ContentType {
+    ContentType::parse("application/msgpack").unwrap()
+  }
+
+  #[test]
+  fn message_pack_round_trip() {
+    let json = json!({
+      "id": 1234567,
+      "name": "Mary",
+      "negative": -200,
+      "small": -5,
+      "price": 10.5,
+      "active": true,
+      "deleted": false,
+      "parent": null,
+      "tags": ["a", "b"],
+      "description": "a string longer than thirty one characters in total"
+    });
+    let body = MessagePackTranscoder.encode(&json).unwrap();
+    expect!(MessagePackTranscoder.decode(&body).unwrap()).to(be_equal_to(json));
+  }
+
+  #[test]
+  fn message_pack_decode() {
+    // {"a": 1, "b": [true, null]}
+    let body = vec![0x82, 0xa1, b'a', 0x01, 0xa1, b'b', 0x92, 0xc3, 0xc0];
+    expect!(MessagePackTranscoder.decode(&body).unwrap()).to(be_equal_to(json!({"a": 1, "b": [true, null]})));
+    // bin 8 with 2 bytes
+    expect!(MessagePackTranscoder.decode(&[0xc4, 0x02, 0x01, 0x02]).unwrap()).to(be_equal_to(json!("AQI=")));
+    expect!(MessagePackTranscoder.decode(&[0xd0, 0x80]).unwrap()).to(be_equal_to(json!(-128)));
+    expect!(MessagePackTranscoder.decode(&[0xcd, 0x01, 0x00]).unwrap()).to(be_equal_to(json!(256)));
+  }
+
+  #[test]
+  fn message_pack_decode_with_invalid_bodies() {
+    expect!(MessagePackTranscoder.decode(&[0x92, 0x01]).map_err(|err| err.to_string())).to(
+      be_err().value("MessagePack body ended unexpectedly at byte 2".to_string()));
+    expect!(MessagePackTranscoder.decode(&[0x01, 0x02]).map_err(|err| err.to_string())).to(
+      be_err().value("MessagePack body has 1 unexpected trailing byte(s)".to_string()));
+    expect!(MessagePackTranscoder.decode(&[0xd4, 0x01, 0x02]).map_err(|err| err.to_string())).to(
+      be_err().value("MessagePack extension type 0xd4 at byte 0 is not supported".to_string()));
+  }
+
+  #[test]
+  fn transcode_test() {
+    let json = ContentType::parse("application/json").unwrap();
+    let body = transcode(b"{\"a\":[1,2]}", &json, &msgpack()).unwrap();
+    expect!(body.clone()).to(be_equal_to(vec![0x81, 0xa1, b'a', 0x92, 0x01, 0x02]));
+    expect!(transcode(&body, &msgpack(), &json).unwrap()).to(be_equal_to(b"{\"a\":[1,2]}".to_vec()));
+    expect!(transcode(b"a: 1", &ContentType::parse("application/yaml").unwrap(), &json)
+      .map_err(|err| err.to_string())).to(
+      be_err().value("There is no transcoder registered for content type application/yaml".to_string()));
+  }
+
+  #[test]
+  fn compatible_content_types() {
+    let cbor = ContentType::parse("application/cbor").unwrap();
+    let json = ContentType::parse("application/vnd.test+json").unwrap();
+    expect!(content_types_are_compatible(&json, &cbor)).to(be_false());
+    declare_compatible_content_types(&cbor, &ContentType::parse("application/json").unwrap());
+    expect!(content_types_are_compatible(&json, &cbor)).to(be_true());
+    expect!(content_types_are_compatible(&cbor, &json)).to(be_true());
+  }
+}
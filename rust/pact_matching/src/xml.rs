@@ -13,7 +13,7 @@ use crate::matchers::*;
 use crate::MatchingContext;
 use crate::models::HttpPart;
 use crate::models::matchingrules::*;
-use crate::models::xml_utils::parse_bytes;
+use crate::models::xml_utils::{is_soap_header, local_name, must_understand, parse_bytes};
 
 use super::DiffConfig;
 use super::Mismatch;
@@ -59,7 +59,7 @@ pub fn match_xml(expected: &dyn HttpPart, actual: &dyn HttpPart, context: &Match
         let element = expected_root_node.unwrap().element().unwrap();
         let name = name(element.name());
         let path = vec!["$", name.as_str()];
-        compare_element(&path, &element, &actual_root_node.unwrap().element().unwrap(), &mut mismatches, &context);
+        compare_element(&path, &element, &actual_root_node.unwrap().element().unwrap(), &mut mismatches, context, false);
       }
     },
     _ => {
@@ -108,19 +108,19 @@ impl<'a> Matches<Element<'a>> for Element<'a> {
              Err(format!("Expected '{}' to be the same type as '{}'", name(self.name()),
                          name(actual.name())))
           },
-          MatchingRule::MinType(min) => if actual.children().len() < min {
+          MatchingRule::MinType(min) => if children(actual).len() < min {
              Err(format!("Expected '{}' to have at least {} children", name(actual.name()), min))
           } else {
              Ok(())
           },
-          MatchingRule::MaxType(max) => if actual.children().len() > max {
+          MatchingRule::MaxType(max) => if children(actual).len() > max {
              Err(format!("Expected '{}' to have at most {} children", name(actual.name()), max))
           } else {
              Ok(())
           },
-          MatchingRule::MinMaxType(min, max) => if actual.children().len() < min {
+          MatchingRule::MinMaxType(min, max) => if children(actual).len() < min {
             Err(format!("Expected '{}' to have at least {} children", name(actual.name()), min))
-          } else if actual.children().len() > max {
+          } else if children(actual).len() > max {
             Err(format!("Expected '{}' to have at most {} children", name(actual.name()), max))
           } else {
             Ok(())
@@ -139,21 +139,82 @@ impl<'a> Matches<Element<'a>> for Element<'a> {
     }
 }
 
+/// An element that is one of a group of repeated elements with the same name (i.e. each-like).
+/// Any minimum or maximum size applies to the number of elements in the group, and not to the
+/// children of each element.
+struct RepeatedElement<'a>(Element<'a>);
+
+impl<'a> Matches<RepeatedElement<'a>> for RepeatedElement<'a> {
+  fn matches(&self, actual: &RepeatedElement<'a>, matcher: &MatchingRule) -> Result<(), String> {
+    match matcher {
+      MatchingRule::MinType(_) | MatchingRule::MaxType(_) | MatchingRule::MinMaxType(_, _) =>
+        self.0.matches(&actual.0, &MatchingRule::Type),
+      _ => self.0.matches(&actual.0, matcher)
+    }
+  }
+}
+
+/// Formats the path, using the bracket notation for the namespace qualified names so the path
+/// can be used as a matching rule path expression
 fn path_to_string(path: &Vec<&str>) -> String {
   path.iter().enumerate().map(|(i, p)| {
-    if i > 0 && !p.starts_with("[") {
-      ".".to_owned() + p
-    } else {
+    if i == 0 || p.starts_with('[') {
       p.to_string()
+    } else if p.contains(':') {
+      format!("['{}']", p)
+    } else {
+      ".".to_owned() + p
     }
   }).collect()
 }
 
+/// Returns the path to use to look up the matching rules. Rules can be defined using either the
+/// namespace qualified names or the local names of the elements and attributes, with the
+/// qualified names taking precedence.
+fn matcher_path(path: &[&str], context: &MatchingContext) -> Vec<String> {
+  let qualified: Vec<String> = path.iter().map(|p| p.to_string()).collect();
+  if context.matcher_is_defined(path) {
+    return qualified;
+  }
+  let local: Vec<String> = path.iter().map(|p| local_name(p)).collect();
+  if local != qualified && context.matcher_is_defined(&as_path(&local)) {
+    local
+  } else {
+    qualified
+  }
+}
+
+fn as_path(path: &[String]) -> Vec<&str> {
+  path.iter().map(|p| p.as_str()).collect()
+}
+
+/// Returns the minimum and maximum number of elements of a group of repeated elements, from the
+/// rules defined for the exact path of the elements
+fn repeated_element_bounds(path: &[&str], context: &MatchingContext) -> (Option<usize>, Option<usize>) {
+  let rules = context.matchers.filter(|&(val, _)| {
+    calc_path_weight(val, path).0 > 0 && path_length(val) == path.len()
+  });
+  rules.rules.values()
+    .flat_map(|rule_list| rule_list.rules.iter())
+    .fold((None, None), |(min, max), rule| match rule {
+      MatchingRule::MinType(m) => (Some(*m), max),
+      MatchingRule::MaxType(m) => (min, Some(*m)),
+      MatchingRule::MinMaxType(mn, mx) => (Some(*mn), Some(*mx)),
+      _ => (min, max)
+    })
+}
+
 fn compare_element(path: &Vec<&str>, expected: &Element, actual: &Element,
-  mismatches: &mut Vec<super::Mismatch>, context: &MatchingContext) {
-  let matcher_result = if context.matcher_is_defined(&path) {
-    log::debug!("calling match_values {:?} on {:?}", path, actual);
-    match_values(&path, context, expected, actual)
+  mismatches: &mut Vec<super::Mismatch>, context: &MatchingContext, repeated: bool) {
+  let lookup = matcher_path(path, context);
+  let lookup = as_path(&lookup);
+  let matcher_result = if context.matcher_is_defined(&lookup) {
+    log::debug!("calling match_values {:?} on {:?}", lookup, actual);
+    if repeated {
+      match_values(&lookup, context, &RepeatedElement(*expected), &RepeatedElement(*actual))
+    } else {
+      match_values(&lookup, context, expected, actual)
+    }
   } else {
     expected.matches(actual, &MatchingRule::Equality).map_err(|err| vec![err])
   };
@@ -240,8 +301,15 @@ fn desc_children(children: &Vec<Element>) -> String {
 
 fn compare_children(path: &Vec<&str>, expected: &Element, actual: &Element,
   mismatches: &mut Vec<super::Mismatch>, context: &MatchingContext) {
-  let expected_children = children(expected);
-  let actual_children = children(actual);
+  let (expected_children, actual_children) = if is_soap_header(expected) {
+    log::debug!("Ignoring any SOAP header entries with mustUnderstand set at path {}", path_to_string(path));
+    (
+      children(expected).into_iter().filter(|child| !must_understand(child)).collect(),
+      children(actual).into_iter().filter(|child| !must_understand(child)).collect()
+    )
+  } else {
+    (children(expected), children(actual))
+  };
 
   if expected_children.is_empty() && !actual_children.is_empty() && context.config == DiffConfig::NoUnexpectedKeys {
     mismatches.push(Mismatch::BodyMismatch {
@@ -275,10 +343,13 @@ fn compare_children(path: &Vec<&str>, expected: &Element, actual: &Element,
       if expected_children_by_name.contains_key(&key) {
         let expected_children = expected_children_by_name.remove(&key).unwrap();
         let expected = expected_children.iter().next().unwrap();
-        if context.type_matcher_defined(&p) {
+        let lookup = matcher_path(&p, context);
+        let lookup = as_path(&lookup);
+        if context.type_matcher_defined(&lookup) {
           log::debug!("Matcher defined for path {}", path_to_string(&p));
+          let each_like = compare_group_size(&p, &lookup, &key, group.len(), mismatches, context);
           for child in group {
-            compare_element(&p, &expected, &child, mismatches, context);
+            compare_element(&p, expected, &child, mismatches, context, each_like);
           }
         } else {
           for pair in expected_children.iter().zip_longest(group) {
@@ -296,12 +367,12 @@ fn compare_children(path: &Vec<&str>, expected: &Element, actual: &Element,
                   mismatch: format!("Expected child <{}/> but was missing", name(expected.name()))});
               },
               EitherOrBoth::Both(expected, actual) => {
-                compare_element(&p, &expected, &actual, mismatches, context);
+                compare_element(&p, expected, &actual, mismatches, context, false);
               }
             }
           }
         }
-      } else if context.config == DiffConfig::NoUnexpectedKeys || context.type_matcher_defined(&p) {
+      } else if context.config == DiffConfig::NoUnexpectedKeys || context.type_matcher_defined(&as_path(&matcher_path(&p, context))) {
         mismatches.push(Mismatch::BodyMismatch { path: path_to_string(path),
           expected: Some(desc_children(&expected_children.clone()).into()),
           actual: Some(desc_children(&actual_children.clone()).into()),
@@ -311,6 +382,14 @@ fn compare_children(path: &Vec<&str>, expected: &Element, actual: &Element,
 
     if !expected_children_by_name.is_empty() {
       for key in expected_children_by_name.keys() {
+        let mut p = path.to_vec();
+        p.push(key.as_str());
+        let lookup = matcher_path(&p, context);
+        let lookup = as_path(&lookup);
+        if context.type_matcher_defined(&lookup) && repeated_element_bounds(&lookup, context).0 == Some(0) {
+          log::debug!("Repeated element <{}/> at path {} can be empty", key, path_to_string(&p));
+          continue;
+        }
         mismatches.push(Mismatch::BodyMismatch { path: path_to_string(path),
           expected: Some(desc_children(&expected_children.clone()).into()),
           actual: Some(desc_children(&actual_children.clone()).into()),
@@ -320,6 +399,27 @@ fn compare_children(path: &Vec<&str>, expected: &Element, actual: &Element,
   }
 }
 
+/// Checks the number of repeated elements against any minimum or maximum size defined for their
+/// path, returning true if there was one
+fn compare_group_size(path: &Vec<&str>, lookup: &[&str], key: &str, size: usize,
+  mismatches: &mut Vec<super::Mismatch>, context: &MatchingContext) -> bool {
+  let (min, max) = repeated_element_bounds(lookup, context);
+  let message = match (min, max) {
+    (Some(min), _) if size < min => Some(format!("Expected at least {} <{}/> element(s) but received {}", min, key, size)),
+    (_, Some(max)) if size > max => Some(format!("Expected at most {} <{}/> element(s) but received {}", max, key, size)),
+    _ => None
+  };
+  if let Some(message) = message {
+    mismatches.push(Mismatch::BodyMismatch {
+      path: path_to_string(path),
+      expected: Some(key.to_string().into()),
+      actual: Some(format!("{} element(s)", size).into()),
+      mismatch: message
+    });
+  }
+  min.is_some() || max.is_some()
+}
+
 fn compare_text(path: &Vec<&str>, expected: &Element, actual: &Element,
     mismatches: &mut Vec<super::Mismatch>, context: &MatchingContext) {
     let expected_text = s!(expected.children().iter().cloned()
@@ -332,8 +432,10 @@ fn compare_text(path: &Vec<&str>, expected: &Element, actual: &Element,
         .collect::<String>().trim());
     let mut p = path.to_vec();
     p.push("#text");
-    let matcher_result = if context.matcher_is_defined(&p) {
-      match_values(&p, context, &expected_text, &actual_text)
+    let lookup = matcher_path(&p, context);
+    let lookup = as_path(&lookup);
+    let matcher_result = if context.matcher_is_defined(&lookup) {
+      match_values(&lookup, context, &expected_text, &actual_text)
     } else {
       expected_text.matches(&actual_text, &MatchingRule::Equality).map_err(|err| vec![err])
     };
@@ -355,8 +457,10 @@ fn compare_text(path: &Vec<&str>, expected: &Element, actual: &Element,
 }
 
 fn compare_value(path: &Vec<&str>, expected: &String, actual: &String, context: &MatchingContext) -> Result<(), Vec<Mismatch>> {
-  let matcher_result = if context.matcher_is_defined(&path) {
-    match_values(path, context, expected, actual)
+  let lookup = matcher_path(path, context);
+  let lookup = as_path(&lookup);
+  let matcher_result = if context.matcher_is_defined(&lookup) {
+    match_values(&lookup, context, expected, actual)
   } else {
     expected.matches(actual, &MatchingRule::Equality).map_err(|err| vec![err])
   };
@@ -943,7 +1047,7 @@ mod tests {
     let actual = request!(r#"<blah xmlns="urn:ns"/>"#);
     let result = match_xml(&expected, &actual, &MatchingContext::with_config(DiffConfig::NoUnexpectedKeys));
     expect!(result).to(be_err().value(vec![ Mismatch::BodyMismatch {
-      path: "$.urn:other:blah".to_string(),
+      path: "$['urn:other:blah']".to_string(),
       expected: Some("urn:other:blah".into()),
       actual: Some("urn:ns:blah".into()),
       mismatch: "Expected 'urn:other:blah' to be equal to 'urn:ns:blah'".to_string()
@@ -956,7 +1060,7 @@ mod tests {
     let actual = request!("<blah/>");
     let result = match_xml(&expected, &actual, &MatchingContext::with_config(DiffConfig::NoUnexpectedKeys));
     expect!(result).to(be_err().value(vec![ Mismatch::BodyMismatch {
-      path: "$.urn:other:blah".to_string(),
+      path: "$['urn:other:blah']".to_string(),
       expected: Some("urn:other:blah".into()),
       actual: Some("blah".into()),
       mismatch: "Expected 'urn:other:blah' to be equal to 'blah'".to_string()
@@ -990,7 +1094,7 @@ mod tests {
     let actual = request!("<foo xmlns:ns=\"urn:a\" ns:something=\"100\"/>");
     let result = match_xml(&expected, &actual, &MatchingContext::with_config(DiffConfig::NoUnexpectedKeys));
     expect!(result).to(be_err().value(vec![ Mismatch::BodyMismatch {
-      path: "$.foo.@urn:b:something".to_string(),
+      path: "$.foo['@urn:b:something']".to_string(),
       expected: Some("urn:b:something".into()),
       actual: None,
       mismatch: "Expected attribute 'urn:b:something'='100' but was missing".to_string()
@@ -1111,4 +1215,88 @@ mod tests {
     let result = match_xml(&expected.clone(), &actual.clone(), &MatchingContext::new(DiffConfig::NoUnexpectedKeys, &matching_rules));
     expect!(result).to(be_ok());
   }
+
+  #[test]
+  fn matching_rules_can_use_the_local_names_of_namespaced_elements_and_attributes() {
+    let expected = request!(r#"<ns:foo xmlns:ns="urn:ns" ns:id="1"><ns:something>101</ns:something></ns:foo>"#);
+    let actual = request!(r#"<a:foo xmlns:a="urn:ns" a:id="22"><a:something>100</a:something></a:foo>"#);
+    let matching_rules = matchingrules! {
+      "body" => {
+        "$.foo.something.#text" => [ MatchingRule::Regex(s!("^[0-9]+$")) ],
+        "$.foo['@id']" => [ MatchingRule::Integer ]
+      }
+    };
+    let result = match_xml(&expected, &actual, &MatchingContext::new(DiffConfig::NoUnexpectedKeys,
+      &matching_rules.rules_for_category("body").unwrap()));
+    expect!(result).to(be_ok());
+  }
+
+  #[test]
+  fn mismatch_paths_use_the_bracket_notation_for_namespaced_names() {
+    let expected = request!(r#"<ns:foo xmlns:ns="urn:ns"><ns:something>101</ns:something></ns:foo>"#);
+    let actual = request!(r#"<ns:foo xmlns:ns="urn:ns"><ns:something>100</ns:something></ns:foo>"#);
+    let result = match_xml(&expected, &actual, &MatchingContext::with_config(DiffConfig::NoUnexpectedKeys));
+    expect!(result.unwrap_err().iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$['urn:ns:foo']['urn:ns:something'].#text -> Expected '101' to be equal to '100'".to_string()
+    ]));
+  }
+
+  #[test]
+  fn each_like_rules_apply_to_the_number_of_repeated_elements() {
+    let expected = request!(r#"<items><item><id>1</id><name>a</name></item></items>"#);
+    let actual = request!(r#"<items><item><id>2</id><name>b</name></item><item><id>3</id><name>c</name></item></items>"#);
+    let rules = |min, max| matchingrules! {
+      "body" => {
+        "$.items.item" => [ MatchingRule::MinMaxType(min, max) ],
+        "$.items.item.*" => [ MatchingRule::Type ],
+        "$.items.item.*.#text" => [ MatchingRule::Type ]
+      }
+    }.rules_for_category("body").unwrap();
+
+    let result = match_xml(&expected, &actual, &MatchingContext::new(DiffConfig::NoUnexpectedKeys, &rules(1, 2)));
+    expect!(result).to(be_ok());
+
+    let result = match_xml(&expected, &actual, &MatchingContext::new(DiffConfig::NoUnexpectedKeys, &rules(3, 4)));
+    expect!(result.unwrap_err().iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$.items.item -> Expected at least 3 <item/> element(s) but received 2".to_string()
+    ]));
+
+    let result = match_xml(&expected, &actual, &MatchingContext::new(DiffConfig::NoUnexpectedKeys, &rules(0, 1)));
+    expect!(result.unwrap_err().iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$.items.item -> Expected at most 1 <item/> element(s) but received 2".to_string()
+    ]));
+
+    let empty = request!(r#"<items/>"#);
+    let result = match_xml(&expected, &empty, &MatchingContext::new(DiffConfig::NoUnexpectedKeys, &rules(0, 2)));
+    expect!(result).to(be_ok());
+  }
+
+  #[test]
+  fn match_xml_ignores_soap_header_entries_with_must_understand_set() {
+    let expected = request!(r#"<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+      <soap:Header>
+        <wsse:Security xmlns:wsse="urn:wsse" soap:mustUnderstand="1"><wsse:Nonce>abc</wsse:Nonce></wsse:Security>
+        <t:Transaction xmlns:t="urn:t">5</t:Transaction>
+      </soap:Header>
+      <soap:Body><m:GetPrice xmlns:m="urn:m"><m:Item>Apples</m:Item></m:GetPrice></soap:Body>
+    </soap:Envelope>"#);
+    let actual = request!(r#"<env:Envelope xmlns:env="http://schemas.xmlsoap.org/soap/envelope/">
+      <env:Header>
+        <t:Transaction xmlns:t="urn:t">5</t:Transaction>
+        <a:Timestamp xmlns:a="urn:a" env:mustUnderstand="true">2021-01-01</a:Timestamp>
+      </env:Header>
+      <env:Body><m:GetPrice xmlns:m="urn:m"><m:Item>Apples</m:Item></m:GetPrice></env:Body>
+    </env:Envelope>"#);
+    let result = match_xml(&expected, &actual, &MatchingContext::with_config(DiffConfig::NoUnexpectedKeys));
+    expect!(result).to(be_ok());
+
+    let actual = request!(r#"<env:Envelope xmlns:env="http://schemas.xmlsoap.org/soap/envelope/">
+      <env:Header><t:Transaction xmlns:t="urn:t">6</t:Transaction></env:Header>
+      <env:Body><m:GetPrice xmlns:m="urn:m"><m:Item>Apples</m:Item></m:GetPrice></env:Body>
+    </env:Envelope>"#);
+    let result = match_xml(&expected, &actual, &MatchingContext::with_config(DiffConfig::NoUnexpectedKeys));
+    expect!(result.unwrap_err().iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+      "$['http://schemas.xmlsoap.org/soap/envelope/:Envelope']['http://schemas.xmlsoap.org/soap/envelope/:Header']['urn:t:Transaction'].#text -> Expected '5' to be equal to '6'".to_string()
+    ]));
+  }
 }
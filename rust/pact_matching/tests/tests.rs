@@ -400,8 +400,8 @@ fn test_load_v2_pact_query() {
                 expect!(pact_request.get("headers")).to(be_equal_to(file_request.get("headers")));
                 expect!(pact_request.get("body")).to(be_equal_to(file_request.get("body")));
                 expect!(pact_request.get("matchers")).to(be_equal_to(file_request.get("matchers")));
-                expect!(pact_request.get("query").unwrap().to_string().to_uppercase()).to(
-                    be_equal_to(file_request.get("query").unwrap().to_string().to_uppercase()));
+                expect!(parse_query_string(pact_request.get("query").unwrap().as_str().unwrap())).to(
+                    be_equal_to(parse_query_string(file_request.get("query").unwrap().as_str().unwrap())));
             }
 
             expect!(pact.metadata.get("pactSpecification").unwrap().get("version")).to(be_some().value("2.0.0"));
@@ -626,3 +626,14 @@ fn test_load_v4_pact_with_message_with_comments() {
     Err(err) => panic!("Failed to load pact from '{:?}' - {}", pact_file, err)
   }
 }
+
+#[test]
+fn spec_runner_passes_all_the_spec_test_cases() {
+  let results = pact_matching::spec_runner::run_test_cases(&fixture_path("spec_testcases"), None).unwrap();
+  let failed = results.iter()
+    .filter(|result| !result.passed())
+    .map(|result| result.test_case.source.clone())
+    .collect::<Vec<String>>();
+  expect!(results.len()).to(be_equal_to(803));
+  expect!(failed.iter()).to(be_empty());
+}
@@ -42,6 +42,29 @@ int main(void) {
 
     message_delete(msg_json);
 
+
+    /*=======================================================================
+     * Checking the kind of error, and reading a message of any length.
+     *---------------------------------------------------------------------*/
+
+    Message *msg_invalid = message_new_from_json(0, "{", PactSpecification_V3);
+
+    if (msg_invalid == NULL) {
+        if (last_error_code() == ErrorCode_JsonParse) {
+            printf("the message JSON could not be parsed\n");
+        }
+
+        int len = last_error_length();
+        char *error_msg = malloc(len);
+        if (error_msg != NULL && last_error_message(error_msg, len) >= 0) {
+            printf("%s\n", error_msg);
+        }
+        free(error_msg);
+    } else {
+        message_delete(msg_invalid);
+        return EXIT_FAILURE;
+    }
+
     return EXIT_SUCCESS;
 }
 
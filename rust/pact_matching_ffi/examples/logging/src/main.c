@@ -38,6 +38,18 @@ int main(void) {
         return EXIT_FAILURE;
     }
 
+    /*=======================================================================
+     * Attach a sink collecting warnings in memory, to be fetched later.
+     *---------------------------------------------------------------------*/
+
+    status = logger_attach_sink("buffer", LevelFilter_Warn);
+    if (status != 0) {
+        char error_msg[ERROR_MSG_LEN];
+        int error = get_error_message(error_msg, ERROR_MSG_LEN);
+        printf("%s\n", error_msg);
+        return EXIT_FAILURE;
+    }
+
     /*=======================================================================
      * Apply the logger, completing logging setup.
      *---------------------------------------------------------------------*/
@@ -50,6 +62,16 @@ int main(void) {
         return EXIT_FAILURE;
     }
 
+    /*=======================================================================
+     * Fetch the warnings collected in the buffer.
+     *---------------------------------------------------------------------*/
+
+    const char *logs = log_fetch_buffer();
+    if (logs != NULL) {
+        printf("%s", logs);
+        string_delete((char *) logs);
+    }
+
     return EXIT_SUCCESS;
 }
 
@@ -0,0 +1,104 @@
+//! The kinds of errors which the FFI functions may report to the C caller.
+
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+/// The kind of the last error, which can be checked with `last_error_code`, so the
+/// caller can react to an error without having to parse the error message.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ErrorCode {
+    /// There is no last error.
+    NoError = 0,
+
+    /// A NULL pointer was passed in where a valid pointer was required.
+    NullPointer = 1,
+
+    /// A string passed in was not valid UTF-8.
+    InvalidUtf8 = 2,
+
+    /// A string passed in could not be parsed as JSON.
+    JsonParse = 3,
+
+    /// The Rust code panicked.
+    Panic = 4,
+
+    /// Any other error (i.e. an invalid pact, or an I/O error).
+    Other = 5,
+}
+
+impl ErrorCode {
+    /// Determine the kind of error from the error, or any error which caused it.
+    pub(crate) fn of(err: &anyhow::Error) -> ErrorCode {
+        err.chain()
+            .find_map(|cause| {
+                if cause.is::<NullPointerError>() {
+                    Some(ErrorCode::NullPointer)
+                } else if cause.is::<Utf8Error>() || cause.is::<FromUtf8Error>() {
+                    Some(ErrorCode::InvalidUtf8)
+                } else if cause.is::<serde_json::Error>() {
+                    Some(ErrorCode::JsonParse)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorCode::Other)
+    }
+}
+
+/// The error raised when a required pointer argument is NULL.
+#[derive(Debug, thiserror::Error)]
+#[error("{0} is null")]
+pub(crate) struct NullPointerError(pub(crate) &'static str);
+
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+    use expectest::prelude::*;
+
+    use crate::error::catch_panic;
+    use crate::error::ffi::last_error_code;
+    use crate::error::last_error::clear_error;
+
+    use super::*;
+
+    #[test]
+    fn error_code_of_maps_the_errors_to_codes() {
+        let invalid_utf8 = vec![0xFF_u8, 0xFE];
+        expect!(ErrorCode::of(&anyhow::Error::new(NullPointerError("pact")))).to(be_equal_to(ErrorCode::NullPointer));
+        expect!(ErrorCode::of(&std::str::from_utf8(&invalid_utf8).unwrap_err().into()))
+            .to(be_equal_to(ErrorCode::InvalidUtf8));
+        expect!(ErrorCode::of(&String::from_utf8(invalid_utf8).unwrap_err().into()))
+            .to(be_equal_to(ErrorCode::InvalidUtf8));
+        expect!(ErrorCode::of(&serde_json::from_str::<serde_json::Value>("{").unwrap_err().into()))
+            .to(be_equal_to(ErrorCode::JsonParse));
+        expect!(ErrorCode::of(&anyhow!("the pact is not valid"))).to(be_equal_to(ErrorCode::Other));
+    }
+
+    #[test]
+    fn error_code_of_checks_the_causes_of_the_error() {
+        let err = serde_json::from_str::<serde_json::Value>("{")
+            .context("error parsing json_str as JSON")
+            .unwrap_err();
+        expect!(ErrorCode::of(&err)).to(be_equal_to(ErrorCode::JsonParse));
+    }
+
+    #[test]
+    fn last_error_code_reports_the_code_of_the_last_error() {
+        clear_error();
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NoError));
+
+        catch_panic::<(), _>(|| Err(anyhow::Error::new(NullPointerError("pact"))));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+
+        catch_panic::<(), _>(|| panic!("something went wrong"));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Panic));
+
+        catch_panic(|| Ok(()));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Panic));
+
+        clear_error();
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NoError));
+    }
+}
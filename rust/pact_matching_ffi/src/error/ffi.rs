@@ -1,6 +1,7 @@
 //! The FFI functions exposed for getting the last error.
 
-use crate::error::last_error::get_error_msg;
+use crate::error::code::ErrorCode;
+use crate::error::last_error::{clear_error, get_error_msg, peek_error};
 use crate::error::status::Status;
 use crate::util::write::write_to_c_buf;
 use libc::{c_char, c_int};
@@ -67,3 +68,70 @@ pub extern "C" fn get_error_message(
 
     status as c_int
 }
+
+/// Get the kind of the last error, so the caller can react to it without having to
+/// parse the error message. Returns `NoError` if there is no last error.
+///
+/// This does not clear the last error, so it should be checked before the message is
+/// read with `last_error_message` (or `get_error_message`).
+#[no_mangle]
+pub extern "C" fn last_error_code() -> ErrorCode {
+    peek_error().map(|err| err.code).unwrap_or(ErrorCode::NoError)
+}
+
+/// Get the size of buffer needed to hold the last error message, including the NULL
+/// terminator. Returns 0 if there is no last error.
+///
+/// This does not clear the last error, so it can be used to allocate a buffer of the right
+/// size before calling `last_error_message`.
+#[no_mangle]
+pub extern "C" fn last_error_length() -> c_int {
+    peek_error().map(|err| err.message.len() as c_int + 1).unwrap_or(0)
+}
+
+/// Write the last error message into the provided buffer, and clear the last error.
+///
+/// # Params
+///
+/// * `buffer`: a pointer to an array of `char` of at least `last_error_length` bytes.
+/// * `length`: an int providing the length of the `buffer`.
+///
+/// # Return Codes
+///
+/// * The length of the error message written to the buffer (not including the NULL
+///   terminator), which will be zero if there is no last error.
+/// * `-1` if the provided buffer is a null pointer.
+/// * `-2` if the provided buffer length is too small for the error message.
+/// * `-3` if the write failed for some other reason.
+/// * `-4` if the error message had an interior NULL
+///
+/// # Error Handling
+///
+/// The last error is only cleared if it was written to the buffer, so on a negative return
+/// code the call can be retried (i.e. with a larger buffer). Any excess space in the buffer
+/// is zeroed.
+#[no_mangle]
+pub extern "C" fn last_error_message(
+    buffer: *mut c_char,
+    length: c_int,
+) -> c_int {
+    if buffer.is_null() {
+        return Status::NullBuffer as c_int;
+    }
+    if length < 0 {
+        return Status::BufferTooSmall as c_int;
+    }
+
+    let buffer = unsafe {
+        slice::from_raw_parts_mut(buffer as *mut u8, length as usize)
+    };
+
+    let message = peek_error().map(|err| err.message).unwrap_or_default();
+    match write_to_c_buf(&message, buffer) {
+        Ok(_) => {
+            clear_error();
+            message.len() as c_int
+        }
+        Err(err) => Status::from(err) as c_int,
+    }
+}
@@ -3,24 +3,126 @@
 // All of this module is `pub(crate)` and should not appear in the C header file
 // or documentation.
 
+use crate::error::code::ErrorCode;
 use std::cell::RefCell;
 
+/// The last error reported by the FFI code: the kind of error, and its message.
+#[derive(Debug, Clone)]
+pub(crate) struct LastError {
+    pub(crate) code: ErrorCode,
+    pub(crate) message: String,
+}
+
 thread_local! {
     // The last error to have been reported by the FFI code.
     /// cbindgen:ignore
-    static LAST_ERROR: RefCell<Option<String>> = RefCell::new(None);
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
 }
 
-/// Update the last error with a new error message.
+/// Update the last error with a new error of the given kind.
 #[inline]
-pub(crate) fn set_error_msg(e: String) {
+pub(crate) fn set_error(code: ErrorCode, message: String) {
     LAST_ERROR.with(|last| {
-        *last.borrow_mut() = Some(e);
+        *last.borrow_mut() = Some(LastError { code, message });
     });
 }
 
-/// Get the last error message if there is one.
+/// Update the last error with a new error message.
+#[inline]
+pub(crate) fn set_error_msg(e: String) {
+    set_error(ErrorCode::Other, e);
+}
+
+/// Get the last error message if there is one, clearing the last error.
 #[inline]
 pub(crate) fn get_error_msg() -> Option<String> {
-    LAST_ERROR.with(|last| last.borrow_mut().take())
+    LAST_ERROR.with(|last| last.borrow_mut().take().map(|err| err.message))
+}
+
+/// Get a copy of the last error if there is one, without clearing it.
+#[inline]
+pub(crate) fn peek_error() -> Option<LastError> {
+    LAST_ERROR.with(|last| last.borrow().clone())
+}
+
+/// Clear the last error.
+#[inline]
+pub(crate) fn clear_error() {
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = None;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use libc::c_char;
+
+    use expectest::prelude::*;
+
+    use crate::error::ffi::{last_error_code, last_error_length, last_error_message};
+
+    use super::*;
+
+    fn message_from(buffer: &[u8]) -> String {
+        let end = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+        String::from_utf8_lossy(&buffer[..end]).to_string()
+    }
+
+    #[test]
+    fn last_error_length_includes_the_null_terminator() {
+        clear_error();
+        expect!(last_error_length()).to(be_equal_to(0));
+
+        set_error(ErrorCode::Other, "the pact is not valid".to_string());
+        expect!(last_error_length()).to(be_equal_to(22));
+        // The length does not clear the error
+        expect!(last_error_length()).to(be_equal_to(22));
+        clear_error();
+    }
+
+    #[test]
+    fn last_error_message_with_no_error_set() {
+        clear_error();
+        let mut buffer = [0xFF_u8; 8];
+        expect!(last_error_message(buffer.as_mut_ptr() as *mut c_char, buffer.len() as i32)).to(be_equal_to(0));
+        expect!(buffer).to(be_equal_to([0_u8; 8]));
+    }
+
+    #[test]
+    fn last_error_message_writes_and_clears_the_error() {
+        set_error(ErrorCode::JsonParse, "invalid JSON".to_string());
+        let mut buffer = vec![0xFF_u8; last_error_length() as usize + 4];
+        let result = last_error_message(buffer.as_mut_ptr() as *mut c_char, buffer.len() as i32);
+
+        expect!(result).to(be_equal_to(12));
+        expect!(message_from(&buffer)).to(be_equal_to("invalid JSON"));
+        expect!(buffer[12..].to_vec()).to(be_equal_to(vec![0_u8; 5]));
+        expect!(peek_error().is_none()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NoError));
+        expect!(last_error_length()).to(be_equal_to(0));
+    }
+
+    #[test]
+    fn last_error_message_with_a_buffer_that_is_too_small() {
+        set_error(ErrorCode::Other, "the pact is not valid".to_string());
+        let mut buffer = [0_u8; 21];
+        expect!(last_error_message(buffer.as_mut_ptr() as *mut c_char, buffer.len() as i32)).to(be_equal_to(-2));
+        expect!(last_error_message(buffer.as_mut_ptr() as *mut c_char, -1)).to(be_equal_to(-2));
+        expect!(last_error_message(std::ptr::null_mut(), 100)).to(be_equal_to(-1));
+
+        // The error is kept, so the call can be retried with a larger buffer
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Other));
+        let mut buffer = [0_u8; 22];
+        expect!(last_error_message(buffer.as_mut_ptr() as *mut c_char, buffer.len() as i32)).to(be_equal_to(21));
+        expect!(message_from(&buffer)).to(be_equal_to("the pact is not valid"));
+    }
+
+    #[test]
+    fn get_error_msg_takes_the_last_error() {
+        set_error_msg("first".to_string());
+        set_error_msg("second".to_string());
+        expect!(peek_error().map(|err| err.code)).to(be_some().value(ErrorCode::Other));
+        expect!(get_error_msg()).to(be_some().value("second"));
+        expect!(get_error_msg()).to(be_none());
+    }
 }
@@ -19,6 +19,10 @@
 //! `get_error_message` function returns an appropriate error sentinel value, which can be checked and handled
 //! by the C code.
 //!
+//! The kind of the last error (i.e. a NULL pointer or invalid UTF-8 was passed in) can be checked with
+//! `last_error_code`, without having to parse the message. `last_error_length` gives the size of buffer
+//! needed for the message, which can then be read with `last_error_message`.
+//!
 //! # How It Works (Crate Internal Perspective)
 //!
 //! All code used for the FFI operations needs to be wrapped in a `catch_unwind`, which captures panics and
@@ -32,14 +36,21 @@
 //! [book]: https://michael-f-bryan.github.io/rust-ffi-guide/errors/index.html "Better Error Handling chapter of the Unofficial Rust FFI book"
 
 mod any_error;
+mod code;
 mod error_msg;
 mod ffi;
 mod last_error;
 mod panic;
 mod status;
 
-// Function for the C program to read the last error message.
-pub use crate::error::ffi::get_error_message;
+// Functions for the C program to read the last error.
+pub use crate::error::ffi::{get_error_message, last_error_code, last_error_length, last_error_message};
+
+// The kinds of errors reported to the C program.
+pub use crate::error::code::ErrorCode;
+
+// The error for NULL pointer arguments, used by the pointer and string macros.
+pub(crate) use crate::error::code::NullPointerError;
 
 // Utility function for setting the last error message.
 pub(crate) use crate::error::last_error::set_error_msg;
@@ -4,7 +4,8 @@
 // or documentation.
 
 use crate::error::any_error::ToErrorMsg;
-use crate::error::last_error::set_error_msg;
+use crate::error::code::ErrorCode;
+use crate::error::last_error::set_error;
 use std::panic::{catch_unwind, UnwindSafe};
 
 /// Convenient panic-catching and reporting.
@@ -22,14 +23,14 @@ where
         Ok(Ok(value)) => Some(value),
         Ok(Err(err)) => {
             // We have an `anyhow::Error`
-            let err = err.to_string();
-            set_error_msg(err);
+            let code = ErrorCode::of(&err);
+            set_error(code, err.to_string());
             None
         }
         Err(err) => {
             // We have an `AnyError`
             let err = err.into_error_msg();
-            set_error_msg(err);
+            set_error(ErrorCode::Panic, err);
             None
         }
     }
@@ -7,13 +7,20 @@
 
 pub mod error;
 pub mod log;
+pub mod mock_server;
 pub mod models;
 pub(crate) mod util;
 
 use crate::util::*;
-use libc::c_char;
+use anyhow::{anyhow, Context};
+use libc::{c_char, c_uchar, size_t};
 use models::message::Message;
+use models::pact::PactInteraction;
+use models::sync_http::{bytes_from_raw, optional_content_type, SynchronousHttp};
 use pact_matching::{self as pm, models::Interaction};
+use pact_matching::models::{matchingrules, Request};
+use pact_models::{OptionalBody, PactSpecification};
+use std::collections::HashMap;
 
 pub use pact_matching::Mismatch;
 
@@ -31,6 +38,114 @@ ffi_fn! {
     }
 }
 
+ffi_fn! {
+    /// Match the actual body against the expected body, taking into account the content
+    /// types and the matching rules, producing a collection of mismatches, which is empty
+    /// if the bodies matched.
+    ///
+    /// The bodies are given as a pointer to the bytes and the length. A NULL body is treated
+    /// as a missing body. The content types may be NULL, in which case they are detected
+    /// from the bodies. The matching rules are given as JSON in the form of the
+    /// `matchingRules` attribute of a V3 or V4 pact (i.e. `{"body": {"$.id": {"matchers":
+    /// [{"match": "integer"}]}}}`), and may be NULL if there are no matching rules. If
+    /// `allow_unexpected_keys` is true, the actual body may have keys in objects which are
+    /// not in the expected body (as is the case for response bodies).
+    ///
+    /// # Safety
+    ///
+    /// The bodies must point to at least the given number of bytes. The returned mismatches
+    /// must be deleted with `mismatches_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On error (i.e. an invalid content type or matching rules), returns NULL.
+    fn match_body(
+        expected: *const c_uchar,
+        expected_len: size_t,
+        expected_content_type: *const c_char,
+        actual: *const c_uchar,
+        actual_len: size_t,
+        actual_content_type: *const c_char,
+        matching_rules: *const c_char,
+        allow_unexpected_keys: bool
+    ) -> *const Mismatches {
+        let expected = http_part_with_body(expected, expected_len, expected_content_type)?;
+        let actual = http_part_with_body(actual, actual_len, actual_content_type)?;
+        let matching_rules = if matching_rules.is_null() {
+            matchingrules::MatchingRules::default()
+        } else {
+            let rules: serde_json::Value = serde_json::from_str(safe_str!(matching_rules))
+                .context("error parsing matching_rules as JSON")?;
+            matchingrules::matchers_from_json(&serde_json::json!({ "matchingRules": rules }), &None)
+        };
+        let config = if allow_unexpected_keys {
+            pm::DiffConfig::AllowUnexpectedKeys
+        } else {
+            pm::DiffConfig::NoUnexpectedKeys
+        };
+        let body_context = pm::MatchingContext::new(config,
+            &matching_rules.rules_for_category("body").unwrap_or_default());
+        let header_context = pm::MatchingContext::new(pm::DiffConfig::NoUnexpectedKeys,
+            &matching_rules.rules_for_category("header").unwrap_or_default());
+        let result = pm::match_body(&expected, &actual, &body_context, &header_context);
+
+        ptr::raw_to(Mismatches(result.mismatches())) as *const Mismatches
+    } {
+        ptr::null_to::<Mismatches>()
+    }
+}
+
+ffi_fn! {
+    /// Match a pair of interactions from pacts (see `pact_interaction_iter_next`), producing
+    /// a collection of mismatches, which is empty if the interactions matched. Both
+    /// interactions must be HTTP interactions, or both must be message interactions.
+    ///
+    /// # Safety
+    ///
+    /// The interactions are not modified, and the returned mismatches do not refer to them.
+    /// The returned mismatches must be deleted with `mismatches_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On error (i.e. the interactions are not of the same type), returns NULL.
+    fn match_interaction(
+        expected: *const PactInteraction,
+        actual: *const PactInteraction
+    ) -> *const Mismatches {
+        let expected = as_ref!(expected);
+        let actual = as_ref!(actual);
+        let mismatches = match_boxed_interactions(expected.boxed(), actual.boxed(), expected.spec_version())?;
+        ptr::raw_to(mismatches) as *const Mismatches
+    } {
+        ptr::null_to::<Mismatches>()
+    }
+}
+
+ffi_fn! {
+    /// Match a pair of HTTP interactions, producing a collection of mismatches from the
+    /// requests and responses, which is empty if the interactions matched.
+    ///
+    /// # Safety
+    ///
+    /// The interactions are not modified, and the returned mismatches do not refer to them.
+    /// The returned mismatches must be deleted with `mismatches_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// If either interaction is NULL, returns NULL.
+    fn match_sync_http(
+        expected: *const SynchronousHttp,
+        actual: *const SynchronousHttp
+    ) -> *const Mismatches {
+        let expected = as_ref!(expected);
+        let actual = as_ref!(actual);
+        let mismatches = match_boxed_interactions(expected.boxed(), actual.boxed(), &PactSpecification::V4)?;
+        ptr::raw_to(mismatches) as *const Mismatches
+    } {
+        ptr::null_to::<Mismatches>()
+    }
+}
+
 ffi_fn! {
     /// Get an iterator over mismatches.
     fn mismatches_get_iter(mismatches: *const Mismatches) -> *mut MismatchesIterator {
@@ -96,6 +211,70 @@ ffi_fn! {
     }
 }
 
+ffi_fn! {
+    /// Get the path of a mismatch. This is the path expression for body mismatches
+    /// (i.e. `$.items[0].id`), the parameter name for query mismatches, and the key
+    /// for header and metadata mismatches.
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// Returns NULL if the mismatch is NULL, or the type of mismatch does not have a
+    /// path (i.e. method and status mismatches).
+    fn mismatch_get_path(mismatch: *const Mismatch) -> *const c_char {
+        let mismatch = as_ref!(mismatch);
+        let path = match mismatch {
+            Mismatch::BodyMismatch { path, .. } => path,
+            Mismatch::QueryMismatch { parameter, .. } => parameter,
+            Mismatch::HeaderMismatch { key, .. } => key,
+            Mismatch::MetadataMismatch { key, .. } => key,
+            _ => return Err(anyhow!("{} does not have a path", mismatch.mismatch_type()))
+        };
+        string::to_c(path)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get the expected value of a mismatch as a string. For body mismatches, this is
+    /// the expected value at the path of the mismatch, and for body type mismatches it is
+    /// the expected content type.
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// Returns NULL if the mismatch is NULL, or there is no expected value.
+    fn mismatch_get_expected(mismatch: *const Mismatch) -> *const c_char {
+        let mismatch = as_ref!(mismatch);
+        let value = mismatch_value(mismatch, "expected")?;
+        string::to_c(&value)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get the actual value of a mismatch as a string. For body mismatches, this is
+    /// the actual value at the path of the mismatch, and for body type mismatches it is
+    /// the actual content type.
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// Returns NULL if the mismatch is NULL, or there is no actual value.
+    fn mismatch_get_actual(mismatch: *const Mismatch) -> *const c_char {
+        let mismatch = as_ref!(mismatch);
+        let value = mismatch_value(mismatch, "actual")?;
+        string::to_c(&value)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
 ffi_fn! {
     /// Get a summary of a mismatch.
     fn mismatch_summary(mismatch: *const Mismatch) -> *const c_char {
@@ -129,6 +308,72 @@ ffi_fn! {
     }
 }
 
+ffi_fn! {
+    /// Get a JSON Patch (RFC 6902) describing how the actual body differs
+    /// from the expected body at the paths of the body mismatches, as a
+    /// JSON string. Applying the patch to the expected body results in one
+    /// that matches the actual body.
+    ///
+    /// The expected and actual bodies must be JSON.
+    fn mismatches_json_patch(
+        mismatches: *const Mismatches,
+        expected: *const c_char,
+        actual: *const c_char
+    ) -> *const c_char {
+        let mismatches = as_ref!(mismatches);
+        let expected: serde_json::Value = serde_json::from_str(safe_str!(expected))
+            .context("error parsing expected as JSON")?;
+        let actual: serde_json::Value = serde_json::from_str(safe_str!(actual))
+            .context("error parsing actual as JSON")?;
+        let patch = pm::json::json_patch(&mismatches.0, &expected, &actual).to_string();
+        string::to_c(&patch)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+fn http_part_with_body(
+    body: *const c_uchar,
+    len: size_t,
+    content_type: *const c_char
+) -> anyhow::Result<Request> {
+    let content_type = optional_content_type(content_type)?;
+    let body = if body.is_null() {
+        OptionalBody::Missing
+    } else if len == 0 {
+        OptionalBody::Empty
+    } else {
+        OptionalBody::Present(bytes_from_raw(body, len)?.into(), content_type.clone())
+    };
+    let headers = content_type.map(|content_type| {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), vec![content_type.to_string()]);
+        headers
+    });
+    Ok(Request { body, headers, .. Request::default() })
+}
+
+fn match_boxed_interactions(
+    expected: Box<dyn Interaction + Send>,
+    actual: Box<dyn Interaction + Send>,
+    spec_version: &PactSpecification
+) -> anyhow::Result<Mismatches> {
+    if expected.is_message() != actual.is_message() {
+        return Err(anyhow!("can not match a {} with a {}", expected.type_of(), actual.type_of()));
+    }
+    pm::match_interaction(expected, actual, spec_version)
+        .map(Mismatches)
+        .map_err(|err| anyhow!(err))
+}
+
+fn mismatch_value(mismatch: &Mismatch, attribute: &str) -> anyhow::Result<String> {
+    match mismatch.to_json().get(attribute) {
+        Some(serde_json::Value::String(s)) => Ok(s.clone()),
+        Some(serde_json::Value::Null) | None => Err(anyhow!("the mismatch has no {} value", attribute)),
+        Some(value) => Ok(value.to_string())
+    }
+}
+
 /// A collection of mismatches from a matching comparison.
 #[allow(missing_copy_implementations)]
 #[allow(missing_debug_implementations)]
@@ -149,3 +394,149 @@ impl MismatchesIterator {
         idx
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use expectest::prelude::*;
+
+    use crate::error::{last_error_code, ErrorCode};
+    use crate::models::pact::{pact_delete, pact_from_json, pact_get_interaction_iter,
+        pact_interaction_iter_delete, pact_interaction_iter_next};
+    use crate::models::sync_http::{sync_http_delete, sync_http_new, sync_http_set_response_body,
+        sync_http_set_response_status};
+
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    fn match_json_bodies(expected: &str, actual: &str, matching_rules: Option<&str>) -> *const Mismatches {
+        let content_type = c("application/json");
+        let matching_rules = matching_rules.map(c);
+        match_body(
+            expected.as_ptr(), expected.len(), content_type.as_ptr(),
+            actual.as_ptr(), actual.len(), content_type.as_ptr(),
+            matching_rules.as_ref().map(|rules| rules.as_ptr()).unwrap_or(std::ptr::null()),
+            true
+        )
+    }
+
+    /// Collects the type and path of each mismatch, and deletes the iterator and the mismatches
+    fn collect_mismatches(mismatches: *const Mismatches) -> Vec<(String, Option<String>)> {
+        let iter = mismatches_get_iter(mismatches);
+        let mut result = vec![];
+        loop {
+            let mismatch = mismatches_iter_next(iter);
+            if mismatch.is_null() {
+                break;
+            }
+            expect!(string::from_c(mismatch_description(mismatch))).to(be_some());
+            expect!(string::from_c(mismatch_to_json(mismatch))).to(be_some());
+            result.push((string::from_c(mismatch_type(mismatch)).unwrap_or_default(),
+                string::from_c(mismatch_get_path(mismatch))));
+        }
+        mismatches_iter_delete(iter);
+        mismatches_delete(mismatches);
+        result
+    }
+
+    #[test]
+    fn match_body_with_matching_bodies() {
+        let mismatches = match_json_bodies(r#"{"id": 1, "name": "Fred"}"#,
+            r#"{"id": 2, "name": "Fred", "age": 40}"#,
+            Some(r#"{"body": {"$.id": {"matchers": [{"match": "integer"}]}}}"#));
+        expect!(mismatches.is_null()).to(be_false());
+        expect!(collect_mismatches(mismatches).iter()).to(be_empty());
+    }
+
+    #[test]
+    fn match_body_with_mismatched_bodies() {
+        let mismatches = match_json_bodies(r#"{"id": 1, "name": "Fred"}"#, r#"{"id": 1, "name": "Mary"}"#, None);
+        expect!(mismatches.is_null()).to(be_false());
+
+        let iter = mismatches_get_iter(mismatches);
+        let mismatch = mismatches_iter_next(iter);
+        expect!(string::from_c(mismatch_get_expected(mismatch))).to(be_some().value("\"Fred\""));
+        expect!(string::from_c(mismatch_get_actual(mismatch))).to(be_some().value("\"Mary\""));
+        expect!(mismatches_iter_next(iter).is_null()).to(be_true());
+        expect!(mismatches_iter_next(iter).is_null()).to(be_true());
+        mismatches_iter_delete(iter);
+
+        expect!(collect_mismatches(mismatches)).to(be_equal_to(vec![
+            ("BodyMismatch".to_string(), Some("$.name".to_string()))
+        ]));
+    }
+
+    #[test]
+    fn match_body_returns_null_on_errors() {
+        let body = "{}";
+        let invalid_content_type = c("not a content type");
+        expect!(match_body(body.as_ptr(), body.len(), invalid_content_type.as_ptr(),
+            body.as_ptr(), body.len(), std::ptr::null(), std::ptr::null(), true).is_null()).to(be_true());
+        expect!(match_json_bodies(body, body, Some("{ not json")).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::JsonParse));
+
+        expect!(mismatches_get_iter(std::ptr::null()).is_null()).to(be_true());
+        expect!(mismatches_iter_next(std::ptr::null_mut()).is_null()).to(be_true());
+        expect!(string::from_c(mismatch_type(std::ptr::null()))).to(be_none());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+    }
+
+    #[test]
+    fn match_sync_http_compares_the_requests_and_responses() {
+        let expected = sync_http_new();
+        let actual = sync_http_new();
+        expect!(collect_mismatches(match_sync_http(expected, actual)).iter()).to(be_empty());
+
+        sync_http_set_response_status(actual, 404);
+        sync_http_set_response_body(expected, c("{\"id\":1}").as_ptr(), c("application/json").as_ptr());
+        sync_http_set_response_body(actual, c("{\"id\":2}").as_ptr(), c("application/json").as_ptr());
+        let mismatches = collect_mismatches(match_sync_http(expected, actual));
+        expect!(mismatches).to(be_equal_to(vec![
+            ("BodyMismatch".to_string(), Some("$.id".to_string())),
+            ("StatusMismatch".to_string(), None)
+        ]));
+
+        expect!(match_sync_http(expected, std::ptr::null()).is_null()).to(be_true());
+        sync_http_delete(expected);
+        sync_http_delete(actual);
+    }
+
+    #[test]
+    fn match_interaction_requires_interactions_of_the_same_type() {
+        let pact_json = c(r#"{
+          "consumer": { "name": "consumer" },
+          "provider": { "name": "provider" },
+          "interactions": [
+            {
+              "type": "Synchronous/HTTP",
+              "description": "get a user",
+              "request": { "method": "GET", "path": "/users/1" },
+              "response": { "status": 200 }
+            },
+            {
+              "type": "Asynchronous/Messages",
+              "description": "a user created event",
+              "contents": { "content": { "id": 1 } }
+            }
+          ],
+          "metadata": { "pactSpecification": { "version": "4.0" } }
+        }"#);
+        let pact = pact_from_json(c("pact.json").as_ptr(), pact_json.as_ptr());
+        expect!(pact.is_null()).to(be_false());
+        let iter = pact_get_interaction_iter(pact);
+        let first = pact_interaction_iter_next(iter);
+        let second = pact_interaction_iter_next(iter);
+
+        expect!(collect_mismatches(match_interaction(first, first)).iter()).to(be_empty());
+        expect!(collect_mismatches(match_interaction(second, second)).iter()).to(be_empty());
+        expect!(match_interaction(first, second).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Other));
+
+        pact_interaction_iter_delete(iter);
+        pact_delete(pact);
+    }
+}
@@ -0,0 +1,37 @@
+//! The in-memory buffer logs may be written to, to be fetched later by the C caller.
+
+// All of this module is `pub(crate)` and should not appear in the C header file
+// or documentation.
+
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Mutex, MutexGuard};
+
+// The buffer is global rather than thread-local, as logs may be written from any thread
+// (i.e. the threads of a running mock server).
+static LOG_BUFFER: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+fn lock_buffer() -> MutexGuard<'static, Vec<u8>> {
+    // A panic while holding the lock can't leave the buffer in an invalid state.
+    LOG_BUFFER.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// A writer which appends to the in-memory log buffer.
+#[derive(Debug)]
+pub(crate) struct LogBuffer;
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        lock_buffer().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Take the contents of the log buffer, leaving it empty.
+pub(crate) fn take_buffer() -> Vec<u8> {
+    mem::take(&mut *lock_buffer())
+}
@@ -1,15 +1,18 @@
 //! The public FFI functions for initializing, adding sinks to, and applying a logger.
 
 use crate::error::set_error_msg;
+use crate::log::buffer::take_buffer;
 use crate::log::level_filter::LevelFilter;
 use crate::log::logger::{add_sink, apply_logger, set_logger};
-use crate::log::sink::Sink;
+use crate::log::sink::{LogCallback, Sink};
 use crate::log::status::Status;
+use crate::util::string;
 use fern::Dispatch;
 use libc::{c_char, c_int};
 use log::LevelFilter as LogLevelFilter;
 use std::convert::TryFrom;
 use std::ffi::{CStr, CString};
+use std::ptr;
 
 /// Convenience function to direct all logging to stdout.
 #[no_mangle]
@@ -63,6 +66,33 @@ pub extern "C" fn log_to_stderr(level_filter: LevelFilter) -> c_int {
     Status::Success as c_int
 }
 
+/// Convenience function to direct all logging to the in-memory buffer, which can be
+/// fetched with `log_fetch_buffer`.
+#[no_mangle]
+pub extern "C" fn log_to_buffer(level_filter: LevelFilter) -> c_int {
+    logger_init();
+
+    let spec = match CString::new("buffer") {
+        Ok(spec) => spec,
+        Err(e) => {
+            set_error_msg(e.to_string());
+            return Status::CantConstructSink as c_int;
+        }
+    };
+
+    let status = logger_attach_sink(spec.as_ptr(), level_filter);
+    if status != 0 {
+        return status;
+    }
+
+    let status = logger_apply();
+    if status != 0 {
+        return status;
+    }
+
+    Status::Success as c_int
+}
+
 /// Convenience function to direct all logging to a file.
 #[no_mangle]
 pub extern "C" fn log_to_file(
@@ -114,6 +144,9 @@ pub extern "C" fn log_to_file(
 // * stdout (`logger_attach_sink("stdout", LevelFilter_Info)`)
 // * stderr (`logger_attach_sink("stderr", LevelFilter_Debug)`)
 // * file w/ file path (`logger_attach_sink("file /some/file/path", LevelFilter_Trace)`)
+// * an in-memory buffer (`logger_attach_sink("buffer", LevelFilter_Debug)`)
+//
+// A C function can also be attached as a sink with `logger_attach_callback`.
 //
 // The general flow is:
 //
@@ -159,11 +192,13 @@ pub extern "C" fn logger_init() {
 ///
 /// This logger does nothing until `logger_apply` has been called.
 ///
-/// Three types of sinks can be specified:
+/// Four types of sinks can be specified:
 ///
 /// - stdout (`logger_attach_sink("stdout", LevelFilter_Info)`)
 /// - stderr (`logger_attach_sink("stderr", LevelFilter_Debug)`)
 /// - file w/ file path (`logger_attach_sink("file /some/file/path", LevelFilter_Trace)`)
+/// - an in-memory buffer (`logger_attach_sink("buffer", LevelFilter_Debug)`), which is
+///   fetched with `log_fetch_buffer`
 ///
 /// # Usage
 ///
@@ -178,7 +213,7 @@ pub extern "C" fn logger_init() {
 /// - `-1`: Can't set logger (applying the logger failed, perhaps because one is applied already).
 /// - `-2`: No logger has been initialized (call `logger_init` before any other log function).
 /// - `-3`: The sink specifier was not UTF-8 encoded.
-/// - `-4`: The sink type specified is not a known type (known types: "stdout", "stderr", or "file /some/path").
+/// - `-4`: The sink type specified is not a known type (known types: "stdout", "stderr", "buffer", or "file /some/path").
 /// - `-5`: No file path was specified in a file-type sink specification.
 /// - `-6`: Opening a sink to the specified file path failed (check permissions).
 ///
@@ -208,6 +243,46 @@ pub extern "C" fn logger_attach_sink(
         Err(err) => return Status::from(err) as c_int,
     };
 
+    attach_sink(sink, level_filter) as c_int
+}
+
+/// Attach a sink to the thread-local logger which calls the `callback` function
+/// with each log message at the level or lower.
+///
+/// This logger does nothing until `logger_apply` has been called.
+///
+/// # Usage
+///
+/// ```c
+/// void log_message(LevelFilter level, const char *message) {
+///     printf("%s\n", message);
+/// }
+///
+/// int result = logger_attach_callback(log_message, LevelFilter_Debug);
+/// ```
+///
+/// # Error Handling
+///
+/// The return error codes are as for `logger_attach_sink`, with `-7` returned if
+/// the callback is NULL.
+///
+/// # Safety
+///
+/// The callback may be called from any thread, and must remain valid for the rest of
+/// the program.
+#[no_mangle]
+pub extern "C" fn logger_attach_callback(
+    callback: Option<LogCallback>,
+    level_filter: LevelFilter,
+) -> c_int {
+    match callback {
+        Some(callback) => attach_sink(Sink::Callback(callback), level_filter) as c_int,
+        None => Status::CantConstructSink as c_int,
+    }
+}
+
+/// Add the sink to the thread-local logger, passing on messages at the level or lower.
+fn attach_sink(sink: Sink, level_filter: LevelFilter) -> Status {
     // Convert from our `#[repr(C)]` LevelFilter to the one from the `log` crate.
     let level_filter: LogLevelFilter = level_filter.into();
 
@@ -224,12 +299,10 @@ pub extern "C" fn logger_attach_sink(
         });
 
     // Take the existing logger, if there is one, add a new sink to it, and put it back.
-    let status = match add_sink(dispatch) {
+    match add_sink(dispatch) {
         Ok(_) => Status::Success,
         Err(err) => Status::from(err),
-    };
-
-    status as c_int
+    }
 }
 
 /// Apply the thread-local logger to the program.
@@ -244,3 +317,132 @@ pub extern "C" fn logger_apply() -> c_int {
 
     status as c_int
 }
+
+/// Set the maximum level of log messages for all the sinks of the applied logger. This
+/// can only reduce what is logged, as each sink still only logs messages at its own
+/// level or lower.
+///
+/// This can be used to turn logging down (or off) around noisy operations, and back up
+/// afterwards.
+#[no_mangle]
+pub extern "C" fn logger_set_max_level(level_filter: LevelFilter) {
+    log::set_max_level(level_filter.into());
+}
+
+/// Fetch the contents of the in-memory log buffer, clearing it. Logs are only written
+/// to the buffer if a "buffer" sink has been attached (i.e. with `log_to_buffer`).
+///
+/// The returned string must be deleted with `string_delete`.
+///
+/// # Error Handling
+///
+/// Returns NULL if the logs contain an interior NULL byte. Any invalid UTF-8 in the logs
+/// is replaced with U+FFFD REPLACEMENT CHARACTER.
+#[no_mangle]
+pub extern "C" fn log_fetch_buffer() -> *const c_char {
+    let logs = take_buffer();
+    match string::to_c(&String::from_utf8_lossy(&logs)) {
+        Ok(logs) => logs as *const c_char,
+        Err(e) => {
+            set_error_msg(e.to_string());
+            ptr::null()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::sync::Mutex;
+
+    use expectest::prelude::*;
+    use log::{Level, Log, Record};
+
+    use crate::log::logger::LOGGER;
+    use crate::util::string;
+
+    use super::*;
+
+    static CALLBACK_MESSAGES: Mutex<Vec<(LogLevelFilter, String)>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_message(level: LevelFilter, message: *const c_char) {
+        let message = unsafe { CStr::from_ptr(message) }.to_string_lossy().to_string();
+        CALLBACK_MESSAGES.lock().unwrap().push((level.into(), message));
+    }
+
+    // Builds the thread-local logger into a `Log` without applying it, as the global
+    // logger can only be set once for the whole test run.
+    fn take_logger() -> Box<dyn Log> {
+        let dispatch = LOGGER.with(|logger| logger.borrow_mut().take())
+            .expect("no logger initialized");
+        dispatch.into_log().1
+    }
+
+    fn log_at(logger: &dyn Log, level: Level, message: &str) {
+        logger.log(&Record::builder()
+            .level(level)
+            .target("log_ffi_test")
+            .args(format_args!("{}", message))
+            .build());
+    }
+
+    #[test]
+    fn buffer_sink_records_messages_at_the_configured_level() {
+        logger_init();
+        let spec = CString::new("buffer").unwrap();
+        expect!(logger_attach_sink(spec.as_ptr(), LevelFilter::Info)).to(be_equal_to(0));
+        let logger = take_logger();
+
+        log_at(logger.as_ref(), Level::Error, "buffer test error message");
+        log_at(logger.as_ref(), Level::Info, "buffer test info message");
+        log_at(logger.as_ref(), Level::Debug, "buffer test debug message");
+        log_at(logger.as_ref(), Level::Trace, "buffer test trace message");
+
+        let logs = string::from_c(log_fetch_buffer()).unwrap();
+        expect!(logs.contains("[ERROR][log_ffi_test] buffer test error message")).to(be_true());
+        expect!(logs.contains("[INFO][log_ffi_test] buffer test info message")).to(be_true());
+        expect!(logs.contains("buffer test debug message")).to(be_false());
+        expect!(logs.contains("buffer test trace message")).to(be_false());
+
+        // Fetching the buffer clears it
+        let logs = string::from_c(log_fetch_buffer()).unwrap();
+        expect!(logs.contains("buffer test")).to(be_false());
+    }
+
+    #[test]
+    fn callback_sink_is_called_with_messages_at_the_configured_level() {
+        logger_init();
+        expect!(logger_attach_callback(Some(record_message), LevelFilter::Warn)).to(be_equal_to(0));
+        let logger = take_logger();
+
+        log_at(logger.as_ref(), Level::Warn, "callback test warn message");
+        log_at(logger.as_ref(), Level::Info, "callback test info message");
+
+        let messages: Vec<(LogLevelFilter, String)> = CALLBACK_MESSAGES.lock().unwrap()
+            .iter()
+            .filter(|(_, message)| message.contains("callback test"))
+            .cloned()
+            .collect();
+        expect!(messages).to(be_equal_to(vec![
+            (LogLevelFilter::Warn, "[WARN][log_ffi_test] callback test warn message".to_string())
+        ]));
+    }
+
+    #[test]
+    fn attaching_sinks_returns_an_error_status() {
+        let spec = CString::new("stdout").unwrap();
+        expect!(logger_attach_sink(spec.as_ptr(), LevelFilter::Info)).to(be_equal_to(Status::NoLogger as c_int));
+        expect!(logger_attach_callback(Some(record_message), LevelFilter::Info)).to(be_equal_to(Status::NoLogger as c_int));
+        expect!(logger_apply()).to(be_equal_to(Status::NoLogger as c_int));
+
+        logger_init();
+        let spec = CString::new("syslog").unwrap();
+        expect!(logger_attach_sink(spec.as_ptr(), LevelFilter::Info)).to(be_equal_to(Status::UnknownSinkType as c_int));
+        let spec = CString::new(vec![b'f', b'o', 0xff]).unwrap();
+        expect!(logger_attach_sink(spec.as_ptr(), LevelFilter::Info)).to(be_equal_to(Status::SpecifierNotUtf8 as c_int));
+        let path = std::env::temp_dir().join("no_such_dir_for_log_ffi").join("log.txt");
+        let spec = CString::new(format!("file {}", path.display())).unwrap();
+        expect!(logger_attach_sink(spec.as_ptr(), LevelFilter::Info)).to(be_equal_to(Status::CantOpenSinkToFile as c_int));
+        expect!(logger_attach_callback(None, LevelFilter::Info)).to(be_equal_to(Status::CantConstructSink as c_int));
+    }
+}
@@ -1,5 +1,6 @@
 //! Sets up a log sink to view logs from the FFI.
 
+mod buffer;
 mod ffi;
 mod level_filter;
 mod logger;
@@ -8,6 +9,8 @@ mod status;
 mod target;
 
 pub use crate::log::ffi::{
-    logger_apply, logger_attach_sink, logger_init,
+    log_fetch_buffer, logger_apply, logger_attach_callback, logger_attach_sink, logger_init,
+    logger_set_max_level,
 };
+pub use crate::log::sink::LogCallback;
 pub(crate) use crate::log::target::TARGET;
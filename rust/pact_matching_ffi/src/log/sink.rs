@@ -3,10 +3,14 @@
 // All of this module is `pub(crate)` and should not appear in the C header file
 // or documentation.
 
-use fern::Dispatch;
+use crate::log::buffer::LogBuffer;
+use crate::log::level_filter::LevelFilter;
+use fern::{Dispatch, Output};
+use libc::c_char;
 use std::convert::TryFrom;
+use std::ffi::CString;
 use std::fs::File;
-use std::io::{self, Stderr, Stdout};
+use std::io::{self, Stderr, Stdout, Write};
 use std::ops::Not;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -22,8 +26,20 @@ pub(crate) enum Sink {
 
     /// Write logs to a file.
     File(File),
+
+    /// Write logs to the in-memory buffer, to be fetched with `log_fetch_buffer`.
+    Buffer(LogBuffer),
+
+    /// Pass logs to a function provided by the C caller.
+    Callback(LogCallback),
 }
 
+/// A function to be called with each log message, along with the level of the message.
+///
+/// The message is only valid for the duration of the call, and must be copied if it
+/// is needed afterwards.
+pub type LogCallback = extern "C" fn(level: LevelFilter, message: *const c_char);
+
 impl From<Sink> for Dispatch {
     fn from(sink: Sink) -> Dispatch {
         let dispatch = Dispatch::new();
@@ -32,6 +48,13 @@ impl From<Sink> for Dispatch {
             Sink::Stdout(stdout) => dispatch.chain(stdout),
             Sink::Stderr(stderr) => dispatch.chain(stderr),
             Sink::File(file) => dispatch.chain(file),
+            Sink::Buffer(buffer) => dispatch.chain(Box::new(buffer) as Box<dyn Write + Send>),
+            Sink::Callback(callback) => dispatch.chain(Output::call(move |record| {
+                // Messages with an interior NULL can't be passed to C, so are dropped.
+                if let Ok(message) = CString::new(record.args().to_string()) {
+                    callback(record.level().to_level_filter().into(), message.as_ptr());
+                }
+            })),
         }
     }
 }
@@ -46,6 +69,8 @@ impl<'a> TryFrom<&'a str> for Sink {
             return Ok(Sink::Stdout(io::stdout()));
         } else if s == "stderr" {
             return Ok(Sink::Stderr(io::stderr()));
+        } else if s == "buffer" {
+            return Ok(Sink::Buffer(LogBuffer));
         }
 
         let pat = "file ";
@@ -79,7 +104,7 @@ impl<'a> TryFrom<&'a str> for Sink {
 /// An error arising from attempting to parse a sink specifier string.
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum SinkSpecifierError {
-    #[error("unknown logger sink type (was '{name}', should be \"stdout\"/\"stderr\"/or \"file <file path>\")")]
+    #[error("unknown logger sink type (was '{name}', should be \"stdout\"/\"stderr\"/\"buffer\"/or \"file <file path>\")")]
     UnknownSinkType { name: String },
 
     #[error("missing path in file sink specifier")]
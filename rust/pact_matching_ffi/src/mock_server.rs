@@ -0,0 +1,259 @@
+//! Starts and controls mock servers from `pact_mock_server`, so consumer tests can be run
+//! against a mock provider.
+//!
+//! Mock servers are identified by the port they are running on, which is returned by
+//! `mock_server_start`.
+
+use crate::util::*;
+use crate::{ffi_fn, safe_str};
+use anyhow::{anyhow, Context};
+use libc::{c_char, c_int, EXIT_FAILURE, EXIT_SUCCESS};
+use std::net::SocketAddr;
+
+ffi_fn! {
+    /// Start a mock server for the pact, which is given as a JSON string. The
+    /// mock server listens on the address (i.e. `127.0.0.1:1234`). If the port
+    /// of the address is 0, or the address is NULL, the operating system will
+    /// allocate a port (listening on `127.0.0.1` for a NULL address).
+    ///
+    /// Returns the port the mock server is running on, which is used to identify
+    /// the mock server with the other mock server functions.
+    ///
+    /// # Safety
+    ///
+    /// The `pact_json` and `addr` parameters must be valid UTF-8 encoded strings.
+    ///
+    /// The mock server must be shut down with `mock_server_shutdown`.
+    ///
+    /// # Error Handling
+    ///
+    /// On error (i.e. the pact is not valid, or the mock server could not be
+    /// started), returns -1.
+    fn mock_server_start(pact_json: *const c_char, addr: *const c_char) -> c_int {
+        let pact_json = safe_str!(pact_json);
+        let addr: SocketAddr = if addr.is_null() {
+            SocketAddr::from(([127, 0, 0, 1], 0))
+        } else {
+            let addr = safe_str!(addr);
+            addr.parse().with_context(|| format!("'{}' is not a valid address", addr))?
+        };
+        pact_mock_server::create_mock_server(pact_json, addr)
+            .map_err(|err| anyhow!("could not start the mock server: {}", err))?
+    } {
+        -1
+    }
+}
+
+ffi_fn! {
+    /// Check if the mock server running on the port has matched all the
+    /// requests from the pact, and not received any unexpected requests.
+    ///
+    /// # Error Handling
+    ///
+    /// Returns false if there is no mock server running on the port.
+    fn mock_server_matched(port: c_int) -> bool {
+        pact_mock_server::mock_server_matched(port)
+    } {
+        false
+    }
+}
+
+ffi_fn! {
+    /// Get the mismatches of the mock server running on the port, as a JSON
+    /// array. The array is empty if all the requests matched.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// Returns NULL if there is no mock server running on the port.
+    fn mock_server_mismatches(port: c_int) -> *const c_char {
+        let mismatches = pact_mock_server::mock_server_mismatches(port)
+            .ok_or(anyhow!("there is no mock server running on port {}", port))?;
+        string::to_c(&mismatches)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Write the pact file of the mock server running on the port to the
+    /// directory. This should be called once all the consumer tests have passed.
+    /// If the directory is NULL, the current working directory is used. If
+    /// `overwrite` is true, any existing pact file is replaced, otherwise the
+    /// pact is merged with it.
+    ///
+    /// # Safety
+    ///
+    /// The `directory` parameter must be a valid UTF-8 encoded string, or NULL.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. there is no mock server running on the port, or the file could
+    /// not be written) will be reported with a non-zero return value.
+    fn mock_server_write_pact(port: c_int, directory: *const c_char, overwrite: bool) -> c_int {
+        let directory = if directory.is_null() {
+            None
+        } else {
+            Some(safe_str!(directory).to_string())
+        };
+        pact_mock_server::write_pact_file(port, directory, overwrite)
+            .map_err(|err| match err {
+                pact_mock_server::WritePactFileErr::IOError =>
+                    anyhow!("the pact file for the mock server on port {} could not be written", port),
+                pact_mock_server::WritePactFileErr::NoMockServer =>
+                    anyhow!("there is no mock server running on port {}", port)
+            })?;
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Shut down the mock server running on the port, and free its resources.
+    ///
+    /// # Error Handling
+    ///
+    /// If there is no mock server running on the port, returns a non-zero value.
+    fn mock_server_shutdown(port: c_int) -> c_int {
+        if !pact_mock_server::shutdown_mock_server(port) {
+            return Err(anyhow!("there is no mock server running on port {}", port));
+        }
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::CString;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use expectest::prelude::*;
+
+    use crate::error::{last_error_code, ErrorCode};
+
+    use super::*;
+
+    const PACT: &str = r#"{
+      "consumer": { "name": "ffi_mock_server_consumer" },
+      "provider": { "name": "ffi_mock_server_provider" },
+      "interactions": [
+        {
+          "description": "get a user",
+          "request": { "method": "GET", "path": "/users/1" },
+          "response": { "status": 200, "body": "Fred" }
+        }
+      ],
+      "metadata": { "pactSpecification": { "version": "3.0.0" } }
+    }"#;
+
+    fn start(addr: Option<&str>) -> c_int {
+        let pact = CString::new(PACT).unwrap();
+        let addr = addr.map(|addr| CString::new(addr).unwrap());
+        mock_server_start(pact.as_ptr(), addr.as_ref().map(|addr| addr.as_ptr()).unwrap_or(std::ptr::null()))
+    }
+
+    /// Sends a GET request to the mock server, returning the status line of the response
+    fn get(port: c_int, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port as u16)).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    fn mismatches(port: c_int) -> Option<serde_json::Value> {
+        string::from_c(mock_server_mismatches(port)).map(|json| serde_json::from_str(&json).unwrap())
+    }
+
+    #[test]
+    fn mock_server_lifecycle_with_a_null_address() {
+        let port = start(None);
+        expect!(port).to(be_greater_than(0));
+        expect!(mock_server_matched(port)).to(be_false());
+        expect!(mismatches(port).and_then(|json| json.as_array().map(|m| m.len()))).to(be_some().value(1));
+
+        expect!(get(port, "/users/1")).to(be_equal_to("HTTP/1.1 200 OK"));
+        expect!(mock_server_matched(port)).to(be_true());
+        expect!(mismatches(port)).to(be_some().value(serde_json::json!([])));
+
+        let dir = env::temp_dir().join(format!("pact_ffi_mock_server_{}", port));
+        let c_dir = CString::new(dir.to_string_lossy().to_string()).unwrap();
+        let result = mock_server_write_pact(port, c_dir.as_ptr(), true);
+        let pact_file = dir.join("ffi_mock_server_consumer-ffi_mock_server_provider.json");
+        let pact_written = pact_file.exists();
+        fs::remove_dir_all(&dir).unwrap_or(());
+        expect!(result).to(be_equal_to(EXIT_SUCCESS));
+        expect!(pact_written).to(be_true());
+
+        expect!(mock_server_shutdown(port)).to(be_equal_to(EXIT_SUCCESS));
+        expect!(mock_server_matched(port)).to(be_false());
+        expect!(mismatches(port)).to(be_none());
+    }
+
+    #[test]
+    fn mock_server_lifecycle_with_an_explicit_address() {
+        let port = start(Some("127.0.0.1:0"));
+        expect!(port).to(be_greater_than(0));
+
+        expect!(get(port, "/users/2")).to(be_equal_to("HTTP/1.1 500 Internal Server Error"));
+        expect!(mock_server_matched(port)).to(be_false());
+        let mismatches = mismatches(port).unwrap_or_default();
+        let types = mismatches.as_array().unwrap().iter()
+            .map(|mismatch| mismatch["type"].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        expect!(types.contains(&"request-not-found".to_string())).to(be_true());
+        expect!(types.contains(&"missing-request".to_string())).to(be_true());
+
+        expect!(mock_server_shutdown(port)).to(be_equal_to(EXIT_SUCCESS));
+    }
+
+    #[test]
+    fn mock_server_start_returns_minus_one_on_errors() {
+        let pact = CString::new(PACT).unwrap();
+        expect!(mock_server_start(std::ptr::null(), std::ptr::null())).to(be_equal_to(-1));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+
+        let invalid_utf8 = CString::new(vec![b'{', 0xFF, b'}']).unwrap();
+        expect!(mock_server_start(invalid_utf8.as_ptr(), std::ptr::null())).to(be_equal_to(-1));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::InvalidUtf8));
+
+        let address = CString::new("not an address").unwrap();
+        expect!(mock_server_start(pact.as_ptr(), address.as_ptr())).to(be_equal_to(-1));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Other));
+
+        let invalid_pact = CString::new("{ not json").unwrap();
+        expect!(mock_server_start(invalid_pact.as_ptr(), std::ptr::null())).to(be_equal_to(-1));
+    }
+
+    #[test]
+    fn mock_server_functions_fail_if_there_is_no_mock_server() {
+        let port = 1;
+        expect!(mock_server_matched(port)).to(be_false());
+        expect!(mismatches(port)).to(be_none());
+        expect!(mock_server_write_pact(port, std::ptr::null(), false)).to(be_equal_to(EXIT_FAILURE));
+        expect!(mock_server_shutdown(port)).to(be_equal_to(EXIT_FAILURE));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Other));
+    }
+
+    #[test]
+    fn mock_server_write_pact_fails_if_the_file_can_not_be_written() {
+        let port = start(None);
+        let file = env::temp_dir().join(format!("pact_ffi_not_a_dir_{}", port));
+        fs::write(&file, "").unwrap();
+        let c_file = CString::new(file.to_string_lossy().to_string()).unwrap();
+        let result = mock_server_write_pact(port, c_file.as_ptr(), true);
+        fs::remove_file(&file).unwrap_or(());
+        mock_server_shutdown(port);
+
+        expect!(result).to(be_equal_to(EXIT_FAILURE));
+    }
+}
@@ -9,8 +9,11 @@ use crate::util::*;
 use crate::{as_mut, as_ref, cstr, ffi_fn, safe_str};
 use anyhow::{anyhow, Context};
 use libc::{c_char, c_int, c_uint, EXIT_FAILURE, EXIT_SUCCESS};
+use pact_matching::models::generators::{self, GeneratorTestMode};
+use pact_matching::models::matchingrules;
 use pact_models::{content_types::ContentType, OptionalBody};
 use serde_json::from_str as from_json_str;
+use serde_json::json;
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 use std::ops::Drop;
@@ -168,6 +171,118 @@ ffi_fn! {
     }
 }
 
+ffi_fn! {
+    /// Set the contents of a `Message`, with the given content type. The
+    /// content type is also stored in the `contentType` metadata.
+    ///
+    /// # Safety
+    ///
+    /// The `contents` and `content_type` parameters must be valid UTF-8
+    /// encoded strings.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. an invalid content type) will be reported with a
+    /// non-zero return value.
+    fn message_set_contents(
+        message: *mut Message,
+        contents: *const c_char,
+        content_type: *const c_char
+    ) -> c_int {
+        let message = as_mut!(message);
+        let contents = cstr!(contents).to_bytes().to_owned();
+        let content_type = ContentType::parse(safe_str!(content_type))
+            .map_err(|s| anyhow!("invalid content type '{}'", s))?;
+
+        message.metadata.insert(String::from("contentType"), content_type.to_string());
+        message.contents = OptionalBody::Present(contents.into(), Some(content_type));
+
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Get the contents of a `Message` with any generators applied to them.
+    /// This is what a consumer test should receive in place of the contents,
+    /// so that values like IDs and timestamps differ for each run.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// If the message is NULL, or the contents are missing, returns NULL.
+    fn message_reify(message: *const Message) -> *const c_char {
+        let message = as_ref!(message);
+        let message = pact_matching::generate_message(message, &GeneratorTestMode::Consumer, &HashMap::new());
+        if !message.contents.is_present() {
+            return Err(anyhow!("the message has no contents"));
+        }
+        string::to_c(message.contents.str_value())? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Matching Rules and Generators
+ */
+
+ffi_fn! {
+    /// Set the matching rules of a `Message`, replacing any existing matching
+    /// rules. The matching rules are given as JSON in the form of the
+    /// `matchingRules` attribute of a V3 message (i.e. `{"body": {"$.id":
+    /// {"matchers": [{"match": "integer"}]}}}`).
+    ///
+    /// # Safety
+    ///
+    /// The `matching_rules` parameter must be a valid UTF-8 encoded string.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. invalid JSON) will be reported with a non-zero return value.
+    fn message_set_matching_rules(message: *mut Message, matching_rules: *const c_char) -> c_int {
+        let message = as_mut!(message);
+        let rules: JsonValue = from_json_str(safe_str!(matching_rules))
+            .context("error parsing matching_rules as JSON")?;
+
+        message.matching_rules = matchingrules::matchers_from_json(&json!({ "matchingRules": rules }), &None);
+
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Set the generators of a `Message`, replacing any existing generators.
+    /// The generators are given as JSON in the form of the `generators`
+    /// attribute of a V3 message (i.e. `{"body": {"$.id": {"type":
+    /// "RandomInt"}}}`).
+    ///
+    /// # Safety
+    ///
+    /// The `generators` parameter must be a valid UTF-8 encoded string.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. invalid JSON) will be reported with a non-zero return value.
+    fn message_set_generators(message: *mut Message, generators: *const c_char) -> c_int {
+        let message = as_mut!(message);
+        let value: JsonValue = from_json_str(safe_str!(generators))
+            .context("error parsing generators as JSON")?;
+
+        message.generators = generators::generators_from_json(&json!({ "generators": value }));
+
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
 /*-----------------------------------------------------------------------------------------------
  * ## Description
  */
@@ -535,7 +650,7 @@ impl Drop for MessageMetadataPair {
  *---------------------------------------------------------------------------------------------*/
 
 /// Result from an attempt to insert into a HashMap
-enum HashMapInsertStatus {
+pub(crate) enum HashMapInsertStatus {
     /// The value was inserted, and the key was unset
     SuccessNew = 0,
     /// The value was inserted, and the key was previously set
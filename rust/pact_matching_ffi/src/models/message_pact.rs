@@ -3,12 +3,14 @@
 use crate::util::*;
 use crate::{as_mut, as_ref, ffi_fn, safe_str};
 use anyhow::{anyhow, Context};
-use libc::c_char;
+use libc::{c_char, c_int, EXIT_FAILURE, EXIT_SUCCESS};
 use std::iter::{self, Iterator};
+use std::path::PathBuf;
 
 // Necessary to make 'cbindgen' generate an opaque struct on the C side.
 use crate::models::message::Message;
 pub use pact_matching::models::message_pact::MessagePact;
+use pact_matching::models::{self as pm, Pact};
 use pact_models::{Consumer, Provider};
 
 ffi_fn! {
@@ -45,6 +47,32 @@ ffi_fn! {
     }
 }
 
+ffi_fn! {
+    /// Construct a new, empty `MessagePact` between the consumer and provider.
+    /// Messages can then be added with `message_pact_add_message`.
+    ///
+    /// # Safety
+    ///
+    /// The `consumer_name` and `provider_name` parameters must both be valid
+    /// UTF-8 encoded strings.
+    ///
+    /// The returned message pact must be deleted with `message_pact_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On error, this function will return a null pointer.
+    fn message_pact_new(consumer_name: *const c_char, provider_name: *const c_char) -> *mut MessagePact {
+        let message_pact = MessagePact {
+            consumer: Consumer { name: safe_str!(consumer_name).to_string() },
+            provider: Provider { name: safe_str!(provider_name).to_string() },
+            .. MessagePact::default()
+        };
+        ptr::raw_to(message_pact)
+    } {
+        ptr::null_mut_to::<MessagePact>()
+    }
+}
+
 ffi_fn! {
     /// Delete the `MessagePact` being pointed to.
     fn message_pact_delete(message_pact: *mut MessagePact) {
@@ -96,6 +124,99 @@ ffi_fn! {
     }
 }
 
+ffi_fn! {
+    /// Set the name of the consumer of the message pact.
+    ///
+    /// # Safety
+    ///
+    /// The `name` parameter must be a valid UTF-8 encoded string.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn message_pact_set_consumer_name(message_pact: *mut MessagePact, name: *const c_char) -> c_int {
+        let message_pact = as_mut!(message_pact);
+        message_pact.consumer.name = safe_str!(name).to_string();
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Set the name of the provider of the message pact.
+    ///
+    /// # Safety
+    ///
+    /// The `name` parameter must be a valid UTF-8 encoded string.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn message_pact_set_provider_name(message_pact: *mut MessagePact, name: *const c_char) -> c_int {
+        let message_pact = as_mut!(message_pact);
+        message_pact.provider.name = safe_str!(name).to_string();
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Add a copy of the message to the message pact. The message can be
+    /// built up with `message_new`, `message_set_contents`,
+    /// `message_insert_metadata`, `message_set_matching_rules` and
+    /// `message_set_generators`.
+    ///
+    /// # Safety
+    ///
+    /// Since the message is copied, it must still be deleted with
+    /// `message_delete`, and later changes to it do not affect the pact.
+    ///
+    /// # Error Handling
+    ///
+    /// This function will only fail if it is passed a NULL pointer, which
+    /// will be reported with a non-zero return value.
+    fn message_pact_add_message(message_pact: *mut MessagePact, message: *const Message) -> c_int {
+        let message_pact = as_mut!(message_pact);
+        let message = as_ref!(message);
+        message_pact.messages.push(message.clone());
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Write the message pact out to a pact file in the directory, named
+    /// after the consumer and provider. If the directory is NULL, the current
+    /// working directory is used. If `overwrite` is true, any existing pact
+    /// file is replaced, otherwise the pact is merged with it.
+    ///
+    /// # Safety
+    ///
+    /// The `directory` parameter must be a valid UTF-8 encoded string, or NULL.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. the file could not be written, or could not be merged with
+    /// the existing pact file) will be reported with a non-zero return value.
+    fn message_pact_write(message_pact: *const MessagePact, directory: *const c_char, overwrite: bool) -> c_int {
+        let message_pact = as_ref!(message_pact);
+        let directory = if directory.is_null() {
+            PathBuf::new()
+        } else {
+            PathBuf::from(safe_str!(directory))
+        };
+        let path = directory.join(message_pact.default_file_name());
+        pm::write_pact(message_pact.boxed(), &path, message_pact.specification_version.clone(), overwrite, pm::MergeStrategy::default())
+            .map_err(|e| anyhow!("{}", e))?;
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
 ffi_fn! {
     /// Get an iterator over the messages of a message pact.
     ///
@@ -1,8 +1,10 @@
-//! Represents messages in `pact_matching`.
+//! Represents pacts and messages in `pact_matching`.
 
 pub mod consumer;
 pub mod message;
 pub mod message_pact;
+pub mod pact;
 pub mod pact_specification;
 pub mod provider;
 pub mod provider_state;
+pub mod sync_http;
@@ -0,0 +1,540 @@
+//! FFI wrapper for loading any type of pact (request/response, message or V4) from
+//! pact_matching, and iterating over its interactions.
+
+use crate::util::*;
+use crate::{as_mut, as_ref, ffi_fn, safe_str};
+use anyhow::{anyhow, Context};
+use libc::{c_char, c_int};
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+
+use crate::models::message::Message;
+use crate::models::pact_specification::PactSpecification;
+use crate::models::sync_http::SynchronousHttp;
+use pact_matching::models::{self as pm, Interaction};
+
+/// A pact loaded from a file or JSON, which can be a request/response, message or V4 pact.
+#[allow(missing_copy_implementations)]
+#[allow(missing_debug_implementations)]
+pub struct Pact {
+    // The pact and interactions are only read through the FFI functions, so
+    // it is safe to use them across the `catch_unwind` boundary.
+    inner: AssertUnwindSafe<Box<dyn pm::Pact>>,
+    interactions: Vec<PactInteraction>,
+}
+
+impl Pact {
+    fn new(inner: Box<dyn pm::Pact>) -> Pact {
+        let spec_version = inner.specification_version();
+        let interactions = inner
+            .interactions()
+            .iter()
+            .map(|interaction| PactInteraction {
+                inner: AssertUnwindSafe(interaction.boxed()),
+                spec_version: spec_version.clone(),
+            })
+            .collect();
+        Pact { inner: AssertUnwindSafe(inner), interactions }
+    }
+}
+
+/// An interaction from a pact.
+#[allow(missing_copy_implementations)]
+#[allow(missing_debug_implementations)]
+pub struct PactInteraction {
+    inner: AssertUnwindSafe<Box<dyn Interaction + Send>>,
+    spec_version: pact_models::PactSpecification,
+}
+
+impl PactInteraction {
+    /// Returns a copy of the interaction
+    pub(crate) fn boxed(&self) -> Box<dyn Interaction + Send> {
+        self.inner.boxed()
+    }
+
+    /// Returns the specification version of the pact the interaction is from
+    pub(crate) fn spec_version(&self) -> &pact_models::PactSpecification {
+        &self.spec_version
+    }
+}
+
+/// The type of an interaction.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InteractionType {
+    /// The type could not be determined (i.e. a NULL pointer was passed in)
+    Unknown,
+    /// An HTTP request/response interaction
+    Http,
+    /// A message interaction
+    Message,
+}
+
+ffi_fn! {
+    /// Construct a new `Pact` from the JSON string. The pact can be any type
+    /// of pact (request/response, message or V4).
+    /// The provided file name is used when generating error messages.
+    ///
+    /// # Safety
+    ///
+    /// The `file_name` and `json_str` parameters must both be valid UTF-8
+    /// encoded strings.
+    ///
+    /// The returned pact must be deleted with `pact_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On error, this function will return a null pointer.
+    fn pact_from_json(file_name: *const c_char, json_str: *const c_char) -> *mut Pact {
+        let file_name = safe_str!(file_name);
+        let json_str = safe_str!(json_str);
+
+        let json_value: serde_json::Value =
+            serde_json::from_str(json_str)
+            .context("error parsing json_str as JSON")?;
+
+        let pact = pm::load_pact_from_json(file_name, &json_value)
+            .map_err(|e| anyhow!("{}", e))?;
+
+        ptr::raw_to(Pact::new(pact))
+    } {
+        ptr::null_mut_to::<Pact>()
+    }
+}
+
+ffi_fn! {
+    /// Load a `Pact` from the pact file at the given path. The pact can be any
+    /// type of pact (request/response, message or V4).
+    ///
+    /// # Safety
+    ///
+    /// The `path` parameter must be a valid UTF-8 encoded string.
+    ///
+    /// The returned pact must be deleted with `pact_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On error (i.e. the file does not exist or is not a valid pact), this
+    /// function will return a null pointer.
+    fn pact_from_file(path: *const c_char) -> *mut Pact {
+        let path = safe_str!(path);
+        let pact = pm::read_pact(Path::new(path)).map_err(|e| anyhow!("{}", e))?;
+        ptr::raw_to(Pact::new(pact))
+    } {
+        ptr::null_mut_to::<Pact>()
+    }
+}
+
+ffi_fn! {
+    /// Delete the `Pact` being pointed to.
+    fn pact_delete(pact: *mut Pact) {
+        ptr::drop_raw(pact);
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the name of the consumer of the pact.
+    ///
+    /// The copy must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// This function will fail if it is passed a NULL pointer,
+    /// or the Rust string contains an embedded NULL byte.
+    /// In the case of error, a NULL pointer will be returned.
+    fn pact_get_consumer_name(pact: *const Pact) -> *const c_char {
+        let pact = as_ref!(pact);
+        string::to_c(&pact.inner.consumer().name)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the name of the provider of the pact.
+    ///
+    /// The copy must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// This function will fail if it is passed a NULL pointer,
+    /// or the Rust string contains an embedded NULL byte.
+    /// In the case of error, a NULL pointer will be returned.
+    fn pact_get_provider_name(pact: *const Pact) -> *const c_char {
+        let pact = as_ref!(pact);
+        string::to_c(&pact.inner.provider().name)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get the specification version of the pact.
+    ///
+    /// # Error Handling
+    ///
+    /// If passed a NULL pointer, this function will return `Unknown`.
+    fn pact_get_specification_version(pact: *const Pact) -> PactSpecification {
+        let pact = as_ref!(pact);
+        pact.inner.specification_version().into()
+    } {
+        PactSpecification::Unknown
+    }
+}
+
+ffi_fn! {
+    /// Get the number of interactions in the pact.
+    ///
+    /// # Error Handling
+    ///
+    /// If passed a NULL pointer, this function will return -1.
+    fn pact_get_interaction_count(pact: *const Pact) -> c_int {
+        let pact = as_ref!(pact);
+        pact.interactions.len() as c_int
+    } {
+        -1
+    }
+}
+
+ffi_fn! {
+    /// Get an iterator over the interactions of a pact.
+    ///
+    /// # Safety
+    ///
+    /// This iterator carries a pointer to the pact, and must not outlive the
+    /// pact.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer.
+    fn pact_get_interaction_iter(pact: *mut Pact) -> *mut PactInteractionIterator {
+        let pact = as_mut!(pact);
+        let iter = PactInteractionIterator { current: 0, pact };
+        ptr::raw_to(iter)
+    } {
+        ptr::null_mut_to::<PactInteractionIterator>()
+    }
+}
+
+ffi_fn! {
+    /// Get the next interaction from the pact.
+    ///
+    /// # Safety
+    ///
+    /// The returned interaction is owned by the pact, and must not outlive it.
+    ///
+    /// # Error Handling
+    ///
+    /// This function will return a NULL pointer if passed a NULL pointer, or
+    /// if there are no more interactions.
+    fn pact_interaction_iter_next(iter: *mut PactInteractionIterator) -> *const PactInteraction {
+        let iter = as_mut!(iter);
+        let pact = as_ref!(iter.pact);
+        let index = iter.next();
+        let interaction = pact
+            .interactions
+            .get(index)
+            .ok_or(anyhow::anyhow!("iter past the end of interactions"))?;
+        interaction as *const PactInteraction
+    } {
+        ptr::null_to::<PactInteraction>()
+    }
+}
+
+ffi_fn! {
+    /// Delete the iterator.
+    fn pact_interaction_iter_delete(iter: *mut PactInteractionIterator) {
+        ptr::drop_raw(iter);
+    }
+}
+
+ffi_fn! {
+    /// Get the type of the interaction, so it can be determined if it is an
+    /// HTTP or a message interaction.
+    ///
+    /// # Error Handling
+    ///
+    /// If passed a NULL pointer, this function will return `Unknown`.
+    fn pact_interaction_get_type(interaction: *const PactInteraction) -> InteractionType {
+        let interaction = as_ref!(interaction);
+        if interaction.inner.is_message() {
+            InteractionType::Message
+        } else {
+            InteractionType::Http
+        }
+    } {
+        InteractionType::Unknown
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the description of the interaction.
+    ///
+    /// The copy must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// This function will fail if it is passed a NULL pointer,
+    /// or the Rust string contains an embedded NULL byte.
+    /// In the case of error, a NULL pointer will be returned.
+    fn pact_interaction_get_description(interaction: *const PactInteraction) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        string::to_c(&interaction.inner.description())? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get the JSON form of the interaction, in the format of the
+    /// specification version of the pact it is from.
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer.
+    fn pact_interaction_to_json(interaction: *const PactInteraction) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        let inner = &interaction.inner;
+        let json = if inner.is_v4() {
+            inner.as_v4().map(|v4| v4.to_json())
+        } else if inner.is_request_response() {
+            inner.as_request_response().map(|http| http.to_json(&interaction.spec_version))
+        } else {
+            inner.as_message().map(|message| message.to_json(&interaction.spec_version))
+        }.ok_or(anyhow!("interaction can not be converted to JSON"))?;
+        string::to_c(&json.to_string())? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of a message interaction as a `Message`, so it can be
+    /// used with the message functions.
+    ///
+    /// # Safety
+    ///
+    /// The returned message must be deleted with `message_delete`. Since it
+    /// is a copy, it may safely outlive the pact.
+    ///
+    /// # Error Handling
+    ///
+    /// This function will return a NULL pointer if passed a NULL pointer, or
+    /// if the interaction is not a message interaction.
+    fn pact_interaction_as_message(interaction: *const PactInteraction) -> *mut Message {
+        let interaction = as_ref!(interaction);
+        let message = interaction.inner
+            .as_message()
+            .ok_or(anyhow!("interaction is not a message interaction"))?;
+        ptr::raw_to(message)
+    } {
+        ptr::null_mut_to::<Message>()
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of an HTTP interaction as a `SynchronousHttp`, so it can be
+    /// used with the HTTP interaction functions. Request/response interactions
+    /// from V1 to V3 pacts are converted to the V4 form.
+    ///
+    /// # Safety
+    ///
+    /// The returned interaction must be deleted with `sync_http_delete`. Since
+    /// it is a copy, it may safely outlive the pact.
+    ///
+    /// # Error Handling
+    ///
+    /// This function will return a NULL pointer if passed a NULL pointer, or
+    /// if the interaction is not an HTTP interaction.
+    fn pact_interaction_as_synchronous_http(interaction: *const PactInteraction) -> *mut SynchronousHttp {
+        let interaction = as_ref!(interaction);
+        let http = interaction.inner
+            .as_v4_http()
+            .ok_or(anyhow!("interaction is not an HTTP interaction"))?;
+        ptr::raw_to(http)
+    } {
+        ptr::null_mut_to::<SynchronousHttp>()
+    }
+}
+
+/// An iterator over the interactions in a pact.
+#[allow(missing_copy_implementations)]
+#[allow(missing_debug_implementations)]
+pub struct PactInteractionIterator {
+    current: usize,
+    pact: *const Pact,
+}
+
+impl PactInteractionIterator {
+    fn next(&mut self) -> usize {
+        let idx = self.current;
+        self.current += 1;
+        idx
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::CString;
+    use std::fs;
+
+    use expectest::prelude::*;
+
+    use crate::error::{last_error_code, ErrorCode};
+    use crate::models::message::{message_delete, message_get_description};
+    use crate::models::sync_http::{sync_http_delete, sync_http_get_request_path};
+
+    use super::*;
+
+    const HTTP_PACT: &str = r#"{
+      "consumer": { "name": "http_consumer" },
+      "provider": { "name": "http_provider" },
+      "interactions": [
+        {
+          "description": "get a user",
+          "request": { "method": "GET", "path": "/users/1" },
+          "response": { "status": 200 }
+        },
+        {
+          "description": "create a user",
+          "request": { "method": "POST", "path": "/users" },
+          "response": { "status": 201 }
+        }
+      ],
+      "metadata": { "pactSpecification": { "version": "3.0.0" } }
+    }"#;
+
+    const MESSAGE_PACT: &str = r#"{
+      "consumer": { "name": "message_consumer" },
+      "provider": { "name": "message_provider" },
+      "messages": [
+        { "description": "a user created event", "contents": { "id": 1 } }
+      ],
+      "metadata": { "pactSpecification": { "version": "3.0.0" } }
+    }"#;
+
+    fn pact_from_str(json: &str) -> *mut Pact {
+        let file_name = CString::new("test.json").unwrap();
+        let json = CString::new(json).unwrap();
+        pact_from_json(file_name.as_ptr(), json.as_ptr())
+    }
+
+    #[test]
+    fn pact_functions_return_errors_for_null_pointers() {
+        let json = CString::new(HTTP_PACT).unwrap();
+        expect!(pact_from_json(std::ptr::null(), json.as_ptr()).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+        let file_name = CString::new("test.json").unwrap();
+        expect!(pact_from_json(file_name.as_ptr(), std::ptr::null()).is_null()).to(be_true());
+        expect!(pact_from_file(std::ptr::null()).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+
+        expect!(string::from_c(pact_get_consumer_name(std::ptr::null()))).to(be_none());
+        expect!(string::from_c(pact_get_provider_name(std::ptr::null()))).to(be_none());
+        expect!(pact_get_specification_version(std::ptr::null())).to(be_equal_to(PactSpecification::Unknown));
+        expect!(pact_get_interaction_count(std::ptr::null())).to(be_equal_to(-1));
+        expect!(pact_get_interaction_iter(std::ptr::null_mut()).is_null()).to(be_true());
+        expect!(pact_interaction_iter_next(std::ptr::null_mut()).is_null()).to(be_true());
+        expect!(pact_interaction_get_type(std::ptr::null())).to(be_equal_to(InteractionType::Unknown));
+        expect!(string::from_c(pact_interaction_get_description(std::ptr::null()))).to(be_none());
+        expect!(string::from_c(pact_interaction_to_json(std::ptr::null()))).to(be_none());
+        expect!(pact_interaction_as_message(std::ptr::null()).is_null()).to(be_true());
+        expect!(pact_interaction_as_synchronous_http(std::ptr::null()).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+    }
+
+    #[test]
+    fn pact_from_json_fails_with_invalid_utf8_or_json() {
+        let file_name = CString::new("test.json").unwrap();
+        let invalid_utf8 = CString::new(vec![b'{', 0xFF, 0xFE, b'}']).unwrap();
+        expect!(pact_from_json(file_name.as_ptr(), invalid_utf8.as_ptr()).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::InvalidUtf8));
+        expect!(pact_from_json(invalid_utf8.as_ptr(), file_name.as_ptr()).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::InvalidUtf8));
+        expect!(pact_from_file(invalid_utf8.as_ptr()).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::InvalidUtf8));
+
+        expect!(pact_from_str("{ not json").is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::JsonParse));
+        expect!(pact_from_str("[]").is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Other));
+    }
+
+    #[test]
+    fn iterates_over_and_frees_http_interactions() {
+        let pact = pact_from_str(HTTP_PACT);
+        expect!(pact.is_null()).to(be_false());
+        expect!(string::from_c(pact_get_consumer_name(pact))).to(be_some().value("http_consumer"));
+        expect!(string::from_c(pact_get_provider_name(pact))).to(be_some().value("http_provider"));
+        expect!(pact_get_specification_version(pact)).to(be_equal_to(PactSpecification::V3));
+        expect!(pact_get_interaction_count(pact)).to(be_equal_to(2));
+
+        let iter = pact_get_interaction_iter(pact);
+        let mut paths = vec![];
+        loop {
+            let interaction = pact_interaction_iter_next(iter);
+            if interaction.is_null() {
+                break;
+            }
+            expect!(pact_interaction_get_type(interaction)).to(be_equal_to(InteractionType::Http));
+            expect!(string::from_c(pact_interaction_get_description(interaction))).to(be_some());
+            expect!(string::from_c(pact_interaction_to_json(interaction))).to(be_some());
+            expect!(pact_interaction_as_message(interaction).is_null()).to(be_true());
+
+            let http = pact_interaction_as_synchronous_http(interaction);
+            expect!(http.is_null()).to(be_false());
+            paths.push(string::from_c(sync_http_get_request_path(http)).unwrap_or_default());
+            sync_http_delete(http);
+        }
+        expect!(pact_interaction_iter_next(iter).is_null()).to(be_true());
+        pact_interaction_iter_delete(iter);
+        pact_delete(pact);
+
+        paths.sort();
+        expect!(paths).to(be_equal_to(vec!["/users".to_string(), "/users/1".to_string()]));
+    }
+
+    #[test]
+    fn iterates_over_and_frees_message_interactions() {
+        let pact = pact_from_str(MESSAGE_PACT);
+        expect!(pact.is_null()).to(be_false());
+        expect!(pact_get_interaction_count(pact)).to(be_equal_to(1));
+
+        let iter = pact_get_interaction_iter(pact);
+        let interaction = pact_interaction_iter_next(iter);
+        expect!(pact_interaction_get_type(interaction)).to(be_equal_to(InteractionType::Message));
+        expect!(string::from_c(pact_interaction_get_description(interaction)))
+            .to(be_some().value("a user created event"));
+        expect!(pact_interaction_as_synchronous_http(interaction).is_null()).to(be_true());
+
+        let message = pact_interaction_as_message(interaction);
+        expect!(message.is_null()).to(be_false());
+        expect!(string::from_c(message_get_description(message))).to(be_some().value("a user created event"));
+        message_delete(message);
+
+        expect!(pact_interaction_iter_next(iter).is_null()).to(be_true());
+        pact_interaction_iter_delete(iter);
+        pact_delete(pact);
+    }
+
+    #[test]
+    fn pact_from_file_loads_the_pact_file() {
+        let path = env::temp_dir().join(format!("pact_ffi_test_{}.json", std::process::id()));
+        fs::write(&path, MESSAGE_PACT).unwrap();
+        let c_path = CString::new(path.to_string_lossy().to_string()).unwrap();
+        let pact = pact_from_file(c_path.as_ptr());
+        fs::remove_file(&path).unwrap_or(());
+
+        expect!(pact.is_null()).to(be_false());
+        expect!(string::from_c(pact_get_consumer_name(pact))).to(be_some().value("message_consumer"));
+        pact_delete(pact);
+
+        let missing = CString::new("/does/not/exist.json").unwrap();
+        expect!(pact_from_file(missing.as_ptr()).is_null()).to(be_true());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::Other));
+    }
+}
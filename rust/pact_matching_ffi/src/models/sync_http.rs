@@ -0,0 +1,1126 @@
+//! The Pact `SynchronousHttp` type, which is a V4 HTTP request/response interaction.
+
+/*===============================================================================================
+ * # Imports
+ *---------------------------------------------------------------------------------------------*/
+
+use crate::error::NullPointerError;
+use crate::models::message::HashMapInsertStatus;
+use crate::util::*;
+use crate::{as_mut, as_ref, cstr, ffi_fn, safe_str};
+use anyhow::{anyhow, Context};
+use libc::{c_char, c_int, c_uchar, c_uint, c_ushort, size_t, EXIT_FAILURE, EXIT_SUCCESS};
+use pact_matching::models::v4::V4Interaction;
+use pact_models::{content_types::ContentType, OptionalBody};
+use std::collections::HashMap;
+use std::ops::Drop;
+
+/*===============================================================================================
+ * # Re-Exports
+ *---------------------------------------------------------------------------------------------*/
+
+// Necessary to make 'cbindgen' generate an opaque struct on the C side.
+pub use pact_matching::models::v4::SynchronousHttp;
+
+/*===============================================================================================
+ * # SynchronousHttp
+ *---------------------------------------------------------------------------------------------*/
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Constructors / Destructor
+ */
+
+ffi_fn! {
+    /// Get a mutable pointer to a newly-created default HTTP interaction on the heap.
+    ///
+    /// # Safety
+    ///
+    /// This function is safe.
+    ///
+    /// # Error Handling
+    ///
+    /// Returns NULL on error.
+    fn sync_http_new() -> *mut SynchronousHttp {
+        let interaction = SynchronousHttp::default();
+        ptr::raw_to(interaction)
+    } {
+        ptr::null_mut_to::<SynchronousHttp>()
+    }
+}
+
+ffi_fn! {
+    /// Destroy the `SynchronousHttp` interaction being pointed to.
+    fn sync_http_delete(interaction: *mut SynchronousHttp) {
+        ptr::drop_raw(interaction);
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Description
+ */
+
+ffi_fn! {
+    /// Get a copy of the description.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// Since it is a copy, the returned string may safely outlive
+    /// the `SynchronousHttp` interaction.
+    ///
+    /// # Errors
+    ///
+    /// On failure, this function will return a NULL pointer.
+    ///
+    /// This function may fail if the Rust string contains embedded
+    /// null ('\0') bytes.
+    fn sync_http_get_description(interaction: *const SynchronousHttp) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        string::to_c(&interaction.description)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Write the `description` field on the `SynchronousHttp` interaction.
+    ///
+    /// # Safety
+    ///
+    /// `description` must contain valid UTF-8.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn sync_http_set_description(interaction: *mut SynchronousHttp, description: *const c_char) -> c_int {
+        let interaction = as_mut!(interaction);
+        let description = safe_str!(description);
+        interaction.description.clear();
+        interaction.description.push_str(description);
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Comments
+ */
+
+ffi_fn! {
+    /// Add a text comment to the `SynchronousHttp` interaction.
+    ///
+    /// # Safety
+    ///
+    /// `comment` must contain valid UTF-8.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn sync_http_add_text_comment(interaction: *mut SynchronousHttp, comment: *const c_char) -> c_int {
+        let interaction = as_mut!(interaction);
+        let comment = safe_str!(comment);
+        interaction.add_text_comment(comment);
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Set the name of the test that generated the `SynchronousHttp` interaction.
+    ///
+    /// # Safety
+    ///
+    /// `name` must contain valid UTF-8.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn sync_http_set_test_name(interaction: *mut SynchronousHttp, name: *const c_char) -> c_int {
+        let interaction = as_mut!(interaction);
+        let name = safe_str!(name);
+        interaction.set_test_name(name);
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Request method and path
+ */
+
+ffi_fn! {
+    /// Get a copy of the method of the request.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer.
+    fn sync_http_get_request_method(interaction: *const SynchronousHttp) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        string::to_c(&interaction.request.method)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Set the method of the request. The method is upper-cased.
+    ///
+    /// # Safety
+    ///
+    /// `method` must contain valid UTF-8.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn sync_http_set_request_method(interaction: *mut SynchronousHttp, method: *const c_char) -> c_int {
+        let interaction = as_mut!(interaction);
+        interaction.request.method = safe_str!(method).to_uppercase();
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the path of the request.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer.
+    fn sync_http_get_request_path(interaction: *const SynchronousHttp) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        string::to_c(&interaction.request.path)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Set the path of the request.
+    ///
+    /// # Safety
+    ///
+    /// `path` must contain valid UTF-8.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn sync_http_set_request_path(interaction: *mut SynchronousHttp, path: *const c_char) -> c_int {
+        let interaction = as_mut!(interaction);
+        interaction.request.path = safe_str!(path).to_string();
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Request query parameters
+ */
+
+ffi_fn! {
+    /// Get a copy of the value at `index` of the query parameter `name`. Query parameters
+    /// can have more than one value.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer. This includes when the request
+    /// does not have the query parameter, or the index is out of bounds.
+    fn sync_http_find_request_query_param(
+        interaction: *const SynchronousHttp,
+        name: *const c_char,
+        index: c_uint
+    ) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        let name = safe_str!(name);
+        let value = interaction.request.query.as_ref()
+            .and_then(|query| query.get(name))
+            .and_then(|values| values.get(index as usize))
+            .ok_or(anyhow!("no query parameter value for the name and index"))?;
+        string::to_c(value)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Add the value to the query parameter `name` of the request. If the query parameter
+    /// already exists, the value is added to its values.
+    ///
+    /// # Safety
+    ///
+    /// This function returns an enum indicating the result;
+    /// see the comments on HashMapInsertStatus for details.
+    ///
+    /// # Error Handling
+    ///
+    /// This function may fail if the provided `name` or `value` strings
+    /// contain invalid UTF-8.
+    fn sync_http_insert_request_query_param(
+        interaction: *mut SynchronousHttp,
+        name: *const c_char,
+        value: *const c_char
+    ) -> c_int {
+        let interaction = as_mut!(interaction);
+        let name = safe_str!(name);
+        let value = safe_str!(value);
+        let query = interaction.request.query.get_or_insert_with(HashMap::new);
+        match query.get_mut(name) {
+            Some(values) => {
+                values.push(value.to_string());
+                HashMapInsertStatus::SuccessOverwrite as c_int
+            },
+            None => {
+                query.insert(name.to_string(), vec![value.to_string()]);
+                HashMapInsertStatus::SuccessNew as c_int
+            }
+        }
+    } {
+        HashMapInsertStatus::Error as c_int
+    }
+}
+
+ffi_fn! {
+    /// Get an iterator over the query parameters of the request. There is a pair for each
+    /// value of a query parameter, and the pairs are ordered by name.
+    ///
+    /// # Safety
+    ///
+    /// The iterator has a copy of the query parameters, so it may safely outlive the
+    /// interaction. Changes to the query parameters will not be seen by the iterator.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer.
+    fn sync_http_get_request_query_param_iter(interaction: *const SynchronousHttp) -> *mut HttpKeyValueIterator {
+        let interaction = as_ref!(interaction);
+        let pairs = interaction.request.query.iter()
+            .flat_map(|query| query.iter())
+            .flat_map(|(name, values)| values.iter().map(move |value| (name.clone(), value.clone())))
+            .collect();
+        ptr::raw_to(HttpKeyValueIterator::new(pairs))
+    } {
+        ptr::null_mut_to::<HttpKeyValueIterator>()
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Request headers and body
+ */
+
+ffi_fn! {
+    /// Get a copy of the value of the request header `name`. Header names are case-insensitive,
+    /// and headers with more than one value have the values joined with a comma.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer. This includes when the request
+    /// does not have the header.
+    fn sync_http_find_request_header(interaction: *const SynchronousHttp, name: *const c_char) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        let value = find_header(&interaction.request.headers, safe_str!(name))?;
+        string::to_c(&value)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Set the request header `name` to the value, replacing any existing values (with a
+    /// case-insensitive match on the name).
+    ///
+    /// # Safety
+    ///
+    /// This function returns an enum indicating the result;
+    /// see the comments on HashMapInsertStatus for details.
+    ///
+    /// # Error Handling
+    ///
+    /// This function may fail if the provided `name` or `value` strings
+    /// contain invalid UTF-8.
+    fn sync_http_insert_request_header(
+        interaction: *mut SynchronousHttp,
+        name: *const c_char,
+        value: *const c_char
+    ) -> c_int {
+        let interaction = as_mut!(interaction);
+        insert_header(&mut interaction.request.headers, safe_str!(name), safe_str!(value)) as c_int
+    } {
+        HashMapInsertStatus::Error as c_int
+    }
+}
+
+ffi_fn! {
+    /// Get an iterator over the headers of the request. The pairs are ordered by name,
+    /// and headers with more than one value have the values joined with a comma.
+    ///
+    /// # Safety
+    ///
+    /// The iterator has a copy of the headers, so it may safely outlive the interaction.
+    /// Changes to the headers will not be seen by the iterator.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer.
+    fn sync_http_get_request_header_iter(interaction: *const SynchronousHttp) -> *mut HttpKeyValueIterator {
+        let interaction = as_ref!(interaction);
+        ptr::raw_to(HttpKeyValueIterator::from_headers(&interaction.request.headers))
+    } {
+        ptr::null_mut_to::<HttpKeyValueIterator>()
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the body of the request as a string.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// If the interaction is NULL or the body is missing, returns NULL. An empty or
+    /// null body is returned as an empty string. This function will also fail if the
+    /// body contains embedded null ('\0') bytes, in which case `sync_http_get_request_body_bin`
+    /// should be used.
+    fn sync_http_get_request_body(interaction: *const SynchronousHttp) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        body_to_c(&interaction.request.body)?
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get a pointer to the bytes of the body of the request. The length of the body
+    /// is returned by `sync_http_get_request_body_length`.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is owned by the interaction, and must not outlive it or be
+    /// used after the body is changed.
+    ///
+    /// # Error Handling
+    ///
+    /// If the interaction is NULL or the request has no body, returns NULL.
+    fn sync_http_get_request_body_bin(interaction: *const SynchronousHttp) -> *const c_uchar {
+        let interaction = as_ref!(interaction);
+        body_bytes(&interaction.request.body)?
+    } {
+        ptr::null_to::<c_uchar>()
+    }
+}
+
+ffi_fn! {
+    /// Get the length of the body of the request. Returns 0 if there is no body.
+    fn sync_http_get_request_body_length(interaction: *const SynchronousHttp) -> size_t {
+        let interaction = as_ref!(interaction);
+        body_length(&interaction.request.body)
+    } {
+        0
+    }
+}
+
+ffi_fn! {
+    /// Set the body of the request to the string, with the given content type. If the
+    /// request does not have a `Content-Type` header, it is set to the content type.
+    ///
+    /// # Safety
+    ///
+    /// `body` and `content_type` must contain valid UTF-8. `content_type` may be NULL,
+    /// in which case the content type is not set.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. an invalid content type) will be reported with a non-zero return value.
+    fn sync_http_set_request_body(
+        interaction: *mut SynchronousHttp,
+        body: *const c_char,
+        content_type: *const c_char
+    ) -> c_int {
+        let interaction = as_mut!(interaction);
+        let body = cstr!(body).to_bytes().to_vec();
+        let content_type = optional_content_type(content_type)?;
+        let request = &mut interaction.request;
+        set_body(&mut request.body, &mut request.headers, body, content_type);
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Set the body of the request to the `len` bytes pointed to by `body`, with the given
+    /// content type. If the request does not have a `Content-Type` header, it is set to the
+    /// content type.
+    ///
+    /// # Safety
+    ///
+    /// `body` must point to at least `len` bytes, which are copied. `content_type` must
+    /// contain valid UTF-8, and may be NULL, in which case the content type is not set.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. an invalid content type) will be reported with a non-zero return value.
+    fn sync_http_set_request_body_bin(
+        interaction: *mut SynchronousHttp,
+        body: *const c_uchar,
+        len: size_t,
+        content_type: *const c_char
+    ) -> c_int {
+        let interaction = as_mut!(interaction);
+        let body = bytes_from_raw(body, len)?;
+        let content_type = optional_content_type(content_type)?;
+        let request = &mut interaction.request;
+        set_body(&mut request.body, &mut request.headers, body, content_type);
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the content type of the request body. This is the content type of the
+    /// body, or the `Content-Type` header, or is detected from the body.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// If the interaction is NULL or the content type can not be determined, returns NULL.
+    fn sync_http_get_request_content_type(interaction: *const SynchronousHttp) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        let content_type = interaction.request.content_type()
+            .ok_or(anyhow!("the request has no content type"))?;
+        string::to_c(&content_type.to_string())? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Response status
+ */
+
+ffi_fn! {
+    /// Get the status of the response.
+    ///
+    /// # Error Handling
+    ///
+    /// If passed a NULL pointer, returns 0.
+    fn sync_http_get_response_status(interaction: *const SynchronousHttp) -> c_ushort {
+        let interaction = as_ref!(interaction);
+        interaction.response.status
+    } {
+        0
+    }
+}
+
+ffi_fn! {
+    /// Set the status of the response.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors will be reported with a non-zero return value.
+    fn sync_http_set_response_status(interaction: *mut SynchronousHttp, status: c_ushort) -> c_int {
+        let interaction = as_mut!(interaction);
+        interaction.response.status = status;
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Response headers and body
+ */
+
+ffi_fn! {
+    /// Get a copy of the value of the response header `name`. Header names are case-insensitive,
+    /// and headers with more than one value have the values joined with a comma.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer. This includes when the response
+    /// does not have the header.
+    fn sync_http_find_response_header(interaction: *const SynchronousHttp, name: *const c_char) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        let value = find_header(&interaction.response.headers, safe_str!(name))?;
+        string::to_c(&value)? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Set the response header `name` to the value, replacing any existing values (with a
+    /// case-insensitive match on the name).
+    ///
+    /// # Safety
+    ///
+    /// This function returns an enum indicating the result;
+    /// see the comments on HashMapInsertStatus for details.
+    ///
+    /// # Error Handling
+    ///
+    /// This function may fail if the provided `name` or `value` strings
+    /// contain invalid UTF-8.
+    fn sync_http_insert_response_header(
+        interaction: *mut SynchronousHttp,
+        name: *const c_char,
+        value: *const c_char
+    ) -> c_int {
+        let interaction = as_mut!(interaction);
+        insert_header(&mut interaction.response.headers, safe_str!(name), safe_str!(value)) as c_int
+    } {
+        HashMapInsertStatus::Error as c_int
+    }
+}
+
+ffi_fn! {
+    /// Get an iterator over the headers of the response. The pairs are ordered by name,
+    /// and headers with more than one value have the values joined with a comma.
+    ///
+    /// # Safety
+    ///
+    /// The iterator has a copy of the headers, so it may safely outlive the interaction.
+    /// Changes to the headers will not be seen by the iterator.
+    ///
+    /// # Error Handling
+    ///
+    /// On failure, this function will return a NULL pointer.
+    fn sync_http_get_response_header_iter(interaction: *const SynchronousHttp) -> *mut HttpKeyValueIterator {
+        let interaction = as_ref!(interaction);
+        ptr::raw_to(HttpKeyValueIterator::from_headers(&interaction.response.headers))
+    } {
+        ptr::null_mut_to::<HttpKeyValueIterator>()
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the body of the response as a string.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// If the interaction is NULL or the body is missing, returns NULL. An empty or
+    /// null body is returned as an empty string. This function will also fail if the
+    /// body contains embedded null ('\0') bytes, in which case `sync_http_get_response_body_bin`
+    /// should be used.
+    fn sync_http_get_response_body(interaction: *const SynchronousHttp) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        body_to_c(&interaction.response.body)?
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+ffi_fn! {
+    /// Get a pointer to the bytes of the body of the response. The length of the body
+    /// is returned by `sync_http_get_response_body_length`.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is owned by the interaction, and must not outlive it or be
+    /// used after the body is changed.
+    ///
+    /// # Error Handling
+    ///
+    /// If the interaction is NULL or the response has no body, returns NULL.
+    fn sync_http_get_response_body_bin(interaction: *const SynchronousHttp) -> *const c_uchar {
+        let interaction = as_ref!(interaction);
+        body_bytes(&interaction.response.body)?
+    } {
+        ptr::null_to::<c_uchar>()
+    }
+}
+
+ffi_fn! {
+    /// Get the length of the body of the response. Returns 0 if there is no body.
+    fn sync_http_get_response_body_length(interaction: *const SynchronousHttp) -> size_t {
+        let interaction = as_ref!(interaction);
+        body_length(&interaction.response.body)
+    } {
+        0
+    }
+}
+
+ffi_fn! {
+    /// Set the body of the response to the string, with the given content type. If the
+    /// response does not have a `Content-Type` header, it is set to the content type.
+    ///
+    /// # Safety
+    ///
+    /// `body` and `content_type` must contain valid UTF-8. `content_type` may be NULL,
+    /// in which case the content type is not set.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. an invalid content type) will be reported with a non-zero return value.
+    fn sync_http_set_response_body(
+        interaction: *mut SynchronousHttp,
+        body: *const c_char,
+        content_type: *const c_char
+    ) -> c_int {
+        let interaction = as_mut!(interaction);
+        let body = cstr!(body).to_bytes().to_vec();
+        let content_type = optional_content_type(content_type)?;
+        let response = &mut interaction.response;
+        set_body(&mut response.body, &mut response.headers, body, content_type);
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Set the body of the response to the `len` bytes pointed to by `body`, with the given
+    /// content type. If the response does not have a `Content-Type` header, it is set to the
+    /// content type.
+    ///
+    /// # Safety
+    ///
+    /// `body` must point to at least `len` bytes, which are copied. `content_type` must
+    /// contain valid UTF-8, and may be NULL, in which case the content type is not set.
+    ///
+    /// # Error Handling
+    ///
+    /// Errors (i.e. an invalid content type) will be reported with a non-zero return value.
+    fn sync_http_set_response_body_bin(
+        interaction: *mut SynchronousHttp,
+        body: *const c_uchar,
+        len: size_t,
+        content_type: *const c_char
+    ) -> c_int {
+        let interaction = as_mut!(interaction);
+        let body = bytes_from_raw(body, len)?;
+        let content_type = optional_content_type(content_type)?;
+        let response = &mut interaction.response;
+        set_body(&mut response.body, &mut response.headers, body, content_type);
+        EXIT_SUCCESS
+    } {
+        EXIT_FAILURE
+    }
+}
+
+ffi_fn! {
+    /// Get a copy of the content type of the response body. This is the content type of the
+    /// body, or the `Content-Type` header, or is detected from the body.
+    ///
+    /// # Safety
+    ///
+    /// The returned string must be deleted with `string_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// If the interaction is NULL or the content type can not be determined, returns NULL.
+    fn sync_http_get_response_content_type(interaction: *const SynchronousHttp) -> *const c_char {
+        let interaction = as_ref!(interaction);
+        let content_type = interaction.response.content_type()
+            .ok_or(anyhow!("the response has no content type"))?;
+        string::to_c(&content_type.to_string())? as *const c_char
+    } {
+        ptr::null_to::<c_char>()
+    }
+}
+
+/*-----------------------------------------------------------------------------------------------
+ * ## Key-value iterator
+ */
+
+ffi_fn! {
+    /// Get the next key and value out of the iterator, if possible.
+    ///
+    /// # Safety
+    ///
+    /// The returned pair must be deleted with `http_key_value_pair_delete`.
+    ///
+    /// # Error Handling
+    ///
+    /// If no further data is present, returns NULL.
+    fn http_key_value_iter_next(iter: *mut HttpKeyValueIterator) -> *mut HttpKeyValuePair {
+        let iter = as_mut!(iter);
+        let (key, value) = iter.next().ok_or(anyhow!("iter past the end of the pairs"))?;
+        let pair = HttpKeyValuePair::new(key, value)?;
+        ptr::raw_to(pair)
+    } {
+        ptr::null_mut_to::<HttpKeyValuePair>()
+    }
+}
+
+ffi_fn! {
+    /// Free the key-value iterator when you're done using it.
+    fn http_key_value_iter_delete(iter: *mut HttpKeyValueIterator) {
+        ptr::drop_raw(iter);
+    }
+}
+
+ffi_fn! {
+    /// Free a pair of key and value returned from `http_key_value_iter_next`.
+    fn http_key_value_pair_delete(pair: *mut HttpKeyValuePair) {
+        ptr::drop_raw(pair);
+    }
+}
+
+/// An iterator over a copy of the headers or query parameters of an HTTP interaction.
+#[derive(Debug)]
+pub struct HttpKeyValueIterator {
+    /// The names and values
+    pairs: Vec<(String, String)>,
+    /// The current pair
+    current: usize,
+}
+
+impl HttpKeyValueIterator {
+    fn new(mut pairs: Vec<(String, String)>) -> HttpKeyValueIterator {
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        HttpKeyValueIterator { pairs, current: 0 }
+    }
+
+    fn from_headers(headers: &Option<HashMap<String, Vec<String>>>) -> HttpKeyValueIterator {
+        HttpKeyValueIterator::new(headers.iter()
+            .flat_map(|headers| headers.iter())
+            .map(|(name, values)| (name.clone(), values.join(", ")))
+            .collect())
+    }
+
+    fn next(&mut self) -> Option<&(String, String)> {
+        let idx = self.current;
+        self.current += 1;
+        self.pairs.get(idx)
+    }
+}
+
+/// A single key-value pair of a header or query parameter exported to the C-side.
+#[derive(Debug)]
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct HttpKeyValuePair {
+    /// The header or query parameter name.
+    key: *const c_char,
+    /// The value.
+    value: *const c_char,
+}
+
+impl HttpKeyValuePair {
+    fn new(key: &str, value: &str) -> anyhow::Result<HttpKeyValuePair> {
+        Ok(HttpKeyValuePair {
+            key: string::to_c(key)? as *const c_char,
+            value: string::to_c(value)? as *const c_char,
+        })
+    }
+}
+
+// Ensure that the owned strings are freed when the pair is dropped. See the notes
+// on `MessageMetadataPair` for why the pointers are cast back to `*mut`.
+impl Drop for HttpKeyValuePair {
+    fn drop(&mut self) {
+        string::string_delete(self.key as *mut c_char);
+        string::string_delete(self.value as *mut c_char);
+    }
+}
+
+/*===============================================================================================
+ * # Helper Functions
+ *---------------------------------------------------------------------------------------------*/
+
+fn find_header(headers: &Option<HashMap<String, Vec<String>>>, name: &str) -> anyhow::Result<String> {
+    headers.iter()
+        .flat_map(|headers| headers.iter())
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, values)| values.join(", "))
+        .ok_or(anyhow!("no header with the name '{}'", name))
+}
+
+fn insert_header(
+    headers: &mut Option<HashMap<String, Vec<String>>>,
+    name: &str,
+    value: &str
+) -> HashMapInsertStatus {
+    let headers = headers.get_or_insert_with(HashMap::new);
+    let existing = headers.keys().find(|key| key.eq_ignore_ascii_case(name)).cloned();
+    let status = match existing {
+        Some(key) => {
+            headers.remove(&key);
+            HashMapInsertStatus::SuccessOverwrite
+        },
+        None => HashMapInsertStatus::SuccessNew
+    };
+    headers.insert(name.to_string(), vec![value.to_string()]);
+    status
+}
+
+fn body_to_c(body: &OptionalBody) -> anyhow::Result<*const c_char> {
+    match body {
+        OptionalBody::Missing => Err(anyhow!("the body is missing")),
+        OptionalBody::Empty | OptionalBody::Null => Ok(string::to_c("")? as *const c_char),
+        _ => Ok(string::to_c(body.str_value())? as *const c_char)
+    }
+}
+
+fn body_bytes(body: &OptionalBody) -> anyhow::Result<*const c_uchar> {
+    match body {
+        OptionalBody::Present(bytes, _) => Ok(bytes.as_ptr()),
+        _ => Err(anyhow!("there is no body"))
+    }
+}
+
+fn body_length(body: &OptionalBody) -> size_t {
+    match body {
+        OptionalBody::Present(bytes, _) => bytes.len(),
+        _ => 0
+    }
+}
+
+pub(crate) fn bytes_from_raw(body: *const c_uchar, len: size_t) -> anyhow::Result<Vec<u8>> {
+    if body.is_null() {
+        Err(NullPointerError("body").into())
+    } else {
+        Ok(unsafe { std::slice::from_raw_parts(body, len) }.to_vec())
+    }
+}
+
+pub(crate) fn optional_content_type(content_type: *const c_char) -> anyhow::Result<Option<ContentType>> {
+    if content_type.is_null() {
+        Ok(None)
+    } else {
+        let content_type = safe_str!(content_type);
+        ContentType::parse(content_type)
+            .map(Some)
+            .map_err(|s| anyhow!("invalid content type '{}'", s))
+    }
+}
+
+fn set_body(
+    body: &mut OptionalBody,
+    headers: &mut Option<HashMap<String, Vec<String>>>,
+    bytes: Vec<u8>,
+    content_type: Option<ContentType>
+) {
+    if let Some(content_type) = &content_type {
+        if find_header(headers, "Content-Type").is_err() {
+            insert_header(headers, "Content-Type", &content_type.to_string());
+        }
+    }
+    *body = OptionalBody::Present(bytes.into(), content_type);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+
+    use expectest::prelude::*;
+
+    use crate::error::{last_error_code, ErrorCode};
+
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    /// Collects the key-value pairs from the iterator, and deletes the pairs and the iterator
+    fn collect_pairs(iter: *mut HttpKeyValueIterator) -> Vec<(String, String)> {
+        let mut pairs = vec![];
+        loop {
+            let pair = http_key_value_iter_next(iter);
+            if pair.is_null() {
+                break;
+            }
+            let (key, value) = unsafe {
+                (CStr::from_ptr((*pair).key), CStr::from_ptr((*pair).value))
+            };
+            pairs.push((key.to_string_lossy().to_string(), value.to_string_lossy().to_string()));
+            http_key_value_pair_delete(pair);
+        }
+        http_key_value_iter_delete(iter);
+        pairs
+    }
+
+    #[test]
+    fn request_round_trip() {
+        let interaction = sync_http_new();
+        expect!(sync_http_set_description(interaction, c("create a user").as_ptr())).to(be_equal_to(EXIT_SUCCESS));
+        expect!(string::from_c(sync_http_get_description(interaction))).to(be_some().value("create a user"));
+
+        expect!(sync_http_set_request_method(interaction, c("post").as_ptr())).to(be_equal_to(EXIT_SUCCESS));
+        expect!(string::from_c(sync_http_get_request_method(interaction))).to(be_some().value("POST"));
+        expect!(sync_http_set_request_path(interaction, c("/users").as_ptr())).to(be_equal_to(EXIT_SUCCESS));
+        expect!(string::from_c(sync_http_get_request_path(interaction))).to(be_some().value("/users"));
+
+        expect!(sync_http_insert_request_query_param(interaction, c("tag").as_ptr(), c("a").as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::SuccessNew as c_int));
+        expect!(sync_http_insert_request_query_param(interaction, c("tag").as_ptr(), c("b").as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::SuccessOverwrite as c_int));
+        expect!(sync_http_insert_request_query_param(interaction, c("notify").as_ptr(), c("true").as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::SuccessNew as c_int));
+        expect!(string::from_c(sync_http_find_request_query_param(interaction, c("tag").as_ptr(), 1)))
+            .to(be_some().value("b"));
+        expect!(string::from_c(sync_http_find_request_query_param(interaction, c("tag").as_ptr(), 2))).to(be_none());
+        expect!(string::from_c(sync_http_find_request_query_param(interaction, c("other").as_ptr(), 0))).to(be_none());
+        expect!(collect_pairs(sync_http_get_request_query_param_iter(interaction))).to(be_equal_to(vec![
+            ("notify".to_string(), "true".to_string()),
+            ("tag".to_string(), "a".to_string()),
+            ("tag".to_string(), "b".to_string())
+        ]));
+
+        expect!(sync_http_insert_request_header(interaction, c("X-Trace").as_ptr(), c("1").as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::SuccessNew as c_int));
+        expect!(sync_http_insert_request_header(interaction, c("x-trace").as_ptr(), c("2").as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::SuccessOverwrite as c_int));
+        expect!(string::from_c(sync_http_find_request_header(interaction, c("X-TRACE").as_ptr()))).to(be_some().value("2"));
+        expect!(string::from_c(sync_http_find_request_header(interaction, c("Accept").as_ptr()))).to(be_none());
+
+        expect!(sync_http_set_request_body(interaction, c("{\"name\":\"Fred\"}").as_ptr(), c("application/json").as_ptr()))
+            .to(be_equal_to(EXIT_SUCCESS));
+        expect!(string::from_c(sync_http_get_request_body(interaction))).to(be_some().value("{\"name\":\"Fred\"}"));
+        expect!(sync_http_get_request_body_length(interaction)).to(be_equal_to(15));
+        expect!(string::from_c(sync_http_get_request_content_type(interaction))).to(be_some().value("application/json"));
+        expect!(collect_pairs(sync_http_get_request_header_iter(interaction))).to(be_equal_to(vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("x-trace".to_string(), "2".to_string())
+        ]));
+
+        sync_http_delete(interaction);
+    }
+
+    #[test]
+    fn response_round_trip() {
+        let interaction = sync_http_new();
+        expect!(sync_http_get_response_status(interaction)).to(be_equal_to(200));
+        expect!(sync_http_set_response_status(interaction, 201)).to(be_equal_to(EXIT_SUCCESS));
+        expect!(sync_http_get_response_status(interaction)).to(be_equal_to(201));
+
+        expect!(sync_http_insert_response_header(interaction, c("Location").as_ptr(), c("/users/1").as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::SuccessNew as c_int));
+        expect!(string::from_c(sync_http_find_response_header(interaction, c("location").as_ptr())))
+            .to(be_some().value("/users/1"));
+
+        expect!(string::from_c(sync_http_get_response_body(interaction))).to(be_none());
+        expect!(sync_http_get_response_body_bin(interaction).is_null()).to(be_true());
+        expect!(sync_http_get_response_body_length(interaction)).to(be_equal_to(0));
+
+        let body = [b'P', b'N', b'G', 0, 1];
+        expect!(sync_http_set_response_body_bin(interaction, body.as_ptr(), body.len(), c("image/png").as_ptr()))
+            .to(be_equal_to(EXIT_SUCCESS));
+        let len = sync_http_get_response_body_length(interaction);
+        let bytes = sync_http_get_response_body_bin(interaction);
+        expect!(unsafe { std::slice::from_raw_parts(bytes, len) }.to_vec()).to(be_equal_to(body.to_vec()));
+        // The body has an embedded NULL, so it can not be returned as a string
+        expect!(string::from_c(sync_http_get_response_body(interaction))).to(be_none());
+        expect!(string::from_c(sync_http_get_response_content_type(interaction))).to(be_some().value("image/png"));
+        expect!(collect_pairs(sync_http_get_response_header_iter(interaction))).to(be_equal_to(vec![
+            ("Content-Type".to_string(), "image/png".to_string()),
+            ("Location".to_string(), "/users/1".to_string())
+        ]));
+
+        expect!(sync_http_set_response_body(interaction, c("").as_ptr(), std::ptr::null())).to(be_equal_to(EXIT_SUCCESS));
+        expect!(sync_http_get_response_body_length(interaction)).to(be_equal_to(0));
+
+        sync_http_delete(interaction);
+    }
+
+    #[test]
+    fn functions_handle_null_pointers() {
+        let null = std::ptr::null::<SynchronousHttp>();
+        let null_mut = std::ptr::null_mut::<SynchronousHttp>();
+        let name = c("name");
+
+        expect!(string::from_c(sync_http_get_description(null))).to(be_none());
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+        expect!(string::from_c(sync_http_get_request_method(null))).to(be_none());
+        expect!(string::from_c(sync_http_get_request_path(null))).to(be_none());
+        expect!(string::from_c(sync_http_find_request_query_param(null, name.as_ptr(), 0))).to(be_none());
+        expect!(sync_http_get_request_query_param_iter(null).is_null()).to(be_true());
+        expect!(string::from_c(sync_http_find_request_header(null, name.as_ptr()))).to(be_none());
+        expect!(sync_http_get_request_header_iter(null).is_null()).to(be_true());
+        expect!(string::from_c(sync_http_get_request_body(null))).to(be_none());
+        expect!(sync_http_get_request_body_bin(null).is_null()).to(be_true());
+        expect!(sync_http_get_request_body_length(null)).to(be_equal_to(0));
+        expect!(string::from_c(sync_http_get_request_content_type(null))).to(be_none());
+        expect!(sync_http_get_response_status(null)).to(be_equal_to(0));
+        expect!(string::from_c(sync_http_find_response_header(null, name.as_ptr()))).to(be_none());
+        expect!(sync_http_get_response_header_iter(null).is_null()).to(be_true());
+        expect!(string::from_c(sync_http_get_response_body(null))).to(be_none());
+        expect!(sync_http_get_response_body_bin(null).is_null()).to(be_true());
+        expect!(sync_http_get_response_body_length(null)).to(be_equal_to(0));
+        expect!(string::from_c(sync_http_get_response_content_type(null))).to(be_none());
+        expect!(http_key_value_iter_next(std::ptr::null_mut()).is_null()).to(be_true());
+
+        expect!(sync_http_set_description(null_mut, name.as_ptr())).to(be_equal_to(EXIT_FAILURE));
+        expect!(sync_http_set_request_method(null_mut, name.as_ptr())).to(be_equal_to(EXIT_FAILURE));
+        expect!(sync_http_set_request_path(null_mut, name.as_ptr())).to(be_equal_to(EXIT_FAILURE));
+        expect!(sync_http_set_response_status(null_mut, 200)).to(be_equal_to(EXIT_FAILURE));
+        expect!(sync_http_insert_request_header(null_mut, name.as_ptr(), name.as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::Error as c_int));
+        expect!(sync_http_set_response_body(null_mut, name.as_ptr(), std::ptr::null())).to(be_equal_to(EXIT_FAILURE));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+
+        let interaction = sync_http_new();
+        expect!(sync_http_set_description(interaction, std::ptr::null())).to(be_equal_to(EXIT_FAILURE));
+        expect!(sync_http_insert_request_query_param(interaction, name.as_ptr(), std::ptr::null()))
+            .to(be_equal_to(HashMapInsertStatus::Error as c_int));
+        expect!(sync_http_insert_response_header(interaction, std::ptr::null(), name.as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::Error as c_int));
+        expect!(sync_http_set_request_body_bin(interaction, std::ptr::null(), 10, std::ptr::null()))
+            .to(be_equal_to(EXIT_FAILURE));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::NullPointer));
+        expect!(sync_http_get_request_query_param_iter(interaction).is_null()).to(be_false());
+        sync_http_delete(interaction);
+    }
+
+    #[test]
+    fn setters_fail_with_invalid_utf8_or_content_types() {
+        let interaction = sync_http_new();
+        let invalid_utf8 = CString::new(vec![b'/', 0xFF, 0xFE]).unwrap();
+        expect!(sync_http_set_request_path(interaction, invalid_utf8.as_ptr())).to(be_equal_to(EXIT_FAILURE));
+        expect!(last_error_code()).to(be_equal_to(ErrorCode::InvalidUtf8));
+        expect!(sync_http_insert_request_header(interaction, invalid_utf8.as_ptr(), c("1").as_ptr()))
+            .to(be_equal_to(HashMapInsertStatus::Error as c_int));
+        expect!(sync_http_set_request_body(interaction, c("{}").as_ptr(), c("not a content type").as_ptr()))
+            .to(be_equal_to(EXIT_FAILURE));
+        expect!(string::from_c(sync_http_get_request_path(interaction))).to(be_some().value("/"));
+        expect!(sync_http_get_request_body_length(interaction)).to(be_equal_to(0));
+        sync_http_delete(interaction);
+    }
+
+    #[test]
+    fn key_value_iterator_has_a_copy_of_the_pairs() {
+        let interaction = sync_http_new();
+        sync_http_insert_response_header(interaction, c("X-Trace").as_ptr(), c("1").as_ptr());
+        let iter = sync_http_get_response_header_iter(interaction);
+        sync_http_delete(interaction);
+
+        expect!(collect_pairs(iter)).to(be_equal_to(vec![("X-Trace".to_string(), "1".to_string())]));
+        string::string_delete(std::ptr::null_mut());
+    }
+}
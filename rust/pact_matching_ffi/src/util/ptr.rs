@@ -40,10 +40,10 @@ pub(crate) fn null_mut_to<T>() -> *mut T {
 #[macro_export]
 macro_rules! as_ref {
     ( $name:expr ) => {{
-        unsafe { $name.as_ref() }.ok_or(::anyhow::anyhow!(concat!(
-            stringify!($name),
-            " is null"
-        )))?
+        let pointer = $name;
+        unsafe { pointer.as_ref() }.ok_or_else(|| {
+            ::anyhow::Error::new($crate::error::NullPointerError(stringify!($name)))
+        })?
     }};
 }
 
@@ -51,9 +51,9 @@ macro_rules! as_ref {
 #[macro_export]
 macro_rules! as_mut {
     ( $name:expr ) => {{
-        unsafe { $name.as_mut() }.ok_or(::anyhow::anyhow!(concat!(
-            stringify!($name),
-            " is null"
-        )))?
+        let pointer = $name;
+        unsafe { pointer.as_mut() }.ok_or_else(|| {
+            ::anyhow::Error::new($crate::error::NullPointerError(stringify!($name)))
+        })?
     }};
 }
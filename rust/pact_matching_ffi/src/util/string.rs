@@ -29,17 +29,31 @@ ffi_fn! {
     }
 }
 
+/// Takes back ownership of a string returned by the FFI functions, returning a copy of it
+/// (or `None` for a NULL pointer) and deleting the original.
+#[cfg(test)]
+pub(crate) fn from_c(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        None
+    } else {
+        let value = unsafe { std::ffi::CStr::from_ptr(s) }.to_string_lossy().to_string();
+        string_delete(s as *mut c_char);
+        Some(value)
+    }
+}
+
 /// Construct a CStr safely with null checks.
 #[macro_export]
 macro_rules! cstr {
     ( $name:ident ) => {{
         use ::std::ffi::CStr;
 
-        if $name.is_null() {
-            ::anyhow::bail!(concat!(stringify!($name), " is null"));
+        let pointer = $name;
+        if pointer.is_null() {
+            return Err(::anyhow::Error::new($crate::error::NullPointerError(stringify!($name))));
         }
 
-        unsafe { CStr::from_ptr($name) }
+        unsafe { CStr::from_ptr(pointer) }
     }};
 }
 
@@ -67,13 +67,12 @@ trait ZeroizedWrite: Write {
 
 impl<'a> ZeroizedWrite for &'a mut [u8] {
     fn zeroized_write(mut self, buf: &[u8]) -> io::Result<()> {
-        // Write the buffer.
+        // Write the buffer. Writing advances the slice past the written bytes, so what
+        // is left of it is the remainder.
         self.write_all(buf)?;
 
-        // Check if there's a remainder and zeroize if there is.
-        if let Some(remainder) = self.get_mut(buf.len()..) {
-            remainder.iter_mut().zeroize();
-        }
+        // Zeroize the remainder.
+        self.iter_mut().zeroize();
 
         Ok(())
     }
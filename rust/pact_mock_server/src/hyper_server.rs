@@ -2,13 +2,17 @@ use std::borrow::BorrowMut;
 use std::collections::HashMap;
 use std::io;
 use std::net::SocketAddr;
+use std::ops::DerefMut;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use futures::prelude::*;
 use futures::StreamExt;
+use futures::stream;
 use futures::task::{Context, Poll};
-use hyper::{Body, Error, Response, Server};
+use bytes::Bytes;
+use hyper::{Body, Response, Server};
 use hyper::http::header::{HeaderName, HeaderValue};
 use hyper::http::response::Builder as ResponseBuilder;
 use hyper::service::make_service_fn;
@@ -16,25 +20,35 @@ use hyper::service::service_fn;
 use log::*;
 use maplit::*;
 use rustls::ServerConfig;
-use serde_json::json;
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio_rustls::server::TlsStream;
-use tokio_rustls::TlsAcceptor;
 
-use pact_matching::models::{HttpPart, Pact, Request, RequestResponsePact};
+use pact_matching::models::{HttpPart, Pact, Request};
 use pact_matching::models::generators::GeneratorTestMode;
-use pact_matching::models::parse_query_string;
+use pact_matching::models::{parse_query_string, query_string_encoding_mismatches};
+use pact_matching::Mismatch;
+use pact_matching::path_template::path_parameters_for_request;
+use pact_matching::plugins::with_plugins;
 use pact_models::OptionalBody;
 
-use crate::matching::{match_request, MatchResult};
-use crate::mock_server::MockServer;
+use crate::matching::{match_request_with_config, MatchResult};
+use crate::metrics::METRICS_PATH;
+use crate::mock_server::{ConnectionOptions, HEALTH_PATH, MockServer, READY_PATH, RESET_PATH, VirtualHost};
+use crate::tls::accept_tls_connections;
+use crate::websocket;
 
 #[derive(Debug, Clone)]
-enum InteractionError {
+pub(crate) enum InteractionError {
     RequestHeaderEncodingError,
     RequestBodyError,
+    RequestTimeout,
     ResponseHeaderEncodingError,
-    ResponseBodyError
+    ResponseBodyError,
+    SimulatedError,
+    SimulatedConnectionReset
 }
 
 fn extract_path(uri: &hyper::Uri) -> String {
@@ -54,7 +68,7 @@ fn extract_query_string(uri: &hyper::Uri) -> Option<HashMap<String, Vec<String>>
     .and_then(|query| parse_query_string(query))
 }
 
-fn extract_headers(headers: &hyper::HeaderMap) -> Result<Option<HashMap<String, Vec<String>>>, InteractionError> {
+pub(crate) fn extract_headers(headers: &hyper::HeaderMap) -> Result<Option<HashMap<String, Vec<String>>>, InteractionError> {
   if !headers.is_empty() {
     let result: Result<HashMap<String, Vec<String>>, InteractionError> = headers.keys()
       .map(|name| -> Result<(String, Vec<String>), InteractionError> {
@@ -93,15 +107,24 @@ fn extract_body(bytes: bytes::Bytes, request: &Request) -> OptionalBody {
     }
 }
 
-async fn hyper_request_to_pact_request(req: hyper::Request<Body>) -> Result<Request, InteractionError> {
+pub(crate) async fn hyper_request_to_pact_request(
+  req: hyper::Request<Body>,
+  read_timeout: Option<Duration>
+) -> Result<Request, InteractionError> {
     let method = req.method().to_string();
     let path = extract_path(req.uri());
     let query = extract_query_string(req.uri());
     let headers = extract_headers(req.headers())?;
 
-    let body_bytes = hyper::body::to_bytes(req.into_body())
-        .await
-        .map_err(|_| InteractionError::RequestBodyError)?;
+    let body = hyper::body::to_bytes(req.into_body());
+    let body_bytes = match read_timeout {
+      Some(timeout) => tokio::time::timeout(timeout, body).await
+        .map_err(|_| {
+          warn!("Timed out reading the request body after {:?}", timeout);
+          InteractionError::RequestTimeout
+        })?,
+      None => body.await
+    }.map_err(|_| InteractionError::RequestBodyError)?;
 
     let request = Request {
       method,
@@ -150,6 +173,36 @@ fn error_body(request: &Request, error: &String) -> String {
     body.to_string()
 }
 
+/// Context used by the generators of the responses
+fn generator_context(ms: &MockServer) -> HashMap<&'static str, Value> {
+  let mut context = hashmap!{
+    "mockServer" => json!({
+      "href": ms.url(),
+      "port": ms.port
+    })
+  };
+  if let Some(seed) = ms.config.generator_seed {
+    context.insert("generatorSeed", json!(seed));
+  }
+  context
+}
+
+/// Streams the events of a server-sent event body one at a time, waiting for the delay before
+/// sending each event after the first one
+fn event_stream_body(body: &Bytes, delay: Option<Duration>) -> Body {
+  let events = pact_matching::sse::split_events(&String::from_utf8_lossy(body));
+  debug!("Streaming {} events with a delay of {:?}", events.len(), delay);
+  Body::wrap_stream(stream::iter(events.into_iter().enumerate())
+    .then(move |(index, event)| async move {
+      if let Some(delay) = delay {
+        if index > 0 {
+          tokio::time::sleep(delay).await;
+        }
+      }
+      Ok::<_, io::Error>(event)
+    }))
+}
+
 fn match_result_to_hyper_response(
   request: &Request,
   match_result: MatchResult,
@@ -161,16 +214,13 @@ fn match_result_to_hyper_response(
   };
 
   match match_result {
-    MatchResult::RequestMatch(ref request, ref response) => {
+    MatchResult::RequestMatch(ref expected, ref response) => {
       let ms = mock_server.lock().unwrap();
-      let context = hashmap!{
-        "mockServer" => json!({
-          "href": ms.url(),
-          "port": ms.port
-        })
-      };
+      let mut context = generator_context(&ms);
+      context.insert("pathParameters", json!(path_parameters_for_request(expected, &request.path)));
       debug!("Test context = {:?}", context);
-      let response = pact_matching::generate_response(response, &GeneratorTestMode::Consumer, &context);
+      let response = with_plugins(&ms.plugins, ||
+        pact_matching::generate_response(response, &GeneratorTestMode::Consumer, &context));
       info!("Request matched, sending response {}", response);
       if response.has_text_body() {
         debug!("     body: '{}'", response.body.str_value());
@@ -185,7 +235,11 @@ fn match_result_to_hyper_response(
 
       set_hyper_headers(&mut builder, &response.headers)?;
 
+      let event_stream = response.content_type()
+        .map(|content_type| content_type.base_type() == "text/event-stream")
+        .unwrap_or(false);
       builder.body(match response.body {
+        OptionalBody::Present(ref s, _) if event_stream => event_stream_body(s, ms.config.event_stream_delay),
         OptionalBody::Present(ref s, _) => Body::from(s.clone()),
         _ => Body::empty()
       })
@@ -193,29 +247,9 @@ fn match_result_to_hyper_response(
     },
     _ => {
       debug!("Request did not match: {}", match_result);
-      if cors_preflight && request.method.to_uppercase() == "OPTIONS" {
+      if cors_preflight && match_result.cors_preflight() {
         info!("Responding to CORS pre-flight request");
-        let origin = match request.headers.clone() {
-          Some(ref h) => h.iter()
-            .find(|kv| kv.0.to_lowercase() == "referer")
-            .map(|kv| kv.1.clone().join(", ")).unwrap_or("*".to_string()),
-          None => "*".to_string()
-        };
-        let cors_headers = match request.headers.clone() {
-          Some(ref h) => h.iter()
-            .find(|kv| kv.0.to_lowercase() == "access-control-request-headers")
-            .map(|kv| kv.1.clone().join(", ") + ", *").unwrap_or("*".to_string()),
-          None => "*".to_string()
-        };
-
-        Response::builder()
-          .status(204)
-          .header(hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
-          .header(hyper::header::ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD, POST, PUT, DELETE, CONNECT, OPTIONS, TRACE, PATCH")
-          .header(hyper::header::ACCESS_CONTROL_ALLOW_HEADERS, cors_headers)
-          .header(hyper::header::ACCESS_CONTROL_EXPOSE_HEADERS, "Location, Link")
-          .body(Body::empty())
-          .map_err(|_| InteractionError::ResponseBodyError)
+        cors_preflight_response(request)
       } else {
         Response::builder()
           .status(500)
@@ -229,37 +263,249 @@ fn match_result_to_hyper_response(
   }
 }
 
+fn find_header(request: &Request, name: &str) -> Option<String> {
+  request.headers.as_ref().and_then(|headers| headers.iter()
+    .find(|(k, _)| k.to_lowercase() == name)
+    .map(|(_, v)| v.join(", ")))
+}
+
+fn cors_preflight_response(request: &Request) -> Result<Response<Body>, InteractionError> {
+  // Echo back the origin so that requests with credentials are also allowed by the browser
+  let origin = find_header(request, "origin");
+  let allow_credentials = origin.is_some();
+  let methods = find_header(request, "access-control-request-method")
+    .map(|method| format!("{}, GET, HEAD, POST, PUT, DELETE, CONNECT, OPTIONS, TRACE, PATCH", method))
+    .unwrap_or_else(|| "GET, HEAD, POST, PUT, DELETE, CONNECT, OPTIONS, TRACE, PATCH".to_string());
+  let cors_headers = find_header(request, "access-control-request-headers")
+    .map(|headers| headers + ", *")
+    .unwrap_or_else(|| "*".to_string());
+
+  let mut builder = Response::builder()
+    .status(204)
+    .header(hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.unwrap_or_else(|| "*".to_string()))
+    .header(hyper::header::ACCESS_CONTROL_ALLOW_METHODS, methods)
+    .header(hyper::header::ACCESS_CONTROL_ALLOW_HEADERS, cors_headers)
+    .header(hyper::header::ACCESS_CONTROL_EXPOSE_HEADERS, "Location, Link")
+    .header(hyper::header::ACCESS_CONTROL_MAX_AGE, "86400");
+  if allow_credentials {
+    builder = builder
+      .header(hyper::header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true")
+      .header(hyper::header::VARY, "Origin");
+  }
+  builder.body(Body::empty())
+    .map_err(|_| InteractionError::ResponseBodyError)
+}
+
+/// Returns the host name the request was sent to, from the URI or `Host` header, falling back to
+/// the SNI host name of the TLS connection
+fn request_host(req: &hyper::Request<Body>, sni_host: &Option<String>) -> Option<String> {
+  req.uri().host().map(|host| host.to_string())
+    .or_else(|| req.headers().get(hyper::header::HOST)
+      .and_then(|host| host.to_str().ok())
+      .and_then(|host| host.parse::<hyper::http::uri::Authority>().ok())
+      .map(|authority| authority.host().to_string()))
+    .or_else(|| sni_host.clone())
+    .map(|host| host.to_lowercase())
+}
+
+/// Routes the request to the virtual host for the host name it was sent to, if there is one,
+/// otherwise to the mock server itself
+fn route_request(
+  req: &hyper::Request<Body>,
+  sni_host: &Option<String>,
+  matches: Arc<Mutex<Vec<MatchResult>>>,
+  mock_server: Arc<Mutex<MockServer>>
+) -> VirtualHost {
+  let virtual_host = request_host(req, sni_host).and_then(|host| {
+    let ms = mock_server.lock().unwrap();
+    ms.virtual_hosts.get(&host).cloned().map(|virtual_host| (host, virtual_host))
+  });
+  match virtual_host {
+    Some((host, virtual_host)) => {
+      debug!("Routing request to virtual host '{}'", host);
+      virtual_host
+    },
+    None => VirtualHost { matches, mock_server }
+  }
+}
+
+/// Malformed percent-encodings in the query string are mismatches, even if the values as they
+/// were received match the interaction
+fn with_query_encoding_mismatches(match_result: MatchResult, query_mismatches: Vec<Mismatch>) -> MatchResult {
+  if query_mismatches.is_empty() {
+    return match_result;
+  }
+  warn!("Request has a query string that is not correctly encoded: {:?}", query_mismatches);
+  match match_result {
+    MatchResult::RequestMatch(request, _) => MatchResult::RequestMismatch(request, query_mismatches),
+    MatchResult::RequestMismatch(request, mut mismatches) => {
+      mismatches.extend(query_mismatches);
+      MatchResult::RequestMismatch(request, mismatches)
+    },
+    result => result
+  }
+}
+
 async fn handle_request(
   req: hyper::Request<Body>,
-  pact: Arc<RequestResponsePact>,
+  sni_host: Option<String>,
+  matches: Arc<Mutex<Vec<MatchResult>>>,
+  mock_server: Arc<Mutex<MockServer>>
+) -> Result<Response<Body>, InteractionError> {
+  let VirtualHost { matches, mock_server } = route_request(&req, &sni_host, matches, mock_server);
+
+  if req.method() == hyper::Method::GET && req.uri().path() == METRICS_PATH {
+    let ms = mock_server.lock().unwrap();
+    if ms.config.metrics_endpoint {
+      return metrics_response(&ms);
+    }
+  }
+
+  if req.method() == hyper::Method::GET && (req.uri().path() == HEALTH_PATH || req.uri().path() == READY_PATH) {
+    let ms = mock_server.lock().unwrap();
+    if ms.config.health_endpoints {
+      return health_response(&ms, req.uri().path() == READY_PATH);
+    }
+  }
+
+  if req.method() == hyper::Method::POST && req.uri().path() == RESET_PATH {
+    let mut ms = mock_server.lock().unwrap();
+    if ms.config.admin_routes {
+      info!("Resetting the state of mock server {}", ms.id);
+      ms.reset();
+      return Response::builder()
+        .status(204)
+        .body(Body::empty())
+        .map_err(|_| InteractionError::ResponseBodyError);
+    }
+  }
+
+  let start = Instant::now();
+  let result = serve_request(req, matches, mock_server.clone()).await;
+  let mut ms = mock_server.lock().unwrap();
+  if result.is_err() {
+    ms.metrics.errors += 1;
+  }
+  ms.metrics.response_times.record(start.elapsed());
+  result
+}
+
+/// Responds with the metrics of the mock server in the Prometheus text format
+fn metrics_response(mock_server: &MockServer) -> Result<Response<Body>, InteractionError> {
+  Response::builder()
+    .status(200)
+    .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")
+    .body(Body::from(mock_server.metrics.to_prometheus(&mock_server.id)))
+    .map_err(|_| InteractionError::ResponseBodyError)
+}
+
+/// Responds to the liveness and readiness probes. The mock server is ready once its pact can be
+/// used to match requests.
+fn health_response(mock_server: &MockServer, readiness: bool) -> Result<Response<Body>, InteractionError> {
+  let (status, body) = if readiness {
+    match mock_server.pact.lock() {
+      Ok(pact) => (200, json!({ "status": "UP", "interactions": pact.interactions().len() })),
+      Err(_) => (503, json!({ "status": "DOWN" }))
+    }
+  } else {
+    (200, json!({ "status": "UP" }))
+  };
+  Response::builder()
+    .status(status)
+    .header(hyper::header::CONTENT_TYPE, "application/json")
+    .body(Body::from(body.to_string()))
+    .map_err(|_| InteractionError::ResponseBodyError)
+}
+
+async fn serve_request(
+  mut req: hyper::Request<Body>,
   matches: Arc<Mutex<Vec<MatchResult>>>,
   mock_server: Arc<Mutex<MockServer>>
 ) -> Result<Response<Body>, InteractionError> {
   debug!("Creating pact request from hyper request");
 
-  {
+  let (read_timeout, pact) = {
     let mut guard = mock_server.lock().unwrap();
     let mock_server = guard.borrow_mut();
     mock_server.metrics.requests = mock_server.metrics.requests + 1;
-  }
+    (mock_server.config.connections.read_timeout, mock_server.request_response_pact.clone())
+  };
 
-  let pact_request = hyper_request_to_pact_request(req).await?;
+  let query_mismatches = req.uri().query()
+    .map(query_string_encoding_mismatches)
+    .unwrap_or_default();
+  let websocket_upgrade = websocket::websocket_key(&req)
+    .map(|key| (key, hyper::upgrade::on(&mut req)));
+  let pact_request = hyper_request_to_pact_request(req, read_timeout).await?;
   info!("Received request {}", pact_request);
   if pact_request.has_text_body() {
     debug!("     body: '{}'", pact_request.body.str_value());
   }
 
-  let match_result = match_request(&pact_request, pact.interactions());
+  if let Some((key, on_upgrade)) = websocket_upgrade {
+    let (interaction, context, plugins) = {
+      let ms = mock_server.lock().unwrap();
+      (websocket::find_interaction(&pact_request, &ms.websocket_interactions), generator_context(&ms), ms.plugins.clone())
+    };
+    if let Some(interaction) = interaction {
+      return Ok(websocket::accept_upgrade(&key, interaction, on_upgrade, matches, context, plugins, read_timeout));
+    }
+  }
+
+  let match_result = {
+    let mut ms = mock_server.lock().unwrap();
+    let MockServer { plugins, sequences, config, .. } = ms.deref_mut();
+    with_plugins(plugins, || match_request_with_config(&pact_request, pact.interactions(), sequences, config))
+  };
+  let match_result = with_query_encoding_mismatches(match_result, query_mismatches);
 
   matches.lock().unwrap().push(match_result.clone());
+  if !match_result.matched() {
+    let mut ms = mock_server.lock().unwrap();
+    if !ms.config.cors_preflight || !match_result.cors_preflight() {
+      ms.metrics.unmatched += 1;
+    }
+  }
+
+  if let MatchResult::RequestMatch(ref request, ref response) = match_result {
+    let (timeout, faults) = {
+      let mut ms = mock_server.lock().unwrap();
+      ms.record_match(&pact_request, request, response);
+      let timeout = if ms.config.simulate_slow_responses {
+        ms.interaction_timeout(request, response)
+      } else {
+        None
+      };
+      (timeout, ms.interaction_faults(request, response))
+    };
+    if let Some(timeout) = timeout {
+      info!("Simulating a slow response, delaying the response by {:?}", timeout);
+      tokio::time::sleep(timeout).await;
+    }
+    if let Some(delay) = faults.delay {
+      info!("Injecting a delay of {:?} into the response", delay);
+      tokio::time::sleep(delay).await;
+    }
+    if faults.reset_rate > 0.0 && rand::random::<f64>() < faults.reset_rate {
+      info!("Injecting a connection reset instead of the response");
+      return Err(InteractionError::SimulatedConnectionReset);
+    }
+    if faults.error_rate > 0.0 && rand::random::<f64>() < faults.error_rate {
+      info!("Injecting a 500 response instead of the response");
+      return Err(InteractionError::SimulatedError);
+    }
+  }
 
   match_result_to_hyper_response(&pact_request, match_result, mock_server)
 }
 
 // TODO: Should instead use some form of X-Pact headers
-fn handle_mock_request_error(result: Result<Response<Body>, InteractionError>) -> Result<Response<Body>, Error> {
+fn handle_mock_request_error(result: Result<Response<Body>, InteractionError>) -> Result<Response<Body>, io::Error> {
     match result {
         Ok(response) => Ok(response),
+        // Returning an error from the service makes hyper close the connection without a response
+        Err(InteractionError::SimulatedConnectionReset) => Err(io::Error::new(io::ErrorKind::ConnectionReset,
+          "Simulated connection reset")),
         Err(error) => {
             let response = match error {
                 InteractionError::RequestHeaderEncodingError => Response::builder()
@@ -268,47 +514,138 @@ fn handle_mock_request_error(result: Result<Response<Body>, InteractionError>) -
                 InteractionError::RequestBodyError => Response::builder()
                     .status(500)
                     .body(Body::from("Could not process request body")),
+                InteractionError::RequestTimeout => Response::builder()
+                    .status(408)
+                    .body(Body::from("Timed out reading the request body")),
                 InteractionError::ResponseBodyError => Response::builder()
                     .status(500)
                     .body(Body::from("Could not process response body")),
                 InteractionError::ResponseHeaderEncodingError => Response::builder()
                     .status(500)
-                    .body(Body::from("Could not set response header"))
+                    .body(Body::from("Could not set response header")),
+                InteractionError::SimulatedError => Response::builder()
+                    .status(500)
+                    .header(hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                    .header(hyper::header::CONTENT_TYPE, "application/json; charset=utf-8")
+                    .header("X-Pact", "Simulated-Error")
+                    .body(Body::from(json!({ "error": "Simulated server error" }).to_string())),
+                InteractionError::SimulatedConnectionReset => unreachable!()
             };
             Ok(response.unwrap())
         }
     }
 }
 
+/// Connection to the mock server, which holds a permit from the connection limit (if there is
+/// one) until it is closed
+struct MockServerConnection<S> {
+  stream: S,
+  _permit: Option<OwnedSemaphorePermit>
+}
+
+impl <S: AsyncRead + Unpin> AsyncRead for MockServerConnection<S> {
+  fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+  }
+}
+
+impl <S: AsyncWrite + Unpin> AsyncWrite for MockServerConnection<S> {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+  }
+
+  fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+  }
+}
+
+pub(crate) async fn connection_permit(limit: &Option<Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+  match limit {
+    Some(semaphore) => {
+      if semaphore.available_permits() == 0 {
+        debug!("Maximum number of connections reached, waiting for a connection to be closed");
+      }
+      semaphore.clone().acquire_owned().await.ok()
+    },
+    None => None
+  }
+}
+
+// Taken from https://github.com/ctz/hyper-rustls/blob/master/examples/server.rs
+struct HyperAcceptor<C> {
+  stream: Pin<Box<dyn Stream<Item = Result<C, io::Error>> + Send>>
+}
+
+impl <C: AsyncRead + AsyncWrite + Unpin> hyper::server::accept::Accept for HyperAcceptor<C> {
+  type Conn = C;
+  type Error = io::Error;
+
+  fn poll_accept(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context,
+  ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+    self.as_mut().stream.poll_next_unpin(cx)
+  }
+}
+
+fn connection_options(mock_server: &Arc<Mutex<MockServer>>) -> (ConnectionOptions, Option<Arc<Semaphore>>) {
+  let options = mock_server.lock().unwrap().config.connections.clone();
+  let limit = options.max_connections.map(|max| Arc::new(Semaphore::new(max)));
+  (options, limit)
+}
+
 // Create and bind the server, but do not start it.
 // Returns a future that drives the server.
-// The reason that the function itself is still async (even if it performs
-// no async operations) is that it needs a tokio context to be able to call try_bind.
 pub(crate) async fn create_and_bind(
-  pact: RequestResponsePact,
   addr: SocketAddr,
   shutdown: impl std::future::Future<Output = ()>,
   matches: Arc<Mutex<Vec<MatchResult>>>,
   mock_server: Arc<Mutex<MockServer>>
-) -> Result<(impl std::future::Future<Output = ()>, SocketAddr), hyper::Error> {
-  let pact = Arc::new(pact);
+) -> Result<(impl std::future::Future<Output = ()>, SocketAddr), io::Error> {
+  let (options, limit) = connection_options(&mock_server);
 
-  let server = Server::try_bind(&addr)?
+  let tcp = TcpListener::bind(&addr).await?;
+  let socket_addr = tcp.local_addr()?;
+  let tcp_stream = stream::unfold((Arc::new(tcp), limit), |(listener, limit)| {
+    async move {
+      let permit = connection_permit(&limit).await;
+      loop {
+        match listener.accept().await {
+          Ok((socket, _)) => {
+            let connection = MockServerConnection { stream: socket, _permit: permit };
+            return Some((Ok(connection), (listener.clone(), limit.clone())));
+          },
+          Err(err) => {
+            // Errors like running out of file handles should not stop the server, so wait a bit and try again
+            error!("Failed to accept connection - {:?}", err);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+          }
+        }
+      }
+    }
+  });
+
+  let server = Server::builder(HyperAcceptor {
+    stream: tcp_stream.boxed()
+  })
+    .http1_keepalive(options.keep_alive)
     .serve(make_service_fn(move |_| {
-      let pact = pact.clone();
       let matches = matches.clone();
       let mock_server = mock_server.clone();
 
       async {
         Ok::<_, hyper::Error>(
           service_fn(move |req| {
-            let pact = pact.clone();
             let matches = matches.clone();
             let mock_server = mock_server.clone();
 
             async {
               handle_mock_request_error(
-                handle_request(req, pact, matches, mock_server).await
+                handle_request(req, None, matches, mock_server).await
               )
             }
           })
@@ -316,8 +653,6 @@ pub(crate) async fn create_and_bind(
       }
     }));
 
-  let socket_addr = server.local_addr();
-
   Ok((
       // This is the future that drives the server:
       async {
@@ -329,65 +664,39 @@ pub(crate) async fn create_and_bind(
   ))
 }
 
-// Taken from https://github.com/ctz/hyper-rustls/blob/master/examples/server.rs
-struct HyperAcceptor {
-  stream: Pin<Box<dyn Stream<Item = Result<TlsStream<TcpStream>, io::Error>> + Send>>
-}
-
-impl hyper::server::accept::Accept for HyperAcceptor {
-  type Conn = TlsStream<TcpStream>;
-  type Error = io::Error;
-
-  fn poll_accept(
-    mut self: Pin<&mut Self>,
-    cx: &mut Context,
-  ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
-    self.as_mut().stream.poll_next_unpin(cx)
-  }
-}
-
 pub(crate) async fn create_and_bind_tls(
-  pact: RequestResponsePact,
   addr: SocketAddr,
   shutdown: impl std::future::Future<Output = ()>,
   matches: Arc<Mutex<Vec<MatchResult>>>,
   tls_cfg: ServerConfig,
   mock_server: Arc<Mutex<MockServer>>
 ) -> Result<(impl std::future::Future<Output = ()>, SocketAddr), io::Error> {
-  let pact = Arc::new(pact);
+  let (options, limit) = connection_options(&mock_server);
 
   let tcp = TcpListener::bind(&addr).await?;
   let socket_addr = tcp.local_addr()?;
-  let tls_acceptor = Arc::new(TlsAcceptor::from(Arc::new(tls_cfg)));
-  let tls_stream = stream::unfold((Arc::new(tcp), tls_acceptor.clone()), |(listener, acceptor)| {
-    async move {
-      let (socket, _) = listener.accept().await.map_err(|err| {
-        error!("Failed to accept TLS connection - {:?}", err);
-        err
-      }).ok()?;
-      let stream = acceptor.accept(socket);
-      Some((stream.await, (listener.clone(), acceptor.clone())))
-    }
-  });
+  let tls_stream = accept_tls_connections(tcp, tls_cfg, limit)
+    .map_ok(|(stream, permit)| MockServerConnection { stream, _permit: permit });
 
   let server = Server::builder(HyperAcceptor {
     stream: tls_stream.boxed()
   })
-    .serve(make_service_fn(move |_| {
-      let pact = pact.clone();
+    .http1_keepalive(options.keep_alive)
+    .serve(make_service_fn(move |conn: &MockServerConnection<TlsStream<TcpStream>>| {
       let matches = matches.clone();
       let mock_server = mock_server.clone();
+      let sni_host = conn.stream.get_ref().1.get_sni_hostname().map(|host| host.to_string());
 
       async {
         Ok::<_, hyper::Error>(
           service_fn(move |req| {
-            let pact = pact.clone();
             let matches = matches.clone();
             let mock_server = mock_server.clone();
+            let sni_host = sni_host.clone();
 
             async {
               handle_mock_request_error(
-                handle_request(req, pact, matches, mock_server).await
+                handle_request(req, sni_host, matches, mock_server).await
               )
             }
           })
@@ -422,7 +731,6 @@ mod tests {
     let matches = Arc::new(Mutex::new(vec![]));
 
     let (future, _) = create_and_bind(
-      RequestResponsePact::default(),
       ([0, 0, 0, 0], 0 as u16).into(),
       async {
           shutdown_rx.await.ok();
@@ -457,4 +765,38 @@ mod tests {
       "content-type".to_string() => vec!["text/plain".to_string()]
     })));
   }
+
+  #[test]
+  fn cors_preflight_response_echos_the_origin_and_requested_headers() {
+    let request = Request {
+      method: "OPTIONS".to_string(),
+      headers: Some(hashmap! {
+        "Origin".to_string() => vec!["http://localhost:9000".to_string()],
+        "Access-Control-Request-Method".to_string() => vec!["PURGE".to_string()],
+        "Access-Control-Request-Headers".to_string() => vec!["x-token".to_string()]
+      }),
+      .. Request::default()
+    };
+    let response = cors_preflight_response(&request).unwrap();
+    expect!(response.status().as_u16()).to(be_equal_to(204));
+    let headers = response.headers();
+    expect!(headers.get(hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap().to_str().unwrap())
+      .to(be_equal_to("http://localhost:9000"));
+    expect!(headers.get(hyper::header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap().to_str().unwrap())
+      .to(be_equal_to("true"));
+    expect!(headers.get(hyper::header::ACCESS_CONTROL_ALLOW_METHODS).unwrap().to_str().unwrap())
+      .to(be_equal_to("PURGE, GET, HEAD, POST, PUT, DELETE, CONNECT, OPTIONS, TRACE, PATCH"));
+    expect!(headers.get(hyper::header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap().to_str().unwrap())
+      .to(be_equal_to("x-token, *"));
+  }
+
+  #[test]
+  fn cors_preflight_response_allows_any_origin_if_none_is_provided() {
+    let request = Request { method: "OPTIONS".to_string(), .. Request::default() };
+    let response = cors_preflight_response(&request).unwrap();
+    let headers = response.headers();
+    expect!(headers.get(hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap().to_str().unwrap())
+      .to(be_equal_to("*"));
+    expect!(headers.get(hyper::header::ACCESS_CONTROL_ALLOW_CREDENTIALS)).to(be_none());
+  }
 }
@@ -18,14 +18,19 @@ use uuid::Uuid;
 use pact_matching::models::{load_pact_from_json, Pact, RequestResponsePact};
 use pact_matching::s;
 
-use crate::mock_server::MockServerConfig;
+use crate::mock_server::{BindAddress, MockServerConfig, ShutdownOptions, ShutdownResult};
 use crate::server_manager::ServerManager;
 
 pub mod matching;
+pub mod metrics;
 pub mod mock_server;
+pub mod multi_pact;
+pub mod recorder;
 pub mod server_manager;
 mod hyper_server;
 pub mod tls;
+pub mod watcher;
+mod websocket;
 
 /// Mock server errors
 #[derive(thiserror::Error, Debug)]
@@ -94,6 +99,89 @@ pub fn start_mock_server_with_config(
     .map(|addr| addr.port() as i32)
 }
 
+/// Starts a mock server with the given ID and pact, bound to the given address. The ID needs to be
+/// unique. The bind address can have a specific port, or a range of ports in which case the first
+/// free port in the range is used. Returns the address and port that the mock server is running
+/// on wrapped in a `Result`.
+///
+/// * `id` - Unique ID for the mock server.
+/// * `pact` - Pact model to use for the mock server.
+/// * `bind_address` - Address and port(s) that the server should listen on.
+/// * `config` - Configuration for the mock server
+///
+/// # Errors
+///
+/// An error with a message will be returned in the following conditions:
+///
+/// - If a mock server is not able to be started
+/// - If none of the ports in the range are free
+pub fn start_mock_server_with_bind_address(
+  id: String,
+  pact: Box<dyn Pact>,
+  bind_address: &BindAddress,
+  config: MockServerConfig
+) -> Result<std::net::SocketAddr, String> {
+  MANAGER.lock().unwrap()
+    .get_or_insert_with(ServerManager::new)
+    .start_mock_server_with_bind_address(id, pact, bind_address, config)
+}
+
+/// Starts a mock server with the given ID that serves the interactions from all the pacts, bound
+/// to the given address (see `start_mock_server_with_bind_address`). The interactions are combined
+/// with `multi_pact::combine_pacts`, so the mock server can be used as a shared stub of a provider
+/// for a number of consumers. Returns the address and port that the mock server is running on
+/// wrapped in a `Result`.
+///
+/// * `id` - Unique ID for the mock server.
+/// * `pacts` - Pacts to use for the mock server.
+/// * `bind_address` - Address and port(s) that the server should listen on.
+/// * `config` - Configuration for the mock server
+///
+/// # Errors
+///
+/// An error with a message will be returned in the following conditions:
+///
+/// - If the pacts can not be combined
+/// - If a mock server is not able to be started
+/// - If none of the ports in the range are free
+pub fn start_mock_server_for_pacts(
+  id: String,
+  pacts: &[Box<dyn Pact>],
+  bind_address: &BindAddress,
+  config: MockServerConfig
+) -> Result<std::net::SocketAddr, String> {
+  let pact = multi_pact::combine_pacts(pacts)?;
+  start_mock_server_with_bind_address(id, pact.boxed(), bind_address, config)
+}
+
+/// Starts a TLS mock server with the given ID and pact, bound to the given address (see
+/// `start_mock_server_with_bind_address`). Returns the address and port that the mock server is
+/// running on wrapped in a `Result`.
+///
+/// * `id` - Unique ID for the mock server.
+/// * `pact` - Pact model to use for the mock server.
+/// * `bind_address` - Address and port(s) that the server should listen on.
+/// * `tls` - TLS config.
+/// * `config` - Configuration for the mock server
+///
+/// # Errors
+///
+/// An error with a message will be returned in the following conditions:
+///
+/// - If a mock server is not able to be started
+/// - If none of the ports in the range are free
+pub fn start_tls_mock_server_with_bind_address(
+  id: String,
+  pact: Box<dyn Pact>,
+  bind_address: &BindAddress,
+  tls: &ServerConfig,
+  config: MockServerConfig
+) -> Result<std::net::SocketAddr, String> {
+  MANAGER.lock().unwrap()
+    .get_or_insert_with(ServerManager::new)
+    .start_tls_mock_server_with_bind_address(id, pact, bind_address, tls, config)
+}
+
 /// Starts a TLS mock server with the given ID, pact and port number. The ID needs to be unique. A port
 /// number of 0 will result in an auto-allocated port by the operating system. Returns the port
 /// that the mock server is running on wrapped in a `Result`.
@@ -227,6 +315,56 @@ pub fn mock_server_mismatches(mock_server_port: i32) -> Option<std::string::Stri
         })
 }
 
+/// Gets the metrics collected by the mock server with the provided port as a JSON document. The
+/// percentiles of the response times are in milliseconds. Returns None if there is no mock server
+/// running on that port.
+pub fn mock_server_metrics(mock_server_port: i32) -> Option<std::string::String> {
+  MANAGER.lock().unwrap()
+    .get_or_insert_with(ServerManager::new)
+    .find_mock_server_by_port_mut(mock_server_port as u16, &|mock_server| {
+      mock_server.metrics.to_json().to_string()
+    })
+}
+
+/// Gets the journal of the requests that matched interactions on the mock server with the
+/// provided port, in the order they were received, as a JSON document. The journal is only
+/// recorded if `record_journal` is set in the config of the mock server. Returns None if there is
+/// no mock server running on that port.
+pub fn mock_server_journal(mock_server_port: i32) -> Option<std::string::String> {
+  MANAGER.lock().unwrap()
+    .get_or_insert_with(ServerManager::new)
+    .find_mock_server_by_port_mut(mock_server_port as u16, &|mock_server| {
+      let journal = mock_server.journal().iter()
+        .map(|entry| entry.to_json())
+        .collect::<Vec<serde_json::Value>>();
+      json!(journal).to_string()
+    })
+}
+
+/// Resets the state of the mock server with the provided port, clearing the recorded matches,
+/// mismatches and journal and resetting the sequences of interactions, so it can be reused for
+/// another test. Returns false if there is no mock server running on that port.
+pub fn reset_mock_server(mock_server_port: i32) -> bool {
+  MANAGER.lock().unwrap()
+    .get_or_insert_with(ServerManager::new)
+    .find_mock_server_by_port_mut(mock_server_port as u16, &|mock_server| {
+      mock_server.reset();
+    })
+    .is_some()
+}
+
+/// Resets the sequences of interactions with the same request on the mock server with the
+/// provided port, so the next request will get the response from the first interaction again.
+/// Returns false if there is no mock server running on that port.
+pub fn reset_mock_server_sequences(mock_server_port: i32) -> bool {
+  MANAGER.lock().unwrap()
+    .get_or_insert_with(ServerManager::new)
+    .find_mock_server_by_port_mut(mock_server_port as u16, &|mock_server| {
+      mock_server.reset_sequences();
+    })
+    .is_some()
+}
+
 /// Write Pact File Errors
 pub enum WritePactFileErr {
   /// IO Error occurred
@@ -276,5 +414,21 @@ pub fn shutdown_mock_server(mock_server_port: i32) -> bool {
     .shutdown_mock_server_by_port(mock_server_port as u16)
 }
 
+/// Gracefully shuts down the mock server with the provided port. The mock server stops accepting
+/// new connections, and waits for any in-flight requests to complete (up to the timeout in the
+/// options). If `write_pact` is set in the options, the pact file is then written if all the
+/// interactions were matched.
+///
+/// Returns the mismatches and if the pact file was written. Returns an `Err` if there is no mock
+/// server running on that port, or the pact file could not be written.
+pub fn shutdown_mock_server_gracefully(
+  mock_server_port: i32,
+  options: &ShutdownOptions
+) -> Result<ShutdownResult, String> {
+  MANAGER.lock().unwrap()
+    .get_or_insert_with(ServerManager::new)
+    .shutdown_mock_server_gracefully_by_port(mock_server_port as u16, options)
+}
+
 #[cfg(test)]
 mod tests;
@@ -3,17 +3,21 @@
 //! against a list of potential interactions.
 //!
 
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
-use std::sync::Arc;
 
 use itertools::Itertools;
+use log::*;
 use serde_json::json;
 
-use pact_matching::Mismatch;
+use pact_matching::{DiffConfig, Mismatch, RequestMatchResult};
+use pact_matching::locale::LocaleOptions;
 use pact_matching::models::{Interaction, Request, RequestResponseInteraction, Response};
-use pact_matching::s;
 use pact_models::PactSpecification;
 
+use crate::mock_server::{MockServerConfig, StubOptions};
+
 /// Enum to define a match result
 #[derive(Debug, Clone, PartialEq)]
 pub enum MatchResult {
@@ -49,7 +53,7 @@ impl MatchResult {
     /// Returns true if this is an unexpected OPTIONS request
     pub fn cors_preflight(&self) -> bool {
       match self {
-        MatchResult::RequestNotFound(req) => req.method == "OPTIONS",
+        MatchResult::RequestNotFound(req) => req.method.to_uppercase() == "OPTIONS",
         _ => false
       }
     }
@@ -107,29 +111,145 @@ fn mismatches_to_json(request: &Request, mismatches: &Vec<Mismatch>) -> serde_js
     })
 }
 
+/// Tracks the number of times each sequence of interactions has been invoked. A sequence is a
+/// group of interactions that have been marked as a sequence (see
+/// `MockServerConfig::sequence_interactions`) and have the same expected request. Each time the
+/// request is received, the response from the next interaction in the group (in the order they
+/// are in the pact) is returned. Once the end of the sequence is reached, the last response is
+/// repeated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseSequences {
+  invocations: HashMap<String, usize>
+}
+
+impl ResponseSequences {
+  /// Returns the number of times the sequence for the expected request has been invoked
+  pub fn invocations(&self, request: &Request) -> usize {
+    self.invocations.get(&sequence_key(request)).cloned().unwrap_or_default()
+  }
+
+  /// Resets all the sequences, so the next request will get the first response again
+  pub fn reset(&mut self) {
+    self.invocations.clear();
+  }
+
+  fn next_index(&mut self, key: String, len: usize) -> usize {
+    let count = self.invocations.entry(key).or_insert(0);
+    let index = min(*count, len - 1);
+    *count += 1;
+    index
+  }
+}
+
+fn sequence_key(request: &Request) -> String {
+  request.to_json(&PactSpecification::V3).to_string()
+}
+
 ///
 /// Matches a request against a list of interactions
 ///
 pub fn match_request(req: &Request, interactions: Vec<&dyn Interaction>) -> MatchResult {
-  let mut match_results = interactions
+  match_request_in_sequence(req, interactions, &mut ResponseSequences::default(), &HashSet::new())
+}
+
+///
+/// Matches a request against a list of interactions. If there are a number of interactions with
+/// the same request whose descriptions are in `sequence_interactions`, the response is selected
+/// based on the number of times the request has already been received (see `ResponseSequences`).
+/// Otherwise, the response from the first matching interaction is returned.
+///
+pub fn match_request_in_sequence(
+  req: &Request,
+  interactions: Vec<&dyn Interaction>,
+  sequences: &mut ResponseSequences,
+  sequence_interactions: &HashSet<String>
+) -> MatchResult {
+  match_request_with_acceptance(req, interactions, sequences, &|result| result.all_matched(),
+    &|_| (false, DiffConfig::NoUnexpectedKeys), &|interaction| sequence_interactions.contains(&interaction.description))
+}
+
+///
+/// Matches a request against a list of interactions using the mock server config. If the config
+/// has stub options, this is the same as `match_request_as_stub`, otherwise it is the same as
+/// `match_request_in_sequence`. The headers of the request are matched strictly, and unexpected
+/// keys in the request body are allowed, for the interactions set in the config.
+///
+pub fn match_request_with_config(
+  req: &Request,
+  interactions: Vec<&dyn Interaction>,
+  sequences: &mut ResponseSequences,
+  config: &MockServerConfig
+) -> MatchResult {
+  let match_options = |interaction: &RequestResponseInteraction|
+    (config.strict_headers_for(interaction), config.body_config_for(interaction));
+  let in_sequence = |interaction: &RequestResponseInteraction| config.in_sequence(interaction);
+  match &config.stub {
+    Some(options) => match_request_with_acceptance(req, interactions, sequences,
+      &|result| options.accepts(result), &match_options, &in_sequence),
+    None => match_request_with_acceptance(req, interactions, sequences,
+      &|result| result.all_matched(), &match_options, &in_sequence)
+  }
+}
+
+///
+/// Matches a request against a list of interactions for a stub server. The response from the
+/// closest matching interaction is returned, as long as the only mismatches are in the parts of the
+/// request that the stub options ignore.
+///
+pub fn match_request_as_stub(
+  req: &Request,
+  interactions: Vec<&dyn Interaction>,
+  options: &StubOptions
+) -> MatchResult {
+  match_request_with_acceptance(req, interactions, &mut ResponseSequences::default(),
+    &|result| options.accepts(result), &|_| (false, DiffConfig::NoUnexpectedKeys), &|_| false)
+}
+
+fn match_request_with_acceptance(
+  req: &Request,
+  interactions: Vec<&dyn Interaction>,
+  sequences: &mut ResponseSequences,
+  accept: &dyn Fn(&RequestMatchResult) -> bool,
+  match_options: &dyn Fn(&RequestResponseInteraction) -> (bool, DiffConfig),
+  in_sequence: &dyn Fn(&RequestResponseInteraction) -> bool
+) -> MatchResult {
+  let match_results = interactions
     .into_iter()
-    .filter(|i| i.is_request_response())
-    .map(|i| {
-      let interaction = i.as_request_response().unwrap();
-      (i.clone(), pact_matching::match_request(interaction.request.clone(), req.clone()))
+    .filter_map(|i| i.as_request_response())
+    .map(|interaction| {
+      let (strict_headers, body_config) = match_options(&interaction);
+      let result = pact_matching::match_request_with_options(interaction.request.clone(), req.clone(),
+        &LocaleOptions::default(), strict_headers, &body_config);
+      (interaction, result)
     })
     .sorted_by(|(_, i1), (_, i2)| {
       Ord::cmp(&i2.score(), &i1.score())
-    });
-  match match_results.next() {
+    })
+    .collect::<Vec<_>>();
+  if let Some((interaction, _)) = match_results.iter().find(|(_, result)| accept(result)) {
+    if !in_sequence(interaction) {
+      return MatchResult::RequestMatch(interaction.request.clone(), interaction.response.clone());
+    }
+    let key = sequence_key(&interaction.request);
+    let sequence = match_results.iter()
+      .filter(|(i, r)| accept(r) && in_sequence(i) && sequence_key(&i.request) == key)
+      .map(|(i, _)| i)
+      .collect::<Vec<_>>();
+    let index = sequences.next_index(key, sequence.len());
+    if sequence.len() > 1 {
+      debug!("Request matched interaction {} of {} in the sequence ('{}')", index + 1,
+        sequence.len(), sequence[index].description);
+    }
+    let interaction = sequence[index];
+    return MatchResult::RequestMatch(interaction.request.clone(), interaction.response.clone());
+  }
+
+  match match_results.first() {
     Some((interaction, result)) => {
-      let request_response_interaction = interaction.as_request_response().unwrap();
-      if result.all_matched() {
-        MatchResult::RequestMatch(request_response_interaction.request, request_response_interaction.response)
-      } else if result.method_or_path_mismatch() {
+      if result.method_or_path_mismatch() {
         MatchResult::RequestNotFound(req.clone())
       } else {
-        MatchResult::RequestMismatch(request_response_interaction.request, result.mismatches())
+        MatchResult::RequestMismatch(interaction.request.clone(), result.mismatches())
       }
     },
     None => MatchResult::RequestNotFound(req.clone())
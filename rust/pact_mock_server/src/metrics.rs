@@ -0,0 +1,214 @@
+//!
+//! Metrics collected by the mock servers. The metrics can be returned as JSON, or in the
+//! Prometheus text format so long running mock servers can be monitored by scraping the
+//! `/__metrics` endpoint.
+//!
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Path of the metrics endpoint of the mock servers
+pub const METRICS_PATH: &str = "/__metrics";
+
+/// Number of the most recent response times that are kept to calculate the percentiles from
+const MAX_RESPONSE_TIME_SAMPLES: usize = 1000;
+
+/// Percentiles of the response times that are reported, with their names in the JSON
+const PERCENTILES: [(f64, &str); 3] = [(0.5, "p50"), (0.9, "p90"), (0.99, "p99")];
+
+/// Response times of the mock server. Only the most recent response times are kept for the
+/// percentiles, while the count and total are for all the responses.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ResponseTimes {
+  samples: VecDeque<u64>,
+  count: u64,
+  total: u64
+}
+
+impl ResponseTimes {
+  /// Records the time taken to respond to a request
+  pub fn record(&mut self, time: Duration) {
+    let micros = time.as_micros() as u64;
+    if self.samples.len() == MAX_RESPONSE_TIME_SAMPLES {
+      self.samples.pop_front();
+    }
+    self.samples.push_back(micros);
+    self.count += 1;
+    self.total += micros;
+  }
+
+  /// Number of responses that have been recorded
+  pub fn count(&self) -> u64 {
+    self.count
+  }
+
+  /// Total time taken for all the recorded responses
+  pub fn total(&self) -> Duration {
+    Duration::from_micros(self.total)
+  }
+
+  /// Returns the given percentile (0.0 to 1.0) of the recent response times, using the nearest
+  /// rank. Returns None if no responses have been recorded.
+  pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+    if self.samples.is_empty() {
+      return None;
+    }
+    let mut samples: Vec<u64> = self.samples.iter().cloned().collect();
+    samples.sort_unstable();
+    let rank = (percentile * samples.len() as f64).ceil() as usize;
+    let index = rank.clamp(1, samples.len()) - 1;
+    Some(Duration::from_micros(samples[index]))
+  }
+}
+
+/// Metrics for the mock server
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct MockServerMetrics {
+  /// Total requests
+  pub requests: usize,
+  /// Requests that matched an interaction
+  pub matched: usize,
+  /// Requests that did not match any interaction, or that matched with mismatches
+  pub unmatched: usize,
+  /// Requests that failed with an error (i.e. the request body could not be read, or an error
+  /// was injected into the response)
+  pub errors: usize,
+  /// Number of requests that matched each interaction, keyed by the interaction description
+  pub interactions: BTreeMap<String, usize>,
+  /// Times taken to respond to the requests
+  #[serde(skip)]
+  pub response_times: ResponseTimes
+}
+
+impl MockServerMetrics {
+  /// Records a request that matched the interaction with the given description
+  pub fn record_match(&mut self, description: &str) {
+    self.matched += 1;
+    *self.interactions.entry(description.to_string()).or_insert(0) += 1;
+  }
+
+  /// Converts the metrics to JSON. The percentiles of the response times are in milliseconds.
+  pub fn to_json(&self) -> serde_json::Value {
+    let mut json = json!(self);
+    let response_times: serde_json::Map<String, serde_json::Value> = PERCENTILES.iter()
+      .filter_map(|(percentile, name)| self.response_times.percentile(*percentile)
+        .map(|time| (name.to_string(), json!(time.as_secs_f64() * 1000.0))))
+      .collect();
+    json["responseTimes"] = serde_json::Value::Object(response_times);
+    json
+  }
+
+  /// Formats the metrics in the Prometheus text format, labelled with the mock server ID
+  pub fn to_prometheus(&self, id: &str) -> String {
+    let id = escape_label(id);
+    let mut text = String::new();
+    let counters = [
+      ("pact_mock_server_requests_total", "Total requests received by the mock server", self.requests),
+      ("pact_mock_server_matched_requests_total", "Requests that matched an interaction", self.matched),
+      ("pact_mock_server_unmatched_requests_total", "Requests that did not match an interaction", self.unmatched),
+      ("pact_mock_server_errors_total", "Requests that failed with an error", self.errors)
+    ];
+    for (name, help, value) in counters.iter() {
+      let _ = writeln!(text, "# HELP {} {}", name, help);
+      let _ = writeln!(text, "# TYPE {} counter", name);
+      let _ = writeln!(text, "{}{{mock_server=\"{}\"}} {}", name, id, value);
+    }
+
+    let _ = writeln!(text, "# HELP pact_mock_server_interaction_requests_total Requests that matched each interaction");
+    let _ = writeln!(text, "# TYPE pact_mock_server_interaction_requests_total counter");
+    for (description, count) in &self.interactions {
+      let _ = writeln!(text, "pact_mock_server_interaction_requests_total{{mock_server=\"{}\",interaction=\"{}\"}} {}",
+        id, escape_label(description), count);
+    }
+
+    let _ = writeln!(text, "# HELP pact_mock_server_response_time_seconds Time taken to respond to the requests");
+    let _ = writeln!(text, "# TYPE pact_mock_server_response_time_seconds summary");
+    for (percentile, _) in PERCENTILES.iter() {
+      if let Some(time) = self.response_times.percentile(*percentile) {
+        let _ = writeln!(text, "pact_mock_server_response_time_seconds{{mock_server=\"{}\",quantile=\"{}\"}} {}",
+          id, percentile, time.as_secs_f64());
+      }
+    }
+    let _ = writeln!(text, "pact_mock_server_response_time_seconds_sum{{mock_server=\"{}\"}} {}", id,
+      self.response_times.total().as_secs_f64());
+    let _ = writeln!(text, "pact_mock_server_response_time_seconds_count{{mock_server=\"{}\"}} {}", id,
+      self.response_times.count());
+    text
+  }
+}
+
+fn escape_label(value: &str) -> String {
+  value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn response_times_percentiles() {
+    let mut times = ResponseTimes::default();
+    expect!(times.percentile(0.5)).to(be_none());
+    for millis in 1..=100 {
+      times.record(Duration::from_millis(millis));
+    }
+    expect!(times.percentile(0.5)).to(be_some().value(Duration::from_millis(50)));
+    expect!(times.percentile(0.9)).to(be_some().value(Duration::from_millis(90)));
+    expect!(times.percentile(0.99)).to(be_some().value(Duration::from_millis(99)));
+    expect!(times.percentile(0.0)).to(be_some().value(Duration::from_millis(1)));
+    expect!(times.count()).to(be_equal_to(100));
+    expect!(times.total()).to(be_equal_to(Duration::from_millis(5050)));
+  }
+
+  #[test]
+  fn response_times_only_keeps_the_recent_samples() {
+    let mut times = ResponseTimes::default();
+    for _ in 0..MAX_RESPONSE_TIME_SAMPLES {
+      times.record(Duration::from_millis(100));
+    }
+    for _ in 0..MAX_RESPONSE_TIME_SAMPLES {
+      times.record(Duration::from_millis(1));
+    }
+    expect!(times.percentile(0.99)).to(be_some().value(Duration::from_millis(1)));
+    expect!(times.count()).to(be_equal_to(2 * MAX_RESPONSE_TIME_SAMPLES as u64));
+  }
+
+  #[test]
+  fn metrics_to_json() {
+    let mut metrics = MockServerMetrics { requests: 3, unmatched: 1, .. MockServerMetrics::default() };
+    metrics.record_match("get the users");
+    metrics.record_match("get the users");
+    metrics.response_times.record(Duration::from_millis(2));
+    expect!(metrics.to_json()).to(be_equal_to(json!({
+      "requests": 3,
+      "matched": 2,
+      "unmatched": 1,
+      "errors": 0,
+      "interactions": { "get the users": 2 },
+      "responseTimes": { "p50": 2.0, "p90": 2.0, "p99": 2.0 }
+    })));
+  }
+
+  #[test]
+  fn metrics_to_prometheus() {
+    let mut metrics = MockServerMetrics { requests: 2, errors: 1, .. MockServerMetrics::default() };
+    metrics.record_match("get the \"users\"");
+    metrics.response_times.record(Duration::from_millis(250));
+    let text = metrics.to_prometheus("abc");
+    expect!(text.contains("# TYPE pact_mock_server_requests_total counter\npact_mock_server_requests_total{mock_server=\"abc\"} 2\n"))
+      .to(be_true());
+    expect!(text.contains("pact_mock_server_errors_total{mock_server=\"abc\"} 1\n")).to(be_true());
+    expect!(text.contains("pact_mock_server_interaction_requests_total{mock_server=\"abc\",interaction=\"get the \\\"users\\\"\"} 1\n"))
+      .to(be_true());
+    expect!(text.contains("pact_mock_server_response_time_seconds{mock_server=\"abc\",quantile=\"0.5\"} 0.25\n")).to(be_true());
+    expect!(text.contains("pact_mock_server_response_time_seconds_count{mock_server=\"abc\"} 1\n")).to(be_true());
+  }
+}
@@ -4,28 +4,381 @@
 //!
 
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::ops::DerefMut;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use log::*;
 use rustls::ServerConfig;
-use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use pact_matching::models::{Pact, RequestResponseInteraction, RequestResponsePact, write_pact, Request};
+use pact_matching::models::{MergeStrategy, Pact, RequestResponseInteraction, RequestResponsePact, write_pact, Request, Response};
 use pact_matching::models::ReadWritePact;
+use pact_matching::{DiffConfig, RequestMatchResult};
+use pact_matching::models::pact_files::{PactFileLayout, write_pact_to_directory};
+use pact_matching::models::v4::SynchronousMessages;
+use pact_matching::models::v4::websocket::{is_websocket_interaction, upgrade_request};
+use pact_matching::plugins::ContentPlugin;
+use pact_models::PactSpecification;
 
 use crate::hyper_server;
-use crate::matching::MatchResult;
+pub use crate::metrics::MockServerMetrics;
+use crate::matching::{MatchResult, ResponseSequences};
 use std::borrow::Borrow;
 
+/// Path of the admin route that resets the state of the mock server
+pub const RESET_PATH: &str = "/__admin/reset";
+
+/// Path of the liveness endpoint of the mock server
+pub const HEALTH_PATH: &str = "/healthz";
+
+/// Path of the readiness endpoint of the mock server
+pub const READY_PATH: &str = "/readyz";
+
 /// Mock server configuration
 #[derive(Debug, Default, Clone)]
 pub struct MockServerConfig {
-  /// If CORS Pre-Flight requests should be responded to
-  pub cors_preflight: bool
+  /// If CORS Pre-Flight requests should be responded to. When enabled, any OPTIONS request that
+  /// does not match an interaction will receive a permissive CORS response and will not be
+  /// reported as an unexpected request.
+  pub cors_preflight: bool,
+  /// If responses for interactions with a timeout should be delayed by that timeout. This can be
+  /// used to test how the consumer behaves when the provider is slow to respond.
+  pub simulate_slow_responses: bool,
+  /// Options for the connections made to the mock server
+  pub connections: ConnectionOptions,
+  /// Faults to inject into the responses of all the interactions
+  pub faults: FaultOptions,
+  /// Faults to inject into the responses of particular interactions, keyed by the interaction
+  /// description. These replace the global faults for those interactions.
+  pub interaction_faults: HashMap<String, FaultOptions>,
+  /// Runs the mock server as a stub server. Instead of failing on strict matching, the response
+  /// from the closest matching interaction is returned, ignoring mismatches in the parts of the
+  /// request set in the options.
+  pub stub: Option<StubOptions>,
+  /// If the headers of the requests must exactly match the headers of the interactions, so a
+  /// request with any additional headers (i.e. cookies) is a mismatch. Headers set by the HTTP
+  /// client or the transport (like `Host` and `Content-Length`) are ignored.
+  pub strict_headers: bool,
+  /// Descriptions of the interactions to match the request headers of strictly, when it is not
+  /// enabled for all the interactions with `strict_headers`
+  pub strict_header_interactions: HashSet<String>,
+  /// If the bodies of the requests can have keys that are not in the bodies of the interactions.
+  /// By default, any unexpected keys are a mismatch.
+  pub allow_unexpected_keys: bool,
+  /// Descriptions of the interactions to allow unexpected keys in the request bodies of, when it
+  /// is not enabled for all the interactions with `allow_unexpected_keys`
+  pub allow_unexpected_keys_interactions: HashSet<String>,
+  /// Descriptions of the interactions to return the responses of in sequence. When a number of
+  /// these interactions have the same request, each repeated request gets the response from the
+  /// next one, and each of them needs to receive a request. Any other interactions with the same
+  /// request are all matched by a single request, which gets the response from the first one.
+  pub sequence_interactions: HashSet<String>,
+  /// Seed for the random generators (RandomInt, RandomString, Uuid, etc.) used for the responses.
+  /// When set, the same response is generated for each request to an interaction, which makes
+  /// snapshots of the consumer tests reproducible.
+  pub generator_seed: Option<u64>,
+  /// Delay between the events of server-sent event (`text/event-stream`) responses. The events of
+  /// these responses are always streamed to the client one at a time, this sets how long to wait
+  /// before sending each event after the first one.
+  pub event_stream_delay: Option<Duration>,
+  /// If the mock server should record a journal of the requests that matched interactions, in the
+  /// order they were received. The journal can be used to assert the order of the interactions.
+  pub record_journal: bool,
+  /// If the mock server should serve its metrics in the Prometheus text format from the
+  /// `/__metrics` path, so it can be monitored when it is running as a long running stub server
+  pub metrics_endpoint: bool,
+  /// If the mock server should serve the admin routes (i.e. `POST /__admin/reset`), so its state
+  /// can be controlled over HTTP when one mock server is reused for a number of tests
+  pub admin_routes: bool,
+  /// If the mock server should serve the `/healthz` (liveness) and `/readyz` (readiness)
+  /// endpoints, so it can be run under Kubernetes probes. These take priority over any
+  /// interactions with the same paths.
+  pub health_endpoints: bool
+}
+
+impl MockServerConfig {
+  /// If the request headers must exactly match the headers of the interaction
+  pub fn strict_headers_for(&self, interaction: &RequestResponseInteraction) -> bool {
+    self.strict_headers || self.strict_header_interactions.contains(&interaction.description)
+  }
+
+  /// If the interaction is part of a sequence of interactions with the same request
+  pub fn in_sequence(&self, interaction: &RequestResponseInteraction) -> bool {
+    self.sequence_interactions.contains(&interaction.description)
+  }
+
+  /// How unexpected keys in the request body are treated for the interaction
+  pub fn body_config_for(&self, interaction: &RequestResponseInteraction) -> DiffConfig {
+    if self.allow_unexpected_keys || self.allow_unexpected_keys_interactions.contains(&interaction.description) {
+      DiffConfig::AllowUnexpectedKeys
+    } else {
+      DiffConfig::NoUnexpectedKeys
+    }
+  }
+}
+
+/// Parts of the request to ignore mismatches in when the mock server is running as a stub server.
+/// The method and path of the request must always match. Defaults to ignoring the body and query
+/// parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StubOptions {
+  /// Ignore mismatches in the request body
+  pub ignore_body: bool,
+  /// Ignore mismatches in the request headers
+  pub ignore_headers: bool,
+  /// Ignore mismatches in the query parameters
+  pub ignore_query: bool
+}
+
+impl Default for StubOptions {
+  fn default() -> Self {
+    StubOptions {
+      ignore_body: true,
+      ignore_headers: false,
+      ignore_query: true
+    }
+  }
+}
+
+impl StubOptions {
+  /// If the result of matching a request is close enough for the request to be served
+  pub fn accepts(&self, result: &RequestMatchResult) -> bool {
+    !result.method_or_path_mismatch() &&
+      (self.ignore_query || result.query.values().all(|m| m.is_empty())) &&
+      (self.ignore_headers || result.headers.values().all(|m| m.is_empty())) &&
+      (self.ignore_body || result.body.all_matched())
+  }
+}
+
+impl FromStr for StubOptions {
+  type Err = String;
+
+  /// Parses the stub options from either `true` (for the default options) or a comma separated
+  /// list of the parts of the request to ignore (`body`, `headers` and `query`)
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if s.trim() == "true" {
+      return Ok(StubOptions::default());
+    }
+    let mut options = StubOptions { ignore_body: false, ignore_headers: false, ignore_query: false };
+    for part in s.split(',').map(|part| part.trim()).filter(|part| !part.is_empty()) {
+      match part.to_lowercase().as_str() {
+        "body" => options.ignore_body = true,
+        "headers" => options.ignore_headers = true,
+        "query" => options.ignore_query = true,
+        _ => return Err(format!("'{}' is not a part of the request that can be ignored, it must be one of body, headers or query", part))
+      }
+    }
+    Ok(options)
+  }
+}
+
+/// Faults to inject into the responses of the mock server, so that consumers can test their
+/// timeout and retry logic. They only apply to requests that match an interaction.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FaultOptions {
+  /// Delay to add before the response is sent
+  pub delay: Option<Duration>,
+  /// Fraction (0.0 to 1.0) of the matched requests that will receive a 500 response instead of
+  /// the response from the interaction
+  pub error_rate: f64,
+  /// Fraction (0.0 to 1.0) of the matched requests where the connection will be closed without
+  /// sending a response
+  pub reset_rate: f64
+}
+
+impl FaultOptions {
+  /// If any faults are configured
+  pub fn is_empty(&self) -> bool {
+    self.delay.is_none() && self.error_rate <= 0.0 && self.reset_rate <= 0.0
+  }
+}
+
+/// Options for the connections made to a mock server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionOptions {
+  /// If HTTP/1 connections should be kept alive between requests (defaults to true)
+  pub keep_alive: bool,
+  /// Maximum number of concurrent connections. Once reached, new connections are not accepted
+  /// until an existing one is closed.
+  pub max_connections: Option<usize>,
+  /// Time allowed to read the body of a request. Requests that take longer will receive a 408
+  /// (Request Timeout) response.
+  pub read_timeout: Option<Duration>
+}
+
+impl Default for ConnectionOptions {
+  fn default() -> Self {
+    ConnectionOptions {
+      keep_alive: true,
+      max_connections: None,
+      read_timeout: None
+    }
+  }
+}
+
+/// Options for shutting down a mock server gracefully
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutdownOptions {
+  /// How long to wait for in-flight requests to complete once the server stops accepting
+  /// connections. Any requests still running after this are dropped.
+  pub timeout: Duration,
+  /// If the pact file should be written once the server has stopped. It is only written if all
+  /// the interactions were matched.
+  pub write_pact: bool,
+  /// Directory to write the pact file to. If not set, the current working directory is used.
+  pub output_path: Option<String>,
+  /// If the pact file should be overwritten, instead of merged with any existing pact file
+  pub overwrite: bool
+}
+
+impl Default for ShutdownOptions {
+  fn default() -> Self {
+    ShutdownOptions {
+      timeout: Duration::from_secs(5),
+      write_pact: false,
+      output_path: None,
+      overwrite: false
+    }
+  }
+}
+
+/// Result of shutting down a mock server gracefully
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutdownResult {
+  /// If all the in-flight requests completed before the timeout
+  pub completed: bool,
+  /// Mismatches collected by the mock server once it had stopped
+  pub mismatches: Vec<MatchResult>,
+  /// If the pact file was written
+  pub pact_written: bool
+}
+
+impl ShutdownResult {
+  /// If all the requests to the mock server were matched
+  pub fn matched(&self) -> bool {
+    self.mismatches.is_empty()
+  }
+}
+
+/// Port(s) that a mock server can bind to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortSelection {
+  /// Use a free port allocated by the operating system
+  Any,
+  /// Use this port, failing if it is already in use
+  Port(u16),
+  /// Use the first free port in this range (inclusive)
+  Range(u16, u16)
+}
+
+/// Address and port(s) that a mock server should bind to. It can be parsed from a string in the
+/// form `address[:port]` or `address:from-to`, i.e. `0.0.0.0`, `127.0.0.1:8080` or
+/// `[::1]:8000-8100`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindAddress {
+  /// IP address to bind to (i.e. 0.0.0.0 to accept connections on all interfaces)
+  pub ip: IpAddr,
+  /// Port or range of ports to bind to
+  pub ports: PortSelection
+}
+
+impl BindAddress {
+  /// Creates a new bind address
+  pub fn new(ip: IpAddr, ports: PortSelection) -> Self {
+    BindAddress { ip, ports }
+  }
+
+  /// Returns the socket addresses to try to bind to, in order
+  pub fn socket_addresses(&self) -> Vec<SocketAddr> {
+    match self.ports {
+      PortSelection::Any => vec![SocketAddr::new(self.ip, 0)],
+      PortSelection::Port(port) => vec![SocketAddr::new(self.ip, port)],
+      PortSelection::Range(from, to) => (from..=to).map(|port| SocketAddr::new(self.ip, port)).collect()
+    }
+  }
+}
+
+impl Default for BindAddress {
+  fn default() -> Self {
+    BindAddress::new(IpAddr::V4(Ipv4Addr::LOCALHOST), PortSelection::Any)
+  }
+}
+
+impl From<SocketAddr> for BindAddress {
+  fn from(addr: SocketAddr) -> Self {
+    match addr.port() {
+      0 => BindAddress::new(addr.ip(), PortSelection::Any),
+      port => BindAddress::new(addr.ip(), PortSelection::Port(port))
+    }
+  }
+}
+
+fn parse_port(port: &str, address: &str) -> Result<u16, String> {
+  port.trim().parse()
+    .map_err(|_| format!("'{}' is not a valid port in bind address '{}'", port, address))
+}
+
+impl FromStr for BindAddress {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (ip, ports) = if let Some(rest) = s.strip_prefix('[') {
+      match rest.split_once(']') {
+        Some((ip, ports)) => (ip, ports.strip_prefix(':')),
+        None => return Err(format!("'{}' is not a valid bind address, the IPv6 address is missing a ']'", s))
+      }
+    } else if s.matches(':').count() == 1 {
+      let (ip, ports) = s.split_once(':').unwrap();
+      (ip, Some(ports))
+    } else {
+      (s, None)
+    };
+
+    let ip = if ip.is_empty() {
+      IpAddr::V4(Ipv4Addr::LOCALHOST)
+    } else {
+      ip.parse().map_err(|_| format!("'{}' is not a valid IP address in bind address '{}'", ip, s))?
+    };
+    let ports = match ports {
+      None => PortSelection::Any,
+      Some(ports) => match ports.split_once('-') {
+        Some((from, to)) => {
+          let from = parse_port(from, s)?;
+          let to = parse_port(to, s)?;
+          if from > to || from == 0 {
+            return Err(format!("'{}' is not a valid port range in bind address '{}'", ports, s));
+          }
+          PortSelection::Range(from, to)
+        },
+        None => match parse_port(ports, s)? {
+          0 => PortSelection::Any,
+          port => PortSelection::Port(port)
+        }
+      }
+    };
+    Ok(BindAddress::new(ip, ports))
+  }
+}
+
+impl Display for BindAddress {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let ip = match self.ip {
+      IpAddr::V4(ip) => ip.to_string(),
+      IpAddr::V6(ip) => format!("[{}]", ip)
+    };
+    match self.ports {
+      PortSelection::Any => write!(f, "{}", ip),
+      PortSelection::Port(port) => write!(f, "{}:{}", ip, port),
+      PortSelection::Range(from, to) => write!(f, "{}:{}-{}", ip, from, to)
+    }
+  }
 }
 
 /// Mock server scheme
@@ -52,11 +405,23 @@ impl ToString for MockServerScheme {
   }
 }
 
-/// Metrics for the mock server
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Eq, PartialEq)]
-pub struct MockServerMetrics {
-  /// Total requests
-  pub requests: usize
+/// Entry in the journal of matched requests of the mock server
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+  /// Description of the interaction the request matched
+  pub description: String,
+  /// Request that was received
+  pub request: Request
+}
+
+impl JournalEntry {
+  /// Converts this entry to a JSON `Value`
+  pub fn to_json(&self) -> serde_json::Value {
+    json!({
+      "description": self.description.clone(),
+      "request": self.request.to_json(&PactSpecification::V3)
+    })
+  }
 }
 
 /// Struct to represent the "foreground" part of mock server
@@ -74,14 +439,75 @@ pub struct MockServer {
   pub resources: Vec<CString>,
   /// Pact that this mock server is based on
   pub pact: Arc<Mutex<dyn Pact + Send + Sync>>,
+  /// Pact with the interactions that the requests are served from
+  pub(crate) request_response_pact: Arc<RequestResponsePact>,
+  /// WebSocket interactions of the pact, which are served when a request upgrades the connection
+  pub(crate) websocket_interactions: Arc<Vec<SynchronousMessages>>,
   /// Receiver of match results
   matches: Arc<Mutex<Vec<MatchResult>>>,
+  /// Number of times each sequence of interactions has been invoked
+  pub(crate) sequences: ResponseSequences,
   /// Shutdown signal
   shutdown_tx: RefCell<Option<futures::channel::oneshot::Sender<()>>>,
   /// Mock server config
   pub config: MockServerConfig,
   /// Metrics collected by the mock server
-  pub metrics: MockServerMetrics
+  pub metrics: MockServerMetrics,
+  /// Requests that matched interactions, in the order they were received. Only recorded if
+  /// `record_journal` is set in the config.
+  pub(crate) journal: Vec<JournalEntry>,
+  /// Host name this mock server serves the requests for, if it is a virtual host that shares the
+  /// port of another mock server
+  pub host: Option<String>,
+  /// Virtual hosts that share the port of this mock server, keyed by host name
+  pub(crate) virtual_hosts: HashMap<String, VirtualHost>,
+  /// Plugins for the contents of the pact (i.e. with the protobuf descriptors from its metadata),
+  /// which are used instead of any registered plugins for the requests to this mock server
+  pub(crate) plugins: Vec<Arc<dyn ContentPlugin>>
+}
+
+/// Mock server that shares the port of another mock server, and serves the requests sent to a
+/// particular host name
+#[derive(Debug, Clone)]
+pub(crate) struct VirtualHost {
+  pub(crate) matches: Arc<Mutex<Vec<MatchResult>>>,
+  pub(crate) mock_server: Arc<Mutex<MockServer>>
+}
+
+/// Splits the interactions of the pact into the request/response interactions and the WebSocket
+/// interactions, as the WebSocket interactions are only served when a request upgrades the connection
+fn split_interactions(pact: &dyn Pact) -> Result<(RequestResponsePact, Vec<SynchronousMessages>), String> {
+  let websocket_interactions: Vec<SynchronousMessages> = pact.interactions().iter()
+    .filter_map(|interaction| interaction.as_v4_sync_message())
+    .filter(|interaction| is_websocket_interaction(interaction))
+    .collect();
+  if websocket_interactions.is_empty() {
+    Ok((pact.as_request_response_pact()?, websocket_interactions))
+  } else {
+    let mut v4_pact = pact.as_v4_pact()?;
+    v4_pact.interactions.retain(|interaction| interaction.as_v4_sync_message()
+      .map(|interaction| !is_websocket_interaction(&interaction))
+      .unwrap_or(true));
+    Ok((v4_pact.as_request_response_pact()?, websocket_interactions))
+  }
+}
+
+/// Loads the plugins for the Avro schema and protobuf descriptors embedded in the pact metadata, so
+/// that Avro and protobuf bodies of the requests can be matched
+fn content_plugins(pact: &dyn Pact) -> Vec<Arc<dyn ContentPlugin>> {
+  let mut plugins: Vec<Arc<dyn ContentPlugin>> = vec![];
+  match pact_matching::avro::plugin_from_pact(pact) {
+    Ok(Some(plugin)) => plugins.push(Arc::new(plugin)),
+    Ok(None) => (),
+    Err(err) => warn!("Failed to load the Avro schema from the pact metadata - {}", err)
+  }
+  #[cfg(feature = "protobuf")]
+  match pact_matching::protobuf::descriptors::plugin_from_pact(pact) {
+    Ok(Some(plugin)) => plugins.push(Arc::new(plugin)),
+    Ok(None) => (),
+    Err(err) => warn!("Failed to load the protobuf descriptors from the pact metadata - {}", err)
+  }
+  plugins
 }
 
 impl MockServer {
@@ -92,6 +518,8 @@ impl MockServer {
     addr: std::net::SocketAddr,
     config: MockServerConfig
   ) -> Result<(Arc<Mutex<MockServer>>, impl std::future::Future<Output = ()>), String> {
+    let (request_response_pact, websocket_interactions) = split_interactions(pact.as_ref())?;
+    let plugins = content_plugins(pact.as_ref());
     let (shutdown_tx, shutdown_rx) = futures::channel::oneshot::channel();
     let matches = Arc::new(Mutex::new(vec![]));
 
@@ -102,14 +530,20 @@ impl MockServer {
       scheme: MockServerScheme::HTTP,
       resources: vec![],
       pact: pact.thread_safe(),
+      request_response_pact: Arc::new(request_response_pact),
+      websocket_interactions: Arc::new(websocket_interactions),
       matches: matches.clone(),
+      sequences: ResponseSequences::default(),
       shutdown_tx: RefCell::new(Some(shutdown_tx)),
       config: config.clone(),
-      metrics: MockServerMetrics::default()
+      metrics: MockServerMetrics::default(),
+      journal: vec![],
+      host: None,
+      virtual_hosts: HashMap::new(),
+      plugins
     }));
 
     let (future, socket_addr) = hyper_server::create_and_bind(
-      pact.as_request_response_pact().unwrap(),
       addr,
       async {
         shutdown_rx.await.ok();
@@ -139,6 +573,8 @@ impl MockServer {
     tls: &ServerConfig,
     config: MockServerConfig
   ) -> Result<(Arc<Mutex<MockServer>>, impl std::future::Future<Output = ()>), String> {
+    let (request_response_pact, websocket_interactions) = split_interactions(pact.as_ref())?;
+    let plugins = content_plugins(pact.as_ref());
     let (shutdown_tx, shutdown_rx) = futures::channel::oneshot::channel();
     let matches = Arc::new(Mutex::new(vec![]));
     let mock_server = Arc::new(Mutex::new(MockServer {
@@ -148,14 +584,20 @@ impl MockServer {
       scheme: MockServerScheme::HTTPS,
       resources: vec![],
       pact: pact.thread_safe(),
+      request_response_pact: Arc::new(request_response_pact),
+      websocket_interactions: Arc::new(websocket_interactions),
       matches: matches.clone(),
+      sequences: ResponseSequences::default(),
       shutdown_tx: RefCell::new(Some(shutdown_tx)),
       config: config.clone(),
-      metrics: MockServerMetrics::default()
+      metrics: MockServerMetrics::default(),
+      journal: vec![],
+      host: None,
+      virtual_hosts: HashMap::new(),
+      plugins
     }));
 
     let (future, socket_addr) = hyper_server::create_and_bind_tls(
-      pact.as_request_response_pact().unwrap(),
       addr,
       async {
         shutdown_rx.await.ok();
@@ -176,6 +618,83 @@ impl MockServer {
     Ok((mock_server.clone(), future))
   }
 
+  /// Adds a virtual host for the pact that shares the port of this mock server. Requests sent to
+  /// the host name (from the `Host` header, or the SNI host name of a TLS connection) are served
+  /// from the pact by the returned mock server, and all other requests by this one.
+  pub fn add_virtual_host(
+    &mut self,
+    id: String,
+    host: &str,
+    pact: Box<dyn Pact>,
+    config: MockServerConfig
+  ) -> Result<Arc<Mutex<MockServer>>, String> {
+    if self.host.is_some() {
+      return Err(format!("Mock server {} is a virtual host, and can not have virtual hosts of its own", self.id));
+    }
+    let host = host.trim().to_lowercase();
+    if host.is_empty() {
+      return Err("The virtual host name can not be empty".to_string());
+    }
+    if self.virtual_hosts.contains_key(&host) {
+      return Err(format!("Mock server {} already has a virtual host for '{}'", self.id, host));
+    }
+    let (request_response_pact, websocket_interactions) = split_interactions(pact.as_ref())?;
+    let plugins = content_plugins(pact.as_ref());
+
+    let matches = Arc::new(Mutex::new(vec![]));
+    let mock_server = Arc::new(Mutex::new(MockServer {
+      id,
+      port: self.port,
+      address: self.address.clone(),
+      scheme: self.scheme.clone(),
+      resources: vec![],
+      pact: pact.thread_safe(),
+      request_response_pact: Arc::new(request_response_pact),
+      websocket_interactions: Arc::new(websocket_interactions),
+      matches: matches.clone(),
+      sequences: ResponseSequences::default(),
+      shutdown_tx: RefCell::new(None),
+      config,
+      metrics: MockServerMetrics::default(),
+      journal: vec![],
+      host: Some(host.clone()),
+      virtual_hosts: HashMap::new(),
+      plugins
+    }));
+    debug!("Added virtual host '{}' to mock server {}", host, self.id);
+    self.virtual_hosts.insert(host, VirtualHost {
+      matches,
+      mock_server: mock_server.clone()
+    });
+    Ok(mock_server)
+  }
+
+  /// Replaces the pact of this mock server, i.e. when the pact file has changed on disk. Requests
+  /// received after this are served from the interactions of the new pact, and the sequences of
+  /// responses are reset. Returns an error if the pact can not be used with a mock server.
+  pub fn update_pact(&mut self, pact: Box<dyn Pact>) -> Result<(), String> {
+    let (request_response_pact, websocket_interactions) = split_interactions(pact.as_ref())?;
+    debug!("Updating the pact of mock server {} - {} interaction(s)", self.id,
+      request_response_pact.interactions.len() + websocket_interactions.len());
+    self.pact = pact.thread_safe();
+    self.request_response_pact = Arc::new(request_response_pact);
+    self.websocket_interactions = Arc::new(websocket_interactions);
+    self.sequences.reset();
+    Ok(())
+  }
+
+  /// Removes the virtual host for the host name, returning true if there was one
+  pub fn remove_virtual_host(&mut self, host: &str) -> bool {
+    self.virtual_hosts.remove(&host.to_lowercase()).is_some()
+  }
+
+  /// Host names of the virtual hosts that share the port of this mock server
+  pub fn virtual_host_names(&self) -> Vec<String> {
+    let mut hosts: Vec<String> = self.virtual_hosts.keys().cloned().collect();
+    hosts.sort();
+    hosts
+  }
+
   /// Send the shutdown signal to the server
   pub fn shutdown(&mut self) -> Result<(), String> {
     let shutdown_future = &mut *self.shutdown_tx.borrow_mut();
@@ -193,18 +712,47 @@ impl MockServer {
     }
   }
 
+  /// Completes a graceful shutdown once the server has stopped. Collects the mismatches and, if
+  /// requested, writes the pact file when all the interactions were matched. The pact file is not
+  /// written if in-flight requests were dropped (`completed` is false), as the results would be
+  /// incomplete.
+  pub fn shutdown_result(&self, options: &ShutdownOptions, completed: bool) -> anyhow::Result<ShutdownResult> {
+    let mismatches = self.mismatches();
+    let pact_written = if !options.write_pact {
+      false
+    } else if !completed {
+      warn!("Mock server {} did not complete all in-flight requests before shutting down, not writing the pact file", self.id);
+      false
+    } else if !mismatches.is_empty() {
+      warn!("Mock server {} has {} mismatch(es), not writing the pact file", self.id, mismatches.len());
+      false
+    } else {
+      self.write_pact(&options.output_path, options.overwrite)?;
+      true
+    };
+    Ok(ShutdownResult { completed, mismatches, pact_written })
+  }
+
     /// Converts this mock server to a `Value` struct
     pub fn to_json(&self) -> serde_json::Value {
-      let pact = self.pact.lock().unwrap();
-      json!({
+      // The pact lock must be released before collecting the mismatches, as that locks it again
+      let provider = self.pact.lock().unwrap().provider().name.clone();
+      let mut json = json!({
         "id" : self.id.clone(),
         "port" : self.port.unwrap_or_default() as u64,
         "address" : self.address.clone().unwrap_or_default(),
         "scheme" : self.scheme.to_string(),
-        "provider" : pact.provider().name.clone(),
+        "provider" : provider,
         "status" : if self.mismatches().is_empty() { "ok" } else { "error" },
-        "metrics" : self.metrics
-      })
+        "metrics" : self.metrics.to_json()
+      });
+      if let Some(ref host) = self.host {
+        json["host"] = json!(host);
+      }
+      if !self.virtual_hosts.is_empty() {
+        json["virtualHosts"] = json!(self.virtual_host_names());
+      }
+      json
     }
 
     /// Returns all collected matches
@@ -212,13 +760,108 @@ impl MockServer {
         self.matches.lock().unwrap().clone()
     }
 
-    /// Returns all the mismatches that have occurred with this mock server
+    /// Returns the journal of the requests that matched interactions, in the order they were
+    /// received. The journal is empty if `record_journal` is not set in the config.
+    pub fn journal(&self) -> Vec<JournalEntry> {
+      self.journal.clone()
+    }
+
+    /// Returns the description of the interaction with the given request and response
+    pub fn interaction_description(&self, request: &Request, response: &Response) -> Option<String> {
+      self.request_response_pact.interactions.iter()
+        .find(|i| &i.request == request && &i.response == response)
+        .map(|i| i.description.clone())
+    }
+
+    /// Returns the requests received by the mock server as JSON, in the order they were received,
+    /// with the status of the match (`matched`, `mismatched` or `unexpected`). Matched requests
+    /// have the description of the interaction they matched, and mismatched requests the
+    /// descriptions of the mismatches. Requests that matched use the method and path of the
+    /// interaction.
+    pub fn received_requests_json(&self) -> serde_json::Value {
+      let requests: Vec<serde_json::Value> = self.matches().iter().filter_map(|result| match result {
+        MatchResult::RequestMatch(request, response) => Some(json!({
+          "status": "matched",
+          "method": request.method,
+          "path": request.path,
+          "interaction": self.interaction_description(request, response).unwrap_or_default()
+        })),
+        MatchResult::RequestMismatch(request, mismatches) => Some(json!({
+          "status": "mismatched",
+          "method": request.method,
+          "path": request.path,
+          "mismatches": mismatches.iter().map(|mismatch| mismatch.description()).collect::<Vec<String>>()
+        })),
+        MatchResult::RequestNotFound(request) => Some(json!({
+          "status": "unexpected",
+          "method": request.method,
+          "path": request.path
+        })),
+        MatchResult::MissingRequest(_) => None
+      }).collect();
+      json!(requests)
+    }
+
+    /// Records the actual request that matched the interaction with the given request and
+    /// response in the metrics, and in the journal if it is enabled
+    pub(crate) fn record_match(&mut self, actual: &Request, request: &Request, response: &Response) {
+      let description = self.interaction_description(request, response).unwrap_or_default();
+      self.metrics.record_match(&description);
+      if self.config.record_journal {
+        self.journal.push(JournalEntry { description, request: actual.clone() });
+      }
+    }
+
+    /// Verifies that the interactions with the given descriptions received their first matching
+    /// request in the given order. Returns an error describing the first interaction that was not
+    /// received, or that was received out of order. Only checks the order of the given
+    /// interactions, so other interactions can be received in between them.
+    pub fn verify_order(&self, descriptions: &[&str]) -> Result<(), String> {
+      let mut previous: Option<(&str, usize)> = None;
+      for description in descriptions {
+        let index = self.journal.iter().position(|entry| entry.description == *description)
+          .ok_or_else(|| format!("Interaction '{}' did not receive a request", description))?;
+        if let Some((previous_description, previous_index)) = previous {
+          if index < previous_index {
+            return Err(format!("Interaction '{}' received a request before interaction '{}'",
+              description, previous_description));
+          }
+        }
+        previous = Some((description, index));
+      }
+      Ok(())
+    }
+
+    /// Resets the sequences of interactions with the same request, so the next request will
+    /// get the response from the first interaction again
+    pub fn reset_sequences(&mut self) {
+      self.sequences.reset();
+    }
+
+    /// Resets the state of the mock server, so it can be reused for another test without
+    /// restarting it. This clears the recorded matches and mismatches and the journal, and resets
+    /// the sequences of interactions with the same request. The state of the virtual hosts of the
+    /// mock server is also reset. The metrics are not cleared.
+    pub fn reset(&mut self) {
+      self.matches.lock().unwrap().clear();
+      self.journal.clear();
+      self.sequences.reset();
+      for virtual_host in self.virtual_hosts.values() {
+        virtual_host.mock_server.lock().unwrap().reset();
+      }
+    }
+
+    /// Returns all the mismatches that have occurred with this mock server. Each interaction
+    /// needs to have received a request, so a sequence of interactions with the same request
+    /// (see `MockServerConfig::sequence_interactions`) requires the request to be received once
+    /// for each interaction in the sequence.
     pub fn mismatches(&self) -> Vec<MatchResult> {
       let matches = self.matches();
       let mismatches = matches.iter()
-        .filter(|m| !m.matched() && !m.cors_preflight())
+        .filter(|m| !m.matched())
+        .filter(|m| !self.config.cors_preflight || !m.cors_preflight())
         .map(|m| m.clone());
-      let requests: Vec<Request> = matches.iter().map(|m| {
+      let mut requests: Vec<Request> = matches.iter().map(|m| {
         match m {
           MatchResult::RequestMatch(request, _) => Some(request),
           MatchResult::RequestMismatch(request, _) => Some(request),
@@ -227,12 +870,22 @@ impl MockServer {
         }
       }).filter(|o| o.is_some()).map(|o| o.unwrap().clone()).collect();
 
-      let pact = self.pact.lock().unwrap();
-      let interactions = pact.interactions();
-      let missing = interactions.iter()
-        .map(|i| i.as_request_response().unwrap().request)
-        .filter(|req| !requests.contains(req))
-        .map(|req| MatchResult::MissingRequest(req.clone()));
+      let expected_requests = self.request_response_pact.interactions.iter()
+        .map(|i| (i.request.clone(), self.config.in_sequence(i)))
+        .chain(self.websocket_interactions.iter()
+          .filter_map(upgrade_request)
+          .map(|req| (req.as_v3_request(), false)));
+      let missing = expected_requests
+        .filter(|(req, in_sequence)| match requests.iter().position(|r| r == req) {
+          Some(index) => {
+            if *in_sequence {
+              requests.remove(index);
+            }
+            false
+          },
+          None => true
+        })
+        .map(|(req, _)| MatchResult::MissingRequest(req));
       mismatches.chain(missing).collect()
     }
 
@@ -251,15 +904,33 @@ impl MockServer {
 
     info!("Writing pact out to '{}'", filename.display());
     let specification = pact.spec_version();
-    match write_pact(pact, filename.as_path(), specification, overwrite) {
+    match write_pact(pact, filename.as_path(), specification, overwrite, MergeStrategy::default()) {
       Ok(_) => Ok(()),
       Err(err) => {
         warn!("Failed to write pact to file - {}", err);
-        Err(err)
+        Err(err.into())
       }
     }
   }
 
+  /// Writes out the pact file to the output directory using the given layout, and updates the
+  /// index file in the directory. Returns the path to the pact file.
+  pub fn write_pact_with_layout(
+    &self,
+    output_path: &Option<String>,
+    overwrite: bool,
+    layout: PactFileLayout
+  ) -> anyhow::Result<PathBuf> {
+    let pact = self.pact.lock().unwrap().boxed();
+    let dir = output_path.as_ref().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+    let specification = pact.spec_version();
+    write_pact_to_directory(pact, dir.as_path(), specification, overwrite, layout)
+      .map_err(|err| {
+        warn!("Failed to write pact to file - {}", err);
+        err.into()
+      })
+  }
+
     /// Returns the URL of the mock server
     pub fn url(&self) -> String {
       let addr = self.address.clone().unwrap_or_else(|| "127.0.0.1".to_string());
@@ -269,6 +940,35 @@ impl MockServer {
         None => "error(port is not set)".to_string()
       }
     }
+
+    /// Returns the faults to inject into the response of the interaction with the request and
+    /// response. Faults configured for the interaction replace the global ones.
+    pub fn interaction_faults(&self, request: &Request, response: &Response) -> FaultOptions {
+      if self.config.interaction_faults.is_empty() {
+        return self.config.faults.clone();
+      }
+      let pact = self.pact.lock().unwrap();
+      pact.interactions().iter()
+        .find(|interaction| match interaction.as_request_response() {
+          Some(i) => &i.request == request && &i.response == response,
+          None => false
+        })
+        .and_then(|interaction| self.config.interaction_faults.get(&interaction.description()))
+        .cloned()
+        .unwrap_or_else(|| self.config.faults.clone())
+    }
+
+    /// Returns the timeout of the interaction in the pact with the given request and response, if
+    /// it has one
+    pub fn interaction_timeout(&self, request: &Request, response: &Response) -> Option<Duration> {
+      let pact = self.pact.lock().unwrap();
+      pact.interactions().iter()
+        .find(|interaction| match interaction.as_request_response() {
+          Some(i) => &i.request == request && &i.response == response,
+          None => false
+        })
+        .and_then(|interaction| interaction.timeout())
+    }
 }
 
 impl Clone for MockServer {
@@ -282,10 +982,17 @@ impl Clone for MockServer {
       scheme: self.scheme.clone(),
       resources: vec![],
       pact: self.pact.clone(),
+      request_response_pact: self.request_response_pact.clone(),
+      websocket_interactions: self.websocket_interactions.clone(),
       matches: self.matches.clone(),
+      sequences: self.sequences.clone(),
       shutdown_tx: RefCell::new(None),
       config: self.config.clone(),
-      metrics: self.metrics.clone()
+      metrics: self.metrics.clone(),
+      journal: self.journal.clone(),
+      host: self.host.clone(),
+      virtual_hosts: self.virtual_hosts.clone(),
+      plugins: self.plugins.clone()
     }
   }
 }
@@ -299,10 +1006,17 @@ impl Default for MockServer {
       address: None,
       resources: vec![],
       pact: Arc::new(Mutex::new(RequestResponsePact::default())),
+      request_response_pact: Arc::new(RequestResponsePact::default()),
+      websocket_interactions: Arc::new(vec![]),
       matches: Arc::new(Mutex::new(vec![])),
+      sequences: Default::default(),
       shutdown_tx: RefCell::new(None),
       config: Default::default(),
-      metrics: Default::default()
+      metrics: Default::default(),
+      journal: vec![],
+      host: None,
+      virtual_hosts: HashMap::new(),
+      plugins: vec![]
     }
   }
 }
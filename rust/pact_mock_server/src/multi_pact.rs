@@ -0,0 +1,195 @@
+//!
+//! Support for running a mock server from a number of pact files, i.e. to use all the pacts for
+//! a provider as a shared stub of that provider.
+//!
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use log::*;
+
+use pact_matching::error::PactError;
+use pact_matching::models::{Pact, read_pact, RequestResponseInteraction, RequestResponsePact};
+use pact_matching::models::pact_files::load_pacts_from_dir;
+use pact_models::{Consumer, PactSpecification, Provider};
+
+/// Loads the pacts from the list of paths. Any directories are expanded to the pact files
+/// (files with a `.json` extension) in them, ordered by provider name and then file name. Files in
+/// a directory that are not valid pacts are skipped with a warning.
+pub fn load_pacts(paths: &[PathBuf]) -> Result<Vec<Box<dyn Pact>>, PactError> {
+  let mut pacts = vec![];
+  for path in paths {
+    if path.is_dir() {
+      pacts.extend(load_pacts_from_dir(path, false)?.all_pacts());
+    } else {
+      debug!("Loading pact file '{}'", path.display());
+      pacts.push(read_pact(path)?);
+    }
+  }
+  Ok(pacts)
+}
+
+/// Loads the pact for a mock server from the list of paths (see `load_pacts`). If there is more
+/// than one pact, the interactions from all of them are combined into one pact with
+/// `combine_pacts`.
+pub fn load_combined_pact(paths: &[PathBuf]) -> Result<Box<dyn Pact>, String> {
+  let mut pacts = load_pacts(paths).map_err(|err| err.to_string())?;
+  if pacts.len() == 1 {
+    Ok(pacts.remove(0))
+  } else {
+    info!("Combining the interactions from {} pacts", pacts.len());
+    combine_pacts(&pacts).map(|pact| Box::new(pact) as Box<dyn Pact>)
+  }
+}
+
+pub(crate) fn pact_files_in_dir(dir: &Path) -> Result<Vec<PathBuf>, PactError> {
+  let entries = fs::read_dir(dir).map_err(|err| PactError::io_error(dir, err))?;
+  let mut files = vec![];
+  for entry in entries {
+    let path = entry.map_err(|err| PactError::io_error(dir, err))?.path();
+    if path.is_file() && path.extension().map(|ext| ext == "json").unwrap_or(false) {
+      files.push(path);
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+/// Combines the interactions from a number of pacts into a single pact, so that one mock server
+/// can serve them all. The consumer (and provider, if they differ) of the combined pact is the
+/// list of names of the pacts.
+///
+/// Interactions that are the same as one from an earlier pact are only included once. If an
+/// interaction from a later pact has the same request and provider states as one from an earlier
+/// pact, but a different response, it conflicts with the earlier one and is dropped with a
+/// warning. Interactions with the same request in one pact are kept, so they can still be
+/// returned in sequence.
+///
+/// # Errors
+///
+/// Returns an error if there are no pacts, or any of the pacts have message interactions.
+pub fn combine_pacts(pacts: &[Box<dyn Pact>]) -> Result<RequestResponsePact, String> {
+  if pacts.is_empty() {
+    return Err("There are no pacts to combine".to_string());
+  }
+
+  let mut interactions: Vec<(usize, RequestResponseInteraction)> = vec![];
+  for (index, pact) in pacts.iter().enumerate() {
+    let pact = pact.as_request_response_pact()
+      .map_err(|err| format!("Pact between {} and {} can not be used with a mock server - {}",
+        pact.consumer().name, pact.provider().name, err))?;
+    for interaction in pact.interactions {
+      let existing = interactions.iter().find(|(i, existing)| *i != index &&
+        existing.request == interaction.request && existing.provider_states == interaction.provider_states);
+      match existing {
+        Some((_, existing)) if existing.response == interaction.response => {
+          debug!("Ignoring interaction '{}' from the pact for consumer '{}' as it is the same as '{}'",
+            interaction.description, pact.consumer.name, existing.description);
+        },
+        Some((_, existing)) => {
+          warn!("Ignoring interaction '{}' from the pact for consumer '{}' as it conflicts with '{}' (it has the same request but a different response)",
+            interaction.description, pact.consumer.name, existing.description);
+        },
+        None => interactions.push((index, interaction))
+      }
+    }
+  }
+
+  let consumers = pacts.iter().map(|pact| pact.consumer().name).unique().join(", ");
+  let providers = pacts.iter().map(|pact| pact.provider().name).unique().join(", ");
+  Ok(RequestResponsePact {
+    consumer: Consumer { name: consumers },
+    provider: Provider { name: providers },
+    interactions: interactions.into_iter().map(|(_, interaction)| interaction).collect(),
+    specification_version: PactSpecification::V3,
+    .. RequestResponsePact::default()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use serde_json::json;
+
+  use pact_test_support::builders::{InteractionBuilder, MessageBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+
+  use super::*;
+
+  fn interaction(description: &str, path: &str, status: u16) -> RequestResponseInteraction {
+    InteractionBuilder::new(description)
+      .request(RequestBuilder::get(path).build())
+      .response(ResponseBuilder::new().status(status).build())
+      .build()
+  }
+
+  #[test]
+  fn combine_pacts_merges_the_interactions_from_all_the_pacts() {
+    let web = PactBuilder::new("web", "users")
+      .interaction(interaction("get user 1", "/users/1", 200))
+      .interaction(interaction("get user 2", "/users/2", 200))
+      .build();
+    let mobile = PactBuilder::new("mobile", "users")
+      .interaction(interaction("a request for user 1", "/users/1", 200))
+      .interaction(interaction("get user 3", "/users/3", 404))
+      .build();
+
+    let pact = combine_pacts(&[web.boxed(), mobile.boxed()]).unwrap();
+
+    expect!(pact.consumer.name).to(be_equal_to("web, mobile"));
+    expect!(pact.provider.name).to(be_equal_to("users"));
+    expect!(pact.interactions.iter().map(|i| i.description.clone()).collect::<Vec<String>>())
+      .to(be_equal_to(vec!["get user 1".to_string(), "get user 2".to_string(), "get user 3".to_string()]));
+  }
+
+  #[test]
+  fn combine_pacts_drops_conflicting_interactions_from_later_pacts() {
+    let web = PactBuilder::new("web", "users")
+      .interaction(interaction("get user 1", "/users/1", 200))
+      .interaction(interaction("get user 1 again", "/users/1", 404))
+      .build();
+    let mobile = PactBuilder::new("mobile", "users")
+      .interaction(interaction("get a missing user 1", "/users/1", 500))
+      .build();
+
+    let pact = combine_pacts(&[web.boxed(), mobile.boxed()]).unwrap();
+
+    expect!(pact.interactions.iter().map(|i| i.description.clone()).collect::<Vec<String>>())
+      .to(be_equal_to(vec!["get user 1".to_string(), "get user 1 again".to_string()]));
+  }
+
+  #[test]
+  fn combine_pacts_returns_an_error_if_there_are_no_pacts_or_message_pacts() {
+    let message_pact = PactBuilder::new("web", "events")
+      .message(MessageBuilder::new("an event")
+        .json_contents(json!({ "id": 1 }))
+        .build())
+      .build_message_pact();
+
+    expect!(combine_pacts(&[])).to(be_err());
+    expect!(combine_pacts(&[message_pact.boxed()])).to(be_err());
+  }
+
+  #[test]
+  fn load_pacts_expands_directories() {
+    let dir = std::env::temp_dir().join("pact_mock_server_load_pacts_expands_directories");
+    fs::remove_dir_all(&dir).unwrap_or_default();
+    fs::create_dir_all(&dir).unwrap();
+    let web = PactBuilder::new("web", "users")
+      .interaction(interaction("get user 1", "/users/1", 200))
+      .build();
+    let mobile = PactBuilder::new("mobile", "users")
+      .interaction(interaction("get user 3", "/users/3", 404))
+      .build();
+    fs::write(dir.join("web-users.json"), web.to_json(PactSpecification::V3).to_string()).unwrap();
+    fs::write(dir.join("mobile-users.json"), mobile.to_json(PactSpecification::V3).to_string()).unwrap();
+    fs::write(dir.join("README.md"), "not a pact").unwrap();
+    fs::write(dir.join("invalid.json"), "{ not json").unwrap();
+
+    let pacts = load_pacts(&[dir.clone()]);
+    fs::remove_dir_all(&dir).unwrap_or_default();
+
+    expect!(pacts.unwrap().iter().map(|pact| pact.consumer().name).collect::<Vec<String>>())
+      .to(be_equal_to(vec!["mobile".to_string(), "web".to_string()]));
+  }
+}
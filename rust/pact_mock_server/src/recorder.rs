@@ -0,0 +1,481 @@
+//!
+//! This module provides a recording proxy, which forwards requests to a real provider and records
+//! the requests and responses as interactions in a pact. The pact can then be used as a starting
+//! point for the contract tests.
+//!
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use hyper::{Body, Client, Response, Server};
+use hyper::client::HttpConnector;
+use hyper::service::{make_service_fn, service_fn};
+use hyper_rustls::HttpsConnector;
+use log::*;
+use serde_json::json;
+
+use pact_matching::models::{
+  HttpPart,
+  MergeStrategy,
+  Pact,
+  ReadWritePact,
+  Request,
+  RequestResponseInteraction,
+  RequestResponsePact,
+  write_pact
+};
+use pact_models::{Consumer, OptionalBody, PactSpecification, Provider};
+
+use crate::hyper_server::{extract_headers, hyper_request_to_pact_request};
+
+/// Headers that only apply to a single connection, and are never forwarded or recorded
+const HOP_BY_HOP_HEADERS: [&str; 9] = ["connection", "keep-alive", "proxy-authenticate", "proxy-authorization",
+  "te", "trailers", "transfer-encoding", "upgrade", "host"];
+
+/// Headers that change with every request or response, and are not recorded
+const UNRECORDED_HEADERS: [&str; 2] = ["content-length", "date"];
+
+/// Configuration for a recording proxy
+#[derive(Debug, Clone, Default)]
+pub struct RecordingConfig {
+  /// Base URL of the provider to forward the requests to (i.e. `http://localhost:8080`)
+  pub provider_url: String,
+  /// Name of the consumer for the recorded pact
+  pub consumer: String,
+  /// Name of the provider for the recorded pact
+  pub provider: String,
+  /// Additional headers that are forwarded to the provider, but not recorded in the pact (i.e.
+  /// `Authorization`)
+  pub ignore_headers: Vec<String>
+}
+
+impl RecordingConfig {
+  fn records_header(&self, name: &str) -> bool {
+    let name = name.to_lowercase();
+    !HOP_BY_HOP_HEADERS.contains(&name.as_str()) && !UNRECORDED_HEADERS.contains(&name.as_str()) &&
+      !self.ignore_headers.iter().any(|header| header.to_lowercase() == name)
+  }
+
+  fn recorded_headers(&self, headers: Option<HashMap<String, Vec<String>>>) -> Option<HashMap<String, Vec<String>>> {
+    headers
+      .map(|headers| headers.into_iter().filter(|(name, _)| self.records_header(name)).collect::<HashMap<_, _>>())
+      .filter(|headers| !headers.is_empty())
+  }
+}
+
+/// Client used to forward the requests to the provider. HTTPS providers need the native root
+/// certificates, so they are only loaded when the provider URL uses HTTPS.
+enum ProviderClient {
+  Http(Client<HttpConnector>),
+  Https(Client<HttpsConnector<HttpConnector>>)
+}
+
+impl ProviderClient {
+  fn new(provider_url: &str) -> Self {
+    if provider_url.to_lowercase().starts_with("https:") {
+      ProviderClient::Https(Client::builder().build(HttpsConnector::with_native_roots()))
+    } else {
+      ProviderClient::Http(Client::new())
+    }
+  }
+
+  async fn request(&self, request: hyper::Request<Body>) -> Result<Response<Body>, hyper::Error> {
+    match self {
+      ProviderClient::Http(client) => client.request(request).await,
+      ProviderClient::Https(client) => client.request(request).await
+    }
+  }
+}
+
+/// Interactions recorded by a proxy
+struct Recording {
+  config: RecordingConfig,
+  interactions: Mutex<Vec<RequestResponseInteraction>>
+}
+
+impl Recording {
+  /// Records the request and response as an interaction. Interactions that have already been
+  /// recorded are ignored, and interactions that have the same request and method as another but a
+  /// different response get a number appended to their description, so the descriptions are unique.
+  fn record(&self, request: Request, response: pact_matching::models::Response) {
+    let mut interactions = self.interactions.lock().unwrap();
+    if interactions.iter().any(|i| i.request == request && i.response == response) {
+      debug!("Request {} has already been recorded with the same response", request);
+      return;
+    }
+
+    let base_description = format!("{} {}", request.method, request.path);
+    let existing = interactions.iter()
+      .filter(|i| i.description == base_description || i.description.starts_with(&format!("{} (", base_description)))
+      .count();
+    let description = if existing == 0 {
+      base_description
+    } else {
+      format!("{} ({})", base_description, existing + 1)
+    };
+    info!("Recording interaction '{}'", description);
+    interactions.push(RequestResponseInteraction {
+      description,
+      request,
+      response,
+      .. RequestResponseInteraction::default()
+    });
+  }
+}
+
+/// Proxy that forwards requests to a real provider, and records the requests and responses as
+/// interactions in a pact
+pub struct RecordingProxy {
+  /// Port the proxy is running on
+  pub port: Option<u16>,
+  /// Address the proxy is bound to
+  pub address: Option<String>,
+  recording: Arc<Recording>,
+  shutdown_tx: Option<futures::channel::oneshot::Sender<()>>
+}
+
+impl RecordingProxy {
+  /// Creates a new recording proxy bound to the address, returning it and the future that drives
+  /// it. The future needs to be spawned onto a runtime for the proxy to handle requests.
+  pub async fn start(
+    config: RecordingConfig,
+    addr: SocketAddr
+  ) -> Result<(RecordingProxy, impl std::future::Future<Output = ()>), String> {
+    if !config.provider_url.starts_with("http://") && !config.provider_url.starts_with("https://") {
+      return Err(format!("'{}' is not a valid provider URL, it must be an HTTP or HTTPS URL", config.provider_url));
+    }
+    let (shutdown_tx, shutdown_rx) = futures::channel::oneshot::channel::<()>();
+    let client = Arc::new(ProviderClient::new(&config.provider_url));
+    let recording = Arc::new(Recording {
+      config,
+      interactions: Mutex::new(vec![])
+    });
+
+    let service_recording = recording.clone();
+    let server = Server::try_bind(&addr)
+      .map_err(|err| format!("Could not start recording proxy: {}", err))?
+      .serve(make_service_fn(move |_| {
+        let client = client.clone();
+        let recording = service_recording.clone();
+
+        async {
+          Ok::<_, hyper::Error>(
+            service_fn(move |req| {
+              let client = client.clone();
+              let recording = recording.clone();
+
+              async move {
+                Ok::<_, hyper::Error>(forward_request(req, client, recording).await)
+              }
+            })
+          )
+        }
+      }));
+    let socket_addr = server.local_addr();
+    debug!("Started recording proxy on {}, forwarding to {}", socket_addr, recording.config.provider_url);
+
+    let proxy = RecordingProxy {
+      port: Some(socket_addr.port()),
+      address: Some(socket_addr.ip().to_string()),
+      recording,
+      shutdown_tx: Some(shutdown_tx)
+    };
+    Ok((proxy, async {
+      let _ = server
+        .with_graceful_shutdown(async { shutdown_rx.await.ok(); })
+        .await;
+    }))
+  }
+
+  /// Returns the URL of the proxy
+  pub fn url(&self) -> String {
+    let addr = self.address.clone().unwrap_or_else(|| "127.0.0.1".to_string());
+    format!("http://{}:{}", if addr == "0.0.0.0" { "127.0.0.1" } else { addr.as_str() }, self.port.unwrap_or_default())
+  }
+
+  /// Returns the interactions that have been recorded
+  pub fn interactions(&self) -> Vec<RequestResponseInteraction> {
+    self.recording.interactions.lock().unwrap().clone()
+  }
+
+  /// Returns a pact with all the recorded interactions
+  pub fn pact(&self) -> RequestResponsePact {
+    RequestResponsePact {
+      consumer: Consumer { name: self.recording.config.consumer.clone() },
+      provider: Provider { name: self.recording.config.provider.clone() },
+      interactions: self.interactions(),
+      metadata: RequestResponsePact::default_metadata(),
+      specification_version: PactSpecification::V3
+    }
+  }
+
+  /// Writes the recorded pact out to the provided directory, returning the path to the pact file
+  pub fn write_pact(&self, output_path: &Option<String>, overwrite: bool) -> anyhow::Result<PathBuf> {
+    let pact = self.pact();
+    let filename = match *output_path {
+      Some(ref path) => PathBuf::from(path).join(pact.default_file_name()),
+      None => PathBuf::from(pact.default_file_name())
+    };
+
+    info!("Writing {} recorded interaction(s) out to '{}'", pact.interactions.len(), filename.display());
+    write_pact(pact.boxed(), filename.as_path(), PactSpecification::V3, overwrite, MergeStrategy::default())
+      .map_err(|err| {
+        warn!("Failed to write pact to file - {}", err);
+        err
+      })?;
+    Ok(filename)
+  }
+
+  /// Send the shutdown signal to the proxy
+  pub fn shutdown(&mut self) -> Result<(), String> {
+    match self.shutdown_tx.take() {
+      Some(sender) => sender.send(())
+        .map_err(|_| "Problem sending shutdown signal to recording proxy".to_string()),
+      None => Err("Recording proxy already shut down".to_string())
+    }
+  }
+}
+
+fn error_response(status: u16, message: String) -> Response<Body> {
+  Response::builder()
+    .status(status)
+    .header(hyper::header::CONTENT_TYPE, "application/json; charset=utf-8")
+    .body(Body::from(json!({ "error": message }).to_string()))
+    .unwrap()
+}
+
+fn provider_request(
+  provider_url: &str,
+  request: &Request,
+  query: Option<&str>
+) -> Result<hyper::Request<Body>, String> {
+  let mut uri = format!("{}{}", provider_url.trim_end_matches('/'), request.path);
+  if let Some(query) = query {
+    uri.push('?');
+    uri.push_str(query);
+  }
+
+  let mut builder = hyper::Request::builder()
+    .method(request.method.as_str())
+    .uri(uri.as_str());
+  if let Some(ref headers) = request.headers {
+    for (name, values) in headers {
+      if !HOP_BY_HOP_HEADERS.contains(&name.to_lowercase().as_str()) {
+        builder = builder.header(name.as_str(), values.join(", "));
+      }
+    }
+  }
+  let body = match request.body {
+    OptionalBody::Present(ref body, _) => Body::from(body.clone()),
+    _ => Body::empty()
+  };
+  builder.body(body).map_err(|err| format!("Could not create the request to the provider - {}", err))
+}
+
+async fn forward_request(
+  req: hyper::Request<Body>,
+  client: Arc<ProviderClient>,
+  recording: Arc<Recording>
+) -> Response<Body> {
+  let query = req.uri().query().map(|query| query.to_string());
+  let request = match hyper_request_to_pact_request(req, None).await {
+    Ok(request) => request,
+    Err(err) => return error_response(400, format!("Could not read the request - {:?}", err))
+  };
+  info!("Forwarding request {} to {}", request, recording.config.provider_url);
+
+  let provider_request = match provider_request(&recording.config.provider_url, &request, query.as_deref()) {
+    Ok(provider_request) => provider_request,
+    Err(err) => return error_response(502, err)
+  };
+  let provider_response = match client.request(provider_request).await {
+    Ok(response) => response,
+    Err(err) => {
+      error!("Request to the provider failed - {}", err);
+      return error_response(502, format!("Request to the provider failed - {}", err));
+    }
+  };
+
+  let status = provider_response.status();
+  let headers = provider_response.headers().clone();
+  let body = match hyper::body::to_bytes(provider_response.into_body()).await {
+    Ok(body) => body,
+    Err(err) => return error_response(502, format!("Could not read the response from the provider - {}", err))
+  };
+
+  let mut response = pact_matching::models::Response {
+    status: status.as_u16(),
+    headers: recording.config.recorded_headers(extract_headers(&headers).unwrap_or_default()),
+    .. pact_matching::models::Response::default()
+  };
+  if !body.is_empty() {
+    response.body = OptionalBody::Present(body.clone(), response.content_type());
+  }
+  // Requests without a body are recorded without one, instead of with an empty body
+  let recorded_request = Request {
+    headers: recording.config.recorded_headers(request.headers.clone()),
+    body: match request.body {
+      OptionalBody::Empty => OptionalBody::Missing,
+      ref body => body.clone()
+    },
+    .. request
+  };
+  recording.record(recorded_request, response);
+
+  let mut builder = Response::builder().status(status);
+  for (name, value) in headers.iter() {
+    if !HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+      builder = builder.header(name.clone(), value.clone());
+    }
+  }
+  builder.body(Body::from(body))
+    .unwrap_or_else(|err| error_response(502, format!("Could not create the response - {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use reqwest::header::AUTHORIZATION;
+
+  use pact_matching::models::read_pact;
+  use pact_test_support::builders::{InteractionBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+
+  use crate::mock_server::MockServerConfig;
+  use crate::server_manager::ServerManager;
+
+  use super::*;
+
+  fn provider_pact() -> Box<dyn Pact> {
+    PactBuilder::new("consumer", "provider")
+      .interaction(InteractionBuilder::new("get the active users")
+        .request(RequestBuilder::get("/users").query_param("active", "true").build())
+        .response(ResponseBuilder::ok().json_body(json!([{ "id": 1, "name": "Mallory" }])).build())
+        .build())
+      .build()
+      .boxed()
+  }
+
+  fn start_proxy(runtime: &tokio::runtime::Runtime, provider_url: String) -> RecordingProxy {
+    let config = RecordingConfig {
+      provider_url,
+      consumer: "recorded_consumer".to_string(),
+      provider: "recorded_provider".to_string(),
+      ignore_headers: vec!["authorization".to_string()]
+    };
+    let (proxy, future) = runtime.block_on(RecordingProxy::start(config, ([127, 0, 0, 1], 0).into())).unwrap();
+    runtime.spawn(future);
+    proxy
+  }
+
+  #[test]
+  fn recording_proxy_records_the_requests_and_responses_from_the_provider() {
+    let mut manager = ServerManager::new();
+    let provider_port = manager.start_mock_server("recording_proxy_provider".to_string(), provider_pact(), 0,
+      MockServerConfig::default()).unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut proxy = start_proxy(&runtime, format!("http://127.0.0.1:{}/", provider_port));
+
+    let client = reqwest::blocking::Client::new();
+    let response = client.get(format!("{}/users?active=true", proxy.url()).as_str())
+      .header(AUTHORIZATION, "Bearer 1234")
+      .send()
+      .unwrap();
+    let status = response.status();
+    let body = response.text().unwrap();
+    proxy.shutdown().unwrap();
+    let provider_mismatches = manager.find_mock_server_by_id("recording_proxy_provider", &|ms| ms.mismatches());
+    manager.shutdown_mock_server_by_port(provider_port);
+
+    expect!(status.as_u16()).to(be_equal_to(200));
+    expect!(body).to(be_equal_to("[{\"id\":1,\"name\":\"Mallory\"}]"));
+    expect!(provider_mismatches).to(be_some().value(vec![]));
+    let interactions = proxy.interactions();
+    expect!(interactions.len()).to(be_equal_to(1));
+    let interaction = &interactions[0];
+    expect!(interaction.description.as_str()).to(be_equal_to("GET /users"));
+    expect!(interaction.request.query.clone()).to(be_some().value(hashmap!{
+      "active".to_string() => vec!["true".to_string()]
+    }));
+    expect!(interaction.request.body.clone()).to(be_equal_to(OptionalBody::Missing));
+    expect!(interaction.request.headers.clone().unwrap_or_default().keys()
+      .any(|name| name.to_lowercase() == "authorization")).to(be_false());
+    expect!(interaction.response.status).to(be_equal_to(200));
+    expect!(interaction.response.body.str_value()).to(be_equal_to("[{\"id\":1,\"name\":\"Mallory\"}]"));
+    expect!(interaction.response.lookup_header_value(&"content-type".to_string()))
+      .to(be_some().value("application/json"));
+  }
+
+  #[test]
+  fn recorded_pact_can_be_written_and_replayed_by_a_mock_server() {
+    let mut manager = ServerManager::new();
+    let provider_port = manager.start_mock_server("recorded_pact_provider".to_string(), provider_pact(), 0,
+      MockServerConfig::default()).unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut proxy = start_proxy(&runtime, format!("http://127.0.0.1:{}", provider_port));
+
+    let client = reqwest::blocking::Client::new();
+    client.get(format!("{}/users?active=true", proxy.url()).as_str()).send().unwrap();
+    proxy.shutdown().unwrap();
+    manager.shutdown_mock_server_by_port(provider_port);
+
+    let dir = std::env::temp_dir().join("pact_mock_server_recorded_pact");
+    let path = proxy.write_pact(&Some(dir.to_string_lossy().to_string()), true).unwrap();
+    let pact = read_pact(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap_or_default();
+
+    expect!(pact.consumer().name).to(be_equal_to("recorded_consumer"));
+    expect!(pact.provider().name).to(be_equal_to("recorded_provider"));
+
+    let port = manager.start_mock_server("recorded_pact_replay".to_string(), pact, 0,
+      MockServerConfig::default()).unwrap();
+    let response = client.get(format!("http://127.0.0.1:{}/users?active=true", port).as_str()).send().unwrap();
+    let mismatches = manager.find_mock_server_by_id("recorded_pact_replay", &|ms| ms.mismatches());
+    manager.shutdown_mock_server_by_port(port);
+
+    expect!(response.status().as_u16()).to(be_equal_to(200));
+    expect!(mismatches).to(be_some().value(vec![]));
+  }
+
+  #[test]
+  fn recording_proxy_returns_a_bad_gateway_response_if_the_provider_is_not_available() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let provider_port = listener.local_addr().unwrap().port();
+    drop(listener);
+    let mut proxy = start_proxy(&runtime, format!("http://127.0.0.1:{}", provider_port));
+
+    let response = reqwest::blocking::get(format!("{}/users", proxy.url()).as_str()).unwrap();
+    proxy.shutdown().unwrap();
+
+    expect!(response.status().as_u16()).to(be_equal_to(502));
+    expect!(proxy.interactions().is_empty()).to(be_true());
+  }
+
+  #[test]
+  fn recording_proxy_requires_an_http_provider_url() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let config = RecordingConfig { provider_url: "localhost:8080".to_string(), .. RecordingConfig::default() };
+    let result = runtime.block_on(RecordingProxy::start(config, ([127, 0, 0, 1], 0).into()));
+    expect!(result.map(|_| ())).to(be_err().value(
+      "'localhost:8080' is not a valid provider URL, it must be an HTTP or HTTPS URL".to_string()));
+  }
+
+  #[test]
+  fn record_ignores_duplicates_and_gives_each_interaction_a_unique_description() {
+    let recording = Recording { config: RecordingConfig::default(), interactions: Mutex::new(vec![]) };
+    let request = RequestBuilder::post("/users").build();
+    recording.record(request.clone(), ResponseBuilder::ok().build());
+    recording.record(request.clone(), ResponseBuilder::ok().build());
+    recording.record(request.clone(), ResponseBuilder::new().status(409).build());
+    recording.record(RequestBuilder::post("/users").body("other").build(), ResponseBuilder::ok().build());
+
+    let descriptions: Vec<String> = recording.interactions.lock().unwrap().iter()
+      .map(|i| i.description.clone())
+      .collect();
+    expect!(descriptions).to(be_equal_to(vec!["POST /users".to_string(), "POST /users (2)".to_string(),
+      "POST /users (3)".to_string()]));
+  }
+}
@@ -1,27 +1,78 @@
 //!
-//! This module defines a manager for holding multiple instances of mock servers.
+//! This module defines a manager for holding multiple instances of mock servers. It is the same
+//! manager the CLI master server uses, so test frameworks can manage many mock servers inside one
+//! process with the same semantics as the CLI (mock servers can be found by their ID or port
+//! number, verified, have their pact files written and be shut down).
 //!
 
 use std::collections::BTreeMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use log::*;
 use rustls::ServerConfig;
 
 use pact_matching::models::{Pact, RequestResponsePact};
+use pact_matching::models::pact_files::PactFileLayout;
 
-use crate::mock_server::{MockServer, MockServerConfig};
+use crate::matching::MatchResult;
+use crate::mock_server::{BindAddress, MockServer, MockServerConfig, PortSelection, ShutdownOptions, ShutdownResult};
+use crate::watcher::PactFileWatcher;
 
 struct ServerEntry {
   mock_server: Arc<Mutex<MockServer>>,
   join_handle: tokio::task::JoinHandle<()>,
 }
 
+struct VirtualHostEntry {
+  mock_server: Arc<Mutex<MockServer>>,
+  parent_id: String
+}
+
+/// Options for writing the pact file of a mock server
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WritePactOptions {
+  /// Directory to write the pact file to. If not set, the current working directory is used.
+  pub output_path: Option<String>,
+  /// If the pact file should be overwritten, instead of merged with any existing pact file
+  pub overwrite: bool,
+  /// Layout of the pact files in the output directory. If set, the index file in the directory
+  /// is also updated.
+  pub layout: Option<PactFileLayout>
+}
+
+/// Errors from verifying a mock server
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+  /// There is no mock server running with the ID or port number
+  NotFound(String),
+  /// The mock server received requests that did not match, or did not receive all the expected
+  /// requests
+  Mismatches(Vec<MatchResult>),
+  /// The pact file could not be written
+  WritePact(String)
+}
+
+impl std::fmt::Display for VerifyError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      VerifyError::NotFound(id) => write!(f, "No mock server running with id or port '{}'", id),
+      VerifyError::Mismatches(mismatches) => write!(f, "Mock server has {} mismatch(es)", mismatches.len()),
+      VerifyError::WritePact(err) => write!(f, "Failed to write pact to file - {}", err)
+    }
+  }
+}
+
+impl std::error::Error for VerifyError {}
+
 /// Struct to represent many mock servers running in a background thread
 pub struct ServerManager {
     runtime: tokio::runtime::Runtime,
     mock_servers: BTreeMap<String, ServerEntry>,
+    virtual_hosts: BTreeMap<String, VirtualHostEntry>,
+    watchers: BTreeMap<String, PactFileWatcher>
 }
 
 impl ServerManager {
@@ -34,7 +85,17 @@ impl ServerManager {
         .enable_all()
         .build()
         .unwrap(),
-      mock_servers: BTreeMap::new()
+      mock_servers: BTreeMap::new(),
+      virtual_hosts: BTreeMap::new(),
+      watchers: BTreeMap::new()
+    }
+  }
+
+  /// Shut down all the running mock servers
+  pub fn shutdown_all(&mut self) {
+    let ids: Vec<String> = self.mock_servers.keys().cloned().collect();
+    for id in ids {
+      self.shutdown_mock_server_by_id(id);
     }
   }
 
@@ -91,6 +152,57 @@ impl ServerManager {
       }
     }
 
+    /// Start a new server on the runtime, bound to the given address. If a range of ports is
+    /// given, the first free port in the range is used. Returns the address the server was bound to.
+    pub fn start_mock_server_with_bind_address(
+      &mut self,
+      id: String,
+      pact: Box<dyn Pact>,
+      bind_address: &BindAddress,
+      config: MockServerConfig
+    ) -> Result<SocketAddr, String> {
+      self.bind_to_first_free_address(bind_address, |manager, addr| {
+        manager.start_mock_server_with_addr(id.clone(), pact.boxed(), addr, config.clone())
+      })
+    }
+
+    /// Start a new TLS server on the runtime, bound to the given address. If a range of ports is
+    /// given, the first free port in the range is used. Returns the address the server was bound to.
+    pub fn start_tls_mock_server_with_bind_address(
+      &mut self,
+      id: String,
+      pact: Box<dyn Pact>,
+      bind_address: &BindAddress,
+      tls_config: &ServerConfig,
+      config: MockServerConfig
+    ) -> Result<SocketAddr, String> {
+      self.bind_to_first_free_address(bind_address, |manager, addr| {
+        manager.start_tls_mock_server_with_addr(id.clone(), pact.boxed(), addr, tls_config, config.clone())
+      })
+    }
+
+    fn bind_to_first_free_address<F>(&mut self, bind_address: &BindAddress, mut start: F) -> Result<SocketAddr, String>
+      where F: FnMut(&mut ServerManager, SocketAddr) -> Result<SocketAddr, String> {
+      let mut last_error = None;
+      for addr in bind_address.socket_addresses() {
+        match start(self, addr) {
+          Ok(addr) => {
+            debug!("Mock server bound to {} (requested {})", addr, bind_address);
+            return Ok(addr);
+          },
+          Err(err) => {
+            trace!("Could not bind mock server to {} - {}", addr, err);
+            last_error = Some(err);
+          }
+        }
+      }
+      match bind_address.ports {
+        PortSelection::Range(from, to) => Err(format!("Could not bind to any port in the range {}-{} on {} - {}",
+          from, to, bind_address.ip, last_error.unwrap_or_default())),
+        _ => Err(last_error.unwrap_or_default())
+      }
+    }
+
     /// Start a new server on the runtime
     pub fn start_mock_server(
       &mut self,
@@ -143,11 +255,19 @@ impl ServerManager {
 
     /// Shut down a server by its id
     pub fn shutdown_mock_server_by_id(&mut self, id: String) -> bool {
+      self.watchers.remove(&id);
+      if let Some(entry) = self.virtual_hosts.remove(&id) {
+        return self.remove_virtual_host_from_parent(&entry);
+      }
       match self.mock_servers.remove(&id) {
         Some(entry) => {
-          let mut ms = entry.mock_server.lock().unwrap();
-          debug!("Shutting down mock server with ID {} - {:?}", id, ms.metrics);
-          match ms.shutdown() {
+          self.remove_virtual_hosts_of(&id);
+          let result = {
+            let mut ms = entry.mock_server.lock().unwrap();
+            debug!("Shutting down mock server with ID {} - {:?}", id, ms.metrics);
+            ms.shutdown()
+          };
+          match result {
             Ok(()) => {
               self.runtime.block_on(entry.join_handle).unwrap();
               true
@@ -169,10 +289,15 @@ impl ServerManager {
         .map(|(_id, entry)| entry.mock_server.lock().unwrap().id.clone());
 
       if let Some(id) = result {
+        self.watchers.remove(&id);
         if let Some(entry) = self.mock_servers.remove(&id) {
-          let mut ms = entry.mock_server.lock().unwrap();
-          debug!("Shutting down mock server with port {} - {:?}", port, ms.metrics);
-          return match ms.shutdown() {
+          self.remove_virtual_hosts_of(&id);
+          let result = {
+            let mut ms = entry.mock_server.lock().unwrap();
+            debug!("Shutting down mock server with port {} - {:?}", port, ms.metrics);
+            ms.shutdown()
+          };
+          return match result {
             Ok(()) => {
               self.runtime.block_on(entry.join_handle).unwrap();
               true
@@ -185,18 +310,185 @@ impl ServerManager {
       false
     }
 
+    /// Gracefully shut down a server by its local port number. The server stops accepting new
+    /// connections, and in-flight requests are given until the timeout in the options to complete.
+    /// Once the server has stopped, the pact file is written if requested and all the interactions
+    /// were matched.
+    pub fn shutdown_mock_server_gracefully_by_port(
+      &mut self,
+      port: u16,
+      options: &ShutdownOptions
+    ) -> Result<ShutdownResult, String> {
+      let id = self
+        .mock_servers
+        .iter()
+        .find(|(_id, entry)| entry.mock_server.lock().unwrap().port.unwrap_or_default() == port)
+        .map(|(id, _entry)| id.clone())
+        .ok_or_else(|| format!("No mock server running on port {}", port))?;
+      let mut entry = self.mock_servers.remove(&id).unwrap();
+      self.watchers.remove(&id);
+      self.remove_virtual_hosts_of(&id);
+
+      // The lock must be released before waiting, as in-flight requests need it to complete
+      {
+        let mut ms = entry.mock_server.lock().unwrap();
+        debug!("Gracefully shutting down mock server with port {} - {:?}", port, ms.metrics);
+        ms.shutdown()?;
+      }
+
+      let timeout = options.timeout;
+      let completed = self.runtime.block_on(async {
+        tokio::time::timeout(timeout, &mut entry.join_handle).await.is_ok()
+      });
+      if !completed {
+        warn!("Mock server with port {} did not complete all in-flight requests within {:?}, stopping it",
+          port, timeout);
+        entry.join_handle.abort();
+      }
+
+      let ms = entry.mock_server.lock().unwrap();
+      ms.shutdown_result(options, completed).map_err(|err| format!("Failed to write pact file - {}", err))
+    }
+
+    /// Shut down a server by its ID or local port number. A value that is all digits is treated
+    /// as a port number.
+    pub fn shutdown_mock_server(&mut self, id_or_port: &str) -> bool {
+      match parse_port(id_or_port) {
+        Some(port) => self.shutdown_mock_server_by_port(port),
+        None => self.shutdown_mock_server_by_id(id_or_port.to_string())
+      }
+    }
+
+    /// Writes the pact file of the mock server with the ID or port number
+    pub fn write_pact(&self, id_or_port: &str, options: &WritePactOptions) -> Result<(), String> {
+      let result = self.find_mock_server(id_or_port, &|ms| match options.layout {
+        Some(layout) => ms.write_pact_with_layout(&options.output_path, options.overwrite, layout).map(|_| ()),
+        None => ms.write_pact(&options.output_path, options.overwrite)
+      });
+      match result {
+        Some(result) => result.map_err(|err| err.to_string()),
+        None => Err(format!("No mock server running with id or port '{}'", id_or_port))
+      }
+    }
+
+    /// Verifies the mock server with the ID or port number, in the same way as the `verify`
+    /// command of the CLI. If all the requests matched, the pact file is written with the options,
+    /// otherwise the mismatches are returned.
+    pub fn verify_mock_server(&self, id_or_port: &str, options: &WritePactOptions) -> Result<(), VerifyError> {
+      let mismatches = self.find_mock_server(id_or_port, &|ms| ms.mismatches())
+        .ok_or_else(|| VerifyError::NotFound(id_or_port.to_string()))?;
+      if !mismatches.is_empty() {
+        return Err(VerifyError::Mismatches(mismatches));
+      }
+      self.write_pact(id_or_port, options).map_err(VerifyError::WritePact)
+    }
+
+    /// Adds a virtual host to the mock server with the parent ID. Requests sent to the mock server
+    /// for the host name will be served from the pact by a new mock server with the given ID, which
+    /// shares the port of the parent mock server.
+    pub fn add_virtual_host(
+      &mut self,
+      parent_id: &str,
+      host: &str,
+      id: String,
+      pact: Box<dyn Pact>,
+      config: MockServerConfig
+    ) -> Result<(), String> {
+      if self.mock_servers.contains_key(&id) || self.virtual_hosts.contains_key(&id) {
+        return Err(format!("There is already a mock server with ID {}", id));
+      }
+      let parent = self.mock_servers.get(parent_id)
+        .ok_or_else(|| format!("No mock server with ID {}", parent_id))?;
+      let mock_server = parent.mock_server.lock().unwrap()
+        .add_virtual_host(id.clone(), host, pact, config)?;
+      self.virtual_hosts.insert(id, VirtualHostEntry {
+        mock_server,
+        parent_id: parent_id.to_string()
+      });
+      Ok(())
+    }
+
+    fn remove_virtual_host_from_parent(&self, entry: &VirtualHostEntry) -> bool {
+      let host = entry.mock_server.lock().unwrap().host.clone().unwrap_or_default();
+      debug!("Removing virtual host '{}' from mock server {}", host, entry.parent_id);
+      match self.mock_servers.get(&entry.parent_id) {
+        Some(parent) => parent.mock_server.lock().unwrap().remove_virtual_host(&host),
+        None => false
+      }
+    }
+
+    fn remove_virtual_hosts_of(&mut self, parent_id: &str) {
+      self.virtual_hosts.retain(|_, entry| entry.parent_id != parent_id);
+      let virtual_hosts = &self.virtual_hosts;
+      let mock_servers = &self.mock_servers;
+      self.watchers.retain(|id, _| mock_servers.contains_key(id) || virtual_hosts.contains_key(id));
+    }
+
+    /// Watches the pact files (or directories of pact files) that the mock server with the ID was
+    /// started from, and reloads its interactions when they change on disk (see `PactFileWatcher`).
+    /// The files are checked for changes every `interval`. Any existing watcher for the mock
+    /// server is replaced, and the watcher is stopped when the mock server is shut down.
+    pub fn watch_pact_files(
+      &mut self,
+      id: &str,
+      paths: Vec<PathBuf>,
+      interval: Duration
+    ) -> Result<(), String> {
+      let mock_server = self.find_entry_by_id(id)
+        .ok_or_else(|| format!("No mock server with ID {}", id))?;
+      let watcher = PactFileWatcher::start(paths, interval, mock_server)?;
+      debug!("Watching the pact files of mock server {}", id);
+      self.watchers.insert(id.to_string(), watcher);
+      Ok(())
+    }
+
+    fn find_entry_by_id(&self, id: &str) -> Option<&Arc<Mutex<MockServer>>> {
+      self.mock_servers.get(id).map(|entry| &entry.mock_server)
+        .or_else(|| self.virtual_hosts.get(id).map(|entry| &entry.mock_server))
+    }
+
     /// Find mock server by id, and map it using supplied function if found
     pub fn find_mock_server_by_id<R>(
       &self,
-      id: &String,
+      id: &str,
       f: &dyn Fn(&MockServer) -> R,
     ) -> Option<R> {
-      match self.mock_servers.get(id) {
-        Some(entry) => Some(f(&entry.mock_server.lock().unwrap())),
-        None => None,
+      self.find_entry_by_id(id).map(|mock_server| f(&mock_server.lock().unwrap()))
+    }
+
+    /// Find mock server by local port number, and map it using supplied function if found
+    pub fn find_mock_server_by_port<R>(
+      &self,
+      port: u16,
+      f: &dyn Fn(&MockServer) -> R,
+    ) -> Option<R> {
+      self.mock_servers.values()
+        .find(|entry| entry.mock_server.lock().unwrap().port.unwrap_or_default() == port)
+        .map(|entry| f(&entry.mock_server.lock().unwrap()))
+    }
+
+    /// Find mock server by id or local port number, and map it using supplied function if found.
+    /// A value that is all digits is treated as a port number.
+    pub fn find_mock_server<R>(
+      &self,
+      id_or_port: &str,
+      f: &dyn Fn(&MockServer) -> R,
+    ) -> Option<R> {
+      match parse_port(id_or_port) {
+        Some(port) => self.find_mock_server_by_port(port, f),
+        None => self.find_mock_server_by_id(id_or_port, f)
       }
     }
 
+    /// Find mock server by id, and apply a mutating operation on it if found
+    pub fn find_mock_server_by_id_mut<R>(
+      &mut self,
+      id: &str,
+      f: &dyn Fn(&mut MockServer) -> R,
+    ) -> Option<R> {
+      self.find_entry_by_id(id).map(|mock_server| f(&mut mock_server.lock().unwrap()))
+    }
+
     /// Find a mock server by port number and apply a mutating operation on it if successful
     pub fn find_mock_server_by_port_mut<R>(
       &mut self,
@@ -219,16 +511,35 @@ impl ServerManager {
       for (_id_, entry) in self.mock_servers.iter() {
         results.push(f(&entry.mock_server.lock().unwrap()));
       }
+      for (_id_, entry) in self.virtual_hosts.iter() {
+        results.push(f(&entry.mock_server.lock().unwrap()));
+      }
       return results;
     }
 }
 
+impl Default for ServerManager {
+  fn default() -> Self {
+    ServerManager::new()
+  }
+}
+
+fn parse_port(id_or_port: &str) -> Option<u16> {
+  if !id_or_port.is_empty() && id_or_port.chars().all(|ch| ch.is_ascii_digit()) {
+    id_or_port.parse().ok()
+  } else {
+    None
+  }
+}
+
 #[cfg(test)]
 mod tests {
-  use std::{thread, time};
+  use std::{env, fs, thread, time};
   use std::net::TcpStream;
 
   use env_logger;
+  use pact_matching::models::{Request, RequestResponseInteraction};
+  use pact_models::{Consumer, Provider};
 
   use super::*;
 
@@ -264,4 +575,135 @@ mod tests {
         // Server should be down
         assert!(TcpStream::connect(("127.0.0.1", server_port)).is_err());
     }
+
+    fn graceful_shutdown_pact(provider: &str) -> Box<dyn Pact> {
+      RequestResponsePact {
+        consumer: Consumer { name: "graceful_shutdown_consumer".to_string() },
+        provider: Provider { name: provider.to_string() },
+        interactions: vec![
+          RequestResponseInteraction {
+            description: "a request".to_string(),
+            request: Request { path: "/ping".to_string(), .. Request::default() },
+            .. RequestResponseInteraction::default()
+          }
+        ],
+        .. RequestResponsePact::default()
+      }.boxed()
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn graceful_shutdown_writes_the_pact_file_if_all_interactions_matched() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let dir = env::temp_dir().join("pact_mock_server_graceful_shutdown");
+        let pact_file = dir.join("graceful_shutdown_consumer-graceful_shutdown_provider.json");
+        let _ = fs::remove_file(&pact_file);
+
+        let mut manager = ServerManager::new();
+        let server_port = manager.start_mock_server("graceful".into(),
+          graceful_shutdown_pact("graceful_shutdown_provider"), 0, MockServerConfig::default()).unwrap();
+        let response = reqwest::blocking::get(format!("http://127.0.0.1:{}/ping", server_port)).unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+
+        let options = ShutdownOptions {
+          write_pact: true,
+          output_path: Some(dir.to_string_lossy().to_string()),
+          overwrite: true,
+          .. ShutdownOptions::default()
+        };
+        let result = manager.shutdown_mock_server_gracefully_by_port(server_port, &options).unwrap();
+        assert_eq!(result, ShutdownResult { completed: true, mismatches: vec![], pact_written: true });
+        assert!(pact_file.exists());
+        assert!(TcpStream::connect(("127.0.0.1", server_port)).is_err());
+
+        let result = manager.shutdown_mock_server_gracefully_by_port(server_port, &options);
+        assert_eq!(result, Err(format!("No mock server running on port {}", server_port)));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn graceful_shutdown_does_not_write_the_pact_file_if_there_are_mismatches() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let dir = env::temp_dir().join("pact_mock_server_graceful_shutdown");
+        let pact_file = dir.join("graceful_shutdown_consumer-graceful_shutdown_mismatch_provider.json");
+        let _ = fs::remove_file(&pact_file);
+
+        let mut manager = ServerManager::new();
+        let server_port = manager.start_mock_server("graceful_mismatch".into(),
+          graceful_shutdown_pact("graceful_shutdown_mismatch_provider"), 0, MockServerConfig::default()).unwrap();
+
+        let options = ShutdownOptions {
+          write_pact: true,
+          output_path: Some(dir.to_string_lossy().to_string()),
+          .. ShutdownOptions::default()
+        };
+        let result = manager.shutdown_mock_server_gracefully_by_port(server_port, &options).unwrap();
+        assert!(result.completed);
+        assert!(!result.matched());
+        assert!(!result.pact_written);
+        assert!(!pact_file.exists());
+    }
+
+    #[test]
+    fn start_mock_server_with_bind_address_uses_the_first_free_port_in_the_range() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+      let used_port = listener.local_addr().unwrap().port();
+      let bind_address = BindAddress::new([127, 0, 0, 1].into(), PortSelection::Range(used_port, used_port + 20));
+
+      let mut manager = ServerManager::new();
+      let result = manager.start_mock_server_with_bind_address("bind_address_range".into(),
+        RequestResponsePact::default().boxed(), &bind_address, MockServerConfig::default());
+
+      let addr = result.unwrap();
+      manager.shutdown_mock_server_by_port(addr.port());
+      assert_eq!(addr.ip().to_string(), "127.0.0.1");
+      assert!(addr.port() > used_port && addr.port() <= used_port + 20);
+
+      let bind_address = BindAddress::new([127, 0, 0, 1].into(), PortSelection::Range(used_port, used_port));
+      let result = manager.start_mock_server_with_bind_address("bind_address_range_in_use".into(),
+        RequestResponsePact::default().boxed(), &bind_address, MockServerConfig::default());
+      assert!(result.unwrap_err().starts_with(&format!("Could not bind to any port in the range {}-{} on 127.0.0.1",
+        used_port, used_port)));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn manager_finds_verifies_and_shuts_down_mock_servers_by_id_or_port() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      let dir = env::temp_dir().join("pact_mock_server_manager_verify");
+      let pact_file = dir.join("graceful_shutdown_consumer-manager_verify_provider.json");
+      let _ = fs::remove_file(&pact_file);
+
+      let mut manager = ServerManager::default();
+      let port = manager.start_mock_server("manager_verify".into(),
+        graceful_shutdown_pact("manager_verify_provider"), 0, MockServerConfig::default()).unwrap();
+      let other_port = manager.start_mock_server("manager_verify_other".into(),
+        graceful_shutdown_pact("manager_verify_other_provider"), 0, MockServerConfig::default()).unwrap();
+
+      assert_eq!(manager.find_mock_server("manager_verify", &|ms| ms.port), Some(Some(port)));
+      assert_eq!(manager.find_mock_server(&port.to_string(), &|ms| ms.id.clone()), Some("manager_verify".to_string()));
+      assert_eq!(manager.find_mock_server("unknown", &|ms| ms.id.clone()), None);
+
+      let options = WritePactOptions {
+        output_path: Some(dir.to_string_lossy().to_string()),
+        overwrite: true,
+        .. WritePactOptions::default()
+      };
+      match manager.verify_mock_server(&port.to_string(), &options) {
+        Err(VerifyError::Mismatches(mismatches)) => assert_eq!(mismatches.len(), 1),
+        result => panic!("Expected mismatches, got {:?}", result)
+      }
+      assert!(!pact_file.exists());
+
+      reqwest::blocking::get(format!("http://127.0.0.1:{}/ping", port)).unwrap();
+      assert_eq!(manager.verify_mock_server("manager_verify", &options), Ok(()));
+      assert!(pact_file.exists());
+      assert_eq!(manager.verify_mock_server("unknown", &options), Err(VerifyError::NotFound("unknown".to_string())));
+
+      assert!(manager.shutdown_mock_server(&port.to_string()));
+      assert!(!manager.shutdown_mock_server("manager_verify"));
+      manager.shutdown_all();
+      assert_eq!(manager.find_mock_server_by_port(other_port, &|ms| ms.id.clone()), None);
+    }
 }
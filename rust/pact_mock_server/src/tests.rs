@@ -1,15 +1,27 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
 use expectest::expect;
 use expectest::prelude::*;
 use maplit::*;
-use reqwest::header::ACCEPT;
+use reqwest::header::{ACCEPT, HOST};
+use serde_json::json;
 
 use pact_matching::matchingrules;
 use pact_matching::Mismatch;
+use pact_matching::path_template::PathTemplate;
 use pact_matching::models::{Interaction, Request, RequestResponseInteraction, Response};
+use pact_matching::models::generators::{Generator, GeneratorCategory};
 use pact_matching::models::matchingrules::*;
-use pact_models::OptionalBody;
+use pact_matching::models::v4::{SynchronousHttp, V4Pact};
+use pact_models::{OptionalBody, PactSpecification};
+use pact_test_support::builders::{InteractionBuilder, PactBuilder, RequestBuilder, ResponseBuilder};
+use pact_test_support::fixtures;
 
-use crate::matching::{match_request, MatchResult};
+use crate::matching::{match_request, match_request_as_stub, match_request_in_sequence, match_request_with_config, MatchResult, ResponseSequences};
+use crate::mock_server::{BindAddress, ConnectionOptions, FaultOptions, PortSelection, StubOptions};
 
 use super::*;
 
@@ -48,6 +60,252 @@ fn match_request_returns_a_match_for_multiple_identical_requests() {
     expect!(result).to(be_equal_to(MatchResult::RequestMatch(interaction.request, interaction.response)));
 }
 
+#[test]
+fn match_request_in_sequence_returns_the_responses_in_order_for_repeated_requests() {
+  let request = RequestBuilder::post("/users").build();
+  let interaction = InteractionBuilder::new("create a user")
+    .request(request.clone())
+    .response(ResponseBuilder::ok().build())
+    .build();
+  let interaction2 = InteractionBuilder::new("create a user again")
+    .request(request.clone())
+    .response(ResponseBuilder::new().status(409).build())
+    .build();
+  let other = InteractionBuilder::new("get the users")
+    .request(RequestBuilder::get("/users").build())
+    .build();
+  let interactions = || vec![
+    &interaction as &dyn Interaction,
+    &other as &dyn Interaction,
+    &interaction2 as &dyn Interaction
+  ];
+  let mut sequences = ResponseSequences::default();
+  let sequence = hashset!{ "create a user".to_string(), "create a user again".to_string() };
+
+  let status = |result: MatchResult| match result {
+    MatchResult::RequestMatch(_, response) => response.status,
+    _ => 0
+  };
+  expect!(status(match_request_in_sequence(&request, interactions(), &mut sequences, &sequence))).to(be_equal_to(200));
+  expect!(status(match_request_in_sequence(&request, interactions(), &mut sequences, &sequence))).to(be_equal_to(409));
+  expect!(status(match_request_in_sequence(&request, interactions(), &mut sequences, &sequence))).to(be_equal_to(409));
+  expect!(sequences.invocations(&request)).to(be_equal_to(3));
+
+  sequences.reset();
+  expect!(sequences.invocations(&request)).to(be_equal_to(0));
+  expect!(status(match_request_in_sequence(&request, interactions(), &mut sequences, &sequence))).to(be_equal_to(200));
+}
+
+#[test]
+fn match_request_in_sequence_returns_the_first_response_for_interactions_not_in_a_sequence() {
+  let request = RequestBuilder::post("/users").build();
+  let interaction = InteractionBuilder::new("create a user")
+    .request(request.clone())
+    .response(ResponseBuilder::ok().build())
+    .build();
+  let interaction2 = InteractionBuilder::new("create a user again")
+    .request(request.clone())
+    .response(ResponseBuilder::new().status(409).build())
+    .build();
+  let interactions = || vec![&interaction as &dyn Interaction, &interaction2 as &dyn Interaction];
+  let mut sequences = ResponseSequences::default();
+
+  let status = |result: MatchResult| match result {
+    MatchResult::RequestMatch(_, response) => response.status,
+    _ => 0
+  };
+  expect!(status(match_request_in_sequence(&request, interactions(), &mut sequences, &hashset!{}))).to(be_equal_to(200));
+  expect!(status(match_request_in_sequence(&request, interactions(), &mut sequences, &hashset!{}))).to(be_equal_to(200));
+  expect!(sequences.invocations(&request)).to(be_equal_to(0));
+}
+
+#[test]
+fn mock_server_returns_the_responses_of_a_sequence_of_interactions() {
+  let request = RequestBuilder::post("/users").build();
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("create a user")
+      .request(request.clone())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .interaction(InteractionBuilder::new("create a user again")
+      .request(request.clone())
+      .response(ResponseBuilder::new().status(409).build())
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "mock_server_returns_the_responses_of_a_sequence_of_interactions".to_string();
+  let config = MockServerConfig {
+    sequence_interactions: hashset!{ "create a user".to_string(), "create a user again".to_string() },
+    .. MockServerConfig::default()
+  };
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, config).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let url = format!("http://127.0.0.1:{}/users", port);
+  let response1 = client.post(url.as_str()).send().unwrap().status();
+  let mismatches_after_first_request = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches().len());
+  let response2 = client.post(url.as_str()).send().unwrap().status();
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+  manager.find_mock_server_by_port_mut(port, &|ms| ms.reset_sequences());
+  let response3 = client.post(url.as_str()).send().unwrap().status();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response1).to(be_equal_to(200));
+  expect!(mismatches_after_first_request).to(be_some().value(1));
+  expect!(response2).to(be_equal_to(409));
+  expect!(mismatches).to(be_some().value(vec![]));
+  expect!(response3).to(be_equal_to(200));
+}
+
+#[test]
+fn mock_server_matches_interactions_with_duplicate_requests_with_a_single_request() {
+  let request = RequestBuilder::get("/users/100").build();
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("get a user")
+      .request(request.clone())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .interaction(InteractionBuilder::new("get a user with a provider state")
+      .given("user 100 exists")
+      .request(request.clone())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "mock_server_matches_interactions_with_duplicate_requests_with_a_single_request".to_string();
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.get(format!("http://127.0.0.1:{}/users/100", port).as_str()).send().unwrap().status();
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response).to(be_equal_to(200));
+  expect!(mismatches).to(be_some().value(vec![]));
+}
+
+#[test]
+fn tls_mock_server_keeps_serving_after_a_failed_handshake() {
+  let tls = tls::TlsConfigBuilder::new()
+    .key(include_bytes!("../../pact_mock_server_ffi/src/self-signed.key"))
+    .cert(include_bytes!("../../pact_mock_server_ffi/src/self-signed.crt"))
+    .build()
+    .unwrap();
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("ping")
+      .request(RequestBuilder::get("/ping").build())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let port = manager.start_tls_mock_server("tls_mock_server_keeps_serving_after_a_failed_handshake".to_string(),
+    pact.boxed(), 0, &tls, MockServerConfig::default()).unwrap();
+
+  // A plain HTTP request fails the TLS handshake
+  let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+  stream.write_all(b"GET /ping HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+  let mut buffer = vec![];
+  let _ = stream.read_to_end(&mut buffer);
+
+  let client = reqwest::blocking::Client::builder().danger_accept_invalid_certs(true).build().unwrap();
+  let response = client.get(format!("https://127.0.0.1:{}/ping", port).as_str()).send();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.unwrap().status().as_u16()).to(be_equal_to(200));
+}
+
+fn stub_interaction() -> RequestResponseInteraction {
+  InteractionBuilder::new("create a user")
+    .request(RequestBuilder::post("/users")
+      .query_param("dryRun", "true")
+      .header("X-Api-Key", "1234")
+      .json_body(json!({ "name": "Fred" }))
+      .build())
+    .response(ResponseBuilder::new().status(201).build())
+    .build()
+}
+
+#[test]
+fn match_request_as_stub_ignores_body_and_query_mismatches_by_default() {
+  let interaction = stub_interaction();
+  let request = RequestBuilder::post("/users")
+    .query_param("dryRun", "false")
+    .header("X-Api-Key", "1234")
+    .json_body(json!({ "name": "Mary" }))
+    .build();
+  let options = StubOptions::default();
+
+  let strict = match_request(&request, vec![&interaction as &dyn Interaction]);
+  let stub = match_request_as_stub(&request, vec![&interaction as &dyn Interaction],
+    &options);
+
+  expect!(strict.matched()).to(be_false());
+  expect!(stub).to(be_equal_to(MatchResult::RequestMatch(interaction.request.clone(), interaction.response.clone())));
+}
+
+#[test]
+fn match_request_as_stub_only_ignores_the_configured_parts_of_the_request() {
+  let interaction = stub_interaction();
+  let request = RequestBuilder::post("/users")
+    .query_param("dryRun", "true")
+    .json_body(json!({ "name": "Fred" }))
+    .build();
+
+  let default_options = match_request_as_stub(&request, vec![&interaction as &dyn Interaction],
+    &StubOptions::default());
+  let ignore_headers = match_request_as_stub(&request, vec![&interaction as &dyn Interaction],
+    &"headers".parse().unwrap());
+  let other_path = match_request_as_stub(&RequestBuilder::post("/orders").build(),
+    vec![&interaction as &dyn Interaction], &"body,headers,query".parse().unwrap());
+
+  expect!(default_options.match_key()).to(be_equal_to("Request-Mismatch"));
+  expect!(ignore_headers.matched()).to(be_true());
+  expect!(other_path.match_key()).to(be_equal_to("Unexpected-Request"));
+}
+
+#[test]
+fn parse_stub_options() {
+  expect!("true".parse::<StubOptions>()).to(be_ok().value(StubOptions::default()));
+  expect!("headers, query".parse::<StubOptions>()).to(be_ok().value(StubOptions {
+    ignore_body: false,
+    ignore_headers: true,
+    ignore_query: true
+  }));
+  expect!("".parse::<StubOptions>()).to(be_ok().value(StubOptions {
+    ignore_body: false,
+    ignore_headers: false,
+    ignore_query: false
+  }));
+  expect!("path".parse::<StubOptions>()).to(be_err());
+}
+
+#[test]
+fn stub_server_serves_the_closest_matching_response() {
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(stub_interaction())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "stub_server_serves_the_closest_matching_response".to_string();
+  let config = MockServerConfig {
+    stub: Some(StubOptions::default()),
+    .. MockServerConfig::default()
+  };
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, config).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.post(format!("http://127.0.0.1:{}/users?dryRun=false", port).as_str())
+    .header("X-Api-Key", "1234")
+    .json(&json!({ "name": "Mary", "age": 21 }))
+    .send().unwrap().status();
+  let missing_header = client.post(format!("http://127.0.0.1:{}/users?dryRun=true", port).as_str())
+    .json(&json!({ "name": "Fred" }))
+    .send().unwrap().status();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response).to(be_equal_to(201));
+  expect!(missing_header).to(be_equal_to(500));
+}
+
 #[test]
 fn match_request_returns_a_match_for_multiple_requests() {
     let request = Request { method: s!("GET"), .. Request::default() };
@@ -237,3 +495,893 @@ fn match_request_with_more_specific_request() {
     &interaction1 as &dyn Interaction, &interaction2 as &dyn Interaction]);
   expect!(result2).to(be_equal_to(MatchResult::RequestMatch(expected.request, expected.response)));
 }
+
+#[test]
+fn match_request_with_extension_methods() {
+  let expected_request = Request {
+    method: s!("PURGE"),
+    path: s!("/cache"),
+    matching_rules: matchingrules! {
+      "method" => { "" => [ MatchingRule::Equality ] }
+    },
+    .. Request::default()
+  };
+  let interaction = RequestResponseInteraction {
+    request: expected_request,
+    .. RequestResponseInteraction::default()
+  };
+
+  let request = Request { method: s!("PURGE"), path: s!("/cache"), .. Request::default() };
+  let result = match_request(&request, vec![&interaction as &dyn Interaction]);
+  expect!(result).to(be_equal_to(MatchResult::RequestMatch(interaction.request.clone(),
+    interaction.response.clone())));
+
+  let request = Request { method: s!("purge"), path: s!("/cache"), .. Request::default() };
+  let result = match_request(&request, vec![&interaction as &dyn Interaction]);
+  expect!(result).to(be_equal_to(MatchResult::RequestNotFound(request)));
+}
+
+#[test]
+fn simulate_slow_responses_delays_the_response_by_the_interaction_timeout() {
+  let pact = V4Pact {
+    interactions: vec![
+      Box::new(SynchronousHttp {
+        timeout: Some(Duration::from_millis(300)),
+        .. SynchronousHttp::default()
+      })
+    ],
+    .. V4Pact::default()
+  };
+  let mut manager = ServerManager::new();
+  let id = "simulate_slow_responses_delays_the_response_by_the_interaction_timeout".to_string();
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig {
+    simulate_slow_responses: true,
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let start = Instant::now();
+  let response = client.get(format!("http://127.0.0.1:{}", port).as_str()).send();
+  let elapsed = start.elapsed();
+
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(mismatches).to(be_some().value(vec![]));
+  expect!(response.unwrap().status()).to(be_equal_to(200));
+  expect!(elapsed >= Duration::from_millis(300)).to(be_true());
+}
+
+#[test]
+fn read_timeout_returns_a_request_timeout_response_if_the_body_is_not_received_in_time() {
+  let mut manager = ServerManager::new();
+  let id = "read_timeout_returns_a_request_timeout_response_if_the_body_is_not_received_in_time".to_string();
+  let port = manager.start_mock_server(id.clone(), RequestResponsePact::default().boxed(), 0, MockServerConfig {
+    connections: ConnectionOptions { read_timeout: Some(Duration::from_millis(100)), .. ConnectionOptions::default() },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+  stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+  stream.write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\n12").unwrap();
+  let mut response = String::new();
+  let _ = stream.read_to_string(&mut response);
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.starts_with("HTTP/1.1 408")).to(be_true());
+}
+
+#[test]
+fn max_connections_limits_the_number_of_concurrent_connections() {
+  let mut manager = ServerManager::new();
+  let id = "max_connections_limits_the_number_of_concurrent_connections".to_string();
+  let port = manager.start_mock_server(id.clone(), RequestResponsePact::default().boxed(), 0, MockServerConfig {
+    connections: ConnectionOptions { max_connections: Some(1), .. ConnectionOptions::default() },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let first = TcpStream::connect(("127.0.0.1", port)).unwrap();
+  let client = reqwest::blocking::Client::builder()
+    .timeout(Duration::from_millis(300))
+    .build()
+    .unwrap();
+  let url = format!("http://127.0.0.1:{}", port);
+  let blocked = client.get(url.as_str()).send();
+  drop(first);
+  let allowed = client.get(url.as_str()).send();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(blocked.is_err()).to(be_true());
+  expect!(allowed.unwrap().status()).to(be_equal_to(500));
+}
+
+#[test]
+fn mock_server_returns_the_fixture_response_for_all_the_specification_versions() {
+  for spec in &[PactSpecification::V1, PactSpecification::V1_1, PactSpecification::V2, PactSpecification::V3,
+    PactSpecification::V4] {
+    let mut manager = ServerManager::new();
+    let id = format!("mock_server_returns_the_fixture_response_for_{}", spec.version_str());
+    let port = manager.start_mock_server(id.clone(), fixtures::pact(spec.clone()), 0,
+      MockServerConfig::default()).unwrap();
+
+    let client = reqwest::blocking::Client::new();
+    let response = client.get(format!("http://127.0.0.1:{}/mallory?name=ron&status=good", port).as_str())
+      .header(ACCEPT, "application/json")
+      .send()
+      .unwrap();
+    let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+    manager.shutdown_mock_server_by_port(port);
+
+    expect!(mismatches).to(be_some().value(vec![]));
+    expect!(response.status()).to(be_equal_to(200));
+    expect!(response.text().unwrap()).to(be_equal_to("{\"id\":100,\"name\":\"Mallory\"}"));
+  }
+}
+
+fn fault_injection_pact() -> Box<dyn Pact> {
+  PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("get the users")
+      .request(RequestBuilder::get("/users").build())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .interaction(InteractionBuilder::new("get the orders")
+      .request(RequestBuilder::get("/orders").build())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .build()
+    .boxed()
+}
+
+#[test]
+fn fault_injection_delays_the_responses() {
+  let mut manager = ServerManager::new();
+  let id = "fault_injection_delays_the_responses".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig {
+    faults: FaultOptions { delay: Some(Duration::from_millis(300)), .. FaultOptions::default() },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let start = Instant::now();
+  let response = client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send();
+  let elapsed = start.elapsed();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.unwrap().status()).to(be_equal_to(200));
+  expect!(elapsed >= Duration::from_millis(300)).to(be_true());
+}
+
+#[test]
+fn fault_injection_returns_server_errors_at_the_error_rate() {
+  let mut manager = ServerManager::new();
+  let id = "fault_injection_returns_server_errors_at_the_error_rate".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig {
+    faults: FaultOptions { error_rate: 1.0, .. FaultOptions::default() },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  let unexpected = client.get(format!("http://127.0.0.1:{}/other", port).as_str()).send().unwrap();
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.status()).to(be_equal_to(500));
+  expect!(response.headers().get("X-Pact").map(|h| h.to_str().unwrap().to_string()))
+    .to(be_some().value("Simulated-Error"));
+  expect!(unexpected.headers().get("X-Pact").map(|h| h.to_str().unwrap().to_string()))
+    .to(be_some().value("Unexpected-Request"));
+  // The request was still received, so only the missing and unexpected requests are reported
+  expect!(mismatches.unwrap().len()).to(be_equal_to(2));
+}
+
+#[test]
+fn fault_injection_resets_the_connection_at_the_reset_rate() {
+  let mut manager = ServerManager::new();
+  let id = "fault_injection_resets_the_connection_at_the_reset_rate".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig {
+    faults: FaultOptions { reset_rate: 1.0, .. FaultOptions::default() },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.is_err()).to(be_true());
+}
+
+#[test]
+fn fault_injection_for_an_interaction_replaces_the_global_faults() {
+  let mut manager = ServerManager::new();
+  let id = "fault_injection_for_an_interaction_replaces_the_global_faults".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig {
+    faults: FaultOptions { error_rate: 1.0, .. FaultOptions::default() },
+    interaction_faults: hashmap!{
+      "get the orders".to_string() => FaultOptions::default()
+    },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let users = client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  let orders = client.get(format!("http://127.0.0.1:{}/orders", port).as_str()).send().unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(users.status()).to(be_equal_to(500));
+  expect!(orders.status()).to(be_equal_to(200));
+}
+
+#[test]
+fn mock_server_streams_the_events_of_event_stream_responses() {
+  let events = "id: 1\ndata: first\n\nid: 2\ndata: second\n\nid: 3\ndata: third\n\n";
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("get the price events")
+      .request(RequestBuilder::get("/prices").build())
+      .response(ResponseBuilder::ok()
+        .header("Content-Type", "text/event-stream")
+        .body(events)
+        .build())
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "mock_server_streams_the_events_of_event_stream_responses".to_string();
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig {
+    event_stream_delay: Some(Duration::from_millis(200)),
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let start = Instant::now();
+  let response = client.get(format!("http://127.0.0.1:{}/prices", port).as_str()).send().unwrap();
+  let status = response.status();
+  let body = response.text().unwrap();
+  let elapsed = start.elapsed();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(status).to(be_equal_to(200));
+  expect!(body.as_str()).to(be_equal_to(events));
+  expect!(elapsed >= Duration::from_millis(400)).to(be_true());
+}
+
+#[test]
+fn mock_server_records_the_journal_of_matched_requests() {
+  let mut manager = ServerManager::new();
+  let id = "mock_server_records_the_journal_of_matched_requests".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig {
+    record_journal: true,
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  client.get(format!("http://127.0.0.1:{}/orders", port).as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/other", port).as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  let journal = manager.find_mock_server_by_id(&id, &|ms| ms.journal()).unwrap();
+  let in_order = manager.find_mock_server_by_id(&id, &|ms| ms.verify_order(&["get the orders", "get the users"])).unwrap();
+  let out_of_order = manager.find_mock_server_by_id(&id, &|ms| ms.verify_order(&["get the users", "get the orders"])).unwrap();
+  let missing = manager.find_mock_server_by_id(&id, &|ms| ms.verify_order(&["get the orders", "get the items"])).unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(journal.iter().map(|entry| entry.description.as_str()).collect::<Vec<_>>())
+    .to(be_equal_to(vec!["get the orders", "get the users"]));
+  expect!(journal[0].request.path.as_str()).to(be_equal_to("/orders"));
+  expect!(in_order).to(be_ok());
+  expect!(out_of_order).to(be_err().value("Interaction 'get the orders' received a request before interaction 'get the users'".to_string()));
+  expect!(missing).to(be_err().value("Interaction 'get the items' did not receive a request".to_string()));
+}
+
+#[test]
+fn mock_server_does_not_record_the_journal_by_default() {
+  let mut manager = ServerManager::new();
+  let id = "mock_server_does_not_record_the_journal_by_default".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  let journal = manager.find_mock_server_by_id(&id, &|ms| ms.journal()).unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(journal).to(be_equal_to(vec![]));
+}
+
+#[test]
+fn mock_server_collects_metrics_for_the_requests() {
+  let mut manager = ServerManager::new();
+  let id = "mock_server_collects_metrics_for_the_requests".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig {
+    metrics_endpoint: true,
+    interaction_faults: hashmap!{
+      "get the orders".to_string() => FaultOptions { error_rate: 1.0, .. FaultOptions::default() }
+    },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/orders", port).as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/other", port).as_str()).send().unwrap();
+  let metrics = client.get(format!("http://127.0.0.1:{}/__metrics", port).as_str()).send().unwrap();
+  let content_type = metrics.headers().get("content-type").map(|h| h.to_str().unwrap().to_string());
+  let text = metrics.text().unwrap();
+  let json = manager.find_mock_server_by_id(&id, &|ms| ms.metrics.to_json()).unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(json["requests"].clone()).to(be_equal_to(json!(4)));
+  expect!(json["matched"].clone()).to(be_equal_to(json!(3)));
+  expect!(json["unmatched"].clone()).to(be_equal_to(json!(1)));
+  expect!(json["errors"].clone()).to(be_equal_to(json!(1)));
+  expect!(json["interactions"].clone()).to(be_equal_to(json!({ "get the users": 2, "get the orders": 1 })));
+  expect!(json["responseTimes"]["p99"].is_number()).to(be_true());
+  expect!(content_type).to(be_some().value("text/plain; version=0.0.4; charset=utf-8"));
+  expect!(text.contains(&format!("pact_mock_server_requests_total{{mock_server=\"{}\"}} 4", id))).to(be_true());
+  expect!(text.contains(&format!("pact_mock_server_interaction_requests_total{{mock_server=\"{}\",interaction=\"get the users\"}} 2", id)))
+    .to(be_true());
+  expect!(text.contains(&format!("pact_mock_server_response_time_seconds_count{{mock_server=\"{}\"}} 4", id))).to(be_true());
+}
+
+#[test]
+fn mock_server_does_not_serve_the_metrics_by_default() {
+  let mut manager = ServerManager::new();
+  let id = "mock_server_does_not_serve_the_metrics_by_default".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.get(format!("http://127.0.0.1:{}/__metrics", port).as_str()).send().unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.status()).to(be_equal_to(500));
+  expect!(response.headers().get("X-Pact").map(|h| h.to_str().unwrap().to_string()))
+    .to(be_some().value("Unexpected-Request"));
+}
+
+#[test]
+fn admin_reset_route_clears_the_state_of_the_mock_server() {
+  let request = RequestBuilder::post("/users").build();
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("create a user")
+      .request(request.clone())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .interaction(InteractionBuilder::new("create a user again")
+      .request(request.clone())
+      .response(ResponseBuilder::new().status(409).build())
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "admin_reset_route_clears_the_state_of_the_mock_server".to_string();
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig {
+    admin_routes: true,
+    record_journal: true,
+    sequence_interactions: hashset!{ "create a user".to_string(), "create a user again".to_string() },
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let url = format!("http://127.0.0.1:{}/users", port);
+  client.post(url.as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/other", port).as_str()).send().unwrap();
+  let reset = client.post(format!("http://127.0.0.1:{}/__admin/reset", port).as_str()).send().unwrap().status();
+  let state_after_reset = manager.find_mock_server_by_id(&id, &|ms| (ms.matches().len(), ms.journal().len()));
+  let response = client.post(url.as_str()).send().unwrap().status();
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches().len());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(reset).to(be_equal_to(204));
+  expect!(state_after_reset).to(be_some().value((0, 0)));
+  expect!(response).to(be_equal_to(200));
+  expect!(mismatches).to(be_some().value(1));
+}
+
+#[test]
+fn admin_routes_are_not_served_by_default() {
+  let mut manager = ServerManager::new();
+  let id = "admin_routes_are_not_served_by_default".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.post(format!("http://127.0.0.1:{}/__admin/reset", port).as_str()).send().unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.status()).to(be_equal_to(500));
+}
+
+#[test]
+fn received_requests_json_returns_the_requests_with_their_match_status() {
+  let mut manager = ServerManager::new();
+  let id = "received_requests_json_returns_the_requests_with_their_match_status".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/orders?page=2", port).as_str()).send().unwrap();
+  client.get(format!("http://127.0.0.1:{}/other", port).as_str()).send().unwrap();
+  let requests = manager.find_mock_server_by_id(&id, &|ms| ms.received_requests_json());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(requests).to(be_some().value(json!([
+    { "status": "matched", "method": "GET", "path": "/users", "interaction": "get the users" },
+    {
+      "status": "mismatched",
+      "method": "GET",
+      "path": "/orders",
+      "mismatches": [ "Unexpected query parameter 'page' received" ]
+    },
+    { "status": "unexpected", "method": "GET", "path": "/other" }
+  ])));
+}
+
+#[test]
+fn mock_server_serves_the_health_endpoints() {
+  let mut manager = ServerManager::new();
+  let id = "mock_server_serves_the_health_endpoints".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig {
+    health_endpoints: true,
+    .. MockServerConfig::default()
+  }).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let health = client.get(format!("http://127.0.0.1:{}/healthz", port).as_str()).send().unwrap();
+  let health_status = health.status();
+  let health_json: serde_json::Value = health.json().unwrap();
+  let ready = client.get(format!("http://127.0.0.1:{}/readyz", port).as_str()).send().unwrap();
+  let ready_status = ready.status();
+  let ready_json: serde_json::Value = ready.json().unwrap();
+  let metrics = manager.find_mock_server_by_id(&id, &|ms| ms.metrics.requests);
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(health_status).to(be_equal_to(200));
+  expect!(health_json).to(be_equal_to(json!({ "status": "UP" })));
+  expect!(ready_status).to(be_equal_to(200));
+  expect!(ready_json).to(be_equal_to(json!({ "status": "UP", "interactions": 2 })));
+  expect!(metrics).to(be_some().value(0));
+}
+
+#[test]
+fn health_endpoints_are_not_served_by_default() {
+  let mut manager = ServerManager::new();
+  let id = "health_endpoints_are_not_served_by_default".to_string();
+  let port = manager.start_mock_server(id.clone(), fault_injection_pact(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.get(format!("http://127.0.0.1:{}/healthz", port).as_str()).send().unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response.status()).to(be_equal_to(500));
+}
+
+fn virtual_host_pact(path: &str) -> Box<dyn Pact> {
+  PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new(&format!("get {}", path))
+      .request(RequestBuilder::get(path).build())
+      .response(ResponseBuilder::ok().body(path).build())
+      .build())
+    .build()
+    .boxed()
+}
+
+#[test]
+fn virtual_hosts_serve_the_requests_for_their_host_name() {
+  let mut manager = ServerManager::new();
+  let id = "virtual_hosts_serve_the_requests_for_their_host_name".to_string();
+  let port = manager.start_mock_server(id.clone(), virtual_host_pact("/users"), 0,
+    MockServerConfig::default()).unwrap();
+  let virtual_host_id = format!("{}-orders", id);
+  manager.add_virtual_host(&id, "orders.example.com", virtual_host_id.clone(), virtual_host_pact("/orders"),
+    MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let users = client.get(format!("http://127.0.0.1:{}/users", port).as_str()).send().unwrap();
+  let orders = client.get(format!("http://127.0.0.1:{}/orders", port).as_str())
+    .header(HOST, format!("orders.example.com:{}", port))
+    .send().unwrap();
+  let users_on_virtual_host = client.get(format!("http://127.0.0.1:{}/users", port).as_str())
+    .header(HOST, "Orders.Example.com")
+    .send().unwrap();
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+  let virtual_host_mismatches = manager.find_mock_server_by_id(&virtual_host_id, &|ms| ms.mismatches());
+  let json = manager.find_mock_server_by_id(&virtual_host_id, &|ms| ms.to_json()).unwrap();
+  let parent_json = manager.find_mock_server_by_id(&id, &|ms| ms.to_json()).unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(users.status()).to(be_equal_to(200));
+  expect!(users.text().unwrap()).to(be_equal_to("/users"));
+  expect!(orders.status()).to(be_equal_to(200));
+  expect!(orders.text().unwrap()).to(be_equal_to("/orders"));
+  expect!(users_on_virtual_host.status()).to(be_equal_to(500));
+  expect!(mismatches).to(be_some().value(vec![]));
+  expect!(virtual_host_mismatches.unwrap().len()).to(be_equal_to(1));
+  expect!(json["host"].clone()).to(be_equal_to(json!("orders.example.com")));
+  expect!(json["port"].clone()).to(be_equal_to(json!(port)));
+  expect!(parent_json["virtualHosts"].clone()).to(be_equal_to(json!(["orders.example.com"])));
+  expect!(manager.find_mock_server_by_id(&virtual_host_id, &|ms| ms.id.clone())).to(be_none());
+}
+
+#[test]
+fn shutting_down_a_virtual_host_routes_its_requests_to_the_parent_mock_server() {
+  let mut manager = ServerManager::new();
+  let id = "shutting_down_a_virtual_host_routes_its_requests_to_the_parent_mock_server".to_string();
+  let port = manager.start_mock_server(id.clone(), virtual_host_pact("/users"), 0,
+    MockServerConfig::default()).unwrap();
+  let virtual_host_id = format!("{}-orders", id);
+  manager.add_virtual_host(&id, "orders.example.com", virtual_host_id.clone(), virtual_host_pact("/orders"),
+    MockServerConfig::default()).unwrap();
+
+  let shutdown = manager.shutdown_mock_server_by_id(virtual_host_id.clone());
+  let client = reqwest::blocking::Client::new();
+  let users = client.get(format!("http://127.0.0.1:{}/users", port).as_str())
+    .header(HOST, "orders.example.com")
+    .send().unwrap();
+  let virtual_hosts = manager.find_mock_server_by_id(&id, &|ms| ms.virtual_host_names());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(shutdown).to(be_true());
+  expect!(users.status()).to(be_equal_to(200));
+  expect!(virtual_hosts).to(be_some().value(Vec::<String>::new()));
+}
+
+#[test]
+fn add_virtual_host_errors() {
+  let mut manager = ServerManager::new();
+  let id = "add_virtual_host_errors".to_string();
+  let port = manager.start_mock_server(id.clone(), virtual_host_pact("/users"), 0,
+    MockServerConfig::default()).unwrap();
+  let first = manager.add_virtual_host(&id, "orders.example.com", "add_virtual_host_errors-1".to_string(),
+    virtual_host_pact("/orders"), MockServerConfig::default());
+  let duplicate_host = manager.add_virtual_host(&id, "ORDERS.example.com", "add_virtual_host_errors-2".to_string(),
+    virtual_host_pact("/orders"), MockServerConfig::default());
+  let duplicate_id = manager.add_virtual_host(&id, "other.example.com", id.clone(),
+    virtual_host_pact("/orders"), MockServerConfig::default());
+  let unknown_parent = manager.add_virtual_host("unknown", "other.example.com", "add_virtual_host_errors-3".to_string(),
+    virtual_host_pact("/orders"), MockServerConfig::default());
+  let nested = manager.add_virtual_host("add_virtual_host_errors-1", "other.example.com",
+    "add_virtual_host_errors-4".to_string(), virtual_host_pact("/orders"), MockServerConfig::default());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(first).to(be_ok());
+  expect!(duplicate_host).to(be_err().value("Mock server add_virtual_host_errors already has a virtual host for 'orders.example.com'".to_string()));
+  expect!(duplicate_id).to(be_err().value("There is already a mock server with ID add_virtual_host_errors".to_string()));
+  expect!(unknown_parent).to(be_err().value("No mock server with ID unknown".to_string()));
+  expect!(nested).to(be_err().value("No mock server with ID add_virtual_host_errors-1".to_string()));
+}
+
+#[test]
+fn parse_bind_address() {
+  expect!("0.0.0.0".parse::<BindAddress>()).to(be_ok().value(BindAddress::new([0, 0, 0, 0].into(), PortSelection::Any)));
+  expect!("127.0.0.1:8080".parse::<BindAddress>()).to(be_ok().value(BindAddress::new([127, 0, 0, 1].into(), PortSelection::Port(8080))));
+  expect!("127.0.0.1:0".parse::<BindAddress>()).to(be_ok().value(BindAddress::new([127, 0, 0, 1].into(), PortSelection::Any)));
+  expect!(":8000-8100".parse::<BindAddress>()).to(be_ok().value(BindAddress::new([127, 0, 0, 1].into(), PortSelection::Range(8000, 8100))));
+  expect!("[::1]:8000-8100".parse::<BindAddress>()).to(be_ok().value(BindAddress::new("::1".parse().unwrap(), PortSelection::Range(8000, 8100))));
+  expect!("::".parse::<BindAddress>()).to(be_ok().value(BindAddress::new("::".parse().unwrap(), PortSelection::Any)));
+  expect!("localhost:8080".parse::<BindAddress>()).to(be_err());
+  expect!("127.0.0.1:80a".parse::<BindAddress>()).to(be_err());
+  expect!("127.0.0.1:8100-8000".parse::<BindAddress>()).to(be_err());
+  expect!("[::1:8080".parse::<BindAddress>()).to(be_err());
+}
+
+#[test]
+fn bind_address_to_string() {
+  expect!(BindAddress::new([0, 0, 0, 0].into(), PortSelection::Any).to_string()).to(be_equal_to("0.0.0.0"));
+  expect!(BindAddress::new("::1".parse().unwrap(), PortSelection::Range(8000, 8100)).to_string()).to(be_equal_to("[::1]:8000-8100"));
+  expect!(BindAddress::from(std::net::SocketAddr::from(([127, 0, 0, 1], 1234))).to_string()).to(be_equal_to("127.0.0.1:1234"));
+}
+
+
+#[test]
+fn mock_server_serves_the_interactions_from_all_the_pacts() {
+  let web = PactBuilder::new("web", "users")
+    .interaction(InteractionBuilder::new("get user 1")
+      .request(RequestBuilder::get("/users/1").build())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .build();
+  let mobile = PactBuilder::new("mobile", "users")
+    .interaction(InteractionBuilder::new("get user 2")
+      .request(RequestBuilder::get("/users/2").build())
+      .response(ResponseBuilder::new().status(404).build())
+      .build())
+    .build();
+  let id = "mock_server_serves_the_interactions_from_all_the_pacts".to_string();
+  let addr = start_mock_server_for_pacts(id, &[web.boxed(), mobile.boxed()], &BindAddress::default(),
+    MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response1 = client.get(format!("http://127.0.0.1:{}/users/1", addr.port()).as_str()).send().unwrap().status();
+  let response2 = client.get(format!("http://127.0.0.1:{}/users/2", addr.port()).as_str()).send().unwrap().status();
+  shutdown_mock_server(addr.port() as i32);
+
+  expect!(response1).to(be_equal_to(200));
+  expect!(response2).to(be_equal_to(404));
+}
+
+#[test]
+fn mock_server_with_strict_headers_rejects_requests_with_unexpected_headers() {
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("get a user")
+      .request(RequestBuilder::get("/users/1").header("Accept", "application/json").build())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .interaction(InteractionBuilder::new("get an order")
+      .request(RequestBuilder::get("/orders/1").header("Accept", "application/json").build())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "mock_server_with_strict_headers_rejects_requests_with_unexpected_headers".to_string();
+  let config = MockServerConfig {
+    strict_header_interactions: hashset!{ "get a user".to_string() },
+    .. MockServerConfig::default()
+  };
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, config).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let user = |cookie: bool| {
+    let request = client.get(format!("http://127.0.0.1:{}/users/1", port).as_str())
+      .header(ACCEPT, "application/json");
+    if cookie { request.header("Cookie", "session=1234") } else { request }
+      .send().unwrap().status()
+  };
+  let response1 = user(false);
+  let response2 = user(true);
+  let response3 = client.get(format!("http://127.0.0.1:{}/orders/1", port).as_str())
+    .header(ACCEPT, "application/json")
+    .header("Cookie", "session=1234")
+    .send().unwrap().status();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response1).to(be_equal_to(200));
+  expect!(response2).to(be_equal_to(500));
+  expect!(response3).to(be_equal_to(200));
+}
+
+#[test]
+fn match_request_with_config_can_allow_unexpected_keys_in_the_request_body() {
+  let interaction = InteractionBuilder::new("create a user")
+    .request(RequestBuilder::post("/users").json_body(json!({ "name": "Mary" })).build())
+    .response(ResponseBuilder::ok().build())
+    .build();
+  let request = RequestBuilder::post("/users")
+    .json_body(json!({ "name": "Mary", "nickname": "May" }))
+    .build();
+  let config = |allow_unexpected_keys, interactions| MockServerConfig {
+    allow_unexpected_keys,
+    allow_unexpected_keys_interactions: interactions,
+    .. MockServerConfig::default()
+  };
+
+  let strict = match_request_with_config(&request, vec![&interaction as &dyn Interaction],
+    &mut ResponseSequences::default(), &config(false, hashset!{}));
+  let allowed = match_request_with_config(&request, vec![&interaction as &dyn Interaction],
+    &mut ResponseSequences::default(), &config(true, hashset!{}));
+  let allowed_for_interaction = match_request_with_config(&request, vec![&interaction as &dyn Interaction],
+    &mut ResponseSequences::default(), &config(false, hashset!{ "create a user".to_string() }));
+
+  expect!(strict.matched()).to(be_false());
+  expect!(allowed.matched()).to(be_true());
+  expect!(allowed_for_interaction.matched()).to(be_true());
+}
+
+#[test]
+fn mock_server_returns_a_mismatch_for_query_strings_with_malformed_encodings() {
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("search for users")
+      .request(RequestBuilder::get("/users").query_param("name", "100%").build())
+      .response(ResponseBuilder::ok().build())
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "mock_server_returns_a_mismatch_for_query_strings_with_malformed_encodings".to_string();
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response1 = client.get(format!("http://127.0.0.1:{}/users?name=100%25", port).as_str())
+    .send().unwrap().status();
+  let response2 = client.get(format!("http://127.0.0.1:{}/users?name=100%", port).as_str())
+    .send().unwrap().status();
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(response1).to(be_equal_to(200));
+  expect!(response2).to(be_equal_to(500));
+  expect!(mismatches.unwrap_or_default().len()).to(be_equal_to(1));
+}
+
+#[test]
+fn mock_server_generates_response_values_from_path_template_parameters() {
+  let template = PathTemplate::parse("/users/{id:\\d+}/orders/{orderId}").unwrap();
+  let mut response = ResponseBuilder::ok().json_body(json!({ "userId": 1, "orderId": "1" })).build();
+  response.generators.add_generator_with_subcategory(&GeneratorCategory::BODY, "$.userId",
+    Generator::PathParameter("id".to_string()));
+  response.generators.add_generator_with_subcategory(&GeneratorCategory::BODY, "$.orderId",
+    Generator::PathParameter("orderId".to_string()));
+  let pact = PactBuilder::new("consumer", "provider")
+    .interaction(InteractionBuilder::new("get an order")
+      .request(RequestBuilder::get("/users/1/orders/1")
+        .matching_rule("path", "", MatchingRule::Regex(template.to_regex()))
+        .build())
+      .response(response)
+      .build())
+    .build();
+  let mut manager = ServerManager::new();
+  let id = "mock_server_generates_response_values_from_path_template_parameters".to_string();
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig::default()).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let response = client.get(format!("http://127.0.0.1:{}/users/100/orders/a2", port).as_str())
+    .send().unwrap();
+  let status = response.status();
+  let body: serde_json::Value = response.json().unwrap();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(status).to(be_equal_to(200));
+  expect!(body).to(be_equal_to(json!({ "userId": 100, "orderId": "a2" })));
+}
+
+#[test]
+fn mock_server_reloads_the_pact_when_the_pact_file_changes() {
+  let dir = std::env::temp_dir().join("mock_server_reloads_the_pact_when_the_pact_file_changes");
+  std::fs::remove_dir_all(&dir).unwrap_or_default();
+  std::fs::create_dir_all(&dir).unwrap();
+  let pact_file = dir.join("consumer-provider.json");
+  let write_pact = |path: &str, status: u16| {
+    let pact = PactBuilder::new("consumer", "provider")
+      .interaction(InteractionBuilder::new("get a user")
+        .request(RequestBuilder::get(path).build())
+        .response(ResponseBuilder::new().status(status).build())
+        .build())
+      .build();
+    std::fs::write(&pact_file, pact.to_json(PactSpecification::V3).to_string()).unwrap();
+    pact
+  };
+  let pact = write_pact("/users/1", 200);
+  let mut manager = ServerManager::new();
+  let id = "mock_server_reloads_the_pact_when_the_pact_file_changes".to_string();
+  let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig::default()).unwrap();
+  manager.watch_pact_files(&id, vec![dir.clone()], Duration::from_millis(20)).unwrap();
+
+  let client = reqwest::blocking::Client::new();
+  let get = |path: &str| client.get(format!("http://127.0.0.1:{}{}", port, path).as_str()).send().unwrap().status();
+  let before = get("/users/1");
+  write_pact("/users/100", 404);
+  let start = Instant::now();
+  while get("/users/100") != 404 && start.elapsed() < Duration::from_secs(5) {
+    std::thread::sleep(Duration::from_millis(20));
+  }
+  let after = get("/users/100");
+  let old_request = get("/users/1");
+  let interactions = manager.find_mock_server_by_id(&id, &|ms| ms.pact.lock().unwrap().interactions().len());
+  manager.shutdown_mock_server_by_port(port);
+  std::fs::remove_dir_all(&dir).unwrap_or_default();
+
+  expect!(before).to(be_equal_to(200));
+  expect!(after).to(be_equal_to(404));
+  expect!(old_request).to(be_equal_to(500));
+  expect!(interactions).to(be_some().value(1));
+  expect!(manager.watch_pact_files(&id, vec![], Duration::from_millis(20))).to(be_err());
+}
+
+#[test]
+fn generator_seed_makes_the_generated_responses_reproducible() {
+  let interaction = RequestResponseInteraction {
+    response: Response {
+      body: OptionalBody::Present("{\"id\": \"1234\", \"count\": 10}".into(), None),
+      generators: pact_matching::generators! {
+        "BODY" => {
+          "$.id" => Generator::Uuid,
+          "$.count" => Generator::RandomInt(1, 1000000)
+        }
+      },
+      .. Response::default()
+    },
+    .. RequestResponseInteraction::default()
+  };
+  let pact = RequestResponsePact { interactions: vec![interaction], .. RequestResponsePact::default() };
+
+  let mut bodies = vec![];
+  for run in 0..2 {
+    let mut manager = ServerManager::new();
+    let id = format!("generator_seed_makes_the_generated_responses_reproducible_{}", run);
+    let port = manager.start_mock_server(id.clone(), pact.boxed(), 0, MockServerConfig {
+      generator_seed: Some(100),
+      .. MockServerConfig::default()
+    }).unwrap();
+
+    let client = reqwest::blocking::Client::new();
+    for _ in 0..2 {
+      let response = client.get(format!("http://127.0.0.1:{}", port).as_str()).send().unwrap();
+      bodies.push(response.text().unwrap());
+    }
+    manager.shutdown_mock_server_by_port(port);
+  }
+
+  expect!(bodies.iter().all(|body| body == &bodies[0])).to(be_true());
+  expect!(bodies[0].contains("1234")).to(be_false());
+}
+
+fn websocket_pact() -> V4Pact {
+  use pact_matching::models::v4::SynchronousMessages;
+  use pact_matching::models::v4::http_parts::HttpRequest;
+  use pact_matching::models::v4::message_parts::MessageContents;
+  use pact_matching::models::v4::websocket::set_upgrade_request;
+
+  let message = |body: &str| MessageContents {
+    contents: OptionalBody::Present(body.to_string().into(), Some("application/json".into())),
+    .. MessageContents::default()
+  };
+  let mut interaction = SynchronousMessages {
+    description: "a price feed".to_string(),
+    request: message(r#"{"subscribe":"AAPL"}"#),
+    response: vec![message(r#"{"symbol":"AAPL","price":100}"#), message(r#"{"symbol":"AAPL","price":101}"#)],
+    .. SynchronousMessages::default()
+  };
+  set_upgrade_request(&mut interaction, &HttpRequest { path: "/prices".to_string(), .. HttpRequest::default() });
+  V4Pact {
+    interactions: vec![
+      Box::new(interaction),
+      Box::new(SynchronousHttp::default())
+    ],
+    .. V4Pact::default()
+  }
+}
+
+fn exchange_websocket_messages(port: u16, message: &str) -> Vec<String> {
+  use futures::{SinkExt, StreamExt};
+  use tokio_tungstenite::tungstenite::Message as Frame;
+
+  let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+  runtime.block_on(async {
+    let (mut socket, response) = tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}/prices", port))
+      .await.unwrap();
+    expect!(response.status().as_u16()).to(be_equal_to(101));
+    socket.send(Frame::Text(message.to_string())).await.unwrap();
+    let mut received = vec![];
+    while let Some(Ok(frame)) = socket.next().await {
+      match frame {
+        Frame::Text(text) => received.push(text),
+        Frame::Close(_) => break,
+        _ => ()
+      }
+      if received.len() == 2 {
+        break;
+      }
+    }
+    socket.close(None).await.ok();
+    received
+  })
+}
+
+#[test]
+fn mock_server_exchanges_the_messages_of_websocket_interactions() {
+  let mut manager = ServerManager::new();
+  let id = "mock_server_exchanges_the_messages_of_websocket_interactions".to_string();
+  let port = manager.start_mock_server(id.clone(), websocket_pact().boxed(), 0, MockServerConfig::default()).unwrap();
+
+  let received = exchange_websocket_messages(port, r#"{"subscribe":"AAPL"}"#);
+  let response = reqwest::blocking::get(format!("http://127.0.0.1:{}/", port).as_str()).unwrap().status();
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches());
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(received).to(be_equal_to(vec![
+    r#"{"symbol":"AAPL","price":100}"#.to_string(),
+    r#"{"symbol":"AAPL","price":101}"#.to_string()
+  ]));
+  expect!(response).to(be_equal_to(200));
+  expect!(mismatches.unwrap_or_default().iter()).to(be_empty());
+}
+
+#[test]
+fn mock_server_returns_a_mismatch_for_unexpected_websocket_messages() {
+  let mut manager = ServerManager::new();
+  let id = "mock_server_returns_a_mismatch_for_unexpected_websocket_messages".to_string();
+  let port = manager.start_mock_server(id.clone(), websocket_pact().boxed(), 0, MockServerConfig::default()).unwrap();
+
+  let received = exchange_websocket_messages(port, r#"{"subscribe":"MSFT"}"#);
+  let mismatches = manager.find_mock_server_by_id(&id, &|ms| ms.mismatches()).unwrap_or_default();
+  manager.shutdown_mock_server_by_port(port);
+
+  expect!(received.iter()).to(be_empty());
+  expect!(mismatches.iter().map(|m| m.match_key()).collect::<Vec<_>>()).to(be_equal_to(vec![
+    "Request-Mismatch".to_string(), "Missing-Request".to_string()
+  ]));
+}
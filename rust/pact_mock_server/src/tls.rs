@@ -23,8 +23,18 @@
 use std::fs::File;
 use std::io::{self, BufReader, Cursor, Read};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
+use futures::{stream, Stream};
+use log::*;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio_rustls::rustls::{NoClientAuth, ServerConfig, TLSError};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::hyper_server::connection_permit;
 
 /// Represents errors that can occur building the TlsConfig
 #[derive(Debug)]
@@ -156,6 +166,35 @@ impl TlsConfigBuilder {
   }
 }
 
+/// Accepts the connections from the listener and performs the TLS handshake with them, for
+/// serving HTTPS with a hyper server. Connections where the handshake fails are logged and
+/// skipped, so a bad client does not stop the server. If there is a connection limit, a permit is
+/// acquired before each connection is accepted, and is returned with the connection so that it is
+/// released when the connection is closed.
+pub fn accept_tls_connections(
+  listener: TcpListener,
+  tls_cfg: ServerConfig,
+  limit: Option<Arc<Semaphore>>
+) -> impl Stream<Item = io::Result<(TlsStream<TcpStream>, Option<OwnedSemaphorePermit>)>> + Send {
+  let acceptor = TlsAcceptor::from(Arc::new(tls_cfg));
+  stream::unfold((listener, acceptor, limit), |(listener, acceptor, limit)| async move {
+    let permit = connection_permit(&limit).await;
+    loop {
+      match listener.accept().await {
+        Ok((socket, _)) => match acceptor.accept(socket).await {
+          Ok(stream) => return Some((Ok((stream, permit)), (listener, acceptor, limit))),
+          Err(err) => warn!("TLS handshake with the client failed - {}", err)
+        },
+        Err(err) => {
+          // Errors like running out of file handles should not stop the server, so wait a bit and try again
+          error!("Failed to accept TLS connection - {:?}", err);
+          tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+      }
+    }
+  })
+}
+
 struct LazyFile {
   path: PathBuf,
   file: Option<File>,
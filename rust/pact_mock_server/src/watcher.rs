@@ -0,0 +1,130 @@
+//!
+//! Watches the pact files a mock server was started from, and reloads the interactions of the
+//! mock server when they change on disk. This is for long-lived stub servers in development
+//! environments, which can then pick up new versions of the pacts without being restarted.
+//!
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use log::*;
+
+use crate::mock_server::MockServer;
+use crate::multi_pact::{load_combined_pact, pact_files_in_dir};
+
+/// Modification time and size of each of the watched pact files
+type Fingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+fn fingerprint(paths: &[PathBuf]) -> Result<Fingerprint, String> {
+  let mut files = vec![];
+  for path in paths {
+    if path.is_dir() {
+      files.extend(pact_files_in_dir(path).map_err(|err| err.to_string())?);
+    } else {
+      files.push(path.clone());
+    }
+  }
+  files.iter().map(|file| {
+    let metadata = fs::metadata(file)
+      .map_err(|err| format!("Could not read the pact file '{}' - {}", file.display(), err))?;
+    Ok((file.clone(), metadata.modified().ok(), metadata.len()))
+  }).collect()
+}
+
+/// Watches the pact files (or directories of pact files) of a mock server, and updates the mock
+/// server with the new pact when any of them change. The files are polled for changes, and if
+/// the pact can not be loaded (i.e. the file is only partially written), the mock server keeps
+/// the interactions it has until the next change.
+///
+/// The watcher stops when it is dropped, or when the mock server it is watching is dropped.
+#[derive(Debug)]
+pub struct PactFileWatcher {
+  stop: Arc<AtomicBool>,
+  handle: Option<thread::JoinHandle<()>>
+}
+
+impl PactFileWatcher {
+  /// Starts watching the paths, checking them for changes every `interval`. Returns an error if
+  /// any of the paths can not be read.
+  pub fn start(
+    paths: Vec<PathBuf>,
+    interval: Duration,
+    mock_server: &Arc<Mutex<MockServer>>
+  ) -> Result<PactFileWatcher, String> {
+    let mut last = fingerprint(&paths)?;
+    let mock_server = Arc::downgrade(mock_server);
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    let handle = thread::Builder::new()
+      .name("pact-file-watcher".to_string())
+      .spawn(move || {
+        while wait(&stop_flag, interval) && mock_server.strong_count() > 0 {
+          match fingerprint(&paths) {
+            Ok(current) if current != last => {
+              last = current;
+              if !reload(&paths, &mock_server) {
+                break;
+              }
+            },
+            Ok(_) => (),
+            Err(err) => debug!("Not reloading the pact files - {}", err)
+          }
+        }
+      })
+      .map_err(|err| format!("Could not start the pact file watcher - {}", err))?;
+    Ok(PactFileWatcher { stop, handle: Some(handle) })
+  }
+
+  /// Stops watching the pact files
+  pub fn stop(mut self) {
+    self.stop_and_join();
+  }
+
+  fn stop_and_join(&mut self) {
+    self.stop.store(true, Ordering::SeqCst);
+    if let Some(handle) = self.handle.take() {
+      handle.join().unwrap_or_default();
+    }
+  }
+}
+
+impl Drop for PactFileWatcher {
+  fn drop(&mut self) {
+    self.stop_and_join();
+  }
+}
+
+/// Waits for the interval, returning false if the watcher was stopped in the meantime
+fn wait(stop: &AtomicBool, interval: Duration) -> bool {
+  let start = Instant::now();
+  while start.elapsed() < interval {
+    if stop.load(Ordering::SeqCst) {
+      return false;
+    }
+    thread::sleep(Duration::from_millis(10).min(interval));
+  }
+  !stop.load(Ordering::SeqCst)
+}
+
+/// Reloads the pact of the mock server. Returns false if the mock server no longer exists.
+fn reload(paths: &[PathBuf], mock_server: &Weak<Mutex<MockServer>>) -> bool {
+  let mock_server = match mock_server.upgrade() {
+    Some(mock_server) => mock_server,
+    None => return false
+  };
+  match load_combined_pact(paths) {
+    Ok(pact) => {
+      let mut ms = mock_server.lock().unwrap();
+      match ms.update_pact(pact) {
+        Ok(()) => info!("Reloaded the pact for mock server {} as the pact files have changed", ms.id),
+        Err(err) => warn!("Could not reload the pact for mock server {} - {}", ms.id, err)
+      }
+    },
+    Err(err) => warn!("Could not reload the pact files - {}", err)
+  }
+  true
+}
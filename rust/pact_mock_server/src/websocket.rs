@@ -0,0 +1,171 @@
+//!
+//! Serves the WebSocket interactions of a pact. When a request upgrades the connection and matches
+//! the upgrade request of a WebSocket interaction, the frame the client sends is matched against
+//! the request message of the interaction, and the response messages are sent back as frames.
+//!
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use hyper::{Body, Response};
+use hyper::upgrade::OnUpgrade;
+use log::*;
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message as Frame;
+use tokio_tungstenite::WebSocketStream;
+
+use pact_matching::{generate_message_contents, match_request, match_sync_message_request, Mismatch};
+use pact_matching::models::{Request, Response as PactResponse};
+use pact_matching::models::generators::GeneratorTestMode;
+use pact_matching::models::v4::message_parts::MessageContents;
+use pact_matching::models::v4::SynchronousMessages;
+use pact_matching::models::v4::websocket::{FrameType, frame_type, received_frame, sends_request_frame, upgrade_request};
+use pact_matching::plugins::{ContentPlugin, with_plugins};
+
+use crate::matching::MatchResult;
+
+/// Returns the `Sec-WebSocket-Key` of the request if it is a request to upgrade the connection to
+/// a WebSocket
+pub(crate) fn websocket_key(req: &hyper::Request<Body>) -> Option<String> {
+  let upgrade = req.headers().get(hyper::header::UPGRADE)
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.eq_ignore_ascii_case("websocket"))
+    .unwrap_or(false);
+  if upgrade {
+    req.headers().get(hyper::header::SEC_WEBSOCKET_KEY)
+      .and_then(|value| value.to_str().ok())
+      .map(|value| value.to_string())
+  } else {
+    None
+  }
+}
+
+/// Finds the WebSocket interaction with an upgrade request that matches the request
+pub(crate) fn find_interaction(request: &Request, interactions: &[SynchronousMessages]) -> Option<SynchronousMessages> {
+  interactions.iter()
+    .find(|interaction| upgrade_request(interaction)
+      .map(|upgrade| match_request(upgrade.as_v3_request(), request.clone()).all_matched())
+      .unwrap_or(false))
+    .cloned()
+}
+
+/// Accepts the upgrade of the connection for the WebSocket interaction, and exchanges the frames of
+/// the interaction with the client once the connection has been upgraded. The result of matching
+/// the frame sent by the client is added to the match results.
+pub(crate) fn accept_upgrade(
+  key: &str,
+  interaction: SynchronousMessages,
+  on_upgrade: OnUpgrade,
+  matches: Arc<Mutex<Vec<MatchResult>>>,
+  context: HashMap<&'static str, Value>,
+  plugins: Vec<Arc<dyn ContentPlugin>>,
+  read_timeout: Option<Duration>
+) -> Response<Body> {
+  info!("Upgrading the connection for WebSocket interaction '{}'", interaction.description);
+  tokio::spawn(async move {
+    match on_upgrade.await {
+      Ok(upgraded) => {
+        let mut socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+        let timeout = interaction.timeout.or(read_timeout);
+        let result = exchange_frames(&mut socket, &interaction, &matches, &context, &plugins, timeout).await;
+        if let Err(err) = result {
+          warn!("WebSocket connection for '{}' failed - {}", interaction.description, err);
+        }
+        // Wait for the client to close the connection, ignoring anything else it sends
+        while let Some(Ok(frame)) = socket.next().await {
+          if frame.is_close() {
+            break;
+          }
+        }
+      },
+      Err(err) => error!("Failed to upgrade the connection to a WebSocket - {}", err)
+    }
+  });
+
+  Response::builder()
+    .status(101)
+    .header(hyper::header::UPGRADE, "websocket")
+    .header(hyper::header::CONNECTION, "Upgrade")
+    .header(hyper::header::SEC_WEBSOCKET_ACCEPT, derive_accept_key(key.as_bytes()))
+    .body(Body::empty())
+    .unwrap()
+}
+
+async fn exchange_frames<S>(
+  socket: &mut WebSocketStream<S>,
+  interaction: &SynchronousMessages,
+  matches: &Arc<Mutex<Vec<MatchResult>>>,
+  context: &HashMap<&str, Value>,
+  plugins: &[Arc<dyn ContentPlugin>],
+  timeout: Option<Duration>
+) -> anyhow::Result<()>
+  where S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin {
+  let expected_request = upgrade_request(interaction).unwrap_or_default().as_v3_request();
+  if sends_request_frame(interaction) {
+    let mismatches = match receive_frame(socket, timeout).await {
+      Ok(Some(data)) => {
+        let actual = received_frame(data.into(), Some(&interaction.request));
+        with_plugins(plugins, || match_sync_message_request(interaction, &actual))
+      },
+      Ok(None) => vec![frame_mismatch("Expected the client to send a message, but the connection was closed")],
+      Err(err) => vec![frame_mismatch(&err.to_string())]
+    };
+    if !mismatches.is_empty() {
+      info!("WebSocket message for '{}' did not match", interaction.description);
+      matches.lock().unwrap().push(MatchResult::RequestMismatch(expected_request, mismatches));
+      socket.close(None).await?;
+      return Ok(());
+    }
+  }
+
+  matches.lock().unwrap().push(MatchResult::RequestMatch(expected_request,
+    PactResponse { status: 101, .. PactResponse::default() }));
+  for message in &interaction.response {
+    let message = with_plugins(plugins, || generate_message_contents(message, &GeneratorTestMode::Consumer, context));
+    debug!("Sending WebSocket message {}", message.contents.str_value());
+    socket.send(to_frame(&message)).await?;
+  }
+  Ok(())
+}
+
+/// Waits for the next data frame from the client, returning None if the connection is closed
+async fn receive_frame<S>(socket: &mut WebSocketStream<S>, timeout: Option<Duration>) -> anyhow::Result<Option<Vec<u8>>>
+  where S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin {
+  let next = async {
+    while let Some(frame) = socket.next().await {
+      match frame? {
+        Frame::Text(text) => return Ok(Some(text.into_bytes())),
+        Frame::Binary(data) => return Ok(Some(data)),
+        Frame::Close(_) => return Ok(None),
+        _ => ()
+      }
+    }
+    Ok(None)
+  };
+  match timeout {
+    Some(timeout) => tokio::time::timeout(timeout, next).await
+      .map_err(|_| anyhow::anyhow!("Timed out waiting for the client to send a message after {:?}", timeout))?,
+    None => next.await
+  }
+}
+
+fn to_frame(message: &MessageContents) -> Frame {
+  let data = message.contents.value().unwrap_or_default();
+  match frame_type(message) {
+    FrameType::Text => Frame::Text(String::from_utf8_lossy(&data).to_string()),
+    FrameType::Binary => Frame::Binary(data.to_vec())
+  }
+}
+
+fn frame_mismatch(message: &str) -> Mismatch {
+  Mismatch::BodyMismatch {
+    path: "$".to_string(),
+    expected: None,
+    actual: None,
+    mismatch: message.to_string()
+  }
+}
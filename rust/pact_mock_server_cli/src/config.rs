@@ -0,0 +1,199 @@
+//!
+//! Configuration file with the default values for the command line options, so teams can check in
+//! a standard configuration instead of using long command lines. Options given on the command line
+//! override the values from the configuration file.
+//!
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Arg;
+use serde::Deserialize;
+
+use pact_matching::models::pact_files::PactFileLayout;
+use pact_mock_server::mock_server::BindAddress;
+
+/// Name of the configuration file that is loaded from the current directory if no configuration
+/// file is specified
+pub const DEFAULT_CONFIG_FILE: &str = "pact_mock_server.toml";
+
+/// Environment variable with the path to the configuration file
+pub const CONFIG_FILE_ENV: &str = "PACT_MOCK_SERVER_CONFIG";
+
+const LOG_LEVELS: [&str; 6] = ["error", "warn", "info", "debug", "trace", "none"];
+
+/// Values from the configuration file. The keys are the same as the long names of the command
+/// line options.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct ConfigFile {
+  host: Option<String>,
+  port: Option<u16>,
+  loglevel: Option<String>,
+  output: Option<String>,
+  pact_layout: Option<String>,
+  base_port: Option<u16>,
+  bind_address: Option<String>,
+  tls_cert: Option<String>,
+  tls_key: Option<String>,
+  https: Option<bool>,
+  ca_cert: Option<String>,
+  #[serde(skip)]
+  values: HashMap<String, String>
+}
+
+impl ConfigFile {
+  /// Parses the TOML configuration. Relative paths in the configuration are resolved against
+  /// the base directory.
+  pub fn parse(toml: &str, base_dir: &Path) -> Result<ConfigFile, String> {
+    let mut config: ConfigFile = toml::from_str(toml).map_err(|err| err.to_string())?;
+    config.validate()?;
+
+    let resolve = |path: &String| base_dir.join(path).to_string_lossy().to_string();
+    let values = vec![
+      ("host", config.host.clone()),
+      ("port", config.port.map(|port| port.to_string())),
+      ("loglevel", config.loglevel.clone()),
+      ("output", config.output.as_ref().map(resolve)),
+      ("pact-layout", config.pact_layout.clone()),
+      ("base-port", config.base_port.map(|port| port.to_string())),
+      ("bind-address", config.bind_address.clone()),
+      ("tls-cert", config.tls_cert.as_ref().map(resolve)),
+      ("tls-key", config.tls_key.as_ref().map(resolve)),
+      ("ca-cert", config.ca_cert.as_ref().map(resolve))
+    ];
+    config.values = values.into_iter()
+      .filter_map(|(name, value)| value.map(|value| (name.to_string(), value)))
+      .collect();
+    Ok(config)
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    if let Some(level) = &self.loglevel {
+      if !LOG_LEVELS.contains(&level.as_str()) {
+        return Err(format!("'{}' is not a valid log level, it must be one of {}", level, LOG_LEVELS.join(", ")));
+      }
+    }
+    if let Some(layout) = &self.pact_layout {
+      layout.parse::<PactFileLayout>()?;
+    }
+    if let Some(address) = &self.bind_address {
+      address.parse::<BindAddress>()?;
+    }
+    if self.tls_cert.is_some() != self.tls_key.is_some() {
+      return Err("tls-cert and tls-key must both be set".to_string());
+    }
+    if self.ca_cert.is_some() && !self.https() {
+      return Err("ca-cert requires https to be set to true".to_string());
+    }
+    Ok(())
+  }
+
+  /// Loads the configuration file given with the `--config` option or the
+  /// `PACT_MOCK_SERVER_CONFIG` environment variable, otherwise the `pact_mock_server.toml` file in
+  /// the current directory if there is one. Returns an empty configuration if there is no file.
+  pub fn load(args: &[String]) -> Result<ConfigFile, String> {
+    let path = config_file_arg(args)
+      .or_else(|| env::var(CONFIG_FILE_ENV).ok().filter(|path| !path.is_empty()))
+      .map(PathBuf::from);
+    let path = match path {
+      Some(path) => path,
+      None => {
+        let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+        if !path.exists() {
+          return Ok(ConfigFile::default());
+        }
+        path
+      }
+    };
+
+    let toml = fs::read_to_string(&path)
+      .map_err(|err| format!("Could not read the configuration file '{}': {}", path.display(), err))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    ConfigFile::parse(&toml, base_dir)
+      .map_err(|err| format!("Invalid configuration file '{}': {}", path.display(), err))
+  }
+
+  /// If the master server should be connected to with HTTPS
+  pub fn https(&self) -> bool {
+    self.https.unwrap_or_default()
+  }
+
+  /// Value from the configuration file for the command line option with the given name
+  pub fn value(&self, name: &str) -> Option<&str> {
+    self.values.get(name).map(|value| value.as_str())
+  }
+
+  /// Creates the command line argument with the given name, defaulting to the value from the
+  /// configuration file
+  pub fn arg<'a, 'b>(&'a self, name: &'a str) -> Arg<'a, 'b> {
+    let arg = Arg::with_name(name);
+    match self.value(name) {
+      Some(value) => arg.default_value(value),
+      None => arg
+    }
+  }
+}
+
+/// Finds the value of the `--config` option in the command line arguments
+fn config_file_arg(args: &[String]) -> Option<String> {
+  args.iter().enumerate().find_map(|(i, arg)| {
+    if arg == "--config" {
+      args.get(i + 1).cloned()
+    } else {
+      arg.strip_prefix("--config=").map(|path| path.to_string())
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use expectest::prelude::*;
+
+  use super::*;
+
+  #[test]
+  fn parse_config_file() {
+    let config = ConfigFile::parse(r#"
+      host = "mock-servers.local"
+      port = 9000
+      loglevel = "debug"
+      output = "pacts"
+      bind-address = "0.0.0.0:8000-8100"
+      tls-cert = "certs/server.pem"
+      tls-key = "/etc/certs/server.key"
+    "#, Path::new("config")).unwrap();
+    expect!(config.value("host")).to(be_some().value("mock-servers.local"));
+    expect!(config.value("port")).to(be_some().value("9000"));
+    expect!(config.value("loglevel")).to(be_some().value("debug"));
+    expect!(config.value("output")).to(be_some().value("config/pacts"));
+    expect!(config.value("bind-address")).to(be_some().value("0.0.0.0:8000-8100"));
+    expect!(config.value("tls-cert")).to(be_some().value("config/certs/server.pem"));
+    expect!(config.value("tls-key")).to(be_some().value("/etc/certs/server.key"));
+    expect!(config.value("base-port")).to(be_none());
+    expect!(config.https()).to(be_false());
+  }
+
+  #[test]
+  fn parse_config_file_with_invalid_values() {
+    expect!(ConfigFile::parse("port = \"abc\"", Path::new(""))).to(be_err());
+    expect!(ConfigFile::parse("unknown = 1", Path::new(""))).to(be_err());
+    expect!(ConfigFile::parse("loglevel = \"verbose\"", Path::new(""))).to(be_err());
+    expect!(ConfigFile::parse("bind-address = \"0.0.0.0:9000-8000\"", Path::new(""))).to(be_err());
+    expect!(ConfigFile::parse("tls-cert = \"server.pem\"", Path::new(""))).to(be_err());
+    expect!(ConfigFile::parse("ca-cert = \"ca.pem\"", Path::new(""))).to(be_err());
+    expect!(ConfigFile::parse("https = true\nca-cert = \"ca.pem\"", Path::new(""))).to(be_ok());
+  }
+
+  #[test]
+  fn config_file_arg_test() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    expect!(config_file_arg(&args(&["cli", "list"]))).to(be_none());
+    expect!(config_file_arg(&args(&["cli", "--config", "a.toml", "list"]))).to(be_some().value("a.toml"));
+    expect!(config_file_arg(&args(&["cli", "list", "--config=b.toml"]))).to(be_some().value("b.toml"));
+  }
+}
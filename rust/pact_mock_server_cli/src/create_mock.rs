@@ -1,36 +1,143 @@
-use std::path::Path;
+use std::path::PathBuf;
 
 use clap::ArgMatches;
 use log::*;
 use serde_json::Value;
 use itertools::Itertools;
 
-use pact_matching::models::{ReadWritePact, Pact, RequestResponsePact};
+use pact_matching::models::{load_pact_from_json, Pact};
+use pact_matching::models::http_utils::{fetch_json_from_url, HttpAuth, TlsConfig};
+use pact_mock_server::multi_pact::load_combined_pact;
 
 use crate::handle_error;
 
+fn http_auth(matches: &ArgMatches) -> Option<HttpAuth> {
+  if let Some(token) = matches.value_of("token") {
+    Some(HttpAuth::Token(token.to_string()))
+  } else {
+    matches.value_of("user").map(|user| HttpAuth::User(user.to_string(),
+      matches.value_of("password").map(|password| password.to_string())))
+  }
+}
+
+/// Loads the pact from the URL (i.e. from a Pact Broker), or combines the pacts from the files
+async fn load_pact(matches: &ArgMatches<'_>, paths: &[PathBuf]) -> Result<Box<dyn Pact>, String> {
+  match matches.value_of("url") {
+    Some(url) => {
+      let url = url.to_string();
+      let auth = http_auth(matches);
+      // The pact is fetched with a blocking HTTP client, which can not be used on the runtime threads
+      let (url, json) = tokio::task::spawn_blocking(move || fetch_json_from_url(&url, &auth, &TlsConfig::default()))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+      load_pact_from_json(&url, &json).map_err(|err| err.to_string())
+    },
+    None => load_combined_pact(paths)
+  }
+}
+
 pub async fn create_mock_server(host: &str, port: u16, matches: &ArgMatches<'_>) -> Result<(), i32> {
-  let file = matches.value_of("file").unwrap();
-  log::info!("Creating mock server from file {}", file);
+  let files = matches.values_of("file").map(|files| files.collect::<Vec<&str>>()).unwrap_or_default();
+  let source = matches.value_of("url").map(|url| url.to_string()).unwrap_or_else(|| files.iter().join(", "));
+  log::info!("Creating mock server from {}", source);
 
-  match RequestResponsePact::read_pact(&Path::new(file)) {
+  let paths = files.iter().map(PathBuf::from).collect::<Vec<PathBuf>>();
+  match load_pact(matches, &paths).await {
     Ok(ref pact) => {
       let mut args = vec![];
       if matches.is_present("cors") {
         info!("Setting mock server to handle CORS pre-flight requests");
         args.push("cors=true");
       }
+      if matches.is_present("slow-responses") {
+        info!("Setting mock server to simulate slow responses");
+        args.push("slow=true");
+      }
+      let fault_args = [("response-delay", "delay"), ("error-rate", "errorRate"), ("reset-rate", "resetRate")].iter()
+        .filter_map(|(arg, param)| matches.value_of(arg).map(|value| {
+          info!("Setting mock server to inject faults with {} = {}", arg, value);
+          format!("{}={}", param, value)
+        }))
+        .collect::<Vec<String>>();
+      args.extend(fault_args.iter().map(|arg| arg.as_str()));
+      let stub_arg;
+      if matches.is_present("stub") {
+        let parts = matches.values_of("stub").map(|values| values.collect::<Vec<&str>>()).unwrap_or_default();
+        stub_arg = if parts.is_empty() {
+          info!("Setting mock server to run as a stub server");
+          "stub=true".to_string()
+        } else {
+          info!("Setting mock server to run as a stub server, ignoring mismatches in the {}", parts.iter().join(", "));
+          format!("stub={}", parts.iter().join(","))
+        };
+        args.push(stub_arg.as_str());
+      }
+      if matches.is_present("strict-headers") {
+        info!("Setting mock server to match request headers strictly");
+        args.push("strictHeaders=true");
+      }
+      let strict_header_args = matches.values_of("strict-headers-for")
+        .map(|values| values.map(|description| {
+          info!("Setting mock server to match request headers strictly for interaction '{}'", description);
+          format!("strictHeadersFor={}", url::form_urlencoded::byte_serialize(description.as_bytes()).collect::<String>())
+        }).collect::<Vec<String>>())
+        .unwrap_or_default();
+      args.extend(strict_header_args.iter().map(|arg| arg.as_str()));
+      if matches.is_present("allow-unexpected-keys") {
+        info!("Setting mock server to allow unexpected keys in request bodies");
+        args.push("allowUnexpectedKeys=true");
+      }
+      let unexpected_keys_args = matches.values_of("allow-unexpected-keys-for")
+        .map(|values| values.map(|description| {
+          info!("Setting mock server to allow unexpected keys in the request body for interaction '{}'", description);
+          format!("allowUnexpectedKeysFor={}", url::form_urlencoded::byte_serialize(description.as_bytes()).collect::<String>())
+        }).collect::<Vec<String>>())
+        .unwrap_or_default();
+      args.extend(unexpected_keys_args.iter().map(|arg| arg.as_str()));
+      let event_delay_arg;
+      if let Some(delay) = matches.value_of("event-delay") {
+        info!("Setting mock server to delay the events of event stream responses by {} ms", delay);
+        event_delay_arg = format!("eventDelay={}", delay);
+        args.push(event_delay_arg.as_str());
+      }
+      let mut watch_args = vec![];
+      if matches.is_present("watch") {
+        for path in &paths {
+          let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+          info!("Setting mock server to reload the pact when '{}' changes", path.display());
+          watch_args.push(format!("watch={}",
+            url::form_urlencoded::byte_serialize(path.to_string_lossy().as_bytes()).collect::<String>()));
+        }
+        if let Some(interval) = matches.value_of("watch-interval") {
+          watch_args.push(format!("watchInterval={}", interval));
+        }
+      }
+      args.extend(watch_args.iter().map(|arg| arg.as_str()));
       if matches.is_present("tls") {
         info!("Setting mock server to use TLS");
         args.push("tls=true");
       }
+      let bind_address;
+      if let Some(address) = matches.value_of("bind-address") {
+        info!("Setting mock server to bind to {}", address);
+        bind_address = format!("bind={}", url::form_urlencoded::byte_serialize(address.as_bytes()).collect::<String>());
+        args.push(bind_address.as_str());
+      }
+      let path = match (matches.value_of("mock-server-id"), matches.value_of("virtual-host")) {
+        (Some(id), Some(virtual_host)) => {
+          info!("Adding the pact as virtual host {} of mock server {}", virtual_host, id);
+          format!("mockserver/{}/hosts/{}", id, url::form_urlencoded::byte_serialize(virtual_host.as_bytes()).collect::<String>())
+        },
+        _ => String::default()
+      };
       let url = if args.is_empty() {
-        format!("http://{}:{}/", host, port)
+        format!("{}/{}", crate::master_server_url(host, port, matches), path)
       } else {
-        format!("http://{}:{}/?{}", host, port, args.iter().join("&"))
+        format!("{}/{}?{}", crate::master_server_url(host, port, matches), path, args.iter().join("&"))
       };
-      let client = reqwest::Client::new();
-      let resp = client.post(url.as_str())
+      let client = crate::master_server_client(matches);
+      let resp = crate::with_auth_token(client.post(url.as_str()), matches)
         .json(&pact.to_json(pact.spec_version()))
         .send().await;
       match resp {
@@ -47,7 +154,10 @@ pub async fn create_mock_server(host: &str, port: u16, matches: &ArgMatches<'_>)
                 let port = mock_server.get("port")
                   .ok_or_else(|| handle_error("Invalid JSON received from master server - mockServer has no port attribute"))?
                   .as_u64().ok_or_else(|| handle_error("Invalid JSON received from master server - mockServer port attribute is not a number"))?;
-                println!("Mock server {} started on port {}", id, port);
+                match mock_server.get("host").and_then(|host| host.as_str()) {
+                  Some(virtual_host) => println!("Mock server {} added as virtual host {} on port {}", id, virtual_host, port),
+                  None => println!("Mock server {} started on port {}", id, port)
+                }
                 Ok(())
               },
               Err(err) => {
@@ -66,7 +176,7 @@ pub async fn create_mock_server(host: &str, port: u16, matches: &ArgMatches<'_>)
       }
     },
     Err(err) => {
-      crate::display_error(format!("Failed to load pact file '{}': {}", file, err), matches);
+      crate::display_error(format!("Failed to load pact '{}': {}", source, err), matches);
     }
   }
 }
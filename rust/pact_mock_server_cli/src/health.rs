@@ -0,0 +1,28 @@
+use clap::ArgMatches;
+use serde_json::Value;
+
+/// Checks the liveness (or readiness with `--ready`) endpoint of the master server. Returns an
+/// error if the master server can not be reached or is not healthy, so it can be used as a
+/// container health check.
+pub async fn check_health(host: &str, port: u16, matches: &ArgMatches<'_>) -> Result<(), i32> {
+  let (path, state) = if matches.is_present("ready") { ("readyz", "ready") } else { ("healthz", "healthy") };
+  let client = crate::master_server_client(matches);
+  let url = format!("{}/{}", crate::master_server_url(host, port, matches), path);
+  match client.get(&url).send().await {
+    Ok(result) => {
+      let status = result.status();
+      let json = result.json::<Value>().await.unwrap_or_default();
+      if status.is_success() {
+        println!("Master server is {}: {}", state, json);
+        Ok(())
+      } else {
+        eprintln!("Master server is not {}: got response {}", state, status);
+        Err(1)
+      }
+    },
+    Err(err) => {
+      eprintln!("Failed to connect to the master mock server '{}': {}", url, err);
+      Err(1)
+    }
+  }
+}
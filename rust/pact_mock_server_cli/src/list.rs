@@ -15,9 +15,9 @@ fn json2string(json: Option<&Value>) -> String {
 
 #[allow(clippy::print_literal)]
 pub async fn list_mock_servers(host: &str, port: u16, matches: &ArgMatches<'_>) -> Result<(), i32> {
-  let client = reqwest::Client::new();
-  let url = format!("http://{}:{}/", host, port);
-  let res = client.get(&url).send().await;
+  let client = crate::master_server_client(matches);
+  let url = format!("{}/", crate::master_server_url(host, port, matches));
+  let res = crate::with_auth_token(client.get(&url), matches).send().await;
 
   match res {
     Ok(result) => {
@@ -12,6 +12,7 @@ use std::io;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use clap::{App, AppSettings, Arg, ArgMatches, ErrorKind, SubCommand};
 use lazy_static::*;
@@ -21,9 +22,14 @@ use rand::Rng;
 use simplelog::{CombinedLogger, Config, SimpleLogger, TermLogger, WriteLogger};
 use uuid::Uuid;
 
+use pact_matching::models::pact_files::PactFileLayout;
+use pact_mock_server::mock_server::{BindAddress, ConnectionOptions};
 use pact_mock_server::server_manager::ServerManager;
+use pact_mock_server::tls::TlsConfigBuilder;
 use pact_models::PactSpecification;
 
+use crate::config::ConfigFile;
+
 pub(crate) fn display_error(error: String, matches: &ArgMatches) -> ! {
     eprintln!("ERROR: {}", error);
     eprintln!();
@@ -31,17 +37,50 @@ pub(crate) fn display_error(error: String, matches: &ArgMatches) -> ! {
     panic!("{}", error)
 }
 
+/// Adds the auth token for the master server to the request, if one has been provided
+pub(crate) fn with_auth_token(request: reqwest::RequestBuilder, matches: &ArgMatches) -> reqwest::RequestBuilder {
+  match matches.value_of("auth-token") {
+    Some(token) => request.bearer_auth(token),
+    None => request
+  }
+}
+
+/// Base URL of the master server, which is an HTTPS URL if the `--https` flag is set
+pub(crate) fn master_server_url(host: &str, port: u16, matches: &ArgMatches) -> String {
+  let scheme = if matches.is_present("https") { "https" } else { "http" };
+  format!("{}://{}:{}", scheme, host, port)
+}
+
+/// HTTP client for the requests to the master server, which trusts the CA certificate if one has
+/// been provided
+pub(crate) fn master_server_client(matches: &ArgMatches) -> reqwest::Client {
+  let mut builder = reqwest::Client::builder();
+  if let Some(path) = matches.value_of("ca-cert") {
+    let certificate = fs::read(path).map_err(|err| err.to_string())
+      .and_then(|pem| reqwest::Certificate::from_pem(&pem).map_err(|err| err.to_string()));
+    match certificate {
+      Ok(certificate) => builder = builder.add_root_certificate(certificate),
+      Err(err) => display_error(format!("Failed to load the CA certificate '{}': {}", path, err), matches)
+    }
+  }
+  builder.build().unwrap_or_else(|err| display_error(format!("Failed to create the HTTP client: {}", err), matches))
+}
+
 pub(crate) fn handle_error(error: &str) -> i32 {
   eprintln!("ERROR: {}", error);
   eprintln!();
   -100
 }
 
+mod config;
 mod server;
 mod create_mock;
 mod list;
 mod verify;
+mod requests;
+mod health;
 mod shutdown;
+mod record;
 
 fn print_version() {
     println!("\npact mock server version  : v{}", clap::crate_version!());
@@ -110,6 +149,30 @@ fn integer_value(v: String) -> Result<(), String> {
     v.parse::<u16>().map(|_| ()).map_err(|e| format!("'{}' is not a valid port value: {}", v, e) )
 }
 
+fn positive_integer_value(v: String) -> Result<(), String> {
+    match v.parse::<u64>() {
+      Ok(0) => Err(format!("'{}' must be greater than zero", v)),
+      Ok(_) => Ok(()),
+      Err(e) => Err(format!("'{}' is not a valid number: {}", v, e))
+    }
+}
+
+fn rate_value(v: String) -> Result<(), String> {
+    match v.parse::<f64>() {
+      Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(()),
+      Ok(_) => Err(format!("'{}' must be between 0.0 and 1.0", v)),
+      Err(e) => Err(format!("'{}' is not a valid number: {}", v, e))
+    }
+}
+
+fn bind_address_value(v: String) -> Result<(), String> {
+    v.parse::<BindAddress>().map(|_| ())
+}
+
+fn pact_layout_value(v: String) -> Result<(), String> {
+    v.parse::<PactFileLayout>().map(|_| ())
+}
+
 fn uuid_value(v: String) -> Result<(), String> {
     Uuid::parse_str(v.as_str()).map(|_| ()).map_err(|e| format!("'{}' is not a valid UUID value: {}", v, e) )
 }
@@ -125,22 +188,43 @@ async fn main() {
 #[derive(Debug, Clone)]
 pub(crate) struct ServerOpts {
   pub output_path: Option<String>,
+  pub pact_layout: Option<PactFileLayout>,
   pub base_port: Option<u16>,
-  pub server_key: String
+  pub bind_address: Option<BindAddress>,
+  pub connections: ConnectionOptions,
+  pub server_key: String,
+  pub auth_token: Option<String>
 }
 
 lazy_static!{
   pub(crate) static ref SERVER_OPTIONS: Mutex<RefCell<ServerOpts>> = Mutex::new(RefCell::new(ServerOpts {
     output_path: None,
+    pact_layout: None,
     base_port: None,
-    server_key: String::default()
+    bind_address: None,
+    connections: ConnectionOptions::default(),
+    server_key: String::default(),
+    auth_token: None
   }));
   pub(crate) static ref SERVER_MANAGER: Mutex<ServerManager> = Mutex::new(ServerManager::new());
 }
 
 async fn handle_command_args() -> Result<(), i32> {
-  let args: Vec<String> = env::args().collect();
+  let mut args: Vec<String> = env::args().collect();
   let program = args[0].clone();
+  let config = match ConfigFile::load(&args) {
+    Ok(config) => config,
+    Err(err) => return Err(handle_error(&err))
+  };
+  if config.https() && !args.iter().any(|arg| arg == "--https") {
+    args.push("--https".to_string());
+  }
+  if let Some(ca_cert) = config.value("ca-cert") {
+    if !args.iter().any(|arg| arg.starts_with("--ca-cert")) {
+      args.push("--ca-cert".to_string());
+      args.push(ca_cert.to_string());
+    }
+  }
 
   let version = format!("v{}", clap::crate_version!());
   let app = App::new(program)
@@ -152,21 +236,21 @@ async fn handle_command_args() -> Result<(), i32> {
       .setting(AppSettings::GlobalVersion)
       .setting(AppSettings::VersionlessSubcommands)
       .setting(AppSettings::ColoredHelp)
-      .arg(Arg::with_name("port")
+      .arg(config.arg("port")
           .short("p")
           .long("port")
           .takes_value(true)
           .use_delimiter(false)
           .global(true)
           .help("port the master mock server runs on (defaults to 8080)"))
-      .arg(Arg::with_name("host")
+      .arg(config.arg("host")
           .short("h")
           .long("host")
           .takes_value(true)
           .use_delimiter(false)
           .global(true)
           .help("hostname the master mock server runs on (defaults to localhost)"))
-      .arg(Arg::with_name("loglevel")
+      .arg(config.arg("loglevel")
           .short("l")
           .long("loglevel")
           .takes_value(true)
@@ -182,21 +266,86 @@ async fn handle_command_args() -> Result<(), i32> {
         .long("no-file-log")
         .global(true)
         .help("Do not log to an output file"))
+      .arg(Arg::with_name("config")
+        .long("config")
+        .env(config::CONFIG_FILE_ENV)
+        .takes_value(true)
+        .use_delimiter(false)
+        .global(true)
+        .help("TOML configuration file with the default values for the options (defaults to pact_mock_server.toml in the current directory, if it exists)"))
+      .arg(Arg::with_name("https")
+        .long("https")
+        .global(true)
+        .help("Connect to the master server with HTTPS, for when it was started with a TLS certificate"))
+      .arg(Arg::with_name("ca-cert")
+        .long("ca-cert")
+        .takes_value(true)
+        .use_delimiter(false)
+        .global(true)
+        .requires("https")
+        .help("PEM file with the CA certificate to trust when connecting to the master server with HTTPS (i.e. for a self-signed certificate)"))
+      .arg(Arg::with_name("auth-token")
+        .long("auth-token")
+        .env("PACT_MOCK_SERVER_AUTH_TOKEN")
+        .takes_value(true)
+        .use_delimiter(false)
+        .global(true)
+        .help("bearer token the master server requires for its control API when started with it, and that is sent to the master server by the other commands"))
       .subcommand(SubCommand::with_name("start")
               .about("Starts the master mock server")
-              .arg(Arg::with_name("output")
+              .arg(config.arg("output")
                     .short("o")
                     .long("output")
                     .takes_value(true)
                     .use_delimiter(false)
                     .help("the directory where to write files to (defaults to current directory)"))
-              .arg(Arg::with_name("base-port")
+              .arg(config.arg("pact-layout")
+                  .long("pact-layout")
+                  .takes_value(true)
+                  .use_delimiter(false)
+                  .possible_values(&["flat", "provider"])
+                  .help("the layout of the written pact files, either flat ({consumer}-{provider}.json) or provider ({provider}/{consumer}.json). When set, an index file (pact-index.json) listing the written pacts and their hashes is also maintained in the output directory")
+                  .validator(pact_layout_value))
+              .arg(config.arg("base-port")
                   .long("base-port")
                   .takes_value(true)
                   .use_delimiter(false)
                   .required(false)
                   .help("the base port number that mock server ports will be allocated from. If not specified, ports will be randomly assigned by the OS.")
                   .validator(integer_value))
+              .arg(config.arg("bind-address")
+                  .long("bind-address")
+                  .takes_value(true)
+                  .use_delimiter(false)
+                  .help("the address mock servers will bind to, with an optional port range to allocate ports from (i.e. 0.0.0.0:8000-8100). Defaults to 0.0.0.0")
+                  .validator(bind_address_value))
+              .arg(Arg::with_name("no-keep-alive")
+                  .long("no-keep-alive")
+                  .help("Do not keep connections to the mock servers alive between requests"))
+              .arg(Arg::with_name("max-connections")
+                  .long("max-connections")
+                  .takes_value(true)
+                  .use_delimiter(false)
+                  .help("the maximum number of concurrent connections each mock server will accept. Further connections will wait until one is closed")
+                  .validator(positive_integer_value))
+              .arg(Arg::with_name("read-timeout")
+                  .long("read-timeout")
+                  .takes_value(true)
+                  .use_delimiter(false)
+                  .help("the time in milliseconds the mock servers will wait to read a request body before responding with a 408 (Request Timeout)")
+                  .validator(positive_integer_value))
+              .arg(config.arg("tls-cert")
+                .long("tls-cert")
+                .takes_value(true)
+                .use_delimiter(false)
+                .requires("tls-key")
+                .help("PEM file with the certificate for the master server to listen with HTTPS"))
+              .arg(config.arg("tls-key")
+                .long("tls-key")
+                .takes_value(true)
+                .use_delimiter(false)
+                .requires("tls-cert")
+                .help("PEM file with the private key of the certificate of the master server"))
               .arg(Arg::with_name("server-key")
                 .long("server-key")
                 .takes_value(true)
@@ -207,21 +356,139 @@ async fn handle_command_args() -> Result<(), i32> {
               .about("Lists all the running mock servers")
               .setting(AppSettings::ColoredHelp))
       .subcommand(SubCommand::with_name("create")
-        .about("Creates a new mock server from a pact file")
+        .about("Creates a new mock server from a pact file or URL")
         .arg(Arg::with_name("file")
           .short("f")
           .long("file")
           .takes_value(true)
           .use_delimiter(false)
-          .required(true)
-          .help("the pact file to define the mock server"))
+          .multiple(true)
+          .number_of_values(1)
+          .required_unless("url")
+          .conflicts_with("url")
+          .help("the pact file to define the mock server. Can be repeated, and can be a directory of pact files, in which case the interactions from all the pacts are served by the one mock server"))
+        .arg(Arg::with_name("url")
+          .short("u")
+          .long("url")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("URL of the pact to define the mock server (i.e. the URL of a pact in a Pact Broker)"))
+        .arg(Arg::with_name("user")
+          .long("user")
+          .env("PACT_BROKER_USERNAME")
+          .takes_value(true)
+          .use_delimiter(false)
+          .requires("url")
+          .conflicts_with("token")
+          .help("username to fetch the pact from the URL with"))
+        .arg(Arg::with_name("password")
+          .long("password")
+          .env("PACT_BROKER_PASSWORD")
+          .takes_value(true)
+          .use_delimiter(false)
+          .requires("url")
+          .conflicts_with("token")
+          .help("password to fetch the pact from the URL with"))
+        .arg(Arg::with_name("token")
+          .long("token")
+          .env("PACT_BROKER_TOKEN")
+          .takes_value(true)
+          .use_delimiter(false)
+          .requires("url")
+          .conflicts_with("user")
+          .help("bearer token to fetch the pact from the URL with"))
         .arg(Arg::with_name("cors")
           .short("c")
           .long("cors-preflight")
           .help("Handle CORS pre-flight requests"))
+        .arg(Arg::with_name("slow-responses")
+          .long("simulate-slow-responses")
+          .help("Delay the responses of interactions that have a timeout by that timeout"))
+        .arg(Arg::with_name("response-delay")
+          .long("response-delay")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("Delay the responses of all the interactions by this many milliseconds")
+          .validator(positive_integer_value))
+        .arg(Arg::with_name("error-rate")
+          .long("error-rate")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("Fraction (0.0 to 1.0) of the matched requests that will receive a 500 response")
+          .validator(rate_value))
+        .arg(Arg::with_name("reset-rate")
+          .long("reset-rate")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("Fraction (0.0 to 1.0) of the matched requests where the connection will be closed without a response")
+          .validator(rate_value))
+        .arg(Arg::with_name("stub")
+          .long("stub")
+          .takes_value(true)
+          .min_values(0)
+          .use_delimiter(true)
+          .possible_values(&["body", "headers", "query"])
+          .help("Run the mock server as a stub server, returning the response of the closest matching interaction. The values are the parts of the request to ignore mismatches in (defaults to body and query)"))
+        .arg(Arg::with_name("strict-headers")
+          .long("strict-headers")
+          .help("Match the request headers strictly, so requests with any headers not in the interaction (i.e. cookies) do not match"))
+        .arg(Arg::with_name("strict-headers-for")
+          .long("strict-headers-for")
+          .takes_value(true)
+          .use_delimiter(false)
+          .multiple(true)
+          .number_of_values(1)
+          .help("Match the request headers strictly for the interaction with this description. Can be repeated"))
+        .arg(Arg::with_name("allow-unexpected-keys")
+          .long("allow-unexpected-keys")
+          .help("Allow keys in the request bodies that are not in the interactions. By default any unexpected keys are a mismatch"))
+        .arg(Arg::with_name("allow-unexpected-keys-for")
+          .long("allow-unexpected-keys-for")
+          .takes_value(true)
+          .use_delimiter(false)
+          .multiple(true)
+          .number_of_values(1)
+          .help("Allow keys in the request body that are not in the interaction with this description. Can be repeated"))
+        .arg(Arg::with_name("event-delay")
+          .long("event-delay")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("Delay between the events of server-sent event (text/event-stream) responses in milliseconds")
+          .validator(positive_integer_value))
+.arg(Arg::with_name("watch")
+          .long("watch")
+          .help("Reload the interactions of the mock server when the pact files change. The master server must be able to read the files"))
+        .arg(Arg::with_name("watch-interval")
+          .long("watch-interval")
+          .takes_value(true)
+          .use_delimiter(false)
+          .requires("watch")
+          .validator(positive_integer_value)
+          .help("How often to check the pact files for changes in milliseconds (defaults to 1000)"))
         .arg(Arg::with_name("tls")
           .long("tls")
           .help("Enable TLS with the mock server (will use a self-signed certificate)"))
+        .arg(Arg::with_name("bind-address")
+          .long("bind-address")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("the address the mock server should bind to, with an optional port or port range (i.e. 0.0.0.0:8000-8100). Defaults to the bind address the master server was started with")
+          .validator(bind_address_value))
+        .arg(Arg::with_name("virtual-host")
+          .long("virtual-host")
+          .takes_value(true)
+          .use_delimiter(false)
+          .requires("mock-server-id")
+          .conflicts_with_all(&["tls", "bind-address"])
+          .help("Add the pact as a virtual host of an existing mock server, serving the requests sent to this host name"))
+        .arg(Arg::with_name("mock-server-id")
+          .short("i")
+          .long("mock-server-id")
+          .takes_value(true)
+          .use_delimiter(false)
+          .requires("virtual-host")
+          .help("the ID of the mock server to add the virtual host to")
+          .validator(uuid_value))
         .setting(AppSettings::ColoredHelp))
       .subcommand(SubCommand::with_name("verify")
               .about("Verify the mock server by id or port number, and generate a pact file if all ok")
@@ -243,6 +510,29 @@ async fn handle_command_args() -> Result<(), i32> {
                   .help("the port number of the mock server")
                   .validator(integer_value))
               .setting(AppSettings::ColoredHelp))
+      .subcommand(SubCommand::with_name("requests")
+              .about("Lists the requests received by the mock server by id or port number, and if they matched")
+              .arg(Arg::with_name("mock-server-id")
+                  .short("i")
+                  .long("mock-server-id")
+                  .takes_value(true)
+                  .use_delimiter(false)
+                  .required_unless("mock-server-port")
+                  .conflicts_with("mock-server-port")
+                  .help("the ID of the mock server")
+                  .validator(uuid_value))
+              .arg(Arg::with_name("mock-server-port")
+                  .short("m")
+                  .long("mock-server-port")
+                  .takes_value(true)
+                  .use_delimiter(false)
+                  .required_unless("mock-server-id")
+                  .help("the port number of the mock server")
+                  .validator(integer_value))
+              .arg(Arg::with_name("json")
+                  .long("json")
+                  .help("Output the requests as JSON instead of a table"))
+              .setting(AppSettings::ColoredHelp))
       .subcommand(SubCommand::with_name("shutdown")
               .about("Shutdown the mock server by id or port number, releasing all its resources")
               .arg(Arg::with_name("mock-server-id")
@@ -263,6 +553,12 @@ async fn handle_command_args() -> Result<(), i32> {
                   .help("the port number of the mock server")
                   .validator(integer_value))
               .setting(AppSettings::ColoredHelp))
+      .subcommand(SubCommand::with_name("healthcheck")
+        .about("Checks the health of the master server, exiting with an error if it is not healthy")
+        .arg(Arg::with_name("ready")
+          .long("ready")
+          .help("Check the readiness of the master server instead of the liveness"))
+        .setting(AppSettings::ColoredHelp))
       .subcommand(SubCommand::with_name("shutdown-master")
         .about("Performs a graceful shutdown of the master server (displayed when it started)")
         .arg(Arg::with_name("server-key")
@@ -279,9 +575,52 @@ async fn handle_command_args() -> Result<(), i32> {
           .help("the period of time in milliseconds to allow the server to shutdown (defaults to 100ms)")
           .validator(integer_value))
         .setting(AppSettings::ColoredHelp))
+      .subcommand(SubCommand::with_name("record")
+        .about("Starts a proxy that records the requests to a real provider, and writes them to a pact file when stopped")
+        .arg(Arg::with_name("provider-url")
+          .short("u")
+          .long("provider-url")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("the base URL of the provider to forward the requests to"))
+        .arg(Arg::with_name("consumer")
+          .long("consumer")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("the name of the consumer for the recorded pact"))
+        .arg(Arg::with_name("provider")
+          .long("provider")
+          .takes_value(true)
+          .use_delimiter(false)
+          .required(true)
+          .help("the name of the provider for the recorded pact"))
+        .arg(config.arg("output")
+          .short("o")
+          .long("output")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("the directory to write the pact file to (defaults to the current directory)"))
+        .arg(Arg::with_name("overwrite")
+          .long("overwrite")
+          .help("Overwrite any existing pact file, instead of merging the recorded interactions into it"))
+        .arg(Arg::with_name("bind-address")
+          .long("bind-address")
+          .takes_value(true)
+          .use_delimiter(false)
+          .help("the address the proxy should bind to, with an optional port or port range (defaults to 127.0.0.1 and a free port)")
+          .validator(bind_address_value))
+        .arg(Arg::with_name("ignore-header")
+          .long("ignore-header")
+          .takes_value(true)
+          .multiple(true)
+          .number_of_values(1)
+          .help("a header that is forwarded to the provider but not recorded (i.e. Authorization), can be repeated"))
+        .setting(AppSettings::ColoredHelp))
   ;
 
-  let matches = app.get_matches_safe();
+  let matches = app.get_matches_from_safe(args);
   match matches {
     Ok(ref matches) => {
       let log_level = matches.value_of("loglevel");
@@ -300,23 +639,44 @@ async fn handle_command_args() -> Result<(), i32> {
           match matches.subcommand() {
             ("start", Some(sub_matches)) => {
               let output_path = sub_matches.value_of("output").map(|s| s.to_owned());
+              let pact_layout = sub_matches.value_of("pact-layout").map(|s| s.parse::<PactFileLayout>().unwrap());
               let base_port = sub_matches.value_of("base-port").map(|s| s.parse::<u16>().unwrap_or(0));
+              let bind_address = sub_matches.value_of("bind-address").map(|s| s.parse::<BindAddress>().unwrap());
+              let connections = ConnectionOptions {
+                keep_alive: !sub_matches.is_present("no-keep-alive"),
+                max_connections: sub_matches.value_of("max-connections").map(|s| s.parse::<usize>().unwrap_or_default()),
+                read_timeout: sub_matches.value_of("read-timeout").map(|s| Duration::from_millis(s.parse::<u64>().unwrap_or_default()))
+              };
               let server_key = sub_matches.value_of("server-key").map(|s| s.to_owned())
                 .unwrap_or_else(|| rand::thread_rng().sample_iter(Alphanumeric).take(16).map(char::from).collect::<String>());
               {
                 let inner = (*SERVER_OPTIONS).lock().unwrap();
                 let mut options = inner.deref().borrow_mut();
                 options.output_path = output_path;
+                options.pact_layout = pact_layout;
                 options.base_port = base_port;
+                options.bind_address = bind_address;
+                options.connections = connections;
                 options.server_key = server_key;
+                options.auth_token = sub_matches.value_of("auth-token").map(|s| s.to_owned());
               }
-              server::start_server(p).await
+              let tls = match (sub_matches.value_of("tls-cert"), sub_matches.value_of("tls-key")) {
+                (Some(cert), Some(key)) => match TlsConfigBuilder::new().cert_path(cert).key_path(key).build() {
+                  Ok(tls) => Some(tls),
+                  Err(err) => display_error(format!("Failed to load the TLS certificate and key: {}", err), sub_matches)
+                },
+                _ => None
+              };
+              server::start_server(p, tls).await
             },
             ("list", Some(sub_matches)) => list::list_mock_servers(host, p, sub_matches).await,
             ("create", Some(sub_matches)) => create_mock::create_mock_server(host, p, sub_matches).await,
             ("verify", Some(sub_matches)) => verify::verify_mock_server(host, p, sub_matches).await,
+            ("requests", Some(sub_matches)) => requests::list_requests(host, p, sub_matches).await,
             ("shutdown", Some(sub_matches)) => shutdown::shutdown_mock_server(host, p, sub_matches).await,
+            ("healthcheck", Some(sub_matches)) => health::check_health(host, p, sub_matches).await,
             ("shutdown-master", Some(sub_matches)) => shutdown::shutdown_master_server(host, p, sub_matches).await,
+            ("record", Some(sub_matches)) => record::record_provider(sub_matches).await,
             _ => Err(3)
           }
         },
@@ -351,7 +711,7 @@ mod test {
 
   use pact_matching::s;
 
-  use super::{integer_value, uuid_value};
+  use super::{integer_value, rate_value, uuid_value};
 
   #[test]
     fn validates_integer_value() {
@@ -393,4 +753,14 @@ mod test {
         expect!(uuid_value(s!("1234x"))).to(be_err());
     }
 
+    #[test]
+    fn validates_rate_value() {
+        expect!(rate_value(s!("0"))).to(be_ok());
+        expect!(rate_value(s!("0.25"))).to(be_ok());
+        expect!(rate_value(s!("1.0"))).to(be_ok());
+        expect!(rate_value(s!("1.5"))).to(be_err());
+        expect!(rate_value(s!("-0.1"))).to(be_err());
+        expect!(rate_value(s!("often"))).to(be_err());
+    }
+
 }
@@ -0,0 +1,57 @@
+use clap::ArgMatches;
+use log::*;
+
+use pact_mock_server::mock_server::BindAddress;
+use pact_mock_server::recorder::{RecordingConfig, RecordingProxy};
+
+use crate::handle_error;
+
+pub async fn record_provider(matches: &ArgMatches<'_>) -> Result<(), i32> {
+  let config = RecordingConfig {
+    provider_url: matches.value_of("provider-url").unwrap().to_string(),
+    consumer: matches.value_of("consumer").unwrap().to_string(),
+    provider: matches.value_of("provider").unwrap().to_string(),
+    ignore_headers: matches.values_of("ignore-header")
+      .map(|values| values.map(|value| value.to_string()).collect())
+      .unwrap_or_default()
+  };
+  let bind_address = matches.value_of("bind-address")
+    .map(|address| address.parse::<BindAddress>().unwrap())
+    .unwrap_or_default();
+
+  let mut started = None;
+  let mut last_error = String::default();
+  for addr in bind_address.socket_addresses() {
+    match RecordingProxy::start(config.clone(), addr).await {
+      Ok(result) => {
+        started = Some(result);
+        break;
+      },
+      Err(err) => last_error = err
+    }
+  }
+  let (mut proxy, future) = started.ok_or_else(|| handle_error(last_error.as_str()))?;
+  let server = tokio::spawn(future);
+  println!("Recording proxy started on {}, forwarding requests to {}", proxy.url(), config.provider_url);
+  println!("Press Ctrl-C to stop recording and write the pact file");
+
+  if let Err(err) = tokio::signal::ctrl_c().await {
+    warn!("Failed to wait for Ctrl-C - {}", err);
+  }
+  proxy.shutdown().map_err(|err| handle_error(err.as_str()))?;
+  server.await.unwrap_or_default();
+
+  let interactions = proxy.interactions().len();
+  if interactions == 0 {
+    println!("No requests were recorded, so no pact file was written");
+    return Ok(());
+  }
+  let output_path = matches.value_of("output").map(|path| path.to_string());
+  match proxy.write_pact(&output_path, matches.is_present("overwrite")) {
+    Ok(path) => {
+      println!("Recorded {} interaction(s) to {}", interactions, path.display());
+      Ok(())
+    },
+    Err(err) => Err(handle_error(format!("Failed to write the pact file - {}", err).as_str()))
+  }
+}
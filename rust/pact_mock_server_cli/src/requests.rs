@@ -0,0 +1,70 @@
+use clap::ArgMatches;
+use http::StatusCode;
+use serde_json::Value;
+
+use pact_matching::models::json_utils::json_to_string;
+
+use crate::handle_error;
+
+pub async fn list_requests(host: &str, port: u16, matches: &ArgMatches<'_>) -> Result<(), i32> {
+  let mock_server_id = matches.value_of("mock-server-id");
+  let mock_server_port = matches.value_of("mock-server-port");
+  let id = if let Some(id) = mock_server_id {
+    (id, "id")
+  } else {
+    (mock_server_port.unwrap(), "port")
+  };
+
+  let client = crate::master_server_client(matches);
+  let url = format!("{}/mockserver/{}/requests", crate::master_server_url(host, port, matches), id.0);
+  match crate::with_auth_token(client.get(&url), matches).send().await {
+    Ok(result) => {
+      let status = result.status();
+      if status == StatusCode::NOT_FOUND {
+        println!("No mock server found with {} '{}', use the 'list' command to get a list of available mock servers.", id.1, id.0);
+        Err(3)
+      } else if !status.is_success() {
+        crate::display_error(format!("Unexpected response from master mock server '{}': {}", url, status), matches);
+      } else {
+        match result.json::<Value>().await {
+          Ok(json) => {
+            let requests = json.as_array()
+              .ok_or_else(|| handle_error("Invalid JSON received from master server - requests is not an array"))?;
+            if matches.is_present("json") {
+              println!("{}", serde_json::to_string_pretty(&json).unwrap_or_default());
+            } else {
+              display_requests(requests);
+            }
+            Ok(())
+          },
+          Err(err) => {
+            log::error!("Failed to parse JSON: {}", err);
+            crate::display_error(format!("Failed to parse JSON: {}", err), matches);
+          }
+        }
+      }
+    },
+    Err(err) => {
+      crate::display_error(format!("Failed to connect to the master mock server '{}': {}", url, err), matches);
+    }
+  }
+}
+
+fn field(request: &Value, name: &str) -> String {
+  request.get(name).map(json_to_string).unwrap_or_default()
+}
+
+fn display_requests(requests: &[Value]) {
+  let method_len = requests.iter().map(|request| field(request, "method").len()).max().unwrap_or_default().max(6);
+  let path_len = requests.iter().map(|request| field(request, "path").len()).max().unwrap_or_default().max(4);
+  println!("{0:>3}  {1:10}  {2:3$}  {4:5$}  {6}", "#", "Status", "Method", method_len, "Path", path_len, "Interaction");
+  for (i, request) in requests.iter().enumerate() {
+    println!("{0:>3}  {1:10}  {2:3$}  {4:5$}  {6}", i + 1, field(request, "status"), field(request, "method"),
+      method_len, field(request, "path"), path_len, field(request, "interaction"));
+    if let Some(mismatches) = request.get("mismatches").and_then(|mismatches| mismatches.as_array()) {
+      for mismatch in mismatches {
+        println!("       - {}", json_to_string(mismatch));
+      }
+    }
+  }
+}
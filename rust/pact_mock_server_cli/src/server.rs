@@ -1,53 +1,206 @@
 use std::{
   iter::FromIterator,
-  net::TcpListener,
-  process,
-  sync::mpsc,
+  sync::{mpsc, Mutex},
   thread,
   time::Duration
 };
 use std::convert::Infallible;
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 
-use futures::channel::oneshot::channel;
-use hyper::server::Server;
+use futures::channel::oneshot::{channel, Sender};
+use futures::TryStreamExt;
+use hyper::server::{accept, Server};
 use hyper::service::make_service_fn;
+use lazy_static::lazy_static;
 use log::*;
 use maplit::*;
+use rustls::ServerConfig;
 use serde_json::{self, json, Value};
+use tokio::net::TcpListener;
 use uuid::Uuid;
 use webmachine_rust::*;
 use webmachine_rust::context::*;
 use webmachine_rust::headers::*;
 
 use pact_matching::models::{RequestResponsePact, load_pact_from_json};
-use pact_mock_server::mock_server::MockServerConfig;
-use pact_mock_server::tls::TlsConfigBuilder;
+use pact_mock_server::mock_server::{BindAddress, FaultOptions, MockServerConfig, PortSelection, StubOptions};
+use pact_mock_server::server_manager::{ServerManager, VerifyError, WritePactOptions};
+use pact_mock_server::tls::{accept_tls_connections, TlsConfigBuilder};
 
 use crate::{SERVER_MANAGER, SERVER_OPTIONS, ServerOpts};
 use crate::verify;
 
+lazy_static! {
+  /// Used to trigger the graceful shutdown of the master server from the shutdown end point
+  static ref SHUTDOWN: Mutex<Option<Sender<()>>> = Mutex::new(None);
+}
+
 fn json_error(error: String) -> String {
     let json_response = json!({ "error" : json!(error) });
     json_response.to_string()
 }
 
-fn get_next_port(base_port: Option<u16>) -> u16 {
-  match base_port {
-    None => 0,
-    Some(p) => if p > 0 {
-      let mut port = p;
-      let mut listener = TcpListener::bind(("127.0.0.1", port));
-      while listener.is_err() && port < p + 1000 {
-        port += 1;
-        listener = TcpListener::bind(("127.0.0.1", port));
-      }
-      match listener {
-        Ok(listener) => listener.local_addr().unwrap().port(),
-        Err(_) => 0
-      }
-    } else {
-      0
+/// Returns the address a new mock server should bind to. An address given with the request takes
+/// precedence, then the bind address and base port the master server was started with.
+fn mock_server_bind_address(context: &WebmachineContext, options: &ServerOpts) -> Result<BindAddress, String> {
+  let requested = context.request.query.get("bind").and_then(|values| values.first());
+  let bind_address = match requested {
+    Some(address) => address.parse::<BindAddress>()?,
+    None => options.bind_address.clone()
+      .unwrap_or_else(|| BindAddress::new(IpAddr::from([0, 0, 0, 0]), PortSelection::Any))
+  };
+  Ok(match (&bind_address.ports, options.base_port) {
+    (PortSelection::Any, Some(base_port)) if base_port > 0 =>
+      BindAddress::new(bind_address.ip, PortSelection::Range(base_port, base_port.saturating_add(1000))),
+    _ => bind_address
+  })
+}
+
+fn query_param_value<T: std::str::FromStr>(context: &WebmachineContext, name: &str) -> Result<Option<T>, String>
+  where T::Err: std::fmt::Display {
+  match context.request.query.get(name).and_then(|values| values.first()) {
+    Some(value) => value.parse::<T>()
+      .map(Some)
+      .map_err(|err| format!("'{}' is not a valid value for {}: {}", value, name, err)),
+    None => Ok(None)
+  }
+}
+
+fn query_param_rate(context: &WebmachineContext, name: &str) -> Result<f64, String> {
+  match query_param_value::<f64>(context, name)? {
+    Some(rate) if !(0.0..=1.0).contains(&rate) => Err(format!("{} must be between 0.0 and 1.0", name)),
+    rate => Ok(rate.unwrap_or_default())
+  }
+}
+
+/// Returns the faults to inject into the responses of a new mock server from the query parameters
+fn mock_server_faults(context: &WebmachineContext) -> Result<FaultOptions, String> {
+  Ok(FaultOptions {
+    delay: query_param_value::<u64>(context, "delay")?.map(Duration::from_millis),
+    error_rate: query_param_rate(context, "errorRate")?,
+    reset_rate: query_param_rate(context, "resetRate")?
+  })
+}
+
+/// Returns the config for a new mock server from the query parameters
+fn mock_server_config(context: &mut WebmachineContext, options: &ServerOpts) -> Result<MockServerConfig, u16> {
+  let faults = mock_server_faults(context).map_err(|err| {
+    context.response.body = Some(json_error(err).into_bytes());
+    422_u16
+  })?;
+  let stub = query_param_value::<StubOptions>(context, "stub").map_err(|err| {
+    context.response.body = Some(json_error(err).into_bytes());
+    422_u16
+  })?;
+  let event_stream_delay = query_param_value::<u64>(context, "eventDelay").map_err(|err| {
+    context.response.body = Some(json_error(err).into_bytes());
+    422_u16
+  })?;
+  let config = MockServerConfig {
+    cors_preflight: query_param_set(context, "cors"),
+    simulate_slow_responses: query_param_set(context, "slow"),
+    connections: options.connections.clone(),
+    faults,
+    stub,
+    strict_headers: query_param_set(context, "strictHeaders"),
+    strict_header_interactions: context.request.query.get("strictHeadersFor").cloned()
+      .unwrap_or_default().into_iter().collect(),
+    allow_unexpected_keys: query_param_set(context, "allowUnexpectedKeys"),
+    allow_unexpected_keys_interactions: context.request.query.get("allowUnexpectedKeysFor").cloned()
+      .unwrap_or_default().into_iter().collect(),
+    sequence_interactions: context.request.query.get("sequenceFor").cloned()
+      .unwrap_or_default().into_iter().collect(),
+    event_stream_delay: event_stream_delay.map(Duration::from_millis),
+    record_journal: query_param_set(context, "journal"),
+    metrics_endpoint: query_param_set(context, "metrics"),
+    admin_routes: query_param_set(context, "admin"),
+    health_endpoints: true,
+    .. MockServerConfig::default()
+  };
+  debug!("Mock server config = {:?}", config);
+  Ok(config)
+}
+
+/// Returns the pact files to watch for changes from the query parameters, along with the
+/// interval to check them at (defaults to one second)
+fn watched_pact_files(context: &mut WebmachineContext) -> Result<Option<(Vec<PathBuf>, Duration)>, u16> {
+  let paths = match context.request.query.get("watch") {
+    Some(paths) if !paths.is_empty() => paths.iter().map(PathBuf::from).collect::<Vec<PathBuf>>(),
+    _ => return Ok(None)
+  };
+  let interval = query_param_value::<u64>(context, "watchInterval").map_err(|err| {
+    context.response.body = Some(json_error(err).into_bytes());
+    422_u16
+  })?.unwrap_or(1000);
+  Ok(Some((paths, Duration::from_millis(interval))))
+}
+
+/// Starts watching the pact files for the new mock server. If they can not be watched, the mock
+/// server is shut down again.
+fn watch_pact_files(
+  context: &mut WebmachineContext,
+  manager: &mut ServerManager,
+  id: &str,
+  watched: Option<(Vec<PathBuf>, Duration)>
+) -> Result<(), u16> {
+  if let Some((paths, interval)) = watched {
+    if let Err(err) = manager.watch_pact_files(id, paths, interval) {
+      error!("Failed to watch the pact files - {}", err);
+      manager.shutdown_mock_server_by_id(id.to_string());
+      context.response.body = Some(json_error(format!("Failed to watch the pact files - {}", err)).into_bytes());
+      return Err(422);
+    }
+  }
+  Ok(())
+}
+
+/// Adds a virtual host for the pact in the request body to the mock server, so that requests sent
+/// to it for the host name are served from that pact
+fn add_virtual_host(context: &mut WebmachineContext) -> Result<bool, u16> {
+  let parent_id = context.metadata.get("id").cloned().unwrap_or_default();
+  let host = context.metadata.get("host").cloned().unwrap_or_default();
+  debug!("add_virtual_host => {} {}", parent_id, host);
+  let json = match context.request.body {
+    Some(ref body) if !body.is_empty() => serde_json::from_slice::<Value>(body).map_err(|err| {
+      error!("Failed to parse json body - {}", err);
+      context.response.body = Some(json_error(format!("Failed to parse json body - {}", err)).into_bytes());
+      422_u16
+    })?,
+    _ => {
+      context.response.body = Some(json_error("No pact provided in the request body".to_string()).into_bytes());
+      return Err(422);
+    }
+  };
+  let pact = load_pact_from_json(&context.request.request_path, &json)
+    .map_err(|err| {
+      error!("Failed to parse Pact JSON - {}", err);
+      422_u16
+    })?;
+  let options = SERVER_OPTIONS.lock().unwrap().borrow().clone();
+  let config = mock_server_config(context, &options)?;
+  let watched = watched_pact_files(context)?;
+
+  let mock_server_id = Uuid::new_v4().to_string();
+  let mut guard = SERVER_MANAGER.lock().unwrap();
+  match guard.add_virtual_host(&parent_id, &host, mock_server_id.clone(), pact, config) {
+    Ok(()) => {
+      watch_pact_files(context, &mut guard, &mock_server_id, watched)?;
+      let mock_server_json = guard.find_mock_server_by_id(&mock_server_id, &|ms| json!({
+        "id" : json!(ms.id.clone()),
+        "port" : json!(ms.port.unwrap_or_default() as i64),
+        "address" : json!(ms.address.clone().unwrap_or_default()),
+        "host" : json!(ms.host.clone().unwrap_or_default())
+      }));
+      let json_response = json!({ "mockServer" : mock_server_json });
+      context.response.body = Some(json_response.to_string().into_bytes());
+      context.response.add_header("Location",
+        vec![HeaderValue::basic(format!("/mockserver/{}", mock_server_id).as_str())]);
+      Ok(true)
+    },
+    Err(msg) => {
+      context.response.body = Some(json_error(format!("Failed to add virtual host - {}", msg)).into_bytes());
+      Err(422)
     }
   }
 }
@@ -65,10 +218,14 @@ fn start_provider(context: &mut WebmachineContext, options: ServerOpts) -> Resul
             })?;
           debug!("Loaded pact = {:?}", pact);
           let mock_server_id = Uuid::new_v4().to_string();
-          let config = MockServerConfig {
-            cors_preflight: query_param_set(context, "cors")
-          };
-          debug!("Mock server config = {:?}", config);
+          let config = mock_server_config(context, &options)?;
+          let watched = watched_pact_files(context)?;
+
+          let bind_address = mock_server_bind_address(context, &options).map_err(|err| {
+            context.response.body = Some(json_error(err).into_bytes());
+            422_u16
+          })?;
+          debug!("Mock server bind address = {}", bind_address);
 
           let mut guard = SERVER_MANAGER.lock().unwrap();
           let result = if query_param_set(context, "tls") {
@@ -83,18 +240,20 @@ fn start_provider(context: &mut WebmachineContext, options: ServerOpts) -> Resul
                 format!("Failed to setup TLS using self-signed certificate - {}", err)
               })
               .and_then(|tls_config| {
-                guard.start_tls_mock_server(mock_server_id.clone(), pact, get_next_port(options.base_port), &tls_config, config)
+                guard.start_tls_mock_server_with_bind_address(mock_server_id.clone(), pact, &bind_address, &tls_config, config)
               })
           } else {
             debug!("Starting mock server with id {}", &mock_server_id);
-            guard.start_mock_server(mock_server_id.clone(), pact, get_next_port(options.base_port), config)
+            guard.start_mock_server_with_bind_address(mock_server_id.clone(), pact, &bind_address, config)
           };
           match result {
             Ok(mock_server) => {
-              debug!("mock server started on port {}", mock_server);
+              debug!("mock server started on {}", mock_server);
+              watch_pact_files(context, &mut guard, &mock_server_id, watched)?;
               let mock_server_json = json!({
                 "id" : json!(mock_server_id.clone()),
-                "port" : json!(mock_server as i64),
+                "port" : json!(mock_server.port() as i64),
+                "address" : json!(mock_server.ip().to_string())
               });
               let json_response = json!({ "mockServer" : mock_server_json });
               context.response.body = Some(json_response.to_string().into_bytes());
@@ -131,29 +290,84 @@ fn query_param_set(context: &mut WebmachineContext, name: &str) -> bool {
 
 pub fn verify_mock_server_request(context: &mut WebmachineContext) -> Result<bool, u16> {
   let id = context.metadata.get("id").cloned().unwrap_or_default();
-  match verify::validate_id(&id, &SERVER_MANAGER) {
-    Ok(ms) => {
-      let mut map = btreemap!{ "mockServer" => ms.to_json() };
-      let mismatches = ms.mismatches();
-      if !mismatches.is_empty() {
-        map.insert("mismatches", json!(
-          Vec::from_iter(mismatches.iter().map(|m| m.to_json()))));
-        context.response.body = Some(json!(map).to_string().into_bytes());
-        Err(422)
-      } else {
-        let inner = SERVER_OPTIONS.lock().unwrap();
-        let options = inner.borrow();
-        match ms.write_pact(&options.output_path, false) {
-          Ok(_) => Ok(true),
-          Err(err) => {
-            map.insert("error", json!(format!("Failed to write pact to file - {}", err)));
-            context.response.body = Some(json!(map).to_string().into_bytes());
-            Err(422)
-          }
+  let options = {
+    let inner = SERVER_OPTIONS.lock().unwrap();
+    let options = inner.borrow();
+    WritePactOptions {
+      output_path: options.output_path.clone(),
+      overwrite: false,
+      layout: options.pact_layout
+    }
+  };
+  let manager = SERVER_MANAGER.lock().unwrap();
+  match manager.verify_mock_server(&id, &options) {
+    Ok(()) => Ok(true),
+    Err(VerifyError::NotFound(_)) => Err(422),
+    Err(err) => {
+      let mut map = btreemap!{ "mockServer" => manager.find_mock_server(&id, &|ms| ms.to_json()).unwrap_or_default() };
+      match err {
+        VerifyError::Mismatches(mismatches) => {
+          map.insert("mismatches", json!(Vec::from_iter(mismatches.iter().map(|m| m.to_json()))));
+        },
+        err => {
+          map.insert("error", json!(err.to_string()));
         }
       }
+      context.response.body = Some(json!(map).to_string().into_bytes());
+      Err(422)
+    }
+  }
+}
+
+/// Compares the two values in constant time (for values of the same length), so the time taken
+/// does not reveal how much of a token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b.iter()).fold(0_u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// If the request has the token as a bearer token in the Authorization header. All the header
+/// values are compared, so the time taken does not depend on which one matched.
+fn has_bearer_token(context: &WebmachineContext, token: &str) -> bool {
+  let expected = format!("Bearer {}", token);
+  context.request.find_header("Authorization").iter()
+    .fold(false, |found, value| constant_time_eq(value.value.as_bytes(), expected.as_bytes()) | found)
+}
+
+/// Requests to the control API need to have the auth token of the master server as a bearer token,
+/// if the master server was started with one
+fn not_authorized(context: &mut WebmachineContext) -> Option<String> {
+  let inner = SERVER_OPTIONS.lock().unwrap();
+  let options = inner.borrow();
+  match &options.auth_token {
+    Some(token) if !has_bearer_token(context, token) => {
+      warn!("Rejecting {} request to {} without a valid auth token", context.request.method, context.request.request_path);
+      Some("Bearer realm=\"pact_mock_server\"".to_string())
     },
-    Err(_) => Err(422)
+    _ => None
+  }
+}
+
+/// Liveness probe of the master server. This does not require the auth token, so it can be used
+/// by Kubernetes probes.
+fn health_resource<'a>() -> WebmachineResource<'a> {
+  WebmachineResource {
+    allowed_methods: vec!["GET", "HEAD"],
+    render_response: callback(&|_, _| Some(json!({ "status": "UP" }).to_string())),
+    .. WebmachineResource::default()
+  }
+}
+
+/// Readiness probe of the master server. The master server is ready while it is able to manage
+/// the mock servers.
+fn readiness_resource<'a>() -> WebmachineResource<'a> {
+  WebmachineResource {
+    allowed_methods: vec!["GET", "HEAD"],
+    available: callback(&|_, _| SERVER_MANAGER.lock().is_ok()),
+    render_response: callback(&|_, _| {
+      let mock_servers = SERVER_MANAGER.lock().unwrap().map_mock_servers(&|_| ());
+      Some(json!({ "status": "UP", "mockServers": mock_servers.len() }).to_string())
+    }),
+    .. WebmachineResource::default()
   }
 }
 
@@ -161,8 +375,8 @@ fn shutdown_resource<'a>() -> WebmachineResource<'a> {
   WebmachineResource {
     allowed_methods: vec!["POST"],
     forbidden: callback(&|context, _| {
-      let options = SERVER_OPTIONS.lock().unwrap();
-      !context.request.has_header_value(&"Authorization".to_owned(), &format!("Bearer {}", options.borrow().server_key))
+      let server_key = SERVER_OPTIONS.lock().unwrap().borrow().server_key.clone();
+      !has_bearer_token(context, &server_key)
     }),
     process_post: callback(&|context, _| {
       let shutdown_period = match context.request.body {
@@ -187,13 +401,13 @@ fn shutdown_resource<'a>() -> WebmachineResource<'a> {
 
       match shutdown_period {
         Ok(period) => {
-          // Need to work out how to do this as the webmachine has to have a static lifetime
-          // shutdown.send(()).unwrap_or_default();
           thread::spawn(move || {
             info!("Scheduling master server to shutdown in {}ms", period);
             thread::sleep(Duration::from_millis(period));
             info!("Shutting down");
-            process::exit(0);
+            if let Some(shutdown) = SHUTDOWN.lock().unwrap().take() {
+              shutdown.send(()).unwrap_or_default();
+            }
           });
           Ok(true)
         }
@@ -207,6 +421,7 @@ fn shutdown_resource<'a>() -> WebmachineResource<'a> {
 fn mock_server_resource<'a>() -> WebmachineResource<'a> {
   WebmachineResource {
     allowed_methods: vec!["OPTIONS", "GET", "HEAD", "POST", "DELETE"],
+    not_authorized: callback(&|context, _| not_authorized(context)),
     resource_exists: callback(&|context, _| {
       debug!("mock_server_resource -> resource_exists");
       let paths: Vec<String> = context.request.request_path
@@ -214,14 +429,17 @@ fn mock_server_resource<'a>() -> WebmachineResource<'a> {
         .filter(|p| !p.is_empty())
         .map(|p| p.to_string())
         .collect();
-      if !paths.is_empty() && paths.len() <= 2 {
+      if !paths.is_empty() && paths.len() <= 3 {
         match verify::validate_id(&paths[0].clone(), &SERVER_MANAGER) {
           Ok(ms) => {
             context.metadata.insert("id".to_string(), ms.id.clone());
-            context.metadata.insert("port".to_string(), ms.port.unwrap_or_default().to_string());
-            if paths.len() > 1 {
+            if paths.len() > 2 {
+              context.metadata.insert("subpath".to_string(), paths[1].clone());
+              context.metadata.insert("host".to_string(), paths[2].clone());
+              paths[1] == "hosts"
+            } else if paths.len() > 1 {
               context.metadata.insert("subpath".to_string(), paths[1].clone());
-              paths[1] == "verify"
+              paths[1] == "verify" || paths[1] == "reset" || paths[1] == "journal" || paths[1] == "requests"
             } else {
               true
             }
@@ -240,6 +458,17 @@ fn mock_server_resource<'a>() -> WebmachineResource<'a> {
           SERVER_MANAGER.lock().unwrap().find_mock_server_by_id(&id, &|ms| ms.to_json())
             .map(|json| json.to_string())
         }
+        Some(subpath) if subpath == "journal" => {
+          let id = context.metadata.get("id".into()).unwrap().clone();
+          SERVER_MANAGER.lock().unwrap().find_mock_server_by_id(&id, &|ms| {
+            json!(ms.journal().iter().map(|entry| entry.to_json()).collect::<Vec<Value>>())
+          }).map(|json| json.to_string())
+        }
+        Some(subpath) if subpath == "requests" => {
+          let id = context.metadata.get("id".into()).unwrap().clone();
+          SERVER_MANAGER.lock().unwrap().find_mock_server_by_id(&id, &|ms| ms.received_requests_json())
+            .map(|json| json.to_string())
+        }
         Some(_) => {
           context.response.status = 405;
           None
@@ -251,6 +480,13 @@ fn mock_server_resource<'a>() -> WebmachineResource<'a> {
       let subpath = context.metadata.get("subpath".into()).unwrap().clone();
       if subpath == "verify" {
         verify_mock_server_request(context)
+      } else if subpath == "reset" {
+        let id = context.metadata.get("id").cloned().unwrap_or_default();
+        SERVER_MANAGER.lock().unwrap().find_mock_server_by_id_mut(&id, &|ms| ms.reset_sequences())
+          .map(|_| true)
+          .ok_or(404)
+      } else if subpath == "hosts" {
+        add_virtual_host(context)
       } else {
         Err(422)
       }
@@ -280,6 +516,7 @@ fn dispatcher() -> WebmachineDispatcher<'static>  {
     routes: btreemap! {
       "/" => WebmachineResource {
         allowed_methods: vec!["OPTIONS", "GET", "HEAD", "POST"],
+        not_authorized: callback(&|context, _| not_authorized(context)),
         resource_exists: callback(&|context, _| {
           debug!("main_resource -> resource_exists");
           context.request.request_path == "/"
@@ -333,36 +570,157 @@ fn dispatcher() -> WebmachineDispatcher<'static>  {
         }),
         .. WebmachineResource::default()
       },
+      "/healthz" => health_resource(),
+      "/readyz" => readiness_resource(),
       "/mockserver" => mock_server_resource(),
       "/shutdown" => shutdown_resource()
     }
   }
 }
 
-pub async fn start_server(port: u16) -> Result<(), i32> {
+fn log_server_started(port: u16, scheme: &str) {
+  let inner = SERVER_OPTIONS.lock().unwrap();
+  let options = inner.borrow();
+  info!("Master server started on port {} ({})", port, scheme);
+  info!("Server key: '{}'", options.server_key);
+  if options.auth_token.is_some() {
+    info!("Requests to the master server require the auth token");
+  }
+}
+
+/// Starts the master server on the port. If a TLS configuration is provided, the master server
+/// listens with HTTPS. The server runs until it is shutdown with the shutdown end point.
+pub async fn start_server(port: u16, tls: Option<ServerConfig>) -> Result<(), i32> {
   let addr = SocketAddr::new(IpAddr::from([0, 0, 0, 0]), port);
   let (shutdown_tx, shutdown_rx) = channel::<()>();
+  SHUTDOWN.lock().unwrap().replace(shutdown_tx);
+  let shutdown = async { shutdown_rx.await.unwrap_or_default() };
 
-  let make_svc = make_service_fn(|_| async {
-    Ok::<_, Infallible>(dispatcher())
-  });
-  match Server::try_bind(&addr) {
-    Ok(server) => {
-      let server = server.serve(make_svc);
-      {
-        let inner = SERVER_OPTIONS.lock().unwrap();
-        let options = inner.borrow();
-        info!("Master server started on port {}", server.local_addr().port());
-        info!("Server key: '{}'", options.server_key);
-      }
-      server.with_graceful_shutdown(async { shutdown_rx.await.unwrap_or_default() }).await.map_err(|err| {
-        error!("Received an error starting master server: {}", err);
-        2
-      })
+  let result = match tls {
+    Some(tls) => {
+      let listener = TcpListener::bind(&addr).await.map_err(|err| {
+        error!("could not start master server: {}", err);
+        1
+      })?;
+      let port = listener.local_addr().map(|addr| addr.port()).unwrap_or(port);
+      let connections = accept_tls_connections(listener, tls, None).map_ok(|(stream, _)| stream);
+      let server = Server::builder(accept::from_stream(connections))
+        .serve(make_service_fn(|_| async { Ok::<_, Infallible>(dispatcher()) }));
+      log_server_started(port, "https");
+      server.with_graceful_shutdown(shutdown).await
     },
-    Err(err) => {
-      error!("could not start master server: {}", err);
-      Err(1)
+    None => match Server::try_bind(&addr) {
+      Ok(server) => {
+        let server = server.serve(make_service_fn(|_| async { Ok::<_, Infallible>(dispatcher()) }));
+        log_server_started(server.local_addr().port(), "http");
+        server.with_graceful_shutdown(shutdown).await
+      },
+      Err(err) => {
+        error!("could not start master server: {}", err);
+        return Err(1);
+      }
     }
+  };
+  result.map_err(|err| {
+    error!("Received an error starting master server: {}", err);
+    2
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::MutexGuard;
+
+  use expectest::prelude::*;
+
+  use super::*;
+
+  lazy_static! {
+    /// The auth token is a global option, so the tests that set it run one at a time
+    static ref AUTH_TOKEN_TESTS: Mutex<()> = Mutex::new(());
+  }
+
+  /// Sets the auth token of the master server, and clears it again when dropped
+  struct AuthToken {
+    _lock: MutexGuard<'static, ()>
+  }
+
+  impl AuthToken {
+    fn set(token: &str) -> AuthToken {
+      let lock = AUTH_TOKEN_TESTS.lock().unwrap_or_else(|err| err.into_inner());
+      SERVER_OPTIONS.lock().unwrap().borrow_mut().auth_token = Some(token.to_string());
+      AuthToken { _lock: lock }
+    }
+  }
+
+  impl Drop for AuthToken {
+    fn drop(&mut self) {
+      SERVER_OPTIONS.lock().unwrap_or_else(|err| err.into_inner()).borrow_mut().auth_token = None;
+    }
+  }
+
+  fn control_request(method: &str, path: &str, authorization: Option<&str>, body: Option<Value>) -> WebmachineContext {
+    let mut context = WebmachineContext::default();
+    context.request.method = method.to_string();
+    context.request.request_path = path.to_string();
+    if let Some(authorization) = authorization {
+      context.request.headers.insert("Authorization".to_string(), vec![HeaderValue::basic(authorization)]);
+    }
+    if let Some(body) = body {
+      context.request.headers.insert("Content-Type".to_string(), vec![HeaderValue::basic("application/json")]);
+      context.request.body = Some(body.to_string().into_bytes());
+    }
+    dispatcher().dispatch_to_resource(&mut context);
+    context
+  }
+
+  #[test]
+  fn constant_time_eq_compares_the_values() {
+    expect!(constant_time_eq(b"Bearer 1234", b"Bearer 1234")).to(be_true());
+    expect!(constant_time_eq(b"Bearer 1234", b"Bearer 1235")).to(be_false());
+    expect!(constant_time_eq(b"Bearer 1234", b"Bearer 12345")).to(be_false());
+    expect!(constant_time_eq(b"", b"")).to(be_true());
+  }
+
+  #[test]
+  fn health_check_does_not_require_the_auth_token() {
+    let _auth_token = AuthToken::set("secret-token");
+    expect!(control_request("GET", "/healthz", None, None).response.status).to(be_equal_to(200));
+  }
+
+  #[test]
+  fn control_routes_reject_a_missing_or_wrong_auth_token() {
+    let _auth_token = AuthToken::set("secret-token");
+
+    let context = control_request("GET", "/", None, None);
+    expect!(context.response.status).to(be_equal_to(401));
+    expect!(context.response.headers.get("WWW-Authenticate").cloned()).to(be_some());
+    expect!(control_request("GET", "/", Some("Bearer wrong-token"), None).response.status).to(be_equal_to(401));
+    expect!(control_request("GET", "/", Some("secret-token"), None).response.status).to(be_equal_to(401));
+    expect!(control_request("GET", "/mockserver/1234", None, None).response.status).to(be_equal_to(401));
+    expect!(control_request("DELETE", "/mockserver/1234", Some("Bearer wrong-token"), None).response.status)
+      .to(be_equal_to(401));
+    expect!(control_request("POST", "/", Some("Bearer wrong-token"), Some(json!({}))).response.status)
+      .to(be_equal_to(401));
+  }
+
+  #[test]
+  fn control_routes_accept_the_auth_token() {
+    let _auth_token = AuthToken::set("secret-token");
+    let token = Some("Bearer secret-token");
+    expect!(control_request("GET", "/", token, None).response.status).to(be_equal_to(200));
+
+    let pact = json!({
+      "consumer": { "name": "auth_consumer" },
+      "provider": { "name": "auth_provider" },
+      "interactions": [],
+      "metadata": { "pactSpecification": { "version": "3.0.0" } }
+    });
+    let context = control_request("POST", "/", token, Some(pact));
+    expect!(context.response.status).to(be_equal_to(200));
+    let body: Value = serde_json::from_slice(&context.response.body.unwrap_or_default()).unwrap();
+    let path = format!("/mockserver/{}", body["mockServer"]["id"].as_str().unwrap_or_default());
+    expect!(control_request("GET", &path, token, None).response.status).to(be_equal_to(200));
+    expect!(control_request("DELETE", &path, token, None).response.status).to(be_equal_to(204));
   }
 }
@@ -11,9 +11,9 @@ pub async fn shutdown_mock_server(host: &str, port: u16, matches: &ArgMatches<'_
     (mock_server_port.unwrap(), "port")
   };
 
-  let client = reqwest::Client::new();
-  let url = format!("http://{}:{}/mockserver/{}", host, port, id.0);
-  let resp = client.delete(&url).send().await;
+  let client = crate::master_server_client(matches);
+  let url = format!("{}/mockserver/{}", crate::master_server_url(host, port, matches), id.0);
+  let resp = crate::with_auth_token(client.delete(&url), matches).send().await;
   match resp {
     Ok(result) => {
       if !result.status().is_success() {
@@ -36,10 +36,10 @@ pub async fn shutdown_mock_server(host: &str, port: u16, matches: &ArgMatches<'_
 }
 
 pub async fn shutdown_master_server(host: &str, port: u16, matches: &ArgMatches<'_>) -> Result<(), i32> {
-  let client = reqwest::Client::new();
+  let client = crate::master_server_client(matches);
   let server_key = matches.value_of("server-key").unwrap().to_owned();
   let shutdown_period = matches.value_of("period").map(|val| val.parse::<u16>().unwrap_or(100)).unwrap_or(100);
-  let url = format!("http://{}:{}/shutdown", host, port);
+  let url = format!("{}/shutdown", crate::master_server_url(host, port, matches));
   let res = client.post(&url)
     .bearer_auth(server_key)
     .json(&json!({ "period": shutdown_period }))
@@ -4,7 +4,6 @@ use pact_mock_server::{
     server_manager::ServerManager,
     mock_server::MockServer
 };
-use pact_matching::s;
 use http::StatusCode;
 use serde_json::Value;
 use crate::handle_error;
@@ -19,9 +18,9 @@ pub async fn verify_mock_server(host: &str, port: u16, matches: &ArgMatches<'_>)
     (mock_server_port.unwrap(), "port")
   };
 
-  let client = reqwest::Client::new();
-  let url = format!("http://{}:{}/mockserver/{}/verify", host, port, id.0);
-  let resp = client.post(&url)
+  let client = crate::master_server_client(matches);
+  let url = format!("{}/mockserver/{}/verify", crate::master_server_url(host, port, matches), id.0);
+  let resp = crate::with_auth_token(client.post(&url), matches)
     .send().await;
   match resp {
     Ok(result) => {
@@ -73,28 +72,10 @@ pub async fn verify_mock_server(host: &str, port: u16, matches: &ArgMatches<'_>)
   }
 }
 
-fn validate_port(port: u16, server_manager: &Mutex<ServerManager>) -> Result<MockServer, String> {
-    server_manager.lock().unwrap()
-        .find_mock_server_by_port_mut(port, &|ms| {
-            ms.clone()
-        })
-        .ok_or(format!("No mock server running with port '{}'", port))
-}
-
-fn validate_uuid(id: &str, server_manager: &Mutex<ServerManager>) -> Result<MockServer, String> {
-    server_manager.lock().unwrap()
-        .find_mock_server_by_id(&id.to_string(), &|ms| {
-            ms.clone()
-        })
-        .ok_or(format!("No mock server running with id '{}'", id))
-}
-
 pub fn validate_id(id: &str, server_manager: &Mutex<ServerManager>) -> Result<MockServer, String> {
-    if id.chars().all(|ch| ch.is_digit(10)) {
-        validate_port(id.parse::<u16>().unwrap(), server_manager)
-    } else {
-        validate_uuid(&s!(id), server_manager)
-    }
+  server_manager.lock().unwrap()
+    .find_mock_server(id, &|ms| ms.clone())
+    .ok_or(format!("No mock server running with id or port '{}'", id))
 }
 
 fn display_verification_errors(id: &str, port: u64, json: &serde_json::Value) {
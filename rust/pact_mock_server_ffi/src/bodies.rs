@@ -10,6 +10,7 @@ use serde_json::{Map, Value};
 use pact_matching::models::{Request, Response};
 use pact_matching::models::generators::{Generator, GeneratorCategory, Generators};
 use pact_matching::models::json_utils::{json_to_num, json_to_string};
+use pact_matching::models::matching_rule_definition::{is_matcher_def, parse_matcher_def};
 use pact_matching::models::matchingrules::{MatchingRule, MatchingRuleCategory, RuleLogic};
 use pact_models::OptionalBody;
 
@@ -48,6 +49,11 @@ pub fn process_object(
   skip_matchers: bool
 ) -> Value {
   if obj.contains_key("pact:matcher:type") {
+    if let Some(Value::String(expression)) = obj.get("pact:matcher:type") {
+      if is_matcher_def(expression) {
+        return process_matcher_def(expression, matching_rules, generators, path, skip_matchers);
+      }
+    }
     if !skip_matchers {
       let matching_rule = from_integration_json(obj);
       if let Some(rule) = &matching_rule {
@@ -102,6 +108,29 @@ pub fn process_object(
   }
 }
 
+/// Process a matching rule definition expression (i.e. `matching(type, 'Name')`), returning the
+/// example value from it
+fn process_matcher_def(
+  expression: &str,
+  matching_rules: &mut MatchingRuleCategory,
+  generators: &mut Generators,
+  path: &str,
+  skip_matchers: bool
+) -> Value {
+  match parse_matcher_def(expression) {
+    Ok(definition) => {
+      if !skip_matchers {
+        definition.add_to(path, matching_rules, generators, &GeneratorCategory::BODY);
+      }
+      definition.value
+    },
+    Err(err) => {
+      warn!("Failed to parse the matching rule definition '{}' - {}", expression, err);
+      Value::Null
+    }
+  }
+}
+
 /// Builds a `MatchingRule` from a `Value` struct used by language integrations
 pub fn from_integration_json(m: &Map<String, Value>) -> Option<MatchingRule> {
   match m.get("pact:matcher:type") {
@@ -357,4 +386,32 @@ mod test {
     }));
     expect!(generators).to(be_equal_to(Generators::default()));
   }
+
+  #[test]
+  fn process_object_with_a_matching_rule_definition() {
+    let json = json!({
+      "name": { "pact:matcher:type": "matching(type, 'Fred')" },
+      "ids": { "pact:matcher:type": "eachLike(matching(integer, 100), 2)" }
+    });
+    let mut matching_rules = MatchingRuleCategory::empty("body");
+    let mut generators = Generators::default();
+    let result = process_object(json.as_object().unwrap(), &mut matching_rules,
+                                &mut generators, "$", false, false);
+
+    expect!(result).to(be_equal_to(json!({
+      "name": "Fred",
+      "ids": [100, 100]
+    })));
+    expect!(matching_rules).to(be_equal_to(matchingrules_list!{
+      "body";
+      "$.name" => [ MatchingRule::Type ],
+      "$.ids" => [ MatchingRule::MinType(2) ],
+      "$.ids[*]" => [ MatchingRule::Integer ]
+    }));
+    expect!(generators).to(be_equal_to(generators! {
+      "BODY" => {
+        "$.ids[*]" => Generator::RandomInt(0, 10)
+      }
+    }));
+  }
 }
@@ -51,6 +51,14 @@ lazy_static! {
     sub_type: "plain".into(),
     .. ContentType::default()
   };
+
+  /// SOAP 1.2 Content Type (SOAP 1.1 uses text/xml)
+  pub static ref SOAP: ContentType = ContentType {
+    main_type: "application".into(),
+    sub_type: "soap".into(),
+    suffix: Some("xml".into()),
+    .. ContentType::default()
+  };
 }
 
 impl ContentType {
@@ -92,6 +100,12 @@ impl ContentType {
     (self.main_type == "application" || self.main_type == "text") && self.sub_type == "xml"
   }
 
+  /// If it is a SOAP type (application/soap+xml)
+  pub fn is_soap(&self) -> bool {
+    self.main_type == "application" && self.sub_type == "soap" &&
+      self.suffix.as_ref().unwrap_or(&String::default()) == "xml"
+  }
+
   /// If it is a text type
   pub fn is_text(&self) -> bool {
     self.main_type == "text" || self.is_xml() || self.is_json()
@@ -129,7 +143,7 @@ impl ContentType {
 
   /// Equals, ignoring attributes if not present on self
   pub fn is_equivalent_to(&self, other: &ContentType) -> bool {
-    if self.is_strict_xml() && other.is_strict_xml() {
+    if (self.is_strict_xml() || self.is_soap()) && (other.is_strict_xml() || other.is_soap()) {
       self.attributes.is_empty() || self.attributes == other.attributes
     }
     else if self.attributes.is_empty() {
@@ -216,7 +230,7 @@ mod tests {
   use expectest::prelude::*;
   use maplit::btreemap;
 
-  use super::ContentType;
+  use super::{ContentType, SOAP};
 
   #[test]
   fn parse_test() {
@@ -396,4 +410,18 @@ mod tests {
     expect!(content_type2.is_equivalent_to(&content_type3)).to(be_true());
     expect!(content_type2.is_equivalent_to(&content_type4)).to(be_false());
   }
+
+  #[test]
+  fn soap_test() {
+    let content_type = ContentType::parse("application/soap+xml; charset=UTF-8").unwrap();
+    expect!(content_type.is_soap()).to(be_true());
+    expect!(content_type.is_xml()).to(be_true());
+    expect!(SOAP.is_soap()).to(be_true());
+    expect!(ContentType::parse("application/xml").unwrap().is_soap()).to(be_false());
+
+    let text_xml = ContentType::parse("text/xml").unwrap();
+    expect!(text_xml.is_equivalent_to(&content_type)).to(be_true());
+    expect!(SOAP.is_equivalent_to(&text_xml)).to(be_true());
+    expect!(SOAP.is_equivalent_to(&ContentType::parse("application/atom+xml").unwrap())).to(be_false());
+  }
 }
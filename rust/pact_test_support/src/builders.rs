@@ -0,0 +1,399 @@
+//! Builders for pacts, interactions, messages, requests and responses. Anything not set on a
+//! builder is defaulted.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use pact_matching::models::{Interaction, RequestResponseInteraction, RequestResponsePact, Request, Response};
+use pact_matching::models::matchingrules::{MatchingRule, MatchingRules, RuleLogic};
+use pact_matching::models::message::Message;
+use pact_matching::models::message_pact::MessagePact;
+use pact_matching::models::provider_states::ProviderState;
+use pact_matching::models::v4::{SynchronousHttp, V4Interaction, V4Pact};
+use pact_models::{Consumer, OptionalBody, PactSpecification, Provider};
+
+fn add_header(headers: &mut Option<HashMap<String, Vec<String>>>, name: &str, value: &str) {
+  headers.get_or_insert_with(HashMap::new)
+    .entry(name.to_string())
+    .or_default()
+    .push(value.to_string());
+}
+
+fn add_matching_rule(rules: &mut MatchingRules, category: &str, path: &str, rule: MatchingRule) {
+  rules.add_category(category).add_rule(path, rule, &RuleLogic::And);
+}
+
+fn json_body(json: &Value) -> OptionalBody {
+  OptionalBody::Present(json.to_string().into(), Some("application/json".into()))
+}
+
+/// Builder for `Request` structs
+#[derive(Debug, Clone, Default)]
+pub struct RequestBuilder {
+  request: Request
+}
+
+impl RequestBuilder {
+  /// Creates a builder for a GET request to `/`
+  pub fn new() -> Self {
+    RequestBuilder::default()
+  }
+
+  /// Creates a builder for a GET request to the path
+  pub fn get(path: &str) -> Self {
+    RequestBuilder::new().method("GET").path(path)
+  }
+
+  /// Creates a builder for a POST request to the path
+  pub fn post(path: &str) -> Self {
+    RequestBuilder::new().method("POST").path(path)
+  }
+
+  /// Sets the request method
+  pub fn method(mut self, method: &str) -> Self {
+    self.request.method = method.to_string();
+    self
+  }
+
+  /// Sets the request path
+  pub fn path(mut self, path: &str) -> Self {
+    self.request.path = path.to_string();
+    self
+  }
+
+  /// Adds a query parameter value
+  pub fn query_param(mut self, name: &str, value: &str) -> Self {
+    self.request.query.get_or_insert_with(HashMap::new)
+      .entry(name.to_string())
+      .or_default()
+      .push(value.to_string());
+    self
+  }
+
+  /// Adds a header value
+  pub fn header(mut self, name: &str, value: &str) -> Self {
+    add_header(&mut self.request.headers, name, value);
+    self
+  }
+
+  /// Sets the body. The content type is taken from the `Content-Type` header if there is one.
+  pub fn body(mut self, body: &str) -> Self {
+    self.request.body = OptionalBody::Present(body.to_string().into(), None);
+    self
+  }
+
+  /// Sets a JSON body, and adds a `Content-Type` header of `application/json`
+  pub fn json_body(mut self, json: Value) -> Self {
+    self.request.body = json_body(&json);
+    self.header("Content-Type", "application/json")
+  }
+
+  /// Adds a matching rule for the path in the category (i.e. `body` and `$.id`)
+  pub fn matching_rule(mut self, category: &str, path: &str, rule: MatchingRule) -> Self {
+    add_matching_rule(&mut self.request.matching_rules, category, path, rule);
+    self
+  }
+
+  /// Builds the request
+  pub fn build(&self) -> Request {
+    self.request.clone()
+  }
+}
+
+/// Builder for `Response` structs
+#[derive(Debug, Clone, Default)]
+pub struct ResponseBuilder {
+  response: Response
+}
+
+impl ResponseBuilder {
+  /// Creates a builder for a 200 response
+  pub fn new() -> Self {
+    ResponseBuilder::default()
+  }
+
+  /// Creates a builder for a 200 response
+  pub fn ok() -> Self {
+    ResponseBuilder::new()
+  }
+
+  /// Sets the response status
+  pub fn status(mut self, status: u16) -> Self {
+    self.response.status = status;
+    self
+  }
+
+  /// Adds a header value
+  pub fn header(mut self, name: &str, value: &str) -> Self {
+    add_header(&mut self.response.headers, name, value);
+    self
+  }
+
+  /// Sets the body. The content type is taken from the `Content-Type` header if there is one.
+  pub fn body(mut self, body: &str) -> Self {
+    self.response.body = OptionalBody::Present(body.to_string().into(), None);
+    self
+  }
+
+  /// Sets a JSON body, and adds a `Content-Type` header of `application/json`
+  pub fn json_body(mut self, json: Value) -> Self {
+    self.response.body = json_body(&json);
+    self.header("Content-Type", "application/json")
+  }
+
+  /// Adds a matching rule for the path in the category (i.e. `body` and `$.id`)
+  pub fn matching_rule(mut self, category: &str, path: &str, rule: MatchingRule) -> Self {
+    add_matching_rule(&mut self.response.matching_rules, category, path, rule);
+    self
+  }
+
+  /// Builds the response
+  pub fn build(&self) -> Response {
+    self.response.clone()
+  }
+}
+
+/// Builder for request/response interactions
+#[derive(Debug, Clone, Default)]
+pub struct InteractionBuilder {
+  interaction: RequestResponseInteraction
+}
+
+impl InteractionBuilder {
+  /// Creates a builder for an interaction with the description
+  pub fn new(description: &str) -> Self {
+    InteractionBuilder {
+      interaction: RequestResponseInteraction {
+        description: description.to_string(),
+        .. RequestResponseInteraction::default()
+      }
+    }
+  }
+
+  /// Adds a provider state
+  pub fn given(mut self, state: &str) -> Self {
+    self.interaction.provider_states.push(ProviderState::default(&state.to_string()));
+    self
+  }
+
+  /// Adds a provider state with parameters
+  pub fn given_with_params(mut self, state: &str, params: HashMap<String, Value>) -> Self {
+    self.interaction.provider_states.push(ProviderState { name: state.to_string(), params });
+    self
+  }
+
+  /// Sets the request
+  pub fn request(mut self, request: Request) -> Self {
+    self.interaction.request = request;
+    self
+  }
+
+  /// Sets the response
+  pub fn response(mut self, response: Response) -> Self {
+    self.interaction.response = response;
+    self
+  }
+
+  /// Builds the interaction
+  pub fn build(&self) -> RequestResponseInteraction {
+    self.interaction.clone()
+  }
+
+  /// Builds the interaction as a V4 synchronous HTTP interaction
+  pub fn build_v4(&self) -> SynchronousHttp {
+    self.interaction.as_v4_http().unwrap()
+  }
+}
+
+/// Builder for message interactions
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+  message: Message
+}
+
+impl MessageBuilder {
+  /// Creates a builder for a message with the description
+  pub fn new(description: &str) -> Self {
+    MessageBuilder {
+      message: Message {
+        description: description.to_string(),
+        .. Message::default()
+      }
+    }
+  }
+
+  /// Adds a provider state
+  pub fn given(mut self, state: &str) -> Self {
+    self.message.provider_states.push(ProviderState::default(&state.to_string()));
+    self
+  }
+
+  /// Sets JSON contents for the message, and a `contentType` metadata value of `application/json`
+  pub fn json_contents(mut self, json: Value) -> Self {
+    self.message.contents = json_body(&json);
+    self.metadata("contentType", "application/json")
+  }
+
+  /// Sets the contents of the message
+  pub fn contents(mut self, contents: &str) -> Self {
+    self.message.contents = OptionalBody::Present(contents.to_string().into(), None);
+    self
+  }
+
+  /// Adds a metadata value
+  pub fn metadata(mut self, key: &str, value: &str) -> Self {
+    self.message.metadata.insert(key.to_string(), value.to_string());
+    self
+  }
+
+  /// Adds a matching rule for the path in the category (i.e. `body` and `$.id`)
+  pub fn matching_rule(mut self, category: &str, path: &str, rule: MatchingRule) -> Self {
+    add_matching_rule(&mut self.message.matching_rules, category, path, rule);
+    self
+  }
+
+  /// Builds the message
+  pub fn build(&self) -> Message {
+    self.message.clone()
+  }
+}
+
+/// Builder for pacts. Depending on which build function is used, this creates a request/response
+/// pact, a message pact or a V4 pact.
+#[derive(Debug, Clone)]
+pub struct PactBuilder {
+  consumer: String,
+  provider: String,
+  interactions: Vec<RequestResponseInteraction>,
+  messages: Vec<Message>,
+  specification: PactSpecification
+}
+
+impl PactBuilder {
+  /// Creates a builder for a V3 pact between the consumer and provider
+  pub fn new(consumer: &str, provider: &str) -> Self {
+    PactBuilder {
+      consumer: consumer.to_string(),
+      provider: provider.to_string(),
+      interactions: vec![],
+      messages: vec![],
+      specification: PactSpecification::V3
+    }
+  }
+
+  /// Adds a request/response interaction
+  pub fn interaction(mut self, interaction: RequestResponseInteraction) -> Self {
+    self.interactions.push(interaction);
+    self
+  }
+
+  /// Adds a message interaction
+  pub fn message(mut self, message: Message) -> Self {
+    self.messages.push(message);
+    self
+  }
+
+  /// Sets the specification version of the pact. This is ignored when building a V4 pact.
+  pub fn specification(mut self, specification: PactSpecification) -> Self {
+    self.specification = specification;
+    self
+  }
+
+  /// Builds a request/response pact with the interactions
+  pub fn build(&self) -> RequestResponsePact {
+    RequestResponsePact {
+      consumer: Consumer { name: self.consumer.clone() },
+      provider: Provider { name: self.provider.clone() },
+      interactions: self.interactions.clone(),
+      specification_version: self.specification.clone(),
+      .. RequestResponsePact::default()
+    }
+  }
+
+  /// Builds a message pact with the messages
+  pub fn build_message_pact(&self) -> MessagePact {
+    MessagePact {
+      consumer: Consumer { name: self.consumer.clone() },
+      provider: Provider { name: self.provider.clone() },
+      messages: self.messages.clone(),
+      metadata: BTreeMap::new(),
+      specification_version: self.specification.clone()
+    }
+  }
+
+  /// Builds a V4 pact with both the interactions and the messages
+  pub fn build_v4(&self) -> V4Pact {
+    let interactions = self.interactions.iter().map(|i| i as &dyn Interaction)
+      .chain(self.messages.iter().map(|m| m as &dyn Interaction))
+      .filter_map(|i| i.as_v4())
+      .collect::<Vec<Box<dyn V4Interaction>>>();
+    V4Pact {
+      consumer: Consumer { name: self.consumer.clone() },
+      provider: Provider { name: self.provider.clone() },
+      interactions,
+      .. V4Pact::default()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+  use serde_json::json;
+
+  use pact_matching::models::{HttpPart, Pact};
+
+  use super::*;
+
+  #[test]
+  fn request_builder_test() {
+    let request = RequestBuilder::post("/users")
+      .query_param("a", "1")
+      .query_param("a", "2")
+      .header("X-Test", "true")
+      .json_body(json!({ "id": 100 }))
+      .matching_rule("body", "$.id", MatchingRule::Integer)
+      .build();
+    expect!(request.method).to(be_equal_to("POST"));
+    expect!(request.path).to(be_equal_to("/users"));
+    expect!(request.query).to(be_some().value(hashmap!{ "a".to_string() => vec!["1".to_string(), "2".to_string()] }));
+    expect!(request.headers).to(be_some().value(hashmap!{
+      "X-Test".to_string() => vec!["true".to_string()],
+      "Content-Type".to_string() => vec!["application/json".to_string()]
+    }));
+    expect!(request.body.str_value()).to(be_equal_to("{\"id\":100}"));
+    expect!(request.matching_rules.rules_for_category("body").unwrap().is_not_empty()).to(be_true());
+  }
+
+  #[test]
+  fn response_builder_test() {
+    let response = ResponseBuilder::ok().status(201).header("Content-Type", "text/plain").body("Created").build();
+    expect!(response.status).to(be_equal_to(201));
+    expect!(response.content_type().unwrap().to_string()).to(be_equal_to("text/plain"));
+    expect!(response.body.str_value()).to(be_equal_to("Created"));
+  }
+
+  #[test]
+  fn pact_builder_test() {
+    let builder = PactBuilder::new("consumer", "provider")
+      .interaction(InteractionBuilder::new("a request").given("a state").build())
+      .message(MessageBuilder::new("a message").json_contents(json!({ "a": 1 })).build());
+
+    let pact = builder.build();
+    expect!(pact.consumer.name).to(be_equal_to("consumer"));
+    expect!(pact.interactions.len()).to(be_equal_to(1));
+    expect!(pact.interactions[0].provider_states[0].name.clone()).to(be_equal_to("a state"));
+
+    let message_pact = builder.build_message_pact();
+    expect!(message_pact.messages.len()).to(be_equal_to(1));
+    expect!(message_pact.messages[0].metadata.get("contentType").cloned()).to(be_some().value("application/json"));
+
+    let v4 = builder.build_v4();
+    expect!(v4.interactions().iter().map(|i| i.type_of()).collect::<Vec<String>>()).to(be_equal_to(vec![
+      "V4 Synchronous/HTTP".to_string(),
+      "V4 Asynchronous/Messages".to_string()
+    ]));
+  }
+}
@@ -0,0 +1,262 @@
+//! Canned fixture pacts for each of the specification versions. All the request/response fixtures
+//! have the same interaction (a request for the user Mallory) between `test_consumer` and
+//! `test_provider`, so tests can check that they get the same result for each version.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use pact_matching::models::{load_pact_from_json, Pact};
+use pact_models::PactSpecification;
+
+/// Name of the consumer in the fixture pacts
+pub const CONSUMER: &str = "test_consumer";
+/// Name of the provider in the fixture pacts
+pub const PROVIDER: &str = "test_provider";
+/// Description of the interaction in the fixture pacts
+pub const INTERACTION: &str = "a retrieve Mallory request";
+/// Provider state of the interaction in the fixture pacts
+pub const PROVIDER_STATE: &str = "there is a user Mallory";
+/// Description of the message in the fixture message pacts
+pub const MESSAGE: &str = "a user created message";
+
+fn metadata(version: &str) -> Value {
+  json!({ "pactSpecification": { "version": version } })
+}
+
+/// Returns the JSON for the request/response fixture pact in the format of the specification
+/// version. `Unknown` returns the V3 fixture.
+pub fn pact_json(spec: PactSpecification) -> Value {
+  match spec {
+    PactSpecification::V1 | PactSpecification::V1_1 => json!({
+      "consumer": { "name": CONSUMER },
+      "provider": { "name": PROVIDER },
+      "interactions": [
+        {
+          "description": INTERACTION,
+          "providerState": PROVIDER_STATE,
+          "request": {
+            "method": "GET",
+            "path": "/mallory",
+            "query": "name=ron&status=good",
+            "headers": { "Accept": "application/json" }
+          },
+          "response": {
+            "status": 200,
+            "headers": { "Content-Type": "application/json" },
+            "body": { "id": 100, "name": "Mallory" }
+          }
+        }
+      ],
+      "metadata": metadata(&spec.version_str())
+    }),
+    PactSpecification::V2 => json!({
+      "consumer": { "name": CONSUMER },
+      "provider": { "name": PROVIDER },
+      "interactions": [
+        {
+          "description": INTERACTION,
+          "providerState": PROVIDER_STATE,
+          "request": {
+            "method": "GET",
+            "path": "/mallory",
+            "query": "name=ron&status=good",
+            "headers": { "Accept": "application/json" }
+          },
+          "response": {
+            "status": 200,
+            "headers": { "Content-Type": "application/json" },
+            "body": { "id": 100, "name": "Mallory" },
+            "matchingRules": {
+              "$.body.id": { "match": "type" }
+            }
+          }
+        }
+      ],
+      "metadata": metadata("2.0.0")
+    }),
+    PactSpecification::V4 => json!({
+      "consumer": { "name": CONSUMER },
+      "provider": { "name": PROVIDER },
+      "interactions": [
+        {
+          "type": "Synchronous/HTTP",
+          "key": "001",
+          "description": INTERACTION,
+          "providerStates": [ { "name": PROVIDER_STATE } ],
+          "request": {
+            "method": "GET",
+            "path": "/mallory",
+            "query": { "name": ["ron"], "status": ["good"] },
+            "headers": { "Accept": ["application/json"] }
+          },
+          "response": {
+            "status": 200,
+            "headers": { "Content-Type": ["application/json"] },
+            "body": {
+              "contentType": "application/json",
+              "encoded": false,
+              "content": { "id": 100, "name": "Mallory" }
+            },
+            "matchingRules": {
+              "body": {
+                "$.id": { "combine": "AND", "matchers": [ { "match": "type" } ] }
+              }
+            }
+          }
+        }
+      ],
+      "metadata": metadata("4.0")
+    }),
+    _ => json!({
+      "consumer": { "name": CONSUMER },
+      "provider": { "name": PROVIDER },
+      "interactions": [
+        {
+          "description": INTERACTION,
+          "providerStates": [ { "name": PROVIDER_STATE } ],
+          "request": {
+            "method": "GET",
+            "path": "/mallory",
+            "query": { "name": ["ron"], "status": ["good"] },
+            "headers": { "Accept": "application/json" }
+          },
+          "response": {
+            "status": 200,
+            "headers": { "Content-Type": "application/json" },
+            "body": { "id": 100, "name": "Mallory" },
+            "matchingRules": {
+              "body": {
+                "$.id": { "combine": "AND", "matchers": [ { "match": "type" } ] }
+              }
+            }
+          }
+        }
+      ],
+      "metadata": metadata("3.0.0")
+    })
+  }
+}
+
+/// Returns the JSON for the message fixture pact. Only V3 and V4 support messages, so any other
+/// version returns the V3 fixture.
+pub fn message_pact_json(spec: PactSpecification) -> Value {
+  match spec {
+    PactSpecification::V4 => json!({
+      "consumer": { "name": CONSUMER },
+      "provider": { "name": PROVIDER },
+      "interactions": [
+        {
+          "type": "Asynchronous/Messages",
+          "key": "m_001",
+          "description": MESSAGE,
+          "providerStates": [ { "name": PROVIDER_STATE } ],
+          "metadata": { "contentType": "application/json" },
+          "contents": {
+            "contentType": "application/json",
+            "encoded": false,
+            "content": { "id": 100, "name": "Mallory" }
+          },
+          "matchingRules": {
+            "body": {
+              "$.id": { "combine": "AND", "matchers": [ { "match": "type" } ] }
+            }
+          }
+        }
+      ],
+      "metadata": metadata("4.0")
+    }),
+    _ => json!({
+      "consumer": { "name": CONSUMER },
+      "provider": { "name": PROVIDER },
+      "messages": [
+        {
+          "description": MESSAGE,
+          "providerStates": [ { "name": PROVIDER_STATE } ],
+          "metaData": { "contentType": "application/json" },
+          "contents": { "id": 100, "name": "Mallory" },
+          "matchingRules": {
+            "body": {
+              "$.id": { "combine": "AND", "matchers": [ { "match": "type" } ] }
+            }
+          }
+        }
+      ],
+      "metadata": metadata("3.0.0")
+    })
+  }
+}
+
+/// Loads the request/response fixture pact for the specification version
+pub fn pact(spec: PactSpecification) -> Box<dyn Pact> {
+  load_pact_from_json("fixture", &pact_json(spec)).unwrap()
+}
+
+/// Loads the message fixture pact for the specification version
+pub fn message_pact(spec: PactSpecification) -> Box<dyn Pact> {
+  load_pact_from_json("fixture", &message_pact_json(spec)).unwrap()
+}
+
+/// Writes the request/response fixture pact for the specification version to a file in the
+/// directory, and returns the path to the file. The file name includes the version, so fixtures
+/// for different versions can be written to the same directory.
+pub fn write_pact_file(dir: &Path, spec: PactSpecification) -> io::Result<PathBuf> {
+  let path = dir.join(format!("{}-{}-v{}.json", CONSUMER, PROVIDER, spec.version_str()));
+  fs::create_dir_all(dir)?;
+  fs::write(&path, serde_json::to_string_pretty(&pact_json(spec))?)?;
+  Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_matching::models::read_pact;
+
+  use super::*;
+
+  fn check_pact(pact: &dyn Pact) {
+    expect!(pact.consumer().name).to(be_equal_to(CONSUMER));
+    expect!(pact.provider().name).to(be_equal_to(PROVIDER));
+    let interactions = pact.interactions();
+    expect!(interactions.len()).to(be_equal_to(1));
+    let interaction = interactions[0].as_request_response().unwrap();
+    expect!(interaction.description).to(be_equal_to(INTERACTION));
+    expect!(interaction.provider_states[0].name.clone()).to(be_equal_to(PROVIDER_STATE));
+    expect!(interaction.request.path).to(be_equal_to("/mallory"));
+    expect!(interaction.request.query.unwrap().len()).to(be_equal_to(2));
+    expect!(interaction.response.body.str_value()).to(be_equal_to("{\"id\":100,\"name\":\"Mallory\"}"));
+  }
+
+  #[test]
+  fn pact_fixtures_load_for_all_the_specification_versions() {
+    for spec in &[PactSpecification::V1, PactSpecification::V1_1, PactSpecification::V2, PactSpecification::V3,
+      PactSpecification::V4] {
+      check_pact(pact(spec.clone()).as_ref());
+    }
+  }
+
+  #[test]
+  fn message_pact_fixtures_load() {
+    for spec in &[PactSpecification::V3, PactSpecification::V4] {
+      let pact = message_pact(spec.clone());
+      let interactions = pact.interactions();
+      expect!(interactions.len()).to(be_equal_to(1));
+      expect!(interactions[0].description()).to(be_equal_to(MESSAGE));
+      expect!(interactions[0].contents().str_value()).to(be_equal_to("{\"id\":100,\"name\":\"Mallory\"}"));
+    }
+  }
+
+  #[test]
+  fn write_pact_file_test() {
+    let dir = std::env::temp_dir().join("pact_test_support_write_pact_file_test");
+    let path = write_pact_file(&dir, PactSpecification::V2).unwrap();
+    let pact = read_pact(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap_or_default();
+
+    expect!(path.file_name().unwrap().to_str()).to(be_some().value("test_consumer-test_provider-v2.0.0.json"));
+    check_pact(pact.as_ref());
+  }
+}
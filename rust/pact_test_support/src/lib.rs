@@ -0,0 +1,30 @@
+//! The `pact_test_support` crate provides builders for the Pact models and canned fixture pacts,
+//! so tests do not have to construct large JSON literals or model structs by hand.
+//!
+//! ```
+//! use pact_test_support::builders::*;
+//!
+//! let pact = PactBuilder::new("consumer", "provider")
+//!   .interaction(InteractionBuilder::new("a request for Mallory")
+//!     .given("there is a user Mallory")
+//!     .request(RequestBuilder::get("/mallory").header("Accept", "application/json").build())
+//!     .response(ResponseBuilder::ok().json_body(serde_json::json!({ "name": "Mallory" })).build())
+//!     .build())
+//!   .build();
+//! assert_eq!(pact.interactions.len(), 1);
+//! ```
+//!
+//! The `fixtures` module has the same pact in the format of each of the specification versions.
+//!
+//! ```
+//! use pact_models::PactSpecification;
+//! use pact_test_support::fixtures;
+//!
+//! let pact = fixtures::pact(PactSpecification::V2);
+//! assert_eq!(pact.interactions().len(), 1);
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod builders;
+pub mod fixtures;
@@ -1,6 +1,8 @@
-//! Executor abstraction for executing callbacks to user code (request filters, provider state change callbacks)
+//! Executor abstraction for executing callbacks to user code (request filters, provider state change callbacks,
+//! message providers)
 
 use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 
 use ansi_term::Colour::Yellow;
@@ -8,6 +10,7 @@ use async_trait::async_trait;
 use maplit::*;
 use serde_json::{json, Value};
 
+use pact_matching::models::message::Message;
 use pact_matching::models::provider_states::ProviderState;
 use pact_matching::models::Request;
 use pact_models::OptionalBody;
@@ -36,6 +39,27 @@ impl RequestFilterExecutor for NullRequestFilterExecutor {
   }
 }
 
+/// Trait for executors that produce the actual message for a message interaction, instead of the
+/// message being fetched from the provider with an HTTP request
+pub trait MessageProviderExecutor: Send + Sync {
+  /// Produces the message (contents and metadata) for the interaction with the given description.
+  /// The provider states of the interaction will have already been set up.
+  fn call(self: Arc<Self>, description: &str, provider_states: &[ProviderState]) -> anyhow::Result<Message>;
+}
+
+impl <F> MessageProviderExecutor for F
+  where F: Fn(&str, &[ProviderState]) -> anyhow::Result<Message> + Send + Sync {
+  fn call(self: Arc<Self>, description: &str, provider_states: &[ProviderState]) -> anyhow::Result<Message> {
+    self(description, provider_states)
+  }
+}
+
+impl Debug for dyn MessageProviderExecutor {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "MessageProviderExecutor")
+  }
+}
+
 /// Struct for returning errors from executing a provider state
 #[derive(Debug, Clone)]
 pub struct ProviderStateError {
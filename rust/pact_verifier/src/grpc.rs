@@ -0,0 +1,226 @@
+//! Verification of synchronous message interactions against gRPC providers
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ansi_term::Colour::*;
+use bytes::{BufMut, Bytes, BytesMut};
+use log::*;
+use serde_json::{json, Value};
+
+use pact_matching::{generate_message_contents, match_sync_message_response};
+use pact_matching::models::generators::GeneratorTestMode;
+use pact_matching::models::json_utils::json_to_string;
+use pact_matching::models::v4::message_parts::MessageContents;
+use pact_matching::models::v4::SynchronousMessages;
+use pact_matching::models::Interaction;
+use pact_matching::plugins::{ContentPlugin, with_plugins};
+use pact_matching::protobuf::{GRPC_CONTENT_TYPE, ProtobufPlugin, ProtoFile};
+use pact_models::OptionalBody;
+
+use crate::{MismatchResult, ProviderInfo, with_pact_plugins};
+
+/// Service and method of the gRPC provider to invoke, from the `grpc` plugin configuration of
+/// the interaction
+#[derive(Debug, Clone, PartialEq)]
+struct GrpcConfig {
+  service: String,
+  method: String,
+  proto: Option<String>
+}
+
+impl GrpcConfig {
+  fn from_interaction(interaction: &SynchronousMessages) -> Option<GrpcConfig> {
+    let config = interaction.plugin_config.get("grpc")?;
+    Some(GrpcConfig {
+      service: config.get("service").map(json_to_string)?,
+      method: config.get("method").map(json_to_string)?,
+      proto: config.get("proto").map(json_to_string)
+    })
+  }
+}
+
+/// Frames the message with the gRPC length prefix
+fn frame_message(message: &[u8]) -> Bytes {
+  let mut buffer = BytesMut::with_capacity(message.len() + 5);
+  buffer.put_u8(0);
+  buffer.put_u32(message.len() as u32);
+  buffer.put_slice(message);
+  buffer.freeze()
+}
+
+/// Splits the body of a gRPC response into the length prefixed messages
+fn unframe_messages(body: &[u8]) -> anyhow::Result<Vec<Bytes>> {
+  let mut messages = vec![];
+  let mut remaining = body;
+  while !remaining.is_empty() {
+    if remaining.len() < 5 {
+      return Err(anyhow::anyhow!("gRPC response has an incomplete message prefix"));
+    }
+    if remaining[0] != 0 {
+      return Err(anyhow::anyhow!("Compressed gRPC messages are not supported"));
+    }
+    let len = u32::from_be_bytes([remaining[1], remaining[2], remaining[3], remaining[4]]) as usize;
+    if remaining.len() < len + 5 {
+      return Err(anyhow::anyhow!("gRPC response has an incomplete message"));
+    }
+    messages.push(Bytes::copy_from_slice(&remaining[5..len + 5]));
+    remaining = &remaining[len + 5..];
+  }
+  Ok(messages)
+}
+
+/// Verifies a synchronous message interaction by invoking the gRPC method of the provider with
+/// the request message, and matching the response messages against the expected ones.
+///
+/// The interaction must have a `grpc` plugin configuration with the `service` and `method` to
+/// invoke. If it also has the source of the `.proto` file as `proto`, a protobuf plugin is
+/// created with it so the messages can be matched field by field. Otherwise the descriptors from
+/// the pact metadata or a registered plugin are used. The `grpc-status` and `grpc-message` of the response are only available
+/// as metadata if the provider returns them in the response headers.
+pub async fn verify_sync_message_from_provider(
+  provider: &ProviderInfo,
+  interaction: &SynchronousMessages,
+  disable_ssl_verification: bool,
+  context: &HashMap<&str, Value>,
+  timeout: Option<Duration>
+) -> Result<Option<String>, MismatchResult> {
+  let config = GrpcConfig::from_interaction(interaction)
+    .ok_or_else(|| MismatchResult::Error(
+      "Synchronous message interactions can only be verified against gRPC providers, and the interaction does not have a gRPC service and method configured".to_string(),
+      interaction.id.clone()))?;
+  let mut plugins: Vec<Arc<dyn ContentPlugin>> = vec![];
+  if let Some(proto) = &config.proto {
+    let proto = ProtoFile::parse(proto)
+      .map_err(|err| MismatchResult::Error(format!("Failed to parse the proto file of the interaction: {}", err),
+        interaction.id.clone()))?;
+    plugins.push(Arc::new(ProtobufPlugin::new(proto)));
+  }
+
+  let request = with_pact_plugins(|| with_plugins(&plugins, ||
+    generate_message_contents(&interaction.request, &GeneratorTestMode::Provider, context)));
+
+  let base_url = match provider.port {
+    Some(port) => format!("{}://{}:{}", provider.protocol, provider.host, port),
+    None => format!("{}://{}", provider.protocol, provider.host),
+  };
+  let url = format!("{}/{}/{}", base_url, config.service, config.method);
+  info!("Invoking gRPC method at {}", url);
+
+  let client = reqwest::Client::builder()
+    .http2_prior_knowledge()
+    .danger_accept_invalid_certs(disable_ssl_verification)
+    .build()
+    .map_err(|err| MismatchResult::Error(err.to_string(), interaction.id.clone()))?;
+  let mut builder = client.post(&url)
+    .header("content-type", GRPC_CONTENT_TYPE)
+    .header("te", "trailers")
+    .body(frame_message(&request.contents.value().unwrap_or_default()));
+  for (key, value) in &request.metadata {
+    if !key.eq_ignore_ascii_case("contenttype") && !key.eq_ignore_ascii_case("content-type") {
+      builder = builder.header(key.as_str(), json_to_string(value));
+    }
+  }
+  if let Some(timeout) = timeout {
+    builder = builder.timeout(timeout);
+  }
+
+  let response = builder.send().await
+    .map_err(|err| MismatchResult::Error(format!("gRPC request failed - {}", err), interaction.id.clone()))?;
+  let metadata: HashMap<String, Value> = response.headers().iter()
+    .filter(|(name, _)| name.as_str().starts_with("grpc-"))
+    .map(|(name, value)| (name.to_string(), json!(value.to_str().unwrap_or_default())))
+    .collect();
+  let body = response.bytes().await
+    .map_err(|err| MismatchResult::Error(format!("Failed to read the gRPC response - {}", err), interaction.id.clone()))?;
+  let messages = unframe_messages(&body)
+    .map_err(|err| MismatchResult::Error(err.to_string(), interaction.id.clone()))?;
+  debug!("Received {} response messages with metadata {:?}", messages.len(), metadata);
+
+  let actual_responses: Vec<MessageContents> = messages.iter().enumerate()
+    .map(|(index, message)| {
+      let content_type = interaction.response.get(index)
+        .and_then(|expected| expected.content_type());
+      MessageContents {
+        contents: OptionalBody::Present(message.clone(), content_type),
+        metadata: metadata.clone(),
+        .. MessageContents::default()
+      }
+    })
+    .collect();
+
+  let mismatches = with_pact_plugins(|| with_plugins(&plugins, ||
+    match_sync_message_response(interaction, &actual_responses)));
+  if mismatches.is_empty() {
+    Ok(interaction.id.clone())
+  } else {
+    Err(MismatchResult::Mismatches {
+      mismatches,
+      expected: interaction.boxed(),
+      actual: SynchronousMessages { response: actual_responses, .. SynchronousMessages::default() }.boxed(),
+      interaction_id: interaction.id.clone()
+    })
+  }
+}
+
+pub fn display_sync_message_result(
+  errors: &mut Vec<(Option<String>, String, Option<MismatchResult>)>,
+  interaction: &SynchronousMessages,
+  match_result: &Result<Option<String>, MismatchResult>,
+  description: &str
+) {
+  println!("    returns response messages which");
+  match match_result {
+    Ok(id) => {
+      println!("      have matching contents ({})", Green.paint("OK"));
+      errors.push((id.clone(), description.to_string(), None));
+    },
+    Err(ref err) => match *err {
+      MismatchResult::Error(ref err_des, _) => {
+        println!("      {}", Red.paint(format!("Request Failed - {}", err_des)));
+        errors.push((err.interaction_id(), description.to_string(), Some(err.clone())));
+      },
+      MismatchResult::Mismatches { .. } => {
+        println!("      have matching contents ({})", Red.paint("FAILED"));
+        errors.push((interaction.id.clone(), description.to_string(), Some(err.clone())));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+
+  use super::*;
+
+  #[test]
+  fn frames_and_unframes_messages() {
+    let framed = frame_message(&[1, 2, 3]);
+    expect!(framed.to_vec()).to(be_equal_to(vec![0, 0, 0, 0, 3, 1, 2, 3]));
+
+    let mut body = framed.to_vec();
+    body.extend(frame_message(&[]).to_vec());
+    expect!(unframe_messages(&body).unwrap()).to(be_equal_to(vec![Bytes::from(vec![1, 2, 3]), Bytes::new()]));
+    expect!(unframe_messages(&[0, 0, 0, 0, 3, 1])).to(be_err());
+    expect!(unframe_messages(&[1, 0, 0, 0, 0])).to(be_err());
+  }
+
+  #[test]
+  fn grpc_config_requires_the_service_and_method() {
+    let interaction = SynchronousMessages {
+      plugin_config: hashmap!{
+        "grpc".to_string() => json!({ "service": "routeguide.RouteGuide", "method": "GetFeature" })
+      },
+      .. SynchronousMessages::default()
+    };
+    expect!(GrpcConfig::from_interaction(&interaction)).to(be_some().value(GrpcConfig {
+      service: "routeguide.RouteGuide".to_string(),
+      method: "GetFeature".to_string(),
+      proto: None
+    }));
+    expect!(GrpcConfig::from_interaction(&SynchronousMessages::default())).to(be_none());
+  }
+}
@@ -0,0 +1,166 @@
+//! Support for verifying message interactions against records produced for Kafka topics. The
+//! record topic, key, partition and headers are mapped onto the message metadata using the
+//! conventions in `pact_matching::models::kafka`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use pact_matching::models::kafka::KafkaMetadata;
+use pact_matching::models::message::Message;
+use pact_matching::models::provider_states::ProviderState;
+use pact_models::content_types::ContentType;
+use pact_models::OptionalBody;
+
+use crate::callback_executors::MessageProviderExecutor;
+
+/// Record produced by a provider for a Kafka topic
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KafkaRecord {
+  /// Topic the record is published to
+  pub topic: String,
+  /// Partition the record is published to
+  pub partition: Option<i32>,
+  /// Key of the record
+  pub key: Option<String>,
+  /// Value of the record
+  pub value: Option<Bytes>,
+  /// Headers of the record
+  pub headers: HashMap<String, String>,
+  /// Content type of the value. If not set, the `content-type` header of the record is used
+  pub content_type: Option<String>
+}
+
+impl KafkaRecord {
+  /// Creates a record for the topic with the given value
+  pub fn new<B: Into<Bytes>>(topic: &str, value: B) -> Self {
+    KafkaRecord {
+      topic: topic.to_string(),
+      value: Some(value.into()),
+      .. KafkaRecord::default()
+    }
+  }
+
+  fn value_content_type(&self) -> Option<ContentType> {
+    self.content_type.clone()
+      .or_else(|| self.headers.iter()
+        .find(|(k, _)| k.to_lowercase() == "content-type" || k.to_lowercase() == "contenttype")
+        .map(|(_, v)| v.clone()))
+      .and_then(|ct| ContentType::parse(ct.as_str()).ok())
+  }
+
+  /// Converts this record into a message that can be verified against a message interaction
+  pub fn to_message(&self, description: &str, provider_states: &[ProviderState]) -> Message {
+    let content_type = self.value_content_type();
+    let mut message = Message {
+      description: description.to_string(),
+      provider_states: provider_states.to_vec(),
+      contents: match &self.value {
+        Some(value) if value.is_empty() => OptionalBody::Empty,
+        Some(value) => OptionalBody::Present(value.clone(), content_type.clone()),
+        None => OptionalBody::Null
+      },
+      .. Message::default()
+    };
+    if let Some(content_type) = content_type {
+      message.metadata.insert("contentType".to_string(), content_type.to_string());
+    }
+    message.set_kafka_topic(&self.topic);
+    if let Some(key) = &self.key {
+      message.set_kafka_key(key);
+    }
+    if let Some(partition) = self.partition {
+      message.set_kafka_partition(partition);
+    }
+    for (name, value) in &self.headers {
+      message.set_kafka_header(name, value);
+    }
+    message
+  }
+}
+
+/// Message provider that produces the actual message from the Kafka record returned by the
+/// producer callback. The producer is called with the description and provider states of the
+/// interaction being verified.
+pub struct KafkaMessageProvider<F> {
+  producer: F
+}
+
+impl <F> KafkaMessageProvider<F>
+  where F: Fn(&str, &[ProviderState]) -> anyhow::Result<KafkaRecord> + Send + Sync + 'static {
+  /// Creates a message provider from the Kafka record producer callback
+  pub fn new(producer: F) -> Self {
+    KafkaMessageProvider { producer }
+  }
+
+  /// Wraps this provider for use as the `message_provider` of the verification options
+  pub fn into_executor(self) -> Arc<dyn MessageProviderExecutor> {
+    Arc::new(self)
+  }
+}
+
+impl <F> MessageProviderExecutor for KafkaMessageProvider<F>
+  where F: Fn(&str, &[ProviderState]) -> anyhow::Result<KafkaRecord> + Send + Sync {
+  fn call(self: Arc<Self>, description: &str, provider_states: &[ProviderState]) -> anyhow::Result<Message> {
+    let record = (self.producer)(description, provider_states)?;
+    Ok(record.to_message(description, provider_states))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use expectest::prelude::*;
+  use maplit::*;
+
+  use pact_matching::match_message;
+  use pact_matching::models::Interaction;
+  use pact_matching::models::kafka::KafkaMetadata;
+  use pact_matching::models::v4::AsynchronousMessage;
+
+  use super::*;
+
+  #[test]
+  fn to_message_maps_the_record_onto_the_message_metadata() {
+    let record = KafkaRecord {
+      partition: Some(2),
+      key: Some("order-1".to_string()),
+      headers: hashmap!{ "content-type".to_string() => "application/json".to_string() },
+      .. KafkaRecord::new("orders", r#"{"id": 1}"#)
+    };
+    let message = record.to_message("an order", &[]);
+
+    expect!(message.description.as_str()).to(be_equal_to("an order"));
+    expect!(message.kafka_topic()).to(be_some().value("orders"));
+    expect!(message.kafka_key()).to(be_some().value("order-1"));
+    expect!(message.kafka_partition()).to(be_some().value(2));
+    expect!(message.kafka_headers().get("content-type").cloned()).to(be_some().value("application/json"));
+    expect!(message.metadata.get("contentType").cloned()).to(be_some().value("application/json"));
+    expect!(message.contents.str_value()).to(be_equal_to(r#"{"id": 1}"#));
+  }
+
+  #[test]
+  fn kafka_message_provider_produces_a_message_that_can_be_verified() {
+    let mut expected = AsynchronousMessage {
+      contents: OptionalBody::Present(r#"{"id": 1}"#.into(), Some("application/json".into())),
+      .. AsynchronousMessage::default()
+    };
+    expected.set_kafka_topic("orders");
+    expected.set_kafka_partition(2);
+
+    let provider = KafkaMessageProvider::new(|_: &str, _: &[ProviderState]| Ok(KafkaRecord {
+      partition: Some(2),
+      content_type: Some("application/json".to_string()),
+      .. KafkaRecord::new("orders", r#"{"id": 1}"#)
+    })).into_executor();
+    let actual = provider.clone().call("an order", &[]).unwrap();
+    expect!(match_message(&expected.boxed(), &actual.boxed()).iter()).to(be_empty());
+
+    let provider = Arc::new(KafkaMessageProvider::new(|_: &str, _: &[ProviderState]|
+      Ok(KafkaRecord::new("invoices", r#"{"id": 1}"#))));
+    let actual = provider.call("an order", &[]).unwrap();
+    expect!(match_message(&expected.boxed(), &actual.boxed()).iter()).to_not(be_empty());
+  }
+}
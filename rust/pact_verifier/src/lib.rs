@@ -3,12 +3,12 @@
 //! and V4 Pact specification (https://github.com/pact-foundation/pact-specification/tree/version-4).
 #![warn(missing_docs)]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::fmt;
-use std::fs;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use ansi_term::*;
 use ansi_term::Colour::*;
@@ -18,30 +18,40 @@ use itertools::Itertools;
 use log::*;
 use maplit::*;
 use regex::Regex;
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
 pub use callback_executors::NullRequestFilterExecutor;
 use callback_executors::RequestFilterExecutor;
 use pact_matching::*;
 use pact_matching::models::*;
 use pact_matching::models::generators::GeneratorTestMode;
-use pact_matching::models::http_utils::HttpAuth;
+use pact_matching::models::http_utils::{HttpAuth, TlsConfig};
 use pact_matching::models::json_utils::json_to_string;
+use pact_matching::models::pact_files::load_pacts_from_dir;
 use pact_matching::models::provider_states::*;
+use pact_matching::plugins::{ContentPlugin, with_plugins};
 
-use crate::callback_executors::{ProviderStateError, ProviderStateExecutor};
-use crate::messages::{display_message_result, verify_message_from_provider};
+use crate::callback_executors::{MessageProviderExecutor, ProviderStateError, ProviderStateExecutor};
+use crate::grpc::display_sync_message_result;
+use crate::messages::display_message_result;
 use crate::pact_broker::{Link, PactVerificationContext, publish_verification_results, TestResult};
 pub use crate::pact_broker::{ConsumerVersionSelector, PactsForVerificationRequest};
-use crate::provider_client::{make_provider_request, provider_client_error_to_string};
+use crate::provider_client::{make_provider_request, provider_client_error_to_string, ProviderClientError};
+use crate::transport::{default_transports, transport_for};
 use crate::request_response::display_request_response_result;
-use pact_matching::models::v4::V4Interaction;
+use pact_matching::models::v4::{V4Interaction, V4InteractionType};
 
 mod provider_client;
 pub mod pact_broker;
 pub mod callback_executors;
+pub mod kafka;
 mod request_response;
 mod messages;
+mod grpc;
+mod websocket;
+mod transport;
+mod pact_sources;
 
 /// Source for loading pacts
 #[derive(Debug, Clone)]
@@ -52,8 +62,13 @@ pub enum PactSource {
     File(String),
     /// Load all the pacts from a Directory
     Dir(String),
+    /// Load all the pact files that match a glob pattern (i.e. `pacts/**/provider-*.json`)
+    Glob(String),
     /// Load the pact from a URL
     URL(String, Option<HttpAuth>),
+    /// Load all the pact files in a remote listing (an HTML directory index page, or an S3 bucket
+    /// listing) at a URL
+    ListingUrl(String, Option<HttpAuth>),
     /// Load all pacts with the provider name from the pact broker url
     BrokerUrl(String, String, Option<HttpAuth>, Vec<Link>),
     /// Load pacts with the newer pacts for verification API
@@ -82,7 +97,9 @@ impl Display for PactSource {
     match *self {
       PactSource::File(ref file) => write!(f, "File({})", file),
       PactSource::Dir(ref dir) => write!(f, "Dir({})", dir),
+      PactSource::Glob(ref pattern) => write!(f, "Glob({})", pattern),
       PactSource::URL(ref url, _) => write!(f, "URL({})", url),
+      PactSource::ListingUrl(ref url, _) => write!(f, "ListingUrl({})", url),
       PactSource::BrokerUrl(ref provider_name, ref broker_url, _, _) => {
           write!(f, "PactBroker({}, provider_name='{}')", broker_url, provider_name)
       }
@@ -212,6 +229,13 @@ impl Clone for MismatchResult {
             actual: Box::new(actual.as_message().unwrap().clone()),
             interaction_id: interaction_id.clone()
           }
+        } else if let Some(ref expected_message) = expected.as_v4_sync_message() {
+          MismatchResult::Mismatches {
+            mismatches: mismatches.clone(),
+            expected: Box::new(expected_message.clone()),
+            actual: Box::new(actual.as_v4_sync_message().unwrap()),
+            interaction_id: interaction_id.clone()
+          }
         } else {
           panic!("Cannot clone this MismatchResult::Mismatches as the expected and actual values are an unknown type")
         }
@@ -223,17 +247,75 @@ impl Clone for MismatchResult {
   }
 }
 
+/// Summary of the result of verifying a provider. This can be serialised to JSON for other tools
+/// (i.e. dashboards or IDE plugins) to consume, in the form
+/// `{ "result": bool, "failures": [..], "pendingFailures": [..], "skipped": number }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationSummary {
+  /// If the verification was successful. Failures of pending pacts or interactions do not fail
+  /// the verification.
+  pub result: bool,
+  /// Failures that failed the verification
+  pub failures: Vec<VerificationFailure>,
+  /// Failures of pending pacts or interactions
+  pub pending_failures: Vec<VerificationFailure>,
+  /// Number of interactions that were not verified as the maximum number of failures was reached
+  pub skipped: usize
+}
+
+/// A failure from verifying an interaction or loading a pact. Mismatches are serialised in the
+/// same form as the mock server mismatches (see `Mismatch::to_json`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationFailure {
+  /// Description of the interaction (including the consumer, provider and provider states)
+  pub description: String,
+  /// Interaction ID if fetched from a pact broker
+  pub interaction_id: Option<String>,
+  /// Error that occurred, if the interaction could not be verified
+  pub error: Option<String>,
+  /// Mismatches between the expected and actual response or message
+  pub mismatches: Vec<Mismatch>,
+  /// Comments from the interaction to display with the failure
+  pub comments: Vec<String>
+}
+
+impl VerificationFailure {
+  fn new(description: &str, result: &MismatchResult, comments: &[String]) -> Self {
+    let (error, mismatches) = match result {
+      MismatchResult::Mismatches { mismatches, .. } => (None, mismatches.clone()),
+      MismatchResult::Error(error, _) => (Some(error.clone()), vec![])
+    };
+    VerificationFailure {
+      description: description.to_string(),
+      interaction_id: result.interaction_id(),
+      error,
+      mismatches,
+      comments: comments.to_vec()
+    }
+  }
+}
+
 async fn verify_response_from_provider<F: RequestFilterExecutor>(
   provider: &ProviderInfo,
   interaction: &RequestResponseInteraction,
   options: &VerificationOptions<F>,
   client: &reqwest::Client,
-  verification_context: &HashMap<&str, Value>
+  verification_context: &HashMap<&str, Value>,
+  timeout: Option<Duration>
 ) -> Result<Option<String>, MismatchResult> {
   let expected_response = &interaction.response;
-  match make_provider_request(provider, &pact_matching::generate_request(&interaction.request, &GeneratorTestMode::Provider, &verification_context), options, client).await {
+  let request = with_pact_plugins(||
+    pact_matching::generate_request(&interaction.request, &GeneratorTestMode::Provider, verification_context));
+  match make_provider_request(provider, &request, options, client, timeout).await {
+    Ok(ref actual_response) if actual_response.status == 503 && expected_response.status != 503 => {
+      Err(MismatchResult::Error(provider_client_error_to_string(ProviderClientError::ProviderUnavailable(
+        "The provider responded with a 503 (Service Unavailable) status".to_string())), interaction.id.clone()))
+    },
     Ok(ref actual_response) => {
-      let mismatches = match_response(expected_response.clone(), actual_response.clone());
+      let mismatches = with_pact_plugins(|| match_response_with_body_config(expected_response.clone(),
+        actual_response.clone(), &locale::LocaleOptions::default(), &options.body_config_for(&interaction.description)));
       if mismatches.is_empty() {
         Ok(interaction.id.clone())
       } else {
@@ -272,10 +354,11 @@ async fn verify_interaction<F: RequestFilterExecutor, S: ProviderStateExecutor>(
   options: &VerificationOptions<F>,
   provider_state_executor: &Arc<S>
 ) -> Result<Option<String>, MismatchResult> {
-  let client = Arc::new(reqwest::Client::builder()
-                .danger_accept_invalid_certs(options.disable_ssl_verification)
-                .build()
-                .unwrap_or(reqwest::Client::new()));
+  let client = options.tls.configure_client(reqwest::Client::builder()
+      .danger_accept_invalid_certs(options.disable_ssl_verification))
+    .and_then(|builder| builder.build().map_err(|err| anyhow::anyhow!(err)))
+    .map(Arc::new)
+    .map_err(|err| MismatchResult::Error(format!("Invalid TLS configuration - {}", err), interaction.id()))?;
 
   let mut provider_states_results = hashmap!{};
   let sc_results = futures::stream::iter(
@@ -293,6 +376,9 @@ async fn verify_interaction<F: RequestFilterExecutor, S: ProviderStateExecutor>(
       }
     }).collect::<Vec<Result<HashMap<String, Value>, MismatchResult>>>().await;
   if sc_results.iter().any(|result| result.is_err()) {
+    // Some of the state change handlers may have succeeded, so make sure the provider states are
+    // still torn down before giving up on this interaction
+    let _ = execute_state_change_teardown(interaction, &client, provider_state_executor).await;
     return Err(MismatchResult::Error("One or more of the state change handlers has failed".to_string(), interaction.id()))
   } else {
     for result in sc_results {
@@ -304,20 +390,30 @@ async fn verify_interaction<F: RequestFilterExecutor, S: ProviderStateExecutor>(
     }
   };
 
+  if let Some(seed) = options.generator_seed {
+    provider_states_results.insert("generatorSeed".to_string(), json!(seed));
+  }
+
   info!("Running provider verification for '{}'", interaction.description());
+  let transports = default_transports::<F>();
   let result = futures::future::ready((provider_states_results.iter()
-    .map(|(k, v)| (k.as_str(), v.clone())).collect(), client.clone()))
+    .map(|(k, v)| (k.as_str(), v.clone())).collect::<HashMap<&str, Value>>(), client.clone()))
     .then(|(context, client)| async move {
-    let mut result = Err(MismatchResult::Error("No interaction was verified".into(), interaction.id().clone()));
-    if let Some(interaction) = interaction.as_request_response() {
-      result = verify_response_from_provider(provider, &interaction, options, &client, &context).await;
-    }
-    if interaction.is_message() {
-      result = verify_message_from_provider(provider, &interaction.boxed(), options, &client, &context).await;
-    }
-    result
+    let transport = transport_for(&transports, interaction)?;
+    debug!("Verifying '{}' with the {} transport", interaction.description(), transport.name());
+    transport.verify(provider, interaction, options, &client, &context).await
   }).await;
 
+  execute_state_change_teardown(interaction, &client, provider_state_executor).await?;
+
+  result
+}
+
+async fn execute_state_change_teardown<S: ProviderStateExecutor>(
+  interaction: &dyn Interaction,
+  client: &Arc<reqwest::Client>,
+  provider_state_executor: &Arc<S>
+) -> Result<(), MismatchResult> {
   if !interaction.provider_states().is_empty() {
     let sc_teardown_result = futures::stream::iter(
       interaction.provider_states().iter().map(|state| (state, client.clone())))
@@ -336,8 +432,7 @@ async fn verify_interaction<F: RequestFilterExecutor, S: ProviderStateExecutor>(
       return Err(MismatchResult::Error("One or more of the state change handlers has failed during teardown phase".to_string(), interaction.id()))
     }
   }
-
-  result
+  Ok(())
 }
 
 fn display_result(
@@ -359,27 +454,30 @@ fn display_result(
   println!("      has a matching body ({})", body_result);
 }
 
-fn walkdir(dir: &Path) -> anyhow::Result<Vec<anyhow::Result<Box<dyn Pact>>>> {
-    let mut pacts = vec![];
-    log::debug!("Scanning {:?}", dir);
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
-            walkdir(&path)?;
-        } else {
-            pacts.push(read_pact(&path))
-        }
+fn display_body_mismatch(expected: &dyn Interaction, actual: &dyn Interaction, mismatch: &Mismatch) {
+  if let Mismatch::BodyMismatch { ref path, .. } = mismatch {
+    if expected.content_type().unwrap_or_default().is_json() {
+      println!("{}", pact_matching::json::display_diff(
+        &expected.contents().str_value().to_string(),
+        &actual.contents().str_value().to_string(),
+        path, "    "));
+    } else if let Some(diff) = mismatch.ansi_diff("    ") {
+      println!("{}", diff);
     }
-    Ok(pacts)
+  }
 }
 
-fn display_body_mismatch(expected: &Box<dyn Interaction>, actual: &Box<dyn Interaction>, path: &str) {
-  if expected.content_type().unwrap_or_default().is_json() {
-    println!("{}", pact_matching::json::display_diff(
-      &expected.contents().str_value().to_string(),
-      &actual.contents().str_value().to_string(),
-      path, "    "));
+/// Displays a JSON Patch of the changes to the expected body that would make it match the actual
+/// body, so the pact can be updated if the change in the provider was intended
+fn display_json_patch(expected: &dyn Interaction, actual: &dyn Interaction, mismatches: &[Mismatch]) {
+  if expected.content_type().unwrap_or_default().is_json() &&
+    mismatches.iter().any(|mismatch| mismatch.json_pointer().is_some()) {
+    let expected_body = serde_json::from_slice::<Value>(&expected.contents().value().unwrap_or_default());
+    let actual_body = serde_json::from_slice::<Value>(&actual.contents().value().unwrap_or_default());
+    if let (Ok(expected_body), Ok(actual_body)) = (expected_body, actual_body) {
+      let patch = pact_matching::json::json_patch(mismatches, &expected_body, &actual_body);
+      println!("    JSON Patch to update the expected body:\n      {}\n", patch);
+    }
   }
 }
 
@@ -393,7 +491,19 @@ pub enum FilterInfo {
     /// Filter on the interaction provider state
     State(String),
     /// Filter on both the interaction description and provider state
-    DescriptionAndState(String, String)
+    DescriptionAndState(String, String),
+    /// Filter on any of the interaction description, provider state and interaction type (i.e.
+    /// `Synchronous/HTTP`). V3 request/response interactions have the `Synchronous/HTTP` type, and
+    /// V3 messages have the `Asynchronous/Messages` type.
+    Filters {
+      /// Regular expression to match the description
+      description: Option<String>,
+      /// Regular expression to match the provider state. The empty string matches interactions
+      /// without a provider state.
+      state: Option<String>,
+      /// V4 interaction type to match
+      interaction_type: Option<String>
+    }
 }
 
 impl FilterInfo {
@@ -403,6 +513,7 @@ impl FilterInfo {
         match *self {
             FilterInfo::Description(_) => true,
             FilterInfo::DescriptionAndState(_, _) => true,
+            FilterInfo::Filters { ref description, .. } => description.is_some(),
             _ => false
         }
     }
@@ -412,15 +523,28 @@ impl FilterInfo {
         match *self {
             FilterInfo::State(_) => true,
             FilterInfo::DescriptionAndState(_, _) => true,
+            FilterInfo::Filters { ref state, .. } => state.is_some(),
             _ => false
         }
     }
 
+    /// If this filter is filtering on the interaction type
+    pub fn has_interaction_type(&self) -> bool {
+        matches!(*self, FilterInfo::Filters { interaction_type: Some(_), .. })
+    }
+
+    /// If this filter filters out any interactions. Verification results are not published when
+    /// the interactions are filtered.
+    pub fn has_filters(&self) -> bool {
+        self.has_description() || self.has_state() || self.has_interaction_type()
+    }
+
     /// Value of the state to filter
     pub fn state(&self) -> String {
         match *self {
             FilterInfo::State(ref s) => s.clone(),
             FilterInfo::DescriptionAndState(_, ref s) => s.clone(),
+            FilterInfo::Filters { ref state, .. } => state.clone().unwrap_or_default(),
             _ => s!("")
         }
     }
@@ -430,6 +554,15 @@ impl FilterInfo {
         match *self {
             FilterInfo::Description(ref s) => s.clone(),
             FilterInfo::DescriptionAndState(ref s, _) => s.clone(),
+            FilterInfo::Filters { ref description, .. } => description.clone().unwrap_or_default(),
+            _ => s!("")
+        }
+    }
+
+    /// Value of the interaction type to filter
+    pub fn interaction_type(&self) -> String {
+        match *self {
+            FilterInfo::Filters { ref interaction_type, .. } => interaction_type.clone().unwrap_or_default(),
             _ => s!("")
         }
     }
@@ -460,21 +593,32 @@ impl FilterInfo {
       let re = Regex::new(&self.description()).unwrap();
       re.is_match(&interaction.description())
     }
+
+    /// If the filter matches the type of the interaction (ignoring case). V3 interactions are
+    /// matched as their equivalent V4 interaction type.
+    pub fn match_interaction_type(&self, interaction: &dyn Interaction) -> bool {
+      let interaction_type = if interaction.as_v4_sync_message().is_some() {
+        V4InteractionType::Synchronous_Messages
+      } else if interaction.is_message() {
+        V4InteractionType::Asynchronous_Messages
+      } else {
+        V4InteractionType::Synchronous_HTTP
+      };
+      interaction_type.to_string().eq_ignore_ascii_case(&self.interaction_type())
+    }
 }
 
 fn filter_interaction(interaction: &dyn Interaction, filter: &FilterInfo) -> bool {
-  if filter.has_description() && filter.has_state() {
-    filter.match_description(interaction) && filter.match_state(interaction)
-  } else if filter.has_description() {
-    filter.match_description(interaction)
-  } else if filter.has_state() {
-    filter.match_state(interaction)
-  } else {
-    true
-  }
+  (!filter.has_description() || filter.match_description(interaction)) &&
+    (!filter.has_state() || filter.match_state(interaction)) &&
+    (!filter.has_interaction_type() || filter.match_interaction_type(interaction))
 }
 
-fn filter_consumers(consumers: &[String], res: &Result<(Box<dyn Pact>, Option<PactVerificationContext>, PactSource), String>) -> bool {
+/// Pact loaded from a pact source, with the verification context if it was fetched from a Pact
+/// Broker, and the source to publish the verification results to
+type FetchedPact = Result<(Box<dyn Pact>, Option<PactVerificationContext>, PactSource), String>;
+
+fn filter_consumers(consumers: &[String], res: &FetchedPact) -> bool {
   consumers.is_empty() || res.is_err() || consumers.contains(&res.as_ref().unwrap().0.consumer().name)
 }
 
@@ -494,7 +638,42 @@ pub struct VerificationOptions<F> where F: RequestFilterExecutor {
   /// Ignore invalid/self-signed SSL certificates
   pub disable_ssl_verification: bool,
   /// Timeout in ms for provider state callbacks
-  pub callback_timeout: u64
+  pub callback_timeout: u64,
+  /// Stop the verification once this many interactions have failed. Any remaining interactions
+  /// will be skipped. `None` will verify all the interactions.
+  pub max_failures: Option<usize>,
+  /// If the response bodies must not have any keys that are not in the bodies of the interactions.
+  /// By default, unexpected keys in the responses are ignored.
+  pub strict_bodies: bool,
+  /// Descriptions of the interactions to reject unexpected keys in the response bodies of, when it
+  /// is not enabled for all the interactions with `strict_bodies`
+  pub strict_body_interactions: HashSet<String>,
+  /// Callback that produces the actual messages for message interactions. If not set, the messages
+  /// are fetched by posting the description and provider states of the interaction to the provider.
+  pub message_provider: Option<Arc<dyn MessageProviderExecutor>>,
+  /// Number of times to retry a request to the provider if the connection fails or the provider
+  /// responds with a 503 (Service Unavailable), i.e. while the provider is still starting up
+  pub provider_retries: usize,
+  /// Delay in ms before the first retry of a request to the provider. The delay is doubled for
+  /// each subsequent retry.
+  pub provider_retry_delay: u64,
+  /// TLS configuration (root CA and client certificates) for the requests to the provider and the
+  /// Pact broker
+  pub tls: TlsConfig,
+  /// Seed for the random generators (RandomInt, RandomString, Uuid, etc.) used for the requests
+  /// to the provider, so that the generated requests are reproducible
+  pub generator_seed: Option<u64>
+}
+
+impl <F: RequestFilterExecutor> VerificationOptions<F> {
+  /// How unexpected keys in the response body are treated for the interaction with the description
+  pub fn body_config_for(&self, description: &str) -> DiffConfig {
+    if self.strict_bodies || self.strict_body_interactions.contains(description) {
+      DiffConfig::NoUnexpectedKeys
+    } else {
+      DiffConfig::AllowUnexpectedKeys
+    }
+  }
 }
 
 impl <F: RequestFilterExecutor> Default for VerificationOptions<F> {
@@ -506,7 +685,15 @@ impl <F: RequestFilterExecutor> Default for VerificationOptions<F> {
       request_filter: None,
       provider_tags: vec![],
       disable_ssl_verification: false,
-      callback_timeout: 5000
+      callback_timeout: 5000,
+      max_failures: None,
+      strict_bodies: false,
+      strict_body_interactions: HashSet::new(),
+      message_provider: None,
+      provider_retries: 0,
+      provider_retry_delay: 1000,
+      tls: TlsConfig::default(),
+      generator_seed: None
     }
   }
 }
@@ -550,19 +737,55 @@ pub fn verify_provider<F: RequestFilterExecutor, S: ProviderStateExecutor>(
 
 /// Verify the provider with the given pact sources (async version)
 pub async fn verify_provider_async<F: RequestFilterExecutor, S: ProviderStateExecutor>(
+  provider_info: ProviderInfo,
+  source: Vec<PactSource>,
+  filter: FilterInfo,
+  consumers: Vec<String>,
+  options: VerificationOptions<F>,
+  provider_state_executor: &Arc<S>
+) -> bool {
+  verify_provider_with_summary_async(provider_info, source, filter, consumers, options, provider_state_executor)
+    .await.result
+}
+
+/// Verify the provider with the given pact sources, returning a summary of the verification
+pub async fn verify_provider_with_summary_async<F: RequestFilterExecutor, S: ProviderStateExecutor>(
     provider_info: ProviderInfo,
     source: Vec<PactSource>,
     filter: FilterInfo,
     consumers: Vec<String>,
     options: VerificationOptions<F>,
     provider_state_executor: &Arc<S>
-) -> bool {
-    let pact_results = fetch_pacts(source, consumers).await;
+) -> VerificationSummary {
+    let pact_results = fetch_pacts(source, consumers, &options.tls).await;
+
+    // Results for a subset of the interactions can't be published, as the broker would treat the
+    // pact as verified
+    let publish = options.publish && !filter.has_filters();
+    if options.publish && !publish {
+      warn!("Verification results will not be published as the interactions have been filtered");
+      println!("{}", Yellow.paint("WARNING: Verification results will not be published as the interactions have been filtered"));
+    }
 
     let mut results: Vec<(Option<String>, Option<MismatchResult>)> = vec![];
-    let mut pending_errors: Vec<(String, MismatchResult)> = vec![];
-    let mut all_errors: Vec<(String, MismatchResult)> = vec![];
+    let mut pending_errors: Vec<(String, MismatchResult, Vec<String>)> = vec![];
+    let mut all_errors: Vec<(String, MismatchResult, Vec<String>)> = vec![];
+    let mut skipped = 0;
     for pact_result in pact_results {
+      let remaining_failures = options.max_failures.map(|max| max.saturating_sub(all_errors.len()));
+      if remaining_failures == Some(0) {
+        match pact_result {
+          Ok((pact, _, _)) => {
+            println!("\nSkipping pact between {} and {} as the maximum number of failures has been reached",
+              Style::new().bold().paint(pact.consumer().name.clone()),
+              Style::new().bold().paint(pact.provider().name.clone()));
+            skipped += pact.interactions().iter().filter(|i| filter_interaction(**i, &filter)).count();
+          },
+          Err(err) => log::error!("Failed to load pact - {}", Red.paint(err.to_string()))
+        }
+        continue;
+      }
+
       match pact_result {
         Ok((pact, context, pact_source)) => {
           let pending = match &context {
@@ -578,20 +801,24 @@ pub async fn verify_provider_async<F: RequestFilterExecutor, S: ProviderStateExe
           if pact.interactions().is_empty() {
             println!("         {}", Yellow.paint("WARNING: Pact file has no interactions"));
           } else {
-            let errors = verify_pact(&provider_info, &filter, pact, &options,
-                                     &provider_state_executor.clone()).await;
-            for (id, desc, error) in &errors {
-              results.push((id.clone(), error.clone()));
-              if let Some(error) = error {
-                if pending {
-                  pending_errors.push((desc.clone(), error.clone()));
+            // Failures of pending pacts do not fail the build, so they don't count towards the limit
+            let failure_limit = if pending { None } else { remaining_failures };
+            let (errors, skipped_interactions) = verify_pact(&provider_info, &filter, pact, &options,
+              &provider_state_executor.clone(), failure_limit).await;
+            skipped += skipped_interactions;
+            for result in &errors {
+              results.push((result.interaction_id.clone(), result.error.clone()));
+              if let Some(error) = &result.error {
+                let failure = (result.description.clone(), error.clone(), result.comments.clone());
+                if pending || result.pending {
+                  pending_errors.push(failure);
                 } else {
-                  all_errors.push((desc.clone(), error.clone()));
+                  all_errors.push(failure);
                 }
               }
             }
 
-            if options.publish {
+            if publish {
               publish_result(&results, &pact_source, &options).await;
 
               if !all_errors.is_empty() || !pending_errors.is_empty() {
@@ -610,7 +837,7 @@ pub async fn verify_provider_async<F: RequestFilterExecutor, S: ProviderStateExe
         },
         Err(err) => {
           log::error!("Failed to load pact - {}", Red.paint(err.to_string()));
-          all_errors.push(("Failed to load pact".to_string(), MismatchResult::Error(err.to_string(), None)));
+          all_errors.push(("Failed to load pact".to_string(), MismatchResult::Error(err.to_string(), None), vec![]));
         }
       }
     };
@@ -620,7 +847,12 @@ pub async fn verify_provider_async<F: RequestFilterExecutor, S: ProviderStateExe
       print_errors(&pending_errors);
       println!("\nThere were {} non-fatal pact failures on pending pacts (see docs.pact.io/pending for more)\n", pending_errors.len());
     }
-    if !all_errors.is_empty() {
+    if skipped > 0 {
+      println!("\n{}\n", Yellow.paint(format!(
+        "WARNING: Verification was stopped after {} failures, {} interactions were not verified",
+        all_errors.len(), skipped)));
+    }
+    let result = if !all_errors.is_empty() {
       println!("\nFailures:\n");
       print_errors(&all_errors);
       println!("\nThere were {} pact failures\n", all_errors.len());
@@ -628,15 +860,31 @@ pub async fn verify_provider_async<F: RequestFilterExecutor, S: ProviderStateExe
     } else {
       println!();
       true
+    };
+
+    VerificationSummary {
+      result,
+      failures: all_errors.iter()
+        .map(|(description, error, comments)| VerificationFailure::new(description, error, comments))
+        .collect(),
+      pending_failures: pending_errors.iter()
+        .map(|(description, error, comments)| VerificationFailure::new(description, error, comments))
+        .collect(),
+      skipped
     }
 }
 
-fn print_errors(errors: &Vec<(String, MismatchResult)>) {
-  for (i, &(ref description, ref mismatch)) in errors.iter().enumerate() {
+fn print_errors(errors: &[(String, MismatchResult, Vec<String>)]) {
+  for (i, (description, mismatch, comments)) in errors.iter().enumerate() {
     match *mismatch {
-        MismatchResult::Error(ref err, _) => println!("{}) {} - {}\n", i + 1, description, err),
+        MismatchResult::Error(ref err, _) => {
+          println!("{}) {} - {}", i + 1, description, err);
+          print_failure_comments(comments);
+          println!();
+        },
         MismatchResult::Mismatches { ref mismatches, ref expected, ref actual, .. } => {
           println!("{}) {}", i + 1, description);
+          print_failure_comments(comments);
 
           let mut j = 1;
           for (_, mut mismatches) in &mismatches.into_iter().group_by(|m| m.mismatch_type()) {
@@ -647,39 +895,92 @@ fn print_errors(errors: &Vec<(String, MismatchResult)>) {
               println!("           {}", mismatch.ansi_description());
             }
 
-            if let Mismatch::BodyMismatch{ref path, ..} = mismatch {
-              display_body_mismatch(expected, actual, path);
+            if let Mismatch::BodyMismatch{..} = mismatch {
+              display_body_mismatch(expected.as_ref(), actual.as_ref(), mismatch);
             }
 
             j += 1;
           }
+
+          display_json_patch(expected.as_ref(), actual.as_ref(), mismatches);
         }
     }
   }
 }
 
-async fn fetch_pact(source: PactSource) -> Vec<Result<(Box<dyn Pact>, Option<PactVerificationContext>, PactSource), String>> {
+fn print_failure_comments(comments: &[String]) {
+  for comment in comments {
+    println!("    {}", Style::new().dimmed().paint(comment));
+  }
+}
+
+/// Pacts fetched from a URL (i.e. by a build triggered by a Pact Broker webhook) can have the
+/// Pact Broker links to publish the verification results with. Returns the Pact Broker source for
+/// the pact if it has the `pb:publish-verification-results` link.
+fn pact_url_source(url: &str, auth: &Option<HttpAuth>, pact_json: &Value) -> Option<PactSource> {
+  let links = pact_broker::links_from_json(pact_json);
+  if links.iter().any(|link| link.name.eq_ignore_ascii_case("pb:publish-verification-results")) {
+    let broker_url = reqwest::Url::parse(url).ok()?.origin().ascii_serialization();
+    let provider_name = pact_json.pointer("/provider/name").and_then(|name| name.as_str()).unwrap_or_default();
+    log::debug!("Pact '{}' was fetched from the Pact Broker '{}'", url, broker_url);
+    Some(PactSource::BrokerUrl(provider_name.to_string(), broker_url, auth.clone(), links))
+  } else {
+    None
+  }
+}
+
+/// Loads the pact from the URL. The source of the pact will be the Pact Broker if the pact has
+/// been fetched from one, otherwise it will be the given source.
+fn fetch_pact_from_url(
+  url: &str,
+  auth: &Option<HttpAuth>,
+  tls: &TlsConfig,
+  source: &PactSource
+) -> FetchedPact {
+  http_utils::fetch_json_from_url(url, auth, tls)
+    .and_then(|(pact_url, pact_json)| load_pact_from_json(&pact_url, &pact_json)
+      .map(|pact| (pact, pact_url_source(url, auth, &pact_json).unwrap_or_else(|| source.clone()))))
+    .map_err(|err| format!("Failed to load pact '{}' - {}", url, err))
+    .map(|(pact, source)| (pact, None, source))
+}
+
+async fn fetch_pact(source: PactSource, tls: &TlsConfig) -> Vec<FetchedPact> {
   match source {
     PactSource::File(ref file) => vec![read_pact(Path::new(&file))
       .map_err(|err| format!("Failed to load pact '{}' - {}", file, err))
       .map(|pact| (pact, None, source))],
-    PactSource::Dir(ref dir) => match walkdir(Path::new(dir)) {
-      Ok(pact_results) => pact_results.into_iter().map(|pact_result| {
-          match pact_result {
-              Ok(pact) => Ok((pact, None, source.clone())),
-              Err(err) => Err(format!("Failed to load pact from '{}' - {}", dir, err))
-          }
-      }).collect(),
+    PactSource::Dir(ref dir) => match load_pacts_from_dir(Path::new(dir), true) {
+      Ok(pacts) => {
+        for (file, err) in &pacts.invalid_files {
+          println!("{}", Yellow.paint(format!("WARNING: Skipping '{}' as it is not a valid pact file - {}",
+            file.display(), err)));
+        }
+        pacts.all_pacts().into_iter().map(|pact| Ok((pact, None, source.clone()))).collect()
+      },
       Err(err) => vec![Err(format!("Could not load pacts from directory '{}' - {}", dir, err))]
     },
-    PactSource::URL(ref url, ref auth) => vec![load_pact_from_url(url, auth)
-      .map_err(|err| format!("Failed to load pact '{}' - {}", url, err))
-      .map(|pact| (pact, None, source))],
+    PactSource::Glob(ref pattern) => match pact_sources::pact_files_matching(pattern) {
+      Ok(files) if files.is_empty() => vec![Err(format!("There are no pact files that match '{}'", pattern))],
+      Ok(files) => files.iter().map(|file| read_pact(file)
+        .map_err(|err| format!("Failed to load pact '{}' - {}", file.display(), err))
+        .map(|pact| (pact, None, source.clone())))
+        .collect(),
+      Err(err) => vec![Err(format!("Could not load pacts matching '{}' - {}", pattern, err))]
+    },
+    PactSource::URL(ref url, ref auth) => vec![fetch_pact_from_url(url, auth, tls, &source)],
+    PactSource::ListingUrl(ref url, ref auth) => match pact_sources::pact_urls_from_listing(url, auth, tls) {
+      Ok(urls) if urls.is_empty() => vec![Err(format!("There are no pact files in the listing '{}'", url))],
+      Ok(urls) => urls.iter()
+        .map(|pact_url| fetch_pact_from_url(pact_url, auth, tls, &PactSource::URL(pact_url.clone(), auth.clone())))
+        .collect(),
+      Err(err) => vec![Err(format!("Could not load the pact listing '{}' - {}", url, err))]
+    },
     PactSource::BrokerUrl(ref provider_name, ref broker_url, ref auth, _) => {
       let result = pact_broker::fetch_pacts_from_broker(
         broker_url.as_str(),
         provider_name.as_str(),
-        auth.clone()
+        auth.clone(),
+        tls
       ).await;
 
       match result {
@@ -712,7 +1013,8 @@ async fn fetch_pact(source: PactSource) -> Vec<Result<(Box<dyn Pact>, Option<Pac
         include_wip_pacts_since,
         provider_tags,
         selectors,
-        auth.clone()
+        auth.clone(),
+        tls
       ).await;
 
       match result {
@@ -742,11 +1044,11 @@ async fn fetch_pact(source: PactSource) -> Vec<Result<(Box<dyn Pact>, Option<Pac
   }
 }
 
-async fn fetch_pacts(source: Vec<PactSource>, consumers: Vec<String>)
-  -> Vec<Result<(Box<dyn Pact>, Option<PactVerificationContext>, PactSource), String>> {
+async fn fetch_pacts(source: Vec<PactSource>, consumers: Vec<String>, tls: &TlsConfig)
+  -> Vec<FetchedPact> {
   futures::stream::iter(source)
     .then(|pact_source| async {
-      futures::stream::iter(fetch_pact(pact_source).await)
+      futures::stream::iter(fetch_pact(pact_source, tls).await)
     })
     .flatten()
     .filter(|res| futures::future::ready(filter_consumers(&consumers, res)))
@@ -754,26 +1056,84 @@ async fn fetch_pacts(source: Vec<PactSource>, consumers: Vec<String>)
     .await
 }
 
+tokio::task_local! {
+  /// Plugins for the contents of the pact being verified (i.e. with the protobuf descriptors from
+  /// its metadata)
+  static PACT_PLUGINS: Vec<Arc<dyn ContentPlugin>>;
+}
+
+/// Matches or generates contents with the plugins for the pact being verified, so that they are
+/// not registered for any other verifications running in the same process
+pub(crate) fn with_pact_plugins<T>(f: impl FnOnce() -> T) -> T {
+  match PACT_PLUGINS.try_with(|plugins| plugins.clone()) {
+    Ok(plugins) => with_plugins(&plugins, f),
+    Err(_) => f()
+  }
+}
+
+/// Loads the plugins for the Avro schema and protobuf descriptors embedded in the pact metadata, so
+/// that Avro and protobuf contents of the pact can be matched
+fn content_plugins(pact: &dyn Pact) -> Vec<Arc<dyn ContentPlugin>> {
+  let mut plugins: Vec<Arc<dyn ContentPlugin>> = vec![];
+  match pact_matching::avro::plugin_from_pact(pact) {
+    Ok(Some(plugin)) => plugins.push(Arc::new(plugin)),
+    Ok(None) => (),
+    Err(err) => warn!("Failed to load the Avro schema from the pact metadata - {}", err)
+  }
+  #[cfg(feature = "protobuf")]
+  match pact_matching::protobuf::descriptors::plugin_from_pact(pact) {
+    Ok(Some(plugin)) => plugins.push(Arc::new(plugin)),
+    Ok(None) => (),
+    Err(err) => warn!("Failed to load the protobuf descriptors from the pact metadata - {}", err)
+  }
+  plugins
+}
+
+/// Result of verifying an interaction of a pact
+struct InteractionResult {
+  /// ID of the interaction
+  interaction_id: Option<String>,
+  /// Description of the verification that is displayed with any failure
+  description: String,
+  /// The mismatches if the verification failed
+  error: Option<MismatchResult>,
+  /// If the interaction is pending, so a failure does not fail the verification
+  pending: bool,
+  /// Comments of the interaction that are displayed with any failure
+  comments: Vec<String>
+}
+
 async fn verify_pact<'a, F: RequestFilterExecutor, S: ProviderStateExecutor>(
   provider_info: &ProviderInfo,
   filter: &FilterInfo,
   pact: Box<dyn Pact + 'a>,
   options: &VerificationOptions<F>,
-  provider_state_executor: &Arc<S>
-) -> Vec<(Option<String>, String, Option<MismatchResult>)> {
+  provider_state_executor: &Arc<S>,
+  failure_limit: Option<usize>
+) -> (Vec<InteractionResult>, usize) {
     let mut errors: Vec<(Option<String>, String, Option<MismatchResult>)> = vec![];
-
-    let results: Vec<(&dyn Interaction, Result<Option<String>, MismatchResult>)> = futures::stream::iter(
-      pact.interactions().iter().cloned()
-    )
-      .filter(|interaction| futures::future::ready(filter_interaction(*interaction, filter)))
-      .then( |interaction| async move {
-        verify_interaction(provider_info, interaction, options, provider_state_executor)
-          .then(|result| futures::future::ready((interaction, result)))
-          .await
-      })
-      .collect()
-      .await;
+    let mut error_details: Vec<(bool, Vec<String>)> = vec![];
+
+    let plugins = content_plugins(pact.as_ref());
+    let (results, skipped) = PACT_PLUGINS.scope(plugins, async {
+      let mut results: Vec<(&dyn Interaction, Result<Option<String>, MismatchResult>)> = vec![];
+      let mut skipped: Vec<&dyn Interaction> = vec![];
+      let mut failures = 0;
+      for interaction in pact.interactions().iter().cloned()
+        .filter(|interaction| filter_interaction(*interaction, filter)) {
+        if failure_limit.map(|limit| failures >= limit).unwrap_or(false) {
+          skipped.push(interaction);
+        } else {
+          let result = verify_interaction(provider_info, interaction, options, provider_state_executor).await;
+          // Failures of pending interactions do not fail the build, so they don't count towards the limit
+          if result.is_err() && !is_pending(interaction) {
+            failures += 1;
+          }
+          results.push((interaction, result));
+        }
+      }
+      (results, skipped)
+    }).await;
 
     for (interaction, match_result) in results {
       let mut description = format!("Verifying a pact between {} and {}",
@@ -786,7 +1146,13 @@ async fn verify_pact<'a, F: RequestFilterExecutor, S: ProviderStateExecutor>(
       }
       description.push_str(" - ");
       description.push_str(&interaction.description());
-      println!("  {}", interaction.description());
+      let pending = is_pending(interaction);
+      if pending {
+        println!("  {} {}", interaction.description(), Yellow.paint("[PENDING]"));
+      } else {
+        println!("  {}", interaction.description());
+      }
+      let error_count = errors.len();
 
       if interaction.is_v4() {
         if let Some(interaction) = interaction.as_v4() {
@@ -800,11 +1166,31 @@ async fn verify_pact<'a, F: RequestFilterExecutor, S: ProviderStateExecutor>(
       if let Some(interaction) = interaction.as_message() {
         display_message_result(&mut errors, &interaction, &match_result, &description)
       }
+      if let Some(interaction) = interaction.as_v4_sync_message() {
+        display_sync_message_result(&mut errors, &interaction, &match_result, &description)
+      }
+      error_details.resize(error_details.len() + errors.len() - error_count,
+        (pending, failure_comments(interaction)));
+    }
+
+    for interaction in &skipped {
+      println!("  {} ({})", interaction.description(),
+        Yellow.paint("SKIPPED as the maximum number of failures has been reached"));
     }
 
     println!();
 
-    errors
+    let errors = errors.into_iter().zip(error_details)
+      .map(|((interaction_id, description, error), (pending, comments))| InteractionResult {
+        interaction_id, description, error, pending, comments
+      })
+      .collect();
+    (errors, skipped.len())
+}
+
+/// If the interaction is a pending V4 interaction
+fn is_pending(interaction: &dyn Interaction) -> bool {
+  interaction.as_v4().map(|interaction| interaction.pending()).unwrap_or(false)
 }
 
 fn display_comments(interaction: Box<dyn V4Interaction>) {
@@ -826,6 +1212,24 @@ fn display_comments(interaction: Box<dyn V4Interaction>) {
   }
 }
 
+/// The test name and text comments of a V4 interaction, to display with the failures of the
+/// interaction
+fn failure_comments(interaction: &dyn Interaction) -> Vec<String> {
+  let comments = interaction.as_v4().map(|interaction| interaction.comments()).unwrap_or_default();
+  let mut lines = vec![];
+  if let Some(testname) = comments.get("testname") {
+    lines.push(format!("Test Name: {}", json_to_string(testname)));
+  }
+  match comments.get("text") {
+    Some(Value::Array(comment_text)) => for value in comment_text {
+      lines.push(format!("Comment: {}", json_to_string(value)));
+    },
+    Some(comment_text) => lines.push(format!("Comment: {}", json_to_string(comment_text))),
+    None => ()
+  }
+  lines
+}
+
 async fn publish_result<F: RequestFilterExecutor>(
   results: &[(Option<String>, Option<MismatchResult>)],
   source: &PactSource,
@@ -848,7 +1252,8 @@ async fn publish_result<F: RequestFilterExecutor>(
       result,
       provider_version,
       options.build_url.clone(),
-      options.provider_tags.clone()
+      options.provider_tags.clone(),
+      &options.tls
     ).await;
 
     match publish_result {
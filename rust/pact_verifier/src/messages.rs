@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 use ansi_term::{ANSIGenericString, Style};
 use ansi_term::Colour::*;
@@ -7,22 +8,58 @@ use maplit::*;
 use serde_json::{json, Value};
 
 use pact_matching::{match_message, Mismatch};
-use pact_matching::models::{Interaction, Request};
+use pact_matching::models::{Interaction, Request, Response};
+use pact_matching::models::json_utils::json_to_string;
 use pact_matching::models::HttpPart;
 use pact_matching::models::message::Message;
 use pact_models::OptionalBody;
 
-use crate::{MismatchResult, ProviderInfo, VerificationOptions};
+use crate::{MismatchResult, ProviderInfo, VerificationOptions, with_pact_plugins};
 use crate::callback_executors::RequestFilterExecutor;
 use crate::provider_client::{make_provider_request, provider_client_error_to_string};
 
+/// Header the provider can use to return the metadata of the message, as Base64 encoded JSON
+const MESSAGE_METADATA_HEADER: &str = "pact-message-metadata";
+
+/// Verifies a message interaction. The actual message is produced by the message provider
+/// callback if one is configured, otherwise it is fetched by posting the description and provider
+/// states of the interaction to the provider.
 pub async fn verify_message_from_provider<F: RequestFilterExecutor>(
   provider: &ProviderInfo,
   interaction: &Box<dyn Interaction + Send>,
   options: &VerificationOptions<F>,
   client: &reqwest::Client,
-  _: &HashMap<&str, Value>
+  _: &HashMap<&str, Value>,
+  timeout: Option<Duration>
 ) -> Result<Option<String>, MismatchResult> {
+  let actual = match &options.message_provider {
+    Some(message_provider) => message_provider.clone()
+      .call(&interaction.description(), &interaction.provider_states())
+      .map_err(|err| MismatchResult::Error(format!("Message provider failed to produce a message - {}", err),
+        interaction.id()))?,
+    None => fetch_message_from_provider(provider, interaction.as_ref(), options, client, timeout).await?
+  };
+  log::debug!("actual message = {:?}", actual);
+  let mismatches = with_pact_plugins(|| match_message(interaction, &actual.boxed()));
+  if mismatches.is_empty() {
+    Ok(interaction.id().clone())
+  } else {
+    Err(MismatchResult::Mismatches {
+      mismatches,
+      expected: interaction.boxed(),
+      actual: actual.boxed(),
+      interaction_id: interaction.id().clone()
+    })
+  }
+}
+
+async fn fetch_message_from_provider<F: RequestFilterExecutor>(
+  provider: &ProviderInfo,
+  interaction: &(dyn Interaction + Send),
+  options: &VerificationOptions<F>,
+  client: &reqwest::Client,
+  timeout: Option<Duration>
+) -> Result<Message, MismatchResult> {
   let mut request_body = json!({
     "description": interaction.description()
   });
@@ -40,27 +77,16 @@ pub async fn verify_message_from_provider<F: RequestFilterExecutor>(
     }),
     .. Request::default()
   };
-  match make_provider_request(provider, &message_request, options, client).await {
+  match make_provider_request(provider, &message_request, options, client, timeout).await {
     Ok(ref actual_response) => {
-      let actual = Message {
+      let mut metadata = message_metadata(actual_response)
+        .map_err(|err| MismatchResult::Error(err, interaction.id()))?;
+      metadata.insert("contentType".into(), actual_response.lookup_content_type().unwrap_or_default());
+      Ok(Message {
         contents: actual_response.body.clone(),
-        metadata: hashmap!{
-          "contentType".into() => actual_response.lookup_content_type().unwrap_or_default()
-        },
+        metadata,
         .. Message::default()
-      };
-      log::debug!("actual message = {:?}", actual);
-      let mismatches = match_message(interaction, &actual.boxed());
-      if mismatches.is_empty() {
-        Ok(interaction.id().clone())
-      } else {
-        Err(MismatchResult::Mismatches {
-          mismatches,
-          expected: interaction.boxed(),
-          actual: actual.boxed(),
-          interaction_id: interaction.id().clone()
-        })
-      }
+      })
     },
     Err(err) => {
       Err(MismatchResult::Error(provider_client_error_to_string(err), interaction.id().clone()))
@@ -68,6 +94,27 @@ pub async fn verify_message_from_provider<F: RequestFilterExecutor>(
   }
 }
 
+/// Decodes the message metadata from the `Pact-Message-Metadata` header of the provider response
+fn message_metadata(response: &Response) -> Result<HashMap<String, String>, String> {
+  let header = response.headers.as_ref()
+    .and_then(|headers| headers.iter()
+      .find(|(name, _)| name.eq_ignore_ascii_case(MESSAGE_METADATA_HEADER)))
+    .and_then(|(_, values)| values.first());
+  match header {
+    Some(value) => {
+      let json = base64::decode(value.trim())
+        .map_err(|err| format!("Message metadata header is not valid Base64 - {}", err))
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes)
+          .map_err(|err| format!("Message metadata header is not valid JSON - {}", err)))?;
+      match json {
+        Value::Object(map) => Ok(map.iter().map(|(k, v)| (k.clone(), json_to_string(v))).collect()),
+        _ => Err(format!("Message metadata header must be a JSON object, got '{}'", json))
+      }
+    },
+    None => Ok(hashmap!{})
+  }
+}
+
 pub fn display_message_result(
   errors: &mut Vec<(Option<String>, String, Option<MismatchResult>)>,
   interaction: &Message,
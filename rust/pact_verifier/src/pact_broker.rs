@@ -17,7 +17,7 @@ use tokio::time::{Duration, sleep};
 
 use pact_matching::Mismatch;
 use pact_matching::models::{Pact, PACT_RUST_VERSION, RequestResponsePact};
-use pact_matching::models::http_utils::HttpAuth;
+use pact_matching::models::http_utils::{HttpAuth, TlsConfig};
 use pact_matching::models::message_pact::MessagePact;
 use pact_matching::s;
 
@@ -86,7 +86,11 @@ pub enum PactBrokerError {
   NotFound(String),
   /// Invalid URL
   #[error("Invalid URL - {0}")]
-  UrlError(String)
+  UrlError(String),
+  /// The request conflicts with an existing resource (i.e. a different pact was already published
+  /// for the consumer version)
+  #[error("Conflict - {0}")]
+  Conflict(String)
 }
 
 impl PartialEq<String> for PactBrokerError {
@@ -96,7 +100,8 @@ impl PartialEq<String> for PactBrokerError {
             PactBrokerError::ContentError(ref s) => s.clone(),
             PactBrokerError::IoError(ref s) => s.clone(),
             PactBrokerError::NotFound(ref s) => s.clone(),
-            PactBrokerError::UrlError(ref s) => s.clone()
+            PactBrokerError::UrlError(ref s) => s.clone(),
+            PactBrokerError::Conflict(ref s) => s.clone()
         };
         message == *other
     }
@@ -109,7 +114,8 @@ impl <'a> PartialEq<&'a str> for PactBrokerError {
             PactBrokerError::ContentError(ref s) => s.clone(),
             PactBrokerError::IoError(ref s) => s.clone(),
             PactBrokerError::NotFound(ref s) => s.clone(),
-            PactBrokerError::UrlError(ref s) => s.clone()
+            PactBrokerError::UrlError(ref s) => s.clone(),
+            PactBrokerError::Conflict(ref s) => s.clone()
         };
         message.as_str() == *other
     }
@@ -192,6 +198,14 @@ impl HALClient {
     HALClient { url: url.to_string(), auth, ..HALClient::default() }
   }
 
+  /// Uses the TLS configuration for the requests to the Pact broker
+  pub fn with_tls(self, tls: &TlsConfig) -> Result<HALClient, PactBrokerError> {
+    let client = tls.configure_client(reqwest::ClientBuilder::new())
+      .and_then(|builder| builder.build().map_err(|err| anyhow!(err)))
+      .map_err(|err| PactBrokerError::IoError(format!("Invalid TLS configuration - {}", err)))?;
+    Ok(HALClient { client, .. self })
+  }
+
   fn update_path_info(self, path_info: serde_json::Value) -> HALClient {
     HALClient {
       client: self.client.clone(),
@@ -278,6 +292,10 @@ impl HALClient {
   }
 
   async fn fetch(self, path: &str) -> Result<serde_json::Value, PactBrokerError> {
+    self.fetch_with_query(path, &[]).await
+  }
+
+  async fn fetch_with_query(self, path: &str, query: &[(String, String)]) -> Result<serde_json::Value, PactBrokerError> {
     info!("Fetching path '{}' from pact broker", path);
 
     let url = join_paths(&self.url, path.to_string()).parse::<reqwest::Url>()
@@ -286,10 +304,11 @@ impl HALClient {
     let request_builder = match self.auth {
         Some(ref auth) => match auth {
             HttpAuth::User(username, password) => self.client.get(url).basic_auth(username, password.clone()),
-            HttpAuth::Token(token) => self.client.get(url).bearer_auth(token)
+            HttpAuth::Token(token) => self.client.get(url).bearer_auth(token),
+            HttpAuth::Headers(headers) => with_headers(self.client.get(url), headers)
         },
         None => self.client.get(url)
-    }.header("accept", "application/hal+json, application/json");
+    }.query(query).header("accept", "application/hal+json, application/json");
 
     let response = with_retries(self.retries, request_builder).await
       .map_err(|err| {
@@ -423,7 +442,8 @@ impl HALClient {
           .basic_auth(username, password.clone()),
         HttpAuth::Token(token) => self.client
           .request(method, url.clone())
-          .bearer_auth(token)
+          .bearer_auth(token),
+        HttpAuth::Headers(headers) => with_headers(self.client.request(method, url.clone()), headers)
       },
       None => self.client.request(method, url.clone())
     }
@@ -436,7 +456,14 @@ impl HALClient {
       .await
       .map_err(|err| PactBrokerError::IoError(
         format!("Failed to send JSON to the pact broker URL '{}' - {}", url, err)
-      ))?
+      ))?;
+    if response.status() == reqwest::StatusCode::CONFLICT {
+      let body = response.text().await.unwrap_or_default();
+      return Err(PactBrokerError::Conflict(
+        format!("Request to pact broker URL '{}' conflicts with an existing resource - {}", url, body)
+      ));
+    }
+    let response = response
       .error_for_status()
       .map_err(|err| PactBrokerError::ContentError(
         format!("Request to pact broker URL '{}' failed - {}",  url, err)
@@ -517,6 +544,10 @@ async fn with_retries(retries: u8, request: RequestBuilder) -> Result<reqwest::R
   }
 }
 
+fn with_headers(request_builder: RequestBuilder, headers: &[(String, String)]) -> RequestBuilder {
+  headers.iter().fold(request_builder, |request_builder, (name, value)| request_builder.header(name.as_str(), value.as_str()))
+}
+
 impl Default for HALClient {
   fn default() -> Self {
     HALClient {
@@ -531,7 +562,7 @@ impl Default for HALClient {
   }
 }
 
-fn links_from_json(json: &serde_json::Value) -> Vec<Link> {
+pub(crate) fn links_from_json(json: &serde_json::Value) -> Vec<Link> {
    match json.get("_links") {
     Some(json) => match *json {
       serde_json::Value::Object(ref v) => {
@@ -550,9 +581,10 @@ fn links_from_json(json: &serde_json::Value) -> Vec<Link> {
 pub async fn fetch_pacts_from_broker(
   broker_url: &str,
   provider_name: &str,
-  auth: Option<HttpAuth>
+  auth: Option<HttpAuth>,
+  tls: &TlsConfig
 ) -> anyhow::Result<Vec<anyhow::Result<(Box<dyn Pact + Send>, Option<PactVerificationContext>, Vec<Link>)>>> {
-    let mut hal_client = HALClient::with_url(broker_url, auth);
+    let mut hal_client = HALClient::with_url(broker_url, auth).with_tls(tls)?;
     let template_values = hashmap!{ "provider".to_string() => provider_name.to_string() };
 
     hal_client = hal_client.navigate("pb:latest-provider-pacts", &template_values)
@@ -600,8 +632,11 @@ pub async fn fetch_pacts_from_broker(
                 Value::Object(ref map) => if map.contains_key("messages") {
                   MessagePact::from_json(&href, &pact_json)
                     .map(|pact| (pact.boxed(), None, links))
+                    .map_err(|err| err.into())
                 } else {
-                  Ok((RequestResponsePact::from_json(&href, &pact_json).boxed(), None, links))
+                  RequestResponsePact::from_json(&href, &pact_json)
+                    .map(|pact| (pact.boxed(), None, links))
+                    .map_err(|err| err.into())
                 },
                 _ => Err(anyhow!("Link '{}' does not point to a valid pact file", href))
               }
@@ -617,6 +652,7 @@ pub async fn fetch_pacts_from_broker(
 }
 
 /// Fetch Pacts from the broker using the "provider-pacts-for-verification" endpoint
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_pacts_dynamically_from_broker(
   broker_url: &str,
   provider_name: String,
@@ -624,9 +660,10 @@ pub async fn fetch_pacts_dynamically_from_broker(
   include_wip_pacts_since: Option<String>,
   provider_tags: Vec<String>,
   consumer_version_selectors: Vec<ConsumerVersionSelector>,
-  auth: Option<HttpAuth>
+  auth: Option<HttpAuth>,
+  tls: &TlsConfig
 ) -> Result<Vec<Result<(Box<dyn Pact + Send>, Option<PactVerificationContext>, Vec<Link>), PactBrokerError>>, PactBrokerError> {
-    let mut hal_client = HALClient::with_url(broker_url, auth);
+    let mut hal_client = HALClient::with_url(broker_url, auth).with_tls(tls)?;
     let template_values = hashmap!{ s!("provider") => provider_name.clone() };
 
     hal_client = hal_client.navigate("pb:provider-pacts-for-verification", &template_values)
@@ -734,7 +771,10 @@ pub async fn fetch_pacts_dynamically_from_broker(
                   Err(err) => Err(PactBrokerError::ContentError(format!("{}", err)))
                 }
               } else {
-                Ok((RequestResponsePact::from_json(&href, &pact_json).boxed(), Some(context), links))
+                match RequestResponsePact::from_json(&href, &pact_json) {
+                  Ok(pact) => Ok((pact.boxed(), Some(context), links)),
+                  Err(err) => Err(PactBrokerError::ContentError(format!("{}", err)))
+                }
               },
               _ => Err(PactBrokerError::ContentError(format!("Link '{}' does not point to a valid pact file", href)))
             }
@@ -768,6 +808,7 @@ impl TestResult {
 }
 
 /// Publishes the result to the "pb:publish-verification-results" link in the links associated with the pact
+#[allow(clippy::too_many_arguments)]
 pub async fn publish_verification_results(
   links: Vec<Link>,
   broker_url: &str,
@@ -775,9 +816,10 @@ pub async fn publish_verification_results(
   result: TestResult,
   version: String,
   build_url: Option<String>,
-  provider_tags: Vec<String>
+  provider_tags: Vec<String>,
+  tls: &TlsConfig
 ) -> Result<serde_json::Value, PactBrokerError> {
-  let hal_client = HALClient::with_url(broker_url, auth.clone());
+  let hal_client = HALClient::with_url(broker_url, auth.clone()).with_tls(tls)?;
 
   if !provider_tags.is_empty() {
     publish_provider_tags(&hal_client, &links, provider_tags, &version).await?;
@@ -899,6 +941,338 @@ fn build_payload(result: TestResult, version: String, build_url: Option<String>)
   json
 }
 
+/// Options for publishing pacts to the Pact broker
+#[derive(Debug, Clone, Default)]
+pub struct PublishOptions {
+  /// Tags to add to the consumer version
+  pub tags: Vec<String>,
+  /// Branch of the consumer version
+  pub branch: Option<String>
+}
+
+/// Publishes the pact to the Pact broker for the consumer version. The tags and branch of the
+/// consumer version are created before the pact is published, so any webhooks triggered by the
+/// pact will see them. Returns a `Conflict` error if a different pact has already been published
+/// for the consumer version.
+pub async fn publish_pact(
+  broker_url: &str,
+  pact_json: &Value,
+  consumer_version: &str,
+  options: &PublishOptions,
+  auth: Option<HttpAuth>,
+  tls: &TlsConfig
+) -> Result<(), PactBrokerError> {
+  let consumer = pacticipant_name(pact_json, "consumer")?;
+  let provider = pacticipant_name(pact_json, "provider")?;
+  let hal_client = index_client(broker_url, auth, tls).await?;
+
+  for tag in &options.tags {
+    tag_version(&hal_client, &consumer, consumer_version, tag).await?;
+  }
+
+  if let Some(branch) = &options.branch {
+    let link = hal_client.find_link("pb:pacticipant-branch-version")
+      .map_err(|_| PactBrokerError::LinkError("Can't publish the consumer version branch as there is no 'pb:pacticipant-branch-version' link".to_string()))?;
+    let template_values = hashmap! {
+      "pacticipant".to_string() => consumer.clone(),
+      "branch".to_string() => branch.clone(),
+      "version".to_string() => consumer_version.to_string()
+    };
+    hal_client.put_json(&hal_client.clone().parse_link_url(&link, &template_values)?, "{}").await?;
+    debug!("Pushed branch {} for consumer version {}", branch, consumer_version);
+  }
+
+  let link = hal_client.find_link("pb:publish-pact")?;
+  let template_values = hashmap! {
+    "provider".to_string() => provider.clone(),
+    "consumer".to_string() => consumer.clone(),
+    "consumerApplicationVersion".to_string() => consumer_version.to_string()
+  };
+  hal_client.put_json(&hal_client.clone().parse_link_url(&link, &template_values)?, &pact_json.to_string()).await
+    .map_err(|err| match err {
+      PactBrokerError::Conflict(_) => PactBrokerError::Conflict(format!(
+        "A different pact between {} and {} has already been published for consumer version {}",
+        consumer, provider, consumer_version)),
+      _ => err
+    })?;
+  info!("Published pact between {} and {} for consumer version {}", consumer, provider, consumer_version);
+  Ok(())
+}
+
+/// Selects a version of a pacticipant for the matrix (can-i-deploy) query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatrixSelector {
+  /// Name of the pacticipant
+  pub pacticipant: String,
+  /// Version of the pacticipant
+  pub version: Option<String>,
+  /// Use the latest version of the pacticipant (with the tag, if given) instead of a version
+  pub latest: bool,
+  /// Tag of the latest version to use
+  pub tag: Option<String>
+}
+
+impl MatrixSelector {
+  /// Selects the version of the pacticipant
+  pub fn version(pacticipant: &str, version: &str) -> MatrixSelector {
+    MatrixSelector { pacticipant: pacticipant.to_string(), version: Some(version.to_string()), .. MatrixSelector::default() }
+  }
+
+  /// Selects the latest version of the pacticipant, optionally with the tag
+  pub fn latest(pacticipant: &str, tag: Option<&str>) -> MatrixSelector {
+    MatrixSelector { pacticipant: pacticipant.to_string(), latest: true, tag: tag.map(|tag| tag.to_string()), .. MatrixSelector::default() }
+  }
+}
+
+/// Where the selected pacticipant versions are to be deployed
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatrixTarget {
+  /// An environment (i.e. `production`) recorded in the Pact Broker
+  Environment(String),
+  /// The latest versions of the other pacticipants with the tag (i.e. `prod`)
+  Tag(String)
+}
+
+/// Summary of the matrix query result
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatrixSummary {
+  /// If the pacticipant versions can be deployed. `None` if the broker could not tell.
+  pub deployable: Option<bool>,
+  /// Reason given by the Pact Broker
+  pub reason: String,
+  /// Number of successful verifications
+  pub success: usize,
+  /// Number of failed verifications
+  pub failed: usize,
+  /// Number of missing verifications
+  pub unknown: usize
+}
+
+/// Row of the matrix, for a consumer version and provider version
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatrixRow {
+  /// Name of the consumer
+  pub consumer: String,
+  /// Version of the consumer
+  pub consumer_version: Option<String>,
+  /// Name of the provider
+  pub provider: String,
+  /// Version of the provider, if the pact has been verified
+  pub provider_version: Option<String>,
+  /// Result of the verification, if the pact has been verified
+  pub success: Option<bool>
+}
+
+/// Result of the matrix (can-i-deploy) query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Matrix {
+  /// Summary of the result
+  pub summary: MatrixSummary,
+  /// Rows of the matrix
+  pub rows: Vec<MatrixRow>
+}
+
+impl Matrix {
+  /// If the pacticipant versions can be safely deployed
+  pub fn deployable(&self) -> bool {
+    self.summary.deployable.unwrap_or(false)
+  }
+
+  /// Parses the result of the matrix query
+  pub fn from_json(json: &Value) -> Result<Matrix, PactBrokerError> {
+    let summary = json.get("summary")
+      .ok_or_else(|| PactBrokerError::ContentError("The matrix response has no summary".to_string()))?;
+    let count = |name: &str| summary.get(name).and_then(|v| v.as_u64()).unwrap_or_default() as usize;
+    let string = |json: &Value, pointer: &str| json.pointer(pointer).and_then(|v| v.as_str()).map(|v| v.to_string());
+    Ok(Matrix {
+      summary: MatrixSummary {
+        deployable: summary.get("deployable").and_then(|v| v.as_bool()),
+        reason: string(summary, "/reason").unwrap_or_default(),
+        success: count("success"),
+        failed: count("failed"),
+        unknown: count("unknown")
+      },
+      rows: json.get("matrix").and_then(|v| v.as_array()).map(|rows| rows.iter().map(|row| MatrixRow {
+        consumer: string(row, "/consumer/name").unwrap_or_default(),
+        consumer_version: string(row, "/consumer/version/number"),
+        provider: string(row, "/provider/name").unwrap_or_default(),
+        provider_version: string(row, "/provider/version/number"),
+        success: row.pointer("/verificationResult/success").and_then(|v| v.as_bool())
+      }).collect()).unwrap_or_default()
+    })
+  }
+}
+
+fn matrix_query(selectors: &[MatrixSelector], target: &Option<MatrixTarget>) -> Vec<(String, String)> {
+  let mut query = vec![];
+  for selector in selectors {
+    query.push(("q[][pacticipant]".to_string(), selector.pacticipant.clone()));
+    if let Some(version) = &selector.version {
+      query.push(("q[][version]".to_string(), version.clone()));
+    }
+    if selector.latest {
+      query.push(("q[][latest]".to_string(), "true".to_string()));
+    }
+    if let Some(tag) = &selector.tag {
+      query.push(("q[][tag]".to_string(), tag.clone()));
+    }
+  }
+  if selectors.len() == 1 {
+    query.push(("latestby".to_string(), "cvp".to_string()));
+  }
+  match target {
+    Some(MatrixTarget::Environment(environment)) => query.push(("environment".to_string(), environment.clone())),
+    Some(MatrixTarget::Tag(tag)) => {
+      query.push(("latest".to_string(), "true".to_string()));
+      query.push(("tag".to_string(), tag.clone()));
+    },
+    None => if selectors.len() == 1 {
+      query.push(("latest".to_string(), "true".to_string()));
+    }
+  }
+  query
+}
+
+/// Queries the matrix of the Pact Broker to find out if the selected pacticipant versions can be
+/// deployed to the target. With a single selector, the pacticipant version is checked against the
+/// latest versions of its integrations (in the target environment or with the target tag).
+pub async fn can_i_deploy(
+  broker_url: &str,
+  selectors: &[MatrixSelector],
+  target: &Option<MatrixTarget>,
+  auth: Option<HttpAuth>,
+  tls: &TlsConfig
+) -> Result<Matrix, PactBrokerError> {
+  let hal_client = HALClient::with_url(broker_url, auth).with_tls(tls)?;
+  let json = hal_client.fetch_with_query("/matrix", &matrix_query(selectors, target)).await?;
+  Matrix::from_json(&json)
+}
+
+/// Client with the index resource of the Pact broker fetched
+async fn index_client(broker_url: &str, auth: Option<HttpAuth>, tls: &TlsConfig) -> Result<HALClient, PactBrokerError> {
+  let hal_client = HALClient::with_url(broker_url, auth).with_tls(tls)?;
+  let index = hal_client.clone().fetch("/").await?;
+  Ok(hal_client.update_path_info(index))
+}
+
+async fn tag_version(hal_client: &HALClient, pacticipant: &str, version: &str, tag: &str) -> Result<(), PactBrokerError> {
+  let link = hal_client.find_link("pb:pacticipant-version-tag")
+    .map_err(|_| PactBrokerError::LinkError("Can't tag the pacticipant version as there is no 'pb:pacticipant-version-tag' link".to_string()))?;
+  let template_values = hashmap! {
+    "pacticipant".to_string() => pacticipant.to_string(),
+    "version".to_string() => version.to_string(),
+    "tag".to_string() => tag.to_string()
+  };
+  hal_client.put_json(&hal_client.clone().parse_link_url(&link, &template_values)?, "{}").await?;
+  debug!("Pushed tag {} for version {} of {}", tag, version, pacticipant);
+  Ok(())
+}
+
+/// Adds the tag to the version of the pacticipant. The Pact broker creates the pacticipant and
+/// version if they do not already exist.
+pub async fn create_version_tag(
+  broker_url: &str,
+  pacticipant: &str,
+  version: &str,
+  tag: &str,
+  auth: Option<HttpAuth>,
+  tls: &TlsConfig
+) -> Result<(), PactBrokerError> {
+  let hal_client = index_client(broker_url, auth, tls).await?;
+  tag_version(&hal_client, pacticipant, version, tag).await?;
+  info!("Tagged version {} of {} with {}", version, pacticipant, tag);
+  Ok(())
+}
+
+/// Finds the link of the pacticipant version resource (i.e. `pb:record-deployment`) for the
+/// environment. The version resource has one of these links for each environment, with the name
+/// of the environment.
+async fn version_environment_link(
+  hal_client: HALClient,
+  pacticipant: &str,
+  version: &str,
+  relation: &str,
+  environment: &str
+) -> Result<(HALClient, Link), PactBrokerError> {
+  let template_values = hashmap! {
+    "pacticipant".to_string() => pacticipant.to_string(),
+    "version".to_string() => version.to_string()
+  };
+  let hal_client = hal_client.navigate("pb:pacticipant-version", &template_values).await
+    .map_err(|err| match err {
+      PactBrokerError::NotFound(_) => PactBrokerError::NotFound(
+        format!("Version {} of {} was not found in the Pact broker", version, pacticipant)),
+      _ => err
+    })?;
+  let links = hal_client.path_info.as_ref()
+    .and_then(|json| json.pointer(&format!("/_links/{}", relation)))
+    .and_then(|links| links.as_array())
+    .cloned()
+    .unwrap_or_default();
+  let link = links.iter()
+    .filter_map(|link| link.as_object())
+    .find(|link| link.get("name").and_then(|name| name.as_str()) == Some(environment))
+    .map(|link| Link::from_json(relation, link))
+    .ok_or_else(|| PactBrokerError::NotFound(format!("Environment '{}' was not found for version {} of {} (link '{}')",
+      environment, version, pacticipant, relation)))?;
+  Ok((hal_client, link))
+}
+
+/// Records that the version of the pacticipant has been deployed to the environment. The
+/// application instance is used when there is more than one instance of the pacticipant deployed
+/// to the environment at the same time.
+pub async fn record_deployment(
+  broker_url: &str,
+  pacticipant: &str,
+  version: &str,
+  environment: &str,
+  application_instance: Option<&str>,
+  auth: Option<HttpAuth>,
+  tls: &TlsConfig
+) -> Result<(), PactBrokerError> {
+  let hal_client = index_client(broker_url, auth, tls).await?;
+  let (hal_client, link) = version_environment_link(hal_client, pacticipant, version,
+    "pb:record-deployment", environment).await?;
+  let body = match application_instance {
+    Some(instance) => json!({ "applicationInstance": instance }),
+    None => json!({})
+  };
+  let url = link.href.clone()
+    .ok_or_else(|| PactBrokerError::LinkError(format!("Link '{}' has no href", link.name)))?;
+  hal_client.post_json(&url, &body.to_string()).await?;
+  info!("Recorded deployment of version {} of {} to {}", version, pacticipant, environment);
+  Ok(())
+}
+
+/// Records that the version of the pacticipant has been released to the environment. Use this
+/// instead of recording a deployment for pacticipants that can have more than one version
+/// released at a time (i.e. mobile apps).
+pub async fn record_release(
+  broker_url: &str,
+  pacticipant: &str,
+  version: &str,
+  environment: &str,
+  auth: Option<HttpAuth>,
+  tls: &TlsConfig
+) -> Result<(), PactBrokerError> {
+  let hal_client = index_client(broker_url, auth, tls).await?;
+  let (hal_client, link) = version_environment_link(hal_client, pacticipant, version,
+    "pb:record-release", environment).await?;
+  let url = link.href.clone()
+    .ok_or_else(|| PactBrokerError::LinkError(format!("Link '{}' has no href", link.name)))?;
+  hal_client.post_json(&url, "{}").await?;
+  info!("Recorded release of version {} of {} to {}", version, pacticipant, environment);
+  Ok(())
+}
+
+fn pacticipant_name(pact_json: &Value, pacticipant: &str) -> Result<String, PactBrokerError> {
+  pact_json.get(pacticipant)
+    .and_then(|pacticipant| pacticipant.get("name"))
+    .and_then(|name| name.as_str())
+    .map(|name| name.to_string())
+    .ok_or_else(|| PactBrokerError::ContentError(format!("The pact does not have a {} name", pacticipant)))
+}
+
 async fn publish_provider_tags(
   hal_client: &HALClient,
   links: &[Link],
@@ -1010,18 +1384,45 @@ mod tests {
   use pact_consumer::*;
   use pact_consumer::prelude::*;
   use pact_matching::Mismatch::MethodMismatch;
+  use pact_consumer::builders::InteractionBuilder;
+  use pact_matching::models::generators::{Generator, GeneratorCategory};
   use pact_matching::models::RequestResponseInteraction;
   use pact_models::{Consumer, PactSpecification, Provider};
 
   use super::*;
   use super::{content_type, json_content_type};
 
+  #[test]
+  fn with_tls_returns_an_error_if_the_tls_configuration_is_invalid() {
+    let tls = TlsConfig { ca_certificate: Some("missing-ca.pem".into()), .. TlsConfig::default() };
+    let result = HALClient::with_url("https://localhost", None).with_tls(&tls);
+    expect!(result.err().map(|err| err.to_string())).to(be_some()
+      .value("IO Error - Invalid TLS configuration - Could not read 'missing-ca.pem'"));
+  }
+
   #[tokio::test]
   async fn fetch_returns_an_error_if_there_is_no_pact_broker() {
     let client = HALClient::with_url("http://idont.exist:6666", None);
     expect!(client.fetch("/").await).to(be_err());
   }
 
+  #[tokio::test]
+  async fn fetch_adds_the_static_headers_to_the_request() {
+    let pact_broker = PactBuilder::new("RustPactVerifier", "PactBroker")
+      .interaction("a request with an API key", |i| {
+        i.request.path("/")
+          .header("X-Api-Key", "1234567890");
+        i.response
+          .header("Content-Type", "application/hal+json")
+          .json_body(json_pattern!({ "_links": {} }));
+      })
+      .start_mock_server();
+
+    let client = HALClient::with_url(pact_broker.url().as_str(),
+      Some(HttpAuth::Headers(vec![("X-Api-Key".to_string(), "1234567890".to_string())])));
+    expect!(client.fetch("/").await).to(be_ok());
+  }
+
   #[tokio::test]
   async fn fetch_returns_an_error_if_it_does_not_get_a_success_response() {
     let pact_broker = PactBuilder::new("RustPactVerifier", "PactBroker")
@@ -1371,7 +1772,7 @@ mod tests {
             .start_mock_server();
 
         let result = fetch_pacts_from_broker(pact_broker.url().as_str(),
-                                             "sad_provider", None).await;
+                                             "sad_provider", None, &TlsConfig::default()).await;
         match result {
           Ok(_) => {
             panic!("Expected an error result, but got OK");
@@ -1452,7 +1853,7 @@ mod tests {
             .start_mock_server();
 
         let result = fetch_pacts_from_broker(pact_broker.url().as_str(),
-          "happy_provider", None).await;
+          "happy_provider", None, &TlsConfig::default()).await;
         match &result {
           Ok(_) => (),
           Err(err) => panic!("Expected an Ok result, got a error {}", err)
@@ -1579,7 +1980,7 @@ mod tests {
       tag: "prod".to_string(),
       fallback_tag: None,
       latest: None
-    }), None).await;
+    }), None, &TlsConfig::default()).await;
 
     match &result {
       Ok(_) => (),
@@ -1665,7 +2066,7 @@ mod tests {
       tag: "prod".to_string(),
       fallback_tag: None,
       latest: None
-    }), None).await;
+    }), None, &TlsConfig::default()).await;
 
     match result {
       Ok(_) => {
@@ -1895,4 +2296,277 @@ mod tests {
     expect!(json.to_string()).to(be_equal_to(
       "{\"href\":\"1234\",\"templated\":true,\"title\":\"title\"}"));
   }
+
+  fn broker_link(interaction: &mut RequestResponseInteraction, link: &str, path: &str) {
+    interaction.response.generators.add_generator_with_subcategory(&GeneratorCategory::BODY,
+      format!("$._links['{}'].href", link), Generator::MockServerURL(format!("http://localhost:9292{}", path),
+        ".*(/pacticipants/.*|/pacts/.*)$".to_string()));
+  }
+
+  fn publish_pact_broker(broker_name: &str, pact_response_status: u16) -> ValidatingMockServer {
+    let mut index = InteractionBuilder::new("a request for the index resource");
+    index.request.path("/");
+    index.response
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({
+        "_links": {
+          "pb:pacticipant-version-tag": {
+            "href": "http://localhost:9292/pacticipants/{pacticipant}/versions/{version}/tags/{tag}",
+            "templated": true
+          },
+          "pb:pacticipant-branch-version": {
+            "href": "http://localhost:9292/pacticipants/{pacticipant}/branches/{branch}/versions/{version}",
+            "templated": true
+          },
+          "pb:publish-pact": {
+            "href": "http://localhost:9292/pacts/provider/{provider}/consumer/{consumer}/version/{consumerApplicationVersion}",
+            "templated": true
+          }
+        }
+      }));
+    let mut index = index.build();
+    broker_link(&mut index, "pb:pacticipant-version-tag", "/pacticipants/{pacticipant}/versions/{version}/tags/{tag}");
+    broker_link(&mut index, "pb:pacticipant-branch-version", "/pacticipants/{pacticipant}/branches/{branch}/versions/{version}");
+    broker_link(&mut index, "pb:publish-pact", "/pacts/provider/{provider}/consumer/{consumer}/version/{consumerApplicationVersion}");
+
+    let mut tag = InteractionBuilder::new("a request to tag the consumer version");
+    tag.request.method("PUT").path("/pacticipants/Consumer/versions/1.0.0/tags/prod");
+    tag.response.status(201)
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({}));
+    let mut branch = InteractionBuilder::new("a request to add the consumer version to a branch");
+    branch.request.method("PUT").path("/pacticipants/Consumer/branches/main/versions/1.0.0");
+    branch.response.status(201)
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({}));
+    let mut pact = InteractionBuilder::new("a request to publish the pact");
+    pact.request.method("PUT").path("/pacts/provider/Provider/consumer/Consumer/version/1.0.0");
+    pact.response.status(pact_response_status)
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({}));
+
+    let pact = RequestResponsePact {
+      consumer: Consumer { name: "RustPactVerifier".to_string() },
+      provider: Provider { name: broker_name.to_string() },
+      interactions: vec![ index, tag.build(), branch.build(), pact.build() ],
+      .. RequestResponsePact::default()
+    };
+    ValidatingMockServer::start(pact.boxed())
+  }
+
+  fn pact_json() -> Value {
+    json!({
+      "consumer": { "name": "Consumer" },
+      "provider": { "name": "Provider" },
+      "interactions": [],
+      "metadata": { "pactSpecification": { "version": "3.0.0" } }
+    })
+  }
+
+  #[tokio::test]
+  async fn publish_pact_creates_the_tags_and_branch_and_publishes_the_pact() {
+    let _ = env_logger::try_init();
+    let pact_broker = publish_pact_broker("PactBrokerPublish", 201);
+
+    let options = PublishOptions { tags: vec!["prod".to_string()], branch: Some("main".to_string()) };
+    let result = publish_pact(pact_broker.url().as_str(), &pact_json(), "1.0.0", &options, None,
+      &TlsConfig::default()).await;
+    expect!(result.map_err(|err| err.to_string())).to(be_ok());
+  }
+
+  #[tokio::test]
+  async fn publish_pact_returns_a_conflict_error_if_a_different_pact_was_published_for_the_version() {
+    let _ = env_logger::try_init();
+    let pact_broker = publish_pact_broker("PactBrokerPublishConflict", 409);
+
+    let options = PublishOptions { tags: vec!["prod".to_string()], branch: Some("main".to_string()) };
+    let result = publish_pact(pact_broker.url().as_str(), &pact_json(), "1.0.0", &options, None,
+      &TlsConfig::default()).await;
+    expect!(result.map_err(|err| err.to_string())).to(be_err().value(
+      "Conflict - A different pact between Consumer and Provider has already been published for consumer version 1.0.0".to_string()));
+
+    let result = publish_pact(pact_broker.url().as_str(), &json!({}), "1.0.0", &options, None,
+      &TlsConfig::default()).await;
+    expect!(result.map_err(|err| err.to_string())).to(be_err().value(
+      "Error with the content of a HAL resource - The pact does not have a consumer name".to_string()));
+  }
+
+  #[test]
+  fn matrix_query_for_the_selectors() {
+    expect!(matrix_query(&[MatrixSelector::version("Foo", "1.2.3")], &None)).to(be_equal_to(vec![
+      ("q[][pacticipant]".to_string(), "Foo".to_string()),
+      ("q[][version]".to_string(), "1.2.3".to_string()),
+      ("latestby".to_string(), "cvp".to_string()),
+      ("latest".to_string(), "true".to_string())
+    ]));
+    expect!(matrix_query(&[MatrixSelector::version("Foo", "1.2.3")], &Some(MatrixTarget::Tag("prod".to_string()))))
+      .to(be_equal_to(vec![
+        ("q[][pacticipant]".to_string(), "Foo".to_string()),
+        ("q[][version]".to_string(), "1.2.3".to_string()),
+        ("latestby".to_string(), "cvp".to_string()),
+        ("latest".to_string(), "true".to_string()),
+        ("tag".to_string(), "prod".to_string())
+      ]));
+    expect!(matrix_query(&[MatrixSelector::version("Foo", "1.2.3"), MatrixSelector::latest("Bar", Some("prod"))],
+      &Some(MatrixTarget::Environment("production".to_string())))).to(be_equal_to(vec![
+        ("q[][pacticipant]".to_string(), "Foo".to_string()),
+        ("q[][version]".to_string(), "1.2.3".to_string()),
+        ("q[][pacticipant]".to_string(), "Bar".to_string()),
+        ("q[][latest]".to_string(), "true".to_string()),
+        ("q[][tag]".to_string(), "prod".to_string()),
+        ("environment".to_string(), "production".to_string())
+      ]));
+  }
+
+  #[tokio::test]
+  async fn can_i_deploy_returns_the_matrix_for_the_pacticipant_version() {
+    let _ = env_logger::try_init();
+    let pact_broker = PactBuilder::new("RustPactVerifier", "PactBrokerMatrix")
+      .interaction("a request for the matrix", |i| {
+        i.request.path("/matrix")
+          .query_param("q[][pacticipant]", "Foo")
+          .query_param("q[][version]", "1.2.3")
+          .query_param("latestby", "cvp")
+          .query_param("environment", "production");
+        i.response
+          .header("Content-Type", "application/hal+json")
+          .json_body(json_pattern!({
+            "summary": {
+              "deployable": false,
+              "reason": "One or more verifications have failed",
+              "success": 1,
+              "failed": 1,
+              "unknown": 0
+            },
+            "matrix": [
+              {
+                "consumer": { "name": "Foo", "version": { "number": "1.2.3" } },
+                "provider": { "name": "Bar", "version": { "number": "4.5.6" } },
+                "verificationResult": { "success": true }
+              },
+              {
+                "consumer": { "name": "Foo", "version": { "number": "1.2.3" } },
+                "provider": { "name": "Baz", "version": { "number": "7.8.9" } },
+                "verificationResult": { "success": false }
+              }
+            ]
+          }));
+      })
+      .start_mock_server();
+
+    let result = can_i_deploy(pact_broker.url().as_str(), &[MatrixSelector::version("Foo", "1.2.3")],
+      &Some(MatrixTarget::Environment("production".to_string())), None, &TlsConfig::default()).await.unwrap();
+    expect!(result.deployable()).to(be_false());
+    expect!(result.summary).to(be_equal_to(MatrixSummary {
+      deployable: Some(false),
+      reason: "One or more verifications have failed".to_string(),
+      success: 1,
+      failed: 1,
+      unknown: 0
+    }));
+    expect!(result.rows.iter().map(|row| (row.provider.as_str(), row.provider_version.as_deref(), row.success))
+      .collect::<Vec<_>>()).to(be_equal_to(vec![
+        ("Bar", Some("4.5.6"), Some(true)),
+        ("Baz", Some("7.8.9"), Some(false))
+      ]));
+  }
+
+  fn environment_link(interaction: &mut RequestResponseInteraction, link: &str, path: &str) {
+    interaction.response.generators.add_generator_with_subcategory(&GeneratorCategory::BODY,
+      format!("$._links['{}'][0].href", link), Generator::MockServerURL(format!("http://localhost:9292{}", path),
+        ".*(/pacticipants/.*)$".to_string()));
+  }
+
+  fn environments_pact_broker(broker_name: &str) -> ValidatingMockServer {
+    let mut index = InteractionBuilder::new("a request for the index resource");
+    index.request.path("/");
+    index.response
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({
+        "_links": {
+          "pb:pacticipant-version-tag": {
+            "href": "http://localhost:9292/pacticipants/{pacticipant}/versions/{version}/tags/{tag}",
+            "templated": true
+          },
+          "pb:pacticipant-version": {
+            "href": "http://localhost:9292/pacticipants/{pacticipant}/versions/{version}",
+            "templated": true
+          }
+        }
+      }));
+    let mut index = index.build();
+    broker_link(&mut index, "pb:pacticipant-version-tag", "/pacticipants/{pacticipant}/versions/{version}/tags/{tag}");
+    broker_link(&mut index, "pb:pacticipant-version", "/pacticipants/{pacticipant}/versions/{version}");
+
+    let mut version = InteractionBuilder::new("a request for the pacticipant version");
+    version.request.path("/pacticipants/Foo/versions/1.2.3");
+    version.response
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({
+        "number": "1.2.3",
+        "_links": {
+          "pb:record-deployment": [
+            {
+              "name": "production",
+              "title": "Record deployment to Production",
+              "href": "http://localhost:9292/pacticipants/Foo/versions/1.2.3/deployed-versions/environment/1234"
+            }
+          ],
+          "pb:record-release": [
+            {
+              "name": "production",
+              "title": "Record release to Production",
+              "href": "http://localhost:9292/pacticipants/Foo/versions/1.2.3/released-versions/environment/1234"
+            }
+          ]
+        }
+      }));
+    let mut version = version.build();
+    environment_link(&mut version, "pb:record-deployment", "/pacticipants/Foo/versions/1.2.3/deployed-versions/environment/1234");
+    environment_link(&mut version, "pb:record-release", "/pacticipants/Foo/versions/1.2.3/released-versions/environment/1234");
+
+    let mut tag = InteractionBuilder::new("a request to tag the pacticipant version");
+    tag.request.method("PUT").path("/pacticipants/Foo/versions/1.2.3/tags/prod");
+    tag.response.status(201)
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({}));
+    let mut deployment = InteractionBuilder::new("a request to record the deployment");
+    deployment.request.method("POST").path("/pacticipants/Foo/versions/1.2.3/deployed-versions/environment/1234")
+      .json_body(json_pattern!({ "applicationInstance": "blue" }));
+    deployment.response.status(201)
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({}));
+    let mut release = InteractionBuilder::new("a request to record the release");
+    release.request.method("POST").path("/pacticipants/Foo/versions/1.2.3/released-versions/environment/1234");
+    release.response.status(201)
+      .header("Content-Type", "application/hal+json")
+      .json_body(json_pattern!({}));
+
+    let pact = RequestResponsePact {
+      consumer: Consumer { name: "RustPactVerifier".to_string() },
+      provider: Provider { name: broker_name.to_string() },
+      interactions: vec![ index, version, tag.build(), deployment.build(), release.build() ],
+      .. RequestResponsePact::default()
+    };
+    ValidatingMockServer::start(pact.boxed())
+  }
+
+  #[tokio::test]
+  async fn tag_and_record_deployment_and_release_of_a_pacticipant_version() {
+    let _ = env_logger::try_init();
+    let pact_broker = environments_pact_broker("PactBrokerRecordDeployment");
+    let broker_url = pact_broker.url();
+
+    let result = create_version_tag(broker_url.as_str(), "Foo", "1.2.3", "prod", None, &TlsConfig::default()).await;
+    expect!(result.map_err(|err| err.to_string())).to(be_ok());
+
+    let result = record_deployment(broker_url.as_str(), "Foo", "1.2.3", "production", Some("blue"), None,
+      &TlsConfig::default()).await;
+    expect!(result.map_err(|err| err.to_string())).to(be_ok());
+    let result = record_release(broker_url.as_str(), "Foo", "1.2.3", "production", None, &TlsConfig::default()).await;
+    expect!(result.map_err(|err| err.to_string())).to(be_ok());
+    let result = record_release(broker_url.as_str(), "Foo", "1.2.3", "test", None, &TlsConfig::default()).await;
+    expect!(result.map_err(|err| err.to_string())).to(be_err().value(
+      "Link/Resource was not found - Environment 'test' was not found for version 1.2.3 of Foo (link 'pb:record-release')".to_string()));
+  }
 }
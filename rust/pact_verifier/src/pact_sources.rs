@@ -0,0 +1,188 @@
+//! Pact sources that expand to a number of pact files: glob patterns of local pact files, and
+//! remote listings of pact files (HTTP server directory index pages and S3 bucket listings)
+
+use std::path::PathBuf;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+
+use pact_matching::error::PactError;
+use pact_matching::models::http_utils::{fetch_text_from_url, HttpAuth, TlsConfig};
+
+lazy_static! {
+  static ref HREF: Regex = Regex::new(r#"(?i)href\s*=\s*["']([^"']+)["']"#).unwrap();
+  static ref S3_KEY: Regex = Regex::new(r"<Key>([^<]+)</Key>").unwrap();
+  static ref S3_CONTINUATION_TOKEN: Regex = Regex::new(r"<NextContinuationToken>([^<]+)</NextContinuationToken>").unwrap();
+}
+
+/// Returns the files that match the glob pattern (i.e. `pacts/**/provider-*.json`), sorted by path
+pub(crate) fn pact_files_matching(pattern: &str) -> Result<Vec<PathBuf>, String> {
+  let paths = glob::glob(pattern)
+    .map_err(|err| format!("'{}' is not a valid glob pattern - {}", pattern, err))?;
+  let mut files = vec![];
+  for path in paths {
+    let path = path.map_err(|err| format!("Could not read '{}' - {}", err.path().display(), err.error()))?;
+    if path.is_file() {
+      files.push(path);
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+/// Fetches the listing at the URL, and returns the URLs of the pact files (files with a `.json`
+/// extension) in it. The listing can either be an S3 bucket listing (the XML returned by the
+/// `ListObjectsV2` API, i.e. `https://bucket.s3.amazonaws.com/?list-type=2&prefix=pacts/`), in
+/// which case truncated listings are followed, or an HTML page that links to the pact files (like
+/// the directory index pages of most HTTP servers).
+pub(crate) fn pact_urls_from_listing(
+  url: &str,
+  auth: &Option<HttpAuth>,
+  tls: &TlsConfig
+) -> Result<Vec<String>, PactError> {
+  let listing_url = Url::parse(url).map_err(|err| PactError::HttpError {
+    url: url.to_string(),
+    status: None,
+    message: format!("Invalid URL - {}", err)
+  })?;
+  let mut listing = fetch_text_from_url(url, auth, tls)?;
+  if listing.contains("<ListBucketResult") {
+    let mut urls = s3_listing_urls(&listing_url, &listing);
+    while let Some(token) = S3_CONTINUATION_TOKEN.captures(&listing).map(|token| unescape_xml(&token[1])) {
+      let next_url = with_continuation_token(&listing_url, &token);
+      log::debug!("S3 listing is truncated, fetching the next page from '{}'", next_url);
+      listing = fetch_text_from_url(next_url.as_str(), auth, tls)?;
+      urls.extend(s3_listing_urls(&listing_url, &listing));
+    }
+    Ok(urls)
+  } else {
+    Ok(html_listing_urls(&listing_url, &listing))
+  }
+}
+
+/// URLs of the `.json` files linked to from the HTML page, resolved against the URL of the page
+fn html_listing_urls(listing_url: &Url, html: &str) -> Vec<String> {
+  let mut urls: Vec<String> = vec![];
+  for link in HREF.captures_iter(html) {
+    if let Ok(url) = listing_url.join(&unescape_xml(&link[1])) {
+      if url.path().ends_with(".json") && !urls.contains(&url.to_string()) {
+        urls.push(url.to_string());
+      }
+    }
+  }
+  urls
+}
+
+/// URLs of the `.json` objects in the S3 bucket listing. The objects are relative to the bucket
+/// URL, which is the listing URL without the query parameters.
+fn s3_listing_urls(listing_url: &Url, xml: &str) -> Vec<String> {
+  let mut bucket_url = listing_url.clone();
+  bucket_url.set_query(None);
+  if !bucket_url.path().ends_with('/') {
+    let path = format!("{}/", bucket_url.path());
+    bucket_url.set_path(&path);
+  }
+  S3_KEY.captures_iter(xml)
+    .map(|key| unescape_xml(&key[1]))
+    .filter(|key| key.ends_with(".json"))
+    .filter_map(|key| bucket_url.join(&key).ok())
+    .map(|url| url.to_string())
+    .collect()
+}
+
+fn with_continuation_token(listing_url: &Url, token: &str) -> Url {
+  let query: Vec<(String, String)> = listing_url.query_pairs()
+    .filter(|(name, _)| name != "continuation-token")
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+  let mut url = listing_url.clone();
+  url.query_pairs_mut()
+    .clear()
+    .extend_pairs(query)
+    .append_pair("continuation-token", token);
+  url
+}
+
+fn unescape_xml(value: &str) -> String {
+  value.replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&apos;", "'")
+    .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use expectest::prelude::*;
+
+  use super::*;
+
+  #[test]
+  fn pact_files_matching_returns_the_files_matching_the_pattern() {
+    let dir = std::env::temp_dir().join("pact_verifier_pact_files_matching");
+    fs::remove_dir_all(&dir).unwrap_or_default();
+    fs::create_dir_all(dir.join("web")).unwrap();
+    fs::create_dir_all(dir.join("mobile")).unwrap();
+    fs::write(dir.join("web").join("provider-users.json"), "{}").unwrap();
+    fs::write(dir.join("mobile").join("provider-users.json"), "{}").unwrap();
+    fs::write(dir.join("mobile").join("other-users.json"), "{}").unwrap();
+
+    let files = pact_files_matching(&format!("{}/**/provider-*.json", dir.display()));
+    fs::remove_dir_all(&dir).unwrap_or_default();
+
+    expect!(files).to(be_ok().value(vec![
+      dir.join("mobile").join("provider-users.json"),
+      dir.join("web").join("provider-users.json")
+    ]));
+    expect!(pact_files_matching("pacts/***/[.json")).to(be_err());
+  }
+
+  #[test]
+  fn html_listing_urls_returns_the_linked_json_files() {
+    let url = Url::parse("http://localhost:8080/pacts/").unwrap();
+    let html = r#"<html><body><h1>Index of /pacts/</h1>
+      <a href="../">../</a>
+      <a href="web-users.json">web-users.json</a>
+      <a href='mobile/mobile-users.json'>mobile-users.json</a>
+      <a HREF="/other/pacts/tablet-users.json?download=true">tablet-users.json</a>
+      <a href="README.md">README.md</a>
+      <a href="web-users.json">web-users.json</a>
+      </body></html>"#;
+    expect!(html_listing_urls(&url, html)).to(be_equal_to(vec![
+      "http://localhost:8080/pacts/web-users.json".to_string(),
+      "http://localhost:8080/pacts/mobile/mobile-users.json".to_string(),
+      "http://localhost:8080/other/pacts/tablet-users.json?download=true".to_string()
+    ]));
+  }
+
+  #[test]
+  fn s3_listing_urls_returns_the_json_objects_relative_to_the_bucket() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+      <ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+        <Name>pacts</Name><Prefix>users/</Prefix><KeyCount>3</KeyCount><IsTruncated>false</IsTruncated>
+        <Contents><Key>users/web.json</Key><Size>1024</Size></Contents>
+        <Contents><Key>users/web &amp; mobile.json</Key><Size>1024</Size></Contents>
+        <Contents><Key>users/README.md</Key><Size>10</Size></Contents>
+      </ListBucketResult>"#;
+
+    let url = Url::parse("https://pacts.s3.amazonaws.com/?list-type=2&prefix=users/").unwrap();
+    expect!(s3_listing_urls(&url, xml)).to(be_equal_to(vec![
+      "https://pacts.s3.amazonaws.com/users/web.json".to_string(),
+      "https://pacts.s3.amazonaws.com/users/web%20&%20mobile.json".to_string()
+    ]));
+
+    let url = Url::parse("https://s3.amazonaws.com/pacts?list-type=2&prefix=users/").unwrap();
+    expect!(s3_listing_urls(&url, xml).first().cloned()).to(be_some().value(
+      "https://s3.amazonaws.com/pacts/users/web.json".to_string()));
+  }
+
+  #[test]
+  fn with_continuation_token_replaces_the_token_in_the_listing_url() {
+    let url = Url::parse("https://pacts.s3.amazonaws.com/?list-type=2&continuation-token=abc").unwrap();
+    expect!(with_continuation_token(&url, "1/2+3").to_string()).to(be_equal_to(
+      "https://pacts.s3.amazonaws.com/?list-type=2&continuation-token=1%2F2%2B3".to_string()));
+  }
+}
@@ -1,8 +1,8 @@
 use std::collections::hash_map::HashMap;
 use std::convert::TryFrom;
+use std::time::Duration;
 
-use futures::future::*;
-use http::{HeaderMap, HeaderValue, Method};
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
 use http::header::{HeaderName, InvalidHeaderName, InvalidHeaderValue};
 use http::header::CONTENT_TYPE;
 use http::method::InvalidMethod;
@@ -26,6 +26,7 @@ pub enum ProviderClientError {
     RequestBodyError(String),
     ResponseError(String),
     ResponseStatusCodeError(u16),
+    ProviderUnavailable(String),
 }
 
 impl From<reqwest::Error> for ProviderClientError {
@@ -43,8 +44,9 @@ pub fn join_paths(base: &str, path: String) -> String {
 
 fn create_native_request(client: &Client, base_url: &str, request: &Request) -> Result<RequestBuilder, ProviderClientError> {
   let url = join_paths(base_url, request.path.clone());
-  let mut builder = client.request(Method::from_bytes(
-    &request.method.clone().into_bytes()).unwrap_or(Method::GET), &url);
+  let method = Method::from_bytes(request.method.as_bytes())
+    .map_err(|err| ProviderClientError::RequestMethodError(request.method.clone(), err))?;
+  let mut builder = client.request(method, &url);
 
   if let Some(query) = &request.query {
     builder = builder.query(&query.iter()
@@ -137,12 +139,16 @@ async fn native_response_to_pact_response(
 }
 
 /// This function makes the actual request to the provider, executing any request filter before
-/// executing the request
+/// executing the request. If a timeout is given (i.e. from the interaction being verified), the
+/// request fails if the provider does not respond within that time. Requests that fail to connect
+/// or get a 503 (Service Unavailable) response are retried with backoff as configured by the
+/// verification options.
 pub async fn make_provider_request<F: RequestFilterExecutor>(
   provider: &ProviderInfo,
   request: &Request,
   options: &VerificationOptions<F>,
-  client: &reqwest::Client
+  client: &reqwest::Client,
+  timeout: Option<Duration>
 ) -> Result<Response, ProviderClientError> {
   let request_filter_option = options.request_filter.clone();
   let request = if request_filter_option.is_some() {
@@ -162,14 +168,52 @@ pub async fn make_provider_request<F: RequestFilterExecutor>(
   debug!("Provider details = {:?}", provider);
   debug!("Sending request {}", request);
   trace!("body: {}", request.body.str_value());
-  let request = create_native_request(client, &base_url, &request)?;
+  if let Some(timeout) = timeout {
+    debug!("Using a request timeout of {:?}", timeout);
+  }
 
-  let response = request.send()
-    .and_then(native_response_to_pact_response)
-    .await
-    .map_err(|err| ProviderClientError::ResponseError(err.to_string()))?;
+  let mut attempt = 0;
+  loop {
+    let mut native_request = create_native_request(client, &base_url, &request)?;
+    if let Some(timeout) = timeout {
+      native_request = native_request.timeout(timeout);
+    }
 
-  Ok(response)
+    let result = native_request.send().await;
+    let unavailable = match &result {
+      Ok(response) => response.status() == StatusCode::SERVICE_UNAVAILABLE,
+      Err(err) => err.is_connect()
+    };
+    if unavailable && attempt < options.provider_retries {
+      let delay = retry_delay(options.provider_retry_delay, attempt);
+      attempt += 1;
+      warn!("Provider is not available, retrying the request in {:?} (retry {} of {})", delay, attempt,
+        options.provider_retries);
+      tokio::time::sleep(delay).await;
+      continue;
+    }
+
+    return match result {
+      Ok(response) => native_response_to_pact_response(response).await
+        .map_err(|err| response_error(err, timeout)),
+      Err(err) if err.is_connect() => Err(ProviderClientError::ProviderUnavailable(
+        format!("Could not connect to the provider at {} after {} attempt(s) - {}", base_url, attempt + 1, err))),
+      Err(err) => Err(response_error(err, timeout))
+    };
+  }
+}
+
+/// Delay before retrying a request to the provider. The delay is doubled for each retry.
+fn retry_delay(initial_delay: u64, attempt: usize) -> Duration {
+  Duration::from_millis(initial_delay.saturating_mul(2_u64.saturating_pow(attempt as u32)))
+}
+
+fn response_error(err: Error, timeout: Option<Duration>) -> ProviderClientError {
+  match timeout {
+    Some(timeout) if err.is_timeout() =>
+      ProviderClientError::ResponseError(format!("Provider did not respond within the interaction timeout of {:?}", timeout)),
+    _ => ProviderClientError::ResponseError(err.to_string())
+  }
 }
 
 /// Make a state change request. If the response returns a JSON body, convert that into a HashMap
@@ -232,7 +276,9 @@ pub fn provider_client_error_to_string(err: ProviderClientError) -> String {
     ProviderClientError::ResponseError(ref message) =>
       format!("Invalid response: {}", message),
     ProviderClientError::ResponseStatusCodeError(ref code) =>
-      format!("Invalid status code: {}", code)
+      format!("Invalid status code: {}", code),
+    ProviderClientError::ProviderUnavailable(ref message) =>
+      format!("Provider unavailable: {}", message)
   }
 }
 
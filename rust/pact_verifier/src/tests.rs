@@ -1,5 +1,6 @@
 use std::panic::catch_unwind;
 use std::sync::Arc;
+use std::time::Duration;
 
 use env_logger::*;
 use expectest::expect;
@@ -8,17 +9,22 @@ use maplit::*;
 use serde_json::json;
 
 use pact_consumer::*;
+use pact_consumer::builders::InteractionBuilder;
 use pact_consumer::prelude::*;
 use pact_matching::models::*;
+use pact_matching::models::http_utils::HttpAuth;
 use pact_matching::models::provider_states::*;
+use pact_matching::models::v4::{SynchronousHttp, V4Pact};
 use pact_matching::s;
-use pact_models::Consumer;
+use pact_models::{Consumer, OptionalBody};
 
-use crate::callback_executors::HttpRequestProviderStateExecutor;
+use crate::callback_executors::{HttpRequestProviderStateExecutor, NullRequestFilterExecutor};
 use crate::pact_broker::Link;
-use crate::PactSource;
+use crate::{MismatchResult, PactSource, ProviderInfo, VerificationFailure, VerificationOptions};
+use crate::messages::verify_message_from_provider;
+use crate::provider_client::{make_provider_request, provider_client_error_to_string};
 
-use super::{execute_state_change, filter_consumers, filter_interaction, FilterInfo};
+use super::{execute_state_change, filter_consumers, filter_interaction, FilterInfo, pact_url_source};
 
 #[test]
 fn if_no_interaction_filter_is_defined_returns_true() {
@@ -98,6 +104,30 @@ fn if_the_state_filter_and_interaction_filter_is_defined_is_false_if_both_do_not
   expect!(filter_interaction(&interaction, &FilterInfo::DescriptionAndState(s!(".*ddy"), s!("bob.*")))).to(be_false());
 }
 
+#[test]
+fn interaction_type_filter_matches_the_v4_type_of_the_interaction() {
+  let filter = FilterInfo::Filters { description: None, state: None, interaction_type: Some(s!("asynchronous/messages")) };
+  expect!(filter_interaction(&message::Message::default(), &filter)).to(be_true());
+  expect!(filter_interaction(&v4::AsynchronousMessage::default(), &filter)).to(be_true());
+  expect!(filter_interaction(&RequestResponseInteraction::default(), &filter)).to(be_false());
+  expect!(filter_interaction(&v4::SynchronousMessages::default(), &filter)).to(be_false());
+
+  let filter = FilterInfo::Filters { description: Some(s!("^get")), state: Some(s!("")), interaction_type: Some(s!("Synchronous/HTTP")) };
+  let interaction = RequestResponseInteraction { description: s!("get a user"), .. RequestResponseInteraction::default() };
+  expect!(filter_interaction(&interaction, &filter)).to(be_true());
+  expect!(filter_interaction(&v4::SynchronousHttp { description: s!("get a user"), .. v4::SynchronousHttp::default() }, &filter)).to(be_true());
+  expect!(filter_interaction(&RequestResponseInteraction { description: s!("update a user"), .. interaction.clone() }, &filter)).to(be_false());
+  expect!(filter_interaction(&RequestResponseInteraction { provider_states: vec![ ProviderState::default(&s!("bob")) ], .. interaction }, &filter)).to(be_false());
+}
+
+#[test]
+fn filters_are_only_defined_if_any_interactions_can_be_filtered_out() {
+  expect!(FilterInfo::None.has_filters()).to(be_false());
+  expect!(FilterInfo::Filters { description: None, state: None, interaction_type: None }.has_filters()).to(be_false());
+  expect!(FilterInfo::State(s!("")).has_filters()).to(be_true());
+  expect!(FilterInfo::Filters { description: None, state: None, interaction_type: Some(s!("Synchronous/HTTP")) }.has_filters()).to(be_true());
+}
+
 #[test]
 fn if_no_consumer_filter_is_defined_returns_true() {
   let consumers = vec![];
@@ -307,3 +337,342 @@ async fn publish_successful_result_to_broker() {
   let source = PactSource::BrokerUrl("Test".to_string(), server.url().to_string(), None, links);
   super::publish_result(&vec![(Some("1".to_string()), None)], &source, &options).await;
 }
+
+#[tokio::test]
+async fn verify_pact_stops_once_the_failure_limit_is_reached() {
+  try_init().unwrap_or(());
+
+  let port = {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+  };
+  let provider = super::ProviderInfo { port: Some(port), .. super::ProviderInfo::default() };
+  let pact = RequestResponsePact {
+    interactions: vec![
+      RequestResponseInteraction { description: s!("one"), .. RequestResponseInteraction::default() },
+      RequestResponseInteraction { description: s!("two"), .. RequestResponseInteraction::default() },
+      RequestResponseInteraction { description: s!("three"), .. RequestResponseInteraction::default() }
+    ],
+    .. RequestResponsePact::default()
+  };
+  let options = super::VerificationOptions::<super::NullRequestFilterExecutor>::default();
+  let provider_state_executor = Arc::new(HttpRequestProviderStateExecutor::default());
+
+  let (errors, skipped) = super::verify_pact(&provider, &FilterInfo::None, Box::new(pact.clone()),
+    &options, &provider_state_executor, Some(2)).await;
+  expect!(errors.iter().filter(|result| result.error.is_some()).count()).to(be_equal_to(2));
+  expect!(skipped).to(be_equal_to(1));
+
+  let (errors, skipped) = super::verify_pact(&provider, &FilterInfo::None, Box::new(pact),
+    &options, &provider_state_executor, None).await;
+  expect!(errors.iter().filter(|result| result.error.is_some()).count()).to(be_equal_to(3));
+  expect!(skipped).to(be_equal_to(0));
+}
+
+#[tokio::test]
+async fn verify_pact_does_not_count_failures_of_pending_interactions() {
+  try_init().unwrap_or(());
+
+  let port = {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+  };
+  let provider = super::ProviderInfo { port: Some(port), .. super::ProviderInfo::default() };
+  let pact = V4Pact {
+    interactions: vec![
+      Box::new(SynchronousHttp { description: s!("one"), pending: true, .. SynchronousHttp::default() }),
+      Box::new(SynchronousHttp { description: s!("two"), pending: true, .. SynchronousHttp::default() }),
+      Box::new(SynchronousHttp { description: s!("three"), .. SynchronousHttp::default() })
+    ],
+    .. V4Pact::default()
+  };
+  let options = super::VerificationOptions::<super::NullRequestFilterExecutor>::default();
+  let provider_state_executor = Arc::new(HttpRequestProviderStateExecutor::default());
+
+  let (errors, skipped) = super::verify_pact(&provider, &FilterInfo::None, Box::new(pact),
+    &options, &provider_state_executor, Some(1)).await;
+  expect!(errors.iter().map(|result| (result.error.is_some(), result.pending)).collect::<Vec<_>>())
+    .to(be_equal_to(vec![(true, true), (true, true), (true, false)]));
+  expect!(skipped).to(be_equal_to(0));
+}
+
+#[tokio::test]
+async fn verify_pact_returns_the_comments_of_the_interaction_with_its_failures() {
+  try_init().unwrap_or(());
+
+  let port = {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+  };
+  let provider = super::ProviderInfo { port: Some(port), .. super::ProviderInfo::default() };
+  let mut interaction = InteractionBuilder::new("a request for a user");
+  interaction
+    .comment("the user must exist")
+    .comment("the user must be active")
+    .test_name("user_test");
+  let pact = V4Pact {
+    interactions: vec![ Box::new(interaction.build_v4()) ],
+    .. V4Pact::default()
+  };
+  let options = super::VerificationOptions::<super::NullRequestFilterExecutor>::default();
+  let provider_state_executor = Arc::new(HttpRequestProviderStateExecutor::default());
+
+  let (errors, _) = super::verify_pact(&provider, &FilterInfo::None, Box::new(pact),
+    &options, &provider_state_executor, None).await;
+  expect!(errors.iter().map(|result| result.comments.clone()).collect::<Vec<_>>())
+    .to(be_equal_to(vec![vec![
+      s!("Test Name: user_test"),
+      s!("Comment: the user must exist"),
+      s!("Comment: the user must be active")
+    ]]));
+}
+
+#[tokio::test]
+async fn make_provider_request_fails_if_the_provider_does_not_respond_within_the_interaction_timeout() {
+  try_init().unwrap_or(());
+
+  let mut interaction = InteractionBuilder::new("a slow request");
+  interaction.timeout(Duration::from_millis(500));
+  interaction.request.path("/slow");
+  let pact = V4Pact {
+    interactions: vec![ Box::new(interaction.build_v4()) ],
+    .. V4Pact::default()
+  };
+  let server = ValidatingMockServer::start_with_config(pact.boxed(), MockServerConfig {
+    simulate_slow_responses: true,
+    .. MockServerConfig::default()
+  });
+
+  let provider = ProviderInfo {
+    port: server.url().port(),
+    .. ProviderInfo::default()
+  };
+  let request = Request { path: s!("/slow"), .. Request::default() };
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions::default();
+  let client = reqwest::Client::new();
+
+  let result = make_provider_request(&provider, &request, &options, &client,
+    Some(Duration::from_millis(100))).await;
+  expect!(result.map_err(provider_client_error_to_string)).to(be_err().value(
+    "Invalid response: Provider did not respond within the interaction timeout of 100ms".to_string()));
+
+  let result = make_provider_request(&provider, &request, &options, &client,
+    Some(Duration::from_secs(5))).await;
+  expect!(result.map(|response| response.status)).to(be_ok().value(200));
+}
+
+#[tokio::test]
+async fn make_provider_request_retries_requests_to_an_unavailable_provider() {
+  try_init().unwrap_or(());
+
+  let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+  let provider = ProviderInfo {
+    host: s!("127.0.0.1"),
+    port: Some(port),
+    .. ProviderInfo::default()
+  };
+  let request = Request::default();
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions {
+    provider_retries: 2,
+    provider_retry_delay: 10,
+    .. VerificationOptions::default()
+  };
+  let client = reqwest::Client::new();
+
+  let result = make_provider_request(&provider, &request, &options, &client, None).await
+    .map_err(provider_client_error_to_string);
+  expect!(result.as_ref().map(|_| ())).to(be_err());
+  expect!(result.unwrap_err().starts_with(&format!(
+    "Provider unavailable: Could not connect to the provider at http://127.0.0.1:{}/ after 3 attempt(s)", port)))
+    .to(be_true());
+}
+
+#[tokio::test]
+async fn verify_response_from_provider_reports_a_503_response_as_the_provider_being_unavailable() {
+  try_init().unwrap_or(());
+
+  let server = PactBuilder::new("RetryConsumer", "RetryProvider")
+    .interaction("a request while the provider is starting", |i| {
+      i.request.path("/orders");
+      i.response.status(503);
+    })
+    .start_mock_server();
+
+  let provider = ProviderInfo {
+    port: server.url().port(),
+    .. ProviderInfo::default()
+  };
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions {
+    provider_retries: 1,
+    provider_retry_delay: 10,
+    .. VerificationOptions::default()
+  };
+  let client = reqwest::Client::new();
+
+  let interaction = RequestResponseInteraction {
+    request: Request { path: s!("/orders"), .. Request::default() },
+    .. RequestResponseInteraction::default()
+  };
+  let result = super::verify_response_from_provider(&provider, &interaction, &options, &client,
+    &hashmap!{}, None).await;
+  match &result {
+    Err(MismatchResult::Error(message, _)) => {
+      expect!(message.as_str())
+        .to(be_equal_to("Provider unavailable: The provider responded with a 503 (Service Unavailable) status"));
+    },
+    _ => panic!("Expected a provider unavailable error")
+  }
+
+  let interaction = RequestResponseInteraction {
+    response: Response { status: 503, .. Response::default() },
+    .. interaction
+  };
+  let result = super::verify_response_from_provider(&provider, &interaction, &options, &client,
+    &hashmap!{}, None).await;
+  expect!(result.map_err(|_| ())).to(be_ok());
+}
+
+#[test]
+fn verification_options_reject_unexpected_body_keys_for_strict_interactions() {
+  use pact_matching::DiffConfig;
+
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions::default();
+  expect!(options.body_config_for("get a user")).to(be_equal_to(DiffConfig::AllowUnexpectedKeys));
+
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions {
+    strict_body_interactions: hashset!{ "get a user".to_string() },
+    .. VerificationOptions::default()
+  };
+  expect!(options.body_config_for("get a user")).to(be_equal_to(DiffConfig::NoUnexpectedKeys));
+  expect!(options.body_config_for("get an order")).to(be_equal_to(DiffConfig::AllowUnexpectedKeys));
+
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions {
+    strict_bodies: true,
+    .. VerificationOptions::default()
+  };
+  expect!(options.body_config_for("get an order")).to(be_equal_to(DiffConfig::NoUnexpectedKeys));
+}
+
+fn order_message(contents: serde_json::Value, topic: &str) -> message::Message {
+  message::Message {
+    description: s!("an order created event"),
+    contents: OptionalBody::Present(contents.to_string().into(), Some("application/json".into())),
+    metadata: hashmap!{ s!("topic") => topic.to_string() },
+    .. message::Message::default()
+  }
+}
+
+#[tokio::test]
+async fn verify_message_from_provider_uses_the_message_provider_callback() {
+  let interaction = order_message(json!({ "id": 100 }), "orders").boxed();
+  let provider = ProviderInfo::default();
+  let client = reqwest::Client::new();
+
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions {
+    message_provider: Some(Arc::new(|description: &str, _: &[ProviderState]| {
+      expect!(description).to(be_equal_to("an order created event"));
+      Ok(order_message(json!({ "id": 100 }), "orders"))
+    })),
+    .. VerificationOptions::default()
+  };
+  let result = verify_message_from_provider(&provider, &interaction, &options, &client, &hashmap!{}, None).await;
+  expect!(result).to(be_ok());
+
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions {
+    message_provider: Some(Arc::new(|_: &str, _: &[ProviderState]| Ok(order_message(json!({ "id": 100 }), "invoices")))),
+    .. VerificationOptions::default()
+  };
+  let result = verify_message_from_provider(&provider, &interaction, &options, &client, &hashmap!{}, None).await;
+  match &result {
+    Err(MismatchResult::Mismatches { mismatches, .. }) => {
+      expect!(mismatches.len()).to(be_equal_to(1));
+    },
+    _ => panic!("Expected a mismatch result, got {:?}", result)
+  }
+
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions {
+    message_provider: Some(Arc::new(|_: &str, _: &[ProviderState]| Err(anyhow::anyhow!("no such event")))),
+    .. VerificationOptions::default()
+  };
+  let result = verify_message_from_provider(&provider, &interaction, &options, &client, &hashmap!{}, None).await;
+  expect!(result.map_err(|err| format!("{:?}", err))).to(be_err().value(
+    "MismatchResult::Error(\"Message provider failed to produce a message - no such event\", None)".to_string()));
+}
+
+#[tokio::test]
+async fn verify_message_from_provider_reads_the_metadata_from_the_provider_response() {
+  try_init().unwrap_or(());
+
+  let server = PactBuilder::new("RustPactVerifier", "MessageProvider")
+    .interaction("an order created event", |i| {
+      i.request.method("POST");
+      i.request.path("/");
+      i.response
+        .header("Content-Type", "application/json")
+        .header("Pact-Message-Metadata", base64::encode(json!({ "topic": "orders" }).to_string()))
+        .json_body(json_pattern!({ "id": 100 }));
+    })
+    .start_mock_server();
+  let provider = ProviderInfo { port: server.url().port(), .. ProviderInfo::default() };
+  let options: VerificationOptions<NullRequestFilterExecutor> = VerificationOptions::default();
+  let client = reqwest::Client::new();
+
+  let interaction = order_message(json!({ "id": 100 }), "orders").boxed();
+  let result = verify_message_from_provider(&provider, &interaction, &options, &client, &hashmap!{}, None).await;
+  expect!(result).to(be_ok());
+}
+
+#[test]
+fn pact_url_source_uses_the_pact_broker_links_of_the_pact_to_publish_the_results() {
+  let pact_json = json!({
+    "consumer": { "name": "Consumer" },
+    "provider": { "name": "Provider" },
+    "interactions": [],
+    "_links": {
+      "pb:publish-verification-results": {
+        "href": "https://broker.example.com/pacts/provider/Provider/consumer/Consumer/pact-version/1234/verification-results"
+      }
+    }
+  });
+  let auth = Some(HttpAuth::Token("1234".to_string()));
+  match pact_url_source("https://broker.example.com/pacts/provider/Provider/consumer/Consumer/version/1.0.0", &auth, &pact_json) {
+    Some(PactSource::BrokerUrl(provider_name, broker_url, auth, links)) => {
+      expect!(provider_name).to(be_equal_to("Provider"));
+      expect!(broker_url).to(be_equal_to("https://broker.example.com"));
+      expect!(auth.map(|auth| auth.to_string())).to(be_some().value("Token(1234)"));
+      expect!(links.iter().map(|link| link.name.clone()).collect::<Vec<_>>())
+        .to(be_equal_to(vec!["pb:publish-verification-results".to_string()]));
+    },
+    source => panic!("Expected a Pact Broker source, got {:?}", source)
+  }
+
+  expect!(pact_url_source("https://example.com/pact.json", &None, &json!({ "consumer": { "name": "Consumer" } })).is_none())
+    .to(be_true());
+}
+
+#[test]
+fn verification_failures_serialise_the_mismatches_or_error_of_the_result() {
+  let result = MismatchResult::Mismatches {
+    mismatches: vec![ pact_matching::Mismatch::StatusMismatch { expected: 200, actual: 404 } ],
+    expected: Box::new(RequestResponseInteraction::default()),
+    actual: Box::new(RequestResponseInteraction::default()),
+    interaction_id: Some(s!("1234"))
+  };
+  let failure = VerificationFailure::new("interaction", &result, &[s!("comment")]);
+  expect!(serde_json::to_value(&failure).unwrap()).to(be_equal_to(json!({
+    "description": "interaction",
+    "interactionId": "1234",
+    "error": null,
+    "mismatches": [ { "type": "StatusMismatch", "expected": 200, "actual": 404 } ],
+    "comments": [ "comment" ]
+  })));
+
+  let failure = VerificationFailure::new("interaction", &MismatchResult::Error(s!("failed"), None), &[]);
+  expect!(serde_json::to_value(&failure).unwrap()).to(be_equal_to(json!({
+    "description": "interaction",
+    "interactionId": null,
+    "error": "failed",
+    "mismatches": [],
+    "comments": []
+  })));
+}
@@ -0,0 +1,250 @@
+//! Transports used to verify interactions against the provider. V4 interactions can specify the
+//! transport to use with their `transport` attribute, otherwise the first transport that can
+//! verify the type of interaction is used.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use pact_matching::models::Interaction;
+use pact_matching::models::v4::websocket::is_websocket_interaction;
+
+use crate::{MismatchResult, ProviderInfo, VerificationOptions, verify_response_from_provider};
+use crate::callback_executors::RequestFilterExecutor;
+use crate::grpc::verify_sync_message_from_provider;
+use crate::messages::verify_message_from_provider;
+use crate::websocket::verify_websocket_from_provider;
+
+/// Transport used to verify interactions against the provider
+#[async_trait(?Send)]
+pub trait Transport<F: RequestFilterExecutor> {
+  /// Name of the transport, as used by the `transport` attribute of V4 interactions
+  fn name(&self) -> &str;
+
+  /// If this transport can verify the interaction
+  fn supports(&self, interaction: &dyn Interaction) -> bool;
+
+  /// Verifies the interaction against the provider, returning the ID of the interaction if it
+  /// was successful
+  async fn verify(
+    &self,
+    provider: &ProviderInfo,
+    interaction: &dyn Interaction,
+    options: &VerificationOptions<F>,
+    client: &reqwest::Client,
+    context: &HashMap<&str, Value>
+  ) -> Result<Option<String>, MismatchResult>;
+}
+
+/// Transport specified by the `transport` attribute of a V4 interaction
+fn interaction_transport(interaction: &dyn Interaction) -> Option<String> {
+  interaction.as_v4().and_then(|interaction| interaction.transport())
+}
+
+/// Sends the request of the interaction to the provider, and matches the response. If the
+/// interaction specifies the transport, the scheme of the transport is used instead of the
+/// configured protocol of the provider.
+struct HttpTransport {
+  scheme: &'static str
+}
+
+#[async_trait(?Send)]
+impl <F: RequestFilterExecutor> Transport<F> for HttpTransport {
+  fn name(&self) -> &str {
+    self.scheme
+  }
+
+  fn supports(&self, interaction: &dyn Interaction) -> bool {
+    interaction.is_request_response()
+  }
+
+  async fn verify(
+    &self,
+    provider: &ProviderInfo,
+    interaction: &dyn Interaction,
+    options: &VerificationOptions<F>,
+    client: &reqwest::Client,
+    context: &HashMap<&str, Value>
+  ) -> Result<Option<String>, MismatchResult> {
+    let provider = if interaction_transport(interaction).is_some() {
+      ProviderInfo { protocol: self.scheme.to_string(), .. provider.clone() }
+    } else {
+      provider.clone()
+    };
+    match interaction.as_request_response() {
+      Some(request_response) => verify_response_from_provider(&provider, &request_response, options, client,
+        context, interaction.timeout()).await,
+      None => Err(MismatchResult::Error(format!("The {} transport can only verify HTTP interactions", self.scheme),
+        interaction.id()))
+    }
+  }
+}
+
+/// Produces the message with the message provider callback, or fetches it from the provider
+struct MessageTransport;
+
+#[async_trait(?Send)]
+impl <F: RequestFilterExecutor> Transport<F> for MessageTransport {
+  fn name(&self) -> &str {
+    "message"
+  }
+
+  fn supports(&self, interaction: &dyn Interaction) -> bool {
+    interaction.is_message()
+  }
+
+  async fn verify(
+    &self,
+    provider: &ProviderInfo,
+    interaction: &dyn Interaction,
+    options: &VerificationOptions<F>,
+    client: &reqwest::Client,
+    context: &HashMap<&str, Value>
+  ) -> Result<Option<String>, MismatchResult> {
+    verify_message_from_provider(provider, &interaction.boxed(), options, client, context,
+      interaction.timeout()).await
+  }
+}
+
+/// Invokes the gRPC method of the provider for synchronous message interactions
+struct GrpcTransport;
+
+#[async_trait(?Send)]
+impl <F: RequestFilterExecutor> Transport<F> for GrpcTransport {
+  fn name(&self) -> &str {
+    "grpc"
+  }
+
+  fn supports(&self, interaction: &dyn Interaction) -> bool {
+    interaction.as_v4_sync_message().is_some()
+  }
+
+  async fn verify(
+    &self,
+    provider: &ProviderInfo,
+    interaction: &dyn Interaction,
+    options: &VerificationOptions<F>,
+    _: &reqwest::Client,
+    context: &HashMap<&str, Value>
+  ) -> Result<Option<String>, MismatchResult> {
+    match interaction.as_v4_sync_message() {
+      Some(message) => verify_sync_message_from_provider(provider, &message, options.disable_ssl_verification,
+        context, interaction.timeout()).await,
+      None => Err(MismatchResult::Error("The grpc transport can only verify synchronous message interactions".to_string(),
+        interaction.id()))
+    }
+  }
+}
+
+/// Connects to the WebSocket endpoint of the provider for WebSocket interactions
+struct WebSocketTransport;
+
+#[async_trait(?Send)]
+impl <F: RequestFilterExecutor> Transport<F> for WebSocketTransport {
+  fn name(&self) -> &str {
+    "websocket"
+  }
+
+  fn supports(&self, interaction: &dyn Interaction) -> bool {
+    is_websocket_interaction(interaction)
+  }
+
+  async fn verify(
+    &self,
+    provider: &ProviderInfo,
+    interaction: &dyn Interaction,
+    _: &VerificationOptions<F>,
+    _: &reqwest::Client,
+    context: &HashMap<&str, Value>
+  ) -> Result<Option<String>, MismatchResult> {
+    match interaction.as_v4_sync_message() {
+      Some(message) => verify_websocket_from_provider(provider, &message, context, interaction.timeout()).await,
+      None => Err(MismatchResult::Error("The websocket transport can only verify synchronous message interactions".to_string(),
+        interaction.id()))
+    }
+  }
+}
+
+/// The transports available to verify interactions, in the order they are selected by default
+pub fn default_transports<F: RequestFilterExecutor>() -> Vec<Box<dyn Transport<F>>> {
+  vec![
+    Box::new(HttpTransport { scheme: "http" }),
+    Box::new(HttpTransport { scheme: "https" }),
+    Box::new(MessageTransport),
+    Box::new(GrpcTransport),
+    Box::new(WebSocketTransport)
+  ]
+}
+
+/// Selects the transport to verify the interaction with. This is the transport named by the
+/// `transport` attribute of V4 interactions, otherwise the first transport that supports the
+/// interaction.
+pub fn transport_for<'a, F: RequestFilterExecutor>(
+  transports: &'a [Box<dyn Transport<F>>],
+  interaction: &dyn Interaction
+) -> Result<&'a dyn Transport<F>, MismatchResult> {
+  match interaction_transport(interaction) {
+    Some(name) => match transports.iter().find(|transport| transport.name().eq_ignore_ascii_case(&name)) {
+      Some(transport) => if transport.supports(interaction) {
+        Ok(transport.as_ref())
+      } else {
+        Err(MismatchResult::Error(format!("The {} transport can not verify {} interactions", name,
+          interaction.type_of()), interaction.id()))
+      },
+      None => Err(MismatchResult::Error(format!("There is no '{}' transport to verify the interaction with", name),
+        interaction.id()))
+    },
+    None => transports.iter()
+      .find(|transport| transport.supports(interaction))
+      .map(|transport| transport.as_ref())
+      .ok_or_else(|| MismatchResult::Error(format!("There is no transport that can verify {} interactions",
+        interaction.type_of()), interaction.id()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+
+  use pact_matching::models::message::Message;
+  use pact_matching::models::RequestResponseInteraction;
+  use pact_matching::models::v4::{SynchronousHttp, SynchronousMessages};
+
+  use crate::NullRequestFilterExecutor;
+
+  use super::*;
+
+  fn selected(interaction: &dyn Interaction) -> Result<String, String> {
+    let transports = default_transports::<NullRequestFilterExecutor>();
+    transport_for(&transports, interaction)
+      .map(|transport| transport.name().to_string())
+      .map_err(|err| match err {
+        MismatchResult::Error(message, _) => message,
+        _ => panic!("Expected an error")
+      })
+  }
+
+  #[test]
+  fn transport_for_selects_the_default_transport_for_the_type_of_interaction() {
+    expect!(selected(&RequestResponseInteraction::default())).to(be_ok().value("http"));
+    expect!(selected(&SynchronousHttp::default())).to(be_ok().value("http"));
+    expect!(selected(&Message::default())).to(be_ok().value("message"));
+    expect!(selected(&SynchronousMessages::default())).to(be_ok().value("grpc"));
+  }
+
+  #[test]
+  fn transport_for_selects_the_transport_of_v4_interactions() {
+    let interaction = SynchronousHttp { transport: Some("HTTPS".to_string()), .. SynchronousHttp::default() };
+    expect!(selected(&interaction)).to(be_ok().value("https"));
+
+    let interaction = SynchronousHttp { transport: Some("grpc".to_string()), .. SynchronousHttp::default() };
+    expect!(selected(&interaction)).to(be_err().value("The grpc transport can not verify V4 Synchronous/HTTP interactions"));
+
+    let interaction = SynchronousMessages { transport: Some("websocket".to_string()), .. SynchronousMessages::default() };
+    expect!(selected(&interaction)).to(be_ok().value("websocket"));
+
+    let interaction = SynchronousMessages { transport: Some("kafka".to_string()), .. SynchronousMessages::default() };
+    expect!(selected(&interaction)).to(be_err().value("There is no 'kafka' transport to verify the interaction with"));
+  }
+}
@@ -0,0 +1,224 @@
+//! Verification of WebSocket interactions against the provider
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use http::header::{HeaderName, HeaderValue};
+use log::*;
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message as Frame;
+
+use pact_matching::{generate_message_contents, generate_request, match_sync_message_response};
+use pact_matching::models::build_query_string;
+use pact_matching::models::generators::GeneratorTestMode;
+use pact_matching::models::v4::message_parts::MessageContents;
+use pact_matching::models::v4::SynchronousMessages;
+use pact_matching::models::v4::websocket::{FrameType, frame_type, received_frame, sends_request_frame, upgrade_request};
+use pact_matching::models::Interaction;
+
+use crate::MismatchResult;
+use crate::ProviderInfo;
+use crate::with_pact_plugins;
+use crate::provider_client::join_paths;
+
+/// Time to wait for each message from the provider if the interaction does not have a timeout
+const DEFAULT_MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Headers of the upgrade request that are set by the WebSocket client
+const HANDSHAKE_HEADERS: [&str; 5] = ["host", "connection", "upgrade", "sec-websocket-key", "sec-websocket-version"];
+
+/// URL of the WebSocket endpoint of the provider for the upgrade request path and query
+fn websocket_url(provider: &ProviderInfo, path: &str, query: &Option<HashMap<String, Vec<String>>>) -> String {
+  let scheme = if provider.protocol.eq_ignore_ascii_case("https") { "wss" } else { "ws" };
+  let base_url = match provider.port {
+    Some(port) => format!("{}://{}:{}{}", scheme, provider.host, port, provider.path),
+    None => format!("{}://{}{}", scheme, provider.host, provider.path),
+  };
+  let url = join_paths(&base_url, path.to_string());
+  match query {
+    Some(query) if !query.is_empty() => format!("{}?{}", url, build_query_string(query.clone())),
+    _ => url
+  }
+}
+
+fn to_frame(message: &MessageContents) -> Frame {
+  let data = message.contents.value().unwrap_or_default();
+  match frame_type(message) {
+    FrameType::Text => Frame::Text(String::from_utf8_lossy(&data).to_string()),
+    FrameType::Binary => Frame::Binary(data.to_vec())
+  }
+}
+
+/// Verifies a WebSocket interaction by upgrading a connection to the provider with the upgrade
+/// request of the interaction, sending the request message (if it has contents) and matching the
+/// messages the provider sends back against the response messages of the interaction.
+///
+/// The provider is expected to send the response messages in order. Waiting for them stops once
+/// the expected number of messages has been received, the provider closes the connection or the
+/// timeout of the interaction (5 seconds by default) passes without a message being received.
+pub async fn verify_websocket_from_provider(
+  provider: &ProviderInfo,
+  interaction: &SynchronousMessages,
+  context: &HashMap<&str, Value>,
+  timeout: Option<Duration>
+) -> Result<Option<String>, MismatchResult> {
+  let upgrade = upgrade_request(interaction)
+    .ok_or_else(|| MismatchResult::Error("The interaction is not a WebSocket interaction".to_string(),
+      interaction.id.clone()))?;
+  let upgrade = with_pact_plugins(|| generate_request(&upgrade.as_v3_request(), &GeneratorTestMode::Provider, context));
+  let error = |message: String| MismatchResult::Error(message, interaction.id.clone());
+
+  let url = websocket_url(provider, &upgrade.path, &upgrade.query);
+  info!("Connecting to the WebSocket endpoint of the provider at {}", url);
+  let mut client_request = url.as_str().into_client_request()
+    .map_err(|err| error(format!("Invalid WebSocket URL '{}' - {}", url, err)))?;
+  for (name, values) in upgrade.headers.clone().unwrap_or_default() {
+    if !HANDSHAKE_HEADERS.contains(&name.to_lowercase().as_str()) {
+      let header = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|err| error(format!("Invalid header name '{}' - {}", name, err)))?;
+      let value = HeaderValue::from_str(&values.join(", "))
+        .map_err(|err| error(format!("Invalid value for header '{}' - {}", name, err)))?;
+      client_request.headers_mut().insert(header, value);
+    }
+  }
+
+  let timeout = timeout.unwrap_or(DEFAULT_MESSAGE_TIMEOUT);
+  let (mut socket, _) = tokio::time::timeout(timeout, tokio_tungstenite::connect_async(client_request)).await
+    .map_err(|_| error(format!("Timed out connecting to the WebSocket endpoint at {}", url)))?
+    .map_err(|err| error(format!("Failed to connect to the WebSocket endpoint at {} - {}", url, err)))?;
+
+  if sends_request_frame(interaction) {
+    let request = with_pact_plugins(|| generate_message_contents(&interaction.request, &GeneratorTestMode::Provider, context));
+    debug!("Sending WebSocket message {}", request.contents.str_value());
+    socket.send(to_frame(&request)).await
+      .map_err(|err| error(format!("Failed to send the WebSocket message - {}", err)))?;
+  }
+
+  let mut actual_responses = vec![];
+  while actual_responses.len() < interaction.response.len() {
+    match tokio::time::timeout(timeout, socket.next()).await {
+      Ok(Some(Ok(Frame::Text(text)))) => actual_responses.push(text.into_bytes()),
+      Ok(Some(Ok(Frame::Binary(data)))) => actual_responses.push(data),
+      Ok(Some(Ok(Frame::Close(_)))) | Ok(None) => break,
+      Ok(Some(Ok(_))) => (),
+      Ok(Some(Err(err))) => return Err(error(format!("Failed to read the WebSocket message - {}", err))),
+      Err(_) => {
+        warn!("Timed out waiting for a WebSocket message from the provider after {:?}", timeout);
+        break;
+      }
+    }
+  }
+  if let Err(err) = socket.close(None).await {
+    debug!("Failed to close the WebSocket connection - {}", err);
+  }
+  debug!("Received {} WebSocket messages", actual_responses.len());
+
+  let actual_responses: Vec<MessageContents> = actual_responses.into_iter().enumerate()
+    .map(|(index, data)| received_frame(data.into(), interaction.response.get(index)))
+    .collect();
+  let mismatches = with_pact_plugins(|| match_sync_message_response(interaction, &actual_responses));
+  if mismatches.is_empty() {
+    Ok(interaction.id.clone())
+  } else {
+    Err(MismatchResult::Mismatches {
+      mismatches,
+      expected: interaction.boxed(),
+      actual: SynchronousMessages { response: actual_responses, .. SynchronousMessages::default() }.boxed(),
+      interaction_id: interaction.id.clone()
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use expectest::prelude::*;
+  use maplit::*;
+
+  use pact_matching::models::v4::http_parts::HttpRequest;
+  use pact_matching::models::v4::websocket::set_upgrade_request;
+  use pact_models::OptionalBody;
+  use tokio::net::TcpListener;
+
+  use super::*;
+
+  fn message(body: &str) -> MessageContents {
+    MessageContents {
+      contents: OptionalBody::Present(body.to_string().into(), Some("application/json".into())),
+      .. MessageContents::default()
+    }
+  }
+
+  /// Provider that replies to the first message with the given messages
+  async fn start_provider(replies: Vec<&'static str>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+      let (stream, _) = listener.accept().await.unwrap();
+      let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+      if let Some(Ok(_)) = socket.next().await {
+        for reply in replies {
+          socket.send(Frame::Text(reply.to_string())).await.unwrap();
+        }
+      }
+      while let Some(Ok(frame)) = socket.next().await {
+        if frame.is_close() {
+          break;
+        }
+      }
+    });
+    port
+  }
+
+  fn websocket_interaction() -> SynchronousMessages {
+    let mut interaction = SynchronousMessages {
+      request: message(r#"{"subscribe":"AAPL"}"#),
+      response: vec![message(r#"{"price":100}"#), message(r#"{"price":101}"#)],
+      .. SynchronousMessages::default()
+    };
+    set_upgrade_request(&mut interaction, &HttpRequest { path: "/prices".to_string(), .. HttpRequest::default() });
+    interaction
+  }
+
+  #[tokio::test]
+  async fn verify_websocket_from_provider_matches_the_messages_from_the_provider() {
+    let port = start_provider(vec![r#"{"price":100}"#, r#"{"price":101}"#]).await;
+    let provider = ProviderInfo { port: Some(port), .. ProviderInfo::default() };
+    let result = verify_websocket_from_provider(&provider, &websocket_interaction(), &hashmap!{},
+      Some(Duration::from_secs(2))).await;
+    expect!(result).to(be_ok());
+  }
+
+  #[tokio::test]
+  async fn verify_websocket_from_provider_returns_mismatches_for_missing_messages() {
+    let port = start_provider(vec![r#"{"price":100}"#]).await;
+    let provider = ProviderInfo { port: Some(port), .. ProviderInfo::default() };
+    let result = verify_websocket_from_provider(&provider, &websocket_interaction(), &hashmap!{},
+      Some(Duration::from_millis(200))).await;
+    match result {
+      Err(MismatchResult::Mismatches { mismatches, .. }) => {
+        expect!(mismatches.iter().map(|m| m.description()).collect::<Vec<_>>()).to(be_equal_to(vec![
+          "$ -> Expected 2 response messages but received 1".to_string()
+        ]));
+      },
+      _ => panic!("Expected mismatches, got {:?}", result)
+    }
+  }
+
+  #[test]
+  fn websocket_url_uses_the_scheme_of_the_provider() {
+    let provider = ProviderInfo {
+      host: "localhost".to_string(),
+      port: Some(8080),
+      path: "/api".to_string(),
+      .. ProviderInfo::default()
+    };
+    expect!(websocket_url(&provider, "/prices", &None)).to(be_equal_to("ws://localhost:8080/api/prices"));
+    expect!(websocket_url(&provider, "/prices", &Some(hashmap!{ "symbol".to_string() => vec!["AAPL".to_string()] })))
+      .to(be_equal_to("ws://localhost:8080/api/prices?symbol=AAPL"));
+
+    let provider = ProviderInfo { protocol: "https".to_string(), port: None, path: "".to_string(), .. provider };
+    expect!(websocket_url(&provider, "/prices", &None)).to(be_equal_to("wss://localhost/prices"));
+  }
+}
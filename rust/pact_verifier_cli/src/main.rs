@@ -15,20 +15,26 @@
 //! Standalone Pact verifier
 //!
 //! USAGE:
-//!     pact_verifier_cli [FLAGS] [OPTIONS] --broker-url <broker-url>... --dir <dir>... --file <file>... --provider-name <provider-name> --url <url>...
+//!     pact_verifier_cli [FLAGS] [OPTIONS] --broker-url <broker-url>... --dir <dir>... --file <file>... --glob <glob>... --listing-url <listing-url>... --provider-name <provider-name> --url <url>...
 //!
 //! FLAGS:
+//!         --disable-ssl-verification Disables validation of SSL certificates
 //!         --enable-pending           Enables Pending Pacts
+//!         --fail-fast                Stops the verification after the first failing interaction
 //!         --filter-no-state          Only validate interactions that have no defined provider state
 //!         --help                     Prints help information
 //!         --publish                  Enables publishing of verification results back to the Pact Broker. Requires the
-//!                                    broker-url and provider-version parameters.
+//!                                    broker-url (or url) and provider-version parameters.
 //!         --state-change-as-query    State change request data will be sent as query parameters instead of in the request
 //!                                    body
 //!         --state-change-teardown    State change teardown requests are to be made after each interaction
+//!         --strict-bodies            Fail the verification if the response bodies have any keys that are not in the pact
 //!     -v, --version                  Prints version information
 //!
 //! OPTIONS:
+//!         --auth-header <auth-header>...
+//!             Static header (NAME: VALUE) to add to the requests when fetching pacts from URLS (can be repeated)
+//!
 //!         --base-path <base-path>                                Base path to add to all requests
 //!     -b, --broker-url <broker-url>...
 //!             URL of the pact broker to fetch pacts from to verify (requires the provider name parameter) [env:
@@ -36,6 +42,13 @@
 //!         --build-url <build-url>
 //!             URL of the build to associate with the published verification results.
 //!
+//!         --ca-cert <ca-cert>
+//!             PEM file with the root CA certificates to trust when connecting to the provider and the Pact Broker
+//!
+//!         --client-cert <client-cert>
+//!             PEM file with the client certificate to use when connecting to the provider and the Pact Broker (mutual TLS)
+//!
+//!         --client-key <client-key>                              PEM file with the private key of the client certificate
 //!         --consumer-version-tags <consumer-version-tags>
 //!             Consumer tags to use when fetching pacts from the Broker. Accepts comma-separated values.
 //!
@@ -44,16 +57,34 @@
 //!     -c, --filter-consumer <filter-consumer>...
 //!             Consumer name to filter the pacts to be verified (can be repeated)
 //!
+//!         --max-failures <max-failures>
+//!             Stops the verification after this number of interactions have failed
+//!
 //!         --filter-description <filter-description>
 //!             Only validate interactions whose descriptions match this filter
 //!
 //!         --filter-state <filter-state>
 //!             Only validate interactions whose provider states match this filter
 //!
+//!         --filter-interaction-type <filter-interaction-type>
+//!             Only validate interactions of this type [possible values: Synchronous/HTTP, Asynchronous/Messages,
+//!             Synchronous/Messages]
+//!
+//!         --generator-seed <generator-seed>
+//!             Seed for the random generators used for the requests to the provider, so the generated values are
+//!             reproducible
+//!
+//!     -g, --glob <glob>...
+//!             Glob pattern of pact files to verify, i.e. 'pacts/**/provider-*.json' (can be repeated)
+//!
 //!     -h, --hostname <hostname>                                  Provider hostname (defaults to localhost)
 //!         --include-wip-pacts-since <include-wip-pacts-since>
 //!             Allow pacts that don't match given consumer selectors (or tags) to  be verified, without causing the overall
 //!             task to fail. For more information, see https://pact.io/wip
+//!         --json <json-file>                                     Writes a JSON summary of the verification results to this file
+//!         --listing-url <listing-url>...
+//!             URL of a listing of pact files to verify, either an HTML directory index page or an S3 bucket listing (can
+//!             be repeated)
 //!     -l, --loglevel <loglevel>
 //!             Log level (defaults to warn) [possible values: error, warn, info, debug,
 //!             trace, none]
@@ -62,6 +93,13 @@
 //!
 //!     -p, --port <port>                                          Provider port (defaults to protocol default 80/443)
 //!     -n, --provider-name <provider-name>                        Provider name (defaults to provider)
+//!         --provider-retries <provider-retries>
+//!             Number of times to retry requests to the provider that fail to connect or get a 503 response (defaults to 0)
+//!
+//!         --provider-retry-delay <provider-retry-delay>
+//!             Delay in milliseconds before the first retry of a request to the provider, doubled for each retry (defaults to
+//!             1000)
+//!
 //!         --provider-tags <provider-tags>
 //!             Provider tags to use when publishing results. Accepts comma-separated values.
 //!
@@ -69,6 +107,9 @@
 //!             Provider version that is being verified. This is required when publishing results.
 //!
 //!     -s, --state-change-url <state-change-url>                  URL to post state change requests to
+//!         --strict-bodies-for <strict-bodies-for>...
+//!             Fail the verification if the response body of the interaction with this description has any keys that are
+//!             not in the pact (can be repeated)
 //!     -t, --token <token>
 //!             Bearer token to use when fetching pacts from URLS [env: PACT_BROKER_TOKEN=Dk8qO3_ZOqau8EeMaagK5w]
 //!
@@ -91,9 +132,27 @@
 //! |--------|------|-------------|
 //! | `-f, --file <file>` | File | Loads a pact from the given file |
 //! | `-u, --url <url>` | URL | Loads a pact from a URL resource |
-//! | `-d, --dir <dir>` | Directory | Loads all the pacts from the given directory |
+//! | `-d, --dir <dir>` | Directory | Loads all the pacts from the given directory and its sub-directories |
+//! | `-g, --glob <glob>` | Glob pattern | Loads all the pact files that match the glob pattern (i.e. `pacts/**/provider-*.json`) |
+//! | `--listing-url <listing-url>` | Listing URL | Loads all the pact files (files with a `.json` extension) linked to from an HTML directory index page, or in an S3 bucket listing (i.e. `https://bucket.s3.amazonaws.com/?list-type=2&prefix=pacts/`) |
 //! | `-b, --broker-url <broker-url>` | Pact Broker | Loads all the pacts for the provider from the pact broker. Requires the `-n, --provider-name <provider-name>` option |
 //!
+//! Pacts fetched from URLs, listing URLs and the Pact Broker can be authenticated with a username and password (`--user` and
+//! `--password`), a bearer token (`-t, --token`), or static headers such as an API key (`--auth-header 'X-Api-Key: 1234'`,
+//! which can be repeated). Only one kind of authentication can be used.
+//!
+//! ### Verifying a pact from a Pact Broker webhook
+//!
+//! A Pact Broker webhook can trigger a build of the provider to verify a changed pact, passing the URL of the pact (i.e.
+//! `${pactbroker.pactUrl}`). The pact can be verified with `-u, --url <url>` and the broker credentials. Pacts fetched from
+//! a Pact Broker have a `pb:publish-verification-results` link, so with `--publish` and `--provider-version` the
+//! verification results are published back to the Pact Broker with the link. Use `--build-url` to include the URL of the
+//! triggered build in the published results.
+//!
+//! ```console
+//! $ pact_verifier_cli -u "$PACT_URL" -t "$PACT_BROKER_TOKEN" --publish --provider-version 1.2.3 --build-url "$BUILD_URL" -p 8080
+//! ```
+//!
 //! ### Provider Options
 //!
 //! The running provider can be specified with the following options:
@@ -104,6 +163,17 @@
 //! | `-p, --port <port>` | The provider port (defaults to 8080) |
 //! | `-n, --provider-name <provider-name>` | The name of the provider. Required if you are loading pacts from a pact broker |
 //!
+//! ### TLS Options
+//!
+//! HTTPS connections to the provider and the Pact Broker can be configured with the following options:
+//!
+//! | Option | Description |
+//! |--------|-------------|
+//! | `--ca-cert <ca-cert>` | PEM file with root CA certificates to trust, in addition to the built-in ones (i.e. for an internal CA) |
+//! | `--client-cert <client-cert>` | PEM file with the client certificate to present for mutual TLS. It can also contain the private key |
+//! | `--client-key <client-key>` | PEM file with the private key of the client certificate, if it is not in the certificate file |
+//! | `--disable-ssl-verification` | Accepts invalid (i.e. self-signed or expired) certificates. Only use this in test environments |
+//!
 //! ### Filtering the interactions
 //!
 //! The interactions that are verified can be filtered by the following options:
@@ -124,6 +194,69 @@
 //!
 //! This option will filter the interactions that are verified that don't have a defined provider state. Can't be used with the `--filter-state` option.
 //!
+//! #### `--filter-interaction-type <filter-interaction-type>`
+//!
+//! This option will filter the interactions that are verified by the V4 interaction type (`Synchronous/HTTP`, `Asynchronous/Messages` or `Synchronous/Messages`). V3 request/response interactions have the `Synchronous/HTTP` type, and V3 messages have the `Asynchronous/Messages` type.
+//!
+//! *NOTE:* Verification results are not published to the Pact Broker when the interactions are filtered by description, provider state or interaction type, as the results would only be for some of the interactions.
+//!
+//! ### Stopping on failures
+//!
+//! Long verification runs can be cut short once interactions start failing. Any interactions not verified will be reported
+//! as skipped. Provider state teardown requests are still made for the interaction that caused the verification to stop.
+//!
+//! #### `--fail-fast`
+//!
+//! This option will stop the verification after the first interaction fails. Can't be used with the `--max-failures` option.
+//!
+//! #### `--max-failures <max-failures>`
+//!
+//! This option will stop the verification after the given number of interactions have failed. Failures from pending pacts
+//! and pending interactions are not counted. Can't be used with the `--fail-fast` option.
+//!
+//! V4 pacts can mark individual interactions as pending (with `"pending": true`). Their failures are reported as pending
+//! failures and do not fail the verification, in the same way as failures from pending pacts.
+//!
+//! ### Unexpected keys in response bodies
+//!
+//! By default, a provider can return response bodies with keys that are not in the pact, as consumers should ignore any
+//! fields they don't use. Some contracts need the responses to be validated strictly instead.
+//!
+//! #### `--strict-bodies`
+//!
+//! This option will fail the verification if any response body has keys that are not in the pact.
+//!
+//! #### `--strict-bodies-for <description>`
+//!
+//! This option only fails the verification for unexpected keys in the response body of the interaction with the given
+//! description. It can be repeated.
+//!
+//! ### Retrying requests to the provider
+//!
+//! A provider that is still starting up can refuse connections or respond with a 503 (Service Unavailable) status. By
+//! default, these interactions fail straight away. Interactions that fail because the provider could not be reached are
+//! reported as "Provider unavailable" errors instead of response mismatches.
+//!
+//! #### `--provider-retries <provider-retries>`
+//!
+//! This option will retry requests to the provider that fail to connect or get a 503 response up to the given number of
+//! times. Once the retries are used up, a 503 response is only reported as an error if the interaction does not expect it.
+//!
+//! #### `--provider-retry-delay <provider-retry-delay>`
+//!
+//! The delay in milliseconds before the first retry of a request to the provider. The delay is doubled for each subsequent
+//! retry. Defaults to 1000.
+//!
+//! ### Generated values
+//!
+//! The generators of the interactions (like `RandomInt`, `RandomString` and `Uuid`) generate new random values for every
+//! request made to the provider.
+//!
+//! #### `--generator-seed <generator-seed>`
+//!
+//! Seeds the random generators with the given number, so that the same values are generated for the requests each time
+//! the provider is verified. This is useful to reproduce a failing verification while debugging.
+//!
 //! ### State change requests
 //!
 //! Provider states are a mechanism to define the state that the provider needs to be in to be able to verify a particular request. This is achieved by setting a state change URL that will receive a POST request with the provider state before the actual request is made.
@@ -140,6 +273,47 @@
 //!
 //! This option will cause the verifier to also make a tear down request after the main request is made. It will receive a second field in the body or a query parameter named `action` with the value `teardown`.
 //!
+//! ## JSON Patch of body mismatches
+//!
+//! When the body of a JSON response does not match, the failures also include a [JSON Patch](https://tools.ietf.org/html/rfc6902)
+//! of the changes to the expected body that would make it match the actual one, i.e.
+//!
+//! ```console,ignore
+//!     JSON Patch to update the expected body:
+//!       [{"op":"replace","path":"/reply","value":"Goodbye"},{"op":"remove","path":"/friends/1"}]
+//! ```
+//!
+//! This can be used to update the pact if the change in the provider was intended. Values that are allowed to differ by
+//! the matching rules are not included.
+//!
+//! ## JSON summary of the results
+//!
+//! The `--json <json-file>` option writes a summary of the verification results to the given file, so other tools can
+//! process the results without having to parse the console output. The summary has the following form, where the
+//! mismatches are in the same form as the mismatches returned from the mock server:
+//!
+//! ```json,ignore
+//! {
+//!   "result": false,
+//!   "failures": [
+//!     {
+//!       "description": "Verifying a pact between Consumer and happy_provider - a request to be happy",
+//!       "interactionId": null,
+//!       "error": null,
+//!       "mismatches": [
+//!         { "type": "StatusMismatch", "expected": 200, "actual": 404 }
+//!       ],
+//!       "comments": []
+//!     }
+//!   ],
+//!   "pendingFailures": [],
+//!   "skipped": 0
+//! }
+//! ```
+//!
+//! Failures of pending pacts or interactions are in `pendingFailures`, and `skipped` is the number of interactions not
+//! verified because the maximum number of failures was reached.
+//!
 //! ## Example run
 //!
 //! This will verify all the pacts for the `happy_provider` found in the pact broker (running on localhost) against the provider running on localhost port 5050. Only the pacts for the consumers `Consumer` and `Consumer2` will be verified.
@@ -1,4 +1,4 @@
-use clap::{App, Arg};
+use clap::{App, Arg, ArgGroup};
 use regex::Regex;
 
 fn integer_value(v: String) -> Result<(), String> {
@@ -20,7 +20,7 @@ pub(crate) fn setup_app<'a, 'b>(program: String, version: &'b str) -> App<'a, 'b
     .arg(Arg::with_name("file")
       .short("f")
       .long("file")
-      .required_unless_one(&["dir", "url", "broker-url"])
+      .required_unless_one(&["dir", "glob", "url", "listing-url", "broker-url"])
       .takes_value(true)
       .use_delimiter(false)
       .multiple(true)
@@ -30,28 +30,47 @@ pub(crate) fn setup_app<'a, 'b>(program: String, version: &'b str) -> App<'a, 'b
     .arg(Arg::with_name("dir")
       .short("d")
       .long("dir")
-      .required_unless_one(&["file", "url", "broker-url"])
+      .required_unless_one(&["file", "glob", "url", "listing-url", "broker-url"])
       .takes_value(true)
       .use_delimiter(false)
       .multiple(true)
       .number_of_values(1)
       .empty_values(false)
       .help("Directory of pact files to verify (can be repeated)"))
+    .arg(Arg::with_name("glob")
+      .short("g")
+      .long("glob")
+      .required_unless_one(&["file", "dir", "url", "listing-url", "broker-url"])
+      .takes_value(true)
+      .use_delimiter(false)
+      .multiple(true)
+      .number_of_values(1)
+      .empty_values(false)
+      .help("Glob pattern of pact files to verify, i.e. 'pacts/**/provider-*.json' (can be repeated)"))
     .arg(Arg::with_name("url")
       .short("u")
       .long("url")
-      .required_unless_one(&["file", "dir", "broker-url"])
+      .required_unless_one(&["file", "dir", "glob", "listing-url", "broker-url"])
       .takes_value(true)
       .use_delimiter(false)
       .multiple(true)
       .number_of_values(1)
       .empty_values(false)
       .help("URL of pact file to verify (can be repeated)"))
+    .arg(Arg::with_name("listing-url")
+      .long("listing-url")
+      .required_unless_one(&["file", "dir", "glob", "url", "broker-url"])
+      .takes_value(true)
+      .use_delimiter(false)
+      .multiple(true)
+      .number_of_values(1)
+      .empty_values(false)
+      .help("URL of a listing of pact files to verify, either an HTML directory index page or an S3 bucket listing (can be repeated)"))
     .arg(Arg::with_name("broker-url")
       .short("b")
       .long("broker-url")
       .env("PACT_BROKER_BASE_URL")
-      .required_unless_one(&["file", "dir", "url"])
+      .required_unless_one(&["file", "dir", "glob", "url", "listing-url"])
       .requires("provider-name")
       .takes_value(true)
       .use_delimiter(false)
@@ -117,6 +136,13 @@ pub(crate) fn setup_app<'a, 'b>(program: String, version: &'b str) -> App<'a, 'b
       .long("filter-no-state")
       .conflicts_with("filter-state")
       .help("Only validate interactions that have no defined provider state"))
+    .arg(Arg::with_name("filter-interaction-type")
+      .long("filter-interaction-type")
+      .takes_value(true)
+      .use_delimiter(false)
+      .possible_values(&["Synchronous/HTTP", "Asynchronous/Messages", "Synchronous/Messages"])
+      .case_insensitive(true)
+      .help("Only validate interactions of this type"))
     .arg(Arg::with_name("filter-consumer")
       .short("c")
       .long("filter-consumer")
@@ -124,6 +150,60 @@ pub(crate) fn setup_app<'a, 'b>(program: String, version: &'b str) -> App<'a, 'b
       .multiple(true)
       .empty_values(false)
       .help("Consumer name to filter the pacts to be verified (can be repeated)"))
+    .arg(Arg::with_name("fail-fast")
+      .long("fail-fast")
+      .conflicts_with("max-failures")
+      .help("Stops the verification after the first failing interaction"))
+    .arg(Arg::with_name("max-failures")
+      .long("max-failures")
+      .takes_value(true)
+      .use_delimiter(false)
+      .validator(|val| val.parse::<usize>()
+        .map_err(|err| format!("'{}' is not a valid number of failures: {}", val, err))
+        .and_then(|v| if v > 0 { Ok(()) } else { Err(format!("'{}' must be greater than zero", val)) }))
+      .conflicts_with("fail-fast")
+      .help("Stops the verification after this number of interactions have failed"))
+    .arg(Arg::with_name("strict-bodies")
+      .long("strict-bodies")
+      .help("Fail the verification if the response bodies have any keys that are not in the pact"))
+    .arg(Arg::with_name("strict-bodies-for")
+      .long("strict-bodies-for")
+      .takes_value(true)
+      .use_delimiter(false)
+      .multiple(true)
+      .number_of_values(1)
+      .help("Fail the verification if the response body of the interaction with this description has any keys that are not in the pact (can be repeated)"))
+    .arg(Arg::with_name("provider-retries")
+      .long("provider-retries")
+      .takes_value(true)
+      .use_delimiter(false)
+      .validator(|val| val.parse::<usize>()
+        .map(|_| ())
+        .map_err(|err| format!("'{}' is not a valid number of retries: {}", val, err)))
+      .help("Number of times to retry requests to the provider that fail to connect or get a 503 response (defaults to 0)"))
+    .arg(Arg::with_name("provider-retry-delay")
+      .long("provider-retry-delay")
+      .takes_value(true)
+      .use_delimiter(false)
+      .requires("provider-retries")
+      .validator(|val| val.parse::<u64>()
+        .map(|_| ())
+        .map_err(|err| format!("'{}' is not a valid delay: {}", val, err)))
+      .help("Delay in milliseconds before the first retry of a request to the provider, doubled for each retry (defaults to 1000)"))
+    .arg(Arg::with_name("generator-seed")
+      .long("generator-seed")
+      .takes_value(true)
+      .use_delimiter(false)
+      .validator(|val| val.parse::<u64>()
+        .map(|_| ())
+        .map_err(|err| format!("'{}' is not a valid seed: {}", val, err)))
+      .help("Seed for the random generators used for the requests to the provider, so the generated values are reproducible"))
+    .arg(Arg::with_name("json-file")
+      .long("json")
+      .takes_value(true)
+      .use_delimiter(false)
+      .empty_values(false)
+      .help("Writes a JSON summary of the verification results to this file"))
     .arg(Arg::with_name("user")
       .long("user")
       .env("PACT_BROKER_USERNAME")
@@ -152,11 +232,27 @@ pub(crate) fn setup_app<'a, 'b>(program: String, version: &'b str) -> App<'a, 'b
       .empty_values(false)
       .conflicts_with("user")
       .help("Bearer token to use when fetching pacts from URLS"))
+    .arg(Arg::with_name("auth-header")
+      .long("auth-header")
+      .takes_value(true)
+      .use_delimiter(false)
+      .multiple(true)
+      .number_of_values(1)
+      .empty_values(false)
+      .conflicts_with_all(&["user", "token"])
+      .validator(|val| match val.split_once(':') {
+        Some((name, _)) if !name.trim().is_empty() => Ok(()),
+        _ => Err(format!("'{}' is not a valid header, it must be in the form 'NAME: VALUE'", val))
+      })
+      .help("Static header (NAME: VALUE) to add to the requests when fetching pacts from URLS (can be repeated)"))
+    .group(ArgGroup::with_name("pact-broker-source")
+      .args(&["broker-url", "url"])
+      .multiple(true))
     .arg(Arg::with_name("publish")
       .long("publish")
-      .requires("broker-url")
+      .requires("pact-broker-source")
       .requires("provider-version")
-      .help("Enables publishing of verification results back to the Pact Broker. Requires the broker-url and provider-version parameters."))
+      .help("Enables publishing of verification results back to the Pact Broker. Requires the broker-url (or url) and provider-version parameters."))
     .arg(Arg::with_name("provider-version")
       .long("provider-version")
       .takes_value(true)
@@ -195,6 +291,25 @@ pub(crate) fn setup_app<'a, 'b>(program: String, version: &'b str) -> App<'a, 'b
       .long("disable-ssl-verification")
       .takes_value(false)
       .help("Disables validation of SSL certificates"))
+    .arg(Arg::with_name("ca-cert")
+      .long("ca-cert")
+      .takes_value(true)
+      .use_delimiter(false)
+      .empty_values(false)
+      .help("PEM file with the root CA certificates to trust when connecting to the provider and the Pact Broker"))
+    .arg(Arg::with_name("client-cert")
+      .long("client-cert")
+      .takes_value(true)
+      .use_delimiter(false)
+      .empty_values(false)
+      .help("PEM file with the client certificate to use when connecting to the provider and the Pact Broker (mutual TLS)"))
+    .arg(Arg::with_name("client-key")
+      .long("client-key")
+      .takes_value(true)
+      .use_delimiter(false)
+      .empty_values(false)
+      .requires("client-cert")
+      .help("PEM file with the private key of the client certificate"))
     .arg(Arg::with_name("enable-pending")
       .long("enable-pending")
       .requires("broker-url")
@@ -1,15 +1,16 @@
 //! Exported verifier functions
 
 use std::env;
+use std::fs;
 use std::str;
 use std::str::FromStr;
 use std::sync::Arc;
 
 use clap::{AppSettings, ArgMatches, ErrorKind};
-use log::{debug, LevelFilter};
+use log::{debug, error, LevelFilter};
 use simplelog::{Config, TerminalMode, TermLogger};
 
-use pact_matching::models::http_utils::HttpAuth;
+use pact_matching::models::http_utils::{HttpAuth, TlsConfig};
 use pact_matching::s;
 use pact_models::PactSpecification;
 use pact_verifier::*;
@@ -25,22 +26,19 @@ fn pact_source(matches: &ArgMatches) -> Vec<PactSource> {
   if let Some(values) = matches.values_of("dir") {
     sources.extend(values.map(|v| PactSource::Dir(s!(v))).collect::<Vec<PactSource>>());
   };
+  if let Some(values) = matches.values_of("glob") {
+    sources.extend(values.map(|v| PactSource::Glob(s!(v))).collect::<Vec<PactSource>>());
+  };
   if let Some(values) = matches.values_of("url") {
-    sources.extend(values.map(|v| {
-      if matches.is_present("user") {
-        PactSource::URL(s!(v), matches.value_of("user").map(|user| {
-          HttpAuth::User(user.to_string(), matches.value_of("password").map(|p| p.to_string()))
-        }))
-      } else if matches.is_present("token") {
-        PactSource::URL(s!(v), matches.value_of("token").map(|token| HttpAuth::Token(token.to_string())))
-      } else {
-        PactSource::URL(s!(v), None)
-      }
-    }).collect::<Vec<PactSource>>());
+    sources.extend(values.map(|v| PactSource::URL(s!(v), http_auth(matches))).collect::<Vec<PactSource>>());
+  };
+  if let Some(values) = matches.values_of("listing-url") {
+    sources.extend(values.map(|v| PactSource::ListingUrl(s!(v), http_auth(matches))).collect::<Vec<PactSource>>());
   };
   if let Some(values) = matches.values_of("broker-url") {
     sources.extend(values.map(|v| {
-      if matches.is_present("user") || matches.is_present("token") {
+      let auth = http_auth(matches);
+      if auth.is_some() {
         let name = matches.value_of("provider-name").unwrap().to_string();
         let pending = matches.is_present("enable-pending");
         let wip = matches.value_of("include-wip-pacts-since").map(|wip| wip.to_string());
@@ -49,15 +47,7 @@ fn pact_source(matches: &ArgMatches) -> Vec<PactSource> {
         let provider_tags = matches.values_of("provider-tags")
           .map_or_else(|| vec![], |tags| tags.map(|tag| tag.to_string()).collect());
 
-        if matches.is_present("token") {
-          PactSource::BrokerWithDynamicConfiguration { provider_name: name, broker_url: s!(v), enable_pending: pending, include_wip_pacts_since: wip, provider_tags: provider_tags, selectors: consumer_version_tags,
-            auth: matches.value_of("token").map(|token| HttpAuth::Token(token.to_string())), links: vec![] }
-        } else {
-        let auth = matches.value_of("user").map(|user| {
-          HttpAuth::User(user.to_string(), matches.value_of("password").map(|p| p.to_string()))
-        });
-          PactSource::BrokerWithDynamicConfiguration { provider_name: name, broker_url: s!(v), enable_pending: pending, include_wip_pacts_since: wip, provider_tags: provider_tags, selectors: consumer_version_tags, auth: auth, links: vec![] }
-        }
+        PactSource::BrokerWithDynamicConfiguration { provider_name: name, broker_url: s!(v), enable_pending: pending, include_wip_pacts_since: wip, provider_tags, selectors: consumer_version_tags, auth, links: vec![] }
       } else {
         PactSource::BrokerUrl(s!(matches.value_of("provider-name").unwrap()), s!(v), None, vec![])
       }
@@ -66,6 +56,21 @@ fn pact_source(matches: &ArgMatches) -> Vec<PactSource> {
   sources
 }
 
+fn http_auth(matches: &ArgMatches) -> Option<HttpAuth> {
+  if matches.is_present("user") {
+    matches.value_of("user").map(|user| {
+      HttpAuth::User(user.to_string(), matches.value_of("password").map(|p| p.to_string()))
+    })
+  } else if matches.is_present("token") {
+    matches.value_of("token").map(|token| HttpAuth::Token(token.to_string()))
+  } else {
+    matches.values_of("auth-header").map(|headers| HttpAuth::Headers(headers
+      .filter_map(|header| header.split_once(':'))
+      .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+      .collect()))
+  }
+}
+
 fn consumer_tags_to_selectors(tags: Vec<&str>) -> Vec<pact_verifier::ConsumerVersionSelector> {
 tags.iter().map(|t| {
   pact_verifier::ConsumerVersionSelector {
@@ -78,7 +83,17 @@ tags.iter().map(|t| {
 }
 
 fn interaction_filter(matches: &ArgMatches) -> FilterInfo {
-  if matches.is_present("filter-description") &&
+  if matches.is_present("filter-interaction-type") {
+    FilterInfo::Filters {
+      description: matches.value_of("filter-description").map(|v| v.to_string()),
+      state: if matches.is_present("filter-no-state") {
+        Some(s!(""))
+      } else {
+        matches.value_of("filter-state").map(|v| v.to_string())
+      },
+      interaction_type: matches.value_of("filter-interaction-type").map(|v| v.to_string())
+    }
+  } else if matches.is_present("filter-description") &&
       (matches.is_present("filter-state") || matches.is_present("filter-no-state")) {
       if matches.is_present("filter-state") {
           FilterInfo::DescriptionAndState(s!(matches.value_of("filter-description").unwrap()),
@@ -98,6 +113,23 @@ fn interaction_filter(matches: &ArgMatches) -> FilterInfo {
   }
 }
 
+fn max_failures(matches: &ArgMatches) -> Option<usize> {
+  if matches.is_present("fail-fast") {
+    Some(1)
+  } else {
+    matches.value_of("max-failures").map(|v| v.parse::<usize>().unwrap())
+  }
+}
+
+fn tls_config(matches: &ArgMatches) -> TlsConfig {
+  TlsConfig {
+    ca_certificate: matches.value_of("ca-cert").map(|v| v.into()),
+    client_certificate: matches.value_of("client-cert").map(|v| v.into()),
+    client_key: matches.value_of("client-key").map(|v| v.into()),
+    accept_invalid_certs: matches.is_present("disable-ssl-verification")
+  }
+}
+
 /// Handles the command line arguments from the running process
 pub async fn handle_cli() -> Result<(), i32> {
   let args: Vec<String> = env::args().collect();
@@ -183,6 +215,19 @@ async fn handle_matches(matches: &clap::ArgMatches<'_>) -> Result<(), i32> {
       provider_tags: matches.values_of("provider-tags")
         .map_or_else(|| vec![], |tags| tags.map(|tag| tag.to_string()).collect()),
       disable_ssl_verification: matches.is_present("disable-ssl-verification"),
+      max_failures: max_failures(matches),
+      strict_bodies: matches.is_present("strict-bodies"),
+      strict_body_interactions: matches.values_of("strict-bodies-for")
+        .map(|values| values.map(|description| description.to_string()).collect())
+        .unwrap_or_default(),
+      provider_retries: matches.value_of("provider-retries")
+        .map(|v| v.parse::<usize>().unwrap())
+        .unwrap_or_default(),
+      provider_retry_delay: matches.value_of("provider-retry-delay")
+        .map(|v| v.parse::<u64>().unwrap())
+        .unwrap_or(1000),
+      tls: tls_config(matches),
+      generator_seed: matches.value_of("generator-seed").map(|v| v.parse::<u64>().unwrap()),
       .. VerificationOptions::default()
     };
 
@@ -190,20 +235,35 @@ async fn handle_matches(matches: &clap::ArgMatches<'_>) -> Result<(), i32> {
       debug!("Pact source to verify = {}", s);
     };
 
-    if verify_provider_async(
+    let summary = verify_provider_with_summary_async(
         provider,
         source,
         filter,
         matches.values_of_lossy("filter-consumer").unwrap_or_default(),
         options,
         &provider_state_executor
-    ).await {
+    ).await;
+
+    if let Some(json_file) = matches.value_of("json-file") {
+      write_json_summary(json_file, &summary);
+    }
+
+    if summary.result {
         Ok(())
     } else {
         Err(1)
     }
 }
 
+fn write_json_summary(json_file: &str, summary: &VerificationSummary) {
+  match serde_json::to_string_pretty(summary) {
+    Ok(json) => if let Err(err) = fs::write(json_file, json) {
+      error!("Failed to write the verification results to '{}' - {}", json_file, err);
+    },
+    Err(err) => error!("Failed to convert the verification results to JSON - {}", err)
+  }
+}
+
 fn print_version() {
   println!("\npact verifier version     : v{}", clap::crate_version!());
   println!("pact specification version: v{}", PactSpecification::V3.version_str());